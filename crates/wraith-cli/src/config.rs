@@ -20,6 +20,12 @@ pub struct Config {
     pub transfer: TransferConfig,
     /// Logging configuration
     pub logging: LoggingConfig,
+    /// Daemon configuration
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    /// Relay server configuration
+    #[serde(default)]
+    pub relay: RelayConfig,
 }
 
 /// Node configuration
@@ -31,6 +37,9 @@ pub struct NodeConfig {
     /// Private key file path
     #[serde(default = "default_private_key_path")]
     pub private_key_file: PathBuf,
+    /// Known peers file (pinned peer keys by address)
+    #[serde(default = "default_known_peers_path")]
+    pub known_peers_file: PathBuf,
 }
 
 /// Network configuration
@@ -48,6 +57,9 @@ pub struct NetworkConfig {
     /// Enable UDP fallback
     #[serde(default = "default_true")]
     pub udp_fallback: bool,
+    /// Forward the listen port on the local gateway (PCP, NAT-PMP or UPnP IGD)
+    #[serde(default = "default_true")]
+    pub port_mapping: bool,
 }
 
 /// Obfuscation configuration
@@ -62,10 +74,13 @@ pub struct ObfuscationConfig {
     /// Enable cover traffic
     #[serde(default)]
     pub cover_traffic: bool,
+    /// Send handshake ephemeral keys Elligator2-encoded
+    #[serde(default)]
+    pub elligator_handshake: bool,
 }
 
 /// Discovery configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiscoveryConfig {
     /// DHT bootstrap nodes
     #[serde(default = "default_bootstrap_nodes")]
@@ -73,6 +88,9 @@ pub struct DiscoveryConfig {
     /// DERP relay servers
     #[serde(default = "default_relay_servers")]
     pub relay_servers: Vec<String>,
+    /// Announce and browse for peers on the local network (mDNS)
+    #[serde(default = "default_true")]
+    pub mdns: bool,
 }
 
 /// Transfer configuration
@@ -100,6 +118,47 @@ pub struct LoggingConfig {
     pub file: Option<PathBuf>,
 }
 
+/// Daemon configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// Unix socket the daemon accepts control commands on
+    #[serde(default = "default_control_socket_path")]
+    pub control_socket: PathBuf,
+    /// Address to serve OpenMetrics on (`GET /metrics`), disabled when unset
+    ///
+    /// The endpoint is unauthenticated; bind it to loopback or a private
+    /// network.
+    pub metrics_addr: Option<String>,
+}
+
+/// Relay server configuration (`wraith relay`, `wraith daemon --relay`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelayConfig {
+    /// Addresses to serve on, one relay per address
+    #[serde(default = "default_relay_bind_addrs")]
+    pub bind_addrs: Vec<String>,
+    /// Maximum number of registered clients per address
+    #[serde(default = "default_relay_max_clients")]
+    pub max_clients: usize,
+    /// Packets per second each client may relay
+    #[serde(default = "default_relay_rate_limit")]
+    pub rate_limit: usize,
+    /// Payload bytes per second each client may relay
+    #[serde(default = "default_relay_bandwidth_limit")]
+    pub bandwidth_limit: u64,
+    /// Total payload bytes a client may relay while registered
+    pub byte_quota: Option<u64>,
+    /// Seconds without traffic before a client is dropped
+    #[serde(default = "default_relay_client_timeout")]
+    pub client_timeout_secs: u64,
+    /// Announce the relay on the local network over mDNS
+    #[serde(default = "default_true")]
+    pub announce: bool,
+    /// Seconds between connection statistics reports (0 disables them)
+    #[serde(default = "default_relay_stats_interval")]
+    pub stats_interval_secs: u64,
+}
+
 // Default values
 
 fn default_private_key_path() -> PathBuf {
@@ -108,6 +167,45 @@ fn default_private_key_path() -> PathBuf {
         .join(".wraith/private_key")
 }
 
+fn default_known_peers_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".wraith/known_peers")
+}
+
+fn default_control_socket_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(".wraith/control.sock")
+}
+
+fn default_relay_bind_addrs() -> Vec<String> {
+    vec![format!(
+        "0.0.0.0:{}",
+        wraith_discovery::relay::DEFAULT_RELAY_PORT
+    )]
+}
+
+fn default_relay_max_clients() -> usize {
+    10_000
+}
+
+fn default_relay_rate_limit() -> usize {
+    100
+}
+
+fn default_relay_bandwidth_limit() -> u64 {
+    1024 * 1024 // 1 MB/s
+}
+
+fn default_relay_client_timeout() -> u64 {
+    60
+}
+
+fn default_relay_stats_interval() -> u64 {
+    30
+}
+
 fn default_listen_addr() -> String {
     "0.0.0.0:40000".to_string()
 }
@@ -145,6 +243,7 @@ impl Default for NodeConfig {
         Self {
             public_key: None,
             private_key_file: default_private_key_path(),
+            known_peers_file: default_known_peers_path(),
         }
     }
 }
@@ -156,6 +255,7 @@ impl Default for NetworkConfig {
             enable_xdp: false,
             xdp_interface: None,
             udp_fallback: true,
+            port_mapping: true,
         }
     }
 }
@@ -166,6 +266,17 @@ impl Default for ObfuscationConfig {
             default_level: default_obfuscation_level(),
             tls_mimicry: true,
             cover_traffic: false,
+            elligator_handshake: false,
+        }
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            bootstrap_nodes: default_bootstrap_nodes(),
+            relay_servers: default_relay_servers(),
+            mdns: true,
         }
     }
 }
@@ -189,6 +300,30 @@ impl Default for LoggingConfig {
     }
 }
 
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            control_socket: default_control_socket_path(),
+            metrics_addr: None,
+        }
+    }
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            bind_addrs: default_relay_bind_addrs(),
+            max_clients: default_relay_max_clients(),
+            rate_limit: default_relay_rate_limit(),
+            bandwidth_limit: default_relay_bandwidth_limit(),
+            byte_quota: None,
+            client_timeout_secs: default_relay_client_timeout(),
+            announce: true,
+            stats_interval_secs: default_relay_stats_interval(),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from file
     ///
@@ -306,6 +441,31 @@ impl Config {
             self.validate_host_port(server, "Relay server")?;
         }
 
+        if let Some(addr) = &self.daemon.metrics_addr {
+            addr.parse::<SocketAddr>().map_err(|e| {
+                anyhow::anyhow!("Invalid metrics address '{addr}': {e} (expected ip:port)")
+            })?;
+        }
+
+        // Validate relay server mode
+        if self.relay.bind_addrs.is_empty() {
+            anyhow::bail!("Relay needs at least one bind address");
+        }
+        for addr in &self.relay.bind_addrs {
+            addr.parse::<SocketAddr>().map_err(|e| {
+                anyhow::anyhow!("Invalid relay bind address '{addr}': {e} (expected ip:port)")
+            })?;
+        }
+        if self.relay.max_clients == 0 {
+            anyhow::bail!("Relay max clients must be at least 1");
+        }
+        if self.relay.rate_limit == 0 || self.relay.bandwidth_limit == 0 {
+            anyhow::bail!("Relay rate and bandwidth limits must be greater than 0");
+        }
+        if self.relay.client_timeout_secs == 0 {
+            anyhow::bail!("Relay client timeout must be greater than 0");
+        }
+
         Ok(())
     }
 
@@ -368,6 +528,12 @@ mod tests {
                 .to_string_lossy()
                 .contains(".wraith/private_key")
         );
+        assert!(
+            node_config
+                .known_peers_file
+                .to_string_lossy()
+                .contains(".wraith/known_peers")
+        );
     }
 
     #[test]
@@ -377,6 +543,7 @@ mod tests {
         assert!(!network_config.enable_xdp);
         assert!(network_config.xdp_interface.is_none());
         assert!(network_config.udp_fallback);
+        assert!(network_config.port_mapping);
     }
 
     #[test]
@@ -407,6 +574,76 @@ mod tests {
         let discovery_config = DiscoveryConfig::default();
         assert!(discovery_config.bootstrap_nodes.is_empty());
         assert!(discovery_config.relay_servers.is_empty());
+        assert!(discovery_config.mdns);
+    }
+
+    #[test]
+    fn test_daemon_config_default() {
+        let daemon_config = DaemonConfig::default();
+        assert!(
+            daemon_config
+                .control_socket
+                .to_string_lossy()
+                .ends_with(".wraith/control.sock")
+        );
+        assert!(daemon_config.metrics_addr.is_none());
+    }
+
+    #[test]
+    fn test_metrics_addr_validation() {
+        let mut config = Config::default();
+        config.daemon.metrics_addr = Some("localhost:9090".to_string());
+        assert!(config.validate().is_err());
+
+        config.daemon.metrics_addr = Some("127.0.0.1:9090".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_without_daemon_section() {
+        // Config files written before the daemon section existed still load
+        let mut config = toml::Value::try_from(Config::default()).unwrap();
+        config.as_table_mut().unwrap().remove("daemon");
+        let config: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(
+            config.daemon.control_socket,
+            DaemonConfig::default().control_socket
+        );
+    }
+
+    #[test]
+    fn test_relay_config_default() {
+        let relay_config = RelayConfig::default();
+        assert_eq!(relay_config.bind_addrs, vec!["0.0.0.0:443".to_string()]);
+        assert_eq!(relay_config.max_clients, 10_000);
+        assert_eq!(relay_config.byte_quota, None);
+        assert!(relay_config.announce);
+
+        // Config files written before the relay section existed still load
+        let mut config = toml::Value::try_from(Config::default()).unwrap();
+        config.as_table_mut().unwrap().remove("relay");
+        let config: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config.relay.bind_addrs, relay_config.bind_addrs);
+    }
+
+    #[test]
+    fn test_relay_config_validation() {
+        let mut config = Config::default();
+        config.relay.bind_addrs = vec!["relay.example.com:443".to_string()];
+        assert!(config.validate().is_err());
+
+        config.relay.bind_addrs.clear();
+        assert!(config.validate().is_err());
+
+        config.relay.bind_addrs = vec!["127.0.0.1:4433".to_string()];
+        assert!(config.validate().is_ok());
+
+        config.relay.max_clients = 0;
+        assert!(config.validate().is_err());
+
+        config.relay.max_clients = 1;
+        config.relay.bandwidth_limit = 0;
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -747,21 +984,25 @@ mod tests {
             node: NodeConfig {
                 public_key: Some("deadbeef".to_string()),
                 private_key_file: PathBuf::from("/custom/path"),
+                known_peers_file: PathBuf::from("/custom/known_peers"),
             },
             network: NetworkConfig {
                 listen_addr: "127.0.0.1:9999".to_string(),
                 enable_xdp: true,
                 xdp_interface: Some("eth1".to_string()),
                 udp_fallback: false,
+                port_mapping: false,
             },
             obfuscation: ObfuscationConfig {
                 default_level: "high".to_string(),
                 tls_mimicry: false,
                 cover_traffic: true,
+                elligator_handshake: true,
             },
             discovery: DiscoveryConfig {
                 bootstrap_nodes: vec!["node1.example.com:8080".to_string()],
                 relay_servers: vec!["relay1.example.com:8080".to_string()],
+                mdns: false,
             },
             transfer: TransferConfig {
                 chunk_size: 512 * 1024,
@@ -772,6 +1013,20 @@ mod tests {
                 level: "debug".to_string(),
                 file: Some(PathBuf::from("/var/log/wraith.log")),
             },
+            daemon: DaemonConfig {
+                control_socket: PathBuf::from("/run/wraith/control.sock"),
+                metrics_addr: Some("127.0.0.1:9090".to_string()),
+            },
+            relay: RelayConfig {
+                bind_addrs: vec!["0.0.0.0:3478".to_string(), "[::]:3478".to_string()],
+                max_clients: 500,
+                rate_limit: 50,
+                bandwidth_limit: 256 * 1024,
+                byte_quota: Some(1 << 30),
+                client_timeout_secs: 30,
+                announce: false,
+                stats_interval_secs: 0,
+            },
         };
 
         assert!(config.validate().is_ok());
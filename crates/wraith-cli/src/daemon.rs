@@ -0,0 +1,937 @@
+//! Daemon side of the control API.
+//!
+//! Serves [`ipc`](crate::ipc) requests against the daemon's node and
+//! broadcasts transfer and session events to subscribed clients. The event
+//! stream is produced by polling the node, so events may lag the underlying
+//! state change by up to [`EVENT_POLL_INTERVAL`].
+
+use crate::config::Config;
+use crate::ipc::{
+    Command, ErrorCode, Event, HealthInfo, MAX_MESSAGE_SIZE, Message, MetricsInfo,
+    PROTOCOL_VERSION, Request, Response, SessionInfo, StatusInfo, TransferInfo, write_message,
+};
+use crate::{PeerTarget, connect_peer_target, parse_peer_id, parse_peer_target, sanitize_path};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, BufReader};
+use tokio::sync::broadcast;
+use wraith_core::node::identity::TransferId;
+use wraith_core::node::progress::TransferProgress;
+use wraith_core::node::{KnownPeers, Node, NodeError};
+
+/// How often the node is polled for events
+pub const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Events buffered per subscriber before the oldest are dropped
+const EVENT_BUFFER: usize = 256;
+
+/// State shared by all control connections
+pub struct DaemonState {
+    node: Arc<Node>,
+    config: Config,
+    started: Instant,
+    events: broadcast::Sender<Event>,
+}
+
+/// Failed request
+type CommandError = (ErrorCode, String);
+
+impl DaemonState {
+    /// Create the state for a started node
+    pub fn new(node: Arc<Node>, config: Config) -> Arc<Self> {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Arc::new(Self {
+            node,
+            config,
+            started: Instant::now(),
+            events,
+        })
+    }
+
+    /// Seconds since the daemon started
+    fn uptime_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Run one command
+    async fn handle(&self, command: Command) -> Result<Response, CommandError> {
+        let node = &self.node;
+        match command {
+            Command::Status => Ok(Response::Status(StatusInfo {
+                protocol_version: PROTOCOL_VERSION,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                node_id: hex::encode(node.node_id()),
+                x25519_key: hex::encode(node.x25519_public_key()),
+                listen_addr: node.listen_addr().await.ok(),
+                uptime_secs: self.uptime_secs(),
+                sessions: node.active_sessions().await.len(),
+                transfers: node.active_transfers().await.len(),
+            })),
+            Command::Send {
+                path,
+                recipients,
+                recursive,
+            } => self.send(&path, &recipients, recursive).await,
+            Command::ListTransfers => Ok(Response::Transfers {
+                transfers: self.transfers().await,
+            }),
+            Command::CancelTransfer { transfer_id } => {
+                let transfer_id = transfer_arg(&transfer_id)?;
+                node.cancel_transfer(&transfer_id)
+                    .await
+                    .map_err(node_error)?;
+                Ok(Response::Done)
+            }
+            Command::PauseTransfer { transfer_id } => {
+                let transfer_id = transfer_arg(&transfer_id)?;
+                node.pause_transfer(&transfer_id)
+                    .await
+                    .map_err(node_error)?;
+                Ok(Response::Done)
+            }
+            Command::ResumeTransfer { transfer_id } => {
+                let transfer_id = transfer_arg(&transfer_id)?;
+                node.resume_transfer(&transfer_id)
+                    .await
+                    .map_err(node_error)?;
+                Ok(Response::Done)
+            }
+            Command::ListSessions => Ok(Response::Sessions {
+                sessions: self.sessions().await,
+            }),
+            Command::CloseSession { peer_id } => {
+                let peer_id = parse_peer_id(&peer_id).map_err(invalid)?;
+                node.close_session(&peer_id).await.map_err(node_error)?;
+                Ok(Response::Done)
+            }
+            Command::DiscoverPeer { peer_id } => {
+                let peer_id = parse_peer_id(&peer_id).map_err(invalid)?;
+                let addrs = node.discover_peer(&peer_id).await.map_err(node_error)?;
+                Ok(Response::Addresses { addrs })
+            }
+            Command::Ping { peer } => self.ping(&peer).await,
+            Command::Health => Ok(Response::Health(self.health().await)),
+            Command::Metrics => Ok(Response::Metrics(self.metrics().await)),
+            // Handled by the connection, which owns the subscription
+            Command::Subscribe => Ok(Response::Done),
+        }
+    }
+
+    /// Start a transfer to each recipient
+    async fn send(
+        &self,
+        path: &Path,
+        recipients: &[String],
+        recursive: bool,
+    ) -> Result<Response, CommandError> {
+        let path = sanitize_path(&path.to_path_buf()).map_err(invalid)?;
+        if path.is_dir() && !recursive {
+            return Err(invalid(format!(
+                "{} is a directory (use -r to send it)",
+                path.display()
+            )));
+        }
+        let targets = recipients
+            .iter()
+            .map(|recipient| parse_peer_target(recipient))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(invalid)?;
+
+        let mut known_peers =
+            KnownPeers::load(&self.config.node.known_peers_file).map_err(failed)?;
+        let mut transfer_ids = Vec::new();
+        for target in &targets {
+            let peer_id = connect_peer_target(&self.node, target, &mut known_peers)
+                .await
+                .map_err(failed)?;
+            let transfer_id = if path.is_dir() {
+                self.node.send_tree(&path, &peer_id).await
+            } else {
+                self.node.send_file(&path, &peer_id).await
+            }
+            .map_err(node_error)?;
+            transfer_ids.push(hex::encode(transfer_id));
+        }
+        Ok(Response::Started { transfer_ids })
+    }
+
+    /// Ping a peer over a session, or time a DHT lookup for bare peer IDs
+    async fn ping(&self, peer: &str) -> Result<Response, CommandError> {
+        let target = parse_peer_target(peer).map_err(invalid)?;
+        let mut known_peers =
+            KnownPeers::load(&self.config.node.known_peers_file).map_err(failed)?;
+        let peer_id = connect_peer_target(&self.node, &target, &mut known_peers)
+            .await
+            .map_err(failed)?;
+
+        let rtt = if matches!(target, PeerTarget::Id(_)) {
+            let start = Instant::now();
+            self.node
+                .discover_peer(&peer_id)
+                .await
+                .map_err(node_error)?;
+            start.elapsed()
+        } else {
+            self.node.ping(&peer_id).await.map_err(node_error)?
+        };
+        Ok(Response::Pong {
+            peer_id: hex::encode(peer_id),
+            rtt_us: u64::try_from(rtt.as_micros()).unwrap_or(u64::MAX),
+        })
+    }
+
+    /// Progress of every transfer
+    async fn transfers(&self) -> Vec<TransferInfo> {
+        let mut transfers = Vec::new();
+        for transfer_id in self.node.active_transfers().await {
+            if let Some(progress) = self.node.get_transfer_progress(&transfer_id).await {
+                transfers.push(transfer_info(&progress));
+            }
+        }
+        transfers.sort_by(|a, b| a.transfer_id.cmp(&b.transfer_id));
+        transfers
+    }
+
+    /// State of every established session
+    async fn sessions(&self) -> Vec<SessionInfo> {
+        let node = &self.node;
+        let mut sessions = Vec::new();
+        for peer_id in node.active_sessions().await {
+            let stats = node.get_connection_stats(&peer_id).unwrap_or_default();
+            sessions.push(SessionInfo {
+                peer_id: hex::encode(peer_id),
+                addr: node.get_session_addr(&peer_id),
+                transport: node
+                    .get_session_transport(&peer_id)
+                    .map(|transport| format!("{transport:?}")),
+                age_secs: node
+                    .get_session_established_at(&peer_id)
+                    .and_then(|at| SystemTime::now().duration_since(at).ok())
+                    .map(|age| age.as_secs()),
+                key_epoch: node.get_session_key_epoch(&peer_id),
+                bytes_sent: stats.bytes_sent,
+                bytes_received: stats.bytes_received,
+                rtt_us: stats.rtt_us,
+            });
+        }
+        sessions.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        sessions
+    }
+
+    /// Node health summary
+    async fn health(&self) -> HealthInfo {
+        let node = &self.node;
+        let transfers = self.transfers().await;
+        let security = node.security_metrics().await;
+        HealthInfo {
+            running: node.is_running(),
+            uptime_secs: self.uptime_secs(),
+            transports: node
+                .transport_addrs()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(transport, addr)| (format!("{transport:?}"), addr))
+                .collect(),
+            sessions: node.active_sessions().await.len(),
+            active_transfers: transfers.iter().filter(|t| !t.is_finished()).count(),
+            failed_transfers: transfers.iter().filter(|t| t.status == "Failed").count(),
+            handshake_failures: security.handshake_failures,
+            bans: security.temp_bans + security.perm_bans,
+        }
+    }
+
+    /// Runtime counters
+    async fn metrics(&self) -> MetricsInfo {
+        let node = &self.node;
+        let transfers = self.transfers().await;
+        let registry = node.metrics().await;
+        let counter = |name| registry.get(name, &[]).unwrap_or(0.0) as u64;
+
+        MetricsInfo {
+            uptime_secs: self.uptime_secs(),
+            sessions: node.active_sessions().await.len(),
+            active_transfers: transfers.iter().filter(|t| !t.is_finished()).count(),
+            completed_transfers: transfers.iter().filter(|t| t.status == "Complete").count(),
+            failed_transfers: transfers.iter().filter(|t| t.status == "Failed").count(),
+            bytes_sent: counter("wraith_transport_sent_bytes"),
+            bytes_received: counter("wraith_transport_received_bytes"),
+            packets_sent: counter("wraith_transport_sent_packets"),
+            packets_received: counter("wraith_transport_received_packets"),
+            routes: node.active_route_count(),
+            handshake_failures: counter("wraith_handshake_failures"),
+            rate_limit_violations: counter("wraith_rate_limit_violations"),
+        }
+    }
+
+    /// Node metrics and daemon uptime in the OpenMetrics text format
+    pub async fn openmetrics(&self) -> String {
+        let mut registry = self.node.metrics().await;
+        registry.gauge(
+            "wraith_daemon_uptime_seconds",
+            "Seconds since the daemon started",
+            self.started.elapsed().as_secs_f64(),
+        );
+        registry.encode()
+    }
+}
+
+/// Convert a transfer's progress for the wire
+fn transfer_info(progress: &TransferProgress) -> TransferInfo {
+    TransferInfo {
+        transfer_id: hex::encode(progress.transfer_id),
+        status: progress.status.to_string(),
+        bytes_done: progress.bytes_sent,
+        bytes_total: progress.bytes_total,
+        speed_bytes_per_sec: progress.speed_bytes_per_sec,
+        eta_secs: progress.eta.map(|eta| eta.as_secs()),
+    }
+}
+
+fn transfer_arg(transfer_id: &str) -> Result<TransferId, CommandError> {
+    wraith_core::node::identity::parse_transfer_id(transfer_id).map_err(invalid)
+}
+
+fn invalid(e: impl std::fmt::Display) -> CommandError {
+    (ErrorCode::InvalidRequest, e.to_string())
+}
+
+fn failed(e: impl std::fmt::Display) -> CommandError {
+    (ErrorCode::Failed, e.to_string())
+}
+
+fn node_error(e: NodeError) -> CommandError {
+    let code = match e {
+        NodeError::TransferNotFound(_)
+        | NodeError::SessionNotFound(_)
+        | NodeError::PeerNotFound(_) => ErrorCode::NotFound,
+        NodeError::InvalidState(_) => ErrorCode::InvalidRequest,
+        _ => ErrorCode::Failed,
+    };
+    (code, e.to_string())
+}
+
+/// Serve one control connection until the client disconnects
+///
+/// # Errors
+///
+/// Returns an error if writing to the client fails.
+pub async fn serve_connection<R, W>(
+    state: Arc<DaemonState>,
+    reader: R,
+    mut writer: W,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut events: Option<broadcast::Receiver<Event>> = None;
+    let mut line = Vec::new();
+
+    loop {
+        // A partial line survives an event interrupting the read
+        let remaining = (MAX_MESSAGE_SIZE + 1).saturating_sub(line.len()) as u64;
+        let mut limited = (&mut reader).take(remaining);
+        tokio::select! {
+            read = limited.read_until(b'\n', &mut line) => {
+                if read? == 0 {
+                    return Ok(());
+                }
+                if line.len() <= MAX_MESSAGE_SIZE && line.last() != Some(&b'\n') {
+                    continue;
+                }
+                if line.len() > MAX_MESSAGE_SIZE {
+                    let error = Message::Error {
+                        id: 0,
+                        code: ErrorCode::InvalidRequest,
+                        message: "Message too large".to_string(),
+                    };
+                    write_message(&mut writer, &error).await?;
+                    return Ok(());
+                }
+
+                let reply = match serde_json::from_slice::<Request>(&line) {
+                    Err(e) => Message::Error {
+                        id: 0,
+                        code: ErrorCode::InvalidRequest,
+                        message: format!("Malformed request: {e}"),
+                    },
+                    Ok(request) if request.version != PROTOCOL_VERSION => Message::Error {
+                        id: request.id,
+                        code: ErrorCode::UnsupportedVersion,
+                        message: format!(
+                            "Unsupported protocol version {} (daemon speaks {PROTOCOL_VERSION})",
+                            request.version
+                        ),
+                    },
+                    Ok(request) => {
+                        if request.command == Command::Subscribe && events.is_none() {
+                            events = Some(state.events.subscribe());
+                        }
+                        match state.handle(request.command).await {
+                            Ok(body) => Message::Response { id: request.id, body },
+                            Err((code, message)) => Message::Error { id: request.id, code, message },
+                        }
+                    }
+                };
+                line.clear();
+                write_message(&mut writer, &reply).await?;
+            }
+            event = recv_event(&mut events) => {
+                match event {
+                    Ok(event) => write_message(&mut writer, &Message::Event { event }).await?,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("Control client lagged, {} events dropped", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => events = None,
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the next event, or forever if not subscribed
+async fn recv_event(
+    events: &mut Option<broadcast::Receiver<Event>>,
+) -> Result<Event, broadcast::error::RecvError> {
+    match events {
+        Some(events) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Poll the node and broadcast changes to subscribers
+pub fn spawn_event_watcher(state: Arc<DaemonState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut sessions: HashSet<String> = HashSet::new();
+        let mut transfers: HashMap<String, TransferInfo> = HashMap::new();
+        let mut ticker = tokio::time::interval(EVENT_POLL_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+            let events = poll_events(&state, &mut sessions, &mut transfers).await;
+            for event in events {
+                // No subscribers is not an error
+                let _ = state.events.send(event);
+            }
+        }
+    })
+}
+
+/// Diff the node's sessions and transfers against the previous poll
+async fn poll_events(
+    state: &DaemonState,
+    sessions: &mut HashSet<String>,
+    transfers: &mut HashMap<String, TransferInfo>,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    let current: HashSet<String> = state
+        .node
+        .active_sessions()
+        .await
+        .iter()
+        .map(hex::encode)
+        .collect();
+    for peer_id in current.difference(sessions) {
+        events.push(Event::SessionOpened {
+            peer_id: peer_id.clone(),
+        });
+    }
+    for peer_id in sessions.difference(&current) {
+        events.push(Event::SessionClosed {
+            peer_id: peer_id.clone(),
+        });
+    }
+    *sessions = current;
+
+    let mut current = HashMap::new();
+    for info in state.transfers().await {
+        let previous = transfers.remove(&info.transfer_id);
+        if previous.is_none() {
+            events.push(Event::TransferStarted {
+                transfer_id: info.transfer_id.clone(),
+            });
+        }
+        if previous.as_ref() != Some(&info) {
+            if info.is_finished() {
+                if !previous.as_ref().is_some_and(TransferInfo::is_finished) {
+                    events.push(Event::TransferFinished {
+                        transfer_id: info.transfer_id.clone(),
+                        status: info.status.clone(),
+                    });
+                }
+            } else {
+                events.push(Event::TransferProgress(info.clone()));
+            }
+        }
+        current.insert(info.transfer_id.clone(), info);
+    }
+    // Transfers that disappeared were cancelled
+    for transfer_id in transfers.keys() {
+        events.push(Event::TransferFinished {
+            transfer_id: transfer_id.clone(),
+            status: "Cancelled".to_string(),
+        });
+    }
+    *transfers = current;
+
+    events
+}
+
+/// Unix socket listener for the control API
+#[cfg(unix)]
+pub mod socket {
+    use super::{DaemonState, serve_connection};
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use tokio::net::{UnixListener, UnixStream};
+
+    /// Bound control socket, removed when dropped
+    pub struct ControlSocket {
+        listener: UnixListener,
+        path: PathBuf,
+        /// Only connections from this user are served
+        owner: u32,
+    }
+
+    impl ControlSocket {
+        /// Bind the control socket at `path`, readable only by its owner
+        ///
+        /// A missing parent directory is created with mode 0700. A stale
+        /// socket left by a crashed daemon is replaced.
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if another daemon is listening on `path` or the
+        /// socket cannot be created.
+        pub async fn bind(path: &Path) -> anyhow::Result<Self> {
+            if let Some(parent) = path.parent()
+                && !parent.as_os_str().is_empty()
+                && !parent.exists()
+            {
+                std::fs::DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(parent)?;
+            }
+
+            if path.exists() {
+                if UnixStream::connect(path).await.is_ok() {
+                    anyhow::bail!("A daemon is already running on {}", path.display());
+                }
+                std::fs::remove_file(path)?;
+            }
+
+            let listener = UnixListener::bind(path)?;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            let owner = std::fs::metadata(path)?.uid();
+
+            Ok(Self {
+                listener,
+                path: path.to_path_buf(),
+                owner,
+            })
+        }
+
+        /// Path of the socket
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Accept control connections until the task is dropped
+        pub async fn serve(self, state: Arc<DaemonState>) {
+            loop {
+                let stream = match self.listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Control socket accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                // The socket mode already restricts access; check again in
+                // case the directory or mode was changed behind our back
+                match stream.peer_cred() {
+                    Ok(cred) if cred.uid() == self.owner => {}
+                    _ => {
+                        tracing::warn!("Rejected control connection from another user");
+                        continue;
+                    }
+                }
+
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    let (reader, writer) = stream.into_split();
+                    if let Err(e) = serve_connection(state, reader, writer).await {
+                        tracing::debug!("Control connection closed: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    impl Drop for ControlSocket {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// HTTP endpoint serving OpenMetrics for Prometheus-compatible scrapers
+pub mod http {
+    use super::DaemonState;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use wraith_core::node::OPENMETRICS_CONTENT_TYPE;
+
+    /// Largest request head accepted
+    const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+    /// Time a client gets to send its request
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Bound metrics listener
+    pub struct MetricsServer {
+        listener: TcpListener,
+    }
+
+    impl MetricsServer {
+        /// Bind the metrics endpoint
+        ///
+        /// # Errors
+        ///
+        /// Returns an error if `addr` cannot be bound.
+        pub async fn bind(addr: SocketAddr) -> anyhow::Result<Self> {
+            let listener = TcpListener::bind(addr)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to bind metrics endpoint {addr}: {e}"))?;
+            Ok(Self { listener })
+        }
+
+        /// Address the endpoint is listening on
+        pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+            self.listener.local_addr()
+        }
+
+        /// Answer scrapes until the task is dropped
+        pub async fn serve(self, state: Arc<DaemonState>) {
+            loop {
+                let stream = match self.listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("Metrics endpoint accept failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    if let Err(e) = serve_request(&state, stream).await {
+                        tracing::debug!("Metrics request failed: {}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Answer one request and close the connection
+    async fn serve_request(state: &DaemonState, mut stream: TcpStream) -> anyhow::Result<()> {
+        let head = tokio::time::timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await??;
+        let mut parts = head.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+
+        let (status, content_type, body) = match (method, path) {
+            ("GET" | "HEAD", "/metrics") => (
+                "200 OK",
+                OPENMETRICS_CONTENT_TYPE,
+                state.openmetrics().await,
+            ),
+            ("GET" | "HEAD", _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+            _ => (
+                "405 Method Not Allowed",
+                "text/plain",
+                "Method not allowed\n".to_string(),
+            ),
+        };
+
+        let mut response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        if method != "HEAD" {
+            response.push_str(&body);
+        }
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Read the request line and headers
+    async fn read_head(stream: &mut TcpStream) -> anyhow::Result<String> {
+        let mut head = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+            if head.len() > MAX_REQUEST_SIZE {
+                anyhow::bail!("request too large");
+            }
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                anyhow::bail!("connection closed before the request ended");
+            }
+            head.extend_from_slice(&buf[..n]);
+        }
+        Ok(String::from_utf8_lossy(&head).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::DaemonClient;
+    use wraith_core::node::progress::TransferStatus;
+
+    async fn test_state() -> Arc<DaemonState> {
+        let node = Node::new_random().await.unwrap();
+        DaemonState::new(Arc::new(node), Config::default())
+    }
+
+    fn connect(state: Arc<DaemonState>) -> DaemonClient {
+        let (client_side, daemon_side) = tokio::io::duplex(64 * 1024);
+        let (reader, writer) = tokio::io::split(daemon_side);
+        tokio::spawn(serve_connection(state, reader, writer));
+        let (reader, writer) = tokio::io::split(client_side);
+        DaemonClient::new(reader, writer)
+    }
+
+    #[tokio::test]
+    async fn test_status_request() {
+        let state = test_state().await;
+        let node_id = hex::encode(state.node.node_id());
+        let mut client = connect(state);
+
+        match client.request(Command::Status).await.unwrap() {
+            Response::Status(status) => {
+                assert_eq!(status.protocol_version, PROTOCOL_VERSION);
+                assert_eq!(status.node_id, node_id);
+                assert_eq!(status.sessions, 0);
+                assert_eq!(status.transfers, 0);
+            }
+            other => panic!("unexpected reply {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_transfer_is_not_found() {
+        let mut client = connect(test_state().await);
+
+        let err = client
+            .request(Command::CancelTransfer {
+                transfer_id: hex::encode([9u8; 32]),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("NotFound"), "{err}");
+
+        let err = client
+            .request(Command::PauseTransfer {
+                transfer_id: "not-hex".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("InvalidRequest"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_other_protocol_versions() {
+        let (client_side, daemon_side) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(daemon_side);
+        tokio::spawn(serve_connection(test_state().await, reader, writer));
+
+        let (reader, mut writer) = tokio::io::split(client_side);
+        let request = Request {
+            version: PROTOCOL_VERSION + 1,
+            id: 5,
+            command: Command::Status,
+        };
+        write_message(&mut writer, &request).await.unwrap();
+        write_message(&mut writer, &"garbage").await.unwrap();
+
+        let mut lines = BufReader::new(reader).lines();
+        let reply: Message =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert!(matches!(
+            reply,
+            Message::Error {
+                id: 5,
+                code: ErrorCode::UnsupportedVersion,
+                ..
+            }
+        ));
+        let reply: Message =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert!(matches!(
+            reply,
+            Message::Error {
+                id: 0,
+                code: ErrorCode::InvalidRequest,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_health_and_metrics() {
+        let mut client = connect(test_state().await);
+
+        match client.request(Command::Health).await.unwrap() {
+            Response::Health(health) => {
+                assert!(!health.running);
+                assert_eq!(health.sessions, 0);
+            }
+            other => panic!("unexpected reply {other:?}"),
+        }
+        match client.request(Command::Metrics).await.unwrap() {
+            Response::Metrics(metrics) => {
+                assert_eq!(metrics.active_transfers, 0);
+                assert_eq!(metrics.bytes_sent, 0);
+            }
+            other => panic!("unexpected reply {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        use tokio::io::AsyncWriteExt;
+
+        let server = http::MetricsServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn(server.serve(test_state().await));
+
+        let get = |path: &str| {
+            let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+            async move {
+                let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                response
+            }
+        };
+
+        let response = get("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/openmetrics-text"));
+        assert!(response.contains("\nwraith_sessions 0\n"));
+        assert!(response.contains("\nwraith_daemon_uptime_seconds "));
+        assert!(response.ends_with("# EOF\n"));
+
+        assert!(get("/").await.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_events_reach_subscribers() {
+        let state = test_state().await;
+        let mut client = connect(Arc::clone(&state));
+        client.subscribe().await.unwrap();
+
+        let event = Event::SessionOpened {
+            peer_id: "aa".to_string(),
+        };
+        state.events.send(event.clone()).unwrap();
+        assert_eq!(client.next_event().await.unwrap(), Some(event));
+    }
+
+    #[tokio::test]
+    async fn test_poll_events_reports_cancelled_transfers() {
+        let state = test_state().await;
+        let mut sessions = HashSet::new();
+        let mut transfers = HashMap::new();
+        transfers.insert(
+            "aa".to_string(),
+            TransferInfo {
+                transfer_id: "aa".to_string(),
+                status: TransferStatus::Transferring.to_string(),
+                bytes_done: 1,
+                bytes_total: 2,
+                speed_bytes_per_sec: 0.0,
+                eta_secs: None,
+            },
+        );
+
+        let events = poll_events(&state, &mut sessions, &mut transfers).await;
+        assert_eq!(
+            events,
+            vec![Event::TransferFinished {
+                transfer_id: "aa".to_string(),
+                status: "Cancelled".to_string(),
+            }]
+        );
+        assert!(transfers.is_empty());
+        assert!(
+            poll_events(&state, &mut sessions, &mut transfers)
+                .await
+                .is_empty()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_control_socket_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/control.sock");
+        let socket = socket::ControlSocket::bind(&path).await.unwrap();
+
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+        let state = test_state().await;
+        let server = tokio::spawn(async move {
+            socket.serve(state).await;
+        });
+
+        // A second daemon refuses to take over the socket
+        assert!(socket::ControlSocket::bind(&path).await.is_err());
+
+        let mut client = DaemonClient::connect(&path).await.unwrap().unwrap();
+        assert!(matches!(
+            client.request(Command::Status).await.unwrap(),
+            Response::Status(_)
+        ));
+
+        server.abort();
+        let _ = server.await;
+        assert!(!path.exists());
+        assert!(DaemonClient::connect(&path).await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stale_socket_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+
+        // Left behind by a daemon that did not shut down cleanly
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        assert!(DaemonClient::connect(&path).await.unwrap().is_none());
+
+        let _socket = socket::ControlSocket::bind(&path).await.unwrap();
+        assert!(DaemonClient::connect(&path).await.unwrap().is_some());
+    }
+}
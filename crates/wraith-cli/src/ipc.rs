@@ -0,0 +1,599 @@
+//! Daemon control protocol.
+//!
+//! A running `wraith daemon` accepts commands on a Unix socket that only its
+//! owner can open. Messages are JSON objects, one per line. Clients send a
+//! [`Request`] tagged with an `id` and the protocol version; the daemon
+//! answers each one with a [`Message::Response`] or [`Message::Error`]
+//! carrying the same `id`. After a `subscribe` request the daemon also pushes
+//! [`Message::Event`]s on the connection.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+
+/// Control protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Longest message accepted on the control socket
+pub const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Request sent to the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    /// Protocol version the client speaks
+    pub version: u32,
+    /// Caller-chosen ID echoed in the reply
+    pub id: u64,
+    /// Command to run
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// Commands understood by the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Node identity, addresses and counts
+    Status,
+    /// Send a file or directory to one or more peers
+    Send {
+        /// Absolute path of the file or directory
+        path: PathBuf,
+        /// Peer IDs, `host:port` or `peer-id@host:port`
+        recipients: Vec<String>,
+        /// Send a directory as a single transfer
+        recursive: bool,
+    },
+    /// List all transfers known to the node
+    ListTransfers,
+    /// Cancel a transfer
+    CancelTransfer {
+        /// Transfer ID (hex)
+        transfer_id: String,
+    },
+    /// Pause an outgoing transfer
+    PauseTransfer {
+        /// Transfer ID (hex)
+        transfer_id: String,
+    },
+    /// Resume a paused transfer
+    ResumeTransfer {
+        /// Transfer ID (hex)
+        transfer_id: String,
+    },
+    /// List established sessions
+    ListSessions,
+    /// Close the session with a peer
+    CloseSession {
+        /// Peer ID (hex)
+        peer_id: String,
+    },
+    /// Look up a peer's addresses in the DHT
+    DiscoverPeer {
+        /// Peer ID (hex)
+        peer_id: String,
+    },
+    /// Measure the round-trip time to a peer
+    Ping {
+        /// Peer ID, `host:port` or `peer-id@host:port`
+        peer: String,
+    },
+    /// Node health summary
+    Health,
+    /// Runtime counters
+    Metrics,
+    /// Start receiving events on this connection
+    Subscribe,
+}
+
+/// Message sent by the daemon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// Successful reply to a request
+    Response {
+        /// ID of the request
+        id: u64,
+        /// Reply payload
+        body: Response,
+    },
+    /// Failed request
+    Error {
+        /// ID of the request (0 if it could not be parsed)
+        id: u64,
+        /// Error class
+        code: ErrorCode,
+        /// Human-readable description
+        message: String,
+    },
+    /// Event pushed to subscribed clients
+    Event {
+        /// The event
+        event: Event,
+    },
+}
+
+/// Reply payloads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Response {
+    /// Reply to `status`
+    Status(StatusInfo),
+    /// Reply to `send`
+    Started {
+        /// IDs of the new transfers, one per recipient
+        transfer_ids: Vec<String>,
+    },
+    /// Reply to `list_transfers`
+    Transfers {
+        /// All transfers
+        transfers: Vec<TransferInfo>,
+    },
+    /// Reply to `list_sessions`
+    Sessions {
+        /// Established sessions
+        sessions: Vec<SessionInfo>,
+    },
+    /// Reply to `discover_peer`
+    Addresses {
+        /// Addresses the peer was found at
+        addrs: Vec<SocketAddr>,
+    },
+    /// Reply to `ping`
+    Pong {
+        /// Resolved peer ID (hex)
+        peer_id: String,
+        /// Round-trip time in microseconds
+        rtt_us: u64,
+    },
+    /// Reply to `health`
+    Health(HealthInfo),
+    /// Reply to `metrics`
+    Metrics(MetricsInfo),
+    /// Reply to commands without a result
+    Done,
+}
+
+/// Error classes reported by the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request's protocol version is not supported
+    UnsupportedVersion,
+    /// The request could not be parsed or has invalid arguments
+    InvalidRequest,
+    /// The transfer, session or peer does not exist
+    NotFound,
+    /// The command failed
+    Failed,
+}
+
+/// Events pushed to subscribed clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum Event {
+    /// A transfer appeared
+    TransferStarted {
+        /// Transfer ID (hex)
+        transfer_id: String,
+    },
+    /// A transfer made progress or changed status
+    TransferProgress(TransferInfo),
+    /// A transfer completed, failed or was cancelled
+    TransferFinished {
+        /// Transfer ID (hex)
+        transfer_id: String,
+        /// Final status
+        status: String,
+    },
+    /// A session was established
+    SessionOpened {
+        /// Peer ID (hex)
+        peer_id: String,
+    },
+    /// A session was closed
+    SessionClosed {
+        /// Peer ID (hex)
+        peer_id: String,
+    },
+}
+
+/// Daemon status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusInfo {
+    /// Control protocol version
+    pub protocol_version: u32,
+    /// Daemon version
+    pub version: String,
+    /// Node ID (hex)
+    pub node_id: String,
+    /// X25519 public key (hex)
+    pub x25519_key: String,
+    /// Address the node listens on
+    pub listen_addr: Option<SocketAddr>,
+    /// Seconds since the daemon started
+    pub uptime_secs: u64,
+    /// Established sessions
+    pub sessions: usize,
+    /// Known transfers
+    pub transfers: usize,
+}
+
+/// Transfer state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferInfo {
+    /// Transfer ID (hex)
+    pub transfer_id: String,
+    /// Status (`Initializing`, `Transferring`, `Paused`, `Complete`, `Failed`)
+    pub status: String,
+    /// Bytes transferred so far
+    pub bytes_done: u64,
+    /// Total bytes
+    pub bytes_total: u64,
+    /// Current speed in bytes per second
+    pub speed_bytes_per_sec: f64,
+    /// Estimated seconds remaining
+    pub eta_secs: Option<u64>,
+}
+
+impl TransferInfo {
+    /// Whether the transfer has completed or failed
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "Complete" | "Failed")
+    }
+}
+
+/// Session state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    /// Peer ID (hex)
+    pub peer_id: String,
+    /// Current peer address
+    pub addr: Option<SocketAddr>,
+    /// Transport carrying the session
+    pub transport: Option<String>,
+    /// Seconds since the session was established
+    pub age_secs: Option<u64>,
+    /// Completed rekeys
+    pub key_epoch: Option<u32>,
+    /// Bytes sent
+    pub bytes_sent: u64,
+    /// Bytes received
+    pub bytes_received: u64,
+    /// Round-trip time in microseconds
+    pub rtt_us: Option<u64>,
+}
+
+/// Node health
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthInfo {
+    /// Whether the node is running
+    pub running: bool,
+    /// Seconds since the daemon started
+    pub uptime_secs: u64,
+    /// Addresses of each listening transport
+    pub transports: Vec<(String, SocketAddr)>,
+    /// Established sessions
+    pub sessions: usize,
+    /// Transfers in progress
+    pub active_transfers: usize,
+    /// Failed transfers
+    pub failed_transfers: usize,
+    /// Handshake failures seen
+    pub handshake_failures: u64,
+    /// Peers banned temporarily or permanently
+    pub bans: u64,
+}
+
+/// Runtime counters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsInfo {
+    /// Seconds since the daemon started
+    pub uptime_secs: u64,
+    /// Established sessions
+    pub sessions: usize,
+    /// Transfers in progress
+    pub active_transfers: usize,
+    /// Completed transfers
+    pub completed_transfers: usize,
+    /// Failed transfers
+    pub failed_transfers: usize,
+    /// Bytes sent by the node's transports
+    pub bytes_sent: u64,
+    /// Bytes received by the node's transports
+    pub bytes_received: u64,
+    /// Packets sent by the node's transports
+    pub packets_sent: u64,
+    /// Packets received by the node's transports
+    pub packets_received: u64,
+    /// Active routes
+    pub routes: usize,
+    /// Handshake failures
+    pub handshake_failures: u64,
+    /// Rate limit violations
+    pub rate_limit_violations: u64,
+}
+
+/// Write one message as a JSON line
+///
+/// # Errors
+///
+/// Returns an error if serialization or the write fails.
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Connection to a running daemon
+pub struct DaemonClient {
+    reader: Lines<BufReader<Box<dyn AsyncRead + Send + Unpin>>>,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    next_id: u64,
+    /// Events received while waiting for a reply
+    events: VecDeque<Event>,
+}
+
+impl DaemonClient {
+    /// Connect to the daemon listening on `path`
+    ///
+    /// Returns `None` if no daemon is running there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket exists but cannot be opened for another
+    /// reason, such as missing permissions.
+    #[cfg(unix)]
+    pub async fn connect(path: &Path) -> anyhow::Result<Option<Self>> {
+        use std::io::ErrorKind;
+
+        match tokio::net::UnixStream::connect(path).await {
+            Ok(stream) => {
+                let (reader, writer) = stream.into_split();
+                Ok(Some(Self::new(reader, writer)))
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+                Ok(None)
+            }
+            Err(e) => Err(anyhow::anyhow!(
+                "Cannot open daemon control socket {}: {e}",
+                path.display()
+            )),
+        }
+    }
+
+    /// Connect to the daemon listening on `path`
+    ///
+    /// The control socket is only available on Unix.
+    #[cfg(not(unix))]
+    pub async fn connect(_path: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(None)
+    }
+
+    /// Wrap an established connection
+    pub fn new(
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> Self {
+        let reader: Box<dyn AsyncRead + Send + Unpin> = Box::new(reader);
+        Self {
+            reader: BufReader::new(reader).lines(),
+            writer: Box::new(writer),
+            next_id: 1,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Run a command and wait for its reply
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails or the daemon reports an
+    /// error.
+    pub async fn request(&mut self, command: Command) -> anyhow::Result<Response> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            id,
+            command,
+        };
+        write_message(&mut self.writer, &request).await?;
+
+        loop {
+            match self.read_message().await? {
+                Message::Response { id: reply_id, body } if reply_id == id => return Ok(body),
+                Message::Error {
+                    id: reply_id,
+                    code,
+                    message,
+                } if reply_id == id || reply_id == 0 => {
+                    anyhow::bail!("Daemon error ({code:?}): {message}")
+                }
+                Message::Event { event } => self.events.push_back(event),
+                // Replies to requests that were given up on
+                _ => {}
+            }
+        }
+    }
+
+    /// Ask the daemon to push events on this connection
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails.
+    pub async fn subscribe(&mut self) -> anyhow::Result<()> {
+        self.request(Command::Subscribe).await?;
+        Ok(())
+    }
+
+    /// Wait for the next event
+    ///
+    /// Returns `None` when the daemon closes the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection fails.
+    pub async fn next_event(&mut self) -> anyhow::Result<Option<Event>> {
+        if let Some(event) = self.events.pop_front() {
+            return Ok(Some(event));
+        }
+        loop {
+            match self.read_message().await {
+                Ok(Message::Event { event }) => return Ok(Some(event)),
+                Ok(_) => {}
+                Err(e) if e.is::<ConnectionClosed>() => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn read_message(&mut self) -> anyhow::Result<Message> {
+        let line = self.reader.next_line().await?.ok_or(ConnectionClosed)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// The daemon closed the control connection
+#[derive(Debug)]
+struct ConnectionClosed;
+
+impl std::fmt::Display for ConnectionClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Daemon closed the connection")
+    }
+}
+
+impl std::error::Error for ConnectionClosed {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            id: 7,
+            command: Command::CancelTransfer {
+                transfer_id: "ab".to_string(),
+            },
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"id":7,"command":"cancel_transfer","transfer_id":"ab"}"#
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let status: Request =
+            serde_json::from_str(r#"{"version":1,"id":1,"command":"status"}"#).unwrap();
+        assert_eq!(status.command, Command::Status);
+    }
+
+    #[test]
+    fn test_message_roundtrip() {
+        let messages = [
+            Message::Response {
+                id: 1,
+                body: Response::Started {
+                    transfer_ids: vec!["00".to_string()],
+                },
+            },
+            Message::Response {
+                id: 2,
+                body: Response::Done,
+            },
+            Message::Error {
+                id: 3,
+                code: ErrorCode::NotFound,
+                message: "Transfer not found".to_string(),
+            },
+            Message::Event {
+                event: Event::SessionOpened {
+                    peer_id: "11".to_string(),
+                },
+            },
+        ];
+        for message in messages {
+            let json = serde_json::to_string(&message).unwrap();
+            assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_transfer_info_finished() {
+        let mut info = TransferInfo {
+            transfer_id: String::new(),
+            status: "Transferring".to_string(),
+            bytes_done: 0,
+            bytes_total: 10,
+            speed_bytes_per_sec: 0.0,
+            eta_secs: None,
+        };
+        assert!(!info.is_finished());
+        info.status = "Complete".to_string();
+        assert!(info.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_client_queues_events_while_waiting() {
+        let (client_side, mut daemon_side) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(client_side);
+        let mut client = DaemonClient::new(reader, writer);
+
+        tokio::spawn(async move {
+            let event = Message::Event {
+                event: Event::TransferStarted {
+                    transfer_id: "aa".to_string(),
+                },
+            };
+            write_message(&mut daemon_side, &event).await.unwrap();
+            let reply = Message::Response {
+                id: 1,
+                body: Response::Done,
+            };
+            write_message(&mut daemon_side, &reply).await.unwrap();
+        });
+
+        assert_eq!(
+            client.request(Command::Subscribe).await.unwrap(),
+            Response::Done
+        );
+        assert_eq!(
+            client.next_event().await.unwrap(),
+            Some(Event::TransferStarted {
+                transfer_id: "aa".to_string()
+            })
+        );
+        assert_eq!(client.next_event().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_client_reports_daemon_errors() {
+        let (client_side, mut daemon_side) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(client_side);
+        let mut client = DaemonClient::new(reader, writer);
+
+        tokio::spawn(async move {
+            let reply = Message::Error {
+                id: 1,
+                code: ErrorCode::UnsupportedVersion,
+                message: "Unsupported protocol version 9".to_string(),
+            };
+            write_message(&mut daemon_side, &reply).await.unwrap();
+        });
+
+        let err = client.request(Command::Status).await.unwrap_err();
+        assert!(err.to_string().contains("Unsupported protocol version"));
+    }
+}
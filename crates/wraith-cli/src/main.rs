@@ -8,22 +8,30 @@
 //! - Memory zeroization for sensitive data
 
 mod config;
+mod daemon;
+mod ipc;
 mod progress;
 mod redops;
+mod relay;
 
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use zeroize::Zeroize;
 
 use config::Config;
+use ipc::{Command, DaemonClient, MetricsInfo, Response, TransferInfo};
 use progress::{TransferProgress, format_bytes};
 
 // WRAITH Core imports
 use wraith_core::node::identity::TransferId;
 use wraith_core::node::session::PeerId;
-use wraith_core::node::{Node, NodeConfig};
+use wraith_core::node::{
+    AcceptAll, IncomingTransfer, KnownPeers, KnownPeersError, Node, NodeConfig, NodeError,
+    PinStatus, TransferAcceptor, TransferDecision, TrustedPeers,
+};
 
 /// Encrypted private key file header magic bytes
 const ENCRYPTED_KEY_MAGIC: &[u8; 8] = b"WRAITH01";
@@ -61,14 +69,18 @@ struct Cli {
 enum Commands {
     /// Send a file to one or more peers
     Send {
-        /// File to send
+        /// File (or directory, with --recursive) to send
         #[arg(required = true)]
         file: String,
 
-        /// Recipient peer ID or address (can be specified multiple times)
+        /// Recipient peer ID, `host:port`, or `peer-id@host:port` (can be specified multiple times)
         #[arg(required = true)]
         recipient: Vec<String>,
 
+        /// Send a directory and everything in it as a single transfer
+        #[arg(short, long)]
+        recursive: bool,
+
         /// Obfuscation mode
         #[arg(long, default_value = "privacy")]
         mode: String,
@@ -80,7 +92,7 @@ enum Commands {
         #[arg(required = true)]
         files: Vec<String>,
 
-        /// Recipient peer ID or address
+        /// Recipient peer ID, `host:port`, or `peer-id@host:port`
         #[arg(short, long, required = true)]
         to: String,
 
@@ -103,7 +115,7 @@ enum Commands {
         #[arg(long)]
         auto_accept: bool,
 
-        /// Comma-separated list of trusted peer IDs (only accept from these peers)
+        /// Comma-separated list of trusted peer IDs or known-peer hosts (only accept from these peers)
         #[arg(long)]
         trusted_peers: Option<String>,
     },
@@ -114,9 +126,20 @@ enum Commands {
         #[arg(short, long, default_value = "0.0.0.0:0")]
         bind: String,
 
-        /// Enable relay mode
+        /// Also run the relay servers from the `[relay]` configuration
         #[arg(long)]
         relay: bool,
+
+        /// Serve OpenMetrics on this address, instead of `daemon.metrics_addr`
+        #[arg(long, value_name = "ADDR")]
+        metrics: Option<String>,
+    },
+
+    /// Run a relay server for peers that cannot connect directly
+    Relay {
+        /// Address to serve on, instead of the configured ones (repeatable)
+        #[arg(short, long)]
+        bind: Vec<String>,
     },
 
     /// Show connection status
@@ -154,6 +177,15 @@ enum Commands {
     /// Show node information
     Info,
 
+    /// List or control transfers on the running daemon
+    Transfers {
+        #[command(subcommand)]
+        action: Option<TransferAction>,
+    },
+
+    /// Stream transfer and session events from the running daemon
+    Events,
+
     /// Generate a new identity keypair
     Keygen {
         /// Output file for private key
@@ -163,7 +195,7 @@ enum Commands {
 
     /// Ping a peer to measure connectivity
     Ping {
-        /// Peer ID to ping
+        /// Peer ID, `host:port`, or `peer-id@host:port` to ping
         #[arg(required = true)]
         peer: String,
 
@@ -187,6 +219,30 @@ enum Commands {
     RedOps(redops::RedOpsCommands),
 }
 
+#[derive(Subcommand)]
+enum TransferAction {
+    /// List all transfers
+    List,
+
+    /// Cancel a transfer
+    Cancel {
+        /// Transfer ID
+        transfer_id: String,
+    },
+
+    /// Pause an outgoing transfer
+    Pause {
+        /// Transfer ID
+        transfer_id: String,
+    },
+
+    /// Resume a paused transfer
+    Resume {
+        /// Transfer ID
+        transfer_id: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum ConfigAction {
     /// Show current configuration
@@ -221,6 +277,104 @@ fn parse_transfer_id(s: &str) -> anyhow::Result<TransferId> {
         .map_err(|e| anyhow::anyhow!("Failed to parse transfer ID: {}", e))
 }
 
+/// A peer named on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PeerTarget {
+    /// Bare peer ID, located through discovery
+    Id(PeerId),
+    /// `host:port`, pinned on first use in the known peers file
+    Host(String),
+    /// `peer-id@host:port`, dialed with the given key
+    Pinned(PeerId, String),
+}
+
+/// Parse a peer ID, `host:port`, or `peer-id@host:port`
+fn parse_peer_target(s: &str) -> anyhow::Result<PeerTarget> {
+    if let Some((peer_id, host)) = s.split_once('@') {
+        return Ok(PeerTarget::Pinned(
+            parse_peer_id(peer_id)?,
+            parse_host(host)?.to_string(),
+        ));
+    }
+    match parse_peer_id(s) {
+        Ok(peer_id) => Ok(PeerTarget::Id(peer_id)),
+        Err(_) if s.contains(':') => Ok(PeerTarget::Host(parse_host(s)?.to_string())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Check that `host` has the form `host:port`
+fn parse_host(host: &str) -> anyhow::Result<&str> {
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.parse::<u16>().is_ok() => Ok(host),
+        _ => anyhow::bail!("Invalid peer address {host:?} (expected host:port)"),
+    }
+}
+
+/// Open a session to `target`, checking and updating the known peers file
+///
+/// Bare peer IDs are returned unchanged and left to discovery. Addresses are
+/// dialed with the pinned key when one is known; an unpinned `host:port`
+/// accepts whichever key answers and pins it (trust on first use).
+async fn connect_peer_target(
+    node: &Node,
+    target: &PeerTarget,
+    known_peers: &mut KnownPeers,
+) -> anyhow::Result<PeerId> {
+    let (host, expected) = match target {
+        PeerTarget::Id(peer_id) => return Ok(*peer_id),
+        PeerTarget::Host(host) => (host, known_peers.lookup(host)),
+        PeerTarget::Pinned(peer_id, host) => {
+            known_peers.verify(host, peer_id)?;
+            (host, Some(*peer_id))
+        }
+    };
+
+    let addr = tokio::net::lookup_host(host.as_str())
+        .await?
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No addresses found for {host}"))?;
+
+    let peer_id = match expected {
+        Some(expected) => match node.establish_session_with_addr(&expected, addr).await {
+            Ok(_) => expected,
+            Err(NodeError::PeerKeyMismatch {
+                expected,
+                presented,
+            }) => {
+                return Err(KnownPeersError::KeyChanged {
+                    host: host.clone(),
+                    pinned: expected,
+                    presented,
+                }
+                .into());
+            }
+            Err(e) => return Err(e.into()),
+        },
+        None => node.establish_session_with_unverified_addr(addr).await?,
+    };
+
+    if known_peers.verify(host, &peer_id)? == PinStatus::New {
+        known_peers.pin(host, peer_id);
+        known_peers.save()?;
+        println!(
+            "Pinned {host} as {} in {}",
+            hex::encode(peer_id),
+            known_peers.path().display()
+        );
+    }
+    Ok(peer_id)
+}
+
+/// Resolve a `--trusted-peers` entry: a peer ID or a host in the known peers file
+fn resolve_trusted_peer(entry: &str, known_peers: &KnownPeers) -> anyhow::Result<PeerId> {
+    parse_peer_id(entry).or_else(|e| {
+        known_peers
+            .lookup(entry)
+            .ok_or_else(|| anyhow::anyhow!("{e} (and {entry:?} is not a known peer host)"))
+    })
+}
+
 /// Format duration as human-readable string
 #[allow(dead_code)]
 fn format_duration(d: Duration) -> String {
@@ -236,14 +390,119 @@ fn format_duration(d: Duration) -> String {
 
 /// Create NodeConfig from CLI Config
 fn create_node_config(config: &Config) -> NodeConfig {
-    NodeConfig {
+    let mut node_config = NodeConfig {
         listen_addr: config
             .network
             .listen_addr
             .parse()
             .unwrap_or_else(|_| "0.0.0.0:0".parse().expect("Invalid default listen address")),
         ..NodeConfig::default()
+    };
+    node_config.obfuscation.elligator_handshake = config.obfuscation.elligator_handshake;
+    node_config.discovery.enable_port_mapping = config.network.port_mapping;
+    node_config.discovery.enable_mdns = config.discovery.mdns;
+    node_config
+}
+
+/// Connect to the running daemon, if there is one
+async fn daemon_client(config: &Config) -> anyhow::Result<Option<DaemonClient>> {
+    DaemonClient::connect(&config.daemon.control_socket).await
+}
+
+/// Connect to the running daemon, failing if there is none
+async fn require_daemon(config: &Config) -> anyhow::Result<DaemonClient> {
+    daemon_client(config).await?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No daemon running on {} (start one with: wraith daemon)",
+            config.daemon.control_socket.display()
+        )
+    })
+}
+
+/// Print one transfer as a table row
+fn print_transfer(transfer: &TransferInfo) {
+    let percent = if transfer.bytes_total > 0 {
+        transfer.bytes_done as f64 / transfer.bytes_total as f64 * 100.0
+    } else {
+        0.0
+    };
+    println!(
+        "  {}  {:<12} {} / {} ({:.1}%)",
+        transfer.transfer_id,
+        transfer.status,
+        format_bytes(transfer.bytes_done),
+        format_bytes(transfer.bytes_total),
+        percent
+    );
+}
+
+/// Error for a daemon reply that does not match the request
+fn unexpected_reply(reply: Response) -> anyhow::Error {
+    anyhow::anyhow!("Unexpected daemon reply: {reply:?}")
+}
+
+/// List, cancel, pause or resume transfers on the daemon
+async fn manage_transfers(action: TransferAction, config: &Config) -> anyhow::Result<()> {
+    let mut client = require_daemon(config).await?;
+
+    let (command, done) = match action {
+        TransferAction::List => {
+            let transfers = match client.request(Command::ListTransfers).await? {
+                Response::Transfers { transfers } => transfers,
+                other => return Err(unexpected_reply(other)),
+            };
+            if transfers.is_empty() {
+                println!("No transfers");
+            } else {
+                println!("Transfers: {}", transfers.len());
+                for transfer in &transfers {
+                    print_transfer(transfer);
+                }
+            }
+            return Ok(());
+        }
+        TransferAction::Cancel { transfer_id } => {
+            (Command::CancelTransfer { transfer_id }, "cancelled")
+        }
+        TransferAction::Pause { transfer_id } => (Command::PauseTransfer { transfer_id }, "paused"),
+        TransferAction::Resume { transfer_id } => {
+            (Command::ResumeTransfer { transfer_id }, "resumed")
+        }
+    };
+
+    match client.request(command).await? {
+        Response::Done => println!("Transfer {done}"),
+        other => return Err(unexpected_reply(other)),
     }
+    Ok(())
+}
+
+/// Print daemon events until the daemon exits
+async fn stream_events(config: &Config) -> anyhow::Result<()> {
+    let mut client = require_daemon(config).await?;
+    client.subscribe().await?;
+    println!(
+        "Streaming events from {} (Ctrl+C to stop)",
+        config.daemon.control_socket.display()
+    );
+
+    while let Some(event) = client.next_event().await? {
+        match event {
+            ipc::Event::TransferStarted { transfer_id } => {
+                println!("transfer started   {transfer_id}");
+            }
+            ipc::Event::TransferProgress(transfer) => print_transfer(&transfer),
+            ipc::Event::TransferFinished {
+                transfer_id,
+                status,
+            } => println!("transfer finished  {transfer_id} ({status})"),
+            ipc::Event::SessionOpened { peer_id } => println!("session opened     {peer_id}"),
+            ipc::Event::SessionClosed { peer_id } => println!("session closed     {peer_id}"),
+        }
+    }
+
+    println!("Daemon stopped");
+    Ok(())
 }
 
 #[tokio::main]
@@ -292,9 +551,10 @@ async fn main() -> anyhow::Result<()> {
         Commands::Send {
             file,
             recipient,
+            recursive,
             mode,
         } => {
-            send_file(PathBuf::from(file), recipient, mode, &config).await?;
+            send_file(PathBuf::from(file), recipient, recursive, mode, &config).await?;
         }
         Commands::Batch { files, to, mode } => {
             send_batch(files, to, mode, &config).await?;
@@ -314,8 +574,15 @@ async fn main() -> anyhow::Result<()> {
             )
             .await?;
         }
-        Commands::Daemon { bind, relay } => {
-            run_daemon(bind, relay, &config).await?;
+        Commands::Daemon {
+            bind,
+            relay,
+            metrics,
+        } => {
+            run_daemon(bind, relay, metrics, &config).await?;
+        }
+        Commands::Relay { bind } => {
+            relay::run(&config.relay, bind).await?;
         }
         Commands::Status { transfer, detailed } => {
             show_status(transfer, detailed, &config).await?;
@@ -332,6 +599,12 @@ async fn main() -> anyhow::Result<()> {
         Commands::Info => {
             show_info(&config).await?;
         }
+        Commands::Transfers { action } => {
+            manage_transfers(action.unwrap_or(TransferAction::List), &config).await?;
+        }
+        Commands::Events => {
+            stream_events(&config).await?;
+        }
         Commands::Keygen { .. } => {
             // Already handled above before config loading
             unreachable!("Keygen command should have been handled earlier")
@@ -398,6 +671,21 @@ fn sanitize_path(path: &PathBuf) -> anyhow::Result<PathBuf> {
     }
 }
 
+/// Total size of the regular files under `dir` (symlinks are not followed)
+fn directory_size(dir: &Path) -> std::io::Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += directory_size(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
 /// Encrypt a private key with a passphrase using Argon2id KDF and XChaCha20-Poly1305
 ///
 /// # Format
@@ -549,6 +837,7 @@ fn prompt_passphrase(prompt: &str, confirm: bool) -> anyhow::Result<String> {
 async fn send_file(
     file: PathBuf,
     recipients: Vec<String>,
+    recursive: bool,
     _mode: String,
     config: &Config,
 ) -> anyhow::Result<()> {
@@ -560,27 +849,59 @@ async fn send_file(
         anyhow::bail!("File not found: {file:?}");
     }
 
-    let file_size = std::fs::metadata(&file)?.len();
+    let is_dir = file.is_dir();
+    if is_dir && !recursive {
+        anyhow::bail!("{} is a directory (use -r to send it)", file.display());
+    }
+    let file_size = if is_dir {
+        directory_size(&file)?
+    } else {
+        std::fs::metadata(&file)?.len()
+    };
     let filename = file
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("unknown");
 
-    // Parse all peer IDs
-    let mut peer_ids = Vec::new();
-    for recipient in &recipients {
-        let peer_id = parse_peer_id(recipient)?;
-        peer_ids.push(peer_id);
-    }
+    // Parse all recipients
+    let targets = recipients
+        .iter()
+        .map(|recipient| parse_peer_target(recipient))
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    println!("File: {}", file.display());
+    if is_dir {
+        println!("Directory: {}", file.display());
+    } else {
+        println!("File: {}", file.display());
+    }
     println!("Size: {}", format_bytes(file_size));
-    println!("Recipients: {}", peer_ids.len());
-    for (idx, peer_id) in peer_ids.iter().enumerate() {
-        println!("  {}: {}", idx + 1, hex::encode(&peer_id[..8]));
+    println!("Recipients: {}", recipients.len());
+    for (idx, recipient) in recipients.iter().enumerate() {
+        println!("  {}: {}", idx + 1, recipient);
     }
     println!();
 
+    // Hand the transfer to the daemon if one is running
+    if let Some(mut client) = daemon_client(config).await? {
+        println!("Using daemon: {}", config.daemon.control_socket.display());
+        let transfer_ids = match client
+            .request(Command::Send {
+                path: file.clone(),
+                recipients,
+                recursive,
+            })
+            .await?
+        {
+            Response::Started { transfer_ids } => transfer_ids,
+            other => return Err(unexpected_reply(other)),
+        };
+        for transfer_id in &transfer_ids {
+            println!("  Transfer started: {}", &transfer_id[..16]);
+        }
+        println!();
+        return wait_for_daemon_transfers(&mut client, &transfer_ids, file_size, filename).await;
+    }
+
     // Create and start node
     let node_config = create_node_config(config);
     let node = Node::new_with_config(node_config).await?;
@@ -593,6 +914,13 @@ async fn send_file(
     println!("Listening on: {}", listen_addr);
     println!();
 
+    // Connect to recipients given by address, verifying their pinned keys
+    let mut known_peers = KnownPeers::load(&config.node.known_peers_file)?;
+    let mut peer_ids = Vec::new();
+    for target in &targets {
+        peer_ids.push(connect_peer_target(&node, target, &mut known_peers).await?);
+    }
+
     // Send file to each recipient
     let mut transfer_ids = Vec::new();
     for (idx, peer_id) in peer_ids.iter().enumerate() {
@@ -605,7 +933,11 @@ async fn send_file(
 
         // Send file using Node API
         tracing::info!("Establishing session with peer...");
-        let transfer_id = node.send_file(&file, peer_id).await?;
+        let transfer_id = if is_dir {
+            node.send_tree(&file, peer_id).await?
+        } else {
+            node.send_file(&file, peer_id).await?
+        };
         transfer_ids.push(transfer_id);
 
         println!("  Transfer started: {}", hex::encode(&transfer_id[..8]));
@@ -677,6 +1009,94 @@ async fn send_file(
     Ok(())
 }
 
+/// Wait for transfers started on the daemon, showing their combined progress
+async fn wait_for_daemon_transfers(
+    client: &mut DaemonClient,
+    transfer_ids: &[String],
+    file_size: u64,
+    filename: &str,
+) -> anyhow::Result<()> {
+    let progress = TransferProgress::new(file_size * transfer_ids.len() as u64, filename);
+    let mut finished = std::collections::HashMap::new();
+
+    loop {
+        let transfers = match client.request(Command::ListTransfers).await? {
+            Response::Transfers { transfers } => transfers,
+            other => return Err(unexpected_reply(other)),
+        };
+
+        let mut bytes_done = 0;
+        for transfer_id in transfer_ids {
+            let Some(transfer) = transfers.iter().find(|t| &t.transfer_id == transfer_id) else {
+                // Removed from the daemon, so cancelled
+                finished
+                    .entry(transfer_id.clone())
+                    .or_insert_with(|| "Cancelled".to_string());
+                continue;
+            };
+            bytes_done += transfer.bytes_done;
+            if transfer.is_finished() && !finished.contains_key(transfer_id) {
+                println!(
+                    "Transfer {}: {}",
+                    &transfer_id[..16],
+                    transfer.status.to_lowercase()
+                );
+                finished.insert(transfer_id.clone(), transfer.status.clone());
+            }
+        }
+        progress.update(bytes_done);
+
+        if finished.len() == transfer_ids.len() {
+            let successful = finished.values().filter(|s| *s == "Complete").count();
+            progress.finish_with_message(format!(
+                "All transfers complete: {}/{} successful",
+                successful,
+                transfer_ids.len()
+            ));
+            return Ok(());
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Build the acceptance policy for `wraith receive`
+///
+/// `--trusted-peers` rejects transfers from any other peer; `--auto-accept`
+/// accepts without asking, otherwise the user is prompted for each transfer.
+fn receive_acceptor(auto_accept: bool, trusted_peer_ids: Vec<PeerId>) -> Arc<dyn TransferAcceptor> {
+    match (trusted_peer_ids.is_empty(), auto_accept) {
+        (true, true) => Arc::new(AcceptAll),
+        (true, false) => Arc::new(prompt_accept_transfer),
+        (false, true) => Arc::new(TrustedPeers::new(trusted_peer_ids)),
+        (false, false) => {
+            Arc::new(TrustedPeers::new(trusted_peer_ids).with_inner(prompt_accept_transfer))
+        }
+    }
+}
+
+/// Ask the user on the terminal whether to accept an incoming transfer
+fn prompt_accept_transfer(request: &IncomingTransfer) -> TransferDecision {
+    use std::io::Write;
+
+    print!(
+        "Accept {} ({}) from {}? [y/N] ",
+        request.metadata.file_name,
+        format_bytes(request.metadata.file_size),
+        hex::encode(&request.peer_id[..8])
+    );
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return TransferDecision::Reject("declined by user".to_string());
+    }
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => TransferDecision::Accept,
+        _ => TransferDecision::Reject("declined by user".to_string()),
+    }
+}
+
 /// Receive files from peers
 async fn receive_files(
     output: PathBuf,
@@ -690,27 +1110,31 @@ async fn receive_files(
         std::fs::create_dir_all(&output)?;
     }
 
-    // Parse trusted peers if provided
+    // Parse trusted peers if provided, resolving known-peer hosts to their pinned keys
     let mut trusted_peer_ids = Vec::new();
     if let Some(peers_str) = trusted_peers {
+        let known_peers = KnownPeers::load(&config.node.known_peers_file)?;
         for peer_str in peers_str.split(',') {
-            let peer_id = parse_peer_id(peer_str.trim())?;
+            let peer_id = resolve_trusted_peer(peer_str.trim(), &known_peers)?;
             trusted_peer_ids.push(peer_id);
         }
     }
 
+    // Extract count before logging to avoid cleartext logging of sensitive trusted_peer_ids variable
+    let trusted_peer_count = trusted_peer_ids.len();
+
     // Create and start node
-    let node_config = create_node_config(config);
+    let mut node_config = create_node_config(config);
+    node_config.transfer.download_dir = output.clone();
     let node = Node::new_with_config(node_config).await?;
+    node.set_transfer_acceptor(receive_acceptor(auto_accept, trusted_peer_ids))
+        .await;
 
     tracing::info!("Starting receive node...");
     node.start().await?;
 
     let listen_addr = node.listen_addr().await?;
 
-    // Extract count before logging to avoid cleartext logging of sensitive trusted_peer_ids variable
-    let trusted_peer_count = trusted_peer_ids.len();
-
     println!("WRAITH Receive Mode");
     println!("Version: {}", env!("CARGO_PKG_VERSION"));
     println!();
@@ -730,8 +1154,6 @@ async fn receive_files(
     let node_arc = Arc::new(node);
     let node_clone = Arc::clone(&node_arc);
     let output_clone = output.clone();
-    let _auto_accept = auto_accept;
-    let _trusted_peer_ids = trusted_peer_ids;
 
     tokio::spawn(async move {
         loop {
@@ -770,10 +1192,28 @@ async fn receive_files(
 }
 
 /// Run daemon mode
-async fn run_daemon(_bind: String, _relay: bool, config: &Config) -> anyhow::Result<()> {
-    // Create and start node
+async fn run_daemon(
+    _bind: String,
+    relay: bool,
+    metrics_addr: Option<String>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    // Create node
     let node_config = create_node_config(config);
-    let node = Node::new_with_config(node_config).await?;
+    let node = Arc::new(Node::new_with_config(node_config).await?);
+
+    // Claim the control socket before starting, so a second daemon fails early
+    #[cfg(unix)]
+    let control_socket = daemon::socket::ControlSocket::bind(&config.daemon.control_socket).await?;
+    let metrics_server = match metrics_addr.or_else(|| config.daemon.metrics_addr.clone()) {
+        Some(addr) => {
+            let addr = addr
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid metrics address '{addr}': {e}"))?;
+            Some(daemon::http::MetricsServer::bind(addr).await?)
+        }
+        None => None,
+    };
 
     tracing::info!("Starting WRAITH daemon...");
     node.start().await?;
@@ -791,13 +1231,45 @@ async fn run_daemon(_bind: String, _relay: bool, config: &Config) -> anyhow::Res
     {
         println!("XDP interface: {iface}");
     }
+
+    // Serve the control API
+    let state = daemon::DaemonState::new(Arc::clone(&node), config.clone());
+    let event_watcher = daemon::spawn_event_watcher(Arc::clone(&state));
+    let metrics_task = match metrics_server {
+        Some(server) => {
+            println!("Metrics: http://{}/metrics", server.local_addr()?);
+            Some(tokio::spawn(server.serve(Arc::clone(&state))))
+        }
+        None => None,
+    };
+    #[cfg(unix)]
+    let control_task = {
+        println!("Control socket: {}", control_socket.path().display());
+        tokio::spawn(control_socket.serve(state))
+    };
+    #[cfg(not(unix))]
+    {
+        drop(state);
+        println!("Control socket: unavailable on this platform");
+    }
+
+    // Relay servers run alongside the node
+    let relays = if relay {
+        let relays = relay::RelayService::start(&config.relay, &[]).await?;
+        for addr in relays.addrs() {
+            println!("Relay: {addr}");
+        }
+        Some(relays)
+    } else {
+        None
+    };
+
     println!();
     println!("Daemon ready. Press Ctrl+C to stop");
     println!();
 
     // Monitor sessions and transfers
-    let node_arc = Arc::new(node);
-    let node_clone = Arc::clone(&node_arc);
+    let node_clone = Arc::clone(&node);
 
     tokio::spawn(async move {
         loop {
@@ -820,7 +1292,25 @@ async fn run_daemon(_bind: String, _relay: bool, config: &Config) -> anyhow::Res
     tokio::signal::ctrl_c().await?;
     println!("\nShutting down...");
 
-    node_arc.stop().await?;
+    // Stop accepting commands; dropping the socket removes it
+    #[cfg(unix)]
+    {
+        control_task.abort();
+        let _ = control_task.await;
+    }
+    event_watcher.abort();
+    if let Some(metrics_task) = metrics_task {
+        metrics_task.abort();
+    }
+
+    if let Some(relays) = relays {
+        for (addr, stats) in relays.stats().await {
+            relay::print_stats(addr, &stats);
+        }
+        relays.shutdown().await;
+    }
+
+    node.stop().await?;
     println!("Daemon stopped");
 
     Ok(())
@@ -833,12 +1323,11 @@ async fn send_batch(
     _mode: String,
     config: &Config,
 ) -> anyhow::Result<()> {
-    // Parse peer ID
-    let peer_id = parse_peer_id(&recipient)?;
+    let target = parse_peer_target(&recipient)?;
 
     println!("Batch Transfer");
     println!("Files: {}", files.len());
-    println!("Recipient: {}", hex::encode(&peer_id[..8]));
+    println!("Recipient: {}", recipient);
     println!();
 
     // Validate and sanitize all file paths
@@ -865,6 +1354,37 @@ async fn send_batch(
     println!("Total size: {}", format_bytes(total_size));
     println!();
 
+    // Hand the files to the daemon if one is running
+    if let Some(mut client) = daemon_client(config).await? {
+        println!("Using daemon: {}", config.daemon.control_socket.display());
+        for (idx, (file_path, file_size)) in sanitized_files.iter().enumerate() {
+            let filename = file_path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+
+            println!("[{}/{}] {}", idx + 1, sanitized_files.len(), filename);
+            println!("  Size: {}", format_bytes(*file_size));
+
+            let transfer_ids = match client
+                .request(Command::Send {
+                    path: file_path.clone(),
+                    recipients: vec![recipient.clone()],
+                    recursive: false,
+                })
+                .await?
+            {
+                Response::Started { transfer_ids } => transfer_ids,
+                other => return Err(unexpected_reply(other)),
+            };
+            wait_for_daemon_transfers(&mut client, &transfer_ids, *file_size, filename).await?;
+        }
+
+        println!();
+        println!("Batch transfer complete: {} files sent", files.len());
+        return Ok(());
+    }
+
     // Create and start node
     let node_config = create_node_config(config);
     let node = Node::new_with_config(node_config).await?;
@@ -877,6 +1397,9 @@ async fn send_batch(
     println!("Listening on: {}", listen_addr);
     println!();
 
+    let mut known_peers = KnownPeers::load(&config.node.known_peers_file)?;
+    let peer_id = connect_peer_target(&node, &target, &mut known_peers).await?;
+
     // Send each file
     for (idx, (file_path, file_size)) in sanitized_files.iter().enumerate() {
         let filename = file_path
@@ -925,12 +1448,37 @@ async fn show_status(
     println!();
 
     if let Some(transfer_id_str) = transfer {
-        let transfer_id = parse_transfer_id(&transfer_id_str)?;
-        println!("Transfer status query: {}", hex::encode(&transfer_id[..8]));
+        let transfer_id = hex::encode(parse_transfer_id(&transfer_id_str)?);
+        println!("Transfer status query: {}", &transfer_id[..16]);
         println!();
-        println!("NOTE: Transfer status queries require a running daemon.");
-        println!("Start a daemon with: wraith daemon");
-        println!("Then query transfer status via IPC (future feature)");
+
+        let Some(mut client) = daemon_client(config).await? else {
+            println!("NOTE: Transfer status queries require a running daemon.");
+            println!("Start a daemon with: wraith daemon");
+            return Ok(());
+        };
+        let transfers = match client.request(Command::ListTransfers).await? {
+            Response::Transfers { transfers } => transfers,
+            other => return Err(unexpected_reply(other)),
+        };
+        let transfer = transfers
+            .iter()
+            .find(|t| t.transfer_id == transfer_id)
+            .ok_or_else(|| anyhow::anyhow!("Transfer not found: {transfer_id}"))?;
+
+        println!("Status: {}", transfer.status);
+        println!(
+            "Progress: {} / {}",
+            format_bytes(transfer.bytes_done),
+            format_bytes(transfer.bytes_total)
+        );
+        println!(
+            "Speed: {}/s",
+            format_bytes(transfer.speed_bytes_per_sec as u64)
+        );
+        if let Some(eta) = transfer.eta_secs {
+            println!("ETA: {}", format_duration(Duration::from_secs(eta)));
+        }
         return Ok(());
     }
 
@@ -953,6 +1501,7 @@ async fn show_status(
         println!("  XDP interface: {}", iface);
     }
     println!("  UDP fallback: {}", config.network.udp_fallback);
+    println!("  Port mapping: {}", config.network.port_mapping);
     println!();
 
     println!("Discovery:");
@@ -961,6 +1510,7 @@ async fn show_status(
         config.discovery.bootstrap_nodes.len()
     );
     println!("  Relay servers: {}", config.discovery.relay_servers.len());
+    println!("  LAN discovery (mDNS): {}", config.discovery.mdns);
     println!();
 
     // Detailed information
@@ -1011,9 +1561,26 @@ async fn show_status(
         println!();
     }
 
-    println!("NOTE: Runtime status requires a running daemon.");
-    println!("Start a daemon with: wraith daemon");
-    println!("Then query status via IPC (future feature)");
+    let Some(mut client) = daemon_client(config).await? else {
+        println!("No daemon running; start one with: wraith daemon");
+        return Ok(());
+    };
+    let status = match client.request(Command::Status).await? {
+        Response::Status(status) => status,
+        other => return Err(unexpected_reply(other)),
+    };
+
+    println!("Daemon:");
+    println!("  Node ID: {}", status.node_id);
+    if let Some(addr) = status.listen_addr {
+        println!("  Listening on: {addr}");
+    }
+    println!(
+        "  Uptime: {}",
+        format_duration(Duration::from_secs(status.uptime_secs))
+    );
+    println!("  Active sessions: {}", status.sessions);
+    println!("  Active transfers: {}", status.transfers);
 
     Ok(())
 }
@@ -1027,6 +1594,28 @@ async fn list_peers(dht_query: Option<String>, config: &Config) -> anyhow::Resul
         println!("Peer ID: {}", hex::encode(peer_id));
         println!();
 
+        if let Some(mut client) = daemon_client(config).await? {
+            println!("Discovering peer via daemon...");
+            let addrs = match client
+                .request(Command::DiscoverPeer {
+                    peer_id: peer_id_str,
+                })
+                .await
+            {
+                Ok(Response::Addresses { addrs }) => addrs,
+                Ok(other) => return Err(unexpected_reply(other)),
+                Err(e) => {
+                    println!("Peer discovery failed: {e}");
+                    return Ok(());
+                }
+            };
+            println!("Addresses: {}", addrs.len());
+            for (idx, addr) in addrs.iter().enumerate() {
+                println!("  {}: {}", idx + 1, addr);
+            }
+            return Ok(());
+        }
+
         // Create temporary node for DHT query
         let node_config = create_node_config(config);
         let node = Node::new_with_config(node_config).await?;
@@ -1098,9 +1687,33 @@ async fn list_peers(dht_query: Option<String>, config: &Config) -> anyhow::Resul
     }
     println!();
 
-    println!("NOTE: Listing active peers requires a running daemon.");
-    println!("Start a daemon with: wraith daemon");
-    println!("Then query peer list via IPC (future feature)");
+    if let Some(mut client) = daemon_client(config).await? {
+        let sessions = match client.request(Command::ListSessions).await? {
+            Response::Sessions { sessions } => sessions,
+            other => return Err(unexpected_reply(other)),
+        };
+        println!("Active sessions: {}", sessions.len());
+        for session in &sessions {
+            let addr = session
+                .addr
+                .map_or_else(|| "-".to_string(), |addr| addr.to_string());
+            let rtt = session.rtt_us.map_or_else(
+                || "-".to_string(),
+                |rtt| format!("{:.2}ms", rtt as f64 / 1000.0),
+            );
+            println!(
+                "  {}  {}  rtt={}  sent={}  received={}",
+                session.peer_id,
+                addr,
+                rtt,
+                format_bytes(session.bytes_sent),
+                format_bytes(session.bytes_received)
+            );
+        }
+    } else {
+        println!("No daemon running; start one with: wraith daemon");
+        println!("to list active sessions.");
+    }
     println!();
     println!("To query a specific peer via DHT, use:");
     println!("  wraith peers --dht-query <peer-id>");
@@ -1160,15 +1773,57 @@ async fn show_health(config: &Config) -> anyhow::Result<()> {
     );
     println!();
 
-    println!("Overall Health: OK");
+    let Some(mut client) = daemon_client(config).await? else {
+        println!("Overall Health: OK");
+        println!();
+        println!("NOTE: For runtime health metrics, start a daemon with: wraith daemon");
+        return Ok(());
+    };
+    let health = match client.request(Command::Health).await? {
+        Response::Health(health) => health,
+        other => return Err(unexpected_reply(other)),
+    };
+
+    println!("Daemon:");
+    println!("  Running: {}", health.running);
+    println!(
+        "  Uptime: {}",
+        format_duration(Duration::from_secs(health.uptime_secs))
+    );
+    for (transport, addr) in &health.transports {
+        println!("  Transport: {transport} on {addr}");
+    }
+    println!("  Active sessions: {}", health.sessions);
+    println!("  Active transfers: {}", health.active_transfers);
+    println!("  Failed transfers: {}", health.failed_transfers);
+    println!("  Handshake failures: {}", health.handshake_failures);
+    println!("  Blocked peers: {}", health.bans);
     println!();
-    println!("NOTE: For runtime health metrics, start a daemon with: wraith daemon");
+
+    println!(
+        "Overall Health: {}",
+        if health.running { "OK" } else { "STOPPED" }
+    );
 
     Ok(())
 }
 
 /// Show metrics
-async fn show_metrics(json: bool, _watch: Option<u64>, config: &Config) -> anyhow::Result<()> {
+async fn show_metrics(json: bool, watch: Option<u64>, config: &Config) -> anyhow::Result<()> {
+    if let Some(interval) = watch {
+        return watch_metrics(json, interval, config).await;
+    }
+
+    if let Some(mut client) = daemon_client(config).await? {
+        let metrics = request_metrics(&mut client).await?;
+        if json {
+            println!("{}", serde_json::to_string_pretty(&metrics)?);
+        } else {
+            print_metrics(&metrics);
+        }
+        return Ok(());
+    }
+
     if json {
         // JSON output
         println!(
@@ -1208,11 +1863,79 @@ async fn show_metrics(json: bool, _watch: Option<u64>, config: &Config) -> anyho
 
     println!("NOTE: Runtime metrics require a running daemon.");
     println!("Start a daemon with: wraith daemon");
-    println!("Then query metrics via IPC (future feature)");
 
     Ok(())
 }
 
+/// Fetch runtime counters from the daemon
+async fn request_metrics(client: &mut DaemonClient) -> anyhow::Result<MetricsInfo> {
+    match client.request(Command::Metrics).await? {
+        Response::Metrics(metrics) => Ok(metrics),
+        other => Err(unexpected_reply(other)),
+    }
+}
+
+/// Print runtime counters as text
+fn print_metrics(metrics: &MetricsInfo) {
+    println!("WRAITH Metrics");
+    println!("Version: {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "Uptime: {}",
+        format_duration(Duration::from_secs(metrics.uptime_secs))
+    );
+    println!();
+    println!("Sessions: {}", metrics.sessions);
+    println!("Routes: {}", metrics.routes);
+    println!(
+        "Transfers: {} active, {} completed, {} failed",
+        metrics.active_transfers, metrics.completed_transfers, metrics.failed_transfers
+    );
+    println!(
+        "Sent: {} ({} packets)",
+        format_bytes(metrics.bytes_sent),
+        metrics.packets_sent
+    );
+    println!(
+        "Received: {} ({} packets)",
+        format_bytes(metrics.bytes_received),
+        metrics.packets_received
+    );
+    println!("Handshake failures: {}", metrics.handshake_failures);
+    println!("Rate limit violations: {}", metrics.rate_limit_violations);
+}
+
+/// Refresh the daemon's metrics every `interval` seconds until Ctrl+C
+///
+/// Text output redraws the screen when stdout is a terminal; JSON output
+/// prints one object per line.
+async fn watch_metrics(json: bool, interval: u64, config: &Config) -> anyhow::Result<()> {
+    if interval == 0 {
+        anyhow::bail!("Watch interval must be at least 1 second");
+    }
+    let mut client = require_daemon(config).await?;
+    let redraw = std::io::stdout().is_terminal();
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        let metrics = request_metrics(&mut client).await?;
+        if json {
+            println!("{}", serde_json::to_string(&metrics)?);
+        } else {
+            if redraw {
+                print!("\x1b[2J\x1b[H");
+            }
+            print_metrics(&metrics);
+            println!();
+            println!("Refreshing every {interval}s. Press Ctrl+C to stop");
+        }
+    }
+}
+
 /// Show node information
 async fn show_info(config: &Config) -> anyhow::Result<()> {
     println!("WRAITH Node Information");
@@ -1226,11 +1949,23 @@ async fn show_info(config: &Config) -> anyhow::Result<()> {
     );
     println!();
 
-    // Generate temporary node to show ID
-    let node = Node::new_random().await?;
+    // Show the daemon's identity, or a temporary one without a daemon
+    let daemon_status = match daemon_client(config).await? {
+        Some(mut client) => match client.request(Command::Status).await? {
+            Response::Status(status) => Some(status),
+            other => return Err(unexpected_reply(other)),
+        },
+        None => None,
+    };
     println!("Node:");
-    println!("  ID: {}", hex::encode(node.node_id()));
-    println!("  X25519 Key: {}", hex::encode(node.x25519_public_key()));
+    if let Some(status) = &daemon_status {
+        println!("  ID: {}", status.node_id);
+        println!("  X25519 Key: {}", status.x25519_key);
+    } else {
+        let node = Node::new_random().await?;
+        println!("  ID: {}", hex::encode(node.node_id()));
+        println!("  X25519 Key: {}", hex::encode(node.x25519_public_key()));
+    }
     println!("  Listen: {}", config.network.listen_addr);
     println!();
 
@@ -1274,8 +2009,10 @@ async fn show_info(config: &Config) -> anyhow::Result<()> {
     println!("  Relay servers: {}", config.discovery.relay_servers.len());
     println!();
 
-    println!("NOTE: Node ID shown is randomly generated.");
-    println!("Use 'wraith keygen' to create a persistent identity.");
+    if daemon_status.is_none() {
+        println!("NOTE: Node ID shown is randomly generated.");
+        println!("Use 'wraith keygen' to create a persistent identity.");
+    }
 
     Ok(())
 }
@@ -1357,14 +2094,17 @@ async fn generate_keypair(output: Option<String>, _config: &Config) -> anyhow::R
 
 /// Ping a peer to measure connectivity and RTT
 async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) -> anyhow::Result<()> {
-    // Parse peer ID
-    let peer_id = parse_peer_id(&peer)?;
+    let target = parse_peer_target(&peer)?;
 
     println!("WRAITH Ping");
-    println!("Peer: {}", hex::encode(peer_id));
+    println!("Peer: {}", peer);
     println!("Count: {count}, Interval: {interval}ms");
     println!();
 
+    if let Some(client) = daemon_client(config).await? {
+        return ping_via_daemon(client, &peer, count, interval).await;
+    }
+
     // Create and start node
     let node_config = create_node_config(config);
     let node = Node::new_with_config(node_config).await?;
@@ -1375,6 +2115,11 @@ async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) ->
     println!("Node ID: {}", hex::encode(node.node_id()));
     println!();
 
+    // Peers given by address are pinged over a session; bare IDs are looked up
+    let mut known_peers = KnownPeers::load(&config.node.known_peers_file)?;
+    let peer_id = connect_peer_target(&node, &target, &mut known_peers).await?;
+    let session_ping = !matches!(target, PeerTarget::Id(_));
+
     // Ping statistics
     let mut rtts = Vec::new();
     let mut packets_sent = 0u32;
@@ -1392,22 +2137,89 @@ async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) ->
         );
         std::io::Write::flush(&mut std::io::stdout())?;
 
-        // Attempt to establish connection or use existing session for RTT measurement
-        match node.discover_peer(&peer_id).await {
-            Ok(addrs) => {
-                let rtt = start.elapsed();
-                rtts.push(rtt);
-                packets_received += 1;
+        if session_ping {
+            match node.ping(&peer_id).await {
+                Ok(rtt) => {
+                    rtts.push(rtt);
+                    packets_received += 1;
+                    println!("time={:.2}ms", rtt.as_secs_f64() * 1000.0);
+                }
+                Err(e) => println!("timeout ({})", e),
+            }
+        } else {
+            // Attempt to establish connection or use existing session for RTT measurement
+            match node.discover_peer(&peer_id).await {
+                Ok(addrs) => {
+                    let rtt = start.elapsed();
+                    rtts.push(rtt);
+                    packets_received += 1;
 
-                println!(
-                    "time={:.2}ms, addrs={}",
-                    rtt.as_secs_f64() * 1000.0,
-                    addrs.len()
-                );
+                    println!(
+                        "time={:.2}ms, addrs={}",
+                        rtt.as_secs_f64() * 1000.0,
+                        addrs.len()
+                    );
+                }
+                Err(e) => {
+                    println!("timeout ({})", e);
+                }
             }
-            Err(e) => {
-                println!("timeout ({})", e);
+        }
+
+        // Wait for interval before next ping (except for last one)
+        if seq < count - 1 {
+            tokio::time::sleep(Duration::from_millis(interval)).await;
+        }
+    }
+
+    println!();
+    print_ping_statistics(
+        &hex::encode(&peer_id[..8]),
+        &rtts,
+        packets_sent,
+        packets_received,
+    );
+    println!();
+
+    // Stop node
+    node.stop().await?;
+
+    Ok(())
+}
+
+/// Ping a peer through the daemon
+async fn ping_via_daemon(
+    mut client: DaemonClient,
+    peer: &str,
+    count: u32,
+    interval: u64,
+) -> anyhow::Result<()> {
+    let mut rtts = Vec::new();
+    let mut peer_id = None;
+    let mut packets_received = 0u32;
+
+    for seq in 0..count {
+        print!("Ping {} ({}/{}): ", peer, seq + 1, count);
+        std::io::Write::flush(&mut std::io::stdout())?;
+
+        match client
+            .request(Command::Ping {
+                peer: peer.to_string(),
+            })
+            .await
+        {
+            Ok(Response::Pong {
+                peer_id: id,
+                rtt_us,
+            }) => {
+                let rtt = Duration::from_micros(rtt_us);
+                rtts.push(rtt);
+                packets_received += 1;
+                peer_id.get_or_insert(id);
+                println!("time={:.2}ms", rtt.as_secs_f64() * 1000.0);
             }
+            Ok(other) => return Err(unexpected_reply(other)),
+            Err(e) => println!("timeout ({})", e),
         }
 
         // Wait for interval before next ping (except for last one)
@@ -1416,8 +2228,16 @@ async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) ->
         }
     }
 
+    println!();
+    let label = peer_id.map_or_else(|| peer.to_string(), |id| id[..16].to_string());
+    print_ping_statistics(&label, &rtts, count, packets_received);
     println!();
 
+    Ok(())
+}
+
+/// Print the summary at the end of a ping run
+fn print_ping_statistics(label: &str, rtts: &[Duration], packets_sent: u32, packets_received: u32) {
     // Calculate statistics
     if !rtts.is_empty() {
         let min_rtt = rtts.iter().min().unwrap();
@@ -1441,7 +2261,7 @@ async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) ->
             0.0
         };
 
-        println!("--- {} ping statistics ---", hex::encode(&peer_id[..8]));
+        println!("--- {label} ping statistics ---");
         println!(
             "{} packets transmitted, {} received, {:.1}% packet loss",
             packets_sent, packets_received, packet_loss
@@ -1454,19 +2274,12 @@ async fn ping_peer(peer: String, count: u32, interval: u64, config: &Config) ->
             mdev * 1000.0
         );
     } else {
-        println!("--- {} ping statistics ---", hex::encode(&peer_id[..8]));
+        println!("--- {label} ping statistics ---");
         println!(
             "{} packets transmitted, 0 received, 100.0% packet loss",
             packets_sent
         );
     }
-
-    println!();
-
-    // Stop node
-    node.stop().await?;
-
-    Ok(())
 }
 
 /// Show configuration (all or specific key)
@@ -1492,12 +2305,18 @@ async fn config_show(key: Option<String>, config: &Config) -> anyhow::Result<()>
             "network.udp_fallback" | "udp_fallback" => {
                 println!("{}", config.network.udp_fallback);
             }
+            "network.port_mapping" | "port_mapping" => {
+                println!("{}", config.network.port_mapping);
+            }
             "obfuscation.default_level" | "default_level" => {
                 println!("{}", config.obfuscation.default_level);
             }
             "obfuscation.tls_mimicry" | "tls_mimicry" => {
                 println!("{}", config.obfuscation.tls_mimicry);
             }
+            "obfuscation.elligator_handshake" | "elligator_handshake" => {
+                println!("{}", config.obfuscation.elligator_handshake);
+            }
             "transfer.chunk_size" | "chunk_size" => {
                 println!("{}", config.transfer.chunk_size);
             }
@@ -1507,6 +2326,30 @@ async fn config_show(key: Option<String>, config: &Config) -> anyhow::Result<()>
             "transfer.enable_resume" | "enable_resume" => {
                 println!("{}", config.transfer.enable_resume);
             }
+            "discovery.mdns" | "mdns" => {
+                println!("{}", config.discovery.mdns);
+            }
+            "daemon.control_socket" | "control_socket" => {
+                println!("{}", config.daemon.control_socket.display());
+            }
+            "daemon.metrics_addr" | "metrics_addr" => {
+                println!("{}", config.daemon.metrics_addr.as_deref().unwrap_or(""));
+            }
+            "relay.bind_addrs" => {
+                println!("{}", config.relay.bind_addrs.join(","));
+            }
+            "relay.max_clients" => {
+                println!("{}", config.relay.max_clients);
+            }
+            "relay.rate_limit" => {
+                println!("{}", config.relay.rate_limit);
+            }
+            "relay.bandwidth_limit" => {
+                println!("{}", config.relay.bandwidth_limit);
+            }
+            "relay.announce" => {
+                println!("{}", config.relay.announce);
+            }
             _ => {
                 anyhow::bail!("Unknown configuration key: {}", key_name);
             }
@@ -1523,11 +2366,16 @@ async fn config_show(key: Option<String>, config: &Config) -> anyhow::Result<()>
             println!("  xdp_interface = \"{}\"", iface);
         }
         println!("  udp_fallback = {}", config.network.udp_fallback);
+        println!("  port_mapping = {}", config.network.port_mapping);
         println!();
 
         println!("[obfuscation]");
         println!("  default_level = \"{}\"", config.obfuscation.default_level);
         println!("  tls_mimicry = {}", config.obfuscation.tls_mimicry);
+        println!(
+            "  elligator_handshake = {}",
+            config.obfuscation.elligator_handshake
+        );
         println!();
 
         println!("[transfer]");
@@ -1545,6 +2393,32 @@ async fn config_show(key: Option<String>, config: &Config) -> anyhow::Result<()>
             "  relay_servers = {} configured",
             config.discovery.relay_servers.len()
         );
+        println!("  mdns = {}", config.discovery.mdns);
+        println!();
+
+        println!("[daemon]");
+        println!(
+            "  control_socket = \"{}\"",
+            config.daemon.control_socket.display()
+        );
+        println!("  metrics_addr = {:?}", config.daemon.metrics_addr);
+        println!();
+
+        println!("[relay]");
+        println!("  bind_addrs = {:?}", config.relay.bind_addrs);
+        println!("  max_clients = {}", config.relay.max_clients);
+        println!("  rate_limit = {}", config.relay.rate_limit);
+        println!("  bandwidth_limit = {}", config.relay.bandwidth_limit);
+        println!("  byte_quota = {:?}", config.relay.byte_quota);
+        println!(
+            "  client_timeout_secs = {}",
+            config.relay.client_timeout_secs
+        );
+        println!("  announce = {}", config.relay.announce);
+        println!(
+            "  stats_interval_secs = {}",
+            config.relay.stats_interval_secs
+        );
         println!();
 
         println!("[logging]");
@@ -1592,6 +2466,16 @@ async fn config_set(key: String, value: String, config_path: &str) -> anyhow::Re
                 anyhow::anyhow!("Invalid boolean value for udp_fallback: {}", value)
             })?;
         }
+        "network.port_mapping" | "port_mapping" => {
+            config.network.port_mapping = value.parse().map_err(|_| {
+                anyhow::anyhow!("Invalid boolean value for port_mapping: {}", value)
+            })?;
+        }
+        "discovery.mdns" | "mdns" => {
+            config.discovery.mdns = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid boolean value for mdns: {}", value))?;
+        }
         "obfuscation.default_level" | "default_level" => {
             config.obfuscation.default_level = value.clone();
         }
@@ -1600,6 +2484,11 @@ async fn config_set(key: String, value: String, config_path: &str) -> anyhow::Re
                 .parse()
                 .map_err(|_| anyhow::anyhow!("Invalid boolean value for tls_mimicry: {}", value))?;
         }
+        "obfuscation.elligator_handshake" | "elligator_handshake" => {
+            config.obfuscation.elligator_handshake = value.parse().map_err(|_| {
+                anyhow::anyhow!("Invalid boolean value for elligator_handshake: {}", value)
+            })?;
+        }
         "transfer.chunk_size" | "chunk_size" => {
             config.transfer.chunk_size = value
                 .parse()
@@ -1618,6 +2507,36 @@ async fn config_set(key: String, value: String, config_path: &str) -> anyhow::Re
         "logging.level" | "level" => {
             config.logging.level = value.clone();
         }
+        "daemon.control_socket" | "control_socket" => {
+            config.daemon.control_socket = PathBuf::from(&value);
+        }
+        "daemon.metrics_addr" | "metrics_addr" => {
+            // An empty value disables the endpoint
+            config.daemon.metrics_addr = Some(value.clone()).filter(|v| !v.is_empty());
+        }
+        "relay.bind_addrs" => {
+            config.relay.bind_addrs = value.split(',').map(|a| a.trim().to_string()).collect();
+        }
+        "relay.max_clients" => {
+            config.relay.max_clients = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid number for max_clients: {}", value))?;
+        }
+        "relay.rate_limit" => {
+            config.relay.rate_limit = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid number for rate_limit: {}", value))?;
+        }
+        "relay.bandwidth_limit" => {
+            config.relay.bandwidth_limit = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid number for bandwidth_limit: {}", value))?;
+        }
+        "relay.announce" => {
+            config.relay.announce = value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid boolean value for announce: {}", value))?;
+        }
         _ => {
             anyhow::bail!("Unknown configuration key: {}", key);
         }
@@ -2023,6 +2942,95 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_peer_target_forms() {
+        let id_hex = "ab".repeat(32);
+        assert_eq!(
+            parse_peer_target(&id_hex).unwrap(),
+            PeerTarget::Id([0xab; 32])
+        );
+        assert_eq!(
+            parse_peer_target("127.0.0.1:8420").unwrap(),
+            PeerTarget::Host("127.0.0.1:8420".to_string())
+        );
+        assert_eq!(
+            parse_peer_target("[::1]:8420").unwrap(),
+            PeerTarget::Host("[::1]:8420".to_string())
+        );
+        assert_eq!(
+            parse_peer_target(&format!("{id_hex}@peer.example.org:8420")).unwrap(),
+            PeerTarget::Pinned([0xab; 32], "peer.example.org:8420".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_peer_target_rejects_invalid() {
+        assert!(parse_peer_target("not-a-peer").is_err());
+        assert!(parse_peer_target("127.0.0.1:notaport").is_err());
+        assert!(parse_peer_target(":8420").is_err());
+        assert!(parse_peer_target("abcd@127.0.0.1:8420").is_err());
+        assert!(parse_peer_target(&format!("{}@127.0.0.1", "ab".repeat(32))).is_err());
+    }
+
+    #[test]
+    fn test_resolve_trusted_peer() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut known_peers = KnownPeers::load(temp_dir.path().join("known_peers")).unwrap();
+        known_peers.pin("127.0.0.1:8420", [7u8; 32]);
+
+        assert_eq!(
+            resolve_trusted_peer(&"cd".repeat(32), &known_peers).unwrap(),
+            [0xcd; 32]
+        );
+        assert_eq!(
+            resolve_trusted_peer("127.0.0.1:8420", &known_peers).unwrap(),
+            [7u8; 32]
+        );
+        assert!(resolve_trusted_peer("127.0.0.1:9999", &known_peers).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_peer_target_pins_on_first_use() {
+        let temp_dir = TempDir::new().unwrap();
+        let known_path = temp_dir.path().join("known_peers");
+
+        let server = Node::new_random_with_port(0).await.unwrap();
+        server.start().await.unwrap();
+        let host = format!("127.0.0.1:{}", server.listen_addr().await.unwrap().port());
+
+        // First contact trusts the presented key and records it
+        let client = Node::new_random_with_port(0).await.unwrap();
+        client.start().await.unwrap();
+        let mut known_peers = KnownPeers::load(&known_path).unwrap();
+        let peer_id =
+            connect_peer_target(&client, &PeerTarget::Host(host.clone()), &mut known_peers)
+                .await
+                .unwrap();
+        assert_eq!(&peer_id, server.x25519_public_key());
+        assert_eq!(
+            KnownPeers::load(&known_path).unwrap().lookup(&host),
+            Some(peer_id)
+        );
+        client.stop().await.unwrap();
+
+        // A different key pinned for the host is reported as a key change
+        let client = Node::new_random_with_port(0).await.unwrap();
+        client.start().await.unwrap();
+        let mut known_peers = KnownPeers::load(&known_path).unwrap();
+        known_peers.pin(&host, [9u8; 32]);
+        let err = connect_peer_target(&client, &PeerTarget::Host(host.clone()), &mut known_peers)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<KnownPeersError>(),
+            Some(KnownPeersError::KeyChanged { .. })
+        ));
+        assert!(client.active_sessions().await.is_empty());
+
+        client.stop().await.unwrap();
+        server.stop().await.unwrap();
+    }
+
     #[test]
     fn test_format_duration() {
         assert_eq!(format_duration(Duration::from_secs(30)), "30s");
@@ -2187,10 +3195,12 @@ mod tests {
             Commands::Send {
                 file,
                 recipient,
+                recursive,
                 mode,
             } => {
                 assert_eq!(file, "file.txt");
                 assert_eq!(recipient.len(), 1);
+                assert!(!recursive);
                 assert_eq!(mode, "privacy");
             }
             _ => panic!("Expected Send command"),
@@ -2218,6 +3228,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_send_recursive() {
+        let peer = "aa".repeat(32);
+        let cli = Cli::parse_from(["wraith", "send", "-r", "project/", &peer]);
+        match cli.command {
+            Commands::Send {
+                file, recursive, ..
+            } => {
+                assert_eq!(file, "project/");
+                assert!(recursive);
+            }
+            _ => panic!("Expected Send command"),
+        }
+    }
+
+    #[test]
+    fn test_directory_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("sub/b"), vec![0u8; 32]).unwrap();
+        assert_eq!(directory_size(dir.path()).unwrap(), 42);
+    }
+
     #[test]
     fn test_cli_parse_batch() {
         let peer = "cc".repeat(32);
@@ -2280,13 +3314,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_receive_acceptor_trusted_peers() {
+        let request = |peer_id: PeerId| IncomingTransfer {
+            peer_id,
+            metadata: wraith_core::node::FileMetadata {
+                transfer_id: [1u8; 32],
+                file_name: "file.bin".to_string(),
+                file_size: 1024,
+                chunk_size: 256,
+                total_chunks: 4,
+                root_hash: [2u8; 32],
+            },
+            proposed_path: PathBuf::from("/tmp/file.bin"),
+            manifest: None,
+        };
+
+        let acceptor = receive_acceptor(true, vec![[1u8; 32]]);
+        assert_eq!(
+            acceptor.decide(&request([1u8; 32])),
+            TransferDecision::Accept
+        );
+        assert!(matches!(
+            acceptor.decide(&request([2u8; 32])),
+            TransferDecision::Reject(_)
+        ));
+
+        let acceptor = receive_acceptor(true, Vec::new());
+        assert_eq!(
+            acceptor.decide(&request([2u8; 32])),
+            TransferDecision::Accept
+        );
+    }
+
     #[test]
     fn test_cli_parse_daemon_defaults() {
         let cli = Cli::parse_from(["wraith", "daemon"]);
         match cli.command {
-            Commands::Daemon { bind, relay } => {
+            Commands::Daemon {
+                bind,
+                relay,
+                metrics,
+            } => {
                 assert_eq!(bind, "0.0.0.0:0");
                 assert!(!relay);
+                assert!(metrics.is_none());
             }
             _ => panic!("Expected Daemon command"),
         }
@@ -2296,7 +3368,7 @@ mod tests {
     fn test_cli_parse_daemon_with_relay() {
         let cli = Cli::parse_from(["wraith", "daemon", "--bind", "0.0.0.0:8080", "--relay"]);
         match cli.command {
-            Commands::Daemon { bind, relay } => {
+            Commands::Daemon { bind, relay, .. } => {
                 assert_eq!(bind, "0.0.0.0:8080");
                 assert!(relay);
             }
@@ -2304,6 +3376,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_daemon_with_metrics() {
+        let cli = Cli::parse_from(["wraith", "daemon", "--metrics", "127.0.0.1:9090"]);
+        match cli.command {
+            Commands::Daemon { metrics, .. } => {
+                assert_eq!(metrics.as_deref(), Some("127.0.0.1:9090"));
+            }
+            _ => panic!("Expected Daemon command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_relay() {
+        let cli = Cli::parse_from(["wraith", "relay"]);
+        match cli.command {
+            Commands::Relay { bind } => assert!(bind.is_empty()),
+            _ => panic!("Expected Relay command"),
+        }
+
+        let cli = Cli::parse_from([
+            "wraith",
+            "relay",
+            "-b",
+            "0.0.0.0:3478",
+            "--bind",
+            "[::]:3478",
+        ]);
+        match cli.command {
+            Commands::Relay { bind } => assert_eq!(bind, vec!["0.0.0.0:3478", "[::]:3478"]),
+            _ => panic!("Expected Relay command"),
+        }
+    }
+
     #[test]
     fn test_cli_parse_status_defaults() {
         let cli = Cli::parse_from(["wraith", "status"]);
@@ -2537,6 +3642,14 @@ mod tests {
         assert_eq!(node_config.listen_addr, "127.0.0.1:9999".parse().unwrap());
     }
 
+    #[test]
+    fn test_create_node_config_elligator_handshake() {
+        let mut config = Config::default();
+        assert!(!create_node_config(&config).obfuscation.elligator_handshake);
+        config.obfuscation.elligator_handshake = true;
+        assert!(create_node_config(&config).obfuscation.elligator_handshake);
+    }
+
     #[test]
     fn test_create_node_config_invalid_addr_fallback() {
         let mut config = Config::default();
@@ -2732,6 +3845,33 @@ mod tests {
         assert!(!loaded.network.udp_fallback);
     }
 
+    #[tokio::test]
+    async fn test_config_set_port_mapping() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("cfg.toml");
+        let s = config_path.to_str().unwrap();
+
+        let result = config_set("network.port_mapping".to_string(), "false".to_string(), s).await;
+        assert!(result.is_ok());
+        let loaded = Config::load(&config_path).unwrap();
+        assert!(!loaded.network.port_mapping);
+        assert!(!create_node_config(&loaded).discovery.enable_port_mapping);
+    }
+
+    #[tokio::test]
+    async fn test_config_set_mdns() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("cfg.toml");
+        let s = config_path.to_str().unwrap();
+
+        assert!(create_node_config(&Config::default()).discovery.enable_mdns);
+        let result = config_set("discovery.mdns".to_string(), "false".to_string(), s).await;
+        assert!(result.is_ok());
+        let loaded = Config::load(&config_path).unwrap();
+        assert!(!loaded.discovery.mdns);
+        assert!(!create_node_config(&loaded).discovery.enable_mdns);
+    }
+
     #[tokio::test]
     async fn test_config_set_udp_fallback_invalid() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,296 @@
+//! Relay server mode.
+//!
+//! Runs a [`RelayServer`] on each configured bind address, for
+//! `wraith relay` and `wraith daemon --relay`. Relays are announced on the
+//! local network over mDNS, so clients find them with a `RelaySelector`
+//! browsing for relays, and report connection statistics periodically.
+
+use crate::config::RelayConfig;
+use crate::progress::format_bytes;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use wraith_discovery::relay::{RelayServer, RelayServerConfig, RelayStats};
+use wraith_discovery::{MdnsConfig, MdnsService};
+
+/// One relay server and its tasks
+struct RunningRelay {
+    server: Arc<RelayServer>,
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+    announcement: Option<MdnsService>,
+}
+
+/// Relay servers started from the `[relay]` configuration
+pub struct RelayService {
+    relays: Vec<RunningRelay>,
+    reporter: Option<JoinHandle<()>>,
+}
+
+/// Server limits for a relay configuration
+fn server_config(config: &RelayConfig) -> RelayServerConfig {
+    RelayServerConfig {
+        max_clients: config.max_clients,
+        rate_limit: config.rate_limit,
+        bandwidth_limit: config.bandwidth_limit,
+        byte_quota: config.byte_quota,
+        client_timeout: Duration::from_secs(config.client_timeout_secs),
+        ..RelayServerConfig::default()
+    }
+}
+
+impl RelayService {
+    /// Bind and start a relay on each address
+    ///
+    /// `bind_addrs` overrides `config.bind_addrs` when not empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an address is invalid or cannot be bound. An
+    /// mDNS announcement that cannot be set up only logs a warning.
+    pub async fn start(config: &RelayConfig, bind_addrs: &[String]) -> anyhow::Result<Self> {
+        let bind_addrs = if bind_addrs.is_empty() {
+            &config.bind_addrs
+        } else {
+            bind_addrs
+        };
+
+        let mut relays = Vec::new();
+        for bind_addr in bind_addrs {
+            let bind_addr: SocketAddr = bind_addr
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid relay bind address '{bind_addr}': {e}"))?;
+            let server = RelayServer::bind_with_config(bind_addr, server_config(config))
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to bind relay on {bind_addr}: {e}"))?;
+            let addr = server.local_addr()?;
+
+            let announcement = if config.announce {
+                match server.announce(MdnsConfig::default()).await {
+                    Ok(service) => Some(service),
+                    Err(e) => {
+                        tracing::warn!("Relay on {} not announced on the LAN: {}", addr, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            let server = Arc::new(server);
+            let running = Arc::clone(&server);
+            let task = tokio::spawn(async move {
+                if let Err(e) = running.run().await {
+                    tracing::error!("Relay on {} stopped: {}", addr, e);
+                }
+            });
+
+            relays.push(RunningRelay {
+                server,
+                addr,
+                task,
+                announcement,
+            });
+        }
+
+        let mut service = Self {
+            relays,
+            reporter: None,
+        };
+        if config.stats_interval_secs > 0 {
+            service.reporter =
+                Some(service.spawn_reporter(Duration::from_secs(config.stats_interval_secs)));
+        }
+        Ok(service)
+    }
+
+    /// Addresses the relays are serving on
+    pub fn addrs(&self) -> Vec<SocketAddr> {
+        self.relays.iter().map(|relay| relay.addr).collect()
+    }
+
+    /// Whether each relay is announced over mDNS
+    pub fn announced(&self) -> bool {
+        self.relays.iter().all(|relay| relay.announcement.is_some())
+    }
+
+    /// Current statistics of every relay
+    pub async fn stats(&self) -> Vec<(SocketAddr, RelayStats)> {
+        let mut stats = Vec::new();
+        for relay in &self.relays {
+            stats.push((relay.addr, relay.server.stats().await));
+        }
+        stats
+    }
+
+    /// Print statistics every `interval`
+    fn spawn_reporter(&self, interval: Duration) -> JoinHandle<()> {
+        let servers: Vec<_> = self
+            .relays
+            .iter()
+            .map(|relay| (relay.addr, Arc::clone(&relay.server)))
+            .collect();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                for (addr, server) in &servers {
+                    print_stats(*addr, &server.stats().await);
+                }
+            }
+        })
+    }
+
+    /// Withdraw the announcements and stop the relays
+    pub async fn shutdown(self) {
+        if let Some(reporter) = self.reporter {
+            reporter.abort();
+            let _ = reporter.await;
+        }
+        for relay in self.relays {
+            if let Some(announcement) = &relay.announcement {
+                announcement.shutdown().await;
+            }
+            relay.task.abort();
+            let _ = relay.task.await;
+        }
+    }
+}
+
+/// Print one relay's statistics
+pub fn print_stats(addr: SocketAddr, stats: &RelayStats) {
+    println!(
+        "Relay {}: {} clients ({} pending), {} packets / {} relayed, {} dropped ({} rate limited, {} over quota), {} auth failures",
+        addr,
+        stats.clients,
+        stats.pending_registrations,
+        stats.packets_relayed,
+        format_bytes(stats.bytes_relayed),
+        stats.packets_dropped,
+        stats.rate_limited,
+        stats.quota_exceeded,
+        stats.auth_failures
+    );
+}
+
+/// Run relay servers until Ctrl+C (`wraith relay`)
+pub async fn run(config: &RelayConfig, bind_addrs: Vec<String>) -> anyhow::Result<()> {
+    let service = RelayService::start(config, &bind_addrs).await?;
+
+    println!("WRAITH Relay");
+    println!("Version: {}", env!("CARGO_PKG_VERSION"));
+    println!();
+    for addr in service.addrs() {
+        println!("Serving on: {addr}");
+    }
+    println!(
+        "Limits: {} clients, {} packets/s and {}/s per client",
+        config.max_clients,
+        config.rate_limit,
+        format_bytes(config.bandwidth_limit)
+    );
+    if let Some(quota) = config.byte_quota {
+        println!("Byte quota: {} per client", format_bytes(quota));
+    }
+    println!("LAN announcement: {}", service.announced());
+    println!();
+    println!("Relay ready. Press Ctrl+C to stop");
+    println!();
+
+    tokio::signal::ctrl_c().await?;
+    println!("\nShutting down...");
+
+    let stats = service.stats().await;
+    service.shutdown().await;
+    for (addr, stats) in &stats {
+        print_stats(*addr, stats);
+    }
+    println!("Relay stopped");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wraith_crypto::signatures::SigningKey;
+    use wraith_discovery::relay::{RelayClient, RelayIdentity};
+
+    fn test_config() -> RelayConfig {
+        RelayConfig {
+            bind_addrs: vec!["127.0.0.1:0".to_string()],
+            announce: false,
+            stats_interval_secs: 0,
+            ..RelayConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_service_forwards_packets() {
+        let service = RelayService::start(&test_config(), &[]).await.unwrap();
+        let [relay_addr] = service.addrs()[..] else {
+            panic!("expected one relay");
+        };
+
+        let alice = RelayIdentity::new(SigningKey::from_bytes(&[1u8; 32]));
+        let bob = RelayIdentity::new(SigningKey::from_bytes(&[2u8; 32]));
+        let mut alice_client = RelayClient::connect(relay_addr, alice.public_key())
+            .await
+            .unwrap();
+        let mut bob_client = RelayClient::connect(relay_addr, bob.public_key())
+            .await
+            .unwrap();
+        alice_client.register(&alice).await.unwrap();
+        bob_client.register(&bob).await.unwrap();
+        bob_client.spawn_receiver();
+
+        alice_client
+            .send_to_peer(bob.public_key(), b"hello")
+            .await
+            .unwrap();
+        let (from, data) =
+            tokio::time::timeout(Duration::from_secs(5), bob_client.recv_from_peer())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(from, alice.public_key());
+        assert_eq!(data, b"hello");
+
+        let stats = service.stats().await;
+        assert_eq!(stats[0].1.clients, 2);
+        assert_eq!(stats[0].1.packets_relayed, 1);
+
+        service.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_bind_override_and_limits() {
+        let config = RelayConfig {
+            max_clients: 3,
+            byte_quota: Some(4096),
+            ..test_config()
+        };
+        let limits = server_config(&config);
+        assert_eq!(limits.max_clients, 3);
+        assert_eq!(limits.byte_quota, Some(4096));
+        assert_eq!(limits.client_timeout, Duration::from_secs(60));
+
+        let service = RelayService::start(
+            &config,
+            &["127.0.0.1:0".to_string(), "127.0.0.1:0".to_string()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(service.addrs().len(), 2);
+        service.shutdown().await;
+
+        assert!(
+            RelayService::start(&config, &["not-an-addr".to_string()])
+                .await
+                .is_err()
+        );
+    }
+}
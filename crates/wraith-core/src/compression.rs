@@ -0,0 +1,129 @@
+//! Per-chunk payload compression.
+//!
+//! File chunks are compressed with zstd before encryption when both peers
+//! negotiated [`Capabilities::COMPRESSION`] during the handshake. A chunk is
+//! only sent compressed if that makes it smaller; the frame then carries the
+//! `CMP` flag and the receiver restores the original bytes before checking
+//! them against the transfer's tree hash, which always covers the
+//! uncompressed file.
+//!
+//! Files whose contents are already compressed (archives, media) are sent
+//! as-is without spending CPU on them.
+//!
+//! [`Capabilities::COMPRESSION`]: crate::node::session::Capabilities::COMPRESSION
+
+use crate::error::CompressionError;
+use std::path::Path;
+
+/// Default zstd compression level (fast, with most of the ratio)
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// File extensions whose contents are already compressed
+pub const PRECOMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "avi", "br", "bz2", "flac", "gif", "gz", "heic", "jpeg", "jpg", "lz4", "lzma",
+    "m4a", "mkv", "mov", "mp3", "mp4", "ogg", "opus", "png", "rar", "tgz", "webm", "webp", "xz",
+    "zip", "zst",
+];
+
+/// Compress a chunk at the given zstd `level`
+///
+/// Returns `None` when the compressed form is not smaller than `data`, in
+/// which case the chunk should be sent uncompressed.
+#[must_use]
+pub fn compress_chunk(data: &[u8], level: i32) -> Option<Vec<u8>> {
+    zstd::bulk::compress(data, level)
+        .ok()
+        .filter(|compressed| compressed.len() < data.len())
+}
+
+/// Decompress a chunk that must expand to at most `max_len` bytes
+///
+/// The bound keeps a hostile peer from inflating a small frame into an
+/// arbitrarily large allocation.
+///
+/// # Errors
+///
+/// Returns [`CompressionError::TooLarge`] if the payload expands beyond
+/// `max_len` and [`CompressionError::Corrupt`] if it cannot be decoded.
+pub fn decompress_chunk(data: &[u8], max_len: usize) -> Result<Vec<u8>, CompressionError> {
+    if let Ok(Some(size)) = zstd::zstd_safe::get_frame_content_size(data)
+        && size > max_len as u64
+    {
+        return Err(CompressionError::TooLarge { max: max_len });
+    }
+    zstd::bulk::decompress(data, max_len).map_err(|e| CompressionError::Corrupt(e.to_string()))
+}
+
+/// Whether `path` names a file that is not worth compressing
+///
+/// Extensions are compared case-insensitively against `skip_extensions`.
+#[must_use]
+pub fn is_precompressed<S: AsRef<str>>(path: &Path, skip_extensions: &[S]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            skip_extensions
+                .iter()
+                .any(|skip| skip.as_ref().eq_ignore_ascii_case(ext))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let data = b"wraith ".repeat(512);
+        let compressed = compress_chunk(&data, DEFAULT_COMPRESSION_LEVEL).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress_chunk(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn test_compress_skips_incompressible() {
+        let mut data = vec![0u8; 4096];
+        getrandom::getrandom(&mut data).unwrap();
+        assert!(compress_chunk(&data, DEFAULT_COMPRESSION_LEVEL).is_none());
+        assert!(compress_chunk(&[], DEFAULT_COMPRESSION_LEVEL).is_none());
+    }
+
+    #[test]
+    fn test_decompress_enforces_bound() {
+        let data = vec![0u8; 64 * 1024];
+        let compressed = compress_chunk(&data, DEFAULT_COMPRESSION_LEVEL).unwrap();
+        assert!(matches!(
+            decompress_chunk(&compressed, 1024),
+            Err(CompressionError::TooLarge { max: 1024 })
+        ));
+    }
+
+    #[test]
+    fn test_decompress_rejects_garbage() {
+        assert!(decompress_chunk(&[0xDE, 0xAD, 0xBE, 0xEF], 1024).is_err());
+    }
+
+    #[test]
+    fn test_precompressed_extensions() {
+        assert!(is_precompressed(
+            Path::new("holiday.JPG"),
+            PRECOMPRESSED_EXTENSIONS
+        ));
+        assert!(is_precompressed(
+            Path::new("dir/archive.tar.gz"),
+            PRECOMPRESSED_EXTENSIONS
+        ));
+        assert!(!is_precompressed(
+            Path::new("notes.txt"),
+            PRECOMPRESSED_EXTENSIONS
+        ));
+        assert!(!is_precompressed(
+            Path::new("Makefile"),
+            PRECOMPRESSED_EXTENSIONS
+        ));
+        assert!(is_precompressed(
+            Path::new("a.custom"),
+            &["custom".to_string()]
+        ));
+    }
+}
@@ -25,6 +25,11 @@ const PROBE_RTT_INTERVAL: Duration = Duration::from_secs(10);
 /// Minimum inflight during `ProbeRtt` (4 packets worth)
 const PROBE_RTT_MIN_INFLIGHT: u64 = 4 * 1_500;
 
+/// Packets sent per loss estimation window.
+/// Once this many packets have been counted, sent and lost counts are
+/// halved so the estimate follows changing path conditions.
+const LOSS_WINDOW_PACKETS: u64 = 1024;
+
 /// Fixed-point pacing gains for `ProbeBw` cycle (8 fractional bits = multiply by 256).
 ///
 /// Values represent: [1.25, 0.75, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]
@@ -100,6 +105,10 @@ pub struct BbrState {
     next_send_time: Instant,
     /// Pacing rate in bytes per second
     pacing_rate_bps: u64,
+    /// Packets sent in the current loss window
+    loss_window_sent: u64,
+    /// Packets lost in the current loss window
+    loss_window_lost: u64,
 }
 
 /// `BBR` algorithm phases
@@ -143,6 +152,8 @@ impl BbrState {
             prior_btl_bw: 0,
             next_send_time: now,
             pacing_rate_bps: 100_000_000 / 8, // Initial 100 Mbps
+            loss_window_sent: 0,
+            loss_window_lost: 0,
         }
     }
 
@@ -270,6 +281,11 @@ impl BbrState {
     /// Called when a packet is sent
     pub fn on_packet_sent(&mut self, bytes: u64) {
         self.bytes_in_flight += bytes;
+        self.loss_window_sent += 1;
+        if self.loss_window_sent >= LOSS_WINDOW_PACKETS {
+            self.loss_window_sent /= 2;
+            self.loss_window_lost /= 2;
+        }
     }
 
     /// Called when a packet is acknowledged
@@ -295,6 +311,7 @@ impl BbrState {
     /// Called when a packet is lost
     pub fn on_packet_lost(&mut self, bytes: u64) {
         self.bytes_in_flight = self.bytes_in_flight.saturating_sub(bytes);
+        self.loss_window_lost += 1;
     }
 
     /// Update `BBR` state machine
@@ -453,6 +470,19 @@ impl BbrState {
         self.min_rtt
     }
 
+    /// Get the estimated packet loss rate (0.0 to 1.0)
+    ///
+    /// Ratio of lost to sent packets over a decaying window of roughly
+    /// the last [`LOSS_WINDOW_PACKETS`] packets.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn loss_rate(&self) -> f64 {
+        if self.loss_window_sent == 0 {
+            return 0.0;
+        }
+        (self.loss_window_lost as f64 / self.loss_window_sent as f64).min(1.0)
+    }
+
     /// Check if connection is bandwidth-limited
     ///
     /// Returns `true` if we're in `ProbeBw` phase and not application-limited.
@@ -1410,4 +1440,34 @@ mod tests {
         // New API should include old behavior (tracking inflight)
         assert_eq!(inflight_new, inflight_old + 1500);
     }
+
+    #[test]
+    fn test_loss_rate_estimate() {
+        let mut bbr = BbrState::new();
+        assert_eq!(bbr.loss_rate(), 0.0);
+
+        for _ in 0..100 {
+            bbr.on_packet_sent(1500);
+        }
+        for _ in 0..5 {
+            bbr.on_packet_lost(1500);
+        }
+        assert!((bbr.loss_rate() - 0.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_loss_rate_decays() {
+        let mut bbr = BbrState::new();
+        for _ in 0..100 {
+            bbr.on_packet_sent(1500);
+            bbr.on_packet_lost(1500);
+        }
+        assert_eq!(bbr.loss_rate(), 1.0);
+
+        // A long loss-free run pulls the estimate back down
+        for _ in 0..(4 * LOSS_WINDOW_PACKETS) {
+            bbr.on_packet_sent(1500);
+        }
+        assert!(bbr.loss_rate() < 0.01);
+    }
 }
@@ -16,6 +16,14 @@ pub enum Error {
     /// Cryptographic error
     #[error("crypto error: {0}")]
     Crypto(#[from] wraith_crypto::CryptoError),
+
+    /// Forward error correction error
+    #[error("FEC error: {0}")]
+    Fec(#[from] FecError),
+
+    /// Payload compression error
+    #[error("compression error: {0}")]
+    Compression(#[from] CompressionError),
 }
 
 /// Frame-level errors
@@ -80,6 +88,60 @@ pub enum FrameError {
     },
 }
 
+/// Forward error correction errors
+#[derive(Debug, Error)]
+pub enum FecError {
+    /// Group has no source symbols or exceeds the code's symbol limit
+    #[error("invalid FEC group: {source_count} source and {repair_count} repair symbols")]
+    InvalidGroup {
+        /// Number of source symbols
+        source_count: usize,
+        /// Number of repair symbols
+        repair_count: usize,
+    },
+
+    /// Source payload too large to protect in a single repair frame
+    #[error("FEC source payload too large: {size} bytes (max {max})")]
+    SourceTooLarge {
+        /// Payload size
+        size: usize,
+        /// Maximum protectable payload size
+        max: usize,
+    },
+
+    /// Source sequence does not follow the previous source in the group
+    #[error("FEC source sequence {got} does not follow {expected}")]
+    NonContiguous {
+        /// Next sequence the group expected
+        expected: u64,
+        /// Sequence that was pushed
+        got: u64,
+    },
+
+    /// Repair frame or recovered symbol is malformed
+    #[error("malformed FEC symbol")]
+    Malformed,
+
+    /// Reed-Solomon encoding or reconstruction failed
+    #[error("erasure coding failed: {0}")]
+    Codec(String),
+}
+
+/// Payload compression errors
+#[derive(Debug, Error)]
+pub enum CompressionError {
+    /// Compressed payload decodes to more than the allowed size
+    #[error("decompressed payload exceeds {max} bytes")]
+    TooLarge {
+        /// Maximum allowed decompressed size
+        max: usize,
+    },
+
+    /// Compressed payload is corrupt or uses an unknown codec
+    #[error("corrupt compressed payload: {0}")]
+    Corrupt(String),
+}
+
 /// Session-level errors
 #[derive(Debug, Error)]
 pub enum SessionError {
@@ -0,0 +1,802 @@
+//! Forward error correction over groups of data frames.
+//!
+//! Consecutive `Data` frames on a stream are grouped into `k` source
+//! symbols. After each group the sender emits `r` [`FrameTypeV2::FecRepair`]
+//! frames carrying Reed-Solomon parity over the group. A receiver holding
+//! any `k` of the `k + r` symbols rebuilds the missing frames locally
+//! instead of waiting a round trip for a retransmission.
+//!
+//! Source frames are ordinary v2 `Data` frames marked with [`FlagsV2::FEC`]
+//! and numbered consecutively. A repair frame names its group through the
+//! frame header: the stream ID is the protected stream and the sequence is
+//! the first source sequence in the group. The repair payload is:
+//!
+//! ```text
+//!  Offset  Size  Field
+//!  0       1     Source count (k)
+//!  1       1     Repair count (r)
+//!  2       1     Repair index (0..r)
+//!  3       1     Reserved (zero)
+//!  4       N     Parity symbol
+//! ```
+//!
+//! Each source symbol is the source frame's offset (8 bytes LE), payload
+//! length (2 bytes LE) and payload, zero-padded to the largest symbol in
+//! the group. The top bit of the length marks a compressed payload so
+//! rebuilt frames keep their `CMP` flag.
+
+use crate::error::{FecError, FrameError};
+use crate::frame::MAX_PAYLOAD_SIZE;
+use crate::frame::frame_v2::{FrameBuilderV2, FrameV2};
+use crate::frame::types_v2::{FRAME_HEADER_V2_SIZE, FlagsV2, FrameTypeV2};
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Size of the repair header preceding the parity symbol
+pub const FEC_REPAIR_HEADER_SIZE: usize = 4;
+
+/// Size of the offset and length fields at the start of a source symbol
+const SOURCE_SYMBOL_HEADER_SIZE: usize = 10;
+
+/// Largest source payload whose repair symbol still fits in one frame
+pub const MAX_FEC_SOURCE_PAYLOAD: usize =
+    MAX_PAYLOAD_SIZE - FEC_REPAIR_HEADER_SIZE - SOURCE_SYMBOL_HEADER_SIZE;
+
+/// GF(2^8) Reed-Solomon limit on source plus repair symbols per group
+pub const MAX_FEC_SYMBOLS: usize = 256;
+
+/// Length field bit marking a compressed source payload
+const COMPRESSED_SYMBOL_BIT: u16 = 0x8000;
+
+/// Source symbols a decoder buffers per peer while waiting for repairs
+const DEFAULT_DECODER_WINDOW: usize = 256;
+
+/// Groups a decoder tracks per peer (pending and recently completed)
+const MAX_TRACKED_GROUPS: usize = 64;
+
+/// Number of repair symbols to send for a group of `source_count` frames
+///
+/// Covers twice the losses expected at `loss_rate` so a group survives
+/// loss bursts around the mean, bounded by `min_repair` and `max_repair`.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::cast_possible_truncation)]
+#[allow(clippy::cast_sign_loss)]
+pub fn repair_count_for_loss(
+    source_count: usize,
+    loss_rate: f64,
+    min_repair: usize,
+    max_repair: usize,
+) -> usize {
+    let expected_losses = (source_count as f64 * loss_rate.clamp(0.0, 1.0) * 2.0).ceil() as usize;
+    expected_losses
+        .max(min_repair)
+        .min(max_repair)
+        .min(MAX_FEC_SYMBOLS.saturating_sub(source_count))
+}
+
+/// The parts of an FEC-protected `Data` frame needed to rebuild it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FecSource {
+    /// Frame sequence number
+    pub sequence: u64,
+    /// File offset carried by the frame
+    pub offset: u64,
+    /// Frame payload
+    pub payload: Vec<u8>,
+    /// Whether the payload is compressed (the frame's `CMP` flag)
+    pub compressed: bool,
+}
+
+impl FecSource {
+    /// Capture the protected parts of a parsed source frame
+    #[must_use]
+    pub fn from_frame(frame: &FrameV2<'_>) -> Self {
+        Self {
+            sequence: frame.sequence(),
+            offset: frame.offset(),
+            payload: frame.payload().to_vec(),
+            compressed: frame.flags().is_compressed(),
+        }
+    }
+
+    /// Build the v2 `Data` frame for this source on `stream_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FrameError`] if the frame cannot be encoded.
+    pub fn build_frame(&self, stream_id: u32) -> Result<Vec<u8>, FrameError> {
+        let mut flags = FlagsV2::empty().with(FlagsV2::FEC);
+        if self.compressed {
+            flags = flags.with(FlagsV2::CMP);
+        }
+        let builder = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Data)
+            .flags(flags)
+            .stream_id(stream_id)
+            .sequence(self.sequence)
+            .offset(self.offset)
+            .payload(&self.payload);
+        let size = builder.min_size();
+        builder.build(size)
+    }
+
+    fn symbol_len(&self) -> usize {
+        SOURCE_SYMBOL_HEADER_SIZE + self.payload.len()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_symbol(&self, symbol_len: usize) -> Vec<u8> {
+        let mut symbol = Vec::with_capacity(symbol_len);
+        symbol.extend_from_slice(&self.offset.to_le_bytes());
+        // Payload length is bounded by MAX_FEC_SOURCE_PAYLOAD when pushed
+        let mut len = self.payload.len() as u16;
+        if self.compressed {
+            len |= COMPRESSED_SYMBOL_BIT;
+        }
+        symbol.extend_from_slice(&len.to_le_bytes());
+        symbol.extend_from_slice(&self.payload);
+        symbol.resize(symbol_len, 0);
+        symbol
+    }
+
+    fn from_symbol(sequence: u64, symbol: &[u8]) -> Result<Self, FecError> {
+        if symbol.len() < SOURCE_SYMBOL_HEADER_SIZE {
+            return Err(FecError::Malformed);
+        }
+        let offset = u64::from_le_bytes(symbol[..8].try_into().map_err(|_| FecError::Malformed)?);
+        let len_field = u16::from_le_bytes([symbol[8], symbol[9]]);
+        let len = usize::from(len_field & !COMPRESSED_SYMBOL_BIT);
+        let payload = symbol[SOURCE_SYMBOL_HEADER_SIZE..]
+            .get(..len)
+            .ok_or(FecError::Malformed)?;
+        Ok(Self {
+            sequence,
+            offset,
+            payload: payload.to_vec(),
+            compressed: len_field & COMPRESSED_SYMBOL_BIT != 0,
+        })
+    }
+}
+
+/// One repair symbol of a group, carried in a `FecRepair` frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FecRepair {
+    /// Sequence of the first source frame in the group
+    pub first_sequence: u64,
+    /// Number of source symbols in the group
+    pub source_count: u8,
+    /// Number of repair symbols in the group
+    pub repair_count: u8,
+    /// Index of this repair symbol within the group
+    pub index: u8,
+    /// Parity symbol
+    pub symbol: Vec<u8>,
+}
+
+impl FecRepair {
+    /// Parse a repair symbol from a `FecRepair` frame
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FecError::Malformed`] if the frame is not a repair frame or
+    /// describes an impossible group.
+    pub fn from_frame(frame: &FrameV2<'_>) -> Result<Self, FecError> {
+        let payload = frame.payload();
+        if frame.frame_type() != FrameTypeV2::FecRepair
+            || payload.len() < FEC_REPAIR_HEADER_SIZE + SOURCE_SYMBOL_HEADER_SIZE
+        {
+            return Err(FecError::Malformed);
+        }
+
+        let (source_count, repair_count, index) = (payload[0], payload[1], payload[2]);
+        if source_count == 0
+            || repair_count == 0
+            || index >= repair_count
+            || usize::from(source_count) + usize::from(repair_count) > MAX_FEC_SYMBOLS
+        {
+            return Err(FecError::Malformed);
+        }
+
+        Ok(Self {
+            first_sequence: frame.sequence(),
+            source_count,
+            repair_count,
+            index,
+            symbol: payload[FEC_REPAIR_HEADER_SIZE..].to_vec(),
+        })
+    }
+
+    /// Build the `FecRepair` frame for this symbol on `stream_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FrameError`] if the frame cannot be encoded.
+    pub fn build_frame(&self, stream_id: u32) -> Result<Vec<u8>, FrameError> {
+        let mut payload = Vec::with_capacity(FEC_REPAIR_HEADER_SIZE + self.symbol.len());
+        payload.extend_from_slice(&[self.source_count, self.repair_count, self.index, 0]);
+        payload.extend_from_slice(&self.symbol);
+
+        FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::FecRepair)
+            .stream_id(stream_id)
+            .sequence(self.first_sequence)
+            .payload(&payload)
+            .build(FRAME_HEADER_V2_SIZE + payload.len())
+    }
+}
+
+/// Groups consecutive source frames on one stream and produces repairs
+#[derive(Debug)]
+pub struct FecEncoder {
+    group_size: usize,
+    sources: Vec<FecSource>,
+}
+
+impl FecEncoder {
+    /// Create an encoder emitting a group every `group_size` source frames
+    ///
+    /// `group_size` is clamped so at least one repair symbol fits the code.
+    #[must_use]
+    pub fn new(group_size: usize) -> Self {
+        let group_size = group_size.clamp(1, MAX_FEC_SYMBOLS - 1);
+        Self {
+            group_size,
+            sources: Vec::with_capacity(group_size),
+        }
+    }
+
+    /// Number of source frames per group
+    #[must_use]
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    /// Number of source frames in the pending group
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Add a source frame to the pending group
+    ///
+    /// Returns `true` once the group is full and should be finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FecError::SourceTooLarge`] if the payload cannot be protected
+    /// and [`FecError::NonContiguous`] if the sequence does not follow the
+    /// previous source in the group.
+    pub fn push(&mut self, source: FecSource) -> Result<bool, FecError> {
+        if source.payload.len() > MAX_FEC_SOURCE_PAYLOAD {
+            return Err(FecError::SourceTooLarge {
+                size: source.payload.len(),
+                max: MAX_FEC_SOURCE_PAYLOAD,
+            });
+        }
+        if let Some(last) = self.sources.last() {
+            let expected = last.sequence.wrapping_add(1);
+            if source.sequence != expected {
+                return Err(FecError::NonContiguous {
+                    expected,
+                    got: source.sequence,
+                });
+            }
+        }
+
+        self.sources.push(source);
+        Ok(self.sources.len() >= self.group_size)
+    }
+
+    /// Compute `repair_count` repair symbols for the pending group
+    ///
+    /// The pending group is cleared either way; a repair count of zero
+    /// leaves the group unprotected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FecError::InvalidGroup`] if the group exceeds the code's
+    /// symbol limit.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn finish_group(&mut self, repair_count: usize) -> Result<Vec<FecRepair>, FecError> {
+        let sources = std::mem::take(&mut self.sources);
+        if sources.is_empty() || repair_count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let source_count = sources.len();
+        if source_count + repair_count > MAX_FEC_SYMBOLS {
+            return Err(FecError::InvalidGroup {
+                source_count,
+                repair_count,
+            });
+        }
+
+        let symbol_len = sources
+            .iter()
+            .map(FecSource::symbol_len)
+            .max()
+            .unwrap_or(SOURCE_SYMBOL_HEADER_SIZE);
+        let mut shards: Vec<Vec<u8>> = sources.iter().map(|s| s.to_symbol(symbol_len)).collect();
+        shards.resize(source_count + repair_count, vec![0u8; symbol_len]);
+
+        let codec = ReedSolomon::new(source_count, repair_count)
+            .map_err(|e| FecError::Codec(format!("{e:?}")))?;
+        codec
+            .encode(&mut shards)
+            .map_err(|e| FecError::Codec(format!("{e:?}")))?;
+
+        // Both counts are below MAX_FEC_SYMBOLS and fit in a byte
+        let first_sequence = sources[0].sequence;
+        Ok(shards
+            .into_iter()
+            .skip(source_count)
+            .enumerate()
+            .map(|(index, symbol)| FecRepair {
+                first_sequence,
+                source_count: source_count as u8,
+                repair_count: repair_count as u8,
+                index: index as u8,
+                symbol,
+            })
+            .collect())
+    }
+}
+
+/// Repair state of one group seen by a decoder
+#[derive(Debug)]
+struct DecoderGroup {
+    source_count: u8,
+    repair_count: u8,
+    repairs: Vec<Option<Vec<u8>>>,
+    /// Sequences rebuilt from repairs
+    recovered: HashSet<u64>,
+    /// All sources are present or rebuilt
+    complete: bool,
+}
+
+impl DecoderGroup {
+    fn covers(&self, first_sequence: u64, sequence: u64) -> bool {
+        sequence
+            .checked_sub(first_sequence)
+            .is_some_and(|i| i < u64::from(self.source_count))
+    }
+}
+
+/// Rebuilds lost source frames from repair frames
+///
+/// Holds a bounded window of recent source frames per stream so that a
+/// repair arriving after its group can fill the gaps.
+#[derive(Debug)]
+pub struct FecDecoder {
+    window: usize,
+    sources: HashMap<(u32, u64), FecSource>,
+    source_order: VecDeque<(u32, u64)>,
+    groups: HashMap<(u32, u64), DecoderGroup>,
+    group_order: VecDeque<(u32, u64)>,
+}
+
+impl Default for FecDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_DECODER_WINDOW)
+    }
+}
+
+impl FecDecoder {
+    /// Create a decoder buffering up to `window` source frames
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            sources: HashMap::new(),
+            source_order: VecDeque::new(),
+            groups: HashMap::new(),
+            group_order: VecDeque::new(),
+        }
+    }
+
+    /// Number of source frames currently buffered
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Check whether a source frame was already rebuilt from repairs
+    ///
+    /// A late original of a rebuilt frame is a duplicate and can be dropped.
+    #[must_use]
+    pub fn was_recovered(&self, stream_id: u32, sequence: u64) -> bool {
+        self.find_group(stream_id, sequence)
+            .and_then(|key| self.groups.get(&key))
+            .is_some_and(|group| group.recovered.contains(&sequence))
+    }
+
+    /// Record a received source frame
+    ///
+    /// Returns any frames of the source's group that can now be rebuilt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the group's repair symbols are inconsistent.
+    pub fn on_source(
+        &mut self,
+        stream_id: u32,
+        source: FecSource,
+    ) -> Result<Vec<FecSource>, FecError> {
+        let group_key = self.find_group(stream_id, source.sequence);
+        if group_key.is_some_and(|key| self.groups[&key].complete) {
+            return Ok(Vec::new());
+        }
+
+        let key = (stream_id, source.sequence);
+        if self.sources.insert(key, source).is_none() {
+            self.source_order.push_back(key);
+            while self.source_order.len() > self.window {
+                if let Some(old) = self.source_order.pop_front() {
+                    self.sources.remove(&old);
+                }
+            }
+        }
+
+        match group_key {
+            Some(group_key) => self.try_recover(group_key),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Record a received repair symbol
+    ///
+    /// Returns the frames of the repair's group that can now be rebuilt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repair disagrees with earlier repairs for the
+    /// same group or reconstruction fails.
+    pub fn on_repair(
+        &mut self,
+        stream_id: u32,
+        repair: FecRepair,
+    ) -> Result<Vec<FecSource>, FecError> {
+        let key = (stream_id, repair.first_sequence);
+        if !self.groups.contains_key(&key) {
+            self.group_order.push_back(key);
+            while self.group_order.len() > MAX_TRACKED_GROUPS {
+                if let Some(old) = self.group_order.pop_front() {
+                    self.groups.remove(&old);
+                }
+            }
+        }
+
+        let group = self.groups.entry(key).or_insert_with(|| DecoderGroup {
+            source_count: repair.source_count,
+            repair_count: repair.repair_count,
+            repairs: vec![None; usize::from(repair.repair_count)],
+            recovered: HashSet::new(),
+            complete: false,
+        });
+        if group.complete {
+            return Ok(Vec::new());
+        }
+        if group.source_count != repair.source_count || group.repair_count != repair.repair_count {
+            return Err(FecError::Malformed);
+        }
+        let symbol_len = group.repairs.iter().flatten().next().map(Vec::len);
+        if symbol_len.is_some_and(|len| len != repair.symbol.len()) {
+            return Err(FecError::Malformed);
+        }
+        group.repairs[usize::from(repair.index)] = Some(repair.symbol);
+
+        self.try_recover(key)
+    }
+
+    fn find_group(&self, stream_id: u32, sequence: u64) -> Option<(u32, u64)> {
+        self.groups
+            .iter()
+            .find(|((stream, first), group)| *stream == stream_id && group.covers(*first, sequence))
+            .map(|(key, _)| *key)
+    }
+
+    fn try_recover(&mut self, key: (u32, u64)) -> Result<Vec<FecSource>, FecError> {
+        let (stream_id, first_sequence) = key;
+        let Some(group) = self.groups.get_mut(&key) else {
+            return Ok(Vec::new());
+        };
+        let source_count = usize::from(group.source_count);
+        let sequences: Vec<u64> = (0..source_count as u64)
+            .map(|i| first_sequence.wrapping_add(i))
+            .collect();
+
+        let present = sequences
+            .iter()
+            .filter(|seq| self.sources.contains_key(&(stream_id, **seq)))
+            .count();
+        if present == source_count {
+            group.complete = true;
+            self.release_sources(stream_id, &sequences);
+            return Ok(Vec::new());
+        }
+        let repairs = group.repairs.iter().flatten().count();
+        if present + repairs < source_count {
+            return Ok(Vec::new());
+        }
+
+        let symbol_len = group
+            .repairs
+            .iter()
+            .flatten()
+            .map(Vec::len)
+            .next()
+            .unwrap_or(0);
+        let mut shards: Vec<Option<Vec<u8>>> =
+            Vec::with_capacity(source_count + group.repairs.len());
+        for seq in &sequences {
+            let shard = match self.sources.get(&(stream_id, *seq)) {
+                Some(source) if source.symbol_len() > symbol_len => {
+                    // A source larger than the repair symbols cannot belong to this group
+                    group.complete = true;
+                    return Err(FecError::Malformed);
+                }
+                Some(source) => Some(source.to_symbol(symbol_len)),
+                None => None,
+            };
+            shards.push(shard);
+        }
+        shards.extend(group.repairs.iter().cloned());
+
+        let codec = ReedSolomon::new(source_count, group.repairs.len())
+            .map_err(|e| FecError::Codec(format!("{e:?}")))?;
+        group.complete = true;
+        codec
+            .reconstruct_data(&mut shards)
+            .map_err(|e| FecError::Codec(format!("{e:?}")))?;
+
+        let mut recovered = Vec::new();
+        for (seq, shard) in sequences.iter().zip(&shards) {
+            if self.sources.contains_key(&(stream_id, *seq)) {
+                continue;
+            }
+            let symbol = shard.as_deref().ok_or(FecError::Malformed)?;
+            recovered.push(FecSource::from_symbol(*seq, symbol)?);
+            group.recovered.insert(*seq);
+        }
+
+        self.release_sources(stream_id, &sequences);
+        Ok(recovered)
+    }
+
+    fn release_sources(&mut self, stream_id: u32, sequences: &[u64]) {
+        for seq in sequences {
+            self.sources.remove(&(stream_id, *seq));
+        }
+        self.source_order
+            .retain(|key| key.0 != stream_id || !sequences.contains(&key.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(first: u64, count: usize) -> Vec<FecSource> {
+        (0..count as u64)
+            .map(|i| FecSource {
+                sequence: first + i,
+                offset: (first + i) * 1000,
+                // Vary lengths so padding is exercised
+                payload: vec![(first + i) as u8; 100 + (i as usize * 37) % 200],
+                compressed: i % 3 == 0,
+            })
+            .collect()
+    }
+
+    fn encode(group: &[FecSource], repair_count: usize) -> Vec<FecRepair> {
+        let mut encoder = FecEncoder::new(group.len());
+        for source in group {
+            encoder.push(source.clone()).unwrap();
+        }
+        encoder.finish_group(repair_count).unwrap()
+    }
+
+    #[test]
+    fn test_repair_count_adapts_to_loss() {
+        assert_eq!(repair_count_for_loss(16, 0.0, 1, 8), 1);
+        assert_eq!(repair_count_for_loss(16, 0.0, 0, 8), 0);
+        assert_eq!(repair_count_for_loss(16, 0.05, 1, 8), 2);
+        assert_eq!(repair_count_for_loss(16, 0.2, 1, 8), 7);
+        assert_eq!(repair_count_for_loss(16, 0.9, 1, 8), 8);
+        assert_eq!(repair_count_for_loss(255, 0.5, 1, 8), 1);
+    }
+
+    #[test]
+    fn test_encoder_signals_full_group() {
+        let mut encoder = FecEncoder::new(3);
+        let group = sources(10, 3);
+        assert!(!encoder.push(group[0].clone()).unwrap());
+        assert!(!encoder.push(group[1].clone()).unwrap());
+        assert!(encoder.push(group[2].clone()).unwrap());
+
+        let repairs = encoder.finish_group(2).unwrap();
+        assert_eq!(repairs.len(), 2);
+        assert_eq!(encoder.pending(), 0);
+        assert!(
+            repairs
+                .iter()
+                .all(|r| r.first_sequence == 10 && r.source_count == 3 && r.repair_count == 2)
+        );
+    }
+
+    #[test]
+    fn test_encoder_rejects_gaps_and_oversized() {
+        let mut encoder = FecEncoder::new(4);
+        let group = sources(0, 3);
+        encoder.push(group[0].clone()).unwrap();
+        assert!(matches!(
+            encoder.push(group[2].clone()),
+            Err(FecError::NonContiguous {
+                expected: 1,
+                got: 2
+            })
+        ));
+
+        let oversized = FecSource {
+            sequence: 1,
+            offset: 0,
+            payload: vec![0; MAX_FEC_SOURCE_PAYLOAD + 1],
+            compressed: false,
+        };
+        assert!(matches!(
+            encoder.push(oversized),
+            Err(FecError::SourceTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_recover_lost_sources() {
+        let group = sources(100, 8);
+        let repairs = encode(&group, 3);
+
+        let mut decoder = FecDecoder::default();
+        // Lose sources 1, 4 and 6
+        for (i, source) in group.iter().enumerate() {
+            if ![1, 4, 6].contains(&i) {
+                assert!(decoder.on_source(16, source.clone()).unwrap().is_empty());
+            }
+        }
+
+        let mut recovered = Vec::new();
+        for repair in repairs {
+            recovered.extend(decoder.on_repair(16, repair).unwrap());
+        }
+        recovered.sort_by_key(|s| s.sequence);
+        assert_eq!(
+            recovered,
+            vec![group[1].clone(), group[4].clone(), group[6].clone()]
+        );
+        assert!(decoder.was_recovered(16, 104));
+        assert!(!decoder.was_recovered(16, 105));
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_recover_when_repair_arrives_before_sources() {
+        let group = sources(0, 4);
+        let repairs = encode(&group, 1);
+
+        let mut decoder = FecDecoder::default();
+        assert!(
+            decoder
+                .on_repair(20, repairs[0].clone())
+                .unwrap()
+                .is_empty()
+        );
+        assert!(decoder.on_source(20, group[0].clone()).unwrap().is_empty());
+        assert!(decoder.on_source(20, group[2].clone()).unwrap().is_empty());
+        let recovered = decoder.on_source(20, group[3].clone()).unwrap();
+        assert_eq!(recovered, vec![group[1].clone()]);
+    }
+
+    #[test]
+    fn test_too_many_losses_recover_nothing() {
+        let group = sources(0, 6);
+        let repairs = encode(&group, 2);
+
+        let mut decoder = FecDecoder::default();
+        for source in &group[3..] {
+            decoder.on_source(16, source.clone()).unwrap();
+        }
+        for repair in repairs {
+            assert!(decoder.on_repair(16, repair).unwrap().is_empty());
+        }
+        assert!(!decoder.was_recovered(16, 0));
+    }
+
+    #[test]
+    fn test_complete_group_needs_no_repair() {
+        let group = sources(0, 4);
+        let repairs = encode(&group, 2);
+
+        let mut decoder = FecDecoder::default();
+        for source in &group {
+            decoder.on_source(16, source.clone()).unwrap();
+        }
+        for repair in repairs {
+            assert!(decoder.on_repair(16, repair).unwrap().is_empty());
+        }
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_streams_are_independent() {
+        let group = sources(0, 2);
+        let repairs = encode(&group, 1);
+
+        let mut decoder = FecDecoder::default();
+        decoder.on_source(16, group[0].clone()).unwrap();
+        // Same sequence on another stream must not count towards the group
+        decoder.on_source(17, group[1].clone()).unwrap();
+        let recovered = decoder.on_repair(16, repairs[0].clone()).unwrap();
+        assert_eq!(recovered, vec![group[1].clone()]);
+    }
+
+    #[test]
+    fn test_decoder_window_is_bounded() {
+        let mut decoder = FecDecoder::new(8);
+        for source in sources(0, 20) {
+            decoder.on_source(16, source).unwrap();
+        }
+        assert_eq!(decoder.buffered(), 8);
+    }
+
+    #[test]
+    fn test_repair_frame_roundtrip() {
+        let group = sources(7, 3);
+        let repair = encode(&group, 2).remove(1);
+
+        let frame = repair.build_frame(70_000).unwrap();
+        let parsed = FrameV2::parse(&frame).unwrap();
+        assert_eq!(parsed.frame_type(), FrameTypeV2::FecRepair);
+        assert_eq!(parsed.stream_id(), 70_000);
+        assert_eq!(FecRepair::from_frame(&parsed).unwrap(), repair);
+    }
+
+    #[test]
+    fn test_repair_frame_rejects_bad_header() {
+        let mut repair = encode(&sources(0, 2), 1).remove(0);
+        repair.index = 1;
+        let frame = repair.build_frame(16).unwrap();
+        let parsed = FrameV2::parse(&frame).unwrap();
+        assert!(matches!(
+            FecRepair::from_frame(&parsed),
+            Err(FecError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn test_source_frame_roundtrip() {
+        let source = sources(3, 4).remove(3);
+        let frame = source.build_frame(16).unwrap();
+        let parsed = FrameV2::parse(&frame).unwrap();
+        assert!(parsed.flags().is_fec());
+        assert!(parsed.flags().is_compressed());
+        assert_eq!(FecSource::from_frame(&parsed), source);
+    }
+
+    #[test]
+    fn test_max_size_source_is_protectable() {
+        let source = FecSource {
+            sequence: 0,
+            offset: 1 << 20,
+            payload: vec![0x5A; MAX_FEC_SOURCE_PAYLOAD],
+            compressed: true,
+        };
+        let repair = encode(std::slice::from_ref(&source), 1).remove(0);
+        let frame = repair.build_frame(16).unwrap();
+        let parsed = FrameV2::parse(&frame).unwrap();
+
+        let mut decoder = FecDecoder::default();
+        let recovered = decoder
+            .on_repair(16, FecRepair::from_frame(&parsed).unwrap())
+            .unwrap();
+        assert_eq!(recovered, vec![source]);
+    }
+}
@@ -2,10 +2,14 @@
 //!
 //! Provides format detection, negotiation, and a unified encoding/decoding
 //! interface that handles both v1 and v2 frame headers transparently.
+//! [`v1_frame_to_v2`] and [`v2_frame_to_v1`] re-encode whole frames so a
+//! node can keep building v1 frames internally while speaking either
+//! format on the wire.
 
+use super::frame_v2::{FrameBuilderV2, FrameV2};
 use super::header_v2::FrameHeaderV2;
 use super::types_v2::{FRAME_HEADER_V2_SIZE, FlagsV2, FrameTypeV2, PROTOCOL_VERSION_V2};
-use super::{FrameFlags, FrameHeader, FrameType};
+use super::{Frame, FrameBuilder, FrameFlags, FrameHeader, FrameType};
 use crate::FRAME_HEADER_SIZE;
 use crate::error::FrameError;
 
@@ -235,6 +239,7 @@ pub fn v2_header_to_v1(v2: &FrameHeaderV2) -> Option<FrameHeader> {
         FrameTypeV2::PathResponse => FrameType::PathResponse,
         FrameTypeV2::Close | FrameTypeV2::CloseAck => FrameType::Close,
         FrameTypeV2::Padding | FrameTypeV2::PaddingRandom => FrameType::Pad,
+        FrameTypeV2::Control => FrameType::Control,
         // Types with no v1 equivalent
         _ => return None,
     };
@@ -249,6 +254,70 @@ pub fn v2_header_to_v1(v2: &FrameHeaderV2) -> Option<FrameHeader> {
     })
 }
 
+/// Flag bits defined by the v1 format (SYN, FIN, ACK, PRI, CMP).
+const V1_FLAGS_MASK: u8 = 0x1F;
+
+/// Re-encode a serialized v1 frame as a v2 frame.
+///
+/// The v1 offset travels in the v2 offset extension. The result keeps the
+/// padded size of the v1 frame whenever it fits, so switching format does
+/// not change packet sizes on the wire.
+///
+/// # Errors
+///
+/// Returns any error from parsing the v1 frame.
+pub fn v1_frame_to_v2(data: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let frame = Frame::parse(data)?;
+    let builder = FrameBuilderV2::new()
+        .frame_type(FrameTypeV2::from(frame.frame_type()))
+        .flags(FlagsV2::from(frame.flags()))
+        .stream_id(u32::from(frame.stream_id()))
+        .sequence(u64::from(frame.sequence()))
+        .offset(frame.offset())
+        .payload(frame.payload());
+    let total_size = builder.min_size().max(data.len());
+    builder.build(total_size)
+}
+
+/// Re-encode a serialized v2 frame as a v1 frame.
+///
+/// Returns `Ok(None)` when the frame has no v1 representation: a v2-only
+/// frame type, or a stream ID or sequence number wider than v1 allows.
+/// v2-only flags (ECN, RTX, EXT) are dropped.
+///
+/// # Errors
+///
+/// Returns any error from parsing the v2 frame.
+pub fn v2_frame_to_v1(data: &[u8]) -> Result<Option<Vec<u8>>, FrameError> {
+    let frame = FrameV2::parse(data)?;
+    let Some(header) = v2_header_to_v1(frame.header()) else {
+        return Ok(None);
+    };
+    let (Ok(stream_id), Ok(sequence)) = (
+        u16::try_from(frame.stream_id()),
+        u32::try_from(frame.sequence()),
+    ) else {
+        return Ok(None);
+    };
+
+    let mut flags = frame.flags().bits().to_le_bytes()[0] & V1_FLAGS_MASK;
+    if frame.frame_type() == FrameTypeV2::DataFin {
+        flags |= FrameFlags::FIN;
+    }
+
+    let payload = frame.payload();
+    let total_size = (FRAME_HEADER_SIZE + payload.len()).max(data.len());
+    FrameBuilder::new()
+        .frame_type(header.frame_type)
+        .flags(FrameFlags(flags))
+        .stream_id(stream_id)
+        .sequence(sequence)
+        .offset(frame.offset())
+        .payload(payload)
+        .build(total_size)
+        .map(Some)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -458,4 +527,98 @@ mod tests {
         let decoded = FrameHeaderV2::decode(&encoded).unwrap();
         assert_eq!(decoded.frame_type, FrameTypeV2::Ack);
     }
+
+    #[test]
+    fn test_v1_frame_to_v2_and_back() {
+        let v1 = FrameBuilder::new()
+            .frame_type(FrameType::Data)
+            .flags(FrameFlags::new().with_fin())
+            .stream_id(300)
+            .sequence(77)
+            .offset(5 * 1024 * 1024 * 1024)
+            .payload(b"chunk bytes")
+            .build(128)
+            .unwrap();
+
+        let v2 = v1_frame_to_v2(&v1).unwrap();
+        assert_eq!(v2.len(), 128, "padded size is preserved");
+        let parsed = FrameV2::parse(&v2).unwrap();
+        assert_eq!(parsed.frame_type(), FrameTypeV2::Data);
+        assert!(parsed.flags().is_fin());
+        assert_eq!(parsed.stream_id(), 300);
+        assert_eq!(parsed.sequence(), 77);
+        assert_eq!(parsed.offset(), 5 * 1024 * 1024 * 1024);
+        assert_eq!(parsed.payload(), b"chunk bytes");
+
+        let back = v2_frame_to_v1(&v2).unwrap().unwrap();
+        let frame = Frame::parse(&back).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Data);
+        assert!(frame.flags().is_fin());
+        assert_eq!(frame.stream_id(), 300);
+        assert_eq!(frame.sequence(), 77);
+        assert_eq!(frame.offset(), 5 * 1024 * 1024 * 1024);
+        assert_eq!(frame.payload(), b"chunk bytes");
+    }
+
+    #[test]
+    fn test_v1_control_frame_survives_v2() {
+        let v1 = FrameBuilder::new()
+            .frame_type(FrameType::Control)
+            .stream_id(16)
+            .payload(&[0x42; 40])
+            .build(FRAME_HEADER_SIZE + 40)
+            .unwrap();
+
+        // Grows by the header difference only when the frame is unpadded
+        let v2 = v1_frame_to_v2(&v1).unwrap();
+        assert_eq!(
+            FrameV2::parse(&v2).unwrap().frame_type(),
+            FrameTypeV2::Control
+        );
+
+        let back = v2_frame_to_v1(&v2).unwrap().unwrap();
+        let frame = Frame::parse(&back).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Control);
+        assert_eq!(frame.payload(), &[0x42; 40][..]);
+    }
+
+    #[test]
+    fn test_v2_frame_to_v1_unrepresentable() {
+        let datagram = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Datagram)
+            .payload(b"x")
+            .build(32)
+            .unwrap();
+        assert_eq!(v2_frame_to_v1(&datagram).unwrap(), None);
+
+        let wide_stream = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Data)
+            .stream_id(u32::from(u16::MAX) + 1)
+            .build(32)
+            .unwrap();
+        assert_eq!(v2_frame_to_v1(&wide_stream).unwrap(), None);
+    }
+
+    #[test]
+    fn test_v2_data_fin_sets_v1_fin() {
+        let v2 = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::DataFin)
+            .stream_id(16)
+            .offset(4096)
+            .payload(b"tail")
+            .build(64)
+            .unwrap();
+        let back = v2_frame_to_v1(&v2).unwrap().unwrap();
+        let frame = Frame::parse(&back).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Data);
+        assert!(frame.flags().is_fin());
+        assert!(!frame.flags().is_compressed());
+        assert_eq!(frame.offset(), 4096);
+    }
+
+    #[test]
+    fn test_frame_transcode_rejects_garbage() {
+        assert!(v1_frame_to_v2(&[0u8; 10]).is_err());
+        assert!(v2_frame_to_v1(&[0u8; 10]).is_err());
+    }
 }
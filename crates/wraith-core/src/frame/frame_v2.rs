@@ -0,0 +1,360 @@
+//! v2 frame view and builder.
+//!
+//! A v2 frame is the 24-byte [`FrameHeaderV2`] followed by the payload and
+//! random padding. The v2 header has no offset field, so frames that carry
+//! a file offset set [`FlagsV2::EXT`] and prefix the payload with an 8-byte
+//! little-endian offset extension:
+//!
+//! ```text
+//!  Offset  Size  Field
+//!  0       24    FrameHeaderV2
+//!  24      8     File offset (little-endian, only when EXT is set)
+//!  24/32   N     Payload (N = header length field)
+//!  ...     *     Padding
+//! ```
+
+use super::header_v2::FrameHeaderV2;
+use super::types_v2::{FRAME_HEADER_V2_SIZE, FlagsV2, FrameTypeV2, PROTOCOL_VERSION_V2};
+use super::{MAX_FILE_OFFSET, MAX_PAYLOAD_SIZE, PADDING_RNG};
+use crate::error::FrameError;
+use rand::Rng;
+
+/// Size of the offset extension carried when [`FlagsV2::EXT`] is set.
+pub const OFFSET_EXTENSION_SIZE: usize = 8;
+
+/// Zero-copy v2 frame view into a packet buffer
+#[derive(Debug)]
+pub struct FrameV2<'a> {
+    header: FrameHeaderV2,
+    offset: u64,
+    payload: &'a [u8],
+}
+
+impl<'a> FrameV2<'a> {
+    /// Parse a v2 frame from raw bytes (zero-copy)
+    ///
+    /// # Errors
+    ///
+    /// Returns `FrameError::TooShort` if data is smaller than the header (and offset extension).
+    /// Returns `FrameError::InvalidFrameType` if the version or frame type byte is unknown.
+    /// Returns `FrameError::PayloadOverflow` if the declared payload length exceeds available data.
+    /// Returns `FrameError::ReservedStreamId` if stream ID is in reserved range (1-15).
+    /// Returns `FrameError::InvalidOffset` if offset exceeds maximum file size.
+    /// Returns `FrameError::PayloadTooLarge` if payload exceeds maximum size.
+    pub fn parse(data: &'a [u8]) -> Result<Self, FrameError> {
+        let header = FrameHeaderV2::decode(data)?;
+        if header.version != PROTOCOL_VERSION_V2 {
+            return Err(FrameError::InvalidFrameType(header.version));
+        }
+
+        let mut body = &data[FRAME_HEADER_V2_SIZE..];
+        let offset = if header.flags.has_extensions() {
+            if body.len() < OFFSET_EXTENSION_SIZE {
+                return Err(FrameError::TooShort {
+                    expected: FRAME_HEADER_V2_SIZE + OFFSET_EXTENSION_SIZE,
+                    actual: data.len(),
+                });
+            }
+            let (ext, rest) = body.split_at(OFFSET_EXTENSION_SIZE);
+            body = rest;
+            u64::from_le_bytes(ext.try_into().expect("8-byte extension"))
+        } else {
+            0
+        };
+
+        let payload_len = header.length as usize;
+        if payload_len > MAX_PAYLOAD_SIZE {
+            return Err(FrameError::PayloadTooLarge {
+                size: payload_len,
+                max: MAX_PAYLOAD_SIZE,
+            });
+        }
+        if payload_len > body.len() {
+            return Err(FrameError::PayloadOverflow);
+        }
+
+        // Validate stream ID (1-15 are reserved for protocol use)
+        if header.stream_id > 0 && header.stream_id < 16 {
+            return Err(FrameError::ReservedStreamId(header.stream_id));
+        }
+
+        if offset > MAX_FILE_OFFSET {
+            return Err(FrameError::InvalidOffset {
+                offset,
+                max: MAX_FILE_OFFSET,
+            });
+        }
+
+        Ok(Self {
+            header,
+            offset,
+            payload: &body[..payload_len],
+        })
+    }
+
+    /// Get the decoded header
+    #[must_use]
+    pub fn header(&self) -> &FrameHeaderV2 {
+        &self.header
+    }
+
+    /// Get the frame type
+    #[must_use]
+    pub fn frame_type(&self) -> FrameTypeV2 {
+        self.header.frame_type
+    }
+
+    /// Get the frame flags
+    #[must_use]
+    pub fn flags(&self) -> FlagsV2 {
+        self.header.flags
+    }
+
+    /// Get the stream ID
+    #[must_use]
+    pub fn stream_id(&self) -> u32 {
+        self.header.stream_id
+    }
+
+    /// Get the sequence number
+    #[must_use]
+    pub fn sequence(&self) -> u64 {
+        self.header.sequence
+    }
+
+    /// Get the file offset (zero when no offset extension is present)
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the payload slice
+    #[must_use]
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+}
+
+/// Builder for constructing v2 frames
+#[derive(Default)]
+pub struct FrameBuilderV2 {
+    frame_type: Option<FrameTypeV2>,
+    flags: FlagsV2,
+    stream_id: u32,
+    sequence: u64,
+    offset: u64,
+    payload: Vec<u8>,
+}
+
+impl FrameBuilderV2 {
+    /// Create a new v2 frame builder
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the frame type
+    #[must_use]
+    pub fn frame_type(mut self, ft: FrameTypeV2) -> Self {
+        self.frame_type = Some(ft);
+        self
+    }
+
+    /// Set the flags (`EXT` is managed by the builder)
+    #[must_use]
+    pub fn flags(mut self, flags: FlagsV2) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Set the stream ID
+    #[must_use]
+    pub fn stream_id(mut self, id: u32) -> Self {
+        self.stream_id = id;
+        self
+    }
+
+    /// Set the sequence number
+    #[must_use]
+    pub fn sequence(mut self, seq: u64) -> Self {
+        self.sequence = seq;
+        self
+    }
+
+    /// Set the file offset (emitted as an extension when non-zero)
+    #[must_use]
+    pub fn offset(mut self, off: u64) -> Self {
+        self.offset = off;
+        self
+    }
+
+    /// Set the payload
+    #[must_use]
+    pub fn payload(mut self, data: &[u8]) -> Self {
+        self.payload = data.to_vec();
+        self
+    }
+
+    /// Minimum encoded size of this frame (header, extension and payload)
+    #[must_use]
+    pub fn min_size(&self) -> usize {
+        let ext = if self.offset != 0 {
+            OFFSET_EXTENSION_SIZE
+        } else {
+            0
+        };
+        FRAME_HEADER_V2_SIZE + ext + self.payload.len()
+    }
+
+    /// Build the frame into a pre-allocated buffer (zero-allocation).
+    ///
+    /// Writes the frame directly into `buf`; bytes beyond the payload are
+    /// filled with random padding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FrameError::PayloadOverflow`] if `buf.len()` is smaller than [`Self::min_size`].
+    pub fn build_into(&self, buf: &mut [u8]) -> Result<usize, FrameError> {
+        let total_size = buf.len();
+        let min_size = self.min_size();
+        if total_size < min_size {
+            return Err(FrameError::PayloadOverflow);
+        }
+
+        let has_offset = self.offset != 0;
+        let flags = if has_offset {
+            self.flags.with(FlagsV2::EXT)
+        } else {
+            self.flags.without(FlagsV2::EXT)
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        let header = FrameHeaderV2 {
+            flags,
+            sequence: self.sequence,
+            length: self.payload.len() as u32,
+            stream_id: self.stream_id,
+            ..FrameHeaderV2::new(self.frame_type.unwrap_or(FrameTypeV2::Data))
+        };
+        header.encode_into(&mut buf[..FRAME_HEADER_V2_SIZE]);
+
+        let mut pos = FRAME_HEADER_V2_SIZE;
+        if has_offset {
+            buf[pos..pos + OFFSET_EXTENSION_SIZE].copy_from_slice(&self.offset.to_le_bytes());
+            pos += OFFSET_EXTENSION_SIZE;
+        }
+        buf[pos..min_size].copy_from_slice(&self.payload);
+
+        // Write random padding using fast PRNG (padding is AEAD-encrypted)
+        if total_size > min_size {
+            PADDING_RNG.with_borrow_mut(|rng| rng.fill(&mut buf[min_size..]));
+        }
+
+        Ok(total_size)
+    }
+
+    /// Build the frame into a byte buffer of `total_size` bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FrameError::PayloadOverflow`] if `total_size` is smaller than [`Self::min_size`].
+    pub fn build(self, total_size: usize) -> Result<Vec<u8>, FrameError> {
+        let mut buf = vec![0u8; total_size];
+        self.build_into(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_v2_roundtrip() {
+        let frame = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Datagram)
+            .flags(FlagsV2::empty().with(FlagsV2::FIN))
+            .stream_id(70_000)
+            .sequence(u64::from(u32::MAX) + 5)
+            .payload(b"datagram")
+            .build(64)
+            .unwrap();
+
+        assert_eq!(frame.len(), 64);
+        let parsed = FrameV2::parse(&frame).unwrap();
+        assert_eq!(parsed.frame_type(), FrameTypeV2::Datagram);
+        assert!(parsed.flags().is_fin());
+        assert!(!parsed.flags().has_extensions());
+        assert_eq!(parsed.stream_id(), 70_000);
+        assert_eq!(parsed.sequence(), u64::from(u32::MAX) + 5);
+        assert_eq!(parsed.offset(), 0);
+        assert_eq!(parsed.payload(), b"datagram");
+    }
+
+    #[test]
+    fn test_frame_v2_offset_extension() {
+        let frame = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Data)
+            .stream_id(16)
+            .offset(1 << 40)
+            .payload(&[0xAB; 100])
+            .build(FRAME_HEADER_V2_SIZE + OFFSET_EXTENSION_SIZE + 100)
+            .unwrap();
+
+        let parsed = FrameV2::parse(&frame).unwrap();
+        assert!(parsed.flags().has_extensions());
+        assert_eq!(parsed.offset(), 1 << 40);
+        assert_eq!(parsed.payload(), &[0xAB; 100][..]);
+    }
+
+    #[test]
+    fn test_frame_v2_build_too_small() {
+        let builder = FrameBuilderV2::new().offset(1).payload(&[0u8; 10]);
+        assert_eq!(builder.min_size(), 42);
+        assert!(matches!(
+            builder.build(41),
+            Err(FrameError::PayloadOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_frame_v2_parse_rejects_truncated_extension() {
+        let mut frame = FrameBuilderV2::new().payload(b"x").build(32).unwrap();
+        // Claim an extension that the frame is too short to hold
+        frame[2] |= FlagsV2::EXT as u8;
+        frame.truncate(FRAME_HEADER_V2_SIZE + 4);
+        assert!(matches!(
+            FrameV2::parse(&frame),
+            Err(FrameError::TooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn test_frame_v2_parse_rejects_bad_version() {
+        let mut frame = FrameBuilderV2::new().build(32).unwrap();
+        frame[0] = 0x10;
+        assert!(matches!(
+            FrameV2::parse(&frame),
+            Err(FrameError::InvalidFrameType(0x10))
+        ));
+    }
+
+    #[test]
+    fn test_frame_v2_parse_rejects_payload_overflow() {
+        let mut frame = FrameBuilderV2::new().payload(b"abc").build(32).unwrap();
+        frame[12..16].copy_from_slice(&100u32.to_le_bytes());
+        assert!(matches!(
+            FrameV2::parse(&frame),
+            Err(FrameError::PayloadOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_frame_v2_parse_rejects_reserved_stream() {
+        let frame = FrameBuilderV2::new().stream_id(5).build(32).unwrap();
+        assert!(matches!(
+            FrameV2::parse(&frame),
+            Err(FrameError::ReservedStreamId(5))
+        ));
+    }
+}
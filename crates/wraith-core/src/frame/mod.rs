@@ -13,6 +13,7 @@
 
 pub mod compat;
 pub mod connection_id;
+pub mod frame_v2;
 pub mod header_v2;
 pub mod polymorphic;
 pub mod types_v2;
@@ -31,7 +32,7 @@ thread_local! {
 }
 
 /// Maximum payload size (9000 - header - auth tag = 8944)
-const MAX_PAYLOAD_SIZE: usize = 8944;
+pub(crate) const MAX_PAYLOAD_SIZE: usize = 8944;
 
 /// Maximum file offset (256 TB - reasonable upper bound)
 const MAX_FILE_OFFSET: u64 = 256 * 1024 * 1024 * 1024 * 1024;
@@ -136,7 +137,7 @@ impl FrameFlags {
     pub const ACK: u8 = 0b0000_0100;
     /// Priority frame (expedited processing)
     pub const PRI: u8 = 0b0000_1000;
-    /// Payload is compressed (LZ4)
+    /// Payload is compressed with the codec negotiated for the session
     pub const CMP: u8 = 0b0001_0000;
 
     /// Create new empty flags
@@ -159,6 +160,13 @@ impl FrameFlags {
         self
     }
 
+    /// Add CMP flag
+    #[must_use]
+    pub fn with_compressed(mut self) -> Self {
+        self.0 |= Self::CMP;
+        self
+    }
+
     /// Check if SYN is set
     #[must_use]
     pub fn is_syn(&self) -> bool {
@@ -899,6 +907,7 @@ mod tests {
         assert!(flags.is_syn());
         assert!(flags.is_fin());
         assert!(!flags.is_compressed());
+        assert!(FrameFlags::new().with_compressed().is_compressed());
 
         let frame = FrameBuilder::new()
             .frame_type(FrameType::Data)
@@ -53,6 +53,8 @@ pub enum FrameTypeV2 {
     QosUpdate = 0x16,
     /// Timestamp synchronization
     Timestamp = 0x17,
+    /// Application control message (transfer metadata, verdicts, manifests)
+    Control = 0x1F,
 
     // === Crypto frames (0x20-0x2F) ===
     /// Forward secrecy ratchet
@@ -124,6 +126,7 @@ static FRAME_TYPE_V2_TABLE: [u8; 256] = {
     table[0x15] = 1; // GoAway
     table[0x16] = 1; // QosUpdate
     table[0x17] = 1; // Timestamp
+    table[0x1F] = 1; // Control
 
     // Crypto frames
     table[0x20] = 1; // Rekey
@@ -278,6 +281,8 @@ impl FlagsV2 {
     pub const RTX: u16 = 0b0000_0000_0100_0000;
     /// Extension header(s) present
     pub const EXT: u16 = 0b0000_0000_1000_0000;
+    /// Source symbol of a forward error correction group
+    pub const FEC: u16 = 0b0000_0001_0000_0000;
 
     /// Create empty flags.
     #[must_use]
@@ -362,6 +367,12 @@ impl FlagsV2 {
     pub const fn has_extensions(self) -> bool {
         self.contains(Self::EXT)
     }
+
+    /// Check FEC (forward error correction source) flag.
+    #[must_use]
+    pub const fn is_fec(self) -> bool {
+        self.contains(Self::FEC)
+    }
 }
 
 /// Convert v1 `FrameType` to v2 `FrameTypeV2`.
@@ -373,7 +384,7 @@ impl From<super::FrameType> for FrameTypeV2 {
             super::FrameType::Reserved => FrameTypeV2::Padding, // map reserved to padding
             super::FrameType::Data => FrameTypeV2::Data,
             super::FrameType::Ack => FrameTypeV2::Ack,
-            super::FrameType::Control => FrameTypeV2::GoAway, // closest mapping
+            super::FrameType::Control => FrameTypeV2::Control,
             super::FrameType::Rekey => FrameTypeV2::Rekey,
             super::FrameType::Ping => FrameTypeV2::Ping,
             super::FrameType::Pong => FrameTypeV2::Pong,
@@ -421,6 +432,7 @@ mod tests {
         assert!(FrameTypeV2::GoAway.is_control());
         assert!(FrameTypeV2::QosUpdate.is_control());
         assert!(FrameTypeV2::Timestamp.is_control());
+        assert!(FrameTypeV2::Control.is_control());
         assert!(!FrameTypeV2::Data.is_control());
     }
 
@@ -474,9 +486,9 @@ mod tests {
     #[test]
     fn test_frame_type_v2_try_from_valid() {
         let valid_bytes: &[u8] = &[
-            0x00, 0x01, 0x02, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x20, 0x21, 0x22,
-            0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x40, 0x41, 0x42, 0x50, 0x51, 0x60, 0x61, 0x62,
-            0xF0, 0xF1,
+            0x00, 0x01, 0x02, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x1F, 0x20, 0x21,
+            0x22, 0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x40, 0x41, 0x42, 0x50, 0x51, 0x60, 0x61,
+            0x62, 0xF0, 0xF1,
         ];
         for &b in valid_bytes {
             assert!(
@@ -575,6 +587,7 @@ mod tests {
             (FlagsV2::ECN, FlagsV2::is_ecn),
             (FlagsV2::RTX, FlagsV2::is_retransmit),
             (FlagsV2::EXT, FlagsV2::has_extensions),
+            (FlagsV2::FEC, FlagsV2::is_fec),
         ];
         for &(flag, check) in flags_and_checks {
             let f = FlagsV2::empty().with(flag);
@@ -587,6 +600,7 @@ mod tests {
         use super::super::FrameType;
         assert_eq!(FrameTypeV2::from(FrameType::Data), FrameTypeV2::Data);
         assert_eq!(FrameTypeV2::from(FrameType::Ack), FrameTypeV2::Ack);
+        assert_eq!(FrameTypeV2::from(FrameType::Control), FrameTypeV2::Control);
         assert_eq!(FrameTypeV2::from(FrameType::Rekey), FrameTypeV2::Rekey);
         assert_eq!(FrameTypeV2::from(FrameType::Ping), FrameTypeV2::Ping);
         assert_eq!(FrameTypeV2::from(FrameType::Close), FrameTypeV2::Close);
@@ -9,6 +9,8 @@
 //! - **Session state machine**: Noise_XX handshake and session lifecycle
 //! - **Stream multiplexing**: Logical channels for concurrent file transfers
 //! - **BBR congestion control**: Bandwidth-aware congestion control
+//! - **Forward error correction**: Reed-Solomon repair frames for lossy links
+//! - **Chunk compression**: Negotiated zstd compression of file chunks
 //! - **Transfer session management**: Multi-peer file transfer coordination
 //! - **Error types and handling**: Comprehensive error management
 //!
@@ -66,6 +68,8 @@
 //! - [`stream`]: Stream multiplexing for concurrent transfers
 //! - [`frame`]: Frame encoding/decoding and protocol data units
 //! - [`congestion`]: BBR congestion control implementation
+//! - [`fec`]: Forward error correction over groups of data frames
+//! - [`compression`]: Per-chunk payload compression
 //! - [`transfer`]: File transfer session management
 //! - [`migration`]: Connection migration and multi-path support
 //! - [`path`]: MTU discovery and path management
@@ -75,8 +79,10 @@
 #![warn(clippy::all)]
 #![deny(unsafe_op_in_unsafe_fn)]
 
+pub mod compression;
 pub mod congestion;
 pub mod error;
+pub mod fec;
 pub mod frame;
 pub mod migration;
 pub mod node;
@@ -88,8 +94,10 @@ pub mod transfer;
 
 pub use congestion::BbrState;
 pub use error::Error;
+pub use fec::{FecDecoder, FecEncoder};
 pub use frame::compat::{FormatNegotiation, WireFormat, detect_format};
 pub use frame::connection_id::ConnectionIdV2;
+pub use frame::frame_v2::{FrameBuilderV2, FrameV2};
 pub use frame::header_v2::FrameHeaderV2;
 pub use frame::polymorphic::PolymorphicFormat;
 pub use frame::types_v2::{
@@ -0,0 +1,207 @@
+//! Incoming transfer acceptance policy
+//!
+//! When a peer opens a file transfer (STREAM_OPEN), the receiving node consults
+//! its [`TransferAcceptor`] before anything is created on disk. The acceptor is
+//! given the peer identity, the sender-supplied [`FileMetadata`] and the path the
+//! node proposes to write to, and decides whether to:
+//!
+//! - **Accept** the transfer at the proposed path
+//! - **Reject** it with a reason, which is signalled back to the sender in a
+//!   STREAM_RESET frame
+//! - **Redirect** it to a different local path
+//!
+//! Directory transfers carry their signed [`TreeManifest`] in
+//! [`IncomingTransfer::manifest`]; the metadata then summarizes the whole tree
+//! (root directory name, total size and chunk count).
+//!
+//! Acceptors are invoked on the blocking thread pool, so implementations are
+//! free to prompt the user or touch the file system synchronously.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use wraith_core::node::Node;
+//! use wraith_core::node::acceptance::{IncomingTransfer, TransferDecision, TrustedPeers};
+//!
+//! # async fn example(trusted: [u8; 32]) -> Result<(), Box<dyn std::error::Error>> {
+//! let node = Node::new_random().await?;
+//!
+//! // Only accept transfers from a known peer, and refuse anything over 1 GiB
+//! let policy = TrustedPeers::new([trusted]).with_inner(|request: &IncomingTransfer| {
+//!     if request.metadata.file_size > 1 << 30 {
+//!         TransferDecision::Reject("file too large".to_string())
+//!     } else {
+//!         TransferDecision::Accept
+//!     }
+//! });
+//! node.set_transfer_acceptor(Arc::new(policy)).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::node::file_transfer::FileMetadata;
+use crate::node::session::PeerId;
+use crate::node::tree_transfer::TreeManifest;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// An incoming transfer awaiting an acceptance decision
+#[derive(Debug, Clone)]
+pub struct IncomingTransfer {
+    /// Peer that opened the transfer
+    pub peer_id: PeerId,
+    /// Metadata announced by the sender
+    pub metadata: FileMetadata,
+    /// Path the node would write the file to if accepted
+    pub proposed_path: PathBuf,
+    /// Signed manifest of a directory transfer (`None` for single files)
+    pub manifest: Option<Arc<TreeManifest>>,
+}
+
+/// Outcome of an acceptance decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferDecision {
+    /// Accept the transfer at the proposed path
+    Accept,
+    /// Reject the transfer; the reason is sent back to the sender
+    Reject(String),
+    /// Accept the transfer but write it to the given path instead
+    Redirect(PathBuf),
+}
+
+/// Policy deciding whether incoming transfers are accepted
+///
+/// Implemented for any `Fn(&IncomingTransfer) -> TransferDecision` closure.
+pub trait TransferAcceptor: Send + Sync {
+    /// Decide whether to accept an incoming transfer
+    fn decide(&self, request: &IncomingTransfer) -> TransferDecision;
+}
+
+impl<F> TransferAcceptor for F
+where
+    F: Fn(&IncomingTransfer) -> TransferDecision + Send + Sync,
+{
+    fn decide(&self, request: &IncomingTransfer) -> TransferDecision {
+        self(request)
+    }
+}
+
+/// Accept every incoming transfer (the default policy)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptAll;
+
+impl TransferAcceptor for AcceptAll {
+    fn decide(&self, _request: &IncomingTransfer) -> TransferDecision {
+        TransferDecision::Accept
+    }
+}
+
+/// Only accept transfers from an allow-list of peers
+///
+/// Transfers from trusted peers are passed on to an inner acceptor
+/// ([`AcceptAll`] unless replaced with [`TrustedPeers::with_inner`]);
+/// transfers from any other peer are rejected.
+#[derive(Clone)]
+pub struct TrustedPeers {
+    peers: HashSet<PeerId>,
+    inner: Arc<dyn TransferAcceptor>,
+}
+
+impl TrustedPeers {
+    /// Create a policy trusting the given peers
+    pub fn new(peers: impl IntoIterator<Item = PeerId>) -> Self {
+        Self {
+            peers: peers.into_iter().collect(),
+            inner: Arc::new(AcceptAll),
+        }
+    }
+
+    /// Set the acceptor consulted for transfers from trusted peers
+    pub fn with_inner(mut self, inner: impl TransferAcceptor + 'static) -> Self {
+        self.inner = Arc::new(inner);
+        self
+    }
+
+    /// Check whether a peer is trusted
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.peers.contains(peer_id)
+    }
+}
+
+impl TransferAcceptor for TrustedPeers {
+    fn decide(&self, request: &IncomingTransfer) -> TransferDecision {
+        if self.is_trusted(&request.peer_id) {
+            self.inner.decide(request)
+        } else {
+            TransferDecision::Reject("peer is not trusted".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(peer_id: PeerId) -> IncomingTransfer {
+        IncomingTransfer {
+            peer_id,
+            metadata: FileMetadata {
+                transfer_id: [1u8; 32],
+                file_name: "report.pdf".to_string(),
+                file_size: 1024,
+                chunk_size: 256,
+                total_chunks: 4,
+                root_hash: [2u8; 32],
+            },
+            proposed_path: PathBuf::from("/tmp/report.pdf"),
+            manifest: None,
+        }
+    }
+
+    #[test]
+    fn test_accept_all() {
+        assert_eq!(
+            AcceptAll.decide(&request([9u8; 32])),
+            TransferDecision::Accept
+        );
+    }
+
+    #[test]
+    fn test_closure_acceptor() {
+        let acceptor = |request: &IncomingTransfer| {
+            TransferDecision::Redirect(
+                PathBuf::from("/srv/inbox").join(&request.metadata.file_name),
+            )
+        };
+
+        assert_eq!(
+            acceptor.decide(&request([9u8; 32])),
+            TransferDecision::Redirect(PathBuf::from("/srv/inbox/report.pdf"))
+        );
+    }
+
+    #[test]
+    fn test_trusted_peers_rejects_unknown_peer() {
+        let policy = TrustedPeers::new([[1u8; 32]]);
+
+        assert!(policy.is_trusted(&[1u8; 32]));
+        assert_eq!(policy.decide(&request([1u8; 32])), TransferDecision::Accept);
+        assert!(matches!(
+            policy.decide(&request([2u8; 32])),
+            TransferDecision::Reject(_)
+        ));
+    }
+
+    #[test]
+    fn test_trusted_peers_delegates_to_inner() {
+        let policy = TrustedPeers::new([[1u8; 32]])
+            .with_inner(|_: &IncomingTransfer| TransferDecision::Reject("busy".to_string()));
+
+        assert_eq!(
+            policy.decide(&request([1u8; 32])),
+            TransferDecision::Reject("busy".to_string())
+        );
+    }
+}
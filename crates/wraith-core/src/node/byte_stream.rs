@@ -0,0 +1,1026 @@
+//! Byte streams over authenticated sessions
+//!
+//! [`Node::open_stream`] and [`Node::accept_stream`] hand out
+//! [`WraithStream`]s: reliable, ordered, flow-controlled byte channels that
+//! implement tokio's [`AsyncRead`] and [`AsyncWrite`], so arbitrary
+//! application protocols can be tunnelled over a WRAITH session. Each stream
+//! keeps its state machine, buffers and flow-control windows in a
+//! [`Stream`]; a driver task per stream owns everything sent on the wire.
+//!
+//! # Wire Protocol
+//!
+//! ```text
+//! STREAM_OPEN    empty payload                   open stream_id
+//! DATA           offset = byte offset            stream bytes
+//! WINDOW_UPDATE  ack (u64) | limit (u64) | flags acknowledgement and credit
+//! STREAM_CLOSE   offset = final length           FIN
+//! STREAM_RESET   empty payload                   abort
+//! ```
+//!
+//! A WINDOW_UPDATE acknowledges the bytes received in order (`ack`), grants
+//! credit up to the absolute offset `limit` and, with [`WINDOW_FLAG_FIN`],
+//! confirms the FIN. The first one also accepts the stream. Because it
+//! carries absolute offsets, lost or reordered updates are harmless. The
+//! STREAM_OPEN, unacknowledged data and the FIN are retransmitted until
+//! acknowledged; a stream whose peer stays silent through
+//! [`MAX_RETRANSMISSIONS`] timeouts is reset.
+//!
+//! The empty STREAM_OPEN payload tells a byte stream apart from a transfer's
+//! file metadata or manifest segments. Each side opens streams of one ID
+//! parity — odd for the peer with the larger X25519 key — so concurrent opens
+//! never collide.
+
+use crate::FRAME_HEADER_SIZE;
+use crate::frame::{Frame, FrameBuilder, FrameType};
+use crate::node::Node;
+use crate::node::error::{NodeError, Result};
+use crate::node::file_transfer::FIRST_TRANSFER_STREAM_ID;
+use crate::node::session::PeerId;
+use crate::stream::{Stream, StreamConfig};
+use getrandom::getrandom;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::{Notify, oneshot};
+
+/// Largest payload carried by one stream DATA frame (fits a v2 frame)
+pub const MAX_SEGMENT_SIZE: usize = 8 * 1024;
+
+/// Consecutive unanswered retransmission timeouts before a stream is reset
+pub const MAX_RETRANSMISSIONS: u32 = 8;
+
+/// WINDOW_UPDATE flag confirming the peer's FIN
+pub const WINDOW_FLAG_FIN: u8 = 0x01;
+
+/// Incoming streams queued for [`Node::accept_stream`] before new ones are refused
+pub const ACCEPT_BACKLOG: usize = 64;
+
+/// Bytes written but not yet acknowledged before writes wait
+const SEND_BUFFER_LIMIT: usize = 256 * 1024;
+
+/// Initial retransmission timeout
+const INITIAL_RTO: Duration = Duration::from_millis(200);
+
+/// Retransmission timeout cap
+const MAX_RTO: Duration = Duration::from_secs(2);
+
+/// How often an idle driver checks that its node and session are still up
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a closed stream keeps acknowledging a retransmitted FIN
+const CLOSE_LINGER: Duration = Duration::from_secs(4);
+
+/// WINDOW_UPDATE payload length
+const WINDOW_UPDATE_LEN: usize = 17;
+
+/// Reliable, ordered byte stream to a peer
+///
+/// Obtained from [`Node::open_stream`] or [`Node::accept_stream`]. Reads
+/// return data in order and end at the peer's FIN; [`AsyncWrite::poll_flush`]
+/// and [`AsyncWrite::poll_shutdown`] complete once the peer has acknowledged
+/// everything written. Dropping the stream closes it gracefully.
+pub struct WraithStream {
+    inner: Arc<ByteStream>,
+}
+
+impl WraithStream {
+    /// Peer at the other end of the stream
+    #[must_use]
+    pub fn peer_id(&self) -> &PeerId {
+        &self.inner.peer_id
+    }
+
+    /// Stream ID within the session
+    #[must_use]
+    pub fn stream_id(&self) -> u16 {
+        self.inner.stream_id
+    }
+}
+
+impl std::fmt::Debug for WraithStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WraithStream")
+            .field("peer_id", &hex::encode(&self.inner.peer_id[..8]))
+            .field("stream_id", &self.inner.stream_id)
+            .finish()
+    }
+}
+
+impl AsyncRead for WraithStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut core = self.inner.lock();
+        loop {
+            if let Some((data, pos)) = core.reading.as_mut() {
+                let n = (data.len() - *pos).min(buf.remaining());
+                buf.put_slice(&data[*pos..*pos + n]);
+                *pos += n;
+                if *pos == data.len() {
+                    core.reading = None;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            if let Some(data) = core.stream.read() {
+                core.stream.update_recv_window(data.len() as u64);
+                core.ack_pending = true;
+                core.reading = Some((data, 0));
+                self.inner.notify.notify_one();
+                continue;
+            }
+
+            if core.fin_received {
+                return Poll::Ready(Ok(()));
+            }
+            if core.reset {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+            }
+            core.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+    }
+}
+
+impl AsyncWrite for WraithStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut core = self.inner.lock();
+        if core.reset {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+        if core.fin_requested {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let buffered = core.buffered_send_bytes();
+        if buffered >= SEND_BUFFER_LIMIT {
+            core.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let n = buf.len().min(SEND_BUFFER_LIMIT - buffered);
+        if core.stream.write(buf[..n].to_vec()).is_err() {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        self.inner.notify.notify_one();
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut core = self.inner.lock();
+        if core.buffered_send_bytes() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        if core.reset {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+        core.write_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut core = self.inner.lock();
+        if !core.fin_requested {
+            core.fin_requested = true;
+            self.inner.notify.notify_one();
+        }
+        if core.fin_acked {
+            return Poll::Ready(Ok(()));
+        }
+        if core.reset {
+            return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+        core.write_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for WraithStream {
+    fn drop(&mut self) {
+        let mut core = self.inner.lock();
+        core.abandon();
+        self.inner.notify.notify_one();
+    }
+}
+
+/// State of one byte stream shared by its handle, driver and packet handler
+pub(crate) struct ByteStream {
+    peer_id: PeerId,
+    stream_id: u16,
+    core: Mutex<StreamCore>,
+    /// Wakes the driver task
+    notify: Notify,
+}
+
+/// Mutable stream state
+struct StreamCore {
+    /// State machine, buffers and flow-control windows
+    stream: Stream,
+    /// Whether the peer has accepted the stream
+    open_acked: bool,
+    /// Whether the STREAM_OPEN has been sent
+    open_sent: bool,
+    /// Completed once the peer accepts the stream
+    open_waiter: Option<oneshot::Sender<()>>,
+    /// Absolute offset up to which the peer grants credit
+    send_limit: u64,
+    /// Sent segments not yet acknowledged (offset, data)
+    unacked: VecDeque<(u64, Vec<u8>)>,
+    /// Whether the local side wants to finish sending
+    fin_requested: bool,
+    /// Whether the FIN has been sent
+    fin_sent: bool,
+    /// Whether the peer acknowledged the FIN
+    fin_acked: bool,
+    /// When outstanding frames are next retransmitted
+    retransmit_due: Option<Instant>,
+    /// Current retransmission timeout
+    rto: Duration,
+    /// Consecutive retransmission timeouts without progress
+    retransmissions: u32,
+    /// Segments received ahead of the next in-order offset
+    out_of_order: BTreeMap<u64, Vec<u8>>,
+    /// Final length announced by the peer's FIN
+    fin_offset: Option<u64>,
+    /// Whether the peer's FIN and all data before it arrived
+    fin_received: bool,
+    /// Whether a WINDOW_UPDATE should be sent
+    ack_pending: bool,
+    /// Segment being copied out to the reader (data, position)
+    reading: Option<(Vec<u8>, usize)>,
+    /// Whether the stream was reset or timed out
+    reset: bool,
+    /// Whether a STREAM_RESET should be sent
+    reset_pending: bool,
+    /// Whether the local handle is gone
+    abandoned: bool,
+    /// Until when a closed stream keeps answering the peer
+    linger_until: Option<Instant>,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl StreamCore {
+    fn new(stream_id: u16, open_waiter: Option<oneshot::Sender<()>>) -> Self {
+        let config = StreamConfig::default();
+        let mut stream = Stream::new(stream_id, config.initial_window);
+        stream.open().expect("a new stream can always be opened");
+
+        Self {
+            stream,
+            // Only the opening side waits for acceptance
+            open_acked: open_waiter.is_none(),
+            open_sent: false,
+            open_waiter,
+            send_limit: config.initial_window,
+            unacked: VecDeque::new(),
+            fin_requested: false,
+            fin_sent: false,
+            fin_acked: false,
+            retransmit_due: None,
+            rto: INITIAL_RTO,
+            retransmissions: 0,
+            out_of_order: BTreeMap::new(),
+            fin_offset: None,
+            fin_received: false,
+            ack_pending: false,
+            reading: None,
+            reset: false,
+            reset_pending: false,
+            abandoned: false,
+            linger_until: None,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    /// Bytes written by the application and not yet acknowledged
+    fn buffered_send_bytes(&self) -> usize {
+        self.stream.send_buffer_size() + self.unacked.iter().map(|(_, d)| d.len()).sum::<usize>()
+    }
+
+    /// Whether anything sent still awaits acknowledgement
+    fn outstanding(&self) -> bool {
+        !self.open_acked || !self.unacked.is_empty() || (self.fin_sent && !self.fin_acked)
+    }
+
+    fn arm_retransmit(&mut self, now: Instant) {
+        if self.retransmit_due.is_none() {
+            self.retransmit_due = Some(now + self.rto);
+        }
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Mark the stream reset, keeping received data readable
+    fn fail(&mut self) {
+        self.reset = true;
+        self.unacked.clear();
+        self.open_waiter = None;
+        self.retransmit_due = None;
+        self.wake();
+    }
+
+    /// The local handle is gone: finish sending and discard incoming data
+    fn abandon(&mut self) {
+        self.abandoned = true;
+        self.fin_requested = true;
+        self.reading = None;
+        self.discard_received();
+    }
+
+    fn discard_received(&mut self) {
+        while let Some(data) = self.stream.read() {
+            self.stream.update_recv_window(data.len() as u64);
+            self.ack_pending = true;
+        }
+    }
+
+    /// Move contiguous segments into the stream and detect the FIN
+    fn deliver_in_order(&mut self) {
+        while let Some(data) = self.out_of_order.remove(&self.stream.bytes_received()) {
+            if self.stream.receive(data).is_err() {
+                break;
+            }
+        }
+        if !self.fin_received && self.fin_offset == Some(self.stream.bytes_received()) {
+            self.fin_received = true;
+            self.stream.mark_fin_received();
+        }
+        if self.abandoned {
+            self.discard_received();
+        }
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Absolute offset up to which the peer may send
+    fn recv_limit(&self) -> u64 {
+        self.stream.bytes_received() + self.stream.recv_window()
+    }
+}
+
+/// Frames a driver pass wants sent, and whether the stream is finished
+struct DriverStep {
+    frames: Vec<Vec<u8>>,
+    finished: bool,
+    wake_at: Option<Instant>,
+}
+
+impl ByteStream {
+    fn new(peer_id: PeerId, stream_id: u16, open_waiter: Option<oneshot::Sender<()>>) -> Self {
+        Self {
+            peer_id,
+            stream_id,
+            core: Mutex::new(StreamCore::new(stream_id, open_waiter)),
+            notify: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StreamCore> {
+        self.core
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Record a DATA frame
+    fn on_data(&self, offset: u64, payload: &[u8]) {
+        let mut core = self.lock();
+        if core.reset {
+            return;
+        }
+
+        let received = core.stream.bytes_received();
+        let end = offset.saturating_add(payload.len() as u64);
+        if end > core.recv_limit() {
+            tracing::debug!(
+                "Dropping data beyond the window of stream {}",
+                self.stream_id
+            );
+        } else if end > received && !payload.is_empty() {
+            let start = offset.max(received);
+            let skip = (start - offset) as usize;
+            core.out_of_order
+                .entry(start)
+                .or_insert_with(|| payload[skip..].to_vec());
+            core.deliver_in_order();
+        }
+
+        core.ack_pending = true;
+        drop(core);
+        self.notify.notify_one();
+    }
+
+    /// Record the peer's FIN at `final_len`
+    fn on_fin(&self, final_len: u64) {
+        let mut core = self.lock();
+        if core.reset {
+            return;
+        }
+        if final_len < core.stream.bytes_received()
+            || core.fin_offset.is_some_and(|offset| offset != final_len)
+        {
+            tracing::debug!("Ignoring inconsistent FIN on stream {}", self.stream_id);
+            return;
+        }
+
+        core.fin_offset = Some(final_len);
+        core.deliver_in_order();
+        core.ack_pending = true;
+        drop(core);
+        self.notify.notify_one();
+    }
+
+    /// Record a WINDOW_UPDATE
+    fn on_window_update(&self, ack: u64, limit: u64, flags: u8) {
+        let mut core = self.lock();
+        if core.reset {
+            return;
+        }
+
+        let mut progress = false;
+        if !core.open_acked {
+            core.open_acked = true;
+            progress = true;
+            let accepted = core
+                .open_waiter
+                .take()
+                .is_some_and(|waiter| waiter.send(()).is_ok());
+            if !accepted {
+                // Nobody is left waiting for the stream
+                core.abandon();
+            }
+        }
+
+        while core
+            .unacked
+            .front()
+            .is_some_and(|(offset, data)| offset + data.len() as u64 <= ack)
+        {
+            core.unacked.pop_front();
+            progress = true;
+        }
+
+        if limit > core.send_limit {
+            let credit = limit - core.send_limit;
+            core.stream.update_send_window(credit);
+            core.send_limit = limit;
+        }
+
+        if flags & WINDOW_FLAG_FIN != 0
+            && core.fin_sent
+            && !core.fin_acked
+            && ack >= core.stream.bytes_sent()
+        {
+            core.fin_acked = true;
+            progress = true;
+        }
+
+        if progress {
+            core.retransmissions = 0;
+            core.rto = INITIAL_RTO;
+            core.retransmit_due = None;
+            if core.outstanding() {
+                core.arm_retransmit(Instant::now());
+            }
+        }
+        core.wake();
+        drop(core);
+        self.notify.notify_one();
+    }
+
+    /// Record a STREAM_RESET from the peer
+    fn on_reset(&self) {
+        let mut core = self.lock();
+        core.fail();
+        drop(core);
+        self.notify.notify_one();
+    }
+
+    /// Work out what to send and whether the stream is finished
+    fn step(&self, now: Instant) -> Result<DriverStep> {
+        let mut core = self.lock();
+        let mut frames = Vec::new();
+
+        if core.reset_pending {
+            core.reset_pending = false;
+            core.fail();
+            frames.push(build_reset_frame(self.stream_id)?);
+        }
+        if core.reset {
+            return Ok(DriverStep {
+                frames,
+                finished: true,
+                wake_at: None,
+            });
+        }
+
+        if let Some(due) = core.retransmit_due
+            && now >= due
+        {
+            core.retransmissions += 1;
+            if core.retransmissions > MAX_RETRANSMISSIONS {
+                tracing::debug!("Stream {} timed out", self.stream_id);
+                core.fail();
+                return Ok(DriverStep {
+                    frames: vec![build_reset_frame(self.stream_id)?],
+                    finished: true,
+                    wake_at: None,
+                });
+            }
+
+            if !core.open_acked {
+                frames.push(build_open_frame(self.stream_id)?);
+            }
+            for (offset, data) in &core.unacked {
+                frames.push(build_stream_data_frame(self.stream_id, *offset, data)?);
+            }
+            if core.fin_sent && !core.fin_acked {
+                frames.push(build_fin_frame(self.stream_id, core.stream.bytes_sent())?);
+            }
+            core.rto = (core.rto * 2).min(MAX_RTO);
+            core.retransmit_due = Some(now + core.rto);
+        }
+
+        if !core.open_sent && !core.open_acked {
+            core.open_sent = true;
+            frames.push(build_open_frame(self.stream_id)?);
+            core.arm_retransmit(now);
+        }
+
+        if core.ack_pending {
+            core.ack_pending = false;
+            let flags = if core.fin_received {
+                WINDOW_FLAG_FIN
+            } else {
+                0
+            };
+            frames.push(build_window_update_frame(
+                self.stream_id,
+                core.stream.bytes_received(),
+                core.recv_limit(),
+                flags,
+            )?);
+        }
+
+        if core.open_acked {
+            while let Some(data) = core.stream.take_send_data(MAX_SEGMENT_SIZE) {
+                let offset = core.stream.bytes_sent() - data.len() as u64;
+                frames.push(build_stream_data_frame(self.stream_id, offset, &data)?);
+                core.unacked.push_back((offset, data));
+                core.arm_retransmit(now);
+            }
+            if core.fin_requested && !core.fin_sent && !core.stream.has_data_to_send() {
+                core.fin_sent = true;
+                core.stream.mark_fin_sent();
+                frames.push(build_fin_frame(self.stream_id, core.stream.bytes_sent())?);
+                core.arm_retransmit(now);
+            }
+        }
+
+        // Both directions done: linger briefly to re-acknowledge a lost FIN ack
+        let mut finished = false;
+        if core.fin_acked && (core.fin_received || core.abandoned) {
+            if !core.fin_received {
+                // Abandoned before the peer finished: tell it nobody is reading
+                core.fail();
+                frames.push(build_reset_frame(self.stream_id)?);
+                finished = true;
+            } else {
+                let linger_until = *core.linger_until.get_or_insert(now + CLOSE_LINGER);
+                finished = now >= linger_until;
+            }
+        }
+
+        Ok(DriverStep {
+            frames,
+            finished,
+            wake_at: core
+                .retransmit_due
+                .into_iter()
+                .chain(core.linger_until)
+                .min(),
+        })
+    }
+}
+
+/// Build the STREAM_OPEN frame opening a byte stream
+fn build_open_frame(stream_id: u16) -> Result<Vec<u8>> {
+    build_stream_frame(FrameType::StreamOpen, stream_id, 0, &[])
+}
+
+/// Build a DATA frame carrying stream bytes at `offset`
+fn build_stream_data_frame(stream_id: u16, offset: u64, data: &[u8]) -> Result<Vec<u8>> {
+    build_stream_frame(FrameType::Data, stream_id, offset, data)
+}
+
+/// Build the STREAM_CLOSE frame announcing the stream's final length
+fn build_fin_frame(stream_id: u16, final_len: u64) -> Result<Vec<u8>> {
+    build_stream_frame(FrameType::StreamClose, stream_id, final_len, &[])
+}
+
+/// Build the STREAM_RESET frame aborting a byte stream
+fn build_reset_frame(stream_id: u16) -> Result<Vec<u8>> {
+    build_stream_frame(FrameType::StreamReset, stream_id, 0, &[])
+}
+
+/// Build a WINDOW_UPDATE frame
+pub fn build_window_update_frame(
+    stream_id: u16,
+    ack: u64,
+    limit: u64,
+    flags: u8,
+) -> Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(WINDOW_UPDATE_LEN);
+    payload.extend_from_slice(&ack.to_be_bytes());
+    payload.extend_from_slice(&limit.to_be_bytes());
+    payload.push(flags);
+    build_stream_frame(FrameType::WindowUpdate, stream_id, 0, &payload)
+}
+
+/// Parse a WINDOW_UPDATE payload into (ack, limit, flags)
+///
+/// # Errors
+///
+/// Returns an error if the payload is too short or `ack` exceeds `limit`.
+pub fn parse_window_update(payload: &[u8]) -> Result<(u64, u64, u8)> {
+    if payload.len() < WINDOW_UPDATE_LEN {
+        return Err(NodeError::InvalidState("WINDOW_UPDATE too short".into()));
+    }
+    let ack = u64::from_be_bytes(payload[0..8].try_into().expect("8-byte slice"));
+    let limit = u64::from_be_bytes(payload[8..16].try_into().expect("8-byte slice"));
+    if ack > limit {
+        return Err(NodeError::InvalidState(
+            "WINDOW_UPDATE acknowledges beyond its limit".into(),
+        ));
+    }
+    Ok((ack, limit, payload[16]))
+}
+
+fn build_stream_frame(
+    frame_type: FrameType,
+    stream_id: u16,
+    offset: u64,
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    FrameBuilder::new()
+        .frame_type(frame_type)
+        .stream_id(stream_id)
+        .offset(offset)
+        .payload(payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| NodeError::Stream(format!("Failed to build stream frame: {e}").into()))
+}
+
+impl Node {
+    /// Open a byte stream to a peer
+    ///
+    /// Establishes a session first if needed and returns once the peer has
+    /// accepted the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no session can be established, the peer refuses
+    /// the stream or does not answer.
+    pub async fn open_stream(&self, peer_id: &PeerId) -> Result<WraithStream> {
+        self.get_or_establish_session(peer_id).await?;
+        let stream_id = self.allocate_stream_id(peer_id)?;
+
+        let (open_tx, open_rx) = oneshot::channel();
+        let byte_stream = Arc::new(ByteStream::new(*peer_id, stream_id, Some(open_tx)));
+        self.inner
+            .byte_streams
+            .insert((*peer_id, stream_id), Arc::clone(&byte_stream));
+        self.spawn_stream_driver(Arc::clone(&byte_stream));
+
+        open_rx
+            .await
+            .map_err(|_| NodeError::Stream("stream refused or peer unreachable".into()))?;
+        tracing::debug!(
+            "Opened stream {} to peer {}",
+            stream_id,
+            hex::encode(&peer_id[..8])
+        );
+        Ok(WraithStream { inner: byte_stream })
+    }
+
+    /// Wait for the next byte stream opened by a peer
+    ///
+    /// Streams opened while nobody is waiting are queued; once
+    /// [`ACCEPT_BACKLOG`] are queued, further streams are refused.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is not running.
+    pub async fn accept_stream(&self) -> Result<WraithStream> {
+        if !self.is_running() {
+            return Err(NodeError::invalid_state("Node not running"));
+        }
+        self.inner
+            .incoming_streams
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| NodeError::Channel("incoming stream queue closed".into()))
+    }
+
+    /// Pick an unused stream ID of this node's parity
+    fn allocate_stream_id(&self, peer_id: &PeerId) -> Result<u16> {
+        let parity = u16::from(self.x25519_public_key() > peer_id);
+        for _ in 0..64 {
+            let mut bytes = [0u8; 2];
+            getrandom(&mut bytes)
+                .map_err(|e| NodeError::Stream(format!("Failed to pick stream ID: {e}").into()))?;
+            let stream_id = (u16::from_be_bytes(bytes) & !1) | parity;
+            if stream_id >= FIRST_TRANSFER_STREAM_ID
+                && !self.inner.byte_streams.contains_key(&(*peer_id, stream_id))
+                && self.find_transfer_by_stream_id(stream_id).is_err()
+            {
+                return Ok(stream_id);
+            }
+        }
+        Err(NodeError::Stream("no free stream ID".into()))
+    }
+
+    /// Look up an open byte stream
+    pub(crate) fn byte_stream(&self, peer_id: &PeerId, stream_id: u16) -> Option<Arc<ByteStream>> {
+        self.inner
+            .byte_streams
+            .get(&(*peer_id, stream_id))
+            .map(|entry| Arc::clone(entry.value()))
+    }
+
+    /// Handle a STREAM_OPEN without payload: a peer opening a byte stream
+    pub(crate) async fn handle_byte_stream_open(&self, stream_id: u16, peer_id: PeerId) {
+        if let Some(byte_stream) = self.byte_stream(&peer_id, stream_id) {
+            // Retransmitted open: our acceptance was lost
+            byte_stream.lock().ack_pending = true;
+            byte_stream.notify.notify_one();
+            return;
+        }
+
+        let Ok(permit) = self.inner.incoming_streams_tx.try_reserve() else {
+            tracing::warn!(
+                "Refusing stream {} from peer {}: accept backlog full",
+                stream_id,
+                hex::encode(&peer_id[..8])
+            );
+            if let (Some(connection), Ok(frame)) = (
+                self.inner
+                    .sessions
+                    .get(&peer_id)
+                    .map(|e| Arc::clone(e.value())),
+                build_reset_frame(stream_id),
+            ) {
+                let _ = self.send_encrypted_frame(&connection, &frame).await;
+            }
+            return;
+        };
+
+        let byte_stream = Arc::new(ByteStream::new(peer_id, stream_id, None));
+        byte_stream.lock().ack_pending = true;
+        self.inner
+            .byte_streams
+            .insert((peer_id, stream_id), Arc::clone(&byte_stream));
+        self.spawn_stream_driver(Arc::clone(&byte_stream));
+        permit.send(WraithStream { inner: byte_stream });
+        tracing::debug!(
+            "Accepted stream {} from peer {}",
+            stream_id,
+            hex::encode(&peer_id[..8])
+        );
+    }
+
+    /// Route a frame to its byte stream
+    ///
+    /// Returns `false` if the frame does not belong to an open byte stream.
+    pub(crate) fn route_byte_stream_frame(&self, frame: &Frame<'_>, peer_id: PeerId) -> bool {
+        let Some(byte_stream) = self.byte_stream(&peer_id, frame.stream_id()) else {
+            return false;
+        };
+
+        match frame.frame_type() {
+            FrameType::Data => byte_stream.on_data(frame.offset(), frame.payload()),
+            FrameType::StreamClose => byte_stream.on_fin(frame.offset()),
+            FrameType::StreamReset => byte_stream.on_reset(),
+            FrameType::WindowUpdate => match parse_window_update(frame.payload()) {
+                Ok((ack, limit, flags)) => byte_stream.on_window_update(ack, limit, flags),
+                Err(e) => {
+                    tracing::debug!("Bad WINDOW_UPDATE on stream {}: {}", frame.stream_id(), e)
+                }
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn spawn_stream_driver(&self, byte_stream: Arc<ByteStream>) {
+        let node = self.clone();
+        tokio::spawn(async move { node.drive_stream(byte_stream).await });
+    }
+
+    /// Send a stream's frames until it is finished, then forget it
+    async fn drive_stream(&self, byte_stream: Arc<ByteStream>) {
+        let key = (byte_stream.peer_id, byte_stream.stream_id);
+        loop {
+            let connection = self
+                .inner
+                .sessions
+                .get(&byte_stream.peer_id)
+                .map(|e| Arc::clone(e.value()));
+            let Some(connection) = connection.filter(|_| self.is_running()) else {
+                byte_stream.lock().fail();
+                break;
+            };
+
+            let now = Instant::now();
+            let step = match byte_stream.step(now) {
+                Ok(step) => step,
+                Err(e) => {
+                    tracing::warn!("Stream {} failed: {}", byte_stream.stream_id, e);
+                    byte_stream.lock().fail();
+                    break;
+                }
+            };
+            for frame in &step.frames {
+                if let Err(e) = self.send_encrypted_frame(&connection, frame).await {
+                    tracing::debug!(
+                        "Failed to send frame on stream {}: {}",
+                        byte_stream.stream_id,
+                        e
+                    );
+                }
+            }
+            if step.finished {
+                break;
+            }
+
+            let wait = step
+                .wake_at
+                .map_or(IDLE_CHECK_INTERVAL, |at| at.saturating_duration_since(now))
+                .min(IDLE_CHECK_INTERVAL);
+            tokio::select! {
+                () = byte_stream.notify.notified() => {}
+                () = tokio::time::sleep(wait) => {}
+            }
+        }
+
+        self.inner
+            .byte_streams
+            .remove_if(&key, |_, entry| Arc::ptr_eq(entry, &byte_stream));
+        tracing::trace!("Stream {} finished", byte_stream.stream_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(byte_stream: &ByteStream) {
+        byte_stream.on_window_update(0, StreamConfig::default().initial_window, 0);
+    }
+
+    #[test]
+    fn test_window_update_round_trip() {
+        let frame = build_window_update_frame(17, 100, 65636, WINDOW_FLAG_FIN).unwrap();
+        let frame = Frame::parse(&frame).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::WindowUpdate);
+        assert_eq!(frame.stream_id(), 17);
+        assert_eq!(
+            parse_window_update(frame.payload()).unwrap(),
+            (100, 65636, WINDOW_FLAG_FIN)
+        );
+
+        assert!(parse_window_update(&[0u8; 16]).is_err());
+        let mut inverted = vec![0u8; WINDOW_UPDATE_LEN];
+        inverted[7] = 2;
+        inverted[15] = 1;
+        assert!(parse_window_update(&inverted).is_err());
+    }
+
+    #[test]
+    fn test_open_is_retransmitted_until_accepted() {
+        let (open_tx, mut open_rx) = oneshot::channel();
+        let byte_stream = ByteStream::new([1u8; 32], 17, Some(open_tx));
+        let start = Instant::now();
+
+        let step = byte_stream.step(start).unwrap();
+        assert_eq!(step.frames.len(), 1);
+        assert_eq!(
+            Frame::parse(&step.frames[0]).unwrap().frame_type(),
+            FrameType::StreamOpen
+        );
+        assert!(byte_stream.step(start).unwrap().frames.is_empty());
+
+        let retry = byte_stream.step(start + INITIAL_RTO).unwrap();
+        assert_eq!(retry.frames.len(), 1);
+
+        accept(&byte_stream);
+        assert!(open_rx.try_recv().is_ok());
+        assert!(byte_stream.lock().retransmit_due.is_none());
+    }
+
+    #[test]
+    fn test_out_of_order_data_is_reassembled() {
+        let byte_stream = ByteStream::new([1u8; 32], 18, None);
+
+        byte_stream.on_data(3, b"def");
+        assert!(!byte_stream.lock().stream.has_received_data());
+
+        byte_stream.on_data(0, b"abc");
+        byte_stream.on_data(0, b"abc");
+        let mut core = byte_stream.lock();
+        assert_eq!(core.stream.read(), Some(b"abc".to_vec()));
+        assert_eq!(core.stream.read(), Some(b"def".to_vec()));
+        assert_eq!(core.stream.bytes_received(), 6);
+        assert!(core.ack_pending);
+    }
+
+    #[test]
+    fn test_data_beyond_window_is_dropped() {
+        let byte_stream = ByteStream::new([1u8; 32], 18, None);
+        let window = StreamConfig::default().initial_window;
+
+        byte_stream.on_data(window, b"x");
+        assert!(byte_stream.lock().out_of_order.is_empty());
+    }
+
+    #[test]
+    fn test_fin_waits_for_missing_data() {
+        let byte_stream = ByteStream::new([1u8; 32], 18, None);
+
+        byte_stream.on_fin(4);
+        assert!(!byte_stream.lock().fin_received);
+        byte_stream.on_data(0, b"data");
+        assert!(byte_stream.lock().fin_received);
+
+        let step = byte_stream.step(Instant::now()).unwrap();
+        let update = Frame::parse(&step.frames[0]).unwrap();
+        let (ack, _, flags) = parse_window_update(update.payload()).unwrap();
+        assert_eq!(ack, 4);
+        assert_eq!(flags, WINDOW_FLAG_FIN);
+    }
+
+    #[test]
+    fn test_unacknowledged_data_is_retransmitted() {
+        let (open_tx, _open_rx) = oneshot::channel();
+        let byte_stream = ByteStream::new([1u8; 32], 17, Some(open_tx));
+        let start = Instant::now();
+        byte_stream.step(start).unwrap();
+        accept(&byte_stream);
+
+        let data = vec![7u8; MAX_SEGMENT_SIZE + 10];
+        byte_stream.lock().stream.write(data).unwrap();
+        let step = byte_stream.step(start).unwrap();
+        assert_eq!(step.frames.len(), 2);
+
+        // First segment acknowledged, second lost
+        byte_stream.on_window_update(MAX_SEGMENT_SIZE as u64, 1 << 20, 0);
+        let retry = byte_stream.step(Instant::now() + INITIAL_RTO).unwrap();
+        assert_eq!(retry.frames.len(), 1);
+        let frame = Frame::parse(&retry.frames[0]).unwrap();
+        assert_eq!(frame.offset(), MAX_SEGMENT_SIZE as u64);
+        assert_eq!(frame.payload().len(), 10);
+    }
+
+    #[test]
+    fn test_silent_peer_resets_stream() {
+        let (open_tx, open_rx) = oneshot::channel();
+        let byte_stream = ByteStream::new([1u8; 32], 17, Some(open_tx));
+        let mut now = Instant::now();
+        byte_stream.step(now).unwrap();
+
+        for _ in 0..=MAX_RETRANSMISSIONS {
+            now += MAX_RTO;
+            let step = byte_stream.step(now).unwrap();
+            if step.finished {
+                break;
+            }
+        }
+        assert!(byte_stream.lock().reset);
+        drop(byte_stream);
+        assert!(open_rx.blocking_recv().is_err());
+    }
+}
@@ -1,11 +1,14 @@
 //! Node configuration
 
+use crate::frame::compat::FormatNegotiation;
 use crate::node::circuit_breaker::CircuitBreakerConfig;
 use crate::node::health::HealthConfig;
 use crate::node::rate_limiter::RateLimitConfig;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
+use wraith_crypto::suite::CryptoSuite;
+use wraith_transport::factory::TransportFactoryConfig;
 
 // Note: The Node module provides configuration types for all subsystems.
 // Actual implementations require the respective crates as dependencies:
@@ -42,6 +45,21 @@ pub struct NodeConfig {
 
     /// Circuit breaker configuration
     pub circuit_breaker: CircuitBreakerConfig,
+
+    /// Handshake cryptography configuration
+    pub crypto: CryptoConfig,
+}
+
+impl NodeConfig {
+    /// Suites, wire formats and features this node offers in handshakes
+    pub fn handshake_policy(&self) -> crate::node::session::HandshakePolicy {
+        crate::node::session::HandshakePolicy {
+            suites: self.crypto.negotiable_suites(),
+            wire_formats: self.transport.wire_format,
+            capabilities: crate::node::session::Capabilities::SUPPORTED,
+            elligator: self.obfuscation.elligator_handshake,
+        }
+    }
 }
 
 impl Default for NodeConfig {
@@ -62,6 +80,84 @@ impl Default for NodeConfig {
             rate_limiting: RateLimitConfig::default(),
             health: HealthConfig::default(),
             circuit_breaker: CircuitBreakerConfig::default(),
+            crypto: CryptoConfig::default(),
+        }
+    }
+}
+
+/// Handshake cryptography configuration
+#[derive(Debug, Clone)]
+pub struct CryptoConfig {
+    /// Cipher suites this node is willing to negotiate
+    ///
+    /// The strongest suite both peers allow is selected. Suites the node
+    /// does not implement are ignored. Leaving out `SuiteD` refuses peers
+    /// that cannot complete the post-quantum hybrid exchange.
+    ///
+    /// Defaults to [`CryptoSuite::local_preference`], which lists Suite B
+    /// (AES-256-GCM) ahead of Suite A on CPUs with AES hardware.
+    pub suites: Vec<CryptoSuite>,
+
+    /// When established sessions replace their keys
+    pub rekey: RekeyConfig,
+}
+
+impl CryptoConfig {
+    /// Configured suites that this node implements, in configured order
+    ///
+    /// The order matters only between Suites A and B: listing B first
+    /// prefers AES-256-GCM whenever the peer does too.
+    pub fn negotiable_suites(&self) -> Vec<CryptoSuite> {
+        let mut suites = Vec::new();
+        for suite in &self.suites {
+            if crate::node::session::SUPPORTED_SUITES.contains(suite) && !suites.contains(suite) {
+                suites.push(*suite);
+            }
+        }
+        suites
+    }
+}
+
+impl Default for CryptoConfig {
+    fn default() -> Self {
+        Self {
+            suites: CryptoSuite::local_preference().to_vec(),
+            rekey: RekeyConfig::default(),
+        }
+    }
+}
+
+/// In-band session rekey configuration
+///
+/// An established session derives a fresh key generation from a new
+/// ephemeral Diffie-Hellman exchange once any limit below is reached;
+/// either peer may start the exchange. Traffic sealed under the previous
+/// generation is still accepted for `grace_period` after the peer switched.
+#[derive(Debug, Clone)]
+pub struct RekeyConfig {
+    /// Maximum lifetime of a key generation
+    pub interval: Duration,
+
+    /// Packets sent and received under one key generation
+    ///
+    /// Must stay below the AEAD nonce limit of one million packets per
+    /// direction, which otherwise stops the session.
+    pub packet_limit: u64,
+
+    /// Bytes sent and received under one key generation
+    pub byte_limit: u64,
+
+    /// How long the previous key generation keeps decrypting late packets
+    pub grace_period: Duration,
+}
+
+impl Default for RekeyConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(120),
+            packet_limit: 900_000,
+            byte_limit: 256 * 1024 * 1024,
+            grace_period: Duration::from_secs(10),
         }
     }
 }
@@ -86,6 +182,19 @@ pub struct TransportConfig {
 
     /// Idle timeout before closing sessions
     pub idle_timeout: Duration,
+
+    /// Frame wire formats offered during the handshake
+    ///
+    /// The strongest format both peers allow is used for the session. Use
+    /// [`FormatNegotiation::v1_only`] to talk only to peers that cannot
+    /// parse v2 frames.
+    pub wire_format: FormatNegotiation,
+
+    /// Transports run alongside the primary UDP socket (e.g. TCP, WebSocket)
+    ///
+    /// They are advertised to every peer after the handshake, so a session
+    /// can migrate to one of them when UDP is blocked.
+    pub additional_transports: Vec<TransportFactoryConfig>,
 }
 
 impl Default for TransportConfig {
@@ -97,6 +206,8 @@ impl Default for TransportConfig {
             worker_threads: None,             // Use all CPUs
             connection_timeout: Duration::from_secs(30),
             idle_timeout: Duration::from_secs(180), // 3 minutes
+            wire_format: FormatNegotiation::default(),
+            additional_transports: Vec::new(),
         }
     }
 }
@@ -115,6 +226,11 @@ pub struct ObfuscationConfig {
 
     /// Cover traffic configuration
     pub cover_traffic: CoverTrafficConfig,
+
+    /// Send handshake ephemeral keys as Elligator2 representatives, making
+    /// the handshakes this node initiates to peers whose key it knows
+    /// indistinguishable from random bytes
+    pub elligator_handshake: bool,
 }
 
 impl Default for ObfuscationConfig {
@@ -124,6 +240,7 @@ impl Default for ObfuscationConfig {
             timing_mode: TimingMode::None,
             mimicry_mode: MimicryMode::None,
             cover_traffic: CoverTrafficConfig::default(),
+            elligator_handshake: false,
         }
     }
 }
@@ -241,6 +358,12 @@ pub struct DiscoveryConfig {
     /// Relay servers
     pub relay_servers: Vec<SocketAddr>,
 
+    /// Ask the local gateway to forward the listen port (PCP, NAT-PMP or UPnP IGD)
+    pub enable_port_mapping: bool,
+
+    /// Announce and browse for peers on the local network over mDNS
+    pub enable_mdns: bool,
+
     /// DHT announcement interval
     pub announcement_interval: Duration,
 }
@@ -253,6 +376,8 @@ impl Default for DiscoveryConfig {
             enable_nat_traversal: true,
             enable_relay: true,
             relay_servers: Vec::new(),
+            enable_port_mapping: false,
+            enable_mdns: true,
             announcement_interval: Duration::from_secs(300), // 5 minutes
         }
     }
@@ -273,6 +398,10 @@ pub struct TransferConfig {
     /// Download directory
     pub download_dir: PathBuf,
 
+    /// Directory holding incoming files until they verify
+    /// (defaults to `.wraith-quarantine` inside the download directory)
+    pub quarantine_dir: Option<PathBuf>,
+
     /// Enable resume support
     pub enable_resume: bool,
 
@@ -284,6 +413,28 @@ pub struct TransferConfig {
 
     /// Chunk assignment strategy for multi-peer downloads
     pub chunk_assignment_strategy: crate::node::multi_peer::ChunkAssignmentStrategy,
+
+    /// How long a sender waits for the receiver to accept a transfer
+    pub acceptance_timeout: Duration,
+
+    /// How long a receiver waits for missing chunk hash segments before
+    /// asking the sender to re-send them
+    pub hash_layer_request_interval: Duration,
+
+    /// Forward error correction for file chunks
+    pub fec: FecConfig,
+
+    /// Compression of outgoing file chunks
+    pub compression: CompressionConfig,
+}
+
+impl TransferConfig {
+    /// Directory holding incoming `.part` files until they verify
+    pub fn quarantine_dir(&self) -> PathBuf {
+        self.quarantine_dir
+            .clone()
+            .unwrap_or_else(|| self.download_dir.join(".wraith-quarantine"))
+    }
 }
 
 impl Default for TransferConfig {
@@ -293,10 +444,78 @@ impl Default for TransferConfig {
             max_concurrent_transfers: 10,
             max_concurrent_chunks: 4,
             download_dir: PathBuf::from("."), // Default to current directory
+            quarantine_dir: None,
             enable_resume: true,
             enable_multi_peer: true,
             max_peers_per_transfer: 5,
             chunk_assignment_strategy: crate::node::multi_peer::ChunkAssignmentStrategy::default(),
+            acceptance_timeout: Duration::from_secs(120),
+            hash_layer_request_interval: Duration::from_secs(1),
+            fec: FecConfig::default(),
+            compression: CompressionConfig::default(),
+        }
+    }
+}
+
+/// Forward error correction configuration
+///
+/// When enabled, file chunks sent over v2 sessions are grouped and followed
+/// by `FecRepair` frames so the receiver can rebuild lost chunks without a
+/// retransmission. The number of repair frames per group follows the
+/// session's loss estimate between `min_repair` and `max_repair`.
+#[derive(Debug, Clone)]
+pub struct FecConfig {
+    /// Send repair frames alongside file chunks
+    pub enabled: bool,
+
+    /// Chunks per FEC group
+    pub group_size: usize,
+
+    /// Repair frames per group when no loss has been observed
+    pub min_repair: usize,
+
+    /// Maximum repair frames per group
+    pub max_repair: usize,
+}
+
+impl Default for FecConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            group_size: 16,
+            min_repair: 1,
+            max_repair: 8,
+        }
+    }
+}
+
+/// Chunk compression configuration
+///
+/// Outgoing chunks are compressed with zstd only when the peer negotiated
+/// compression support, the file's extension is not in `skip_extensions`
+/// and the compressed chunk is smaller than the original. Incoming
+/// compressed chunks are always accepted from peers that negotiated it.
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    /// Compress outgoing file chunks
+    pub enabled: bool,
+
+    /// zstd compression level
+    pub level: i32,
+
+    /// Extensions of files sent uncompressed (already compressed formats)
+    pub skip_extensions: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: crate::compression::DEFAULT_COMPRESSION_LEVEL,
+            skip_extensions: crate::compression::PRECOMPRESSED_EXTENSIONS
+                .iter()
+                .map(|ext| (*ext).to_string())
+                .collect(),
         }
     }
 }
@@ -1,14 +1,25 @@
 //! Connection lifecycle management
 //!
 //! Monitors connection health, handles session migration, and manages keepalives.
-
-use crate::node::session::PeerId;
+//!
+//! Sessions can migrate between addresses and between transports. Each node
+//! tells its peers which extra transports (TCP, WebSocket, QUIC) it listens on
+//! with a TRANSPORTS control frame; when a session stops answering over its
+//! current transport, the health check validates one of those paths with
+//! PATH_CHALLENGE/RESPONSE and moves the session there, keeping its keys and
+//! any in-flight transfers.
+
+use crate::FRAME_HEADER_SIZE;
+use crate::frame::{FrameBuilder, FrameType};
+use crate::node::file_transfer::CONTROL_TRANSPORTS;
+use crate::node::session::{PeerConnection, PeerId};
 use crate::node::{Node, NodeError};
 use std::borrow::Cow;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::interval;
-use wraith_transport::transport::Transport;
+use wraith_transport::factory::TransportType;
 
 /// Connection health status
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,6 +59,87 @@ pub struct HealthMetrics {
 /// Maximum consecutive failed pings before considering connection dead
 const MAX_FAILED_PINGS: u32 = 3;
 
+/// Wire code of a transport in a TRANSPORTS control frame
+///
+/// Only transports a remote peer can dial are advertised; io_uring and
+/// AF_XDP sockets speak plain UDP and are reached through the session's
+/// own address.
+fn transport_code(transport: TransportType) -> Option<u8> {
+    match transport {
+        TransportType::Udp => Some(0),
+        TransportType::Quic => Some(1),
+        TransportType::Tcp => Some(2),
+        TransportType::WebSocket => Some(3),
+        TransportType::IoUring | TransportType::AfXdp => None,
+    }
+}
+
+fn transport_from_code(code: u8) -> Option<TransportType> {
+    match code {
+        0 => Some(TransportType::Udp),
+        1 => Some(TransportType::Quic),
+        2 => Some(TransportType::Tcp),
+        3 => Some(TransportType::WebSocket),
+        _ => None,
+    }
+}
+
+/// Build a TRANSPORTS control frame advertising the given listening ports
+///
+/// Payload format: type(1) + count(1) + count * (transport(1) + port(2, BE)).
+/// Transports that cannot be advertised are skipped.
+pub fn build_transports_frame(transports: &[(TransportType, u16)]) -> Result<Vec<u8>, NodeError> {
+    let entries: Vec<(u8, u16)> = transports
+        .iter()
+        .filter_map(|&(transport, port)| Some((transport_code(transport)?, port)))
+        .take(usize::from(u8::MAX))
+        .collect();
+
+    let mut payload = Vec::with_capacity(2 + entries.len() * 3);
+    payload.push(CONTROL_TRANSPORTS);
+    payload.push(entries.len() as u8);
+    for (code, port) in entries {
+        payload.push(code);
+        payload.extend_from_slice(&port.to_be_bytes());
+    }
+
+    FrameBuilder::new()
+        .frame_type(FrameType::Control)
+        .stream_id(0)
+        .sequence(0)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| {
+            NodeError::InvalidState(format!("Failed to build transports frame: {e}").into())
+        })
+}
+
+/// Parse a TRANSPORTS control payload into dialable peer addresses
+///
+/// The advertised ports are combined with `peer_ip`, the address the
+/// session's packets come from. Unknown transport codes are ignored.
+pub fn parse_transports(
+    payload: &[u8],
+    peer_ip: IpAddr,
+) -> Result<Vec<(TransportType, SocketAddr)>, NodeError> {
+    if payload.len() < 2 || payload[0] != CONTROL_TRANSPORTS {
+        return Err(NodeError::invalid_state("Malformed transports frame"));
+    }
+    let count = usize::from(payload[1]);
+    let entries = payload[2..]
+        .get(..count * 3)
+        .ok_or_else(|| NodeError::invalid_state("Truncated transports frame"))?;
+
+    Ok(entries
+        .chunks_exact(3)
+        .filter_map(|entry| {
+            let transport = transport_from_code(entry[0])?;
+            let port = u16::from_be_bytes([entry[1], entry[2]]);
+            Some((transport, SocketAddr::new(peer_ip, port)))
+        })
+        .collect())
+}
+
 impl Node {
     /// Start the connection manager background task
     ///
@@ -106,11 +198,14 @@ impl Node {
         for (peer_id, session) in sessions {
             if session.is_stale(idle_timeout) {
                 // Send ping to check if connection is alive
-                match self.ping_session(&peer_id, session).await {
+                match self.ping_session(&peer_id, Arc::clone(&session)).await {
                     Ok(latency) => {
                         tracing::trace!("Ping to {:?}: {} µs", peer_id, latency.as_micros());
                     }
                     Err(e) => {
+                        if self.fail_over_session(&peer_id, &session).await {
+                            continue;
+                        }
                         // Connection is dead, remove it
                         tracing::info!("Removing dead session for peer {:?}: {}", peer_id, e);
                         self.inner.sessions.remove(&peer_id);
@@ -122,6 +217,55 @@ impl Node {
         Ok(())
     }
 
+    /// Move an unresponsive session to another transport the peer advertised
+    ///
+    /// Tries each advertised path other than the current one in turn.
+    /// Returns `true` once a path has been validated and the session moved.
+    async fn fail_over_session(&self, peer_id: &PeerId, session: &PeerConnection) -> bool {
+        let current = session.transport_type();
+        for (transport, addr) in session.peer_transports() {
+            if transport == current {
+                continue;
+            }
+            match self
+                .migrate_session_to_transport(peer_id, transport, addr)
+                .await
+            {
+                Ok(()) => {
+                    tracing::info!(
+                        "Session with peer {:?} failed over from {} to {} at {}",
+                        peer_id,
+                        current,
+                        transport,
+                        addr
+                    );
+                    session.reset_failed_pings();
+                    return true;
+                }
+                Err(e) => {
+                    tracing::debug!("Fail-over to {} at {} failed: {}", transport, addr, e);
+                }
+            }
+        }
+        false
+    }
+
+    /// Ping a connected peer and return the round-trip time
+    ///
+    /// # Errors
+    ///
+    /// Returns `NodeError::SessionNotFound` if there is no session with the
+    /// peer, or an error if no PONG arrives within 5 seconds.
+    pub async fn ping(&self, peer_id: &PeerId) -> Result<Duration, NodeError> {
+        let session = self
+            .inner
+            .sessions
+            .get(peer_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(NodeError::SessionNotFound(*peer_id))?;
+        self.ping_session(peer_id, session).await
+    }
+
     /// Send ping to a session and measure latency
     ///
     /// Sends a PING frame and waits for the corresponding PONG response.
@@ -131,8 +275,6 @@ impl Node {
         peer_id: &PeerId,
         session: std::sync::Arc<crate::node::session::PeerConnection>,
     ) -> Result<Duration, NodeError> {
-        use crate::frame::{FrameBuilder, FrameType};
-
         let start = std::time::Instant::now();
 
         // Build PING frame with a unique sequence number for matching PONG
@@ -152,25 +294,21 @@ impl Node {
         self.inner.pending_pings.insert((*peer_id, sequence), tx);
 
         // Encrypt frame
-        let encrypted = session.encrypt_frame(&frame).await.inspect_err(|_| {
+        let encrypted = session.seal_frame(&frame).await.inspect_err(|_| {
             self.inner.pending_pings.remove(&(*peer_id, sequence));
         })?;
 
-        // Send via transport
-        let transport_guard = self.inner.transport.lock().await;
-        if let Some(transport) = transport_guard.as_ref() {
-            transport
-                .send_to(&encrypted, session.peer_addr())
-                .await
-                .map_err(|e| {
-                    self.inner.pending_pings.remove(&(*peer_id, sequence));
-                    NodeError::Transport(format!("Failed to send PING: {e}").into())
-                })?;
-        } else {
+        // Send via the session's transport
+        let transport = self.transport_for(&session).await.inspect_err(|_| {
             self.inner.pending_pings.remove(&(*peer_id, sequence));
-            return Err(NodeError::Transport("Transport not initialized".into()));
-        }
-        drop(transport_guard);
+        })?;
+        transport
+            .send_to(&encrypted, session.peer_addr())
+            .await
+            .map_err(|e| {
+                self.inner.pending_pings.remove(&(*peer_id, sequence));
+                NodeError::Transport(format!("Failed to send PING: {e}").into())
+            })?;
 
         // Wait for PONG response with timeout
         let ping_timeout = Duration::from_secs(5);
@@ -235,13 +373,47 @@ impl Node {
         peer_id: &PeerId,
         new_addr: SocketAddr,
     ) -> Result<(), NodeError> {
-        use crate::frame::{FrameBuilder, FrameType};
+        let transport = self
+            .inner
+            .sessions
+            .get(peer_id)
+            .ok_or(NodeError::SessionNotFound(*peer_id))?
+            .transport_type();
+        self.migrate_session_to_transport(peer_id, transport, new_addr)
+            .await
+    }
+
+    /// Migrate a session to a new transport and address
+    ///
+    /// Validates the new path with PATH_CHALLENGE/RESPONSE sent over
+    /// `transport`, then moves the session there. The session keeps its
+    /// keys, streams, and transfers; the peer switches its side of the path
+    /// when it answers the challenge. Used when UDP is blocked mid-session
+    /// and the peer also listens on TCP or WebSocket.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer_id` - The peer whose session to migrate
+    /// * `transport` - The local transport to use for the session
+    /// * `new_addr` - The peer's address on that transport
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the session is not found, the node has no transport
+    /// of the requested type, or the peer does not answer over the new path.
+    pub async fn migrate_session_to_transport(
+        &self,
+        peer_id: &PeerId,
+        transport_type: TransportType,
+        new_addr: SocketAddr,
+    ) -> Result<(), NodeError> {
         use crate::migration::PathValidator;
         use crate::node::node::MigrationState;
 
         tracing::info!(
-            "Migrating session for peer {:?} to new address {}",
+            "Migrating session for peer {:?} to {} address {}",
             peer_id,
+            transport_type,
             new_addr
         );
 
@@ -253,14 +425,24 @@ impl Node {
             .ok_or(NodeError::SessionNotFound(*peer_id))?;
         let session = session.clone();
 
+        let transport = self
+            .get_transport()
+            .await?
+            .transport(transport_type)
+            .await
+            .ok_or_else(|| {
+                NodeError::Migration(format!("No {transport_type} transport available").into())
+            })?;
+
         // Create path validator
         let mut path_validator = PathValidator::new(Duration::from_secs(3));
 
-        // Generate path ID from new address (simple hash)
+        // Generate path ID from new transport and address (simple hash)
         let path_id = {
             use std::collections::hash_map::DefaultHasher;
             use std::hash::{Hash, Hasher};
             let mut hasher = DefaultHasher::new();
+            transport_type.hash(&mut hasher);
             new_addr.hash(&mut hasher);
             hasher.finish()
         };
@@ -275,6 +457,7 @@ impl Node {
         let migration_state = MigrationState {
             peer_id: *peer_id,
             new_addr,
+            transport: transport_type,
             challenge,
             sender: response_tx,
             initiated_at: std::time::Instant::now(),
@@ -295,19 +478,12 @@ impl Node {
             })?;
 
         // Encrypt and send to new address
-        let encrypted = session.encrypt_frame(&frame).await?;
+        let encrypted = session.seal_frame(&frame).await?;
 
-        let transport_guard = self.inner.transport.lock().await;
-        if let Some(transport) = transport_guard.as_ref() {
-            transport.send_to(&encrypted, new_addr).await.map_err(|e| {
-                self.inner.pending_migrations.remove(&path_id);
-                NodeError::Migration(format!("Failed to send PATH_CHALLENGE: {e}").into())
-            })?;
-        } else {
+        transport.send_to(&encrypted, new_addr).await.map_err(|e| {
             self.inner.pending_migrations.remove(&path_id);
-            return Err(NodeError::Migration("Transport not initialized".into()));
-        }
-        drop(transport_guard);
+            NodeError::Migration(format!("Failed to send PATH_CHALLENGE: {e}").into())
+        })?;
 
         tracing::debug!(
             "PATH_CHALLENGE sent to {}, awaiting PATH_RESPONSE",
@@ -318,13 +494,14 @@ impl Node {
         let timeout = Duration::from_secs(5);
         match tokio::time::timeout(timeout, response_rx).await {
             Ok(Ok(Ok(latency))) => {
-                // Migration successful - update session peer address
-                session.update_peer_addr(new_addr);
+                // Migration successful - move the session to the new path
+                session.update_path(transport_type, new_addr);
                 session.touch(); // Update last activity
 
                 tracing::info!(
-                    "Migration to {} verified with {}µs RTT",
+                    "Migration to {} over {} verified with {}µs RTT",
                     new_addr,
+                    transport_type,
                     latency.as_micros()
                 );
                 Ok(())
@@ -734,4 +911,102 @@ mod tests {
         let health = node.get_all_connection_health().await;
         assert_eq!(health.len(), 2);
     }
+
+    #[test]
+    fn test_transports_frame_round_trip() {
+        use crate::frame::Frame;
+
+        let frame = build_transports_frame(&[
+            (TransportType::Tcp, 9000),
+            (TransportType::AfXdp, 9001),
+            (TransportType::WebSocket, 9002),
+        ])
+        .unwrap();
+        let frame = Frame::parse(&frame).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Control);
+
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        let transports = parse_transports(frame.payload(), ip).unwrap();
+        assert_eq!(
+            transports,
+            vec![
+                (TransportType::Tcp, SocketAddr::new(ip, 9000)),
+                (TransportType::WebSocket, SocketAddr::new(ip, 9002)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_transports_rejects_malformed() {
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+        assert!(parse_transports(&[], ip).is_err());
+        assert!(parse_transports(&[0x04, 0], ip).is_err());
+        assert!(parse_transports(&[CONTROL_TRANSPORTS, 2, 2, 0x23, 0x28], ip).is_err());
+        // Unknown transport codes are skipped
+        assert_eq!(
+            parse_transports(&[CONTROL_TRANSPORTS, 1, 0x7F, 0x23, 0x28], ip).unwrap(),
+            vec![]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_to_advertised_transport() {
+        use crate::node::NodeConfig;
+        use wraith_transport::factory::TransportFactoryConfig;
+
+        let mut config = NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            ..NodeConfig::default()
+        };
+        config.transport.additional_transports =
+            vec![TransportFactoryConfig::tcp("127.0.0.1:0".parse().unwrap())];
+        let node1 = Node::new_with_config(config.clone()).await.unwrap();
+        let node2 = Node::new_with_config(config).await.unwrap();
+        node1.start().await.unwrap();
+        node2.start().await.unwrap();
+
+        let node2_addr = node2.listen_addr().await.unwrap();
+        node1
+            .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let peer_id = *node2.x25519_public_key();
+        let session = Arc::clone(node1.inner.sessions.get(&peer_id).unwrap().value());
+        let tcp_addr = node2
+            .transport_addrs()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|(transport, _)| *transport == TransportType::Tcp)
+            .unwrap()
+            .1;
+        assert_eq!(
+            session.peer_transports(),
+            vec![(TransportType::Tcp, tcp_addr)]
+        );
+
+        assert!(node1.fail_over_session(&peer_id, &session).await);
+        assert_eq!(session.transport_type(), TransportType::Tcp);
+        assert_eq!(session.peer_addr(), tcp_addr);
+        assert_eq!(
+            node2.get_session_transport(node1.x25519_public_key()),
+            Some(TransportType::Tcp)
+        );
+
+        // The session keeps working over TCP
+        node1.ping_session(&peer_id, session).await.unwrap();
+
+        node1.stop().await.unwrap();
+        node2.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fail_over_without_advertised_transports() {
+        let node = Node::new_random().await.unwrap();
+        let peer_id = [42u8; 32];
+        let session = PeerConnection::new_for_test(peer_id, "127.0.0.1:8420".parse().unwrap());
+        assert!(!node.fail_over_session(&peer_id, &session).await);
+    }
 }
@@ -262,6 +262,41 @@ impl Node {
         Ok(peers)
     }
 
+    /// Peers announced on the local network over mDNS
+    ///
+    /// The returned peer ID is the peer's handshake key when it publishes one,
+    /// so it can be passed straight to `establish_session`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if discovery is not initialized.
+    pub async fn lan_peers(&self) -> Result<Vec<PeerInfo>, NodeError> {
+        let discovery = {
+            let guard = self.inner.discovery.lock().await;
+            guard
+                .as_ref()
+                .ok_or(NodeError::Discovery(std::borrow::Cow::Borrowed(
+                    "Discovery not initialized",
+                )))?
+                .clone()
+        };
+
+        let peers = discovery
+            .lan_peers()
+            .await
+            .into_iter()
+            .map(|peer| PeerInfo {
+                peer_id: peer.peer_key.unwrap_or(*peer.node_id.as_bytes()),
+                addresses: peer.addrs,
+                nat_type: NatType::None,
+                capabilities: NodeCapabilities::default(),
+                last_seen: SystemTime::now(),
+            })
+            .collect();
+
+        Ok(peers)
+    }
+
     /// Bootstrap from known nodes
     ///
     /// Connects to bootstrap nodes to join the DHT network.
@@ -466,6 +501,43 @@ mod tests {
         assert!(caps.multi_peer); // Default from TransferConfig
     }
 
+    #[tokio::test]
+    async fn test_lan_peers_over_mdns() {
+        let config = || crate::node::NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            discovery: crate::node::DiscoveryConfig {
+                enable_nat_traversal: false,
+                enable_relay: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let a = Node::new_with_config(config()).await.unwrap();
+        let b = Node::new_with_config(config()).await.unwrap();
+        a.start().await.unwrap();
+        b.start().await.unwrap();
+        let b_key = *b.x25519_public_key();
+        let b_addr = b.listen_addr().await.unwrap();
+
+        let mut found = None;
+        for _ in 0..50 {
+            let peers = a.lan_peers().await.unwrap();
+            found = peers.into_iter().find(|peer| peer.peer_id == b_key);
+            if found.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        let found = found.expect("peer announced over mDNS");
+        assert_eq!(found.addresses, vec![b_addr]);
+
+        // Discovery resolves the handshake key to the announced address
+        assert_eq!(a.discover_peer(&b_key).await.unwrap(), vec![b_addr]);
+
+        a.stop().await.unwrap();
+        b.stop().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_bootstrap_empty_list() {
         let node = Node::new_random().await.unwrap();
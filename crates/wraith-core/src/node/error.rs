@@ -49,6 +49,19 @@ pub enum NodeError {
     #[error("Handshake failed: {0}")]
     Handshake(Cow<'static, str>),
 
+    /// Peer presented a different static key than expected
+    #[error(
+        "Peer key mismatch: expected {}, presented {}",
+        hex::encode(expected),
+        hex::encode(presented)
+    )]
+    PeerKeyMismatch {
+        /// Key the peer was expected to present
+        expected: [u8; 32],
+        /// Key the peer actually presented
+        presented: [u8; 32],
+    },
+
     // ============ Session Errors ============
     /// Session establishment failed
     #[error("Session establishment failed: {0}")]
@@ -75,6 +88,11 @@ pub enum NodeError {
     #[error("Hash mismatch: integrity verification failed")]
     HashMismatch,
 
+    // ============ Stream Errors ============
+    /// Byte stream refused, reset or timed out
+    #[error("Stream error: {0}")]
+    Stream(Cow<'static, str>),
+
     // ============ I/O Errors ============
     /// File I/O error
     #[error("File I/O error: {0}")]
@@ -159,6 +177,7 @@ impl NodeError {
     /// - Invalid configuration
     /// - Session/transfer not found
     /// - Hash mismatches (data corruption)
+    /// - Peers presenting an unexpected key
     /// - Cryptographic failures
     #[must_use]
     pub fn is_permanent(&self) -> bool {
@@ -169,6 +188,7 @@ impl NodeError {
                 | NodeError::TransferNotFound(_)
                 | NodeError::PeerNotFound(_)
                 | NodeError::HashMismatch
+                | NodeError::PeerKeyMismatch { .. }
                 | NodeError::InvalidState(_)
         )
     }
@@ -251,6 +271,13 @@ mod tests {
         assert!(NodeError::TransferNotFound([0u8; 32]).is_permanent());
         assert!(NodeError::PeerNotFound([0u8; 32]).is_permanent());
         assert!(NodeError::HashMismatch.is_permanent());
+        assert!(
+            NodeError::PeerKeyMismatch {
+                expected: [1u8; 32],
+                presented: [2u8; 32],
+            }
+            .is_permanent()
+        );
         assert!(NodeError::InvalidState(Cow::Borrowed("test")).is_permanent());
     }
 
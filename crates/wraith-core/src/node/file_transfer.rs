@@ -3,18 +3,90 @@
 //! This module provides helpers for coordinating file transfers between nodes:
 //! - Metadata message serialization/deserialization
 //! - Chunk-to-frame conversion
+//! - Chunk hash layer distribution and per-chunk verification
+//! - Sanitization of sender-supplied file names and quarantine of partial files
 //! - Progress tracking integration
 
 use crate::FRAME_HEADER_SIZE;
-use crate::frame::{FrameBuilder, FrameType};
+use crate::frame::{FrameBuilder, FrameFlags, FrameType};
 use crate::node::error::{NodeError, Result};
+use crate::node::multi_peer::MultiPeerCoordinator;
+use crate::node::session::PeerId;
+use crate::node::tree_transfer::{TreeMember, TreeStaging};
 use crate::transfer::session::TransferSession;
-use std::path::Path;
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use tokio::sync::{Mutex, RwLock};
 use wraith_files::chunker::FileReassembler;
 use wraith_files::tree_hash::FileTreeHash;
 
+/// Control frame request type: file metadata request
+pub const CONTROL_METADATA_REQUEST: u8 = 0x01;
+
+/// Control frame request type: (re)transmission request for a single chunk
+pub const CONTROL_CHUNK_REQUEST: u8 = 0x02;
+
+/// Control frame type: segment of the chunk hash layer
+pub const CONTROL_CHUNK_HASHES: u8 = 0x03;
+
+/// Control frame type: receiver accepted the transfer, sender may stream chunks
+pub const CONTROL_TRANSFER_ACCEPT: u8 = 0x04;
+
+/// Control frame type: transports the sender listens on besides the session's own
+pub const CONTROL_TRANSPORTS: u8 = 0x05;
+
+/// Control frame request type: re-send the chunk hash layer from a chunk index on
+pub const CONTROL_CHUNK_HASHES_REQUEST: u8 = 0x06;
+
+/// STREAM_OPEN flag: CHUNK_HASHES segments follow the metadata
+///
+/// Carried in a byte after the serialized [`FileMetadata`], which baseline
+/// peers ignore. Without it the receiver has no chunk hash layer to wait for
+/// and checks the file against its root once every chunk has landed.
+pub const METADATA_FLAG_CHUNK_HASHES: u8 = 0x01;
+
+/// Maximum length in bytes of a rejection reason carried in STREAM_RESET
+pub const MAX_REJECT_REASON_LEN: usize = 255;
+
+/// Maximum number of chunk hashes carried in a single CHUNK_HASHES frame
+///
+/// 32 hashes * 32 bytes + 9 bytes of header keeps a sealed segment within the
+/// 1280-byte IPv6 minimum MTU, so the hash layer never depends on IP
+/// fragmentation to arrive.
+pub const CHUNK_HASHES_PER_FRAME: usize = 32;
+
+/// Maximum number of chunks buffered while the chunk hash layer is incomplete
+const MAX_DEFERRED_CHUNKS: usize = 64;
+
+/// Maximum number of CHUNK_HASHES requests sent before a receiver gives up
+///
+/// Each request re-sends every segment from the first missing one, so a
+/// sender that answers none of them is not going to complete the transfer.
+pub const MAX_HASH_LAYER_REQUESTS: u32 = 5;
+
+/// Maximum number of chunks accepted for a single incoming transfer
+///
+/// Bounds the memory used by the receiver's chunk hash layer (4M chunks is
+/// 1 TiB at the default 256 KiB chunk size).
+pub const MAX_TRANSFER_CHUNKS: u64 = 1 << 22;
+
+/// Stream IDs below this are connection-level (0) or reserved (1-15)
+pub(crate) const FIRST_TRANSFER_STREAM_ID: u16 = 16;
+
+/// Stream ID carrying a transfer's frames
+///
+/// Derived from the first two bytes of the transfer ID. IDs that land in the
+/// reserved range are shifted up so every transfer gets a usable stream.
+pub fn transfer_stream_id(transfer_id: &[u8; 32]) -> u16 {
+    let stream_id = u16::from_be_bytes([transfer_id[0], transfer_id[1]]);
+    if stream_id < FIRST_TRANSFER_STREAM_ID {
+        stream_id + FIRST_TRANSFER_STREAM_ID
+    } else {
+        stream_id
+    }
+}
+
 /// File transfer context consolidating all per-transfer state
 ///
 /// This struct combines the transfer session, file reassembler (for receives),
@@ -33,6 +105,27 @@ pub struct FileTransferContext {
 
     /// Tree hash for integrity verification
     pub tree_hash: FileTreeHash,
+
+    /// Chunk hash layer received from the sender (receive transfers only)
+    pub chunk_hashes: Option<Arc<Mutex<ChunkHashLayer>>>,
+
+    /// Peer pushing this transfer to us (pushed receive transfers only)
+    pub sender: Option<PeerId>,
+
+    /// Peer tracker used to record peers that deliver corrupt chunks
+    pub coordinator: Option<Arc<MultiPeerCoordinator>>,
+
+    /// Quarantined `.part` file and final destination (receive transfers only)
+    pub quarantine: Option<QuarantinedFile>,
+
+    /// Tree transfer this file belongs to (files of directory transfers only)
+    pub tree_member: Option<TreeMember>,
+
+    /// Staging directory of an incoming directory transfer (tree receive transfers only)
+    pub tree_staging: Option<Arc<TreeStaging>>,
+
+    /// Reason given by the receiver for rejecting this transfer (send transfers only)
+    rejection: OnceLock<String>,
 }
 
 impl FileTransferContext {
@@ -47,6 +140,13 @@ impl FileTransferContext {
             transfer_session,
             reassembler: None,
             tree_hash,
+            chunk_hashes: None,
+            sender: None,
+            coordinator: None,
+            quarantine: None,
+            tree_member: None,
+            tree_staging: None,
+            rejection: OnceLock::new(),
         }
     }
 
@@ -62,8 +162,408 @@ impl FileTransferContext {
             transfer_session,
             reassembler: Some(reassembler),
             tree_hash,
+            chunk_hashes: None,
+            sender: None,
+            coordinator: None,
+            quarantine: None,
+            tree_member: None,
+            tree_staging: None,
+            rejection: OnceLock::new(),
         }
     }
+
+    /// Attach a chunk hash layer used to verify chunks as they arrive
+    pub fn with_chunk_hashes(mut self, layer: ChunkHashLayer) -> Self {
+        self.chunk_hashes = Some(Arc::new(Mutex::new(layer)));
+        self
+    }
+
+    /// Accept chunks and hash segments for this transfer from `peer_id` only
+    pub fn with_sender(mut self, peer_id: PeerId) -> Self {
+        self.sender = Some(peer_id);
+        self
+    }
+
+    /// Attach a peer coordinator used to track misbehaving peers
+    pub fn with_coordinator(mut self, coordinator: Arc<MultiPeerCoordinator>) -> Self {
+        self.coordinator = Some(coordinator);
+        self
+    }
+
+    /// Write the file to a quarantine `.part` file until it verifies
+    pub fn with_quarantine(mut self, quarantine: QuarantinedFile) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    /// Mark this transfer as one file of a directory transfer
+    pub fn with_tree_member(mut self, member: TreeMember) -> Self {
+        self.tree_member = Some(member);
+        self
+    }
+
+    /// Assemble an incoming directory transfer in the given staging directory
+    pub fn with_tree_staging(mut self, staging: Arc<TreeStaging>) -> Self {
+        self.tree_staging = Some(staging);
+        self
+    }
+
+    /// Reason the receiver gave for rejecting this transfer, if it was rejected
+    pub fn rejection_reason(&self) -> Option<&str> {
+        self.rejection.get().map(String::as_str)
+    }
+
+    /// Record the receiver's rejection reason (the first reason wins)
+    pub(crate) fn set_rejected(&self, reason: String) {
+        let _ = self.rejection.set(reason);
+    }
+}
+
+/// Result of verifying a received chunk against the chunk hash layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkVerdict {
+    /// Chunk matches its hash in the verified layer
+    Valid,
+    /// Chunk does not match its hash (or its index is out of range)
+    Corrupt,
+    /// Chunk hash layer has not been fully received and verified yet
+    Unverified,
+}
+
+/// Receiver-side chunk hash layer
+///
+/// The sender ships the leaf layer of the file's Merkle tree in one or more
+/// CHUNK_HASHES control frames after the StreamOpen metadata. Segments are
+/// collected here until the layer is complete, at which point it is checked
+/// against the root hash from the metadata. Only a layer whose Merkle root
+/// matches is ever used to verify chunk data.
+///
+/// Chunks that arrive before the layer is verified are buffered (up to a
+/// small bound) and verified once the layer completes.
+///
+/// Hashes are stored as segments arrive, so the memory held for a transfer
+/// grows with the hashes its sender has actually sent rather than with the
+/// chunk count announced in STREAM_OPEN.
+pub struct ChunkHashLayer {
+    /// Expected Merkle root (from StreamOpen metadata)
+    root: [u8; 32],
+    /// Number of chunks in the file
+    total_chunks: u64,
+    /// Chunk hashes received so far, by chunk index
+    hashes: BTreeMap<u64, [u8; 32]>,
+    /// Verified tree hash (set once the layer matches the root)
+    verified: Option<FileTreeHash>,
+    /// Chunks received before the layer was verified
+    deferred: Vec<(u64, Vec<u8>)>,
+    /// Chunks dropped because the deferred buffer was full
+    dropped: BTreeSet<u64>,
+    /// Whether missing segments are being requested from the sender
+    recovering: bool,
+}
+
+impl ChunkHashLayer {
+    /// Create an empty layer for a file with `total_chunks` chunks
+    pub fn new(root: [u8; 32], total_chunks: u64) -> Self {
+        Self {
+            root,
+            total_chunks,
+            hashes: BTreeMap::new(),
+            verified: None,
+            deferred: Vec::new(),
+            dropped: BTreeSet::new(),
+            recovering: false,
+        }
+    }
+
+    /// Check if the layer has been received and verified against the root
+    pub fn is_verified(&self) -> bool {
+        self.verified.is_some()
+    }
+
+    /// Index of the first chunk whose hash has not been received
+    pub fn first_missing(&self) -> u64 {
+        (0..)
+            .zip(self.hashes.keys())
+            .find(|(expected, index)| expected != *index)
+            .map_or(self.hashes.len() as u64, |(expected, _)| expected)
+    }
+
+    /// Mark the layer as being recovered from the sender
+    ///
+    /// Returns `true` only for the first call on an unverified layer, so a
+    /// single recovery task runs per transfer.
+    pub fn begin_recovery(&mut self) -> bool {
+        if self.verified.is_some() || self.recovering {
+            return false;
+        }
+        self.recovering = true;
+        true
+    }
+
+    /// Insert a segment of chunk hashes starting at `start_index`
+    ///
+    /// Returns `Ok(true)` when this segment completed the layer and the
+    /// layer verified against the root.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NodeError::InvalidState`] if the segment lies outside the
+    /// file, or [`NodeError::HashMismatch`] if the completed layer does not
+    /// match the root. On mismatch the collected hashes are discarded so the
+    /// layer can be re-sent.
+    pub fn insert_segment(&mut self, start_index: u64, hashes: &[[u8; 32]]) -> Result<bool> {
+        if self.verified.is_some() {
+            return Ok(false);
+        }
+
+        start_index
+            .checked_add(hashes.len() as u64)
+            .filter(|end| *end <= self.total_chunks)
+            .ok_or_else(|| NodeError::invalid_state("Chunk hash segment out of range"))?;
+
+        self.hashes
+            .extend((start_index..).zip(hashes.iter().copied()));
+
+        if (self.hashes.len() as u64) < self.total_chunks {
+            return Ok(false);
+        }
+
+        let chunks: Vec<[u8; 32]> = std::mem::take(&mut self.hashes).into_values().collect();
+        let tree = FileTreeHash::new(self.root, chunks);
+        if !tree.verify_root() {
+            return Err(NodeError::HashMismatch);
+        }
+
+        self.verified = Some(tree);
+        Ok(true)
+    }
+
+    /// Verify chunk data against the layer
+    pub fn verify_chunk(&self, chunk_index: u64, chunk_data: &[u8]) -> ChunkVerdict {
+        match &self.verified {
+            Some(tree) if tree.verify_chunk(chunk_index as usize, chunk_data) => {
+                ChunkVerdict::Valid
+            }
+            Some(_) => ChunkVerdict::Corrupt,
+            None => ChunkVerdict::Unverified,
+        }
+    }
+
+    /// Buffer a chunk until the layer is verified
+    ///
+    /// Returns `false` if the deferred buffer is full and the chunk was not
+    /// kept. Dropped chunks are remembered so they can be re-requested once
+    /// the layer has verified.
+    pub fn defer_chunk(&mut self, chunk_index: u64, chunk_data: &[u8]) -> bool {
+        if self.deferred.len() >= MAX_DEFERRED_CHUNKS {
+            if chunk_index < self.total_chunks {
+                self.dropped.insert(chunk_index);
+            }
+            return false;
+        }
+        self.deferred.push((chunk_index, chunk_data.to_vec()));
+        true
+    }
+
+    /// Take all chunks buffered while the layer was incomplete
+    pub fn take_deferred(&mut self) -> Vec<(u64, Vec<u8>)> {
+        std::mem::take(&mut self.deferred)
+    }
+
+    /// Take the indices of chunks dropped while the layer was incomplete
+    pub fn take_dropped(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.dropped).into_iter().collect()
+    }
+}
+
+/// Maximum length in bytes of a file name written to disk
+pub const MAX_FILE_NAME_LEN: usize = 255;
+
+/// Suffix of in-progress files in the quarantine directory
+pub const PART_FILE_SUFFIX: &str = ".part";
+
+/// Name used when a sender-supplied file name sanitizes to nothing
+const FALLBACK_FILE_NAME: &str = "received_file";
+
+/// Maximum `name (n).ext` suffix tried when resolving name collisions
+const MAX_COLLISION_SUFFIX: u32 = 10_000;
+
+/// Windows device names that cannot be used as file names (with any extension)
+const RESERVED_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Reduce a sender-supplied file name to a single safe path component
+///
+/// The remote peer controls `FileMetadata::file_name`, so it must never be
+/// joined onto a local path as-is. This:
+/// - keeps only the final component (both `/` and `\` are separators)
+/// - replaces control, bidi-override and Windows-reserved characters with `_`
+/// - strips leading dots and spaces (no hidden files, no `.`/`..`) and
+///   trailing dots and spaces (ignored by Windows)
+/// - prefixes Windows device names (`CON`, `NUL.txt`, `com1`, ...) with `_`
+/// - truncates to [`MAX_FILE_NAME_LEN`] bytes, keeping the extension
+///
+/// Names that sanitize to nothing become `received_file`.
+pub fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let cleaned: String = base
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = cleaned
+        .trim_start_matches(['.', ' '])
+        .trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return FALLBACK_FILE_NAME.to_string();
+    }
+
+    let device_stem = trimmed.split('.').next().unwrap_or_default().trim_end();
+    let name = if RESERVED_DEVICE_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device_stem))
+    {
+        format!("_{trimmed}")
+    } else {
+        trimmed.to_string()
+    };
+
+    with_suffix(&name, "")
+}
+
+/// Insert `suffix` before the extension, truncating the stem to fit [`MAX_FILE_NAME_LEN`]
+fn with_suffix(name: &str, suffix: &str) -> String {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    let extension = extension
+        .filter(|ext| ext.len() + suffix.len() + 2 <= MAX_FILE_NAME_LEN)
+        .map(|ext| format!(".{ext}"))
+        .unwrap_or_default();
+    let stem = if extension.is_empty() { name } else { stem };
+
+    let mut stem_len = MAX_FILE_NAME_LEN
+        .saturating_sub(extension.len() + suffix.len())
+        .min(stem.len());
+    while !stem.is_char_boundary(stem_len) {
+        stem_len -= 1;
+    }
+
+    format!("{}{suffix}{extension}", &stem[..stem_len])
+}
+
+/// Candidate destinations for `file_name` in `dir`: `name.ext`, `name (1).ext`, ...
+pub(crate) fn destination_candidates<'a>(
+    dir: &'a Path,
+    file_name: &'a str,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    std::iter::once(dir.join(file_name)).chain(
+        (1..=MAX_COLLISION_SUFFIX)
+            .map(move |n| dir.join(with_suffix(file_name, &format!(" ({n})")))),
+    )
+}
+
+/// Pick a path in `dir` for `file_name` that does not collide with an existing file
+///
+/// `file_name` should already be sanitized with [`sanitize_file_name`].
+pub fn unique_destination(dir: &Path, file_name: &str) -> Result<PathBuf> {
+    destination_candidates(dir, file_name)
+        .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+        .ok_or_else(|| NodeError::invalid_state("Too many files with the same name"))
+}
+
+/// An incoming file held in the quarantine directory until it verifies
+///
+/// Chunks are written to `<quarantine>/<transfer id>.part`. Only once every
+/// chunk has arrived and the file's Merkle root matches the announced root is
+/// it moved to its destination; a file that fails verification never appears
+/// in the download directory.
+#[derive(Debug, Clone)]
+pub struct QuarantinedFile {
+    /// In-progress file inside the quarantine directory
+    pub part_path: PathBuf,
+    /// Where the file is moved once verified (a free `name (n).ext` is used
+    /// if this path is taken by then)
+    pub destination: PathBuf,
+}
+
+impl QuarantinedFile {
+    /// Create the quarantine directory (if needed) and name the `.part` file
+    pub fn new(
+        quarantine_dir: &Path,
+        transfer_id: &[u8; 32],
+        destination: PathBuf,
+    ) -> Result<Self> {
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(quarantine_dir)
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+
+        Ok(Self {
+            part_path: quarantine_dir
+                .join(format!("{}{PART_FILE_SUFFIX}", hex::encode(transfer_id))),
+            destination,
+        })
+    }
+
+    /// Verify the completed `.part` file against `root` and move it into place
+    ///
+    /// Returns the final path. On a root mismatch the `.part` file is deleted
+    /// and [`NodeError::HashMismatch`] is returned.
+    pub fn commit(&self, root: &[u8; 32], chunk_size: usize) -> Result<PathBuf> {
+        let tree_hash = wraith_files::tree_hash::compute_tree_hash(&self.part_path, chunk_size)
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+        if tree_hash.root != *root {
+            self.discard();
+            return Err(NodeError::HashMismatch);
+        }
+
+        let dir = self.destination.parent().unwrap_or_else(|| Path::new("."));
+        let file_name = self
+            .destination
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(FALLBACK_FILE_NAME);
+
+        for candidate in destination_candidates(dir, file_name) {
+            match persist_no_clobber(&self.part_path, &candidate) {
+                Ok(()) => return Ok(candidate),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(NodeError::Io(e.to_string())),
+            }
+        }
+
+        Err(NodeError::invalid_state(
+            "Too many files with the same name",
+        ))
+    }
+
+    /// Remove the `.part` file (failed or abandoned transfer)
+    pub fn discard(&self) {
+        let _ = std::fs::remove_file(&self.part_path);
+    }
+}
+
+/// Atomically move `from` to `to`, failing with `AlreadyExists` instead of overwriting
+fn persist_no_clobber(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::hard_link(from, to) {
+        Ok(()) => std::fs::remove_file(from),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(e),
+        // File systems without hard links: fall back to a plain rename
+        Err(_) if std::fs::symlink_metadata(to).is_err() => std::fs::rename(from, to),
+        Err(_) => Err(std::io::ErrorKind::AlreadyExists.into()),
+    }
 }
 
 /// File transfer metadata sent in StreamOpen frame
@@ -160,6 +660,27 @@ impl FileMetadata {
         buf
     }
 
+    /// Validate metadata received from a remote peer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the chunk size is zero, the chunk count does not
+    /// match the file size, or the chunk count exceeds [`MAX_TRANSFER_CHUNKS`].
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(NodeError::invalid_state("Invalid chunk size"));
+        }
+        if self.total_chunks != self.file_size.div_ceil(u64::from(self.chunk_size)) {
+            return Err(NodeError::invalid_state(
+                "Chunk count does not match file size",
+            ));
+        }
+        if self.total_chunks > MAX_TRANSFER_CHUNKS {
+            return Err(NodeError::invalid_state("Too many chunks in transfer"));
+        }
+        Ok(())
+    }
+
     /// Deserialize metadata from bytes
     pub fn deserialize(data: &[u8]) -> Result<Self> {
         if data.len() < 85 {
@@ -227,8 +748,18 @@ impl FileMetadata {
 }
 
 /// Build a metadata frame (StreamOpen) for file transfer
-pub fn build_metadata_frame(stream_id: u16, metadata: &FileMetadata) -> Result<Vec<u8>> {
-    let metadata_bytes = metadata.serialize();
+///
+/// `chunk_hashes` announces that CHUNK_HASHES segments follow (see
+/// [`METADATA_FLAG_CHUNK_HASHES`]).
+pub fn build_metadata_frame(
+    stream_id: u16,
+    metadata: &FileMetadata,
+    chunk_hashes: bool,
+) -> Result<Vec<u8>> {
+    let mut metadata_bytes = metadata.serialize();
+    if chunk_hashes {
+        metadata_bytes.push(METADATA_FLAG_CHUNK_HASHES);
+    }
     let frame_size = FRAME_HEADER_SIZE + metadata_bytes.len();
 
     FrameBuilder::new()
@@ -240,6 +771,14 @@ pub fn build_metadata_frame(stream_id: u16, metadata: &FileMetadata) -> Result<V
         .map_err(|e| NodeError::InvalidState(format!("Failed to build metadata frame: {e}").into()))
 }
 
+/// Check whether a STREAM_OPEN metadata payload announces a chunk hash layer
+pub fn announces_chunk_hashes(payload: &[u8]) -> bool {
+    payload
+        .get(32)
+        .and_then(|&name_len| payload.get(85 + usize::from(name_len)))
+        .is_some_and(|flags| flags & METADATA_FLAG_CHUNK_HASHES != 0)
+}
+
 /// Build a data frame for file chunk
 pub fn build_chunk_frame(stream_id: u16, chunk_index: u64, chunk_data: &[u8]) -> Result<Vec<u8>> {
     let frame_size = FRAME_HEADER_SIZE + chunk_data.len();
@@ -257,9 +796,245 @@ pub fn build_chunk_frame(stream_id: u16, chunk_index: u64, chunk_data: &[u8]) ->
         .map_err(|e| NodeError::InvalidState(format!("Failed to build chunk frame: {e}").into()))
 }
 
+/// Build a data frame carrying a compressed file chunk
+///
+/// The frame is flagged `CMP` and its offset is that of the uncompressed
+/// chunk, whose length is `chunk_len`.
+pub fn build_compressed_chunk_frame(
+    stream_id: u16,
+    chunk_index: u64,
+    chunk_len: usize,
+    compressed: &[u8],
+) -> Result<Vec<u8>> {
+    let frame_size = FRAME_HEADER_SIZE + compressed.len();
+
+    FrameBuilder::new()
+        .frame_type(FrameType::Data)
+        .flags(FrameFlags::new().with_compressed())
+        .stream_id(stream_id)
+        .sequence(chunk_index as u32)
+        .offset(chunk_index * chunk_len as u64)
+        .payload(compressed)
+        .build(frame_size)
+        .map_err(|e| NodeError::InvalidState(format!("Failed to build chunk frame: {e}").into()))
+}
+
+/// Build CHUNK_HASHES control frames carrying the chunk hash layer
+///
+/// Payload format: type(1) + start_index(8, big-endian) + N * hash(32),
+/// with at most [`CHUNK_HASHES_PER_FRAME`] hashes per frame.
+pub fn build_chunk_hash_frames(stream_id: u16, tree_hash: &FileTreeHash) -> Result<Vec<Vec<u8>>> {
+    tree_hash
+        .chunks
+        .chunks(CHUNK_HASHES_PER_FRAME)
+        .enumerate()
+        .map(|(segment, hashes)| {
+            let start_index = (segment * CHUNK_HASHES_PER_FRAME) as u64;
+            let mut payload = Vec::with_capacity(9 + hashes.len() * 32);
+            payload.push(CONTROL_CHUNK_HASHES);
+            payload.extend_from_slice(&start_index.to_be_bytes());
+            for hash in hashes {
+                payload.extend_from_slice(hash);
+            }
+
+            FrameBuilder::new()
+                .frame_type(FrameType::Control)
+                .stream_id(stream_id)
+                .sequence(segment as u32)
+                .payload(&payload)
+                .build(FRAME_HEADER_SIZE + payload.len())
+                .map_err(|e| {
+                    NodeError::InvalidState(format!("Failed to build chunk hash frame: {e}").into())
+                })
+        })
+        .collect()
+}
+
+/// Parse a CHUNK_HASHES control payload into (start_index, hashes)
+pub fn parse_chunk_hashes(payload: &[u8]) -> Result<(u64, Vec<[u8; 32]>)> {
+    if payload.len() < 9
+        || payload[0] != CONTROL_CHUNK_HASHES
+        || !(payload.len() - 9).is_multiple_of(32)
+    {
+        return Err(NodeError::invalid_state("Malformed chunk hash segment"));
+    }
+
+    let start_index = u64::from_be_bytes(
+        payload[1..9]
+            .try_into()
+            .map_err(|_| NodeError::invalid_state("Invalid start_index"))?,
+    );
+    let hashes = payload[9..]
+        .chunks_exact(32)
+        .map(|h| {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(h);
+            hash
+        })
+        .collect();
+
+    Ok((start_index, hashes))
+}
+
+/// Build a CHUNK_REQUEST control frame asking a peer to (re)send one chunk
+///
+/// Payload format: type(1) + transfer_id(32) + chunk_index(8, big-endian)
+pub fn build_chunk_request_frame(
+    stream_id: u16,
+    transfer_id: &[u8; 32],
+    chunk_index: u64,
+) -> Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(41);
+    payload.push(CONTROL_CHUNK_REQUEST);
+    payload.extend_from_slice(transfer_id);
+    payload.extend_from_slice(&chunk_index.to_be_bytes());
+
+    FrameBuilder::new()
+        .frame_type(FrameType::Control)
+        .stream_id(stream_id)
+        .sequence(chunk_index as u32)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| NodeError::InvalidState(format!("Failed to build chunk request: {e}").into()))
+}
+
+/// Parse a CHUNK_REQUEST control payload into (transfer_id, chunk_index)
+pub fn parse_chunk_request(payload: &[u8]) -> Result<([u8; 32], u64)> {
+    if payload.len() < 41 || payload[0] != CONTROL_CHUNK_REQUEST {
+        return Err(NodeError::invalid_state("Malformed chunk request"));
+    }
+
+    let mut transfer_id = [0u8; 32];
+    transfer_id.copy_from_slice(&payload[1..33]);
+    let chunk_index = u64::from_be_bytes(
+        payload[33..41]
+            .try_into()
+            .map_err(|_| NodeError::invalid_state("Invalid chunk_index"))?,
+    );
+
+    Ok((transfer_id, chunk_index))
+}
+
+/// Build a CHUNK_HASHES_REQUEST control frame asking the sender to re-send
+/// the chunk hash layer from `start_index` on
+///
+/// Payload format: type(1) + transfer_id(32) + start_index(8, big-endian)
+pub fn build_chunk_hashes_request_frame(
+    stream_id: u16,
+    transfer_id: &[u8; 32],
+    start_index: u64,
+) -> Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(41);
+    payload.push(CONTROL_CHUNK_HASHES_REQUEST);
+    payload.extend_from_slice(transfer_id);
+    payload.extend_from_slice(&start_index.to_be_bytes());
+
+    FrameBuilder::new()
+        .frame_type(FrameType::Control)
+        .stream_id(stream_id)
+        .sequence(0)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| {
+            NodeError::InvalidState(format!("Failed to build chunk hashes request: {e}").into())
+        })
+}
+
+/// Parse a CHUNK_HASHES_REQUEST control payload into (transfer_id, start_index)
+pub fn parse_chunk_hashes_request(payload: &[u8]) -> Result<([u8; 32], u64)> {
+    if payload.len() < 41 || payload[0] != CONTROL_CHUNK_HASHES_REQUEST {
+        return Err(NodeError::invalid_state("Malformed chunk hashes request"));
+    }
+
+    let mut transfer_id = [0u8; 32];
+    transfer_id.copy_from_slice(&payload[1..33]);
+    let start_index = u64::from_be_bytes(
+        payload[33..41]
+            .try_into()
+            .map_err(|_| NodeError::invalid_state("Invalid start_index"))?,
+    );
+
+    Ok((transfer_id, start_index))
+}
+
+/// Build a TRANSFER_ACCEPT control frame telling the sender to start streaming
+///
+/// Payload format: type(1) + transfer_id(32)
+pub fn build_transfer_accept_frame(stream_id: u16, transfer_id: &[u8; 32]) -> Result<Vec<u8>> {
+    let mut payload = Vec::with_capacity(33);
+    payload.push(CONTROL_TRANSFER_ACCEPT);
+    payload.extend_from_slice(transfer_id);
+
+    FrameBuilder::new()
+        .frame_type(FrameType::Control)
+        .stream_id(stream_id)
+        .sequence(0)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| NodeError::InvalidState(format!("Failed to build accept frame: {e}").into()))
+}
+
+/// Parse a TRANSFER_ACCEPT control payload into the accepted transfer_id
+pub fn parse_transfer_accept(payload: &[u8]) -> Result<[u8; 32]> {
+    if payload.len() < 33 || payload[0] != CONTROL_TRANSFER_ACCEPT {
+        return Err(NodeError::invalid_state("Malformed transfer accept"));
+    }
+
+    let mut transfer_id = [0u8; 32];
+    transfer_id.copy_from_slice(&payload[1..33]);
+    Ok(transfer_id)
+}
+
+/// Build a STREAM_RESET frame rejecting an incoming transfer
+///
+/// Payload format: transfer_id(32) + reason_len(1) + reason (UTF-8). Reasons
+/// longer than [`MAX_REJECT_REASON_LEN`] bytes are truncated on a character
+/// boundary.
+pub fn build_transfer_reject_frame(
+    stream_id: u16,
+    transfer_id: &[u8; 32],
+    reason: &str,
+) -> Result<Vec<u8>> {
+    let mut reason_len = reason.len().min(MAX_REJECT_REASON_LEN);
+    while !reason.is_char_boundary(reason_len) {
+        reason_len -= 1;
+    }
+    let reason = &reason.as_bytes()[..reason_len];
+
+    let mut payload = Vec::with_capacity(33 + reason.len());
+    payload.extend_from_slice(transfer_id);
+    payload.push(reason.len() as u8);
+    payload.extend_from_slice(reason);
+
+    FrameBuilder::new()
+        .frame_type(FrameType::StreamReset)
+        .stream_id(stream_id)
+        .sequence(0)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| NodeError::InvalidState(format!("Failed to build reject frame: {e}").into()))
+}
+
+/// Parse a transfer STREAM_RESET payload into (transfer_id, reason)
+pub fn parse_transfer_reject(payload: &[u8]) -> Result<([u8; 32], String)> {
+    if payload.len() < 33 {
+        return Err(NodeError::invalid_state("Malformed transfer reject"));
+    }
+
+    let mut transfer_id = [0u8; 32];
+    transfer_id.copy_from_slice(&payload[..32]);
+    let reason_len = payload[32] as usize;
+    let reason = payload
+        .get(33..33 + reason_len)
+        .ok_or_else(|| NodeError::invalid_state("Truncated rejection reason"))?;
+
+    Ok((transfer_id, String::from_utf8_lossy(reason).into_owned()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wraith_files::tree_hash::compute_tree_hash_from_data;
 
     #[test]
     fn test_metadata_serialization_roundtrip() {
@@ -300,6 +1075,30 @@ mod tests {
         assert_eq!(metadata.file_name, deserialized.file_name);
     }
 
+    #[test]
+    fn test_metadata_validate() {
+        let mut metadata = FileMetadata {
+            transfer_id: [1u8; 32],
+            file_name: "test.dat".to_string(),
+            file_size: 1000,
+            chunk_size: 256,
+            total_chunks: 4,
+            root_hash: [2u8; 32],
+        };
+        assert!(metadata.validate().is_ok());
+
+        metadata.total_chunks = u64::MAX;
+        assert!(metadata.validate().is_err());
+
+        metadata.chunk_size = 0;
+        assert!(metadata.validate().is_err());
+
+        metadata.chunk_size = 1;
+        metadata.file_size = MAX_TRANSFER_CHUNKS + 1;
+        metadata.total_chunks = MAX_TRANSFER_CHUNKS + 1;
+        assert!(metadata.validate().is_err());
+    }
+
     #[test]
     fn test_metadata_deserialize_truncated() {
         let short_data = vec![0u8; 50]; // Too short
@@ -317,18 +1116,26 @@ mod tests {
             root_hash: [2u8; 32],
         };
 
-        let frame_bytes = build_metadata_frame(42, &metadata).unwrap();
+        let frame_bytes = build_metadata_frame(42, &metadata, true).unwrap();
 
         // Verify frame can be parsed
         let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
         assert_eq!(frame.frame_type(), FrameType::StreamOpen);
         assert_eq!(frame.stream_id(), 42);
         assert_eq!(frame.sequence(), 0);
+        assert!(announces_chunk_hashes(frame.payload()));
 
         // Verify metadata can be deserialized from payload
         let parsed_metadata = FileMetadata::deserialize(frame.payload()).unwrap();
         assert_eq!(metadata.file_name, parsed_metadata.file_name);
         assert_eq!(metadata.file_size, parsed_metadata.file_size);
+
+        // Baseline metadata carries no flags byte
+        let frame_bytes = build_metadata_frame(42, &metadata, false).unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+        assert_eq!(frame.payload(), metadata.serialize());
+        assert!(!announces_chunk_hashes(frame.payload()));
+        assert!(!announces_chunk_hashes(&[]));
     }
 
     #[test]
@@ -345,4 +1152,288 @@ mod tests {
         assert_eq!(frame.sequence(), chunk_index as u32);
         assert_eq!(frame.payload(), &chunk_data);
     }
+
+    #[test]
+    fn test_chunk_hash_frames_roundtrip() {
+        let data = vec![0x5A; 300 * 64];
+        let tree = compute_tree_hash_from_data(&data, 64);
+        assert_eq!(tree.chunk_count(), 300);
+
+        let frames = build_chunk_hash_frames(70, &tree).unwrap();
+        assert_eq!(frames.len(), 300usize.div_ceil(CHUNK_HASHES_PER_FRAME));
+        assert!(frames.iter().all(|frame| frame.len() < 1200));
+
+        let mut layer = ChunkHashLayer::new(tree.root, 300);
+        let mut completed = false;
+        for frame_bytes in &frames {
+            let frame = crate::frame::Frame::parse(frame_bytes).unwrap();
+            assert_eq!(frame.frame_type(), FrameType::Control);
+            assert_eq!(frame.stream_id(), 70);
+            let (start, hashes) = parse_chunk_hashes(frame.payload()).unwrap();
+            completed = layer.insert_segment(start, &hashes).unwrap();
+        }
+        assert!(completed);
+        assert!(layer.is_verified());
+        assert_eq!(layer.verify_chunk(0, &data[..64]), ChunkVerdict::Valid);
+        assert_eq!(layer.verify_chunk(0, &[0u8; 64]), ChunkVerdict::Corrupt);
+        assert_eq!(layer.verify_chunk(300, &data[..64]), ChunkVerdict::Corrupt);
+    }
+
+    #[test]
+    fn test_chunk_hash_layer_rejects_forged_hashes() {
+        let data = vec![0x11; 4 * 64];
+        let tree = compute_tree_hash_from_data(&data, 64);
+
+        let mut forged = tree.chunks.clone();
+        forged[1] = *blake3::hash(b"malicious").as_bytes();
+
+        let mut layer = ChunkHashLayer::new(tree.root, 4);
+        assert!(matches!(
+            layer.insert_segment(0, &forged),
+            Err(NodeError::HashMismatch)
+        ));
+        assert!(!layer.is_verified());
+        assert_eq!(layer.verify_chunk(0, &data[..64]), ChunkVerdict::Unverified);
+
+        // Layer can be re-sent correctly after a mismatch
+        assert!(layer.insert_segment(0, &tree.chunks).unwrap());
+    }
+
+    #[test]
+    fn test_chunk_hash_layer_allocates_per_segment() {
+        let data = vec![0x22; 3 * 64];
+        let tree = compute_tree_hash_from_data(&data, 64);
+
+        // Announcing the maximum chunk count costs nothing until hashes arrive
+        let mut huge = ChunkHashLayer::new([0u8; 32], MAX_TRANSFER_CHUNKS);
+        assert!(huge.hashes.is_empty());
+        assert!(
+            !huge
+                .insert_segment(MAX_TRANSFER_CHUNKS - 1, &[[0u8; 32]])
+                .unwrap()
+        );
+        assert_eq!(huge.hashes.len(), 1);
+
+        // Segments may arrive out of order and overlap
+        let mut layer = ChunkHashLayer::new(tree.root, 3);
+        assert!(!layer.insert_segment(2, &tree.chunks[2..]).unwrap());
+        assert!(!layer.insert_segment(1, &tree.chunks[1..]).unwrap());
+        assert!(layer.insert_segment(0, &tree.chunks[..1]).unwrap());
+        assert_eq!(layer.verify_chunk(1, &data[64..128]), ChunkVerdict::Valid);
+    }
+
+    #[test]
+    fn test_chunk_hash_layer_out_of_range() {
+        let mut layer = ChunkHashLayer::new([0u8; 32], 2);
+        assert!(layer.insert_segment(1, &[[0u8; 32]; 2]).is_err());
+        assert!(layer.insert_segment(u64::MAX, &[[0u8; 32]]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_hash_layer_deferred() {
+        let mut layer = ChunkHashLayer::new([0u8; 32], 1);
+        for i in 0..MAX_DEFERRED_CHUNKS as u64 {
+            assert!(layer.defer_chunk(i, b"data"));
+        }
+        assert!(!layer.defer_chunk(99, b"data"));
+        assert!(!layer.defer_chunk(0, b"data"));
+        assert_eq!(layer.take_deferred().len(), MAX_DEFERRED_CHUNKS);
+        assert!(layer.take_deferred().is_empty());
+
+        // Only dropped chunks inside the file are kept for re-requesting
+        assert_eq!(layer.take_dropped(), vec![0]);
+        assert!(layer.take_dropped().is_empty());
+    }
+
+    #[test]
+    fn test_chunk_hash_layer_recovery() {
+        let data = vec![0x33; 70 * 64];
+        let tree = compute_tree_hash_from_data(&data, 64);
+        let mut layer = ChunkHashLayer::new(tree.root, 70);
+        assert_eq!(layer.first_missing(), 0);
+
+        // The segment after a lost one is kept; recovery resumes at the gap
+        layer.insert_segment(0, &tree.chunks[..32]).unwrap();
+        layer.insert_segment(64, &tree.chunks[64..]).unwrap();
+        assert_eq!(layer.first_missing(), 32);
+        assert!(layer.begin_recovery());
+        assert!(!layer.begin_recovery());
+
+        assert!(layer.insert_segment(32, &tree.chunks[32..64]).unwrap());
+        assert!(layer.is_verified());
+        assert!(!layer.begin_recovery());
+    }
+
+    #[test]
+    fn test_chunk_request_roundtrip() {
+        let frame_bytes = build_chunk_request_frame(30, &[9u8; 32], 42).unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Control);
+
+        let (transfer_id, chunk_index) = parse_chunk_request(frame.payload()).unwrap();
+        assert_eq!(transfer_id, [9u8; 32]);
+        assert_eq!(chunk_index, 42);
+
+        assert!(parse_chunk_request(&[CONTROL_CHUNK_REQUEST; 10]).is_err());
+        assert!(parse_chunk_hashes(&[CONTROL_CHUNK_HASHES; 12]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_hashes_request_roundtrip() {
+        let frame_bytes = build_chunk_hashes_request_frame(30, &[9u8; 32], 64).unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Control);
+        assert_eq!(frame.stream_id(), 30);
+
+        let (transfer_id, start_index) = parse_chunk_hashes_request(frame.payload()).unwrap();
+        assert_eq!(transfer_id, [9u8; 32]);
+        assert_eq!(start_index, 64);
+
+        assert!(parse_chunk_hashes_request(&[CONTROL_CHUNK_HASHES_REQUEST; 10]).is_err());
+        let chunk_request = build_chunk_request_frame(30, &[9u8; 32], 64).unwrap();
+        let frame = crate::frame::Frame::parse(&chunk_request).unwrap();
+        assert!(parse_chunk_hashes_request(frame.payload()).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_file_name_hostile_names() {
+        let cases = [
+            ("report.pdf", "report.pdf"),
+            ("../../etc/passwd", "passwd"),
+            ("/etc/shadow", "shadow"),
+            ("..\\..\\Windows\\System32\\cmd.exe", "cmd.exe"),
+            ("C:\\boot.ini", "boot.ini"),
+            ("C:evil.txt", "C_evil.txt"),
+            ("file.txt:stream", "file.txt_stream"),
+            (".bashrc", "bashrc"),
+            ("...hidden", "hidden"),
+            ("trailing. . ", "trailing"),
+            ("CON", "_CON"),
+            ("nul.txt", "_nul.txt"),
+            ("Com1.tar.gz", "_Com1.tar.gz"),
+            ("LPT9 .log", "_LPT9 .log"),
+            ("console.log", "console.log"),
+            ("a\u{0}b\nc\r.txt", "a_b_c_.txt"),
+            ("invoice\u{202E}fdp.exe", "invoice_fdp.exe"),
+            ("what?<*>|\"", "what______"),
+            ("résumé.pdf", "résumé.pdf"),
+        ];
+        for (hostile, expected) in cases {
+            assert_eq!(sanitize_file_name(hostile), expected, "input {hostile:?}");
+        }
+    }
+
+    #[test]
+    fn test_sanitize_file_name_empty_results() {
+        for hostile in ["", ".", "..", "/", "../", "dir/", "...", " . "] {
+            assert_eq!(sanitize_file_name(hostile), FALLBACK_FILE_NAME);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_file_name_truncates_keeping_extension() {
+        let long = format!("{}.tar", "ä".repeat(200));
+        let sanitized = sanitize_file_name(&long);
+
+        assert!(sanitized.len() <= MAX_FILE_NAME_LEN);
+        assert!(sanitized.ends_with(".tar"));
+        assert!(sanitized.starts_with("ää"));
+    }
+
+    #[test]
+    fn test_unique_destination_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            unique_destination(dir.path(), "notes.txt").unwrap(),
+            dir.path().join("notes.txt")
+        );
+
+        std::fs::write(dir.path().join("notes.txt"), b"1").unwrap();
+        std::fs::write(dir.path().join("notes (1).txt"), b"2").unwrap();
+        assert_eq!(
+            unique_destination(dir.path(), "notes.txt").unwrap(),
+            dir.path().join("notes (2).txt")
+        );
+
+        std::fs::write(dir.path().join("Makefile"), b"3").unwrap();
+        assert_eq!(
+            unique_destination(dir.path(), "Makefile").unwrap(),
+            dir.path().join("Makefile (1)")
+        );
+    }
+
+    #[test]
+    fn test_quarantined_file_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("data.bin");
+        std::fs::write(&destination, b"already here").unwrap();
+
+        let quarantine =
+            QuarantinedFile::new(&dir.path().join("q"), &[6u8; 32], destination.clone()).unwrap();
+        assert!(
+            quarantine
+                .part_path
+                .to_string_lossy()
+                .ends_with(PART_FILE_SUFFIX)
+        );
+
+        let data = vec![0x5Au8; 1000];
+        std::fs::write(&quarantine.part_path, &data).unwrap();
+        let tree = wraith_files::tree_hash::compute_tree_hash_from_data(&data, 256);
+
+        let final_path = quarantine.commit(&tree.root, 256).unwrap();
+        assert_eq!(final_path, dir.path().join("data (1).bin"));
+        assert_eq!(std::fs::read(&final_path).unwrap(), data);
+        assert_eq!(std::fs::read(&destination).unwrap(), b"already here");
+        assert!(!quarantine.part_path.exists());
+    }
+
+    #[test]
+    fn test_quarantined_file_commit_root_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("data.bin");
+        let quarantine =
+            QuarantinedFile::new(&dir.path().join("q"), &[7u8; 32], destination.clone()).unwrap();
+        std::fs::write(&quarantine.part_path, vec![0x5Au8; 1000]).unwrap();
+
+        let result = quarantine.commit(&[0u8; 32], 256);
+        assert!(matches!(result, Err(NodeError::HashMismatch)));
+        assert!(!quarantine.part_path.exists());
+        assert!(!destination.exists());
+    }
+
+    #[test]
+    fn test_transfer_accept_roundtrip() {
+        let frame_bytes = build_transfer_accept_frame(30, &[5u8; 32]).unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Control);
+        assert_eq!(parse_transfer_accept(frame.payload()).unwrap(), [5u8; 32]);
+
+        assert!(parse_transfer_accept(&[CONTROL_CHUNK_REQUEST; 33]).is_err());
+    }
+
+    #[test]
+    fn test_transfer_reject_roundtrip() {
+        let frame_bytes = build_transfer_reject_frame(30, &[5u8; 32], "not wanted").unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::StreamReset);
+
+        let (transfer_id, reason) = parse_transfer_reject(frame.payload()).unwrap();
+        assert_eq!(transfer_id, [5u8; 32]);
+        assert_eq!(reason, "not wanted");
+
+        assert!(parse_transfer_reject(&[0u8; 20]).is_err());
+    }
+
+    #[test]
+    fn test_transfer_reject_truncates_long_reason() {
+        // 200 two-byte characters: truncation must land on a char boundary
+        let reason = "é".repeat(200);
+        let frame_bytes = build_transfer_reject_frame(30, &[5u8; 32], &reason).unwrap();
+        let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
+
+        let (_, parsed) = parse_transfer_reject(frame.payload()).unwrap();
+        assert_eq!(parsed.len(), 254);
+        assert!(reason.starts_with(&parsed));
+    }
 }
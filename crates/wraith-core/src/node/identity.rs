@@ -19,8 +19,9 @@
 //! ```
 
 use crate::node::error::{NodeError, Result};
+use std::sync::Arc;
 use wraith_crypto::noise::NoiseKeypair;
-use wraith_crypto::signatures::SigningKey as Ed25519SigningKey;
+use wraith_crypto::signatures::{Signature, SigningKey as Ed25519SigningKey};
 
 /// Transfer ID (32-byte unique identifier)
 ///
@@ -170,6 +171,9 @@ pub struct Identity {
 
     /// X25519 keypair for Noise handshakes
     x25519: NoiseKeypair,
+
+    /// Ed25519 signing key (absent for identities restored from a bare node ID)
+    ed25519: Option<Arc<Ed25519SigningKey>>,
 }
 
 impl Identity {
@@ -195,13 +199,23 @@ impl Identity {
 
         // Generate Ed25519 keypair and extract public key as node ID
         let ed25519 = Ed25519SigningKey::generate(&mut OsRng);
-        let node_id = ed25519.verifying_key().to_bytes();
-        // Note: We don't store the signing key, only use the public key as node ID
 
         // Generate X25519 keypair for Noise handshakes
         let x25519 = NoiseKeypair::generate().map_err(|e| NodeError::Crypto(e.to_string()))?;
 
-        Ok(Self { node_id, x25519 })
+        Ok(Self::from_signing_key(ed25519, x25519))
+    }
+
+    /// Create identity from an Ed25519 signing key and X25519 keypair
+    ///
+    /// The node ID is the Ed25519 public key, and the identity can sign data
+    /// with [`Self::sign`].
+    pub fn from_signing_key(ed25519: Ed25519SigningKey, x25519: NoiseKeypair) -> Self {
+        Self {
+            node_id: ed25519.verifying_key().to_bytes(),
+            x25519,
+            ed25519: Some(Arc::new(ed25519)),
+        }
     }
 
     /// Create identity from existing components
@@ -224,7 +238,11 @@ impl Identity {
     /// let identity = Identity::from_components(node_id, x25519);
     /// ```
     pub fn from_components(node_id: [u8; 32], x25519: NoiseKeypair) -> Self {
-        Self { node_id, x25519 }
+        Self {
+            node_id,
+            x25519,
+            ed25519: None,
+        }
     }
 
     /// Get the node's public key (node ID)
@@ -257,6 +275,19 @@ impl Identity {
     pub fn x25519_keypair(&self) -> &NoiseKeypair {
         &self.x25519
     }
+
+    /// Sign a message with the node's Ed25519 key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identity was created without a signing key
+    /// (see [`Self::from_components`]).
+    pub fn sign(&self, message: &[u8]) -> Result<Signature> {
+        self.ed25519
+            .as_ref()
+            .map(|key| key.sign(message))
+            .ok_or_else(|| NodeError::invalid_state("Identity has no signing key"))
+    }
 }
 
 impl std::fmt::Debug for Identity {
@@ -304,6 +335,19 @@ mod tests {
         assert_eq!(*identity.x25519_public_key(), x25519_pub);
     }
 
+    #[test]
+    fn test_identity_sign() {
+        use wraith_crypto::signatures::VerifyingKey;
+
+        let identity = Identity::generate().unwrap();
+        let signature = identity.sign(b"manifest").unwrap();
+        let verifying_key = VerifyingKey::from_bytes(identity.public_key()).unwrap();
+        assert!(verifying_key.verify(b"manifest", &signature).is_ok());
+
+        let bare = Identity::from_components([42u8; 32], NoiseKeypair::generate().unwrap());
+        assert!(bare.sign(b"manifest").is_err());
+    }
+
     #[test]
     fn test_identity_debug() {
         let identity = Identity::generate().unwrap();
@@ -0,0 +1,325 @@
+//! Known-peers store for pinning peer keys to addresses
+//!
+//! A [`KnownPeers`] file records which X25519 static key was seen at each
+//! peer address, in the spirit of SSH's `known_hosts`. The first connection to
+//! an address trusts whatever key answers (trust on first use) and pins it;
+//! later connections dial with the pinned key as the expected peer ID, so the
+//! handshake fails with [`NodeError::PeerKeyMismatch`] if a different key
+//! answers.
+//!
+//! The file holds one entry per line: the host (as the user typed it, usually
+//! `host:port`) followed by the hex-encoded peer ID. Blank lines and lines
+//! starting with `#` are ignored.
+//!
+//! ```text
+//! # WRAITH known peers
+//! 192.0.2.10:8420 3b6a27bcceb6a42d62a3a8d02a6f0d73653215771de243a63ac048a18b59da29
+//! relay.example.org:8420 8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a
+//! ```
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wraith_core::node::Node;
+//! use wraith_core::node::known_peers::{KnownPeers, PinStatus};
+//!
+//! # async fn example(node: Node) -> Result<(), Box<dyn std::error::Error>> {
+//! let mut known = KnownPeers::load("known_peers")?;
+//! let host = "192.0.2.10:8420";
+//! let addr = host.parse()?;
+//!
+//! let peer_id = match known.lookup(host) {
+//!     Some(pinned) => {
+//!         node.establish_session_with_addr(&pinned, addr).await?;
+//!         pinned
+//!     }
+//!     None => node.establish_session_with_unverified_addr(addr).await?,
+//! };
+//! if known.verify(host, &peer_id)? == PinStatus::New {
+//!     known.pin(host, peer_id);
+//!     known.save()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`NodeError::PeerKeyMismatch`]: crate::node::NodeError::PeerKeyMismatch
+
+use crate::node::identity::parse_peer_id;
+use crate::node::session::PeerId;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Header written at the top of a saved known-peers file
+const FILE_HEADER: &str = "# WRAITH known peers: <host> <peer-id>\n";
+
+/// Errors from loading, checking or saving a known-peers file
+#[derive(Debug, thiserror::Error)]
+pub enum KnownPeersError {
+    /// Reading or writing the file failed
+    #[error("Known peers I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// A line of the file could not be parsed
+    #[error("Known peers file line {line}: {reason}")]
+    Parse {
+        /// 1-based line number
+        line: usize,
+        /// What was wrong with the line
+        reason: String,
+    },
+
+    /// The host is pinned to a different key than the one presented
+    #[error(
+        "Peer key for {host} has changed: pinned {}, presented {}. \
+         If the peer rotated its key, remove the entry from the known peers file",
+        hex::encode(pinned),
+        hex::encode(presented)
+    )]
+    KeyChanged {
+        /// Host whose key changed
+        host: String,
+        /// Key recorded in the store
+        pinned: PeerId,
+        /// Key the peer presented
+        presented: PeerId,
+    },
+}
+
+/// A pinned host and its peer ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KnownPeer {
+    /// Host as written in the file (usually `host:port`)
+    pub host: String,
+    /// Pinned X25519 static key
+    pub peer_id: PeerId,
+}
+
+/// Result of checking a presented key against the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinStatus {
+    /// The host is pinned to the presented key
+    Known,
+    /// The host has no entry yet
+    New,
+}
+
+/// Persistent map of hosts to pinned peer keys
+#[derive(Debug, Clone)]
+pub struct KnownPeers {
+    path: PathBuf,
+    entries: Vec<KnownPeer>,
+}
+
+impl KnownPeers {
+    /// Load the store from `path`
+    ///
+    /// A missing file yields an empty store that will be created on
+    /// [`save`](Self::save).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or a line is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, KnownPeersError> {
+        let path = path.as_ref().to_path_buf();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_error = |reason: String| KnownPeersError::Parse {
+                line: index + 1,
+                reason,
+            };
+            let mut fields = line.split_whitespace();
+            let (Some(host), Some(peer_id), None) = (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(parse_error("expected `<host> <peer-id>`".to_string()));
+            };
+            let peer_id = parse_peer_id(peer_id).map_err(|e| parse_error(e.to_string()))?;
+            entries.push(KnownPeer {
+                host: host.to_string(),
+                peer_id,
+            });
+        }
+
+        Ok(Self { path, entries })
+    }
+
+    /// Path the store is loaded from and saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All pinned entries, in file order
+    pub fn entries(&self) -> &[KnownPeer] {
+        &self.entries
+    }
+
+    /// Look up the key pinned for `host`
+    pub fn lookup(&self, host: &str) -> Option<PeerId> {
+        self.entries
+            .iter()
+            .find(|entry| entry.host == host)
+            .map(|entry| entry.peer_id)
+    }
+
+    /// Check `presented` against the key pinned for `host`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KnownPeersError::KeyChanged`] if the host is pinned to a
+    /// different key.
+    pub fn verify(&self, host: &str, presented: &PeerId) -> Result<PinStatus, KnownPeersError> {
+        match self.lookup(host) {
+            None => Ok(PinStatus::New),
+            Some(pinned) if pinned == *presented => Ok(PinStatus::Known),
+            Some(pinned) => Err(KnownPeersError::KeyChanged {
+                host: host.to_string(),
+                pinned,
+                presented: *presented,
+            }),
+        }
+    }
+
+    /// Pin `host` to `peer_id`, replacing any existing entry
+    pub fn pin(&mut self, host: &str, peer_id: PeerId) {
+        match self.entries.iter_mut().find(|entry| entry.host == host) {
+            Some(entry) => entry.peer_id = peer_id,
+            None => self.entries.push(KnownPeer {
+                host: host.to_string(),
+                peer_id,
+            }),
+        }
+    }
+
+    /// Remove the entry for `host`, returning its pinned key
+    pub fn remove(&mut self, host: &str) -> Option<PeerId> {
+        let index = self.entries.iter().position(|entry| entry.host == host)?;
+        Some(self.entries.remove(index).peer_id)
+    }
+
+    /// Write the store back to its file
+    ///
+    /// The file is written to a temporary sibling and renamed into place, so
+    /// a crash never leaves a truncated store behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file or its parent directory cannot be written.
+    pub fn save(&self) -> Result<(), KnownPeersError> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut tmp_name = self.path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(FILE_HEADER.as_bytes())?;
+        for entry in &self.entries {
+            writeln!(file, "{} {}", entry.host, hex::encode(entry.peer_id))?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+        let known = KnownPeers::load(dir.path().join("known_peers")).unwrap();
+        assert!(known.entries().is_empty());
+        assert_eq!(known.lookup("127.0.0.1:8420"), None);
+    }
+
+    #[test]
+    fn test_pin_save_and_reload() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("known_peers");
+
+        let mut known = KnownPeers::load(&path).unwrap();
+        known.pin("127.0.0.1:8420", [1u8; 32]);
+        known.pin("example.org:9000", [2u8; 32]);
+        known.save().unwrap();
+
+        let reloaded = KnownPeers::load(&path).unwrap();
+        assert_eq!(reloaded.entries(), known.entries());
+        assert_eq!(reloaded.lookup("example.org:9000"), Some([2u8; 32]));
+        assert!(!path.with_extension("tmp").exists());
+    }
+
+    #[test]
+    fn test_verify_detects_key_change() {
+        let dir = TempDir::new().unwrap();
+        let mut known = KnownPeers::load(dir.path().join("known_peers")).unwrap();
+
+        assert_eq!(
+            known.verify("127.0.0.1:8420", &[1u8; 32]).unwrap(),
+            PinStatus::New
+        );
+        known.pin("127.0.0.1:8420", [1u8; 32]);
+        assert_eq!(
+            known.verify("127.0.0.1:8420", &[1u8; 32]).unwrap(),
+            PinStatus::Known
+        );
+
+        let err = known.verify("127.0.0.1:8420", &[9u8; 32]).unwrap_err();
+        assert!(matches!(
+            err,
+            KnownPeersError::KeyChanged { pinned, presented, .. }
+                if pinned == [1u8; 32] && presented == [9u8; 32]
+        ));
+        assert!(err.to_string().contains("remove the entry"));
+    }
+
+    #[test]
+    fn test_pin_replaces_and_remove() {
+        let dir = TempDir::new().unwrap();
+        let mut known = KnownPeers::load(dir.path().join("known_peers")).unwrap();
+        known.pin("host:1", [1u8; 32]);
+        known.pin("host:1", [3u8; 32]);
+        assert_eq!(known.entries().len(), 1);
+        assert_eq!(known.remove("host:1"), Some([3u8; 32]));
+        assert_eq!(known.remove("host:1"), None);
+    }
+
+    #[test]
+    fn test_load_skips_comments_and_rejects_garbage() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("known_peers");
+        let id = hex::encode([7u8; 32]);
+
+        std::fs::write(&path, format!("# comment\n\nhost:1 {id}\n")).unwrap();
+        let known = KnownPeers::load(&path).unwrap();
+        assert_eq!(known.lookup("host:1"), Some([7u8; 32]));
+
+        std::fs::write(&path, format!("host:1 {id}\nhost:2\n")).unwrap();
+        assert!(matches!(
+            KnownPeers::load(&path),
+            Err(KnownPeersError::Parse { line: 2, .. })
+        ));
+
+        std::fs::write(&path, "host:1 nothex\n").unwrap();
+        assert!(matches!(
+            KnownPeers::load(&path),
+            Err(KnownPeersError::Parse { line: 1, .. })
+        ));
+    }
+}
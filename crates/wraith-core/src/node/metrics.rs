@@ -0,0 +1,535 @@
+//! Unified metrics registry
+//!
+//! Gathers the counters kept by the node's subsystems (transport, routing,
+//! security monitor, IP reputation, health monitor and per-session
+//! congestion control) into one snapshot, rendered as OpenMetrics text for
+//! Prometheus-compatible scrapers.
+//!
+//! A [`MetricsRegistry`] is a point-in-time snapshot: build one with
+//! [`Node::metrics`](crate::node::Node::metrics), add anything else the
+//! embedding application tracks, and [`encode`](MetricsRegistry::encode) it.
+//! All metric names start with `wraith_`.
+
+use crate::congestion::BbrState;
+use crate::node::health::{HealthMetrics, HealthStatus};
+use crate::node::ip_reputation::IpReputationMetrics;
+use crate::node::routing::RoutingStats;
+use crate::node::security_monitor::SecurityMetrics;
+use crate::session::SessionStats;
+use std::fmt::Write;
+use wraith_transport::transport::TransportStats;
+
+/// HTTP `Content-Type` of [`MetricsRegistry::encode`] output
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Metric type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// Monotonically increasing total; exposed with a `_total` suffix
+    Counter,
+    /// Value that can go up and down
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+        }
+    }
+}
+
+/// One labelled value of a metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Label names and values
+    pub labels: Vec<(String, String)>,
+    /// Sample value
+    pub value: f64,
+}
+
+/// A metric and all its samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricFamily {
+    /// Metric name, without the `_total` suffix of counters
+    pub name: String,
+    /// Help text
+    pub help: String,
+    /// Metric type
+    pub kind: MetricKind,
+    /// Samples, in insertion order
+    pub samples: Vec<Sample>,
+}
+
+/// Snapshot of metrics from every subsystem
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    families: Vec<MetricFamily>,
+}
+
+impl MetricsRegistry {
+    /// Create an empty registry
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample to the metric `name`, creating the metric on first use
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` was already registered with a different kind.
+    pub fn add(
+        &mut self,
+        name: &str,
+        kind: MetricKind,
+        help: &str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        debug_assert!(is_valid_name(name), "invalid metric name {name}");
+        let sample = Sample {
+            labels: labels
+                .iter()
+                .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+                .collect(),
+            value,
+        };
+
+        if let Some(family) = self.families.iter_mut().find(|f| f.name == name) {
+            assert_eq!(family.kind, kind, "metric {name} registered twice");
+            family.samples.push(sample);
+            return;
+        }
+        self.families.push(MetricFamily {
+            name: name.to_string(),
+            help: help.to_string(),
+            kind,
+            samples: vec![sample],
+        });
+    }
+
+    /// Add an unlabelled counter
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) {
+        self.add(name, MetricKind::Counter, help, &[], value as f64);
+    }
+
+    /// Add an unlabelled gauge
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) {
+        self.add(name, MetricKind::Gauge, help, &[], value);
+    }
+
+    /// Registered metrics, in insertion order
+    #[must_use]
+    pub fn families(&self) -> &[MetricFamily] {
+        &self.families
+    }
+
+    /// Value of the sample of `name` with exactly `labels`
+    #[must_use]
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        let family = self.families.iter().find(|f| f.name == name)?;
+        family
+            .samples
+            .iter()
+            .find(|sample| {
+                sample.labels.len() == labels.len()
+                    && sample
+                        .labels
+                        .iter()
+                        .zip(labels)
+                        .all(|((k, v), (lk, lv))| k == lk && v == lv)
+            })
+            .map(|sample| sample.value)
+    }
+
+    /// Render in the OpenMetrics text format
+    #[must_use]
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind.as_str());
+            let _ = writeln!(
+                out,
+                "# HELP {} {}",
+                family.name,
+                escape(&family.help, false)
+            );
+            let suffix = match family.kind {
+                MetricKind::Counter => "_total",
+                MetricKind::Gauge => "",
+            };
+            for sample in &family.samples {
+                out.push_str(&family.name);
+                out.push_str(suffix);
+                if !sample.labels.is_empty() {
+                    out.push('{');
+                    for (i, (name, value)) in sample.labels.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        let _ = write!(out, "{name}=\"{}\"", escape(value, true));
+                    }
+                    out.push('}');
+                }
+                let _ = writeln!(out, " {}", format_value(sample.value));
+            }
+        }
+        out.push_str("# EOF\n");
+        out
+    }
+
+    /// Add transport packet and byte counters
+    pub fn record_transport(&mut self, stats: &TransportStats) {
+        self.counter(
+            "wraith_transport_sent_bytes",
+            "Bytes sent by all transports",
+            stats.bytes_sent,
+        );
+        self.counter(
+            "wraith_transport_received_bytes",
+            "Bytes received by all transports",
+            stats.bytes_received,
+        );
+        self.counter(
+            "wraith_transport_sent_packets",
+            "Packets sent by all transports",
+            stats.packets_sent,
+        );
+        self.counter(
+            "wraith_transport_received_packets",
+            "Packets received by all transports",
+            stats.packets_received,
+        );
+        for (direction, errors) in [("send", stats.send_errors), ("recv", stats.recv_errors)] {
+            self.add(
+                "wraith_transport_errors",
+                MetricKind::Counter,
+                "Transport send and receive errors",
+                &[("direction", direction)],
+                errors as f64,
+            );
+        }
+    }
+
+    /// Add routing table size and lookup counters
+    pub fn record_routing(&mut self, stats: &RoutingStats) {
+        self.gauge(
+            "wraith_routes",
+            "Connection IDs in the routing table",
+            stats.active_routes as f64,
+        );
+        for (result, count) in [
+            ("hit", stats.successful_lookups),
+            ("miss", stats.failed_lookups),
+        ] {
+            self.add(
+                "wraith_route_lookups",
+                MetricKind::Counter,
+                "Routing table lookups by result",
+                &[("result", result)],
+                count as f64,
+            );
+        }
+    }
+
+    /// Add security monitor counters
+    pub fn record_security(&mut self, metrics: &SecurityMetrics) {
+        self.counter(
+            "wraith_handshake_failures",
+            "Failed handshakes",
+            metrics.handshake_failures,
+        );
+        self.counter(
+            "wraith_rate_limit_violations",
+            "Packets or connections refused by rate limiting",
+            metrics.rate_limit_violations,
+        );
+        for (kind, bans) in [
+            ("temporary", metrics.temp_bans),
+            ("permanent", metrics.perm_bans),
+        ] {
+            self.add(
+                "wraith_bans",
+                MetricKind::Counter,
+                "IP bans issued",
+                &[("kind", kind)],
+                bans as f64,
+            );
+        }
+        self.counter(
+            "wraith_invalid_packets",
+            "Packets that failed to parse or authenticate",
+            metrics.invalid_packets,
+        );
+        self.counter(
+            "wraith_replay_attacks",
+            "Replayed packets detected",
+            metrics.replay_attacks,
+        );
+    }
+
+    /// Add IP reputation counters
+    pub fn record_reputation(&mut self, metrics: &IpReputationMetrics) {
+        self.counter(
+            "wraith_reputation_blocked_connections",
+            "Connections blocked by IP reputation",
+            metrics.connections_blocked,
+        );
+        self.counter(
+            "wraith_reputation_failures",
+            "Failures recorded against IP addresses",
+            metrics.total_failures,
+        );
+        for (status, count) in [
+            ("warning", metrics.warning_count),
+            ("backoff", metrics.backoff_count),
+            ("temp_banned", metrics.temp_banned_count),
+            ("perm_banned", metrics.perm_banned_count),
+        ] {
+            self.add(
+                "wraith_reputation_status_changes",
+                MetricKind::Counter,
+                "IP addresses moved to each reputation status",
+                &[("status", status)],
+                count as f64,
+            );
+        }
+    }
+
+    /// Add health monitor state
+    pub fn record_health(&mut self, metrics: &HealthMetrics) {
+        let status = match metrics.status {
+            HealthStatus::Healthy => 0.0,
+            HealthStatus::Degraded => 1.0,
+            HealthStatus::Critical => 2.0,
+        };
+        self.gauge(
+            "wraith_health_status",
+            "Node health (0 healthy, 1 degraded, 2 critical)",
+            status,
+        );
+        self.gauge(
+            "wraith_memory_used_bytes",
+            "System memory in use",
+            metrics.used_memory as f64,
+        );
+        for (state, count) in [
+            ("degraded", metrics.degraded_count),
+            ("critical", metrics.critical_count),
+            ("healthy", metrics.recovery_count),
+        ] {
+            self.add(
+                "wraith_health_transitions",
+                MetricKind::Counter,
+                "Health status transitions by new status",
+                &[("status", state)],
+                count as f64,
+            );
+        }
+    }
+
+    /// Add the traffic and congestion control state of one session
+    ///
+    /// `peer` labels the samples; pass the peer ID in hex.
+    pub fn record_session(&mut self, peer: &str, stats: &SessionStats, bbr: &BbrState) {
+        let labels = [("peer", peer)];
+        self.add(
+            "wraith_session_sent_bytes",
+            MetricKind::Counter,
+            "Bytes sent in the session",
+            &labels,
+            stats.bytes_sent as f64,
+        );
+        self.add(
+            "wraith_session_received_bytes",
+            MetricKind::Counter,
+            "Bytes received in the session",
+            &labels,
+            stats.bytes_received as f64,
+        );
+        self.add(
+            "wraith_session_rtt_seconds",
+            MetricKind::Gauge,
+            "Smoothed round-trip time estimate",
+            &labels,
+            bbr.estimated_rtt().as_secs_f64(),
+        );
+        self.add(
+            "wraith_session_cwnd_bytes",
+            MetricKind::Gauge,
+            "Congestion window",
+            &labels,
+            bbr.cwnd() as f64,
+        );
+        self.add(
+            "wraith_session_bytes_in_flight",
+            MetricKind::Gauge,
+            "Bytes sent and not yet acknowledged",
+            &labels,
+            bbr.bytes_in_flight() as f64,
+        );
+        self.add(
+            "wraith_session_loss_ratio",
+            MetricKind::Gauge,
+            "Fraction of packets lost",
+            &labels,
+            bbr.loss_rate(),
+        );
+    }
+}
+
+/// Metric and label names: `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Escape help text or a label value
+fn escape(text: &str, quote: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '"' if quote => out.push_str("\\\""),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Format a sample value, spelling out non-finite values
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_encode_openmetrics_text() {
+        let mut registry = MetricsRegistry::new();
+        registry.gauge("wraith_sessions", "Active sessions", 2.0);
+        registry.add(
+            "wraith_bans",
+            MetricKind::Counter,
+            "IP bans issued",
+            &[("kind", "temporary")],
+            3.0,
+        );
+        registry.add(
+            "wraith_bans",
+            MetricKind::Counter,
+            "IP bans issued",
+            &[("kind", "permanent")],
+            1.0,
+        );
+
+        assert_eq!(
+            registry.encode(),
+            "# TYPE wraith_sessions gauge\n\
+             # HELP wraith_sessions Active sessions\n\
+             wraith_sessions 2\n\
+             # TYPE wraith_bans counter\n\
+             # HELP wraith_bans IP bans issued\n\
+             wraith_bans_total{kind=\"temporary\"} 3\n\
+             wraith_bans_total{kind=\"permanent\"} 1\n\
+             # EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_label_values_and_special_floats_are_escaped() {
+        let mut registry = MetricsRegistry::new();
+        registry.add(
+            "wraith_test",
+            MetricKind::Gauge,
+            "Help with \\ and\nnewline",
+            &[("peer", "a\"b\\c\nd")],
+            f64::NAN,
+        );
+        registry.gauge("wraith_inf", "Infinite", f64::INFINITY);
+
+        let text = registry.encode();
+        assert!(text.contains("# HELP wraith_test Help with \\\\ and\\nnewline\n"));
+        assert!(text.contains("wraith_test{peer=\"a\\\"b\\\\c\\nd\"} NaN\n"));
+        assert!(text.contains("wraith_inf +Inf\n"));
+    }
+
+    #[test]
+    fn test_get_matches_exact_labels() {
+        let mut registry = MetricsRegistry::new();
+        registry.record_security(&SecurityMetrics {
+            handshake_failures: 7,
+            temp_bans: 2,
+            ..SecurityMetrics::default()
+        });
+
+        assert_eq!(registry.get("wraith_handshake_failures", &[]), Some(7.0));
+        assert_eq!(
+            registry.get("wraith_bans", &[("kind", "temporary")]),
+            Some(2.0)
+        );
+        assert_eq!(registry.get("wraith_bans", &[]), None);
+        assert_eq!(registry.get("wraith_missing", &[]), None);
+    }
+
+    #[test]
+    fn test_record_session() {
+        let mut bbr = BbrState::new();
+        bbr.update_rtt(Duration::from_millis(40));
+        bbr.on_packet_sent(1200);
+
+        let stats = SessionStats {
+            state: crate::session::SessionState::Established,
+            bytes_sent: 1200,
+            bytes_received: 600,
+            packets_sent: 1,
+            packets_received: 1,
+            stream_count: 0,
+            established_at: None,
+            last_activity: std::time::Instant::now(),
+        };
+
+        let mut registry = MetricsRegistry::new();
+        registry.record_session("ab", &stats, &bbr);
+
+        let peer = [("peer", "ab")];
+        assert_eq!(
+            registry.get("wraith_session_sent_bytes", &peer),
+            Some(1200.0)
+        );
+        assert_eq!(
+            registry.get("wraith_session_bytes_in_flight", &peer),
+            Some(1200.0)
+        );
+        assert_eq!(
+            registry.get("wraith_session_cwnd_bytes", &peer),
+            Some(bbr.cwnd() as f64)
+        );
+        assert!(registry.get("wraith_session_rtt_seconds", &peer).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_names_are_valid() {
+        assert!(is_valid_name("wraith_sessions"));
+        assert!(is_valid_name("_private:x1"));
+        assert!(!is_valid_name("1st"));
+        assert!(!is_valid_name("has-dash"));
+        assert!(!is_valid_name(""));
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! This module provides the high-level Node API that coordinates all protocol
 //! components:
-//! - Cryptographic handshakes (Noise_XX via wraith-crypto)
+//! - Cryptographic handshakes (Noise_XX with hybrid ML-KEM via wraith-crypto)
 //! - Transport selection (AF_XDP/UDP via wraith-transport)
 //! - Obfuscation (padding/timing via wraith-obfuscation)
 //! - Peer discovery (DHT/NAT via wraith-discovery)
@@ -29,6 +29,11 @@
 //! - [`transfer_manager`] - File transfer coordination
 //! - [`session`] - PeerConnection and handshake functions
 //! - [`config`] - Configuration types
+//! - [`acceptance`] - Incoming transfer acceptance policy
+//! - [`byte_stream`] - Async byte streams over sessions
+//! - [`known_peers`] - Known-peers store for pinning peer keys
+//! - [`rekey`] - In-band session rekeying
+//! - [`tree_transfer`] - Directory transfers with signed manifests
 //! - [`error`] - Error types
 //!
 //! # Example
@@ -56,6 +61,8 @@
 // The buffer pool is now defined in wraith-transport where it's primarily used
 pub use wraith_transport::BufferPool;
 
+pub mod acceptance;
+pub mod byte_stream;
 pub mod circuit_breaker;
 pub mod config;
 pub mod connection;
@@ -66,6 +73,8 @@ pub mod health;
 pub mod ice;
 pub mod identity;
 pub mod ip_reputation;
+pub mod known_peers;
+pub mod metrics;
 pub mod multi_peer;
 pub mod nat;
 #[allow(clippy::module_inception)]
@@ -75,6 +84,7 @@ pub mod packet_handler;
 pub mod padding_strategy;
 pub mod progress;
 pub mod rate_limiter;
+pub mod rekey;
 pub mod resume;
 pub mod routing;
 pub mod security_monitor;
@@ -82,15 +92,20 @@ pub mod session;
 pub mod session_manager;
 pub mod transfer;
 pub mod transfer_manager;
+pub mod tree_transfer;
 
 // BufferPool is re-exported from wraith_transport at the top of this module
+pub use acceptance::{
+    AcceptAll, IncomingTransfer, TransferAcceptor, TransferDecision, TrustedPeers,
+};
+pub use byte_stream::WraithStream;
 pub use circuit_breaker::{
     CircuitBreaker, CircuitBreakerConfig, CircuitMetrics, CircuitState, RetryConfig,
 };
 pub use config::{
-    CoverTrafficConfig, CoverTrafficDistribution, DiscoveryConfig, LogLevel, LoggingConfig,
-    MimicryMode, NodeConfig, ObfuscationConfig, PaddingMode, TimingMode, TransferConfig,
-    TransportConfig,
+    CompressionConfig, CoverTrafficConfig, CoverTrafficDistribution, CryptoConfig, DiscoveryConfig,
+    FecConfig, LogLevel, LoggingConfig, MimicryMode, NodeConfig, ObfuscationConfig, PaddingMode,
+    RekeyConfig, TimingMode, TransferConfig, TransportConfig,
 };
 pub use connection::{HealthMetrics, HealthStatus};
 pub use discovery::{NatType, NodeCapabilities, PeerAnnouncement, PeerInfo};
@@ -106,6 +121,8 @@ pub use identity::{Identity, TransferId};
 pub use ip_reputation::{
     IpReputationConfig, IpReputationMetrics, IpReputationSystem, ReputationStatus,
 };
+pub use known_peers::{KnownPeer, KnownPeers, KnownPeersError, PinStatus};
+pub use metrics::{MetricFamily, MetricKind, MetricsRegistry, OPENMETRICS_CONTENT_TYPE, Sample};
 pub use multi_peer::{ChunkAssignmentStrategy, MultiPeerCoordinator, PeerPerformance};
 pub use nat::{CandidateType, IceAgentDiagnostics, IceCandidate};
 pub use node::Node;
@@ -122,6 +139,11 @@ pub use security_monitor::{
     SecurityEvent, SecurityEventCallback, SecurityEventType, SecurityMetrics, SecurityMonitor,
     SecurityMonitorConfig,
 };
-pub use session::PeerConnection;
+pub use session::{
+    Capabilities, HandshakePolicy, Negotiated, PeerConnection, SUPPORTED_SUITES,
+    SUPPORTED_WIRE_FORMATS,
+};
 pub use session_manager::SessionManager;
 pub use transfer_manager::TransferManager;
+pub use tree_transfer::{TreeEntry, TreeEntryKind, TreeManifest};
+pub use wraith_crypto::suite::CryptoSuite;
@@ -47,6 +47,9 @@ pub struct PeerPerformance {
     /// Number of chunks that failed
     pub chunks_failed: usize,
 
+    /// Number of chunks that failed integrity verification
+    pub chunks_corrupt: usize,
+
     /// Last activity timestamp
     pub last_active: Instant,
 
@@ -74,6 +77,7 @@ impl PeerPerformance {
             throughput_bps: 1_000_000, // Initial estimate: 1 MB/s
             chunks_succeeded: 0,
             chunks_failed: 0,
+            chunks_corrupt: 0,
             last_active: now,
             in_flight: 0,
             max_concurrent: 4,
@@ -193,6 +197,15 @@ impl PeerPerformance {
         self.update_cached_score();
     }
 
+    /// Record a chunk that failed integrity verification
+    ///
+    /// Counts as a failure and additionally tracks the corruption so callers
+    /// can distinguish misbehaving peers from merely lossy ones.
+    pub fn record_corruption(&mut self) {
+        self.chunks_corrupt += 1;
+        self.record_failure();
+    }
+
     /// Record chunk assignment
     pub fn record_assignment(&mut self) {
         self.in_flight += 1;
@@ -231,6 +244,14 @@ impl MultiPeerCoordinator {
         peers.insert(peer_id, PeerPerformance::new(peer_id, address));
     }
 
+    /// Add a peer unless it is already tracked, keeping its recorded performance
+    pub async fn ensure_peer(&self, peer_id: [u8; 32], address: SocketAddr) {
+        let mut peers = self.peers.write().await;
+        peers
+            .entry(peer_id)
+            .or_insert_with(|| PeerPerformance::new(peer_id, address));
+    }
+
     /// Remove a peer from the coordinator
     pub async fn remove_peer(&self, peer_id: &[u8; 32]) {
         let mut peers = self.peers.write().await;
@@ -356,6 +377,29 @@ impl MultiPeerCoordinator {
         }
     }
 
+    /// Record that a peer delivered a chunk failing hash verification
+    ///
+    /// Drops any assignment of the chunk so it can be re-requested and
+    /// penalizes the peer's reliability score.
+    pub async fn record_corrupt_chunk(&self, peer_id: &[u8; 32], chunk_index: usize) {
+        self.assignments.write().await.remove(&chunk_index);
+
+        let mut peers = self.peers.write().await;
+        if let Some(peer) = peers.get_mut(peer_id) {
+            peer.record_corruption();
+        }
+    }
+
+    /// Peers that have delivered at least one corrupt chunk
+    pub async fn corrupt_peers(&self) -> Vec<[u8; 32]> {
+        let peers = self.peers.read().await;
+        peers
+            .values()
+            .filter(|p| p.chunks_corrupt > 0)
+            .map(|p| p.peer_id)
+            .collect()
+    }
+
     /// Update peer RTT
     pub async fn update_peer_rtt(&self, peer_id: &[u8; 32], rtt_us: u64) {
         let mut peers = self.peers.write().await;
@@ -592,4 +636,31 @@ mod tests {
         let perf = coordinator.peer_performance(&peer_id).await.unwrap();
         assert_eq!(perf.chunks_succeeded, 1);
     }
+
+    #[tokio::test]
+    async fn test_multi_peer_record_corrupt_chunk() {
+        let coordinator = MultiPeerCoordinator::new(ChunkAssignmentStrategy::RoundRobin);
+        let honest = [1u8; 32];
+        let malicious = [2u8; 32];
+
+        coordinator
+            .add_peer(honest, "127.0.0.1:8420".parse().unwrap())
+            .await;
+        coordinator
+            .add_peer(malicious, "127.0.0.1:8421".parse().unwrap())
+            .await;
+
+        coordinator.record_corrupt_chunk(&malicious, 3).await;
+
+        let perf = coordinator.peer_performance(&malicious).await.unwrap();
+        assert_eq!(perf.chunks_corrupt, 1);
+        assert_eq!(perf.chunks_failed, 1);
+        assert_eq!(coordinator.corrupt_peers().await, vec![malicious]);
+
+        // Seeing the peer again on another transfer does not wipe its record
+        coordinator
+            .ensure_peer(malicious, "127.0.0.1:8421".parse().unwrap())
+            .await;
+        assert_eq!(coordinator.corrupt_peers().await, vec![malicious]);
+    }
 }
@@ -27,7 +27,7 @@ use crate::node::ice::{
     CandidateType as IceCandidateType, IceAgent, IceCandidate as FullIceCandidate, IceConfig,
     IceRole,
 };
-use crate::node::session::PeerConnection;
+use crate::node::session::{PeerConnection, PeerId};
 use crate::node::{Node, NodeError};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -126,6 +126,17 @@ impl Node {
         }
     }
 
+    /// Get the public address the gateway forwards to this node, if any
+    ///
+    /// Set once a port mapping (PCP, NAT-PMP or UPnP IGD) has been obtained;
+    /// requires `discovery.enable_port_mapping`.
+    pub async fn mapped_address(&self) -> Option<SocketAddr> {
+        let guard = self.inner.discovery.lock().await;
+        guard
+            .as_ref()
+            .and_then(|discovery| discovery.mapped_address())
+    }
+
     /// Attempt NAT traversal to connect to peer
     ///
     /// Uses ICE-lite to establish a connection through NAT.
@@ -254,7 +265,10 @@ impl Node {
                 remote.address
             );
 
-            match self.try_connect_candidate(&local, &remote).await {
+            match self
+                .try_connect_candidate(&peer.peer_id, &local, &remote)
+                .await
+            {
                 Ok(conn) => {
                     tracing::info!("Hole punch successful via {:?}", local.address);
                     return Ok(conn);
@@ -368,6 +382,17 @@ impl Node {
         };
 
         if let Some(ref disc) = discovery {
+            // The gateway port mapping is a server reflexive address peers
+            // can reach without hole punching
+            if let Some(addr) = disc.mapped_address() {
+                candidates.push(IceCandidate {
+                    address: addr,
+                    candidate_type: CandidateType::ServerReflexive,
+                    priority: 100, // Type preference for server reflexive
+                    foundation: format!("srflx-{addr}"),
+                });
+            }
+
             // Get NAT type which triggers STUN detection
             if let Some(nat_type) = disc.nat_type().await {
                 tracing::debug!("Detected NAT type: {:?}", nat_type);
@@ -758,9 +783,11 @@ impl Node {
     /// Try to connect using a specific candidate pair
     ///
     /// Attempts to establish a connection using the specified local and remote ICE candidates.
-    /// This includes sending hole-punch packets and attempting the Noise handshake.
+    /// This includes sending hole-punch packets and attempting the Noise handshake, which
+    /// fails unless the remote candidate answers with `peer_id`'s static key.
     async fn try_connect_candidate(
         &self,
+        peer_id: &PeerId,
         local: &IceCandidate,
         remote: &IceCandidate,
     ) -> Result<PeerConnection, NodeError> {
@@ -803,21 +830,12 @@ impl Node {
         }
 
         // Attempt to establish session using the remote candidate's address
-        // We derive a temporary peer ID from the remote address for the connection attempt
-        // The real peer ID will be discovered during the Noise handshake
-        let temp_peer_id = {
-            let addr_bytes = remote.address.to_string();
-            let hash = blake3::hash(addr_bytes.as_bytes());
-            *hash.as_bytes()
-        };
-
         match self
-            .establish_session_with_addr(&temp_peer_id, remote.address)
+            .establish_session_with_addr(peer_id, remote.address)
             .await
         {
             Ok(session_id) => {
                 // Session established successfully
-                // Retrieve the actual peer ID that was discovered during handshake
                 // Find the connection by session_id in the sessions map
                 if let Some(entry) = self
                     .inner
@@ -1182,6 +1200,42 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_port_mapping_without_gateway() {
+        // Mapping runs in the background, so an unreachable gateway must not
+        // hold up start or stop
+        let config = crate::node::NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            discovery: crate::node::DiscoveryConfig {
+                enable_nat_traversal: false,
+                enable_relay: false,
+                enable_port_mapping: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let node = Node::new_with_config(config).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), node.start())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.mapped_address().await, None);
+        assert!(
+            node.gather_ice_candidates()
+                .await
+                .unwrap()
+                .iter()
+                .all(|c| c.candidate_type == CandidateType::Host)
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), node.stop())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.mapped_address().await, None);
+    }
+
     #[tokio::test]
     async fn test_prioritize_candidates() {
         let node = Node::new_random_with_port(0).await.unwrap();
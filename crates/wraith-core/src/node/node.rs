@@ -21,17 +21,23 @@
 //! }
 //! ```
 
+use crate::frame::compat::WireFormat;
+use crate::node::acceptance::{AcceptAll, TransferAcceptor};
+use crate::node::byte_stream::{ACCEPT_BACKLOG, ByteStream, WraithStream};
 use crate::node::config::NodeConfig;
 use crate::node::error::{NodeError, Result};
 use crate::node::file_transfer::FileTransferContext;
 use crate::node::identity::{Identity, TransferId};
 use crate::node::ip_reputation::IpReputationSystem;
+use crate::node::metrics::{MetricKind, MetricsRegistry};
+use crate::node::multi_peer::MultiPeerCoordinator;
 use crate::node::obfuscation::ObfuscationStats;
 use crate::node::rate_limiter::RateLimiter;
 use crate::node::routing::RoutingTable;
 use crate::node::security_monitor::SecurityMonitor;
 use crate::node::session::{HandshakePacket, PeerConnection, PeerId, SessionId};
-use crate::transfer::TransferSession;
+use crate::node::tree_transfer::{ManifestAssembly, TreeManifest};
+use crate::transfer::{Direction, TransferSession, TransferState};
 use crate::{ConnectionId, HandshakePhase, SessionState};
 use dashmap::DashMap;
 use getrandom::getrandom;
@@ -40,15 +46,26 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, RwLock, oneshot};
-use wraith_discovery::{DiscoveryConfig as DiscoveryConfigInternal, DiscoveryManager};
-use wraith_files::tree_hash::compute_tree_hash;
+use tokio::sync::{Mutex, RwLock, mpsc, oneshot};
+use wraith_crypto::suite::CryptoSuite;
+use wraith_discovery::{
+    DiscoveryConfig as DiscoveryConfigInternal, DiscoveryManager, MdnsConfig, PortMappingConfig,
+};
+use wraith_files::tree_hash::{FileTreeHash, compute_tree_hash};
 use wraith_obfuscation::{DohTunnel, TlsRecordWrapper, WebSocketFrameWrapper};
+use wraith_transport::factory::{TransportFactory, TransportType};
+use wraith_transport::manager::TransportManager;
 use wraith_transport::transport::Transport;
 use wraith_transport::udp_async::AsyncUdpTransport;
 
-/// Type alias for pending chunk request map: (stream_id, chunk_index) -> data sender
-type PendingChunkMap = DashMap<(u16, u64), oneshot::Sender<Vec<u8>>>;
+/// Type alias for pending chunk request map: (stream_id, chunk_index) -> (requested peer, data sender)
+type PendingChunkMap = DashMap<(u16, u64), (PeerId, oneshot::Sender<Vec<u8>>)>;
+
+/// Type alias for pending acceptance map: transfer_id -> (receiver peer, verdict sender)
+///
+/// The verdict is `Ok(())` when the receiver accepts and `Err(reason)` when it rejects.
+type PendingAcceptanceMap =
+    DashMap<TransferId, (PeerId, oneshot::Sender<std::result::Result<(), String>>)>;
 
 /// Migration state for tracking PATH_CHALLENGE/RESPONSE
 #[allow(dead_code)]
@@ -57,6 +74,8 @@ pub(crate) struct MigrationState {
     pub peer_id: PeerId,
     /// New address to migrate to
     pub new_addr: SocketAddr,
+    /// Transport the new address is reached over
+    pub transport: TransportType,
     /// Challenge data (8 bytes)
     pub challenge: [u8; 8],
     /// Channel to signal migration completion
@@ -77,18 +96,32 @@ pub(crate) struct NodeInner {
     pub(crate) routing: Arc<RoutingTable>,
     /// Active file transfers (transfer_id -> transfer context)
     pub(crate) transfers: Arc<DashMap<TransferId, Arc<FileTransferContext>>>,
+    /// Files of active directory transfers (file transfer_id -> transfer context)
+    pub(crate) tree_files: Arc<DashMap<TransferId, Arc<FileTransferContext>>>,
+    /// Incoming directory manifests still being reassembled (transfer_id -> segments)
+    pub(crate) pending_manifests: Arc<DashMap<TransferId, ManifestAssembly>>,
     /// Pending handshakes (peer_addr -> channel)
     pub(crate) pending_handshakes: Arc<DashMap<SocketAddr, oneshot::Sender<HandshakePacket>>>,
     /// Pending pings (peer_id, sequence -> response channel)
     pub(crate) pending_pings: Arc<DashMap<(PeerId, u32), oneshot::Sender<Instant>>>,
     /// Pending migrations (path_id -> migration state)
     pub(crate) pending_migrations: Arc<DashMap<u64, MigrationState>>,
-    /// Pending chunk requests ((stream_id, chunk_idx) -> data sender)
+    /// Pending chunk requests ((stream_id, chunk_idx) -> (requested peer, data sender))
     pub(crate) pending_chunks: Arc<PendingChunkMap>,
+    /// Outgoing transfers awaiting the receiver's accept/reject verdict
+    pub(crate) pending_acceptances: Arc<PendingAcceptanceMap>,
+    /// Open byte streams ((peer_id, stream_id) -> stream state)
+    pub(crate) byte_streams: Arc<DashMap<(PeerId, u16), Arc<ByteStream>>>,
+    /// Queue of incoming byte streams for `accept_stream`
+    pub(crate) incoming_streams_tx: mpsc::Sender<WraithStream>,
+    /// Receiving end of the incoming byte stream queue
+    pub(crate) incoming_streams: Arc<Mutex<mpsc::Receiver<WraithStream>>>,
+    /// Policy consulted before accepting incoming transfers
+    pub(crate) transfer_acceptor: Arc<RwLock<Arc<dyn TransferAcceptor>>>,
     /// Node running state
     pub(crate) running: Arc<AtomicBool>,
-    /// Transport layer
-    pub(crate) transport: Arc<Mutex<Option<Arc<AsyncUdpTransport>>>>,
+    /// Transport layer (UDP primary plus any additional transports)
+    pub(crate) transport: Arc<Mutex<Option<Arc<TransportManager>>>>,
     /// Discovery manager
     pub(crate) discovery: Arc<Mutex<Option<Arc<DiscoveryManager>>>>,
     /// Rate limiter for DoS protection
@@ -108,6 +141,8 @@ pub(crate) struct NodeInner {
     /// Available files for seeding (root_hash -> (metadata, file_path))
     pub(crate) available_files:
         Arc<DashMap<[u8; 32], (crate::node::transfer::FileMetadata, PathBuf)>>,
+    /// Performance of the peers chunks are received from, across all transfers
+    pub(crate) peer_coordinator: Arc<MultiPeerCoordinator>,
 }
 
 /// WRAITH Protocol Node
@@ -179,6 +214,8 @@ impl Node {
         let websocket_wrapper = WebSocketFrameWrapper::new(false); // Server mode (no masking)
         let doh_tunnel = DohTunnel::new("https://1.1.1.1/dns-query".to_string());
         let obfuscation_stats = ObfuscationStats::default();
+        let (incoming_streams_tx, incoming_streams) = mpsc::channel(ACCEPT_BACKLOG);
+        let peer_coordinator = MultiPeerCoordinator::new(config.transfer.chunk_assignment_strategy);
 
         let inner = NodeInner {
             identity: Arc::new(identity),
@@ -190,6 +227,13 @@ impl Node {
             pending_pings: Arc::new(DashMap::new()),
             pending_migrations: Arc::new(DashMap::new()),
             pending_chunks: Arc::new(DashMap::new()),
+            pending_acceptances: Arc::new(DashMap::new()),
+            tree_files: Arc::new(DashMap::new()),
+            pending_manifests: Arc::new(DashMap::new()),
+            byte_streams: Arc::new(DashMap::new()),
+            incoming_streams_tx,
+            incoming_streams: Arc::new(Mutex::new(incoming_streams)),
+            transfer_acceptor: Arc::new(RwLock::new(Arc::new(AcceptAll))),
             running: Arc::new(AtomicBool::new(false)),
             transport: Arc::new(Mutex::new(None)),
             discovery: Arc::new(Mutex::new(None)),
@@ -201,6 +245,7 @@ impl Node {
             doh_tunnel: Arc::new(doh_tunnel),
             obfuscation_stats: Arc::new(Mutex::new(obfuscation_stats)),
             available_files: Arc::new(DashMap::new()),
+            peer_coordinator: Arc::new(peer_coordinator),
         };
         Ok(Self {
             inner: Arc::new(inner),
@@ -274,6 +319,27 @@ impl Node {
     /// - The transport fails to bind to the configured address
     /// - The discovery manager fails to initialize or start
     pub async fn start(&self) -> Result<()> {
+        self.start_on(None).await
+    }
+
+    /// Start the node on an already bound primary transport
+    ///
+    /// Like [`start`](Self::start), but sends and receives over `transport`
+    /// instead of binding UDP on the configured listen address, for
+    /// example a `SimulatedTransport` in tests. The transport may not reach
+    /// the real network, so discovery keeps only its in-memory DHT: NAT
+    /// type detection, gateway port mapping, mDNS and relays stay off
+    /// whatever the configuration says.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node is already running or the discovery
+    /// manager fails to initialize or start
+    pub async fn start_with_transport(&self, transport: Arc<dyn Transport>) -> Result<()> {
+        self.start_on(Some(transport)).await
+    }
+
+    async fn start_on(&self, primary: Option<Arc<dyn Transport>>) -> Result<()> {
         if self
             .inner
             .running
@@ -291,19 +357,63 @@ impl Node {
             self.inner.config.listen_addr
         );
 
-        // Initialize transport
-        let transport = AsyncUdpTransport::bind(self.inner.config.listen_addr)
-            .await
-            .map_err(|e| NodeError::Transport(format!("Failed to bind transport: {e}").into()))?;
-        let transport = Arc::new(transport);
-        *self.inner.transport.lock().await = Some(Arc::clone(&transport));
+        // Only probe the real network from a transport bound on it
+        let probe_network = primary.is_none();
+
+        // Initialize transports: UDP is the primary, others are fallbacks
+        let transport: Arc<dyn Transport> = match primary {
+            Some(transport) => transport,
+            None => Arc::new(
+                AsyncUdpTransport::bind(self.inner.config.listen_addr)
+                    .await
+                    .map_err(|e| {
+                        NodeError::Transport(format!("Failed to bind transport: {e}").into())
+                    })?,
+            ),
+        };
+        let udp_addr = transport.local_addr().ok();
+        let manager = TransportManager::new(transport);
+        for config in &self.inner.config.transport.additional_transports {
+            let transport = TransportFactory::create(config.clone())
+                .await
+                .map_err(|e| {
+                    NodeError::Transport(
+                        format!("Failed to bind {} transport: {e}", config.transport_type).into(),
+                    )
+                })?;
+            tracing::info!(
+                "Listening on {} at {:?}",
+                config.transport_type,
+                transport.local_addr()
+            );
+            manager.add_transport(transport).await;
+        }
+        *self.inner.transport.lock().await = Some(Arc::new(manager));
 
         // Initialize discovery
         let node_id_bytes = wraith_discovery::dht::NodeId::from_bytes(*self.node_id());
         let mut discovery_config =
             DiscoveryConfigInternal::new(node_id_bytes, self.inner.config.listen_addr);
-        discovery_config.nat_detection_enabled = self.inner.config.discovery.enable_nat_traversal;
-        discovery_config.relay_enabled = self.inner.config.discovery.enable_relay;
+        let discovery_settings = &self.inner.config.discovery;
+        discovery_config.nat_detection_enabled =
+            probe_network && discovery_settings.enable_nat_traversal;
+        discovery_config.relay_enabled = probe_network && discovery_settings.enable_relay;
+        if probe_network && discovery_settings.enable_port_mapping {
+            // Map the port actually bound, which differs from the configured
+            // one when listening on port 0
+            discovery_config.port_mapping = Some(PortMappingConfig {
+                internal_port: udp_addr.map(|addr| addr.port()),
+                ..PortMappingConfig::default()
+            });
+        }
+        discovery_config.mdns =
+            (probe_network && discovery_settings.enable_mdns).then(|| MdnsConfig {
+                // Sessions are keyed by the X25519 static key, so publish it for
+                // peers that look this node up by it
+                listen_port: udp_addr.map(|addr| addr.port()),
+                peer_key: Some(*self.x25519_public_key()),
+                ..MdnsConfig::default()
+            });
 
         let discovery = DiscoveryManager::new(discovery_config).await.map_err(|e| {
             NodeError::Discovery(format!("Failed to create discovery manager: {e}").into())
@@ -371,6 +481,14 @@ impl Node {
             tracing::warn!("Error closing transport: {}", e);
         }
 
+        // Stop discovery, which also removes any gateway port mapping
+        let discovery = self.inner.discovery.lock().await.take();
+        if let Some(discovery) = discovery
+            && let Err(e) = discovery.shutdown().await
+        {
+            tracing::warn!("Error stopping discovery: {}", e);
+        }
+
         tracing::info!("Node stopped");
         Ok(())
     }
@@ -386,7 +504,12 @@ impl Node {
 // ═══════════════════════════════════════════════════════════════════════════
 
 impl Node {
-    /// Discover peer addresses via DHT/STUN/relay
+    /// Discover peer addresses via mDNS/DHT/STUN/relay
+    ///
+    /// Addresses the peer announced on the local network come first. Those
+    /// announcements are unauthenticated, so callers must verify the peer's
+    /// key at each address and fall back to the later ones, as
+    /// [`Self::establish_session`] does.
     pub async fn discover_peer(&self, peer_id: &PeerId) -> Result<Vec<SocketAddr>> {
         let discovery = self.inner.discovery.lock().await;
         let discovery =
@@ -400,15 +523,21 @@ impl Node {
         let dht_node_id = wraith_discovery::dht::NodeId::from_bytes(*peer_id);
 
         // Use DiscoveryManager to find peer
-        match discovery.connect_to_peer(dht_node_id).await {
-            Ok(peer_connection) => {
-                tracing::info!(
-                    "Discovered peer {} at {} via {}",
-                    hex::encode(&peer_id[..8]),
-                    peer_connection.addr,
-                    peer_connection.connection_type
-                );
-                Ok(vec![peer_connection.addr])
+        match discovery.peer_candidates(dht_node_id).await {
+            Ok(candidates) => {
+                let mut addrs = Vec::with_capacity(candidates.len());
+                for candidate in candidates {
+                    tracing::info!(
+                        "Discovered peer {} at {} via {}",
+                        hex::encode(&peer_id[..8]),
+                        candidate.addr,
+                        candidate.connection_type
+                    );
+                    if !addrs.contains(&candidate.addr) {
+                        addrs.push(candidate.addr);
+                    }
+                }
+                Ok(addrs)
             }
             Err(e) => {
                 tracing::warn!(
@@ -429,23 +558,84 @@ impl Node {
             return Ok(connection.session_id);
         }
 
-        // Discover peer addresses via DHT/STUN/relay
+        // Discover peer addresses via mDNS/DHT/STUN/relay
         let addrs = self.discover_peer(peer_id).await?;
-        if addrs.is_empty() {
-            return Err(NodeError::PeerNotFound(*peer_id));
-        }
+        self.establish_session_with_candidates(peer_id, &addrs)
+            .await
+    }
 
-        // Try first address (in future, could try multiple in parallel)
-        let peer_addr = addrs[0];
-        self.establish_session_with_addr(peer_id, peer_addr).await
+    /// Establish a session with the first candidate address that answers
+    /// with `peer_id`'s key
+    ///
+    /// A failed handshake or a different static key, as from a stale or
+    /// spoofed LAN announcement, moves on to the next candidate.
+    async fn establish_session_with_candidates(
+        &self,
+        peer_id: &PeerId,
+        addrs: &[SocketAddr],
+    ) -> Result<SessionId> {
+        let mut last_error = NodeError::PeerNotFound(*peer_id);
+        for &peer_addr in addrs {
+            match self.establish_session_with_addr(peer_id, peer_addr).await {
+                Ok(session_id) => return Ok(session_id),
+                Err(e) => {
+                    tracing::debug!(
+                        "Candidate {} for peer {} failed: {}",
+                        peer_addr,
+                        hex::encode(&peer_id[..8]),
+                        e
+                    );
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
     }
 
     /// Establish session with peer at known address
+    ///
+    /// The handshake fails unless the peer at `peer_addr` presents the X25519
+    /// static key `expected_peer_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NodeError::PeerKeyMismatch` if a different key answers, or an
+    /// error if the handshake fails.
     pub async fn establish_session_with_addr(
         &self,
-        _expected_peer_id: &PeerId,
+        expected_peer_id: &PeerId,
         peer_addr: SocketAddr,
     ) -> Result<SessionId> {
+        self.handshake_with_addr(Some(expected_peer_id), peer_addr)
+            .await
+            .map(|(session_id, _)| session_id)
+    }
+
+    /// Establish session with whichever peer answers at `peer_addr`
+    ///
+    /// Accepts any static key and returns it as the peer ID. Callers that
+    /// learn a peer's address out of band should pin the returned key (see
+    /// [`KnownPeers`]) and use [`Self::establish_session_with_addr`] from then on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handshake fails.
+    ///
+    /// [`KnownPeers`]: crate::node::known_peers::KnownPeers
+    pub async fn establish_session_with_unverified_addr(
+        &self,
+        peer_addr: SocketAddr,
+    ) -> Result<PeerId> {
+        self.handshake_with_addr(None, peer_addr)
+            .await
+            .map(|(_, peer_id)| peer_id)
+    }
+
+    async fn handshake_with_addr(
+        &self,
+        expected_peer_id: Option<&PeerId>,
+        peer_addr: SocketAddr,
+    ) -> Result<(SessionId, PeerId)> {
         let transport = self.get_transport().await?;
         tracing::info!("Establishing session with peer at {}", peer_addr);
 
@@ -454,22 +644,30 @@ impl Node {
 
         let handshake_result = crate::node::session::perform_handshake_initiator(
             self.inner.identity.x25519_keypair(),
+            &self.inner.config.handshake_policy(),
             peer_addr,
             transport.as_ref(),
             Some(msg2_rx),
+            expected_peer_id,
         )
         .await;
         self.inner.pending_handshakes.remove(&peer_addr);
-        let (crypto, session_id, peer_id) = handshake_result?;
+        let (crypto, session_id, peer_id, negotiated) = handshake_result?;
 
         if let Some(connection) = self.inner.sessions.get(&peer_id) {
-            return Ok(connection.session_id);
+            return Ok((connection.session_id, peer_id));
         }
 
         let mut connection_id_bytes = [0u8; 8];
         connection_id_bytes.copy_from_slice(&session_id[..8]);
         let connection_id = ConnectionId::from_bytes(connection_id_bytes);
-        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto);
+        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto)
+            .with_transport(TransportType::Udp)
+            .with_crypto_suite(negotiated.suite)
+            .with_wire_format(negotiated.wire_format)
+            .with_legacy(negotiated.legacy)
+            .with_capabilities(negotiated.capabilities)
+            .with_rekey_config(self.inner.config.crypto.rekey.clone());
 
         connection
             .transition_to(SessionState::Handshaking(HandshakePhase::InitSent))
@@ -484,7 +682,9 @@ impl Node {
             .sessions
             .insert(peer_id, Arc::clone(&connection_arc));
         let cid_u64 = u64::from_be_bytes(connection_id_bytes);
-        self.inner.routing.add_route(cid_u64, connection_arc);
+        self.inner
+            .routing
+            .add_route(cid_u64, Arc::clone(&connection_arc));
 
         tracing::info!(
             "Session established with peer {} (X25519), session: {}, route: {:016x}",
@@ -493,10 +693,14 @@ impl Node {
             cid_u64
         );
 
+        // The responder may not have installed the session yet; advertise
+        // once its first packet arrives
+        connection_arc.defer_advertisement();
+
         // Announce peer to DHT (best-effort, don't fail session if announcement fails)
         self.announce_peer_to_dht(&peer_id, peer_addr).await;
 
-        Ok(session_id)
+        Ok((session_id, peer_id))
     }
 
     /// Announce peer to DHT (best-effort)
@@ -564,6 +768,84 @@ impl Node {
         self.inner.routing.stats()
     }
 
+    /// Get security event counters (handshake failures, bans, rate limiting)
+    pub async fn security_metrics(&self) -> crate::node::security_monitor::SecurityMetrics {
+        self.inner.security_monitor.metrics().await
+    }
+
+    /// Snapshot of node metrics for export
+    ///
+    /// Covers sessions, transfers by state, transport traffic, routing,
+    /// security events, IP reputation and the congestion control state of
+    /// every session. Transport counters are omitted while the node is not
+    /// running.
+    pub async fn metrics(&self) -> MetricsRegistry {
+        let mut registry = MetricsRegistry::new();
+
+        registry.gauge(
+            "wraith_sessions",
+            "Established sessions",
+            self.inner.sessions.len() as f64,
+        );
+
+        let mut by_state = [
+            ("initializing", 0u64),
+            ("transferring", 0),
+            ("paused", 0),
+            ("complete", 0),
+            ("failed", 0),
+        ];
+        let transfers: Vec<_> = self
+            .inner
+            .transfers
+            .iter()
+            .map(|entry| Arc::clone(entry.value()))
+            .collect();
+        for context in transfers {
+            let index = match context.transfer_session.read().await.state() {
+                TransferState::Initializing | TransferState::Handshaking => 0,
+                TransferState::Transferring | TransferState::Completing => 1,
+                TransferState::Paused => 2,
+                TransferState::Complete => 3,
+                TransferState::Failed => 4,
+            };
+            by_state[index].1 += 1;
+        }
+        for (state, count) in by_state {
+            registry.add(
+                "wraith_transfers",
+                MetricKind::Gauge,
+                "Tracked transfers by state",
+                &[("state", state)],
+                count as f64,
+            );
+        }
+
+        if let Ok(transport) = self.get_transport().await {
+            registry.record_transport(&transport.aggregated_stats().await);
+        }
+        registry.record_routing(&self.inner.routing.stats());
+        registry.record_security(&self.inner.security_monitor.metrics().await);
+        registry.record_reputation(&self.inner.ip_reputation.metrics().await);
+
+        let connections: Vec<_> = self
+            .inner
+            .sessions
+            .iter()
+            .map(|entry| Arc::clone(entry.value()))
+            .collect();
+        for connection in connections {
+            let session = connection.session.read().await;
+            registry.record_session(
+                &hex::encode(connection.peer_id),
+                &session.stats(),
+                session.bbr(),
+            );
+        }
+
+        registry
+    }
+
     /// Get number of active routes
     pub fn active_route_count(&self) -> usize {
         self.inner.routing.route_count()
@@ -609,6 +891,66 @@ impl Node {
             .get(peer_id)
             .map(|connection| connection.established_at)
     }
+
+    /// Get the key epoch of a session
+    ///
+    /// Starts at 0 after the handshake and increases with every completed
+    /// in-band rekey. Returns `None` if no active session exists with that peer.
+    pub fn get_session_key_epoch(&self, peer_id: &PeerId) -> Option<u32> {
+        self.inner
+            .sessions
+            .get(peer_id)
+            .map(|connection| connection.key_epoch())
+    }
+
+    /// Get the cipher suite negotiated for a session
+    ///
+    /// Returns `None` if no active session exists with that peer.
+    pub fn get_session_crypto_suite(&self, peer_id: &PeerId) -> Option<CryptoSuite> {
+        self.inner
+            .sessions
+            .get(peer_id)
+            .and_then(|connection| connection.crypto_suite)
+    }
+
+    /// Get the frame wire format negotiated for a session
+    ///
+    /// Returns `None` if no active session exists with that peer.
+    pub fn get_session_wire_format(&self, peer_id: &PeerId) -> Option<WireFormat> {
+        self.inner
+            .sessions
+            .get(peer_id)
+            .map(|connection| connection.wire_format)
+    }
+
+    /// Get the peer address a session is currently using
+    ///
+    /// Returns `None` if no active session exists with that peer.
+    pub fn get_session_addr(&self, peer_id: &PeerId) -> Option<SocketAddr> {
+        self.inner
+            .sessions
+            .get(peer_id)
+            .map(|connection| connection.peer_addr())
+    }
+
+    /// Get the transport a session is currently carried over
+    ///
+    /// Returns `None` if no active session exists with that peer.
+    pub fn get_session_transport(&self, peer_id: &PeerId) -> Option<TransportType> {
+        self.inner
+            .sessions
+            .get(peer_id)
+            .map(|connection| connection.transport_type())
+    }
+
+    /// Get the local address of every transport the node listens on
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node has not been started.
+    pub async fn transport_addrs(&self) -> Result<Vec<(TransportType, SocketAddr)>> {
+        Ok(self.get_transport().await?.local_addrs().await)
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════
@@ -637,6 +979,7 @@ impl Node {
 
         let mut transfer =
             TransferSession::new_send(transfer_id, file_path.to_path_buf(), file_size, chunk_size);
+        transfer.add_peer(*peer_id);
         transfer.start();
 
         let transfer_arc = Arc::new(RwLock::new(transfer));
@@ -650,7 +993,7 @@ impl Node {
             .insert(transfer_id, Arc::clone(&context));
 
         let connection = self.get_or_establish_session(peer_id).await?;
-        let stream_id = ((transfer_id[0] as u16) << 8) | (transfer_id[1] as u16);
+        let stream_id = crate::node::file_transfer::transfer_stream_id(&transfer_id);
 
         let metadata = crate::node::file_transfer::FileMetadata::from_path_and_hash(
             transfer_id,
@@ -659,18 +1002,37 @@ impl Node {
             chunk_size,
             &tree_hash,
         )?;
+        // Baseline peers know nothing of chunk hash layers
+        let chunk_hashes = !connection.legacy;
         let metadata_frame =
-            crate::node::file_transfer::build_metadata_frame(stream_id, &metadata)?;
-        self.send_encrypted_frame(&connection, &metadata_frame)
+            crate::node::file_transfer::build_metadata_frame(stream_id, &metadata, chunk_hashes)?;
+
+        let verdict_rx = self
+            .open_outgoing_transfer(&connection, peer_id, transfer_id, &[metadata_frame])
             .await?;
 
+        // Stream chunks only once the receiver has accepted the transfer
         let node = self.clone();
         let file_path_buf = file_path.to_path_buf();
         tokio::spawn(async move {
-            if let Err(e) = node
-                .send_file_chunks(transfer_id, file_path_buf, stream_id, connection)
-                .await
-            {
+            if !node.await_acceptance(&context, verdict_rx).await {
+                return;
+            }
+
+            // Ship the chunk hash layer so the receiver can verify each chunk on arrival
+            let result = async {
+                if chunk_hashes {
+                    for hash_frame in
+                        crate::node::file_transfer::build_chunk_hash_frames(stream_id, &tree_hash)?
+                    {
+                        node.send_encrypted_frame(&connection, &hash_frame).await?;
+                    }
+                }
+                node.send_file_chunks(transfer_id, file_path_buf, stream_id, connection)
+                    .await
+            }
+            .await;
+            if let Err(e) = result {
                 tracing::error!("Error sending file chunks: {}", e);
             }
         });
@@ -678,6 +1040,206 @@ impl Node {
         Ok(transfer_id)
     }
 
+    /// Send a directory tree to peer as a single transfer
+    ///
+    /// The tree is described by a signed [`TreeManifest`] (paths, sizes, modes,
+    /// mtimes, symlinks and per-file Merkle roots). Once the receiver accepts,
+    /// each file is streamed on its own stream within the transfer. Progress,
+    /// [`Node::wait_for_transfer`] and [`Node::cancel_transfer`] apply to the
+    /// tree as a whole.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` is not a readable directory, contains names
+    /// that cannot be sent safely, has no file data, or the peer is unreachable
+    /// or predates directory transfers.
+    pub async fn send_tree(&self, dir: impl AsRef<Path>, peer_id: &PeerId) -> Result<TransferId> {
+        let dir = dir.as_ref().to_path_buf();
+        let chunk_size = self.inner.config.transfer.chunk_size;
+        let transfer_id = Self::generate_transfer_id();
+
+        // Baseline peers know nothing of directory manifests
+        let connection = self.get_or_establish_session(peer_id).await?;
+        if connection.legacy {
+            return Err(NodeError::InvalidState(
+                "Peer does not support directory transfers".into(),
+            ));
+        }
+
+        // Walking and hashing the tree is blocking work
+        let identity = Arc::clone(&self.inner.identity);
+        let walk_dir = dir.clone();
+        let (manifest, files) = tokio::task::spawn_blocking(move || {
+            let (mut manifest, files) =
+                TreeManifest::from_directory(transfer_id, &walk_dir, chunk_size)?;
+            manifest.sign(&identity)?;
+            Ok::<_, NodeError>((manifest, files))
+        })
+        .await
+        .map_err(|e| NodeError::Other(format!("Manifest task failed: {e}").into()))??;
+
+        let mut transfer =
+            TransferSession::new_send(transfer_id, dir, manifest.total_size(), chunk_size)
+                .with_chunk_count(manifest.total_chunks());
+        transfer.add_peer(*peer_id);
+        transfer.start();
+
+        let context = Arc::new(FileTransferContext::new_send(
+            transfer_id,
+            Arc::new(RwLock::new(transfer)),
+            FileTreeHash {
+                root: manifest.digest(),
+                chunks: Vec::new(),
+            },
+        ));
+        self.inner
+            .transfers
+            .insert(transfer_id, Arc::clone(&context));
+
+        let members = manifest.members();
+        for file in &files {
+            let file_id = manifest.child_transfer_id(file.entry_index);
+            let mut session = TransferSession::new_send(
+                file_id,
+                file.source.clone(),
+                manifest.entries[file.entry_index].size,
+                chunk_size,
+            );
+            session.add_peer(*peer_id);
+            session.start();
+            let file_context = FileTransferContext::new_send(
+                file_id,
+                Arc::new(RwLock::new(session)),
+                file.tree_hash.clone(),
+            )
+            .with_tree_member(members[file.entry_index]);
+            self.inner
+                .tree_files
+                .insert(file_id, Arc::new(file_context));
+        }
+
+        let manifest_frames = crate::node::tree_transfer::build_manifest_frames(
+            crate::node::file_transfer::transfer_stream_id(&transfer_id),
+            &manifest,
+        )?;
+        let verdict_rx = self
+            .open_outgoing_transfer(&connection, peer_id, transfer_id, &manifest_frames)
+            .await?;
+
+        tracing::info!(
+            "Sending directory {} ({} entries, {} bytes) as transfer {}",
+            manifest.root_name,
+            manifest.entries.len(),
+            manifest.total_size(),
+            hex::encode(&transfer_id[..8])
+        );
+
+        // Stream the files one after another once the receiver has accepted
+        let node = self.clone();
+        tokio::spawn(async move {
+            if !node.await_acceptance(&context, verdict_rx).await {
+                return;
+            }
+
+            for file in files {
+                let file_id = manifest.child_transfer_id(file.entry_index);
+                let stream_id = manifest.child_stream_id(file.entry_index);
+                let result = async {
+                    for hash_frame in crate::node::file_transfer::build_chunk_hash_frames(
+                        stream_id,
+                        &file.tree_hash,
+                    )? {
+                        node.send_encrypted_frame(&connection, &hash_frame).await?;
+                    }
+                    node.send_file_chunks(file_id, file.source, stream_id, Arc::clone(&connection))
+                        .await
+                }
+                .await;
+                if let Err(e) = result {
+                    tracing::error!("Error sending directory: {}", e);
+                    return;
+                }
+                if context.transfer_session.read().await.is_failed() {
+                    return;
+                }
+            }
+        });
+
+        Ok(transfer_id)
+    }
+
+    /// Send the frames opening an outgoing transfer and register for the verdict
+    ///
+    /// The returned channel yields the receiver's accept/reject decision.
+    /// Baseline peers never send one, so legacy sessions get no channel and
+    /// the transfer streams right away.
+    async fn open_outgoing_transfer(
+        &self,
+        connection: &PeerConnection,
+        peer_id: &PeerId,
+        transfer_id: TransferId,
+        frames: &[Vec<u8>],
+    ) -> Result<Option<oneshot::Receiver<std::result::Result<(), String>>>> {
+        // Register before sending so a fast verdict cannot be missed
+        let verdict_rx = (!connection.legacy).then(|| {
+            let (verdict_tx, verdict_rx) = oneshot::channel();
+            self.inner
+                .pending_acceptances
+                .insert(transfer_id, (*peer_id, verdict_tx));
+            verdict_rx
+        });
+
+        for frame in frames {
+            if let Err(e) = self.send_encrypted_frame(connection, frame).await {
+                self.inner.pending_acceptances.remove(&transfer_id);
+                return Err(e);
+            }
+        }
+
+        Ok(verdict_rx)
+    }
+
+    /// Wait for the receiver's verdict on an outgoing transfer
+    ///
+    /// Returns `false` if the transfer was rejected or not accepted within the
+    /// configured acceptance timeout (the transfer is then marked failed).
+    /// Without a verdict channel (legacy sessions) there is nothing to wait for.
+    async fn await_acceptance(
+        &self,
+        context: &FileTransferContext,
+        verdict_rx: Option<oneshot::Receiver<std::result::Result<(), String>>>,
+    ) -> bool {
+        let Some(verdict_rx) = verdict_rx else {
+            return true;
+        };
+        let acceptance_timeout = self.inner.config.transfer.acceptance_timeout;
+        match tokio::time::timeout(acceptance_timeout, verdict_rx).await {
+            Ok(Ok(Ok(()))) => true,
+            Ok(Ok(Err(_))) => false, // Rejection already recorded by the STREAM_RESET handler
+            Ok(Err(_)) | Err(_) => {
+                self.inner.pending_acceptances.remove(&context.transfer_id);
+                tracing::warn!(
+                    "Transfer {} was not accepted within {:?}",
+                    hex::encode(&context.transfer_id[..8]),
+                    acceptance_timeout
+                );
+                context.set_rejected("acceptance timed out".to_string());
+                context.transfer_session.write().await.mark_failed();
+                false
+            }
+        }
+    }
+
+    /// Set the policy used to accept or reject incoming transfers
+    ///
+    /// The acceptor is consulted for every incoming STREAM_OPEN before any file
+    /// is created. Defaults to [`AcceptAll`].
+    ///
+    /// [`AcceptAll`]: crate::node::acceptance::AcceptAll
+    pub async fn set_transfer_acceptor(&self, acceptor: Arc<dyn TransferAcceptor>) {
+        *self.inner.transfer_acceptor.write().await = acceptor;
+    }
+
     /// Send file to multiple peers using multi-peer coordination
     ///
     /// Establishes sessions with all peers and uses the MultiPeerCoordinator
@@ -811,7 +1373,7 @@ impl Node {
             .map_err(|e| NodeError::Io(e.to_string()))?;
 
         let total_chunks = chunker.num_chunks();
-        let stream_id = ((transfer_id[0] as u16) << 8) | (transfer_id[1] as u16);
+        let stream_id = crate::node::file_transfer::transfer_stream_id(&transfer_id);
 
         tracing::debug!(
             "Uploading {} chunks across {} peers",
@@ -894,9 +1456,18 @@ impl Node {
     pub async fn wait_for_transfer(&self, transfer_id: TransferId) -> Result<()> {
         loop {
             if let Some(context) = self.inner.transfers.get(&transfer_id) {
-                if context.transfer_session.read().await.is_complete() {
+                let session = context.transfer_session.read().await;
+                if session.is_complete() {
                     return Ok(());
                 }
+                if session.is_failed() {
+                    return Err(match context.rejection_reason() {
+                        Some(reason) => {
+                            NodeError::Transfer(format!("Transfer rejected: {reason}").into())
+                        }
+                        None => NodeError::Transfer("Transfer failed".into()),
+                    });
+                }
             } else {
                 return Err(NodeError::TransferNotFound(transfer_id));
             }
@@ -926,6 +1497,10 @@ impl Node {
         // Set status based on session state
         if session.is_complete() {
             progress.status = crate::node::progress::TransferStatus::Complete;
+        } else if session.is_failed() {
+            progress.status = crate::node::progress::TransferStatus::Failed;
+        } else if session.state() == TransferState::Paused {
+            progress.status = crate::node::progress::TransferStatus::Paused;
         } else if bytes_sent > 0 {
             progress.status = crate::node::progress::TransferStatus::Transferring;
         }
@@ -963,7 +1538,21 @@ impl Node {
         use crate::frame::{FrameBuilder, FrameType};
 
         // Remove transfer from map
-        if let Some((_, _context)) = self.inner.transfers.remove(transfer_id) {
+        if let Some((_, context)) = self.inner.transfers.remove(transfer_id) {
+            // Drop the files of a directory transfer along with any partial data
+            self.inner.tree_files.retain(|_, file| {
+                let in_tree = file
+                    .tree_member
+                    .is_some_and(|member| member.tree_id == *transfer_id);
+                if in_tree && let Some(quarantine) = &file.quarantine {
+                    quarantine.discard();
+                }
+                !in_tree
+            });
+            if let Some(staging) = &context.tree_staging {
+                staging.discard();
+            }
+
             // Get the session if transfer has one
             let session_opt = self
                 .inner
@@ -978,7 +1567,7 @@ impl Node {
 
             // Send STREAM_CLOSE frame if we have a session
             if let Some(session) = session_opt {
-                let stream_id = ((transfer_id[0] as u16) << 8) | (transfer_id[1] as u16);
+                let stream_id = crate::node::file_transfer::transfer_stream_id(transfer_id);
                 let close_frame = FrameBuilder::new()
                     .frame_type(FrameType::StreamClose)
                     .stream_id(stream_id)
@@ -986,12 +1575,11 @@ impl Node {
                     .ok();
 
                 if let Some(frame) = close_frame
-                    && let Ok(encrypted) = session.encrypt_frame(&frame).await
+                    && let Ok(encrypted) = session.seal_frame(&frame).await
+                    && let Ok(transport) = self.transport_for(&session).await
                 {
                     // Send CLOSE frame (best-effort - don't fail cancellation if send fails)
-                    if let Some(transport) = self.inner.transport.lock().await.as_ref() {
-                        let _ = transport.send_to(&encrypted, session.peer_addr()).await;
-                    }
+                    let _ = transport.send_to(&encrypted, session.peer_addr()).await;
                 }
             }
 
@@ -1002,6 +1590,63 @@ impl Node {
         }
     }
 
+    /// Pause an outgoing transfer
+    ///
+    /// The sender stops streaming chunks until [`Node::resume_transfer`] is
+    /// called. Pausing an already paused transfer is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransferNotFound` if the transfer ID is unknown, or
+    /// `InvalidState` if the transfer is incoming or not in progress.
+    pub async fn pause_transfer(&self, transfer_id: &TransferId) -> Result<()> {
+        let context = self
+            .inner
+            .transfers
+            .get(transfer_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(NodeError::TransferNotFound(*transfer_id))?;
+        let mut session = context.transfer_session.write().await;
+
+        if session.direction != Direction::Send {
+            return Err(NodeError::InvalidState(
+                "Only outgoing transfers can be paused".into(),
+            ));
+        }
+        if !matches!(
+            session.state(),
+            TransferState::Transferring | TransferState::Paused
+        ) {
+            return Err(NodeError::InvalidState(
+                "Transfer is not in progress".into(),
+            ));
+        }
+
+        session.pause();
+        tracing::info!("Paused transfer: {:?}", hex::encode(&transfer_id[..8]));
+        Ok(())
+    }
+
+    /// Resume a paused transfer
+    ///
+    /// Resuming a transfer that is not paused is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TransferNotFound` if the transfer ID is unknown.
+    pub async fn resume_transfer(&self, transfer_id: &TransferId) -> Result<()> {
+        let context = self
+            .inner
+            .transfers
+            .get(transfer_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(NodeError::TransferNotFound(*transfer_id))?;
+
+        context.transfer_session.write().await.resume();
+        tracing::info!("Resumed transfer: {:?}", hex::encode(&transfer_id[..8]));
+        Ok(())
+    }
+
     /// Generate random transfer ID
     ///
     /// # Panics
@@ -1051,8 +1696,8 @@ impl Node {
             .map_err(|e| NodeError::Other(format!("Failed to build frame: {e:?}").into()))?;
 
         // Encrypt and send
-        let encrypted = connection.encrypt_frame(&frame).await?;
-        let transport = self.get_transport().await?;
+        let encrypted = connection.seal_frame(&frame).await?;
+        let transport = self.transport_for(&connection).await?;
         transport
             .send_to(&encrypted, connection.peer_addr())
             .await
@@ -1074,7 +1719,7 @@ impl Node {
 
 impl Node {
     /// Get transport layer
-    pub(crate) async fn get_transport(&self) -> Result<Arc<AsyncUdpTransport>> {
+    pub(crate) async fn get_transport(&self) -> Result<Arc<TransportManager>> {
         let guard = self.inner.transport.lock().await;
         guard
             .as_ref()
@@ -1083,6 +1728,55 @@ impl Node {
             )))
             .cloned()
     }
+
+    /// Get the transport a session's packets are currently sent over
+    pub(crate) async fn transport_for(
+        &self,
+        connection: &PeerConnection,
+    ) -> Result<Arc<dyn Transport>> {
+        let transport_type = connection.transport_type();
+        self.get_transport()
+            .await?
+            .transport(transport_type)
+            .await
+            .ok_or_else(|| {
+                NodeError::Transport(format!("No {transport_type} transport available").into())
+            })
+    }
+
+    /// Tell a newly connected peer which other transports this node listens on
+    ///
+    /// Sent once after session establishment so the peer can fail over to
+    /// TCP or WebSocket if UDP becomes unusable. Nothing is sent when the
+    /// node only runs UDP.
+    pub(crate) async fn advertise_transports(&self, connection: &PeerConnection) {
+        let Ok(manager) = self.get_transport().await else {
+            return;
+        };
+        let current = connection.transport_type();
+        let others: Vec<(TransportType, u16)> = manager
+            .local_addrs()
+            .await
+            .into_iter()
+            .filter(|(transport, _)| *transport != current)
+            .map(|(transport, addr)| (transport, addr.port()))
+            .collect();
+        if others.is_empty() {
+            return;
+        }
+
+        let result = match crate::node::connection::build_transports_frame(&others) {
+            Ok(frame) => self.send_encrypted_frame(connection, &frame).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::debug!(
+                "Failed to advertise transports to peer {}: {}",
+                hex::encode(&connection.peer_id[..8]),
+                e
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1250,6 +1944,36 @@ mod tests {
         assert_eq!(stats.unwrap().bytes_sent, 0);
     }
 
+    #[tokio::test]
+    async fn test_metrics_snapshot() {
+        let node = Node::new_random().await.unwrap();
+        let peer_id = [42u8; 32];
+        let conn = Arc::new(PeerConnection::new_for_test(
+            peer_id,
+            "127.0.0.1:5000".parse().unwrap(),
+        ));
+        conn.session.write().await.record_sent(1200);
+        node.inner.sessions.insert(peer_id, conn);
+
+        let metrics = node.metrics().await;
+        assert_eq!(metrics.get("wraith_sessions", &[]), Some(1.0));
+        assert_eq!(
+            metrics.get("wraith_transfers", &[("state", "transferring")]),
+            Some(0.0)
+        );
+        assert_eq!(metrics.get("wraith_handshake_failures", &[]), Some(0.0));
+        assert_eq!(
+            metrics.get(
+                "wraith_session_sent_bytes",
+                &[("peer", &hex::encode(peer_id))]
+            ),
+            Some(1200.0)
+        );
+        // Not started: no transport counters
+        assert_eq!(metrics.get("wraith_transport_sent_bytes", &[]), None);
+        assert!(metrics.encode().ends_with("# EOF\n"));
+    }
+
     #[tokio::test]
     async fn test_get_session_established_at() {
         let node = Node::new_random().await.unwrap();
@@ -1292,6 +2016,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_send_tree_to_legacy_peer() {
+        let node = Node::new_random().await.unwrap();
+        let peer_id = [43u8; 32];
+        node.inner.sessions.insert(
+            peer_id,
+            Arc::new(
+                PeerConnection::new_for_test(peer_id, "127.0.0.1:5000".parse().unwrap())
+                    .with_legacy(true),
+            ),
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), b"baseline").unwrap();
+        let err = node.send_tree(dir.path(), &peer_id).await.unwrap_err();
+        assert!(err.to_string().contains("directory transfers"));
+        assert!(node.inner.transfers.is_empty());
+    }
+
     #[tokio::test]
     async fn test_send_file_to_peers_empty() {
         let node = Node::new_random().await.unwrap();
@@ -1360,6 +2103,85 @@ mod tests {
         assert!(!node.inner.transfers.contains_key(&transfer_id));
     }
 
+    #[tokio::test]
+    async fn test_pause_resume_transfer() {
+        use crate::node::file_transfer::FileTransferContext;
+        use crate::node::progress::TransferStatus;
+        use wraith_files::tree_hash::FileTreeHash;
+
+        let node = Node::new_random().await.unwrap();
+        let transfer_id = [42u8; 32];
+
+        let transfer = crate::transfer::TransferSession::new_send(
+            transfer_id,
+            PathBuf::from("test.dat"),
+            1024,
+            256,
+        );
+        let session = Arc::new(RwLock::new(transfer));
+        let tree_hash = FileTreeHash {
+            root: [0u8; 32],
+            chunks: Vec::new(),
+        };
+        let context = Arc::new(FileTransferContext::new_send(
+            transfer_id,
+            Arc::clone(&session),
+            tree_hash,
+        ));
+        node.inner.transfers.insert(transfer_id, context);
+
+        // Not started yet
+        assert!(matches!(
+            node.pause_transfer(&transfer_id).await,
+            Err(NodeError::InvalidState(_))
+        ));
+
+        session.write().await.start();
+        node.pause_transfer(&transfer_id).await.unwrap();
+        let progress = node.get_transfer_progress(&transfer_id).await.unwrap();
+        assert_eq!(progress.status, TransferStatus::Paused);
+
+        node.resume_transfer(&transfer_id).await.unwrap();
+        assert_eq!(session.read().await.state(), TransferState::Transferring);
+
+        assert!(matches!(
+            node.pause_transfer(&[7u8; 32]).await,
+            Err(NodeError::TransferNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pause_incoming_transfer_rejected() {
+        use crate::node::file_transfer::FileTransferContext;
+        use wraith_files::tree_hash::FileTreeHash;
+
+        let node = Node::new_random().await.unwrap();
+        let transfer_id = [43u8; 32];
+
+        let mut transfer = crate::transfer::TransferSession::new_receive(
+            transfer_id,
+            PathBuf::from("test.dat"),
+            1024,
+            256,
+        );
+        transfer.start();
+        let tree_hash = FileTreeHash {
+            root: [0u8; 32],
+            chunks: Vec::new(),
+        };
+        let context = Arc::new(FileTransferContext::new_send(
+            transfer_id,
+            Arc::new(RwLock::new(transfer)),
+            tree_hash,
+        ));
+        node.inner.transfers.insert(transfer_id, context);
+
+        assert!(matches!(
+            node.pause_transfer(&transfer_id).await,
+            Err(NodeError::InvalidState(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_get_transfer_progress_none() {
         let node = Node::new_random().await.unwrap();
@@ -1397,6 +2219,84 @@ mod tests {
         assert!(progress.is_some());
     }
 
+    #[tokio::test]
+    async fn test_start_with_transport_skips_network_probing() {
+        use wraith_transport::simulated::SimulatedNetwork;
+
+        let config = NodeConfig {
+            discovery: crate::node::DiscoveryConfig {
+                enable_mdns: true,
+                enable_nat_traversal: true,
+                enable_relay: true,
+                enable_port_mapping: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let node = Node::new_with_config(config).await.unwrap();
+        let network = SimulatedNetwork::new(7);
+        let transport = network.bind("10.0.0.1:7000".parse().unwrap()).unwrap();
+        node.start_with_transport(Arc::new(transport))
+            .await
+            .unwrap();
+
+        let discovery = node.inner.discovery.lock().await.clone().unwrap();
+        assert!(!discovery.mdns_running().await);
+        assert_eq!(discovery.nat_type().await, None);
+        assert!(discovery.port_mapping().is_none());
+        assert_eq!(
+            node.listen_addr().await.unwrap(),
+            "10.0.0.1:7000".parse().unwrap()
+        );
+        node.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_establish_session_falls_back_past_wrong_key() {
+        let config = || NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            discovery: crate::node::DiscoveryConfig {
+                enable_mdns: false,
+                enable_nat_traversal: false,
+                enable_relay: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let node = Node::new_with_config(config()).await.unwrap();
+        let peer = Node::new_with_config(config()).await.unwrap();
+        let impostors = [
+            Node::new_with_config(config()).await.unwrap(),
+            Node::new_with_config(config()).await.unwrap(),
+        ];
+        for n in [&node, &peer, &impostors[0], &impostors[1]] {
+            n.start().await.unwrap();
+        }
+        let peer_key = *peer.x25519_public_key();
+        let peer_addr = peer.listen_addr().await.unwrap();
+
+        // With no other candidate, the mismatch is reported
+        let impostor_addr = impostors[0].listen_addr().await.unwrap();
+        let result = node
+            .establish_session_with_candidates(&peer_key, &[impostor_addr])
+            .await;
+        assert!(
+            matches!(result, Err(NodeError::PeerKeyMismatch { .. })),
+            "{result:?}"
+        );
+
+        // A candidate answering with another key is skipped
+        let impostor_addr = impostors[1].listen_addr().await.unwrap();
+        node.establish_session_with_candidates(&peer_key, &[impostor_addr, peer_addr])
+            .await
+            .unwrap();
+        assert_eq!(node.get_session_addr(&peer_key), Some(peer_addr));
+
+        for n in [&node, &peer, &impostors[0], &impostors[1]] {
+            n.stop().await.unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn test_node_clone() {
         let node = Node::new_random().await.unwrap();
@@ -7,7 +7,6 @@ use crate::node::config::{MimicryMode, TimingMode};
 use crate::node::session::PeerConnection;
 use crate::node::{Node, NodeError};
 use std::time::Duration;
-use wraith_transport::transport::Transport;
 
 /// Protocol types for mimicry
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -67,6 +66,11 @@ impl Node {
         strategy.apply(data)
     }
 
+    /// Whether timing obfuscation delays outgoing packets
+    pub fn timing_obfuscation_enabled(&self) -> bool {
+        !matches!(self.inner.config.obfuscation.timing_mode, TimingMode::None)
+    }
+
     /// Get timing delay for next packet
     ///
     /// Returns the delay to apply before sending the next packet.
@@ -151,7 +155,7 @@ impl Node {
         let wrapped = self.wrap_protocol(&packet)?;
 
         // 4. Send via transport
-        let transport = self.get_transport().await?;
+        let transport = self.transport_for(session).await?;
         transport
             .send_to(&wrapped, session.peer_addr())
             .await
@@ -356,6 +360,7 @@ mod tests {
         let delay = node.get_timing_delay();
 
         assert_eq!(delay, Duration::ZERO);
+        assert!(!node.timing_obfuscation_enabled());
     }
 
     #[tokio::test]
@@ -512,6 +517,7 @@ mod tests {
         let delay = node.get_timing_delay();
 
         assert_eq!(delay, Duration::from_millis(10));
+        assert!(node.timing_obfuscation_enabled());
     }
 
     #[tokio::test]
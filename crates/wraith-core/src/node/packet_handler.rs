@@ -6,48 +6,112 @@
 //! # Packet Flow
 //!
 //! ```text
-//! UDP Socket → recv_from → handle_incoming_packet → dispatch_frame → handler
-//!                                |
-//!                                └→ handshake → SessionManager
+//! UDP/TCP/WebSocket → recv_from → handle_incoming_packet → dispatch_frame → handler
+//!                                        |
+//!                                        └→ handshake → SessionManager
 //! ```
 
+use crate::compression::{compress_chunk, decompress_chunk, is_precompressed};
+use crate::fec::{FecEncoder, FecRepair, FecSource, MAX_FEC_SOURCE_PAYLOAD, repair_count_for_loss};
+use crate::frame::compat::v2_frame_to_v1;
+use crate::frame::frame_v2::FrameV2;
+use crate::frame::types_v2::FrameTypeV2;
 use crate::frame::{Frame, FrameBuilder, FrameType};
 use crate::node::Node;
+use crate::node::acceptance::{IncomingTransfer, TransferDecision};
 use crate::node::config::CoverTrafficDistribution;
 use crate::node::error::{NodeError, Result};
-use crate::node::file_transfer::FileTransferContext;
+use crate::node::file_transfer::{
+    CONTROL_CHUNK_HASHES, CONTROL_CHUNK_HASHES_REQUEST, CONTROL_CHUNK_REQUEST,
+    CONTROL_TRANSFER_ACCEPT, CONTROL_TRANSPORTS, ChunkHashLayer, ChunkVerdict, FileMetadata,
+    FileTransferContext, QuarantinedFile, sanitize_file_name, transfer_stream_id,
+    unique_destination,
+};
 use crate::node::routing::extract_connection_id;
-use crate::node::session::{HandshakePacket, PeerConnection};
-use crate::transfer::TransferSession;
+use crate::node::session::{HandshakePacket, PeerConnection, PeerId};
+use crate::node::tree_transfer::{
+    MAX_PENDING_MANIFESTS, ManifestAssembly, TreeManifest, TreeMember, TreeStaging,
+    is_manifest_segment, parse_manifest_segment,
+};
+use crate::transfer::{TransferSession, TransferState};
 use crate::{ConnectionId, HandshakePhase, SessionState};
 use getrandom::getrandom;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock, oneshot};
 use wraith_files::chunker::FileChunker;
+use wraith_transport::factory::TransportType;
 use wraith_transport::transport::Transport;
 
+/// Room above the chunk size for framing, AEAD overhead and padding
+const PACKET_HEADROOM: usize = 4096;
+
+/// How often a paused sender checks whether it may continue
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Chunks streamed per `send_batch` call when timing obfuscation is off
+const SEND_BATCH_CHUNKS: usize = 32;
+
+/// Chunk frames sealed for the wire and not yet sent
+#[derive(Default)]
+struct ChunkQueue {
+    /// Datagrams in send order, FEC repairs included
+    datagrams: Vec<Vec<u8>>,
+    /// Queued chunks: (index, chunk length, chunk frame length)
+    chunks: Vec<(u64, usize, usize)>,
+}
+
+/// Extract the challenge from a decrypted PATH_CHALLENGE frame
+///
+/// PATH_CHALLENGE is answered over the path it arrived on, so it is picked
+/// out before the frame reaches the transport-agnostic dispatchers.
+fn path_challenge(frame_bytes: &[u8], is_v2: bool) -> Option<[u8; 8]> {
+    if is_v2 {
+        let frame = FrameV2::parse(frame_bytes).ok()?;
+        if frame.frame_type() != FrameTypeV2::PathChallenge {
+            return None;
+        }
+        frame.payload().get(..8)?.try_into().ok()
+    } else {
+        let frame = Frame::parse(frame_bytes).ok()?;
+        if frame.frame_type() != FrameType::PathChallenge {
+            return None;
+        }
+        frame.payload().get(..8)?.try_into().ok()
+    }
+}
+
 impl Node {
     /// Packet receive loop - main event loop for incoming packets
     ///
-    /// Continuously receives packets from the transport layer and dispatches
+    /// Receives packets from every transport the node runs and dispatches
     /// them for processing. Runs until the node is stopped.
     pub(crate) async fn packet_receive_loop(&self) {
-        let mut buf = vec![0u8; 65536];
+        let Ok(manager) = self.get_transport().await else {
+            return;
+        };
+
+        let mut receivers = tokio::task::JoinSet::new();
+        for transport in manager.transports().await {
+            let node = self.clone();
+            receivers.spawn(async move { node.transport_receive_loop(transport).await });
+        }
+        while receivers.join_next().await.is_some() {}
+    }
+
+    /// Receive loop for a single transport
+    async fn transport_receive_loop(&self, transport: Arc<dyn Transport>) {
+        let via = transport.transport_type();
+        // Stream transports carry whole chunk frames, which exceed a datagram
+        let buf_len = (self.inner.config.transfer.chunk_size + PACKET_HEADROOM).max(65536);
+        let mut buf = vec![0u8; buf_len];
         loop {
             if !self.is_running() {
                 break;
             }
 
-            let transport = {
-                let guard = self.inner.transport.lock().await;
-                match guard.as_ref() {
-                    Some(t) => Arc::clone(t),
-                    None => break,
-                }
-            };
-
             match tokio::time::timeout(Duration::from_millis(100), transport.recv_from(&mut buf))
                 .await
             {
@@ -55,13 +119,14 @@ impl Node {
                     let packet_data = buf[..size].to_vec();
                     let node = self.clone();
                     tokio::spawn(async move {
-                        if let Err(e) = node.handle_incoming_packet(packet_data, from).await {
+                        if let Err(e) = node.handle_incoming_packet(packet_data, from, via).await {
                             tracing::debug!("Error handling packet from {}: {}", from, e);
                         }
                     });
                 }
+                Ok(Err(_)) if transport.is_closed() => break,
                 Ok(Err(e)) => {
-                    tracing::warn!("Error receiving packet: {}", e);
+                    tracing::warn!("Error receiving {} packet: {}", via, e);
                 }
                 Err(_) => {
                     // Timeout - continue loop
@@ -135,11 +200,13 @@ impl Node {
     /// Handle incoming packet from network
     ///
     /// Unwraps protocol obfuscation, routes packet by Connection ID,
-    /// and dispatches to appropriate handler.
+    /// and dispatches to appropriate handler. `via` is the transport the
+    /// packet arrived on.
     pub(crate) async fn handle_incoming_packet(
         &self,
         data: Vec<u8>,
         from: SocketAddr,
+        via: TransportType,
     ) -> Result<()> {
         use crate::node::security_monitor::{SecurityEvent, SecurityEventType};
 
@@ -165,8 +232,16 @@ impl Node {
             tokio::time::sleep(backoff_delay).await;
         }
 
+        // Unwrap any protocol mimicry
+        let unwrapped = self.unwrap_protocol(&data)?;
+
+        // Only new connections count against the per-IP connection rate limit;
+        // packets of established sessions must not be throttled by it
+        let established = extract_connection_id(&unwrapped)
+            .and_then(|connection_id| self.inner.routing.lookup(connection_id));
+
         // Check connection rate limit
-        if !self.inner.rate_limiter.check_connection(source_ip) {
+        if established.is_none() && !self.inner.rate_limiter.check_connection(source_ip) {
             tracing::warn!("Rate limit exceeded for IP: {}", source_ip);
             self.inner.ip_reputation.record_failure(source_ip).await;
             let event = SecurityEvent::new(SecurityEventType::RateLimitExceeded, source_ip)
@@ -175,9 +250,6 @@ impl Node {
             return Ok(()); // Silently drop
         }
 
-        // Unwrap any protocol mimicry
-        let unwrapped = self.unwrap_protocol(&data)?;
-
         // Check for pending handshake matching this source
         let matching_addr = self
             .inner
@@ -207,15 +279,38 @@ impl Node {
 
         // Route by Connection ID
         match extract_connection_id(&unwrapped) {
-            Some(connection_id) => {
-                if let Some(conn) = self.inner.routing.lookup(connection_id) {
+            Some(_) => {
+                if let Some(conn) = established {
                     conn.touch();
                     match conn.decrypt_frame(&unwrapped[8..]).await {
                         Ok(frame_bytes) => {
+                            conn.session
+                                .write()
+                                .await
+                                .record_received(unwrapped.len() as u64);
+                            if conn.take_advertisement_pending() {
+                                let node = self.clone();
+                                let conn = Arc::clone(&conn);
+                                tokio::spawn(async move { node.advertise_transports(&conn).await });
+                            }
+                            if let Some(init) = conn.begin_rekey_if_due() {
+                                self.spawn_rekey(conn.peer_id, init);
+                            }
                             let node = self.clone();
                             let peer_id = conn.peer_id;
+                            let wire_format = conn.wire_format;
                             tokio::spawn(async move {
-                                if let Err(e) = node.dispatch_frame(frame_bytes, peer_id).await {
+                                let result = if let Some(challenge) =
+                                    path_challenge(&frame_bytes, wire_format.is_v2())
+                                {
+                                    node.handle_path_challenge(challenge, peer_id, from, via)
+                                        .await
+                                } else if wire_format.is_v2() {
+                                    node.dispatch_frame_v2(frame_bytes, peer_id).await
+                                } else {
+                                    node.dispatch_frame(frame_bytes, peer_id).await
+                                };
+                                if let Err(e) = result {
                                     tracing::warn!("Error handling frame: {}", e);
                                 }
                             });
@@ -226,14 +321,20 @@ impl Node {
                     }
                 } else {
                     // Unknown Connection ID - might be a handshake initiation
-                    if let Err(e) = self.handle_handshake_initiation(&unwrapped, from).await {
+                    if let Err(e) = self
+                        .handle_handshake_initiation(&unwrapped, from, via)
+                        .await
+                    {
                         tracing::warn!("Handshake initiation failed from {}: {}", from, e);
                     }
                 }
             }
             None => {
                 // No Connection ID - likely a handshake initiation
-                if let Err(e) = self.handle_handshake_initiation(&unwrapped, from).await {
+                if let Err(e) = self
+                    .handle_handshake_initiation(&unwrapped, from, via)
+                    .await
+                {
                     tracing::warn!("Handshake initiation failed from {}: {}", from, e);
                 }
             }
@@ -251,11 +352,25 @@ impl Node {
         let frame = Frame::parse(&frame_bytes)
             .map_err(|e| NodeError::Other(format!("Failed to parse frame: {e}").into()))?;
 
+        // Frames of open byte streams never reach the transfer handlers
+        if self.route_byte_stream_frame(&frame, peer_id) {
+            return Ok(());
+        }
+
         match frame.frame_type() {
-            FrameType::StreamOpen => self.handle_stream_open_frame(frame).await,
-            FrameType::Data => self.handle_data_frame(frame).await,
+            FrameType::StreamOpen if frame.payload().is_empty() => {
+                self.handle_byte_stream_open(frame.stream_id(), peer_id)
+                    .await;
+                Ok(())
+            }
+            FrameType::StreamOpen => self.handle_stream_open_frame(frame, peer_id).await,
+            FrameType::Data => self.handle_data_frame(frame, peer_id).await,
+            FrameType::Control => self.handle_control_frame(frame, peer_id).await,
+            FrameType::Ping => self.handle_ping_frame(frame, peer_id).await,
             FrameType::Pong => self.handle_pong_frame(frame, peer_id).await,
             FrameType::PathResponse => self.handle_path_response_frame(frame, peer_id).await,
+            FrameType::Rekey => self.handle_rekey_frame(frame, peer_id).await,
+            FrameType::StreamReset => self.handle_stream_reset_frame(frame, peer_id).await,
             FrameType::StreamClose => {
                 tracing::debug!("Received StreamClose frame");
                 Ok(())
@@ -267,19 +382,149 @@ impl Node {
         }
     }
 
+    /// Dispatch a v2 frame
+    ///
+    /// Frames with a v1 equivalent are re-encoded through the compat layer
+    /// and take the same handlers as v1 frames. v2-only frame types are
+    /// handled here.
+    pub(crate) async fn dispatch_frame_v2(
+        &self,
+        frame_bytes: Vec<u8>,
+        peer_id: crate::node::session::PeerId,
+    ) -> Result<()> {
+        let parse_error = |e| NodeError::Other(format!("Failed to parse frame: {e}").into());
+
+        if let Some(v1_bytes) = v2_frame_to_v1(&frame_bytes).map_err(parse_error)? {
+            let frame = FrameV2::parse(&frame_bytes).map_err(parse_error)?;
+            if frame.flags().is_fec() && frame.frame_type() == FrameTypeV2::Data {
+                return self.handle_fec_source_frame(frame, v1_bytes, peer_id).await;
+            }
+            return self.dispatch_frame(v1_bytes, peer_id).await;
+        }
+
+        let frame = FrameV2::parse(&frame_bytes).map_err(parse_error)?;
+        match frame.frame_type() {
+            FrameTypeV2::FecRepair => self.handle_fec_repair_frame(frame, peer_id).await,
+            _ => {
+                tracing::debug!("Unhandled v2 frame type: {:?}", frame.frame_type());
+                Ok(())
+            }
+        }
+    }
+
+    /// Handle an FEC-protected data frame
+    ///
+    /// The frame is delivered as usual and recorded as a source symbol, which
+    /// may complete a group whose repair frames arrived first.
+    async fn handle_fec_source_frame(
+        &self,
+        frame: FrameV2<'_>,
+        v1_bytes: Vec<u8>,
+        peer_id: PeerId,
+    ) -> Result<()> {
+        let stream_id = frame.stream_id();
+        let mut recovered = Vec::new();
+        if let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+        {
+            let mut decoder = connection.fec_decoder.lock().await;
+            if decoder.was_recovered(stream_id, frame.sequence()) {
+                tracing::trace!(
+                    "Dropping late frame {} on stream {} already rebuilt by FEC",
+                    frame.sequence(),
+                    stream_id
+                );
+                return Ok(());
+            }
+            match decoder.on_source(stream_id, FecSource::from_frame(&frame)) {
+                Ok(sources) => recovered = sources,
+                Err(e) => tracing::debug!("FEC group on stream {} unusable: {}", stream_id, e),
+            }
+        }
+
+        let result = self.dispatch_frame(v1_bytes, peer_id).await;
+        self.dispatch_fec_recovered(stream_id, recovered, peer_id)
+            .await;
+        result
+    }
+
+    /// Handle FEC_REPAIR frame
+    ///
+    /// Rebuilds and delivers any frames of the repaired group that were lost.
+    async fn handle_fec_repair_frame(&self, frame: FrameV2<'_>, peer_id: PeerId) -> Result<()> {
+        let repair = FecRepair::from_frame(&frame)
+            .map_err(|e| NodeError::Other(format!("Invalid FEC repair frame: {e}").into()))?;
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+        else {
+            return Ok(());
+        };
+
+        let stream_id = frame.stream_id();
+        let recovered = connection
+            .fec_decoder
+            .lock()
+            .await
+            .on_repair(stream_id, repair)
+            .map_err(|e| NodeError::Other(format!("FEC recovery failed: {e}").into()))?;
+        self.dispatch_fec_recovered(stream_id, recovered, peer_id)
+            .await;
+        Ok(())
+    }
+
+    /// Deliver data frames rebuilt from FEC repair symbols
+    async fn dispatch_fec_recovered(
+        &self,
+        stream_id: u32,
+        recovered: Vec<FecSource>,
+        peer_id: PeerId,
+    ) {
+        for source in recovered {
+            tracing::debug!(
+                "Recovered frame {} on stream {} from FEC repair",
+                source.sequence,
+                stream_id
+            );
+            let v1_bytes = source
+                .build_frame(stream_id)
+                .ok()
+                .and_then(|frame| v2_frame_to_v1(&frame).ok().flatten());
+            let Some(v1_bytes) = v1_bytes else {
+                tracing::debug!("Recovered frame {} has no v1 encoding", source.sequence);
+                continue;
+            };
+            if let Err(e) = self.dispatch_frame(v1_bytes, peer_id).await {
+                tracing::debug!("Failed to handle recovered frame: {}", e);
+            }
+        }
+    }
+
     /// Handle handshake initiation (responder side)
     ///
     /// When a packet arrives that doesn't match a known Connection ID,
-    /// it may be a Noise_XX handshake initiation.
+    /// it may be a Noise_XX handshake initiation. The handshake is answered,
+    /// and the session carried, over the transport it arrived on.
     pub(crate) async fn handle_handshake_initiation(
         &self,
         msg1: &[u8],
         peer_addr: SocketAddr,
+        via: TransportType,
     ) -> Result<crate::node::session::SessionId> {
         use crate::node::security_monitor::{SecurityEvent, SecurityEventType};
 
         let source_ip = peer_addr.ip();
-        let transport = self.get_transport().await?;
+        let transport = self
+            .get_transport()
+            .await?
+            .transport(via)
+            .await
+            .ok_or_else(|| NodeError::Transport(format!("No {via} transport available").into()))?;
 
         tracing::info!(
             "Handling handshake initiation from {} ({} bytes)",
@@ -304,6 +549,7 @@ impl Node {
         // Perform Noise_XX handshake as responder
         let handshake_result = crate::node::session::perform_handshake_responder(
             self.inner.identity.x25519_keypair(),
+            &self.inner.config.handshake_policy(),
             msg1,
             peer_addr,
             transport.as_ref(),
@@ -315,7 +561,7 @@ impl Node {
         self.inner.pending_handshakes.remove(&peer_addr);
 
         // Handle handshake failure
-        let (crypto, session_id, peer_id) = match handshake_result {
+        let (crypto, session_id, peer_id, negotiated) = match handshake_result {
             Ok(result) => result,
             Err(e) => {
                 tracing::warn!("Handshake failed from {}: {}", peer_addr, e);
@@ -333,7 +579,13 @@ impl Node {
         let connection_id = ConnectionId::from_bytes(connection_id_bytes);
 
         // Create connection
-        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto);
+        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto)
+            .with_transport(via)
+            .with_crypto_suite(negotiated.suite)
+            .with_wire_format(negotiated.wire_format)
+            .with_legacy(negotiated.legacy)
+            .with_capabilities(negotiated.capabilities)
+            .with_rekey_config(self.inner.config.crypto.rekey.clone());
 
         // Transition through handshake states
         connection
@@ -355,21 +607,36 @@ impl Node {
             .insert(peer_id, Arc::clone(&connection_arc));
 
         let cid_u64 = u64::from_be_bytes(connection_id_bytes);
-        self.inner.routing.add_route(cid_u64, connection_arc);
+        self.inner
+            .routing
+            .add_route(cid_u64, Arc::clone(&connection_arc));
 
         tracing::info!(
-            "Session established as responder with peer {}, session: {}, route: {:016x}",
+            "Session established as responder with peer {} over {}, session: {}, route: {:016x}",
             hex::encode(&peer_id[..8]),
+            via,
             hex::encode(&session_id[..8]),
             cid_u64
         );
 
+        self.advertise_transports(&connection_arc).await;
+
         Ok(session_id)
     }
 
     /// Handle StreamOpen frame (file transfer metadata)
-    pub(crate) async fn handle_stream_open_frame(&self, frame: Frame<'_>) -> Result<()> {
-        let metadata = crate::node::file_transfer::FileMetadata::deserialize(frame.payload())?;
+    pub(crate) async fn handle_stream_open_frame(
+        &self,
+        frame: Frame<'_>,
+        peer_id: PeerId,
+    ) -> Result<()> {
+        if is_manifest_segment(frame.payload()) {
+            return self.handle_manifest_segment(frame, peer_id).await;
+        }
+
+        let metadata = FileMetadata::deserialize(frame.payload())?;
+        metadata.validate()?;
+        let stream_id = frame.stream_id();
 
         tracing::info!(
             "Received file transfer request: {} ({} bytes)",
@@ -377,171 +644,1344 @@ impl Node {
             metadata.file_size
         );
 
+        if self.inner.transfers.contains_key(&metadata.transfer_id) {
+            tracing::debug!(
+                "Ignoring duplicate STREAM_OPEN for transfer {}",
+                hex::encode(&metadata.transfer_id[..8])
+            );
+            return Ok(());
+        }
+
+        // Never trust the sender's name: reduce it to one safe component
+        let file_name = sanitize_file_name(&metadata.file_name);
+        let proposed_path =
+            unique_destination(&self.inner.config.transfer.download_dir, &file_name)?;
+        let output_path = match self
+            .decide_incoming_transfer(peer_id, &metadata, None, proposed_path.clone())
+            .await
+        {
+            TransferDecision::Accept => proposed_path,
+            TransferDecision::Redirect(path) => path,
+            TransferDecision::Reject(reason) => {
+                return self
+                    .reject_incoming_transfer(peer_id, stream_id, &metadata.transfer_id, &reason)
+                    .await;
+            }
+        };
+
         // Create receive transfer session
         let mut transfer = TransferSession::new_receive(
             metadata.transfer_id,
-            std::path::PathBuf::from(&metadata.file_name),
+            output_path.clone(),
             metadata.file_size,
             metadata.chunk_size as usize,
         );
         transfer.start();
 
-        // Create file reassembler
+        // Chunks land in the quarantine directory until the root hash verifies
+        let quarantine = QuarantinedFile::new(
+            &self.inner.config.transfer.quarantine_dir(),
+            &metadata.transfer_id,
+            output_path,
+        )?;
         let reassembler = wraith_files::chunker::FileReassembler::new(
-            &metadata.file_name,
+            &quarantine.part_path,
             metadata.file_size,
             metadata.chunk_size as usize,
         )
         .map_err(|e| NodeError::Io(e.to_string()))?;
 
-        // Create tree hash (root only for now)
+        // Root only until the sender's chunk hash layer arrives and verifies
         let tree_hash = wraith_files::tree_hash::FileTreeHash {
             root: metadata.root_hash,
             chunks: Vec::new(),
         };
+        // Baseline senders ship no hash layer: only the root is checked, on completion
+        let legacy = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .is_some_and(|connection| connection.legacy);
+        let chunk_hashes = (!legacy
+            && crate::node::file_transfer::announces_chunk_hashes(frame.payload()))
+        .then(|| ChunkHashLayer::new(metadata.root_hash, metadata.total_chunks));
+
+        // Corrupt chunks count against the sender in the node's peer coordinator
+        self.track_sender(peer_id).await;
 
         // Store transfer context
-        let context = Arc::new(FileTransferContext::new_receive(
+        let mut context = FileTransferContext::new_receive(
             metadata.transfer_id,
             Arc::new(RwLock::new(transfer)),
             Arc::new(Mutex::new(reassembler)),
             tree_hash,
-        ));
+        )
+        .with_sender(peer_id)
+        .with_coordinator(Arc::clone(&self.inner.peer_coordinator))
+        .with_quarantine(quarantine);
+        if let Some(layer) = chunk_hashes {
+            context = context.with_chunk_hashes(layer);
+        }
+        let context = Arc::new(context);
         self.inner.transfers.insert(metadata.transfer_id, context);
 
-        Ok(())
-    }
-
-    /// Handle PONG frame (ping response)
-    pub(crate) async fn handle_pong_frame(
-        &self,
-        frame: Frame<'_>,
-        peer_id: crate::node::session::PeerId,
-    ) -> Result<()> {
-        let sequence = frame.sequence();
-
-        // Look up pending ping by (peer_id, sequence)
-        if let Some((_key, tx)) = self.inner.pending_pings.remove(&(peer_id, sequence)) {
-            // Send timestamp back to waiting ping_session
-            let _ = tx.send(std::time::Instant::now());
-            tracing::trace!("PONG received from {:?}, seq {}", peer_id, sequence);
-        } else {
-            tracing::debug!(
-                "Received unexpected PONG from {:?}, seq {}",
-                peer_id,
-                sequence
-            );
+        // Baseline senders stream without waiting for a verdict
+        if !legacy {
+            let accept_frame = crate::node::file_transfer::build_transfer_accept_frame(
+                stream_id,
+                &metadata.transfer_id,
+            )?;
+            self.send_frame_to_peer(peer_id, &accept_frame).await;
         }
 
         Ok(())
     }
 
-    /// Handle Data frame (file chunk)
-    pub(crate) async fn handle_data_frame(&self, frame: Frame<'_>) -> Result<()> {
-        let chunk_index = frame.sequence() as u64;
-        let chunk_data = frame.payload();
-        let stream_id = frame.stream_id();
+    /// Handle a STREAM_OPEN segment of a directory transfer manifest
+    ///
+    /// Segments are collected until the manifest is complete; it is then
+    /// validated, its signature checked and the transfer offered to the acceptor.
+    async fn handle_manifest_segment(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let segment = parse_manifest_segment(frame.payload())?;
+        let transfer_id = segment.transfer_id;
 
-        // Check if there's a pending chunk request waiting for this data
-        let chunk_key = (stream_id, chunk_index);
-        if let Some((_, sender)) = self.inner.pending_chunks.remove(&chunk_key) {
-            // Send chunk data to waiting request_chunk_from_peer()
-            let _ = sender.send(chunk_data.to_vec());
-            tracing::trace!(
-                "Chunk {} (stream {}) routed to pending request",
-                chunk_index,
-                stream_id
+        if self.inner.transfers.contains_key(&transfer_id) {
+            tracing::debug!(
+                "Ignoring duplicate manifest segment for transfer {}",
+                hex::encode(&transfer_id[..8])
             );
             return Ok(());
         }
 
-        // No pending request - handle as async chunk push (e.g., from seeder)
-        // Find matching transfer by stream ID
-        let mut matched_context = None;
-        for entry in self.inner.transfers.iter() {
-            let tid = entry.key();
-            let tid_stream_id = ((tid[0] as u16) << 8) | (tid[1] as u16);
-            if tid_stream_id == stream_id {
-                matched_context = Some(entry.value().clone());
-                break;
+        if !self.inner.pending_manifests.contains_key(&transfer_id) {
+            // Abandoned manifests must not block new transfers forever
+            let timeout = self.inner.config.transfer.acceptance_timeout;
+            self.inner
+                .pending_manifests
+                .retain(|_, assembly| !assembly.is_expired(timeout));
+            if self.inner.pending_manifests.len() >= MAX_PENDING_MANIFESTS {
+                return Err(NodeError::invalid_state(
+                    "Too many pending directory manifests",
+                ));
             }
+            let assembly = ManifestAssembly::new(peer_id, segment.total_len)?;
+            self.inner
+                .pending_manifests
+                .entry(transfer_id)
+                .or_insert(assembly);
         }
 
-        let context = matched_context.ok_or_else(|| {
-            NodeError::InvalidState(format!("No transfer for stream_id {stream_id}").into())
-        })?;
-        let transfer_id = context.transfer_id;
+        let manifest_bytes = {
+            let Some(mut assembly) = self.inner.pending_manifests.get_mut(&transfer_id) else {
+                return Ok(());
+            };
+            if *assembly.peer_id() != peer_id {
+                tracing::warn!(
+                    "Ignoring manifest segment for transfer {} from unrelated peer {}",
+                    hex::encode(&transfer_id[..8]),
+                    hex::encode(&peer_id[..8])
+                );
+                return Ok(());
+            }
+            match assembly.insert(&segment)? {
+                Some(bytes) => bytes,
+                None => return Ok(()),
+            }
+        };
+        self.inner.pending_manifests.remove(&transfer_id);
 
-        // Write chunk to reassembler
-        if let Some(reassembler_arc) = &context.reassembler {
-            reassembler_arc
-                .lock()
-                .await
-                .write_chunk(chunk_index, chunk_data)
-                .map_err(|e| NodeError::Io(e.to_string()))?;
+        let manifest = TreeManifest::deserialize(&manifest_bytes)?;
+        if manifest.transfer_id != transfer_id {
+            return Err(NodeError::invalid_state("Manifest transfer ID mismatch"));
         }
+        manifest.validate()?;
+        manifest.verify_signature()?;
 
-        // Verify chunk hash if available
-        if chunk_index < context.tree_hash.chunks.len() as u64 {
-            let computed_hash = blake3::hash(chunk_data);
-            if computed_hash.as_bytes() != &context.tree_hash.chunks[chunk_index as usize] {
-                return Err(NodeError::InvalidState(
-                    "Chunk hash verification failed".into(),
-                ));
+        self.accept_tree_transfer(Arc::new(manifest), peer_id, frame.stream_id())
+            .await
+    }
+
+    /// Offer a verified directory manifest to the acceptor and prepare to receive it
+    async fn accept_tree_transfer(
+        &self,
+        manifest: Arc<TreeManifest>,
+        peer_id: PeerId,
+        stream_id: u16,
+    ) -> Result<()> {
+        let metadata = manifest.summary_metadata();
+        tracing::info!(
+            "Received directory transfer request: {} ({} entries, {} bytes)",
+            metadata.file_name,
+            manifest.entries.len(),
+            metadata.file_size
+        );
+
+        let dir_name = sanitize_file_name(&manifest.root_name);
+        let proposed_path =
+            unique_destination(&self.inner.config.transfer.download_dir, &dir_name)?;
+        let output_path = match self
+            .decide_incoming_transfer(
+                peer_id,
+                &metadata,
+                Some(Arc::clone(&manifest)),
+                proposed_path.clone(),
+            )
+            .await
+        {
+            TransferDecision::Accept => proposed_path,
+            TransferDecision::Redirect(path) => path,
+            TransferDecision::Reject(reason) => {
+                return self
+                    .reject_incoming_transfer(peer_id, stream_id, &metadata.transfer_id, &reason)
+                    .await;
             }
-        }
+        };
 
-        // Update transfer progress
-        let mut transfer = context.transfer_session.write().await;
-        transfer.mark_chunk_transferred(chunk_index, chunk_data.len());
+        // Directories and empty files are created up front, off the async runtime
+        let quarantine_dir = self.inner.config.transfer.quarantine_dir();
+        let staging_manifest = Arc::clone(&manifest);
+        let staging_path = output_path.clone();
+        let staging = tokio::task::spawn_blocking(move || {
+            TreeStaging::new(&quarantine_dir, staging_manifest, staging_path)
+        })
+        .await
+        .map_err(|e| NodeError::Other(format!("Staging task failed: {e}").into()))??;
 
-        if transfer.is_complete() {
-            tracing::info!(
-                "File transfer {:?} completed ({} bytes)",
-                hex::encode(&transfer_id[..8]),
-                transfer.file_size
-            );
+        let mut transfer = TransferSession::new_receive(
+            metadata.transfer_id,
+            output_path,
+            metadata.file_size,
+            metadata.chunk_size as usize,
+        )
+        .with_chunk_count(metadata.total_chunks);
+        transfer.start();
+
+        // Every file of the tree attributes corrupt chunks to the sender
+        self.track_sender(peer_id).await;
+
+        // No reassembler: each file of the tree gets its own context (see `open_tree_file`)
+        let context = FileTransferContext::new_send(
+            metadata.transfer_id,
+            Arc::new(RwLock::new(transfer)),
+            wraith_files::tree_hash::FileTreeHash {
+                root: metadata.root_hash,
+                chunks: Vec::new(),
+            },
+        )
+        .with_sender(peer_id)
+        .with_coordinator(Arc::clone(&self.inner.peer_coordinator))
+        .with_tree_staging(Arc::new(staging));
+        self.inner
+            .transfers
+            .insert(metadata.transfer_id, Arc::new(context));
+
+        let accept_frame = crate::node::file_transfer::build_transfer_accept_frame(
+            stream_id,
+            &metadata.transfer_id,
+        )?;
+        self.send_frame_to_peer(peer_id, &accept_frame).await;
+
+        Ok(())
+    }
+
+    /// Track the sender of an incoming transfer in the node's peer coordinator
+    async fn track_sender(&self, peer_id: PeerId) {
+        let address = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|connection| connection.peer_addr());
+        if let Some(address) = address {
+            self.inner
+                .peer_coordinator
+                .ensure_peer(peer_id, address)
+                .await;
         }
+    }
 
+    /// Tell the sender an incoming transfer was rejected
+    async fn reject_incoming_transfer(
+        &self,
+        peer_id: PeerId,
+        stream_id: u16,
+        transfer_id: &[u8; 32],
+        reason: &str,
+    ) -> Result<()> {
+        tracing::info!(
+            "Rejected transfer {} from peer {}: {}",
+            hex::encode(&transfer_id[..8]),
+            hex::encode(&peer_id[..8]),
+            reason
+        );
+        let reject_frame = crate::node::file_transfer::build_transfer_reject_frame(
+            stream_id,
+            transfer_id,
+            reason,
+        )?;
+        self.send_frame_to_peer(peer_id, &reject_frame).await;
         Ok(())
     }
 
-    /// Handle PATH_RESPONSE frame (connection migration)
-    pub(crate) async fn handle_path_response_frame(
+    /// Consult the transfer acceptor about an incoming transfer
+    ///
+    /// The acceptor runs on the blocking pool since it may prompt the user.
+    async fn decide_incoming_transfer(
+        &self,
+        peer_id: PeerId,
+        metadata: &FileMetadata,
+        manifest: Option<Arc<TreeManifest>>,
+        proposed_path: std::path::PathBuf,
+    ) -> TransferDecision {
+        let acceptor = Arc::clone(&*self.inner.transfer_acceptor.read().await);
+        let request = IncomingTransfer {
+            peer_id,
+            metadata: metadata.clone(),
+            proposed_path,
+            manifest,
+        };
+
+        tokio::task::spawn_blocking(move || acceptor.decide(&request))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Transfer acceptor panicked: {}", e);
+                TransferDecision::Reject("internal error".to_string())
+            })
+    }
+
+    /// Handle STREAM_RESET frame (receiver rejected or aborted an outgoing transfer)
+    pub(crate) async fn handle_stream_reset_frame(
         &self,
         frame: Frame<'_>,
-        _peer_id: crate::node::session::PeerId,
+        peer_id: PeerId,
     ) -> Result<()> {
-        let response_data = frame.payload();
-        if response_data.len() != 8 {
+        let (transfer_id, reason) =
+            crate::node::file_transfer::parse_transfer_reject(frame.payload())?;
+
+        let Some(context) = self
+            .inner
+            .transfers
+            .get(&transfer_id)
+            .map(|entry| Arc::clone(entry.value()))
+        else {
+            tracing::debug!(
+                "STREAM_RESET for unknown transfer {}",
+                hex::encode(&transfer_id[..8])
+            );
+            return Ok(());
+        };
+
+        // Only the receiving peer of an outgoing transfer may reset it
+        if context.reassembler.is_some()
+            || context.tree_staging.is_some()
+            || !context
+                .transfer_session
+                .read()
+                .await
+                .peer_ids()
+                .contains(&peer_id)
+        {
             tracing::warn!(
-                "Invalid PATH_RESPONSE payload length: {} (expected 8)",
-                response_data.len()
+                "Ignoring STREAM_RESET for transfer {} from unrelated peer {}",
+                hex::encode(&transfer_id[..8]),
+                hex::encode(&peer_id[..8])
             );
             return Ok(());
         }
 
-        let mut response_challenge = [0u8; 8];
-        response_challenge.copy_from_slice(response_data);
-
-        tracing::debug!(
-            "Received PATH_RESPONSE with challenge: {:?}",
-            response_challenge
+        tracing::info!(
+            "Transfer {} rejected by peer {}: {}",
+            hex::encode(&transfer_id[..8]),
+            hex::encode(&peer_id[..8]),
+            reason
         );
 
-        // Find matching pending migration by iterating through all pending migrations
-        // and matching the challenge data
-        let mut matched_path_id = None;
-        for entry in self.inner.pending_migrations.iter() {
-            if entry.value().challenge == response_challenge {
-                matched_path_id = Some(*entry.key());
-                break;
-            }
+        context.set_rejected(reason.clone());
+        self.fail_transfer(&context).await;
+        if let Some((_, (_, verdict_tx))) = self.inner.pending_acceptances.remove(&transfer_id) {
+            let _ = verdict_tx.send(Err(reason));
         }
 
-        if let Some(path_id) = matched_path_id {
-            if let Some((_path_id, migration_state)) =
-                self.inner.pending_migrations.remove(&path_id)
+        Ok(())
+    }
+
+    /// Handle a TRANSFER_ACCEPT by releasing the waiting chunk sender
+    async fn handle_transfer_accept(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let transfer_id = crate::node::file_transfer::parse_transfer_accept(frame.payload())?;
+
+        match self
+            .inner
+            .pending_acceptances
+            .remove_if(&transfer_id, |_, (expected_peer, _)| {
+                *expected_peer == peer_id
+            }) {
+            Some((_, (_, verdict_tx))) => {
+                tracing::debug!(
+                    "Transfer {} accepted by peer {}",
+                    hex::encode(&transfer_id[..8]),
+                    hex::encode(&peer_id[..8])
+                );
+                let _ = verdict_tx.send(Ok(()));
+            }
+            None => {
+                tracing::debug!(
+                    "Unexpected TRANSFER_ACCEPT for transfer {}",
+                    hex::encode(&transfer_id[..8])
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Best-effort send of a frame to a peer over its existing session
+    async fn send_frame_to_peer(&self, peer_id: PeerId, frame_bytes: &[u8]) {
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|entry| Arc::clone(entry.value()))
+        else {
+            tracing::debug!("No session with peer {}", hex::encode(&peer_id[..8]));
+            return;
+        };
+
+        if let Err(e) = self.send_encrypted_frame(&connection, frame_bytes).await {
+            tracing::warn!(
+                "Failed to send frame to peer {}: {}",
+                hex::encode(&peer_id[..8]),
+                e
+            );
+        }
+    }
+
+    /// Handle Control frame (chunk hash layer, chunk requests, transfer accept)
+    pub(crate) async fn handle_control_frame(
+        &self,
+        frame: Frame<'_>,
+        peer_id: PeerId,
+    ) -> Result<()> {
+        match frame.payload().first() {
+            Some(&CONTROL_CHUNK_HASHES) => self.handle_chunk_hashes(frame, peer_id).await,
+            Some(&CONTROL_CHUNK_REQUEST) => self.handle_chunk_request(frame, peer_id).await,
+            Some(&CONTROL_CHUNK_HASHES_REQUEST) => {
+                self.handle_chunk_hashes_request(frame, peer_id).await
+            }
+            Some(&CONTROL_TRANSFER_ACCEPT) => self.handle_transfer_accept(frame, peer_id).await,
+            Some(&CONTROL_TRANSPORTS) => self.handle_transports(frame, peer_id),
+            other => {
+                tracing::debug!("Unhandled control frame type: {:?}", other);
+                Ok(())
+            }
+        }
+    }
+
+    /// Record the transports a peer advertised as fallback paths
+    fn handle_transports(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+        else {
+            return Ok(());
+        };
+
+        let transports = crate::node::connection::parse_transports(
+            frame.payload(),
+            connection.peer_addr().ip(),
+        )?;
+        tracing::debug!(
+            "Peer {} also listens on {:?}",
+            hex::encode(&peer_id[..8]),
+            transports
+        );
+        connection.set_peer_transports(transports);
+        Ok(())
+    }
+
+    /// Handle a CHUNK_HASHES segment for an incoming transfer
+    ///
+    /// Once the full layer has arrived and matches the root hash, any chunks
+    /// buffered while waiting for it are verified and stored.
+    async fn handle_chunk_hashes(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let (start_index, hashes) =
+            crate::node::file_transfer::parse_chunk_hashes(frame.payload())?;
+        let stream_id = frame.stream_id();
+        let context = self.find_transfer_by_stream_id(stream_id)?;
+        let from_source = match context.sender {
+            Some(sender) => sender == peer_id,
+            // A pulled file takes its layer from any peer it downloads from
+            None => {
+                context.reassembler.is_some()
+                    && context
+                        .transfer_session
+                        .read()
+                        .await
+                        .peer_ids()
+                        .contains(&peer_id)
+            }
+        };
+        if !from_source {
+            tracing::warn!(
+                "Ignoring chunk hashes for transfer {} from unrelated peer {}",
+                hex::encode(&context.transfer_id[..8]),
+                hex::encode(&peer_id[..8])
+            );
+            return Ok(());
+        }
+
+        let Some(layer) = &context.chunk_hashes else {
+            tracing::debug!("Ignoring chunk hashes for transfer without hash layer");
+            return Ok(());
+        };
+
+        let (deferred, dropped) = {
+            let mut layer = layer.lock().await;
+            match layer.insert_segment(start_index, &hashes) {
+                Ok(true) => {
+                    let deferred = layer
+                        .take_deferred()
+                        .into_iter()
+                        .map(|(index, data)| (layer.verify_chunk(index, &data), index, data))
+                        .collect::<Vec<_>>();
+                    (deferred, layer.take_dropped())
+                }
+                Ok(false) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!(
+                        "Chunk hash layer from peer {} does not match root for transfer {}",
+                        hex::encode(&peer_id[..8]),
+                        hex::encode(&context.transfer_id[..8])
+                    );
+                    // The collected hashes are gone: ask the sender for the whole layer again
+                    if context.sender.is_some() && layer.begin_recovery() {
+                        drop(layer);
+                        self.spawn_hash_layer_recovery(Arc::clone(&context), peer_id, stream_id);
+                    }
+                    return Err(e);
+                }
+            }
+        };
+
+        tracing::debug!(
+            "Chunk hash layer verified for transfer {} ({} deferred chunks)",
+            hex::encode(&context.transfer_id[..8]),
+            deferred.len()
+        );
+
+        for (verdict, chunk_index, chunk_data) in deferred {
+            if verdict == ChunkVerdict::Valid {
+                self.store_chunk(&context, chunk_index, &chunk_data).await?;
+            } else {
+                self.reject_chunk(&context, peer_id, stream_id, chunk_index)
+                    .await;
+            }
+        }
+        for chunk_index in dropped {
+            self.request_chunk_retransmit(&context, peer_id, stream_id, chunk_index)
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Handle a CHUNK_HASHES_REQUEST by re-sending hash segments of an outgoing transfer
+    async fn handle_chunk_hashes_request(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let (transfer_id, start_index) =
+            crate::node::file_transfer::parse_chunk_hashes_request(frame.payload())?;
+
+        let tree_hash = match self.find_transfer(&transfer_id) {
+            Some(context) => {
+                // Only the receiving peer of an outgoing file may ask for its layer
+                if context.reassembler.is_some()
+                    || context.tree_staging.is_some()
+                    || !context
+                        .transfer_session
+                        .read()
+                        .await
+                        .peer_ids()
+                        .contains(&peer_id)
+                {
+                    tracing::warn!(
+                        "Ignoring chunk hashes request for transfer {} from unrelated peer {}",
+                        hex::encode(&transfer_id[..8]),
+                        hex::encode(&peer_id[..8])
+                    );
+                    return Ok(());
+                }
+                context.tree_hash.clone()
+            }
+            // Seeded files are requested by their root hash
+            None => match self.seeded_tree_hash(&transfer_id).await? {
+                Some(tree_hash) => tree_hash,
+                None => {
+                    tracing::debug!(
+                        "Chunk hashes request for unknown transfer {}",
+                        hex::encode(&transfer_id[..8])
+                    );
+                    return Ok(());
+                }
+            },
+        };
+
+        let connection = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(NodeError::SessionNotFound(peer_id))?;
+
+        let first_segment = usize::try_from(start_index).unwrap_or(usize::MAX)
+            / crate::node::file_transfer::CHUNK_HASHES_PER_FRAME;
+        let frames =
+            crate::node::file_transfer::build_chunk_hash_frames(frame.stream_id(), &tree_hash)?;
+        for hash_frame in frames.iter().skip(first_segment) {
+            self.send_encrypted_frame(&connection, hash_frame).await?;
+        }
+
+        tracing::debug!(
+            "Re-sent chunk hashes from {} of transfer {} to peer {}",
+            start_index,
+            hex::encode(&transfer_id[..8]),
+            hex::encode(&peer_id[..8])
+        );
+
+        Ok(())
+    }
+
+    /// Chunk hash layer of a file this node seeds, computed from the file on disk
+    async fn seeded_tree_hash(
+        &self,
+        root_hash: &[u8; 32],
+    ) -> Result<Option<wraith_files::tree_hash::FileTreeHash>> {
+        let Some((chunk_size, path)) = self
+            .inner
+            .available_files
+            .get(root_hash)
+            .map(|entry| (entry.value().0.chunk_size, entry.value().1.clone()))
+        else {
+            return Ok(None);
+        };
+
+        let tree_hash = tokio::task::spawn_blocking(move || {
+            wraith_files::tree_hash::compute_tree_hash(&path, chunk_size)
+        })
+        .await
+        .map_err(|e| NodeError::Other(format!("Hashing task failed: {e}").into()))?
+        .map_err(|e| NodeError::Io(e.to_string()))?;
+
+        // The file may have changed since it was announced
+        Ok((tree_hash.root == *root_hash).then_some(tree_hash))
+    }
+
+    /// Ask the sender to re-send missing chunk hash segments until the layer verifies
+    ///
+    /// Gives up after [`MAX_HASH_LAYER_REQUESTS`] unanswered requests and fails
+    /// the transfer, since none of its chunks can be verified without the layer.
+    ///
+    /// [`MAX_HASH_LAYER_REQUESTS`]: crate::node::file_transfer::MAX_HASH_LAYER_REQUESTS
+    fn spawn_hash_layer_recovery(
+        &self,
+        context: Arc<FileTransferContext>,
+        peer_id: PeerId,
+        stream_id: u16,
+    ) {
+        use crate::node::file_transfer::MAX_HASH_LAYER_REQUESTS;
+
+        let Some(layer) = context.chunk_hashes.clone() else {
+            return;
+        };
+        let interval = self.inner.config.transfer.hash_layer_request_interval;
+        let node = self.clone();
+        tokio::spawn(async move {
+            for attempt in 0..=MAX_HASH_LAYER_REQUESTS {
+                tokio::time::sleep(interval).await;
+                let start_index = {
+                    let layer = layer.lock().await;
+                    if layer.is_verified() {
+                        return;
+                    }
+                    layer.first_missing()
+                };
+                if node.find_transfer(&context.transfer_id).is_none()
+                    || context.transfer_session.read().await.is_failed()
+                {
+                    return;
+                }
+                if attempt == MAX_HASH_LAYER_REQUESTS {
+                    break;
+                }
+
+                tracing::debug!(
+                    "Requesting chunk hashes from {} of transfer {} (attempt {})",
+                    start_index,
+                    hex::encode(&context.transfer_id[..8]),
+                    attempt + 1
+                );
+                match crate::node::file_transfer::build_chunk_hashes_request_frame(
+                    stream_id,
+                    &context.transfer_id,
+                    start_index,
+                ) {
+                    Ok(frame) => node.send_frame_to_peer(peer_id, &frame).await,
+                    Err(e) => tracing::debug!("Failed to request chunk hashes: {}", e),
+                }
+            }
+
+            node.abandon_hash_layer(&context, peer_id).await;
+        });
+    }
+
+    /// Fail a receive transfer whose chunk hash layer never completed
+    async fn abandon_hash_layer(&self, context: &FileTransferContext, peer_id: PeerId) {
+        tracing::warn!(
+            "Chunk hash layer of transfer {} never completed from peer {}",
+            hex::encode(&context.transfer_id[..8]),
+            hex::encode(&peer_id[..8])
+        );
+
+        context.transfer_session.write().await.mark_failed();
+        if let Some(quarantine) = &context.quarantine {
+            quarantine.discard();
+        }
+
+        // A file of a directory transfer takes the whole tree down with it
+        let transfer_id = match context
+            .tree_member
+            .and_then(|member| self.find_transfer(&member.tree_id))
+        {
+            Some(tree) => {
+                self.fail_transfer(&tree).await;
+                tree.transfer_id
+            }
+            None => context.transfer_id,
+        };
+        if let Err(e) = self
+            .reject_incoming_transfer(
+                peer_id,
+                transfer_stream_id(&transfer_id),
+                &transfer_id,
+                "Chunk hash layer unavailable",
+            )
+            .await
+        {
+            tracing::debug!("Failed to reject transfer: {}", e);
+        }
+    }
+
+    /// Handle a CHUNK_REQUEST by re-sending the chunk from an outgoing transfer
+    async fn handle_chunk_request(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let (transfer_id, chunk_index) =
+            crate::node::file_transfer::parse_chunk_request(frame.payload())?;
+
+        let Some(context) = self.find_transfer(&transfer_id) else {
+            tracing::debug!(
+                "Chunk request for unknown transfer {}",
+                hex::encode(&transfer_id[..8])
+            );
+            return Ok(());
+        };
+
+        if context.reassembler.is_some() {
+            tracing::debug!("Ignoring chunk request for a receive transfer");
+            return Ok(());
+        }
+
+        let (file_path, chunk_size) = {
+            let session = context.transfer_session.read().await;
+            (session.file_path.clone(), session.chunk_size)
+        };
+
+        let mut chunker =
+            FileChunker::new(&file_path, chunk_size).map_err(|e| NodeError::Io(e.to_string()))?;
+        if chunk_index >= chunker.num_chunks() {
+            return Err(NodeError::invalid_state("Requested chunk out of range"));
+        }
+        let chunk_data = chunker
+            .read_chunk_at(chunk_index)
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+
+        let connection = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|entry| Arc::clone(entry.value()))
+            .ok_or(NodeError::SessionNotFound(peer_id))?;
+
+        let chunk_frame = self.build_outgoing_chunk_frame(
+            &connection,
+            &file_path,
+            frame.stream_id(),
+            chunk_index,
+            chunk_data,
+        )?;
+        // A re-request means the original never arrived intact
+        connection
+            .session
+            .write()
+            .await
+            .record_lost(chunk_frame.len() as u64);
+        self.send_encrypted_frame(&connection, &chunk_frame).await?;
+
+        tracing::debug!(
+            "Re-sent chunk {} of transfer {} to peer {}",
+            chunk_index,
+            hex::encode(&transfer_id[..8]),
+            hex::encode(&peer_id[..8])
+        );
+
+        Ok(())
+    }
+
+    /// Handle PING frame by echoing its sequence number in a PONG
+    pub(crate) async fn handle_ping_frame(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+        else {
+            return Ok(());
+        };
+
+        let pong = FrameBuilder::new()
+            .frame_type(FrameType::Pong)
+            .stream_id(0)
+            .sequence(frame.sequence())
+            .build(128)
+            .map_err(|e| NodeError::Other(format!("Failed to build PONG frame: {e}").into()))?;
+        self.send_encrypted_frame(&connection, &pong).await
+    }
+
+    /// Handle PONG frame (ping response)
+    pub(crate) async fn handle_pong_frame(
+        &self,
+        frame: Frame<'_>,
+        peer_id: crate::node::session::PeerId,
+    ) -> Result<()> {
+        let sequence = frame.sequence();
+
+        // Look up pending ping by (peer_id, sequence)
+        if let Some((_key, tx)) = self.inner.pending_pings.remove(&(peer_id, sequence)) {
+            // Send timestamp back to waiting ping_session
+            let _ = tx.send(std::time::Instant::now());
+            tracing::trace!("PONG received from {:?}, seq {}", peer_id, sequence);
+        } else {
+            tracing::debug!(
+                "Received unexpected PONG from {:?}, seq {}",
+                peer_id,
+                sequence
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Handle Data frame (file chunk)
+    ///
+    /// Chunks are verified against the transfer's chunk hash layer before
+    /// they are written. Corrupt chunks are discarded, attributed to the
+    /// sending peer and re-requested.
+    pub(crate) async fn handle_data_frame(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let chunk_index = frame.sequence() as u64;
+        let stream_id = frame.stream_id();
+        let decompressed;
+        let chunk_data = if frame.flags().is_compressed() {
+            decompressed = self
+                .decompress_chunk_payload(stream_id, frame.payload(), peer_id)
+                .await?;
+            decompressed.as_slice()
+        } else {
+            frame.payload()
+        };
+
+        // Check if there's a pending chunk request waiting for this data
+        // (the requester verifies it against its transfer's chunk hash layer)
+        let chunk_key = (stream_id, chunk_index);
+        if let Some((_, (_, sender))) = self
+            .inner
+            .pending_chunks
+            .remove_if(&chunk_key, |_, (requested, _)| *requested == peer_id)
+        {
+            // Send chunk data to waiting request_chunk_from_peer()
+            let _ = sender.send(chunk_data.to_vec());
+            tracing::trace!(
+                "Chunk {} (stream {}) routed to pending request",
+                chunk_index,
+                stream_id
+            );
+            return Ok(());
+        }
+
+        // No pending request - handle as async chunk push (e.g., from seeder)
+        let context = self.find_transfer_by_stream_id(stream_id)?;
+        if context.sender != Some(peer_id) {
+            tracing::warn!(
+                "Ignoring chunk {} for transfer {} from unrelated peer {}",
+                chunk_index,
+                hex::encode(&context.transfer_id[..8]),
+                hex::encode(&peer_id[..8])
+            );
+            return Ok(());
+        }
+
+        if let Some(layer) = &context.chunk_hashes {
+            let mut layer = layer.lock().await;
+            match layer.verify_chunk(chunk_index, chunk_data) {
+                ChunkVerdict::Valid => {}
+                ChunkVerdict::Corrupt => {
+                    drop(layer);
+                    self.reject_chunk(&context, peer_id, stream_id, chunk_index)
+                        .await;
+                    return Err(NodeError::HashMismatch);
+                }
+                ChunkVerdict::Unverified => {
+                    // Chunks follow the layer, so one arriving first means segments went missing
+                    let recover = layer.begin_recovery();
+                    if !layer.defer_chunk(chunk_index, chunk_data) {
+                        tracing::debug!(
+                            "Deferred chunk buffer full, chunk {} is re-requested once the layer verifies",
+                            chunk_index
+                        );
+                    }
+                    drop(layer);
+                    if recover {
+                        self.spawn_hash_layer_recovery(Arc::clone(&context), peer_id, stream_id);
+                    }
+                    return Ok(());
+                }
+            }
+        } else if chunk_index < context.tree_hash.chunks.len() as u64
+            && !context
+                .tree_hash
+                .verify_chunk(chunk_index as usize, chunk_data)
+        {
+            return Err(NodeError::HashMismatch);
+        }
+
+        self.store_chunk(&context, chunk_index, chunk_data).await
+    }
+
+    /// Restore the original bytes of a compressed file chunk
+    ///
+    /// Only peers that negotiated compression may send compressed chunks, and
+    /// a chunk may not expand beyond its transfer's chunk size.
+    async fn decompress_chunk_payload(
+        &self,
+        stream_id: u16,
+        payload: &[u8],
+        peer_id: PeerId,
+    ) -> Result<Vec<u8>> {
+        let negotiated = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .is_some_and(|connection| connection.capabilities.supports_compression());
+        if !negotiated {
+            return Err(NodeError::invalid_state(
+                "Compressed chunk from a peer that did not negotiate compression",
+            ));
+        }
+
+        let max_len = match self.find_transfer_by_stream_id(stream_id) {
+            Ok(context) => context.transfer_session.read().await.chunk_size,
+            Err(_) => self.inner.config.transfer.chunk_size,
+        };
+        decompress_chunk(payload, max_len)
+            .map_err(|e| NodeError::Transfer(format!("Failed to decompress chunk: {e}").into()))
+    }
+
+    /// Find a transfer context by ID, including files of directory transfers
+    fn find_transfer(&self, transfer_id: &[u8; 32]) -> Option<Arc<FileTransferContext>> {
+        self.inner
+            .transfers
+            .get(transfer_id)
+            .or_else(|| self.inner.tree_files.get(transfer_id))
+            .map(|entry| Arc::clone(entry.value()))
+    }
+
+    /// Find the transfer context whose stream ID matches `stream_id`
+    pub(crate) fn find_transfer_by_stream_id(
+        &self,
+        stream_id: u16,
+    ) -> Result<Arc<FileTransferContext>> {
+        if let Some(context) = self
+            .inner
+            .transfers
+            .iter()
+            .find(|entry| transfer_stream_id(entry.key()) == stream_id)
+            .map(|entry| Arc::clone(entry.value()))
+        {
+            return Ok(context);
+        }
+
+        if let Some(context) = self
+            .inner
+            .tree_files
+            .iter()
+            .find(|entry| {
+                entry
+                    .value()
+                    .tree_member
+                    .is_some_and(|member| member.stream_id == stream_id)
+            })
+            .map(|entry| Arc::clone(entry.value()))
+        {
+            return Ok(context);
+        }
+
+        self.open_tree_file(stream_id)?.ok_or_else(|| {
+            NodeError::InvalidState(format!("No transfer for stream_id {stream_id}").into())
+        })
+    }
+
+    /// Create the receive context for a file of an incoming directory transfer
+    ///
+    /// File contexts are created when the first frame for their stream arrives
+    /// and dropped once the file has verified, so a large tree does not hold
+    /// a file handle per file.
+    fn open_tree_file(&self, stream_id: u16) -> Result<Option<Arc<FileTransferContext>>> {
+        let Some((tree, staging, entry_index)) = self.inner.transfers.iter().find_map(|entry| {
+            let staging = entry.value().tree_staging.as_ref()?;
+            let entry_index = staging.manifest.child_entry_index(stream_id)?;
+            Some((Arc::clone(entry.value()), Arc::clone(staging), entry_index))
+        }) else {
+            return Ok(None);
+        };
+
+        let manifest = &staging.manifest;
+        let file_id = manifest.child_transfer_id(entry_index);
+        let context = self
+            .inner
+            .tree_files
+            .entry(file_id)
+            .or_try_insert_with(|| {
+                // Checked under the map lock: a verified file is marked complete
+                // before its context is removed
+                if staging.is_file_complete(entry_index) {
+                    return Err(NodeError::invalid_state("Frame for completed file"));
+                }
+
+                let entry = &manifest.entries[entry_index];
+                let chunk_size = manifest.chunk_size as usize;
+                let destination = staging.entry_path(entry_index);
+                let quarantine = QuarantinedFile::new(
+                    &self.inner.config.transfer.quarantine_dir(),
+                    &file_id,
+                    destination.clone(),
+                )?;
+                let reassembler = wraith_files::chunker::FileReassembler::new(
+                    &quarantine.part_path,
+                    entry.size,
+                    chunk_size,
+                )
+                .map_err(|e| NodeError::Io(e.to_string()))?;
+
+                let mut transfer =
+                    TransferSession::new_receive(file_id, destination, entry.size, chunk_size);
+                transfer.start();
+
+                let mut context = FileTransferContext::new_receive(
+                    file_id,
+                    Arc::new(RwLock::new(transfer)),
+                    Arc::new(Mutex::new(reassembler)),
+                    wraith_files::tree_hash::FileTreeHash {
+                        root: entry.root_hash,
+                        chunks: Vec::new(),
+                    },
+                )
+                .with_chunk_hashes(ChunkHashLayer::new(
+                    entry.root_hash,
+                    entry.chunk_count(manifest.chunk_size),
+                ))
+                .with_quarantine(quarantine)
+                .with_tree_member(staging.member(entry_index));
+                if let Some(coordinator) = &tree.coordinator {
+                    context = context.with_coordinator(Arc::clone(coordinator));
+                }
+                if let Some(sender) = tree.sender {
+                    context = context.with_sender(sender);
+                }
+                Ok(Arc::new(context))
+            })?;
+
+        Ok(Some(Arc::clone(context.value())))
+    }
+
+    /// Write a verified chunk to the reassembler and update progress
+    async fn store_chunk(
+        &self,
+        context: &FileTransferContext,
+        chunk_index: u64,
+        chunk_data: &[u8],
+    ) -> Result<()> {
+        if let Some(reassembler_arc) = &context.reassembler {
+            reassembler_arc
+                .lock()
+                .await
+                .write_chunk(chunk_index, chunk_data)
+                .map_err(|e| NodeError::Io(e.to_string()))?;
+        }
+
+        let mut transfer = context.transfer_session.write().await;
+        let was_complete = transfer.is_complete();
+        let in_range = chunk_index < transfer.total_chunks;
+        transfer.mark_chunk_transferred(chunk_index, chunk_data.len());
+
+        if !was_complete && transfer.is_complete() {
+            // Hold the session lock so nobody observes completion before the file is in place
+            if let Some(quarantine) = &context.quarantine {
+                match self
+                    .commit_quarantined(context, quarantine, transfer.chunk_size)
+                    .await
+                {
+                    Ok(path) => transfer.file_path = path,
+                    Err(e) => {
+                        tracing::warn!(
+                            "File transfer {:?} failed final verification: {}",
+                            hex::encode(&context.transfer_id[..8]),
+                            e
+                        );
+                        transfer.mark_failed();
+                        drop(transfer);
+                        if let Some(tree) = context
+                            .tree_member
+                            .and_then(|member| self.find_transfer(&member.tree_id))
+                        {
+                            self.fail_transfer(&tree).await;
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+
+            if let Some(member) = &context.tree_member
+                && let Some(staging) = self
+                    .find_transfer(&member.tree_id)
+                    .and_then(|tree| tree.tree_staging.clone())
+            {
+                staging.mark_file_complete(member.entry_index);
+                self.inner.tree_files.remove(&context.transfer_id);
+            }
+
+            tracing::info!(
+                "File transfer {:?} completed ({} bytes) -> {}",
+                hex::encode(&context.transfer_id[..8]),
+                transfer.file_size,
+                transfer.file_path.display()
+            );
+        }
+        drop(transfer);
+
+        if in_range && let Some(member) = &context.tree_member {
+            self.record_tree_chunk(member, chunk_index, chunk_data.len())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Count a file's chunk towards its directory transfer
+    ///
+    /// When the last chunk of the tree lands, an incoming tree is finalized
+    /// and moved out of its staging directory.
+    async fn record_tree_chunk(
+        &self,
+        member: &TreeMember,
+        chunk_index: u64,
+        chunk_len: usize,
+    ) -> Result<()> {
+        let Some(tree) = self.find_transfer(&member.tree_id) else {
+            return Ok(());
+        };
+
+        let mut transfer = tree.transfer_session.write().await;
+        let was_complete = transfer.is_complete();
+        transfer.mark_chunk_transferred(member.first_chunk + chunk_index, chunk_len);
+        if was_complete || !transfer.is_complete() {
+            return Ok(());
+        }
+
+        // Hold the session lock so nobody observes completion before the tree is in place
+        if let Some(staging) = &tree.tree_staging {
+            let staging = Arc::clone(staging);
+            let result = tokio::task::spawn_blocking(move || {
+                let result = staging.commit();
+                if result.is_err() {
+                    staging.discard();
+                }
+                result
+            })
+            .await
+            .map_err(|e| NodeError::Other(format!("Commit task failed: {e}").into()))?;
+
+            match result {
+                Ok(path) => transfer.file_path = path,
+                Err(e) => {
+                    tracing::warn!(
+                        "Directory transfer {:?} could not be finalized: {}",
+                        hex::encode(&tree.transfer_id[..8]),
+                        e
+                    );
+                    transfer.mark_failed();
+                    return Err(e);
+                }
+            }
+        }
+
+        tracing::info!(
+            "Directory transfer {:?} completed ({} bytes) -> {}",
+            hex::encode(&tree.transfer_id[..8]),
+            transfer.file_size,
+            transfer.file_path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Mark a transfer failed, along with every file of a directory transfer
+    ///
+    /// Partial data of an incoming directory is discarded.
+    async fn fail_transfer(&self, context: &FileTransferContext) {
+        context.transfer_session.write().await.mark_failed();
+        if let Some(staging) = &context.tree_staging {
+            staging.discard();
+        }
+
+        let files: Vec<_> = self
+            .inner
+            .tree_files
+            .iter()
+            .filter(|entry| {
+                entry
+                    .value()
+                    .tree_member
+                    .is_some_and(|member| member.tree_id == context.transfer_id)
+            })
+            .map(|entry| Arc::clone(entry.value()))
+            .collect();
+        for file in files {
+            file.transfer_session.write().await.mark_failed();
+            if let Some(quarantine) = &file.quarantine {
+                quarantine.discard();
+                self.inner.tree_files.remove(&file.transfer_id);
+            }
+        }
+    }
+
+    /// Flush a completed receive and move it out of quarantine once the root verifies
+    async fn commit_quarantined(
+        &self,
+        context: &FileTransferContext,
+        quarantine: &QuarantinedFile,
+        chunk_size: usize,
+    ) -> Result<std::path::PathBuf> {
+        if let Some(reassembler) = &context.reassembler {
+            reassembler
+                .lock()
+                .await
+                .sync()
+                .map_err(|e| NodeError::Io(e.to_string()))?;
+        }
+
+        let quarantine = quarantine.clone();
+        let root = context.tree_hash.root;
+        tokio::task::spawn_blocking(move || quarantine.commit(&root, chunk_size))
+            .await
+            .map_err(|e| NodeError::Other(format!("Commit task failed: {e}").into()))?
+    }
+
+    /// Discard a corrupt chunk, record the offending peer and re-request it
+    async fn reject_chunk(
+        &self,
+        context: &FileTransferContext,
+        peer_id: PeerId,
+        stream_id: u16,
+        chunk_index: u64,
+    ) {
+        tracing::warn!(
+            "Chunk {} of transfer {} from peer {} failed hash verification",
+            chunk_index,
+            hex::encode(&context.transfer_id[..8]),
+            hex::encode(&peer_id[..8])
+        );
+
+        if let Some(coordinator) = &context.coordinator {
+            coordinator
+                .record_corrupt_chunk(&peer_id, chunk_index as usize)
+                .await;
+        }
+
+        self.request_chunk_retransmit(context, peer_id, stream_id, chunk_index)
+            .await;
+    }
+
+    /// Ask a peer to re-send a chunk (best-effort)
+    async fn request_chunk_retransmit(
+        &self,
+        context: &FileTransferContext,
+        peer_id: PeerId,
+        stream_id: u16,
+        chunk_index: u64,
+    ) {
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|entry| Arc::clone(entry.value()))
+        else {
+            return;
+        };
+
+        let result = match crate::node::file_transfer::build_chunk_request_frame(
+            stream_id,
+            &context.transfer_id,
+            chunk_index,
+        ) {
+            Ok(frame) => self.send_encrypted_frame(&connection, &frame).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::debug!("Failed to request chunk {}: {}", chunk_index, e);
+        }
+    }
+
+    /// Handle PATH_RESPONSE frame (connection migration)
+    pub(crate) async fn handle_path_response_frame(
+        &self,
+        frame: Frame<'_>,
+        _peer_id: crate::node::session::PeerId,
+    ) -> Result<()> {
+        let response_data = frame.payload();
+        if response_data.len() != 8 {
+            tracing::warn!(
+                "Invalid PATH_RESPONSE payload length: {} (expected 8)",
+                response_data.len()
+            );
+            return Ok(());
+        }
+
+        let mut response_challenge = [0u8; 8];
+        response_challenge.copy_from_slice(response_data);
+
+        tracing::debug!(
+            "Received PATH_RESPONSE with challenge: {:?}",
+            response_challenge
+        );
+
+        // Find matching pending migration by iterating through all pending migrations
+        // and matching the challenge data
+        let mut matched_path_id = None;
+        for entry in self.inner.pending_migrations.iter() {
+            if entry.value().challenge == response_challenge {
+                matched_path_id = Some(*entry.key());
+                break;
+            }
+        }
+
+        if let Some(path_id) = matched_path_id {
+            if let Some((_path_id, migration_state)) =
+                self.inner.pending_migrations.remove(&path_id)
             {
                 let latency = migration_state.initiated_at.elapsed();
 
@@ -560,66 +2000,349 @@ impl Node {
                 response_challenge
             );
         }
-
+
+        Ok(())
+    }
+
+    /// Handle PATH_CHALLENGE frame (migration responder side)
+    ///
+    /// Echoes the challenge in a PATH_RESPONSE over the transport and address
+    /// the challenge came from, then moves the session onto that path. The
+    /// challenge authenticated under the session keys, so only the peer can
+    /// move it.
+    pub(crate) async fn handle_path_challenge(
+        &self,
+        challenge: [u8; 8],
+        peer_id: PeerId,
+        from: SocketAddr,
+        via: TransportType,
+    ) -> Result<()> {
+        let connection = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+            .ok_or(NodeError::SessionNotFound(peer_id))?;
+
+        let frame = FrameBuilder::new()
+            .frame_type(FrameType::PathResponse)
+            .stream_id(0)
+            .sequence(0)
+            .payload(&challenge)
+            .build(128)
+            .map_err(|e| {
+                NodeError::Migration(format!("Failed to build PATH_RESPONSE: {e}").into())
+            })?;
+        let encrypted = connection.seal_frame(&frame).await?;
+
+        let transport = self
+            .get_transport()
+            .await?
+            .transport(via)
+            .await
+            .ok_or_else(|| NodeError::Migration(format!("No {via} transport available").into()))?;
+        transport.send_to(&encrypted, from).await.map_err(|e| {
+            NodeError::Migration(format!("Failed to send PATH_RESPONSE: {e}").into())
+        })?;
+
+        if connection.transport_type() != via || connection.peer_addr() != from {
+            connection.update_path(via, from);
+        }
+        Ok(())
+    }
+
+    /// Send file chunks to peer
+    pub(crate) async fn send_file_chunks(
+        &self,
+        transfer_id: crate::node::identity::TransferId,
+        file_path: std::path::PathBuf,
+        stream_id: u16,
+        connection: Arc<PeerConnection>,
+    ) -> Result<()> {
+        let context = self
+            .find_transfer(&transfer_id)
+            .ok_or(NodeError::TransferNotFound(transfer_id))?;
+
+        let mut chunker = FileChunker::new(&file_path, self.inner.config.transfer.chunk_size)
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+
+        let total_chunks = chunker.num_chunks();
+
+        // Repair frames have no v1 encoding, so FEC needs a v2 session
+        let fec = &self.inner.config.transfer.fec;
+        let mut fec_encoder = (fec.enabled
+            && connection.wire_format.is_v2()
+            && self.inner.config.transfer.chunk_size <= MAX_FEC_SOURCE_PAYLOAD)
+            .then(|| FecEncoder::new(fec.group_size));
+
+        // Timing obfuscation delays every packet, so it sends them one by one
+        let batch_chunks = if self.timing_obfuscation_enabled() {
+            1
+        } else {
+            SEND_BATCH_CHUNKS
+        };
+        let mut queue = ChunkQueue::default();
+
+        for chunk_index in 0..total_chunks {
+            // Stop streaming if the receiver reset the transfer
+            if context.transfer_session.read().await.is_failed() {
+                tracing::info!(
+                    "Transfer {} aborted after {} of {} chunks",
+                    hex::encode(&transfer_id[..8]),
+                    chunk_index,
+                    total_chunks
+                );
+                return Ok(());
+            }
+
+            // Hold the stream while the transfer is paused
+            if context.transfer_session.read().await.state() == TransferState::Paused {
+                self.flush_chunks(&connection, &context, &mut queue).await?;
+            }
+            while context.transfer_session.read().await.state() == TransferState::Paused {
+                if self.find_transfer(&transfer_id).is_none() {
+                    // Cancelled while paused
+                    return Ok(());
+                }
+                tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+            }
+
+            let chunk_data = chunker
+                .read_chunk_at(chunk_index)
+                .map_err(|e| NodeError::Io(e.to_string()))?;
+            let chunk_len = chunk_data.len();
+
+            // Verify chunk hash
+            if chunk_index < context.tree_hash.chunks.len() as u64 {
+                let computed_hash = blake3::hash(&chunk_data);
+                if computed_hash.as_bytes() != &context.tree_hash.chunks[chunk_index as usize] {
+                    return Err(NodeError::InvalidState(
+                        "Chunk hash verification failed".into(),
+                    ));
+                }
+            }
+
+            // Build and queue chunk frame
+            let frame_len = if let Some(encoder) = fec_encoder.as_mut() {
+                let compressed = self.compress_outgoing_chunk(&connection, &file_path, &chunk_data);
+                let source = FecSource {
+                    sequence: chunk_index,
+                    offset: chunk_index * chunk_len as u64,
+                    compressed: compressed.is_some(),
+                    payload: compressed.unwrap_or(chunk_data),
+                };
+                self.queue_fec_chunk(&connection, encoder, stream_id, source, &mut queue)
+                    .await?
+            } else {
+                let chunk_frame = self.build_outgoing_chunk_frame(
+                    &connection,
+                    &file_path,
+                    stream_id,
+                    chunk_index,
+                    chunk_data,
+                )?;
+                let sealed = connection.seal_frame(&chunk_frame).await?;
+                queue
+                    .datagrams
+                    .push(self.prepare_sealed(&connection, sealed)?);
+                chunk_frame.len()
+            };
+            queue.chunks.push((chunk_index, chunk_len, frame_len));
+
+            if queue.chunks.len() >= batch_chunks {
+                self.flush_chunks(&connection, &context, &mut queue).await?;
+            }
+        }
+
+        // Protect the final, partial group
+        if let Some(encoder) = fec_encoder.as_mut() {
+            self.queue_fec_repairs(&connection, encoder, stream_id, &mut queue)
+                .await?;
+        }
+        self.flush_chunks(&connection, &context, &mut queue).await?;
+
+        tracing::info!(
+            "File transfer {:?} completed ({} chunks sent)",
+            hex::encode(&transfer_id[..8]),
+            total_chunks
+        );
+
+        Ok(())
+    }
+
+    /// Compress a chunk of `file_path` for `connection`
+    ///
+    /// Returns `None` if compression is disabled, not negotiated with the
+    /// peer, skipped for the file's type, or does not shrink the chunk.
+    fn compress_outgoing_chunk(
+        &self,
+        connection: &PeerConnection,
+        file_path: &Path,
+        chunk_data: &[u8],
+    ) -> Option<Vec<u8>> {
+        let compression = &self.inner.config.transfer.compression;
+        if !compression.enabled
+            || !connection.capabilities.supports_compression()
+            || is_precompressed(file_path, &compression.skip_extensions)
+        {
+            return None;
+        }
+        compress_chunk(chunk_data, compression.level)
+    }
+
+    /// Build the data frame for a file chunk, compressed when that helps
+    fn build_outgoing_chunk_frame(
+        &self,
+        connection: &PeerConnection,
+        file_path: &Path,
+        stream_id: u16,
+        chunk_index: u64,
+        chunk_data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        match self.compress_outgoing_chunk(connection, file_path, &chunk_data) {
+            Some(compressed) => crate::node::file_transfer::build_compressed_chunk_frame(
+                stream_id,
+                chunk_index,
+                chunk_data.len(),
+                &compressed,
+            ),
+            None => {
+                crate::node::file_transfer::build_chunk_frame(stream_id, chunk_index, &chunk_data)
+            }
+        }
+    }
+
+    /// Queue a file chunk as an FEC source frame
+    ///
+    /// Queues the group's repair frames once the group is full. Returns the
+    /// size of the chunk frame.
+    async fn queue_fec_chunk(
+        &self,
+        connection: &PeerConnection,
+        encoder: &mut FecEncoder,
+        stream_id: u16,
+        source: FecSource,
+        queue: &mut ChunkQueue,
+    ) -> Result<usize> {
+        let frame = source.build_frame(u32::from(stream_id)).map_err(|e| {
+            NodeError::InvalidState(format!("Failed to build chunk frame: {e}").into())
+        })?;
+        let sealed = connection.seal_frame_v2(&frame).await?;
+        queue
+            .datagrams
+            .push(self.prepare_sealed(connection, sealed)?);
+
+        let group_full = encoder
+            .push(source)
+            .map_err(|e| NodeError::InvalidState(format!("FEC encoding failed: {e}").into()))?;
+        if group_full {
+            self.queue_fec_repairs(connection, encoder, stream_id, queue)
+                .await?;
+        }
+        Ok(frame.len())
+    }
+
+    /// Queue repair frames for the encoder's pending group
+    ///
+    /// The repair count follows the session's current loss estimate.
+    async fn queue_fec_repairs(
+        &self,
+        connection: &PeerConnection,
+        encoder: &mut FecEncoder,
+        stream_id: u16,
+        queue: &mut ChunkQueue,
+    ) -> Result<()> {
+        let fec = &self.inner.config.transfer.fec;
+        let loss_rate = connection.session.read().await.bbr().loss_rate();
+        let repair_count =
+            repair_count_for_loss(encoder.pending(), loss_rate, fec.min_repair, fec.max_repair);
+
+        let repairs = encoder
+            .finish_group(repair_count)
+            .map_err(|e| NodeError::InvalidState(format!("FEC encoding failed: {e}").into()))?;
+        for repair in repairs {
+            let frame = repair.build_frame(u32::from(stream_id)).map_err(|e| {
+                NodeError::InvalidState(format!("Failed to build repair frame: {e}").into())
+            })?;
+            let sealed = connection.seal_frame_v2(&frame).await?;
+            queue
+                .datagrams
+                .push(self.prepare_sealed(connection, sealed)?);
+        }
         Ok(())
     }
 
-    /// Send file chunks to peer
-    pub(crate) async fn send_file_chunks(
+    /// Send the queued datagrams and record progress for their chunks
+    async fn flush_chunks(
         &self,
-        transfer_id: crate::node::identity::TransferId,
-        file_path: std::path::PathBuf,
-        stream_id: u16,
-        connection: Arc<PeerConnection>,
+        connection: &PeerConnection,
+        context: &FileTransferContext,
+        queue: &mut ChunkQueue,
     ) -> Result<()> {
-        let context = self
-            .inner
-            .transfers
-            .get(&transfer_id)
-            .ok_or(NodeError::TransferNotFound(transfer_id))?
-            .clone();
-
-        let mut chunker = FileChunker::new(&file_path, self.inner.config.transfer.chunk_size)
-            .map_err(|e| NodeError::Io(e.to_string()))?;
-
-        let total_chunks = chunker.num_chunks();
-
-        for chunk_index in 0..total_chunks {
-            let chunk_data = chunker
-                .read_chunk_at(chunk_index)
-                .map_err(|e| NodeError::Io(e.to_string()))?;
-            let chunk_len = chunk_data.len();
-
-            // Verify chunk hash
-            if chunk_index < context.tree_hash.chunks.len() as u64 {
-                let computed_hash = blake3::hash(&chunk_data);
-                if computed_hash.as_bytes() != &context.tree_hash.chunks[chunk_index as usize] {
-                    return Err(NodeError::InvalidState(
-                        "Chunk hash verification failed".into(),
-                    ));
-                }
-            }
-
-            // Build and send chunk frame
-            let chunk_frame =
-                crate::node::file_transfer::build_chunk_frame(stream_id, chunk_index, &chunk_data)?;
+        self.send_datagrams(connection, &queue.datagrams).await?;
+        queue.datagrams.clear();
 
-            self.send_encrypted_frame(&connection, &chunk_frame).await?;
-
-            // Update progress
+        for (chunk_index, chunk_len, frame_len) in queue.chunks.drain(..) {
+            connection
+                .session
+                .write()
+                .await
+                .record_sent(frame_len as u64);
             context
                 .transfer_session
                 .write()
                 .await
                 .mark_chunk_transferred(chunk_index, chunk_len);
+            if let Some(member) = &context.tree_member {
+                self.record_tree_chunk(member, chunk_index, chunk_len)
+                    .await?;
+            }
         }
+        Ok(())
+    }
 
-        tracing::info!(
-            "File transfer {:?} completed ({} chunks sent)",
-            hex::encode(&transfer_id[..8]),
-            total_chunks
-        );
+    /// Send prepared datagrams to the peer, batched unless timing
+    /// obfuscation delays each one
+    async fn send_datagrams(
+        &self,
+        connection: &PeerConnection,
+        datagrams: &[Vec<u8>],
+    ) -> Result<()> {
+        if datagrams.is_empty() {
+            return Ok(());
+        }
+        let transport = self.transport_for(connection).await?;
+        let addr = connection.peer_addr();
+        let send_error = |e| NodeError::Transport(format!("Failed to send packet: {e}").into());
+
+        if self.timing_obfuscation_enabled() {
+            for datagram in datagrams {
+                let delay = self.get_timing_delay();
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                transport
+                    .send_to(datagram, addr)
+                    .await
+                    .map_err(send_error)?;
+            }
+            return Ok(());
+        }
+
+        let batch: Vec<(&[u8], SocketAddr)> = datagrams
+            .iter()
+            .map(|datagram| (datagram.as_slice(), addr))
+            .collect();
+        let mut rest = &batch[..];
+        while !rest.is_empty() {
+            let sent = transport.send_batch(rest).await.map_err(send_error)?;
+            if sent == 0 {
+                return Err(NodeError::Transport("Transport sent no packets".into()));
+            }
+            rest = &rest[sent..];
+        }
 
+        tracing::trace!("Sent {} packets to {}", datagrams.len(), addr);
         Ok(())
     }
 
@@ -630,16 +2353,15 @@ impl Node {
         connection: &PeerConnection,
         frame_bytes: &[u8],
     ) -> Result<()> {
-        // Encrypt the frame
-        let encrypted = connection.encrypt_frame(frame_bytes).await?;
-        let encrypted_len = encrypted.len();
-
-        // Apply padding obfuscation
-        let mut obfuscated = encrypted;
-        self.apply_obfuscation(&mut obfuscated)?;
+        // Encrypt the frame and prefix the connection ID for routing
+        let encrypted = connection.seal_frame(frame_bytes).await?;
+        self.send_sealed(connection, encrypted).await
+    }
 
-        // Wrap in protocol mimicry (if enabled)
-        let wrapped = self.wrap_protocol(&obfuscated)?;
+    /// Obfuscate and send an already sealed packet
+    async fn send_sealed(&self, connection: &PeerConnection, encrypted: Vec<u8>) -> Result<()> {
+        let encrypted_len = encrypted.len();
+        let wrapped = self.prepare_sealed(connection, encrypted)?;
 
         // Apply timing delay
         let delay = self.get_timing_delay();
@@ -648,7 +2370,7 @@ impl Node {
         }
 
         // Send via transport
-        let transport = self.get_transport().await?;
+        let transport = self.transport_for(connection).await?;
         transport
             .send_to(&wrapped, connection.peer_addr())
             .await
@@ -663,6 +2385,22 @@ impl Node {
 
         Ok(())
     }
+
+    /// Pad and wrap a sealed packet for the wire
+    ///
+    /// Starts a rekey first if one is due.
+    fn prepare_sealed(&self, connection: &PeerConnection, encrypted: Vec<u8>) -> Result<Vec<u8>> {
+        if let Some(init) = connection.begin_rekey_if_due() {
+            self.spawn_rekey(connection.peer_id, init);
+        }
+
+        // Apply padding obfuscation
+        let mut obfuscated = encrypted;
+        self.apply_obfuscation(&mut obfuscated)?;
+
+        // Wrap in protocol mimicry (if enabled)
+        self.wrap_protocol(&obfuscated)
+    }
 }
 
 #[cfg(test)]
@@ -878,6 +2616,7 @@ mod tests {
         let migration_state = MigrationState {
             peer_id,
             new_addr: "192.168.1.100:8420".parse().unwrap(),
+            transport: TransportType::Udp,
             challenge,
             sender: tx,
             initiated_at: std::time::Instant::now(),
@@ -923,7 +2662,7 @@ mod tests {
         let (tx, rx) = tokio::sync::oneshot::channel();
         node.inner
             .pending_chunks
-            .insert((stream_id, chunk_index), tx);
+            .insert((stream_id, chunk_index), ([7u8; 32], tx));
 
         // Build a Data frame matching the pending chunk
         let frame_bytes = FrameBuilder::new()
@@ -936,7 +2675,7 @@ mod tests {
 
         let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
 
-        let result = node.handle_data_frame(frame).await;
+        let result = node.handle_data_frame(frame, [7u8; 32]).await;
         assert!(result.is_ok());
 
         // Pending chunk should have been resolved
@@ -965,7 +2704,7 @@ mod tests {
         let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
 
         // Should fail because there's no transfer for this stream_id
-        let result = node.handle_data_frame(frame).await;
+        let result = node.handle_data_frame(frame, [7u8; 32]).await;
         assert!(result.is_err());
     }
 
@@ -975,7 +2714,8 @@ mod tests {
         use crate::frame::{FrameBuilder, FrameType};
         use crate::node::file_transfer::FileMetadata;
 
-        let node = Node::new_random().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
 
         // Create file metadata
         let metadata = FileMetadata {
@@ -1000,7 +2740,7 @@ mod tests {
 
         let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
 
-        let result = node.handle_stream_open_frame(frame).await;
+        let result = node.handle_stream_open_frame(frame, [7u8; 32]).await;
         assert!(result.is_ok());
 
         // Verify transfer was stored
@@ -1026,10 +2766,780 @@ mod tests {
 
         let frame = crate::frame::Frame::parse(&frame_bytes).unwrap();
 
-        let result = node.handle_stream_open_frame(frame).await;
+        let result = node.handle_stream_open_frame(frame, [7u8; 32]).await;
         assert!(result.is_err());
     }
 
+    /// Create a node that downloads into `dir`
+    async fn node_in(dir: &std::path::Path) -> Node {
+        let mut config = crate::node::NodeConfig::default();
+        config.transfer.download_dir = dir.to_path_buf();
+        Node::new_with_config(config).await.unwrap()
+    }
+
+    /// Start a 4-chunk incoming transfer from `peer_id` and return its data and tree hash
+    async fn open_verified_transfer(
+        node: &Node,
+        peer_id: PeerId,
+        transfer_id: [u8; 32],
+        dir: &std::path::Path,
+    ) -> (Vec<u8>, wraith_files::tree_hash::FileTreeHash) {
+        use crate::node::file_transfer::{FileMetadata, build_metadata_frame};
+
+        assert_eq!(node.inner.config.transfer.download_dir, dir);
+        let data: Vec<u8> = (0..256u32).map(|i| (i * 7) as u8).collect();
+        let tree = wraith_files::tree_hash::compute_tree_hash_from_data(&data, 64);
+
+        node.inner.sessions.insert(
+            peer_id,
+            Arc::new(PeerConnection::new_for_test(
+                peer_id,
+                "127.0.0.1:5000".parse().unwrap(),
+            )),
+        );
+
+        let metadata = FileMetadata {
+            transfer_id,
+            file_name: "verified.dat".to_string(),
+            file_size: data.len() as u64,
+            chunk_size: 64,
+            total_chunks: 4,
+            root_hash: tree.root,
+        };
+        let stream_id = crate::node::file_transfer::transfer_stream_id(&transfer_id);
+        let frame_bytes = build_metadata_frame(stream_id, &metadata, true).unwrap();
+        node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+
+        (data, tree)
+    }
+
+    #[tokio::test]
+    async fn test_handle_data_frame_rejects_corrupt_chunk() {
+        use crate::node::file_transfer::{build_chunk_frame, build_chunk_hash_frames};
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [7u8; 32];
+        let transfer_id = [3u8; 32];
+        let stream_id = 0x0303;
+        let (data, tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+
+        // Corrupt chunk is rejected and attributed to the sender
+        let corrupt = build_chunk_frame(stream_id, 1, &[0xEE; 64]).unwrap();
+        let frame = Frame::parse(&corrupt).unwrap();
+        let result = node.handle_data_frame(frame, peer_id).await;
+        assert!(matches!(result, Err(NodeError::HashMismatch)));
+
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        let coordinator = context.coordinator.as_ref().unwrap();
+        assert_eq!(coordinator.corrupt_peers().await, vec![peer_id]);
+        assert_eq!(context.transfer_session.read().await.transferred_count(), 0);
+
+        // Valid chunks complete the transfer
+        for (index, chunk) in data.chunks(64).enumerate() {
+            let frame_bytes = build_chunk_frame(stream_id, index as u64, chunk).unwrap();
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        assert!(context.transfer_session.read().await.is_complete());
+
+        // Verified file moved out of quarantine into the download directory
+        let final_path = dir.path().join("verified.dat");
+        assert_eq!(context.transfer_session.read().await.file_path, final_path);
+        assert_eq!(std::fs::read(&final_path).unwrap(), data);
+        assert!(!context.quarantine.as_ref().unwrap().part_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_frames_from_unrelated_peer_are_ignored() {
+        use crate::node::file_transfer::{build_chunk_frame, build_chunk_hash_frames};
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [7u8; 32];
+        let intruder = [8u8; 32];
+        let transfer_id = [5u8; 32];
+        let stream_id = 0x0505;
+        let (data, tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+        node.inner.sessions.insert(
+            intruder,
+            Arc::new(PeerConnection::new_for_test(
+                intruder,
+                "127.0.0.1:5001".parse().unwrap(),
+            )),
+        );
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert_eq!(context.sender, Some(peer_id));
+
+        // Hash segments and chunks from another session never reach the transfer
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, intruder).await.unwrap();
+        }
+        assert!(
+            !context
+                .chunk_hashes
+                .as_ref()
+                .unwrap()
+                .lock()
+                .await
+                .is_verified()
+        );
+        let chunk = build_chunk_frame(stream_id, 0, &[0xEE; 64]).unwrap();
+        node.dispatch_frame(chunk, intruder).await.unwrap();
+        assert_eq!(context.transfer_session.read().await.transferred_count(), 0);
+        assert!(
+            context
+                .coordinator
+                .as_ref()
+                .unwrap()
+                .corrupt_peers()
+                .await
+                .is_empty()
+        );
+
+        // A pending chunk request is only answered by the peer it was sent to
+        let (tx, mut rx) = tokio::sync::oneshot::channel();
+        node.inner.pending_chunks.insert((0x0909, 2), (peer_id, tx));
+        let reply = build_chunk_frame(0x0909, 2, b"forged").unwrap();
+        let _ = node.dispatch_frame(reply, intruder).await;
+        assert!(rx.try_recv().is_err());
+        let reply = build_chunk_frame(0x0909, 2, b"genuine").unwrap();
+        node.dispatch_frame(reply, peer_id).await.unwrap();
+        assert_eq!(rx.await.unwrap(), b"genuine");
+
+        // The sender still completes the transfer
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        for (index, chunk) in data.chunks(64).enumerate() {
+            let frame_bytes = build_chunk_frame(stream_id, index as u64, chunk).unwrap();
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        assert!(context.transfer_session.read().await.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_handle_data_frame_decompresses_chunks() {
+        use crate::node::file_transfer::{build_chunk_hash_frames, build_compressed_chunk_frame};
+        use crate::node::session::Capabilities;
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [7u8; 32];
+        let transfer_id = [4u8; 32];
+        let stream_id = 0x0404;
+        let (data, tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        let compressed_frame = |index: usize, chunk: &[u8]| {
+            let compressed = zstd::bulk::compress(chunk, 3).unwrap();
+            build_compressed_chunk_frame(stream_id, index as u64, chunk.len(), &compressed).unwrap()
+        };
+
+        // Peers that did not negotiate compression may not send compressed chunks
+        let frame_bytes = compressed_frame(0, &data[..64]);
+        let result = node
+            .handle_data_frame(Frame::parse(&frame_bytes).unwrap(), peer_id)
+            .await;
+        assert!(matches!(result, Err(NodeError::InvalidState(_))));
+
+        let connection = PeerConnection::new_for_test(peer_id, "127.0.0.1:5000".parse().unwrap())
+            .with_capabilities(Capabilities::SUPPORTED);
+        node.inner.sessions.insert(peer_id, Arc::new(connection));
+
+        // Chunks are verified against the tree hash after decompression
+        for (index, chunk) in data.chunks(64).enumerate() {
+            let frame_bytes = compressed_frame(index, chunk);
+            let frame = Frame::parse(&frame_bytes).unwrap();
+            assert!(frame.flags().is_compressed());
+            node.handle_data_frame(frame, peer_id).await.unwrap();
+        }
+
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert!(context.transfer_session.read().await.is_complete());
+        assert_eq!(
+            std::fs::read(dir.path().join("verified.dat")).unwrap(),
+            data
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fec_repair_rebuilds_lost_chunk() {
+        use crate::fec::FecEncoder;
+        use crate::node::file_transfer::{build_chunk_hash_frames, transfer_stream_id};
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [9u8; 32];
+        let transfer_id = [5u8; 32];
+        let stream_id = transfer_stream_id(&transfer_id);
+        let (data, tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+
+        let connection = PeerConnection::new_for_test(peer_id, "127.0.0.1:5000".parse().unwrap())
+            .with_capabilities(crate::node::session::Capabilities::SUPPORTED);
+        node.inner.sessions.insert(peer_id, Arc::new(connection));
+
+        // Chunk 2 travels compressed, so its rebuilt frame must stay flagged
+        let mut encoder = FecEncoder::new(4);
+        let mut source_frames = Vec::new();
+        for (index, chunk) in data.chunks(64).enumerate() {
+            let compressed = index == 2;
+            let source = FecSource {
+                sequence: index as u64,
+                offset: index as u64 * 64,
+                payload: if compressed {
+                    zstd::bulk::compress(chunk, 3).unwrap()
+                } else {
+                    chunk.to_vec()
+                },
+                compressed,
+            };
+            source_frames.push(source.build_frame(u32::from(stream_id)).unwrap());
+            encoder.push(source).unwrap();
+        }
+        let repairs = encoder.finish_group(1).unwrap();
+
+        // Chunk 2 is lost in transit
+        let lost = source_frames.remove(2);
+        for frame_bytes in source_frames {
+            node.dispatch_frame_v2(frame_bytes, peer_id).await.unwrap();
+        }
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert!(!context.transfer_session.read().await.is_complete());
+
+        // The repair frame rebuilds it without a retransmission
+        let repair_frame = repairs[0].build_frame(u32::from(stream_id)).unwrap();
+        node.dispatch_frame_v2(repair_frame, peer_id).await.unwrap();
+        assert!(context.transfer_session.read().await.is_complete());
+        assert_eq!(
+            std::fs::read(dir.path().join("verified.dat")).unwrap(),
+            data
+        );
+
+        // A late original of the rebuilt chunk is dropped
+        node.dispatch_frame_v2(lost, peer_id).await.unwrap();
+        assert!(context.transfer_session.read().await.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_handle_data_frame_defers_until_hashes_verified() {
+        use crate::node::file_transfer::{build_chunk_frame, build_chunk_hash_frames};
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [8u8; 32];
+        let transfer_id = [4u8; 32];
+        let stream_id = 0x0404;
+        let (data, tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+
+        // Chunk arrives before the hash layer: buffered, not yet counted
+        let early = build_chunk_frame(stream_id, 0, &data[..64]).unwrap();
+        node.dispatch_frame(early, peer_id).await.unwrap();
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert_eq!(context.transfer_session.read().await.transferred_count(), 0);
+
+        for frame_bytes in build_chunk_hash_frames(stream_id, &tree).unwrap() {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        assert_eq!(context.transfer_session.read().await.transferred_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_without_hash_layer_checks_root() {
+        use crate::node::file_transfer::{FileMetadata, build_chunk_frame, build_metadata_frame};
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let data: Vec<u8> = (0..256u32).map(|i| (i * 13) as u8).collect();
+        let tree = wraith_files::tree_hash::compute_tree_hash_from_data(&data, 64);
+
+        // A baseline peer announces no layer; a legacy session is never asked for one
+        let cases = [
+            ([0x21u8; 32], [0x31u8; 32], false, false),
+            ([0x22u8; 32], [0x32u8; 32], true, true),
+            ([0x23u8; 32], [0x33u8; 32], false, false),
+        ];
+        for (index, (transfer_id, peer_id, legacy, announced)) in cases.into_iter().enumerate() {
+            node.inner.sessions.insert(
+                peer_id,
+                Arc::new(
+                    PeerConnection::new_for_test(peer_id, "127.0.0.1:5000".parse().unwrap())
+                        .with_legacy(legacy),
+                ),
+            );
+            let metadata = FileMetadata {
+                transfer_id,
+                file_name: format!("baseline{index}.dat"),
+                file_size: data.len() as u64,
+                chunk_size: 64,
+                total_chunks: 4,
+                root_hash: tree.root,
+            };
+            let stream_id = crate::node::file_transfer::transfer_stream_id(&transfer_id);
+            let open = build_metadata_frame(stream_id, &metadata, announced).unwrap();
+            node.dispatch_frame(open, peer_id).await.unwrap();
+
+            let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+            assert!(context.chunk_hashes.is_none());
+
+            // The last case corrupts a chunk, which only the root check catches
+            let corrupt = index == 2;
+            let mut result = Ok(());
+            for (chunk_index, chunk) in data.chunks(64).enumerate() {
+                let chunk = if corrupt && chunk_index == 1 {
+                    &[0xEE; 64][..]
+                } else {
+                    chunk
+                };
+                let frame = build_chunk_frame(stream_id, chunk_index as u64, chunk).unwrap();
+                result = node.dispatch_frame(frame, peer_id).await;
+            }
+
+            let final_path = dir.path().join(format!("baseline{index}.dat"));
+            if corrupt {
+                assert!(matches!(result, Err(NodeError::HashMismatch)));
+                assert!(context.transfer_session.read().await.is_failed());
+                assert!(!final_path.exists());
+            } else {
+                result.unwrap();
+                assert!(context.transfer_session.read().await.is_complete());
+                assert_eq!(std::fs::read(&final_path).unwrap(), data);
+            }
+            assert!(!context.quarantine.as_ref().unwrap().part_path.exists());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_chunk_hashes_forged_layer() {
+        use crate::node::file_transfer::build_chunk_hash_frames;
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let peer_id = [9u8; 32];
+        let transfer_id = [5u8; 32];
+        let (_data, mut tree) =
+            open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+
+        tree.chunks[2] = [0xAA; 32];
+        let frames = build_chunk_hash_frames(0x0505, &tree).unwrap();
+        let result = node.dispatch_frame(frames[0].clone(), peer_id).await;
+        assert!(matches!(result, Err(NodeError::HashMismatch)));
+    }
+
+    /// Wait up to five seconds for `condition` to hold
+    async fn wait_until<F: std::future::Future<Output = bool>>(mut condition: impl FnMut() -> F) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition().await {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition never held");
+    }
+
+    #[tokio::test]
+    async fn test_lost_chunk_hash_layer_is_requested_again() {
+        use crate::node::NodeConfig;
+        use crate::node::file_transfer::build_chunk_frame;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            ..NodeConfig::default()
+        };
+        config.transfer.hash_layer_request_interval = Duration::from_millis(20);
+        let sender = Node::new_with_config(config.clone()).await.unwrap();
+        config.transfer.download_dir = dir.path().to_path_buf();
+        let receiver = Node::new_with_config(config).await.unwrap();
+        sender.start().await.unwrap();
+        receiver.start().await.unwrap();
+        sender
+            .establish_session_with_addr(
+                receiver.x25519_public_key(),
+                receiver.listen_addr().await.unwrap(),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let sender_id = *sender.x25519_public_key();
+        let receiver_id = *receiver.x25519_public_key();
+        let transfer_id = [0x42u8; 32];
+        let stream_id = transfer_stream_id(&transfer_id);
+
+        // The receiver opens the transfer but the hash segments never arrive
+        let session = Arc::clone(receiver.inner.sessions.get(&sender_id).unwrap().value());
+        let (data, tree) =
+            open_verified_transfer(&receiver, sender_id, transfer_id, dir.path()).await;
+        receiver.inner.sessions.insert(sender_id, session);
+        let mut outgoing = TransferSession::new_send(
+            transfer_id,
+            dir.path().join("source.dat"),
+            data.len() as u64,
+            64,
+        );
+        outgoing.add_peer(receiver_id);
+        sender.inner.transfers.insert(
+            transfer_id,
+            Arc::new(FileTransferContext::new_send(
+                transfer_id,
+                Arc::new(RwLock::new(outgoing)),
+                tree,
+            )),
+        );
+
+        // An early chunk makes the receiver ask the sender for the layer
+        let chunk = build_chunk_frame(stream_id, 0, &data[..64]).unwrap();
+        receiver.dispatch_frame(chunk, sender_id).await.unwrap();
+        let context = receiver.inner.transfers.get(&transfer_id).unwrap().clone();
+        wait_until(|| async { context.transfer_session.read().await.transferred_count() == 1 })
+            .await;
+
+        for (index, chunk) in data.chunks(64).enumerate().skip(1) {
+            let frame_bytes = build_chunk_frame(stream_id, index as u64, chunk).unwrap();
+            receiver
+                .dispatch_frame(frame_bytes, sender_id)
+                .await
+                .unwrap();
+        }
+        assert!(context.transfer_session.read().await.is_complete());
+        assert_eq!(
+            std::fs::read(dir.path().join("verified.dat")).unwrap(),
+            data
+        );
+
+        sender.stop().await.unwrap();
+        receiver.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_chunk_hash_layer_fails_transfer() {
+        use crate::node::file_transfer::{build_chunk_frame, build_chunk_hash_frames};
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = crate::node::NodeConfig::default();
+        config.transfer.download_dir = dir.path().to_path_buf();
+        config.transfer.hash_layer_request_interval = Duration::from_millis(10);
+        let node = Node::new_with_config(config).await.unwrap();
+        let peer_id = [7u8; 32];
+
+        // Nobody answers the requests: the transfer fails once they run out
+        let transfer_id = [0x43u8; 32];
+        let (data, _tree) = open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+        let chunk = build_chunk_frame(transfer_stream_id(&transfer_id), 0, &data[..64]).unwrap();
+        node.dispatch_frame(chunk, peer_id).await.unwrap();
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        wait_until(|| async { context.transfer_session.read().await.is_failed() }).await;
+        assert!(!context.quarantine.as_ref().unwrap().part_path.exists());
+
+        // A forged layer is discarded and the genuine one still completes the transfer
+        let transfer_id = [0x44u8; 32];
+        let stream_id = transfer_stream_id(&transfer_id);
+        let (data, mut tree) =
+            open_verified_transfer(&node, peer_id, transfer_id, dir.path()).await;
+        let genuine = build_chunk_hash_frames(stream_id, &tree).unwrap();
+        tree.chunks[1] = [0xAA; 32];
+        let forged = build_chunk_hash_frames(stream_id, &tree).unwrap();
+        let result = node.dispatch_frame(forged[0].clone(), peer_id).await;
+        assert!(matches!(result, Err(NodeError::HashMismatch)));
+        node.dispatch_frame(genuine[0].clone(), peer_id)
+            .await
+            .unwrap();
+        for (index, chunk) in data.chunks(64).enumerate() {
+            let frame_bytes = build_chunk_frame(stream_id, index as u64, chunk).unwrap();
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(context.transfer_session.read().await.is_complete());
+        assert!(!context.transfer_session.read().await.is_failed());
+    }
+
+    /// Build a StreamOpen frame announcing a 1 KiB file
+    fn stream_open_frame(transfer_id: [u8; 32], file_name: String) -> Vec<u8> {
+        use crate::node::file_transfer::{FileMetadata, build_metadata_frame};
+
+        let metadata = FileMetadata {
+            transfer_id,
+            file_name,
+            file_size: 1024,
+            chunk_size: 256,
+            total_chunks: 4,
+            root_hash: [0xAB; 32],
+        };
+        build_metadata_frame(0x1010, &metadata, true).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_rejected_by_acceptor() {
+        use crate::node::acceptance::TrustedPeers;
+
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        node.set_transfer_acceptor(Arc::new(TrustedPeers::new([[1u8; 32]])))
+            .await;
+
+        let frame_bytes = stream_open_frame([0x10; 32], "unwanted.dat".to_string());
+        node.dispatch_frame(frame_bytes, [7u8; 32]).await.unwrap();
+
+        assert!(!node.inner.transfers.contains_key(&[0x10; 32]));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_redirected_by_acceptor() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        let redirect = dir.path().join("inbox.dat");
+        let target = redirect.clone();
+        node.set_transfer_acceptor(Arc::new(move |_: &IncomingTransfer| {
+            TransferDecision::Redirect(target.clone())
+        }))
+        .await;
+
+        let frame_bytes = stream_open_frame([0x11; 32], "proposed.dat".to_string());
+        node.dispatch_frame(frame_bytes, [7u8; 32]).await.unwrap();
+
+        let context = node.inner.transfers.get(&[0x11; 32]).unwrap().clone();
+        assert_eq!(context.transfer_session.read().await.file_path, redirect);
+        assert_eq!(context.quarantine.as_ref().unwrap().destination, redirect);
+    }
+
+    #[tokio::test]
+    async fn test_stream_open_sanitizes_hostile_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = node_in(dir.path()).await;
+        std::fs::write(dir.path().join("passwd"), b"existing").unwrap();
+
+        let frame_bytes = stream_open_frame([0x14; 32], "../../etc/passwd".to_string());
+        node.dispatch_frame(frame_bytes, [7u8; 32]).await.unwrap();
+
+        // Confined to the download directory, no clobbering, data kept in quarantine
+        let context = node.inner.transfers.get(&[0x14; 32]).unwrap().clone();
+        let quarantine = context.quarantine.as_ref().unwrap();
+        assert_eq!(quarantine.destination, dir.path().join("passwd (1)"));
+        assert!(
+            quarantine
+                .part_path
+                .starts_with(dir.path().join(".wraith-quarantine"))
+        );
+        assert!(quarantine.part_path.exists());
+        assert!(!quarantine.destination.exists());
+    }
+
+    /// Register an outgoing transfer to `peer_id` that is awaiting acceptance
+    fn pending_send_transfer(
+        node: &Node,
+        peer_id: PeerId,
+        transfer_id: [u8; 32],
+    ) -> oneshot::Receiver<std::result::Result<(), String>> {
+        let mut session = TransferSession::new_send(transfer_id, "outgoing.dat".into(), 1024, 256);
+        session.add_peer(peer_id);
+        session.start();
+        let context = Arc::new(FileTransferContext::new_send(
+            transfer_id,
+            Arc::new(RwLock::new(session)),
+            wraith_files::tree_hash::FileTreeHash {
+                root: [0u8; 32],
+                chunks: Vec::new(),
+            },
+        ));
+        node.inner.transfers.insert(transfer_id, context);
+
+        let (tx, rx) = oneshot::channel();
+        node.inner
+            .pending_acceptances
+            .insert(transfer_id, (peer_id, tx));
+        rx
+    }
+
+    #[tokio::test]
+    async fn test_stream_reset_fails_outgoing_transfer() {
+        use crate::node::file_transfer::build_transfer_reject_frame;
+
+        let node = Node::new_random().await.unwrap();
+        let peer_id = [7u8; 32];
+        let transfer_id = [0x12; 32];
+        let verdict = pending_send_transfer(&node, peer_id, transfer_id);
+        let reject = build_transfer_reject_frame(0x1212, &transfer_id, "disk full").unwrap();
+
+        // A reset from an unrelated peer is ignored
+        node.dispatch_frame(reject.clone(), [8u8; 32])
+            .await
+            .unwrap();
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert!(!context.transfer_session.read().await.is_failed());
+
+        node.dispatch_frame(reject, peer_id).await.unwrap();
+        assert_eq!(verdict.await.unwrap(), Err("disk full".to_string()));
+        assert!(context.transfer_session.read().await.is_failed());
+        assert_eq!(context.rejection_reason(), Some("disk full"));
+
+        let result = node.wait_for_transfer(transfer_id).await;
+        assert!(matches!(result, Err(NodeError::Transfer(_))));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_accept_releases_sender() {
+        use crate::node::file_transfer::build_transfer_accept_frame;
+
+        let node = Node::new_random().await.unwrap();
+        let peer_id = [7u8; 32];
+        let transfer_id = [0x13; 32];
+        let verdict = pending_send_transfer(&node, peer_id, transfer_id);
+        let accept = build_transfer_accept_frame(0x1313, &transfer_id).unwrap();
+
+        // Only the intended receiver can accept
+        node.dispatch_frame(accept.clone(), [8u8; 32])
+            .await
+            .unwrap();
+        assert!(node.inner.pending_acceptances.contains_key(&transfer_id));
+
+        node.dispatch_frame(accept, peer_id).await.unwrap();
+        assert_eq!(verdict.await.unwrap(), Ok(()));
+        assert!(!node.inner.pending_acceptances.contains_key(&transfer_id));
+    }
+
+    /// Build and sign a manifest for a small directory containing a nested file
+    fn signed_tree(
+        source: &std::path::Path,
+        transfer_id: [u8; 32],
+    ) -> (TreeManifest, Vec<crate::node::tree_transfer::TreeFile>) {
+        std::fs::create_dir_all(source.join("project/sub")).unwrap();
+        std::fs::write(source.join("project/a.txt"), vec![1u8; 100]).unwrap();
+        std::fs::write(source.join("project/sub/b.bin"), vec![2u8; 200]).unwrap();
+        std::fs::write(source.join("project/sub/empty"), b"").unwrap();
+
+        let identity = crate::node::Identity::generate().unwrap();
+        let (mut manifest, files) =
+            TreeManifest::from_directory(transfer_id, &source.join("project"), 64).unwrap();
+        manifest.sign(&identity).unwrap();
+        (manifest, files)
+    }
+
+    #[tokio::test]
+    async fn test_tree_transfer_received_and_committed() {
+        use crate::node::file_transfer::{build_chunk_frame, build_chunk_hash_frames};
+        use crate::node::tree_transfer::build_manifest_frames;
+
+        let source = tempfile::tempdir().unwrap();
+        let download = tempfile::tempdir().unwrap();
+        let node = node_in(download.path()).await;
+        let peer_id = [7u8; 32];
+        let transfer_id = [0x42u8; 32];
+        let (manifest, files) = signed_tree(source.path(), transfer_id);
+
+        for frame_bytes in
+            build_manifest_frames(transfer_stream_id(&transfer_id), &manifest).unwrap()
+        {
+            node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+        }
+        let context = node.inner.transfers.get(&transfer_id).unwrap().clone();
+        assert_eq!(
+            context.transfer_session.read().await.total_chunks,
+            manifest.total_chunks()
+        );
+
+        for file in &files {
+            let stream_id = manifest.child_stream_id(file.entry_index);
+            for frame_bytes in build_chunk_hash_frames(stream_id, &file.tree_hash).unwrap() {
+                node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+            }
+            let data = std::fs::read(&file.source).unwrap();
+            for (index, chunk) in data.chunks(64).enumerate() {
+                let frame_bytes = build_chunk_frame(stream_id, index as u64, chunk).unwrap();
+                node.dispatch_frame(frame_bytes, peer_id).await.unwrap();
+            }
+        }
+
+        // One progress for the whole tree; file contexts are released once verified
+        let session = context.transfer_session.read().await;
+        assert!(session.is_complete());
+        assert_eq!(session.bytes_transferred(), 300);
+        assert!(node.inner.tree_files.is_empty());
+
+        let root = download.path().join("project");
+        assert_eq!(session.file_path, root);
+        assert_eq!(std::fs::read(root.join("a.txt")).unwrap(), vec![1u8; 100]);
+        assert_eq!(
+            std::fs::read(root.join("sub/b.bin")).unwrap(),
+            vec![2u8; 200]
+        );
+        assert!(root.join("sub/empty").is_file());
+        assert!(!context.tree_staging.as_ref().unwrap().staging_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_tree_manifest_with_bad_signature_is_ignored() {
+        use crate::node::tree_transfer::build_manifest_frames;
+
+        let source = tempfile::tempdir().unwrap();
+        let download = tempfile::tempdir().unwrap();
+        let node = node_in(download.path()).await;
+        let peer_id = [7u8; 32];
+        let transfer_id = [0x43u8; 32];
+        let (mut manifest, _) = signed_tree(source.path(), transfer_id);
+        manifest.entries[0].path = "evil.txt".to_string();
+
+        let offered = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let seen = Arc::clone(&offered);
+        node.set_transfer_acceptor(Arc::new(move |_: &IncomingTransfer| {
+            seen.store(true, std::sync::atomic::Ordering::SeqCst);
+            TransferDecision::Accept
+        }))
+        .await;
+
+        let mut result = Ok(());
+        for frame_bytes in
+            build_manifest_frames(transfer_stream_id(&transfer_id), &manifest).unwrap()
+        {
+            result = node.dispatch_frame(frame_bytes, peer_id).await;
+        }
+
+        assert!(matches!(result, Err(NodeError::Crypto(_))));
+        assert!(!offered.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(!node.inner.transfers.contains_key(&transfer_id));
+        assert!(node.inner.pending_manifests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tree_manifest_offered_to_acceptor() {
+        use crate::node::tree_transfer::build_manifest_frames;
+
+        let source = tempfile::tempdir().unwrap();
+        let download = tempfile::tempdir().unwrap();
+        let node = node_in(download.path()).await;
+        let transfer_id = [0x44u8; 32];
+        let (manifest, _) = signed_tree(source.path(), transfer_id);
+
+        node.set_transfer_acceptor(Arc::new(|request: &IncomingTransfer| {
+            match &request.manifest {
+                Some(manifest) if manifest.entries.len() == 4 => {
+                    TransferDecision::Reject(format!("{} bytes", request.metadata.file_size))
+                }
+                _ => TransferDecision::Accept,
+            }
+        }))
+        .await;
+
+        for frame_bytes in
+            build_manifest_frames(transfer_stream_id(&transfer_id), &manifest).unwrap()
+        {
+            node.dispatch_frame(frame_bytes, [7u8; 32]).await.unwrap();
+        }
+
+        // Rejected trees leave nothing behind
+        assert!(!node.inner.transfers.contains_key(&transfer_id));
+        assert!(!download.path().join("project").exists());
+    }
+
     #[tokio::test]
     async fn test_node_is_not_running_initially() {
         let node = Node::new_random().await.unwrap();
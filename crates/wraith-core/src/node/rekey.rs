@@ -0,0 +1,788 @@
+//! In-band session rekeying
+//!
+//! Established sessions periodically replace their traffic keys without a
+//! new handshake. Once a key generation reaches one of the
+//! [`RekeyConfig`] limits (time, packets or bytes), whichever peer notices
+//! first starts an exchange of fresh X25519 ephemeral keys. Both sides mix
+//! the Diffie-Hellman output into the current generation's rekey secret
+//! (see [`SessionCrypto::next_generation`]), so traffic keys gain forward
+//! secrecy as old generations are dropped.
+//!
+//! # Wire Protocol
+//!
+//! REKEY frames carry a message kind, the epoch of the new generation and,
+//! for the first two messages, an ephemeral public key:
+//!
+//! ```text
+//! INIT      kind (u8) | epoch (u32) | ephemeral (32)  old keys
+//! RESPONSE  kind (u8) | epoch (u32) | ephemeral (32)  old keys
+//! CONFIRM   kind (u8) | epoch (u32)                   new keys
+//! ```
+//!
+//! The initiator switches to the new generation as soon as the RESPONSE
+//! arrives; the responder keeps sending under the old keys until the first
+//! packet sealed under the new ones (normally the CONFIRM) authenticates, so
+//! neither side ever sends packets its peer cannot open yet. The top bit of
+//! each packet counter is the key phase (the epoch's parity) and selects
+//! the generation a packet is opened with. The previous generation keeps
+//! decrypting in-flight packets until [`RekeyConfig::grace_period`] after
+//! the peer switched.
+//!
+//! An unanswered INIT is retransmitted [`REKEY_ATTEMPTS`] times. When both
+//! peers start an exchange at once, the INIT with the larger ephemeral key
+//! wins and the other peer answers it instead.
+
+use crate::FRAME_HEADER_SIZE;
+use crate::SessionState;
+use crate::frame::{Frame, FrameBuilder, FrameType};
+use crate::node::Node;
+use crate::node::config::RekeyConfig;
+use crate::node::error::{NodeError, Result};
+use crate::node::session::{PeerConnection, PeerId};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wraith_crypto::CryptoError;
+use wraith_crypto::aead::SessionCrypto;
+use wraith_crypto::random::SecureRng;
+use wraith_crypto::x25519::{PrivateKey, PublicKey};
+
+/// REKEY message starting an exchange
+pub const REKEY_INIT: u8 = 0x01;
+
+/// REKEY message answering an INIT
+pub const REKEY_RESPONSE: u8 = 0x02;
+
+/// REKEY message sealed under the new keys, prompting the responder to switch
+pub const REKEY_CONFIRM: u8 = 0x03;
+
+/// Times an unanswered INIT is sent before the exchange is abandoned
+pub const REKEY_ATTEMPTS: u32 = 5;
+
+/// Packet counter bit carrying the key phase
+pub const KEY_PHASE_BIT: u64 = 1 << 63;
+
+/// Wait for a RESPONSE before resending the INIT
+const REKEY_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A parsed REKEY frame payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RekeyMessage {
+    /// Start of an exchange for `epoch`
+    Init {
+        /// Epoch of the proposed key generation
+        epoch: u32,
+        /// Initiator's ephemeral X25519 public key
+        public_key: [u8; 32],
+    },
+    /// Answer to an INIT
+    Response {
+        /// Epoch of the new key generation
+        epoch: u32,
+        /// Responder's ephemeral X25519 public key
+        public_key: [u8; 32],
+    },
+    /// Initiator's first message under the new keys
+    Confirm {
+        /// Epoch of the new key generation
+        epoch: u32,
+    },
+}
+
+impl RekeyMessage {
+    /// Encode the message as a REKEY frame payload
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, epoch, public_key) = match *self {
+            Self::Init { epoch, public_key } => (REKEY_INIT, epoch, Some(public_key)),
+            Self::Response { epoch, public_key } => (REKEY_RESPONSE, epoch, Some(public_key)),
+            Self::Confirm { epoch } => (REKEY_CONFIRM, epoch, None),
+        };
+        let mut payload = Vec::with_capacity(37);
+        payload.push(kind);
+        payload.extend_from_slice(&epoch.to_be_bytes());
+        if let Some(public_key) = public_key {
+            payload.extend_from_slice(&public_key);
+        }
+        payload
+    }
+
+    /// Parse a REKEY frame payload
+    ///
+    /// Returns `None` for unknown kinds and truncated payloads.
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        let (&kind, rest) = payload.split_first()?;
+        let epoch = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?);
+        let public_key = || -> Option<[u8; 32]> { rest.get(4..36)?.try_into().ok() };
+        match kind {
+            REKEY_INIT => Some(Self::Init {
+                epoch,
+                public_key: public_key()?,
+            }),
+            REKEY_RESPONSE => Some(Self::Response {
+                epoch,
+                public_key: public_key()?,
+            }),
+            REKEY_CONFIRM => Some(Self::Confirm { epoch }),
+            _ => None,
+        }
+    }
+}
+
+/// Build a REKEY frame carrying `message`
+pub fn build_rekey_frame(message: &RekeyMessage) -> Result<Vec<u8>> {
+    let payload = message.encode();
+    FrameBuilder::new()
+        .frame_type(FrameType::Rekey)
+        .stream_id(0)
+        .payload(&payload)
+        .build(FRAME_HEADER_SIZE + payload.len())
+        .map_err(|e| NodeError::Other(format!("Failed to build REKEY frame: {e}").into()))
+}
+
+/// Previous key generation, kept for packets still in flight
+struct PreviousKeys {
+    crypto: SessionCrypto,
+    /// Set once the peer is known to have switched
+    retire_at: Option<Instant>,
+}
+
+/// Exchange this side started and is waiting on
+struct PendingRekey {
+    epoch: u32,
+    secret: PrivateKey,
+    public_key: [u8; 32],
+}
+
+/// RESPONSE already sent, repeated if the INIT is retransmitted
+struct SentResponse {
+    epoch: u32,
+    initiator_key: [u8; 32],
+    public_key: [u8; 32],
+}
+
+/// Key generation bookkeeping of one session
+///
+/// The current generation lives in [`PeerConnection::crypto`]; this holds
+/// the usage counters that trigger a rekey and the generations on either
+/// side of it. Callers lock the session crypto before this state.
+pub(crate) struct RekeyState {
+    config: RekeyConfig,
+    epoch: u32,
+    started: Instant,
+    packets: u64,
+    bytes: u64,
+    previous: Option<PreviousKeys>,
+    /// Responder's next generation, used once the initiator switched
+    next: Option<SessionCrypto>,
+    pending: Option<PendingRekey>,
+    response: Option<SentResponse>,
+}
+
+impl RekeyState {
+    pub(crate) fn new(config: RekeyConfig) -> Self {
+        Self {
+            config,
+            epoch: 0,
+            started: Instant::now(),
+            packets: 0,
+            bytes: 0,
+            previous: None,
+            next: None,
+            pending: None,
+            response: None,
+        }
+    }
+
+    /// Epoch of the current key generation
+    pub(crate) fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Key phase bit of packets sealed under the current generation
+    pub(crate) fn key_phase(&self) -> u64 {
+        if self.epoch % 2 == 1 {
+            KEY_PHASE_BIT
+        } else {
+            0
+        }
+    }
+
+    /// Count a packet sealed or opened under the current generation
+    pub(crate) fn record(&mut self, len: usize) {
+        self.packets += 1;
+        self.bytes += len as u64;
+    }
+
+    /// Whether no exchange is running and the peer uses the current keys
+    fn can_initiate(&self) -> bool {
+        self.pending.is_none()
+            && self.next.is_none()
+            && self
+                .previous
+                .as_ref()
+                .is_none_or(|previous| previous.retire_at.is_some())
+    }
+
+    /// Start an exchange if the current generation reached a limit
+    ///
+    /// Returns the INIT to send, or `None` when no rekey is due or one is
+    /// already running.
+    pub(crate) fn begin_if_due(&mut self) -> Option<RekeyMessage> {
+        let due = self.packets >= self.config.packet_limit
+            || self.bytes >= self.config.byte_limit
+            || self.started.elapsed() >= self.config.interval;
+        if due { self.begin() } else { None }
+    }
+
+    /// Start an exchange for the next epoch
+    pub(crate) fn begin(&mut self) -> Option<RekeyMessage> {
+        if !self.can_initiate() {
+            return None;
+        }
+        let secret = PrivateKey::generate(&mut SecureRng::new());
+        let public_key = secret.public_key().to_bytes();
+        let epoch = self.epoch.wrapping_add(1);
+        self.pending = Some(PendingRekey {
+            epoch,
+            secret,
+            public_key,
+        });
+        Some(RekeyMessage::Init { epoch, public_key })
+    }
+
+    /// INIT of the exchange this side is running, if it is still `epoch`'s
+    pub(crate) fn pending_init(&self, epoch: u32) -> Option<RekeyMessage> {
+        self.pending
+            .as_ref()
+            .filter(|pending| pending.epoch == epoch)
+            .map(|pending| RekeyMessage::Init {
+                epoch,
+                public_key: pending.public_key,
+            })
+    }
+
+    /// Give up on an unanswered exchange so a later one can start
+    pub(crate) fn abandon(&mut self, epoch: u32) {
+        if self.pending.as_ref().is_some_and(|p| p.epoch == epoch) {
+            self.pending = None;
+        }
+    }
+
+    /// Answer the peer's INIT
+    ///
+    /// Derives the next generation and returns the RESPONSE to send, or
+    /// `None` if the INIT is stale, invalid or loses a simultaneous start.
+    pub(crate) fn respond(
+        &mut self,
+        current: &SessionCrypto,
+        epoch: u32,
+        initiator_key: [u8; 32],
+    ) -> Option<RekeyMessage> {
+        if let Some(sent) = &self.response
+            && sent.epoch == epoch
+            && sent.initiator_key == initiator_key
+        {
+            return Some(RekeyMessage::Response {
+                epoch,
+                public_key: sent.public_key,
+            });
+        }
+        // A new key for an epoch already answered means the initiator gave
+        // up on that exchange without switching, so it is replaced
+        if epoch != self.epoch.wrapping_add(1) {
+            return None;
+        }
+        if let Some(pending) = &self.pending {
+            if pending.public_key > initiator_key {
+                return None;
+            }
+            self.pending = None;
+        }
+
+        let secret = PrivateKey::generate(&mut SecureRng::new());
+        let public_key = secret.public_key().to_bytes();
+        let shared = secret.exchange(&PublicKey::from_bytes(initiator_key))?;
+        self.next = Some(current.next_generation(shared.as_bytes(), false));
+        self.response = Some(SentResponse {
+            epoch,
+            initiator_key,
+            public_key,
+        });
+        Some(RekeyMessage::Response { epoch, public_key })
+    }
+
+    /// Finish this side's exchange with the peer's RESPONSE
+    ///
+    /// Switches `current` to the new generation and returns `true`, or
+    /// returns `false` if the RESPONSE does not answer the pending INIT.
+    pub(crate) fn complete(
+        &mut self,
+        current: &mut SessionCrypto,
+        epoch: u32,
+        responder_key: [u8; 32],
+    ) -> bool {
+        let Some(pending) = self.pending.as_ref().filter(|p| p.epoch == epoch) else {
+            return false;
+        };
+        let Some(shared) = pending
+            .secret
+            .exchange(&PublicKey::from_bytes(responder_key))
+        else {
+            return false;
+        };
+        let next = current.next_generation(shared.as_bytes(), true);
+        let old = std::mem::replace(current, next);
+        // The responder still sends under the old keys until it sees ours
+        self.advance(old, None);
+        true
+    }
+
+    /// Open a packet, picking the key generation by its key phase
+    ///
+    /// The first packet the initiator sealed under the next generation
+    /// switches the responder over.
+    pub(crate) fn decrypt(
+        &mut self,
+        current: &mut SessionCrypto,
+        counter: u64,
+        ciphertext: &[u8],
+    ) -> std::result::Result<Vec<u8>, CryptoError> {
+        let phase = counter & KEY_PHASE_BIT;
+        let counter = counter & !KEY_PHASE_BIT;
+
+        if self
+            .previous
+            .as_ref()
+            .and_then(|previous| previous.retire_at)
+            .is_some_and(|retire_at| Instant::now() >= retire_at)
+        {
+            self.previous = None;
+        }
+
+        if phase == self.key_phase() {
+            let plaintext = current.decrypt_with_counter(counter, ciphertext, &[])?;
+            if let Some(previous) = &mut self.previous
+                && previous.retire_at.is_none()
+            {
+                previous.retire_at = Some(Instant::now() + self.config.grace_period);
+            }
+            self.record(ciphertext.len());
+            return Ok(plaintext);
+        }
+
+        if let Some(next) = &mut self.next
+            && let Ok(plaintext) = next.decrypt_with_counter(counter, ciphertext, &[])
+        {
+            let next = self.next.take().expect("next generation present");
+            let old = std::mem::replace(current, next);
+            self.advance(old, Some(Instant::now() + self.config.grace_period));
+            self.record(ciphertext.len());
+            return Ok(plaintext);
+        }
+
+        match &mut self.previous {
+            Some(previous) => previous
+                .crypto
+                .decrypt_with_counter(counter, ciphertext, &[]),
+            None => Err(CryptoError::DecryptionFailed),
+        }
+    }
+
+    fn advance(&mut self, old: SessionCrypto, retire_at: Option<Instant>) {
+        self.epoch = self.epoch.wrapping_add(1);
+        self.started = Instant::now();
+        self.packets = 0;
+        self.bytes = 0;
+        self.previous = Some(PreviousKeys {
+            crypto: old,
+            retire_at,
+        });
+        self.pending = None;
+        self.response = None;
+        tracing::debug!("Session switched to key epoch {}", self.epoch);
+    }
+}
+
+impl Node {
+    /// Start rekeying the session with `peer_id` now
+    ///
+    /// Rekeys normally start on their own once a [`RekeyConfig`] limit is
+    /// reached. Returns once the INIT has been sent; the exchange completes
+    /// in the background.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NodeError::SessionNotFound` if there is no session with the
+    /// peer and `NodeError::InvalidState` if an exchange is already running.
+    pub async fn rekey_session(&self, peer_id: &PeerId) -> Result<()> {
+        let connection = self
+            .inner
+            .sessions
+            .get(peer_id)
+            .map(|e| Arc::clone(e.value()))
+            .ok_or(NodeError::SessionNotFound(*peer_id))?;
+        let init = connection
+            .begin_rekey()
+            .ok_or_else(|| NodeError::InvalidState("Session rekey already in progress".into()))?;
+        self.spawn_rekey(*peer_id, init);
+        Ok(())
+    }
+
+    /// Drive an exchange this side started, resending the INIT until answered
+    pub(crate) fn spawn_rekey(&self, peer_id: PeerId, init: RekeyMessage) {
+        let RekeyMessage::Init { epoch, .. } = init else {
+            return;
+        };
+        let node = self.clone();
+        tokio::spawn(async move {
+            let mut message = Some(init);
+            for _ in 0..REKEY_ATTEMPTS {
+                let Some(init) = message else {
+                    // Completed, or yielded to the peer's simultaneous INIT
+                    return;
+                };
+                let Some(connection) = node
+                    .inner
+                    .sessions
+                    .get(&peer_id)
+                    .map(|e| Arc::clone(e.value()))
+                else {
+                    return;
+                };
+                connection.set_rekeying(true).await;
+                if let Err(e) = node.send_rekey_message(&connection, &init).await {
+                    tracing::debug!("Failed to send REKEY INIT: {}", e);
+                }
+                tokio::time::sleep(REKEY_RETRY_INTERVAL).await;
+                message = connection.rekey_state().pending_init(epoch);
+            }
+
+            if let Some(connection) = node
+                .inner
+                .sessions
+                .get(&peer_id)
+                .map(|e| Arc::clone(e.value()))
+                && connection.rekey_state().pending_init(epoch).is_some()
+            {
+                connection.rekey_state().abandon(epoch);
+                connection.set_rekeying(false).await;
+                tracing::warn!(
+                    "Peer {} did not answer rekey to epoch {}",
+                    hex::encode(&peer_id[..8]),
+                    epoch
+                );
+            }
+        });
+    }
+
+    /// Handle a REKEY frame
+    pub(crate) async fn handle_rekey_frame(&self, frame: Frame<'_>, peer_id: PeerId) -> Result<()> {
+        let message = RekeyMessage::parse(frame.payload())
+            .ok_or_else(|| NodeError::Other("Malformed REKEY frame".into()))?;
+        let Some(connection) = self
+            .inner
+            .sessions
+            .get(&peer_id)
+            .map(|e| Arc::clone(e.value()))
+        else {
+            return Ok(());
+        };
+
+        match message {
+            RekeyMessage::Init { epoch, public_key } => {
+                if let Some(response) = connection.respond_to_rekey(epoch, public_key).await {
+                    connection.set_rekeying(true).await;
+                    self.send_rekey_message(&connection, &response).await?;
+                }
+            }
+            RekeyMessage::Response { epoch, public_key } => {
+                if connection.complete_rekey(epoch, public_key).await {
+                    connection.set_rekeying(false).await;
+                    self.send_rekey_message(&connection, &RekeyMessage::Confirm { epoch })
+                        .await?;
+                }
+            }
+            RekeyMessage::Confirm { epoch } => {
+                // Opening the packet already switched us over
+                tracing::trace!("Peer confirmed key epoch {}", epoch);
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_rekey_message(
+        &self,
+        connection: &PeerConnection,
+        message: &RekeyMessage,
+    ) -> Result<()> {
+        let frame = build_rekey_frame(message)?;
+        self.send_encrypted_frame(connection, &frame).await
+    }
+}
+
+impl PeerConnection {
+    /// Leave or enter the `Rekeying` state, if the session is in the other
+    pub(crate) async fn set_rekeying(&self, rekeying: bool) {
+        let (from, to) = if rekeying {
+            (SessionState::Established, SessionState::Rekeying)
+        } else {
+            (SessionState::Rekeying, SessionState::Established)
+        };
+        let mut session = self.session.write().await;
+        if session.state() == from {
+            let _ = session.transition_to(to);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (SessionCrypto, SessionCrypto) {
+        (
+            SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]),
+            SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]),
+        )
+    }
+
+    fn seal(
+        crypto: &mut SessionCrypto,
+        state: &mut RekeyState,
+        plaintext: &[u8],
+    ) -> (u64, Vec<u8>) {
+        let counter = crypto.send_counter();
+        let ciphertext = crypto.encrypt(plaintext, &[]).unwrap();
+        state.record(ciphertext.len());
+        (counter | state.key_phase(), ciphertext)
+    }
+
+    /// Run an exchange from `a` to `b`, returning the CONFIRM packet
+    fn exchange(
+        a: (&mut SessionCrypto, &mut RekeyState),
+        b: (&mut SessionCrypto, &mut RekeyState),
+    ) -> (u64, Vec<u8>) {
+        let RekeyMessage::Init { epoch, public_key } = a.1.begin().unwrap() else {
+            panic!("expected INIT");
+        };
+        let RekeyMessage::Response {
+            public_key: response_key,
+            ..
+        } = b.1.respond(b.0, epoch, public_key).unwrap()
+        else {
+            panic!("expected RESPONSE");
+        };
+        assert!(a.1.complete(a.0, epoch, response_key));
+        seal(a.0, a.1, b"confirm")
+    }
+
+    #[test]
+    fn test_rekey_message_roundtrip() {
+        let messages = [
+            RekeyMessage::Init {
+                epoch: 3,
+                public_key: [7u8; 32],
+            },
+            RekeyMessage::Response {
+                epoch: u32::MAX,
+                public_key: [9u8; 32],
+            },
+            RekeyMessage::Confirm { epoch: 1 },
+        ];
+        for message in messages {
+            assert_eq!(RekeyMessage::parse(&message.encode()), Some(message));
+        }
+        assert_eq!(RekeyMessage::parse(&[REKEY_INIT, 0, 0, 0, 1]), None);
+        assert_eq!(RekeyMessage::parse(&[0xFF, 0, 0, 0, 1]), None);
+        assert_eq!(RekeyMessage::parse(&[]), None);
+    }
+
+    #[test]
+    fn test_exchange_switches_both_sides() {
+        let (mut alice, mut bob) = pair();
+        let mut alice_state = RekeyState::new(RekeyConfig::default());
+        let mut bob_state = RekeyState::new(RekeyConfig::default());
+
+        let (confirm_counter, confirm) =
+            exchange((&mut alice, &mut alice_state), (&mut bob, &mut bob_state));
+        assert_eq!(alice_state.epoch(), 1);
+        assert_eq!(confirm_counter, KEY_PHASE_BIT);
+
+        // Until the CONFIRM arrives Bob still seals under the old keys,
+        // which Alice keeps accepting
+        assert_eq!(bob_state.epoch(), 0);
+        let (counter, ciphertext) = seal(&mut bob, &mut bob_state, b"old");
+        assert_eq!(counter & KEY_PHASE_BIT, 0);
+        assert_eq!(
+            alice_state
+                .decrypt(&mut alice, counter, &ciphertext)
+                .unwrap(),
+            b"old"
+        );
+
+        // The CONFIRM switches Bob over
+        assert_eq!(
+            bob_state
+                .decrypt(&mut bob, confirm_counter, &confirm)
+                .unwrap(),
+            b"confirm"
+        );
+        assert_eq!(bob_state.epoch(), 1);
+        let (counter, ciphertext) = seal(&mut bob, &mut bob_state, b"new");
+        assert_eq!(counter, KEY_PHASE_BIT);
+        assert_eq!(
+            alice_state
+                .decrypt(&mut alice, counter, &ciphertext)
+                .unwrap(),
+            b"new"
+        );
+    }
+
+    #[test]
+    fn test_previous_generation_retires_after_grace() {
+        let (mut alice, mut bob) = pair();
+        let config = RekeyConfig {
+            grace_period: Duration::ZERO,
+            ..RekeyConfig::default()
+        };
+        let mut alice_state = RekeyState::new(config.clone());
+        let mut bob_state = RekeyState::new(config);
+
+        // A packet Alice sealed before the rekey, delivered late
+        let (late_counter, late) = seal(&mut alice, &mut alice_state, b"late");
+
+        let (counter, confirm) =
+            exchange((&mut alice, &mut alice_state), (&mut bob, &mut bob_state));
+        bob_state.decrypt(&mut bob, counter, &confirm).unwrap();
+
+        // With no grace period the old generation is gone
+        assert!(bob_state.decrypt(&mut bob, late_counter, &late).is_err());
+    }
+
+    #[test]
+    fn test_late_packets_open_within_grace() {
+        let (mut alice, mut bob) = pair();
+        let mut alice_state = RekeyState::new(RekeyConfig::default());
+        let mut bob_state = RekeyState::new(RekeyConfig::default());
+
+        let (late_counter, late) = seal(&mut alice, &mut alice_state, b"late");
+        let (counter, confirm) =
+            exchange((&mut alice, &mut alice_state), (&mut bob, &mut bob_state));
+        bob_state.decrypt(&mut bob, counter, &confirm).unwrap();
+
+        assert_eq!(
+            bob_state.decrypt(&mut bob, late_counter, &late).unwrap(),
+            b"late"
+        );
+        // Replays of old packets are still rejected
+        assert!(bob_state.decrypt(&mut bob, late_counter, &late).is_err());
+    }
+
+    #[test]
+    fn test_repeated_rekeys_alternate_initiator() {
+        let (mut alice, mut bob) = pair();
+        let mut alice_state = RekeyState::new(RekeyConfig::default());
+        let mut bob_state = RekeyState::new(RekeyConfig::default());
+
+        for round in 0..6u32 {
+            let (counter, confirm) = if round % 2 == 0 {
+                let (counter, confirm) =
+                    exchange((&mut alice, &mut alice_state), (&mut bob, &mut bob_state));
+                bob_state.decrypt(&mut bob, counter, &confirm).unwrap();
+                seal(&mut bob, &mut bob_state, b"ack")
+            } else {
+                let (counter, confirm) =
+                    exchange((&mut bob, &mut bob_state), (&mut alice, &mut alice_state));
+                alice_state.decrypt(&mut alice, counter, &confirm).unwrap();
+                seal(&mut alice, &mut alice_state, b"ack")
+            };
+            let receiver = if round % 2 == 0 {
+                alice_state.decrypt(&mut alice, counter, &confirm)
+            } else {
+                bob_state.decrypt(&mut bob, counter, &confirm)
+            };
+            assert_eq!(receiver.unwrap(), b"ack");
+            assert_eq!(alice_state.epoch(), round + 1);
+            assert_eq!(bob_state.epoch(), round + 1);
+        }
+    }
+
+    #[test]
+    fn test_simultaneous_init_larger_key_wins() {
+        let (alice, bob) = pair();
+        let mut alice_state = RekeyState::new(RekeyConfig::default());
+        let mut bob_state = RekeyState::new(RekeyConfig::default());
+
+        let Some(RekeyMessage::Init {
+            epoch,
+            public_key: alice_key,
+        }) = alice_state.begin()
+        else {
+            panic!("expected INIT");
+        };
+        let Some(RekeyMessage::Init {
+            public_key: bob_key,
+            ..
+        }) = bob_state.begin()
+        else {
+            panic!("expected INIT");
+        };
+
+        let alice_answer = alice_state.respond(&alice, epoch, bob_key);
+        let bob_answer = bob_state.respond(&bob, epoch, alice_key);
+        // Exactly one side yields and answers the other's INIT
+        assert_ne!(alice_answer.is_some(), bob_answer.is_some());
+        assert_eq!(alice_answer.is_some(), bob_key > alice_key);
+    }
+
+    #[test]
+    fn test_rekey_due_on_limits() {
+        let config = RekeyConfig {
+            packet_limit: 3,
+            ..RekeyConfig::default()
+        };
+        let mut state = RekeyState::new(config);
+        state.record(100);
+        state.record(100);
+        assert!(state.begin_if_due().is_none());
+        state.record(100);
+        assert!(matches!(
+            state.begin_if_due(),
+            Some(RekeyMessage::Init { epoch: 1, .. })
+        ));
+        // Already running
+        assert!(state.begin_if_due().is_none());
+        state.abandon(1);
+        assert!(state.begin_if_due().is_some());
+
+        let mut state = RekeyState::new(RekeyConfig {
+            byte_limit: 1000,
+            ..RekeyConfig::default()
+        });
+        state.record(999);
+        assert!(state.begin_if_due().is_none());
+        state.record(1);
+        assert!(state.begin_if_due().is_some());
+
+        let mut state = RekeyState::new(RekeyConfig {
+            interval: Duration::ZERO,
+            ..RekeyConfig::default()
+        });
+        assert!(state.begin_if_due().is_some());
+    }
+
+    #[test]
+    fn test_duplicate_init_repeats_response() {
+        let (_, bob) = pair();
+        let mut alice_state = RekeyState::new(RekeyConfig::default());
+        let mut bob_state = RekeyState::new(RekeyConfig::default());
+
+        let Some(RekeyMessage::Init { epoch, public_key }) = alice_state.begin() else {
+            panic!("expected INIT");
+        };
+        let first = bob_state.respond(&bob, epoch, public_key).unwrap();
+        let second = bob_state.respond(&bob, epoch, public_key).unwrap();
+        assert_eq!(first, second);
+        // A stale epoch is ignored
+        assert!(bob_state.respond(&bob, epoch + 5, public_key).is_none());
+    }
+}
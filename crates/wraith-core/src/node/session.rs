@@ -1,19 +1,37 @@
 //! Session management with Noise_XX handshake integration
 
+use crate::fec::FecDecoder;
+use crate::frame::compat::{FormatNegotiation, WireFormat, v1_frame_to_v2, v2_frame_to_v1};
+use crate::frame::frame_v2::FrameV2;
+use crate::frame::header_v2::FrameHeaderV2;
+use crate::node::config::RekeyConfig;
 use crate::node::error::{NodeError, Result};
+use crate::node::rekey::{RekeyMessage, RekeyState};
 use crate::{ConnectionId, Session, SessionState};
+use std::borrow::Cow;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tokio::sync::{RwLock, oneshot};
+use tokio::sync::{Mutex, RwLock, oneshot};
+use wraith_crypto::SessionKeys;
 use wraith_crypto::aead::SessionCrypto;
+use wraith_crypto::hybrid::{HybridCiphertext, HybridKeyPair, HybridPublicKey, HybridSharedSecret};
 use wraith_crypto::noise::{NoiseHandshake, NoiseKeypair};
+use wraith_crypto::random::SecureRng;
+use wraith_crypto::suite::CryptoSuite;
+use wraith_transport::factory::TransportType;
 use wraith_transport::transport::Transport;
 
+/// Size of the packet counter that precedes each frame's ciphertext
+const PACKET_COUNTER_SIZE: usize = 8;
+
 /// Type alias for peer address with interior mutability for connection migration
 type PeerAddr = std::sync::RwLock<SocketAddr>;
 
+/// Type alias for the transport carrying a session (changes on transport migration)
+type PeerTransport = std::sync::RwLock<TransportType>;
+
 /// Handshake packet with sender address (for channel-based handshake)
 ///
 /// # Handshake Packet Channeling
@@ -61,6 +79,15 @@ pub struct PeerConnection {
     /// Peer address (wrapped for interior mutability during connection migration)
     peer_addr: PeerAddr,
 
+    /// Transport the session's packets are sent over
+    transport: PeerTransport,
+
+    /// Other transports the peer advertised, usable as fallback paths
+    peer_transports: std::sync::RwLock<Vec<(TransportType, SocketAddr)>>,
+
+    /// Whether our own transports still need advertising to the peer
+    advertisement_pending: AtomicBool,
+
     /// Connection ID for this session
     pub connection_id: ConnectionId,
 
@@ -70,6 +97,9 @@ pub struct PeerConnection {
     /// Session crypto (AEAD + ratchet)
     pub crypto: Arc<RwLock<SessionCrypto>>,
 
+    /// Key generations around the current one and rekey triggers
+    rekey: Arc<std::sync::Mutex<RekeyState>>,
+
     /// Connection statistics
     pub stats: ConnectionStats,
 
@@ -82,6 +112,25 @@ pub struct PeerConnection {
 
     /// Timestamp when the session was established
     pub established_at: std::time::SystemTime,
+
+    /// Cipher suite negotiated during the handshake (`None` for sessions
+    /// built from raw keys rather than a handshake)
+    pub crypto_suite: Option<CryptoSuite>,
+
+    /// Frame wire format negotiated during the handshake (v1 for sessions
+    /// built from raw keys)
+    pub wire_format: WireFormat,
+
+    /// Optional features negotiated during the handshake (none for sessions
+    /// built from raw keys)
+    pub capabilities: Capabilities,
+
+    /// Whether the peer predates suite negotiation, in which case packets
+    /// carry no packet counter and are never rekeyed
+    pub legacy: bool,
+
+    /// Forward error correction state for frames received from the peer
+    pub fec_decoder: Arc<Mutex<FecDecoder>>,
 }
 
 /// Get current time as milliseconds since UNIX epoch
@@ -99,10 +148,16 @@ impl Clone for PeerConnection {
             peer_id: self.peer_id,
             // Clone peer_addr by reading current value and creating new RwLock
             peer_addr: std::sync::RwLock::new(self.peer_addr()),
+            transport: std::sync::RwLock::new(self.transport_type()),
+            peer_transports: std::sync::RwLock::new(self.peer_transports()),
+            advertisement_pending: AtomicBool::new(
+                self.advertisement_pending.load(Ordering::Relaxed),
+            ),
             connection_id: self.connection_id,
             // Clone Arc references (cheap - just incrementing refcount)
             session: Arc::clone(&self.session),
             crypto: Arc::clone(&self.crypto),
+            rekey: Arc::clone(&self.rekey),
             stats: self.stats.clone(),
             // Clone AtomicU64 by loading its current value
             last_activity_ms: AtomicU64::new(self.last_activity_ms.load(Ordering::Relaxed)),
@@ -110,6 +165,11 @@ impl Clone for PeerConnection {
                 self.failed_pings.load(Ordering::Relaxed),
             ),
             established_at: self.established_at,
+            crypto_suite: self.crypto_suite,
+            wire_format: self.wire_format,
+            capabilities: self.capabilities,
+            legacy: self.legacy,
+            fec_decoder: Arc::clone(&self.fec_decoder),
         }
     }
 }
@@ -127,16 +187,63 @@ impl PeerConnection {
             session_id,
             peer_id,
             peer_addr: std::sync::RwLock::new(peer_addr),
+            transport: std::sync::RwLock::new(TransportType::Udp),
+            peer_transports: std::sync::RwLock::new(Vec::new()),
+            advertisement_pending: AtomicBool::new(false),
             connection_id,
             session: Arc::new(RwLock::new(Session::new())),
             crypto: Arc::new(RwLock::new(crypto)),
+            rekey: Arc::new(std::sync::Mutex::new(RekeyState::new(
+                RekeyConfig::default(),
+            ))),
             stats: ConnectionStats::default(),
             last_activity_ms: AtomicU64::new(current_time_ms()),
             failed_pings: std::sync::atomic::AtomicU32::new(0),
             established_at: std::time::SystemTime::now(),
+            crypto_suite: None,
+            wire_format: WireFormat::V1,
+            capabilities: Capabilities::empty(),
+            legacy: false,
+            fec_decoder: Arc::new(Mutex::new(FecDecoder::default())),
         }
     }
 
+    /// Record the cipher suite negotiated for this session
+    pub fn with_crypto_suite(mut self, suite: CryptoSuite) -> Self {
+        self.crypto_suite = Some(suite);
+        self
+    }
+
+    /// Record the frame wire format negotiated for this session
+    pub fn with_wire_format(mut self, wire_format: WireFormat) -> Self {
+        self.wire_format = wire_format;
+        self
+    }
+
+    /// Record the optional features negotiated for this session
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Record whether the peer predates suite negotiation
+    pub fn with_legacy(mut self, legacy: bool) -> Self {
+        self.legacy = legacy;
+        self
+    }
+
+    /// Set the limits that trigger rekeying this session
+    pub fn with_rekey_config(self, config: RekeyConfig) -> Self {
+        *self.rekey_state() = RekeyState::new(config);
+        self
+    }
+
+    /// Record the transport the session was established over
+    pub fn with_transport(self, transport: TransportType) -> Self {
+        *self.transport.write().expect("transport lock poisoned") = transport;
+        self
+    }
+
     /// Get the current peer address
     ///
     /// Thread-safe read access to the peer address.
@@ -149,6 +256,55 @@ impl PeerConnection {
         *self.peer_addr.read().expect("peer_addr lock poisoned")
     }
 
+    /// Get the transport the session's packets are currently sent over
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport RwLock is poisoned.
+    #[inline]
+    pub fn transport_type(&self) -> TransportType {
+        *self.transport.read().expect("transport lock poisoned")
+    }
+
+    /// Get the transports the peer advertised besides the session's own
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    pub fn peer_transports(&self) -> Vec<(TransportType, SocketAddr)> {
+        self.peer_transports
+            .read()
+            .expect("peer_transports lock poisoned")
+            .clone()
+    }
+
+    /// Record the transports the peer advertised
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    pub fn set_peer_transports(&self, transports: Vec<(TransportType, SocketAddr)>) {
+        *self
+            .peer_transports
+            .write()
+            .expect("peer_transports lock poisoned") = transports;
+    }
+
+    /// Defer advertising our transports until the peer is known to be ready
+    ///
+    /// Used by the handshake initiator, whose first packets can arrive
+    /// before the responder has installed the session.
+    pub fn defer_advertisement(&self) {
+        self.advertisement_pending.store(true, Ordering::Relaxed);
+    }
+
+    /// Claim a deferred transport advertisement
+    ///
+    /// Returns `true` exactly once after [`Self::defer_advertisement`].
+    pub fn take_advertisement_pending(&self) -> bool {
+        self.advertisement_pending.swap(false, Ordering::Relaxed)
+    }
+
     /// Increment failed ping counter
     pub fn increment_failed_pings(&self) -> u32 {
         self.failed_pings.fetch_add(1, Ordering::Relaxed) + 1
@@ -218,13 +374,24 @@ impl PeerConnection {
             session_id,
             peer_id,
             peer_addr: std::sync::RwLock::new(peer_addr),
+            transport: std::sync::RwLock::new(TransportType::Udp),
+            peer_transports: std::sync::RwLock::new(Vec::new()),
+            advertisement_pending: AtomicBool::new(false),
             connection_id,
             session: Arc::new(RwLock::new(crate::Session::new())),
             crypto: Arc::new(RwLock::new(crypto)),
+            rekey: Arc::new(std::sync::Mutex::new(RekeyState::new(
+                RekeyConfig::default(),
+            ))),
             stats: ConnectionStats::default(),
             last_activity_ms: AtomicU64::new(current_time_ms()),
             failed_pings: std::sync::atomic::AtomicU32::new(0),
             established_at: std::time::SystemTime::now(),
+            crypto_suite: None,
+            wire_format: WireFormat::V1,
+            capabilities: Capabilities::empty(),
+            legacy: false,
+            fec_decoder: Arc::new(Mutex::new(FecDecoder::default())),
         }
     }
 
@@ -244,21 +411,104 @@ impl PeerConnection {
 
     /// Encrypt frame data for transmission
     ///
-    /// Takes serialized frame bytes and encrypts them using the session crypto.
+    /// Takes serialized v1 frame bytes (as built by [`FrameBuilder`]) and
+    /// encrypts them using the session crypto. When the session negotiated
+    /// the v2 wire format the frame is re-encoded as v2 first, so callers
+    /// build v1 frames regardless of what the peer speaks.
     /// Automatically manages nonce counters and checks for rekey conditions.
     ///
     /// # Arguments
     ///
-    /// * `frame_bytes` - Serialized frame data to encrypt
+    /// * `frame_bytes` - Serialized v1 frame data to encrypt
     ///
     /// # Returns
     ///
-    /// Encrypted frame data (ciphertext + auth tag)
+    /// Encrypted frame data: the 8-byte big-endian packet counter the nonce
+    /// was derived from, followed by ciphertext + auth tag. Carrying the
+    /// counter lets the peer decrypt packets that arrive out of order or
+    /// over a different transport after migration; its top bit is the key
+    /// phase telling the peer which key generation sealed the packet.
     ///
     /// # Errors
     ///
-    /// Returns error if encryption fails or rekey is needed.
+    /// Returns error if the frame cannot be re-encoded, encryption fails or
+    /// the nonce space ran out before an in-band rekey completed.
+    ///
+    /// [`FrameBuilder`]: crate::frame::FrameBuilder
     pub async fn encrypt_frame(&self, frame_bytes: &[u8]) -> Result<Vec<u8>> {
+        let encoded =
+            if self.wire_format.is_v2() {
+                Cow::Owned(v1_frame_to_v2(frame_bytes).map_err(|e| {
+                    NodeError::Other(format!("Failed to encode v2 frame: {e}").into())
+                })?)
+            } else {
+                Cow::Borrowed(frame_bytes)
+            };
+        self.encrypt_encoded(&encoded).await
+    }
+
+    /// Encrypt a v2 frame for transmission
+    ///
+    /// Takes serialized v2 frame bytes (as built by [`FrameBuilderV2`]). On
+    /// sessions that negotiated the v1 wire format the frame is re-encoded
+    /// as v1, which fails for v2-only frame types.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NodeError::InvalidState` if the peer speaks v1 and the frame
+    /// has no v1 equivalent, or an error if encryption fails.
+    ///
+    /// [`FrameBuilderV2`]: crate::frame::frame_v2::FrameBuilderV2
+    pub async fn encrypt_frame_v2(&self, frame_bytes: &[u8]) -> Result<Vec<u8>> {
+        if self.wire_format.is_v2() {
+            return self.encrypt_encoded(frame_bytes).await;
+        }
+
+        let v1 = v2_frame_to_v1(frame_bytes)
+            .map_err(|e| NodeError::Other(format!("Failed to encode v1 frame: {e}").into()))?;
+        match v1 {
+            Some(v1) => self.encrypt_encoded(&v1).await,
+            None => {
+                let frame_type = FrameV2::parse(frame_bytes).map(|frame| frame.frame_type());
+                Err(NodeError::InvalidState(
+                    format!("Peer speaks the v1 wire format, which cannot carry {frame_type:?}")
+                        .into(),
+                ))
+            }
+        }
+    }
+
+    /// Encrypt a frame and prefix the connection ID
+    ///
+    /// Produces a complete packet: the 8-byte connection ID the receiver
+    /// routes on, followed by the output of [`Self::encrypt_frame`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::encrypt_frame`].
+    pub async fn seal_frame(&self, frame_bytes: &[u8]) -> Result<Vec<u8>> {
+        let encrypted = self.encrypt_frame(frame_bytes).await?;
+        Ok(self.prefix_connection_id(encrypted))
+    }
+
+    /// Encrypt a v2 frame and prefix the connection ID
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::encrypt_frame_v2`].
+    pub async fn seal_frame_v2(&self, frame_bytes: &[u8]) -> Result<Vec<u8>> {
+        let encrypted = self.encrypt_frame_v2(frame_bytes).await?;
+        Ok(self.prefix_connection_id(encrypted))
+    }
+
+    fn prefix_connection_id(&self, encrypted: Vec<u8>) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(8 + encrypted.len());
+        packet.extend_from_slice(&self.connection_id.to_bytes());
+        packet.extend_from_slice(&encrypted);
+        packet
+    }
+
+    async fn encrypt_encoded(&self, frame_bytes: &[u8]) -> Result<Vec<u8>> {
         let mut crypto = self.crypto.write().await;
 
         // Check if rekey is needed
@@ -268,10 +518,37 @@ impl PeerConnection {
             ));
         }
 
+        // v2 sessions key each packet to the stream its frame belongs to
+        let stream_id = if self.wire_format.is_v2() {
+            FrameHeaderV2::decode(frame_bytes).map_or(0, |header| header.stream_id)
+        } else {
+            0
+        };
+
+        // Legacy peers count packets implicitly, as they arrive
+        if self.legacy {
+            return crypto
+                .encrypt(frame_bytes, &[])
+                .map_err(|e| NodeError::Crypto(e.to_string()));
+        }
+
         // Encrypt with empty AAD (frame already contains all necessary data)
-        crypto
-            .encrypt(frame_bytes, &[])
-            .map_err(|e| NodeError::Crypto(e.to_string()))
+        let counter = crypto.send_counter();
+        let ciphertext = crypto
+            .encrypt_stream(stream_id, frame_bytes, &[])
+            .map_err(|e| NodeError::Crypto(e.to_string()))?;
+
+        // Tag the counter with the key phase of the generation used
+        let counter = {
+            let mut rekey = self.rekey_state();
+            rekey.record(ciphertext.len());
+            counter | rekey.key_phase()
+        };
+
+        let mut encrypted = Vec::with_capacity(PACKET_COUNTER_SIZE + ciphertext.len());
+        encrypted.extend_from_slice(&counter.to_be_bytes());
+        encrypted.extend_from_slice(&ciphertext);
+        Ok(encrypted)
     }
 
     /// Decrypt received frame data
@@ -281,22 +558,100 @@ impl PeerConnection {
     ///
     /// # Arguments
     ///
-    /// * `encrypted_bytes` - Encrypted frame data (ciphertext + auth tag)
+    /// * `encrypted_bytes` - Encrypted frame data (packet counter + ciphertext + auth tag)
     ///
     /// # Returns
     ///
-    /// Decrypted frame data ready for parsing
+    /// Decrypted frame data ready for parsing, in the session's wire format
     ///
     /// # Errors
     ///
     /// Returns error if decryption fails, authentication fails, or replay is detected.
     pub async fn decrypt_frame(&self, encrypted_bytes: &[u8]) -> Result<Vec<u8>> {
+        if self.legacy {
+            return self
+                .crypto
+                .write()
+                .await
+                .decrypt(encrypted_bytes, &[])
+                .map_err(|e| NodeError::Crypto(e.to_string()));
+        }
+        if encrypted_bytes.len() < PACKET_COUNTER_SIZE {
+            return Err(NodeError::Crypto(
+                wraith_crypto::CryptoError::DecryptionFailed.to_string(),
+            ));
+        }
+        let (counter, ciphertext) = encrypted_bytes.split_at(PACKET_COUNTER_SIZE);
+        let counter = u64::from_be_bytes(counter.try_into().expect("split at counter size"));
+
         let mut crypto = self.crypto.write().await;
 
-        // Decrypt with empty AAD
-        crypto
-            .decrypt(encrypted_bytes, &[])
-            .map_err(|e| NodeError::Crypto(e.to_string()))
+        // Decrypt with empty AAD under the generation the key phase selects
+        let (plaintext, switched) = {
+            let mut rekey = self.rekey_state();
+            let epoch = rekey.epoch();
+            let plaintext = rekey
+                .decrypt(&mut crypto, counter, ciphertext)
+                .map_err(|e| NodeError::Crypto(e.to_string()))?;
+            (plaintext, rekey.epoch() != epoch)
+        };
+        drop(crypto);
+
+        if switched {
+            self.set_rekeying(false).await;
+        }
+        Ok(plaintext)
+    }
+
+    /// Lock the session's rekey state
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rekey Mutex is poisoned.
+    pub(crate) fn rekey_state(&self) -> std::sync::MutexGuard<'_, RekeyState> {
+        self.rekey.lock().expect("rekey lock poisoned")
+    }
+
+    /// Epoch of the key generation packets are currently sealed with
+    ///
+    /// Starts at 0 and increases by one with every completed rekey.
+    pub fn key_epoch(&self) -> u32 {
+        self.rekey_state().epoch()
+    }
+
+    /// Start a rekey exchange, returning the INIT to send
+    ///
+    /// Returns `None` if an exchange is already running.
+    pub(crate) fn begin_rekey(&self) -> Option<RekeyMessage> {
+        if self.legacy {
+            return None;
+        }
+        self.rekey_state().begin()
+    }
+
+    /// Start a rekey exchange if the current key generation reached a limit
+    pub(crate) fn begin_rekey_if_due(&self) -> Option<RekeyMessage> {
+        if self.legacy {
+            return None;
+        }
+        self.rekey_state().begin_if_due()
+    }
+
+    /// Answer the peer's rekey INIT, returning the RESPONSE to send
+    pub(crate) async fn respond_to_rekey(
+        &self,
+        epoch: u32,
+        initiator_key: [u8; 32],
+    ) -> Option<RekeyMessage> {
+        let crypto = self.crypto.read().await;
+        self.rekey_state().respond(&crypto, epoch, initiator_key)
+    }
+
+    /// Switch to the new key generation once the peer answered our INIT
+    pub(crate) async fn complete_rekey(&self, epoch: u32, responder_key: [u8; 32]) -> bool {
+        let mut crypto = self.crypto.write().await;
+        self.rekey_state()
+            .complete(&mut crypto, epoch, responder_key)
     }
 
     /// Check if session needs rekeying
@@ -346,6 +701,32 @@ impl PeerConnection {
             hex::encode(&self.session_id[..8])
         );
     }
+
+    /// Move the session to a new transport and peer address
+    ///
+    /// Called after PATH_CHALLENGE/RESPONSE validation over the new path.
+    /// Keys, streams, and transfer state are unaffected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either RwLock is poisoned.
+    pub fn update_path(&self, transport: TransportType, new_addr: SocketAddr) {
+        let old_transport = std::mem::replace(
+            &mut *self.transport.write().expect("transport lock poisoned"),
+            transport,
+        );
+        if old_transport != transport {
+            tracing::info!(
+                "Moved session {:?} from {} to {}",
+                hex::encode(&self.session_id[..8]),
+                old_transport,
+                transport
+            );
+        }
+        if self.peer_addr() != new_addr {
+            self.update_peer_addr(new_addr);
+        }
+    }
 }
 
 /// Connection statistics
@@ -370,6 +751,617 @@ pub struct ConnectionStats {
     pub loss_rate: f64,
 }
 
+/// Cipher suites the session layer implements
+///
+/// Suite C needs ML-KEM-1024, which is not wired into sessions yet, so it is
+/// never offered.
+pub const SUPPORTED_SUITES: &[CryptoSuite] = &[
+    CryptoSuite::SuiteA,
+    CryptoSuite::SuiteB,
+    CryptoSuite::SuiteD,
+];
+
+/// Frame wire formats the session layer implements, in preference order
+///
+/// Polymorphic v2 needs a per-session format key that the handshake does
+/// not derive yet, so it is never offered.
+pub const SUPPORTED_WIRE_FORMATS: &[WireFormat] = &[WireFormat::V2, WireFormat::V1];
+
+/// Version byte leading the suite negotiation payloads
+const NEGOTIATION_VERSION: u8 = 3;
+
+/// Suite of peers that predate negotiation and send empty handshake payloads
+const LEGACY_SUITE: CryptoSuite = CryptoSuite::SuiteD;
+
+/// Optional protocol features agreed on during the handshake
+///
+/// Each side advertises the features it can handle; a feature is enabled for
+/// the session only when both sides advertise it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    /// Data payloads flagged `CMP` are zstd-compressed
+    pub const COMPRESSION: u8 = 0x01;
+
+    /// Features this build implements
+    pub const SUPPORTED: Self = Self(Self::COMPRESSION);
+
+    /// No optional features
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Create from raw bits, dropping features this build does not know
+    #[must_use]
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & Self::SUPPORTED.0)
+    }
+
+    /// Get raw bits
+    #[must_use]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Check if a feature is present
+    #[must_use]
+    pub const fn contains(self, flag: u8) -> bool {
+        self.0 & flag != 0
+    }
+
+    /// Add a feature
+    #[must_use]
+    pub const fn with(self, flag: u8) -> Self {
+        Self::from_bits(self.0 | flag)
+    }
+
+    /// Remove a feature
+    #[must_use]
+    pub const fn without(self, flag: u8) -> Self {
+        Self(self.0 & !flag)
+    }
+
+    /// Features present in both sets
+    #[must_use]
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    /// Check if compressed data payloads are allowed
+    #[must_use]
+    pub const fn supports_compression(self) -> bool {
+        self.contains(Self::COMPRESSION)
+    }
+}
+
+/// What a node allows during handshake negotiation
+#[derive(Debug, Clone)]
+pub struct HandshakePolicy {
+    /// Cipher suites this node allows, from [`CryptoConfig::negotiable_suites`]
+    ///
+    /// [`CryptoConfig::negotiable_suites`]: crate::node::config::CryptoConfig::negotiable_suites
+    pub suites: Vec<CryptoSuite>,
+    /// Frame wire formats this node allows
+    pub wire_formats: FormatNegotiation,
+    /// Optional features this node advertises
+    pub capabilities: Capabilities,
+    /// Send our ephemeral key Elligator2-encoded when initiating, so the
+    /// handshake is indistinguishable from random bytes. Responders always
+    /// answer in the encoding the initiator chose.
+    pub elligator: bool,
+}
+
+impl Default for HandshakePolicy {
+    fn default() -> Self {
+        Self {
+            suites: crate::node::config::CryptoConfig::default().negotiable_suites(),
+            wire_formats: FormatNegotiation::default(),
+            capabilities: Capabilities::SUPPORTED,
+            elligator: false,
+        }
+    }
+}
+
+impl HandshakePolicy {
+    /// Bitmask of the allowed wire formats this node implements
+    fn wire_format_mask(&self) -> u8 {
+        wire_format_mask(&self.wire_formats)
+    }
+}
+
+/// Parameters agreed on during the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Negotiated {
+    /// Cipher suite protecting the session
+    pub suite: CryptoSuite,
+    /// Frame wire format used for the session's data frames
+    pub wire_format: WireFormat,
+    /// Optional features both sides advertised
+    pub capabilities: Capabilities,
+    /// Whether the peer predates negotiation and sent an empty payload
+    ///
+    /// Such peers derive their keys from the handshake hash and number
+    /// packets implicitly instead of prefixing a packet counter.
+    pub legacy: bool,
+}
+
+impl Negotiated {
+    /// Build the session crypto for a completed handshake
+    ///
+    /// The AEAD follows the negotiated suite. Sessions on a v2 wire format
+    /// also seal every packet under its own key from a per-direction packet
+    /// ratchet, so a compromised session state cannot decrypt packets
+    /// captured earlier.
+    pub fn session_crypto(
+        &self,
+        send_key: [u8; 32],
+        recv_key: [u8; 32],
+        chain_key: &[u8; 32],
+    ) -> SessionCrypto {
+        let crypto =
+            SessionCrypto::with_aead(self.suite.aead_algorithm(), send_key, recv_key, chain_key);
+        if self.wire_format.is_v2() {
+            crypto.with_packet_ratchet()
+        } else {
+            crypto
+        }
+    }
+}
+
+/// Bit identifying a wire format in the negotiation bitmasks
+const fn wire_format_bit(format: WireFormat) -> u8 {
+    match format {
+        WireFormat::V1 => 0x01,
+        WireFormat::V2 => 0x02,
+        WireFormat::V2Polymorphic => 0x04,
+    }
+}
+
+/// Bitmask of the wire formats allowed by `formats` that this node implements
+fn wire_format_mask(formats: &FormatNegotiation) -> u8 {
+    SUPPORTED_WIRE_FORMATS
+        .iter()
+        .filter(|format| formats.supports(**format))
+        .fold(0, |mask, format| mask | wire_format_bit(*format))
+}
+
+/// Strongest wire format present in both bitmasks
+fn select_wire_format(local: u8, remote: u8) -> Option<WireFormat> {
+    [WireFormat::V2Polymorphic, WireFormat::V2, WireFormat::V1]
+        .into_iter()
+        .find(|format| local & remote & wire_format_bit(*format) != 0)
+}
+
+/// Suite offer carried in the payload of handshake msg1
+///
+/// msg1 is sent before any DH so the offer travels in the clear, but Noise
+/// mixes it into the transcript hash. A man in the middle who strips suites,
+/// wire formats or the KEM key makes the responder's msg2 fail
+/// authentication at the initiator, so a downgrade cannot go unnoticed.
+///
+/// Wire format: `version || count || suite_id * count || kem_len (u16 BE) || kem_public || format_mask
+/// || capabilities`
+///
+/// Peers that predate negotiation send an empty payload, which stands for
+/// the classical suite on the v1 wire format.
+struct SuiteOffer {
+    suites: Vec<CryptoSuite>,
+    kem_public: Option<HybridPublicKey>,
+    wire_formats: u8,
+    capabilities: Capabilities,
+}
+
+/// Suite selection carried in the encrypted payload of handshake msg2
+///
+/// The responder echoes its own suite list and wire format mask so the
+/// initiator can check each selection is the strongest both sides allow,
+/// and its capabilities so both sides enable the same features.
+///
+/// Wire format: `version || selected_id || count || suite_id * count || ct_len (u16 BE) || kem_ciphertext
+/// || selected_format || format_mask || capabilities`
+struct SuiteSelection {
+    suite: CryptoSuite,
+    responder_suites: Vec<CryptoSuite>,
+    kem_ciphertext: Option<HybridCiphertext>,
+    wire_format: WireFormat,
+    responder_wire_formats: u8,
+    responder_capabilities: Capabilities,
+}
+
+fn encode_suites(buf: &mut Vec<u8>, suites: &[CryptoSuite]) {
+    buf.push(suites.len() as u8);
+    buf.extend(suites.iter().map(|suite| suite.to_id()));
+}
+
+fn encode_blob(buf: &mut Vec<u8>, blob: Option<Vec<u8>>) {
+    let blob = blob.unwrap_or_default();
+    buf.extend_from_slice(&(blob.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&blob);
+}
+
+/// Cursor over a negotiation payload
+struct PayloadReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PayloadReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(NodeError::Handshake(
+                "Truncated suite negotiation payload".into(),
+            ));
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn version(&mut self) -> Result<()> {
+        match self.byte()? {
+            NEGOTIATION_VERSION => Ok(()),
+            v => Err(NodeError::Handshake(
+                format!("Unsupported suite negotiation version {v}").into(),
+            )),
+        }
+    }
+
+    /// Read a suite list, skipping suite ids this build does not know
+    fn suites(&mut self) -> Result<Vec<CryptoSuite>> {
+        let count = self.byte()? as usize;
+        Ok(self
+            .take(count)?
+            .iter()
+            .filter_map(|id| CryptoSuite::from_id(*id))
+            .collect())
+    }
+
+    /// Read a selected wire format (exactly one known format bit)
+    fn wire_format(&mut self) -> Result<WireFormat> {
+        let bit = self.byte()?;
+        [WireFormat::V1, WireFormat::V2, WireFormat::V2Polymorphic]
+            .into_iter()
+            .find(|format| wire_format_bit(*format) == bit)
+            .ok_or_else(|| {
+                NodeError::Handshake(format!("Peer selected unknown wire format {bit:#04x}").into())
+            })
+    }
+
+    fn blob(&mut self) -> Result<Option<&'a [u8]>> {
+        let len = u16::from_be_bytes([self.byte()?, self.byte()?]) as usize;
+        let blob = self.take(len)?;
+        Ok((!blob.is_empty()).then_some(blob))
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.data.is_empty() {
+            Ok(())
+        } else {
+            Err(NodeError::Handshake(
+                "Trailing bytes in suite negotiation payload".into(),
+            ))
+        }
+    }
+}
+
+impl SuiteOffer {
+    fn encode(
+        suites: &[CryptoSuite],
+        kem_public: Option<&HybridPublicKey>,
+        wire_formats: u8,
+        capabilities: Capabilities,
+    ) -> Vec<u8> {
+        let mut buf = vec![NEGOTIATION_VERSION];
+        encode_suites(&mut buf, suites);
+        encode_blob(&mut buf, kem_public.map(HybridPublicKey::to_bytes));
+        buf.push(wire_formats);
+        buf.push(capabilities.bits());
+        buf
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self> {
+        if payload.is_empty() {
+            return Ok(Self {
+                suites: vec![LEGACY_SUITE],
+                kem_public: None,
+                wire_formats: wire_format_bit(WireFormat::V1),
+                capabilities: Capabilities::empty(),
+            });
+        }
+        let mut reader = PayloadReader { data: payload };
+        reader.version()?;
+        let suites = reader.suites()?;
+        let kem_public = reader
+            .blob()?
+            .map(HybridPublicKey::from_bytes)
+            .transpose()
+            .map_err(|e| NodeError::Handshake(format!("Invalid hybrid public key: {e}").into()))?;
+        let wire_formats = reader.byte()?;
+        let capabilities = Capabilities::from_bits(reader.byte()?);
+        reader.finish()?;
+        Ok(Self {
+            suites,
+            kem_public,
+            wire_formats,
+            capabilities,
+        })
+    }
+}
+
+impl SuiteSelection {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![NEGOTIATION_VERSION, self.suite.to_id()];
+        encode_suites(&mut buf, &self.responder_suites);
+        encode_blob(
+            &mut buf,
+            self.kem_ciphertext.as_ref().map(HybridCiphertext::to_bytes),
+        );
+        buf.push(wire_format_bit(self.wire_format));
+        buf.push(self.responder_wire_formats);
+        buf.push(self.responder_capabilities.bits());
+        buf
+    }
+
+    fn decode(payload: &[u8]) -> Result<Self> {
+        if payload.is_empty() {
+            return Ok(Self {
+                suite: LEGACY_SUITE,
+                responder_suites: vec![LEGACY_SUITE],
+                kem_ciphertext: None,
+                wire_format: WireFormat::V1,
+                responder_wire_formats: wire_format_bit(WireFormat::V1),
+                responder_capabilities: Capabilities::empty(),
+            });
+        }
+        let mut reader = PayloadReader { data: payload };
+        reader.version()?;
+        let id = reader.byte()?;
+        let suite = CryptoSuite::from_id(id).ok_or_else(|| {
+            NodeError::Handshake(format!("Peer selected unknown cipher suite {id:#04x}").into())
+        })?;
+        let responder_suites = reader.suites()?;
+        let kem_ciphertext = reader
+            .blob()?
+            .map(HybridCiphertext::from_bytes)
+            .transpose()
+            .map_err(|e| NodeError::Handshake(format!("Invalid hybrid ciphertext: {e}").into()))?;
+        let wire_format = reader.wire_format()?;
+        let responder_wire_formats = reader.byte()?;
+        let responder_capabilities = Capabilities::from_bits(reader.byte()?);
+        reader.finish()?;
+        Ok(Self {
+            suite,
+            responder_suites,
+            kem_ciphertext,
+            wire_format,
+            responder_wire_formats,
+            responder_capabilities,
+        })
+    }
+}
+
+/// Build the msg1 suite offer
+///
+/// Returns the payload and, when a post-quantum suite is offered, the
+/// ephemeral hybrid keypair the responder will encapsulate to.
+fn build_suite_offer(policy: &HandshakePolicy) -> Result<(Vec<u8>, Option<HybridKeyPair>)> {
+    let local_suites = &policy.suites;
+    if local_suites.is_empty() {
+        return Err(NodeError::Handshake(
+            "No supported cipher suites configured".into(),
+        ));
+    }
+
+    let kem = local_suites
+        .iter()
+        .any(|suite| suite.supports_post_quantum())
+        .then(|| HybridKeyPair::generate(&mut SecureRng::new()));
+    let payload = SuiteOffer::encode(
+        local_suites,
+        kem.as_ref().map(|kp| &kp.public),
+        policy.wire_format_mask(),
+        policy.capabilities,
+    );
+    Ok((payload, kem))
+}
+
+/// Answer a msg1 suite offer (responder side)
+///
+/// Selects the strongest mutually allowed suite and wire format and, for
+/// post-quantum suites, encapsulates a hybrid secret to the initiator's KEM
+/// key. Returns the msg2 payload, the negotiated parameters and the hybrid
+/// shared secret.
+///
+/// A legacy peer's empty offer gets the classical suite on the v1 wire
+/// format, if the policy allows both, and an empty msg2 payload.
+fn answer_suite_offer(
+    policy: &HandshakePolicy,
+    payload: &[u8],
+) -> Result<(Vec<u8>, Negotiated, Option<HybridSharedSecret>)> {
+    let local_suites = &policy.suites;
+    let local_formats = policy.wire_format_mask();
+    let legacy = payload.is_empty();
+    let offer = SuiteOffer::decode(payload)?;
+    let suite = CryptoSuite::negotiate(local_suites, &offer.suites).ok_or_else(|| {
+        NodeError::Handshake(if legacy {
+            "Legacy peer needs the classical suite, which local policy forbids".into()
+        } else {
+            "No cipher suite in common with peer".into()
+        })
+    })?;
+    let wire_format = select_wire_format(local_formats, offer.wire_formats).ok_or_else(|| {
+        NodeError::Handshake(if legacy {
+            "Legacy peer needs the v1 wire format, which local policy forbids".into()
+        } else {
+            "No wire format in common with peer".into()
+        })
+    })?;
+
+    let (secret, kem_ciphertext) = if suite.supports_post_quantum() {
+        let kem_public = offer.kem_public.ok_or_else(|| {
+            NodeError::Handshake(format!("Peer offered {suite} without a hybrid public key").into())
+        })?;
+        let (secret, ciphertext) = kem_public.encapsulate(&mut SecureRng::new())?;
+        (Some(secret), Some(ciphertext))
+    } else {
+        (None, None)
+    };
+
+    let selection = SuiteSelection {
+        suite,
+        responder_suites: local_suites.to_vec(),
+        kem_ciphertext,
+        wire_format,
+        responder_wire_formats: local_formats,
+        responder_capabilities: policy.capabilities,
+    };
+    let negotiated = Negotiated {
+        suite,
+        wire_format,
+        capabilities: policy.capabilities.intersection(offer.capabilities),
+        legacy,
+    };
+    let payload = if legacy {
+        Vec::new()
+    } else {
+        selection.encode()
+    };
+    Ok((payload, negotiated, secret))
+}
+
+/// Check the msg2 suite selection (initiator side)
+///
+/// The responder must have picked exactly the suite and wire format that
+/// negotiation over both sides' lists yields; anything weaker is treated as
+/// a downgrade attempt. Returns the negotiated parameters and the
+/// decapsulated hybrid shared secret.
+///
+/// An empty payload comes from a legacy responder and means the classical
+/// suite on the v1 wire format. msg2 is authenticated against the
+/// transcript, which includes our offer, so an attacker cannot fake it.
+fn accept_suite_selection(
+    policy: &HandshakePolicy,
+    kem: Option<&HybridKeyPair>,
+    payload: &[u8],
+) -> Result<(Negotiated, Option<HybridSharedSecret>)> {
+    if payload.is_empty()
+        && !(policy.suites.contains(&LEGACY_SUITE)
+            && policy.wire_format_mask() & wire_format_bit(WireFormat::V1) != 0)
+    {
+        return Err(NodeError::Handshake(
+            "Legacy peer needs the classical suite on the v1 wire format, which local policy forbids"
+                .into(),
+        ));
+    }
+    let selection = SuiteSelection::decode(payload)?;
+    let expected = CryptoSuite::negotiate(&policy.suites, &selection.responder_suites);
+    if expected != Some(selection.suite) {
+        return Err(NodeError::Handshake(
+            format!(
+                "Cipher suite downgrade: peer selected {} but negotiation yields {}",
+                selection.suite,
+                expected.map_or_else(|| "no suite".to_string(), |suite| suite.to_string())
+            )
+            .into(),
+        ));
+    }
+
+    let expected_format =
+        select_wire_format(policy.wire_format_mask(), selection.responder_wire_formats);
+    if expected_format != Some(selection.wire_format) {
+        return Err(NodeError::Handshake(
+            format!(
+                "Wire format downgrade: peer selected {:?} but negotiation yields {:?}",
+                selection.wire_format, expected_format
+            )
+            .into(),
+        ));
+    }
+
+    let secret = match (
+        selection.suite.supports_post_quantum(),
+        selection.kem_ciphertext,
+    ) {
+        (true, Some(ciphertext)) => {
+            let kem = kem.ok_or_else(|| {
+                NodeError::Handshake("Post-quantum suite selected without a KEM key".into())
+            })?;
+            Some(kem.secret.decapsulate(&ciphertext)?)
+        }
+        (true, None) => {
+            return Err(NodeError::Handshake(
+                "Peer selected a post-quantum suite without a KEM ciphertext".into(),
+            ));
+        }
+        (false, Some(_)) => {
+            return Err(NodeError::Handshake(
+                "Peer sent a KEM ciphertext for a classical suite".into(),
+            ));
+        }
+        (false, None) => None,
+    };
+
+    let negotiated = Negotiated {
+        suite: selection.suite,
+        wire_format: selection.wire_format,
+        capabilities: policy
+            .capabilities
+            .intersection(selection.responder_capabilities),
+        legacy: payload.is_empty(),
+    };
+    Ok((negotiated, secret))
+}
+
+/// Derive the session keys of a completed handshake
+///
+/// Legacy peers derive their keys from the handshake hash alone;
+/// negotiating peers also mix in the hybrid secret.
+fn derive_session_keys(
+    noise: NoiseHandshake,
+    negotiated: &Negotiated,
+    hybrid_secret: Option<&HybridSharedSecret>,
+) -> Result<SessionKeys> {
+    let keys = if negotiated.legacy {
+        noise.into_session_keys()
+    } else {
+        noise.into_hybrid_session_keys(hybrid_secret.map(HybridSharedSecret::as_bytes))
+    };
+    keys.map_err(|e| NodeError::Handshake(format!("Failed to extract keys: {e}").into()))
+}
+
+/// Start the initiator side of a Noise_XX handshake under `policy`
+///
+/// The Elligator2 encoding is keyed with the responder's static key, so
+/// handshakes with a peer whose key is not known yet use the plain encoding.
+fn new_initiator_handshake(
+    local_keypair: &NoiseKeypair,
+    policy: &HandshakePolicy,
+    responder: Option<&PeerId>,
+) -> Result<NoiseHandshake> {
+    let noise = match responder {
+        Some(responder) if policy.elligator => {
+            NoiseHandshake::new_initiator_elligator(local_keypair, responder)
+        }
+        _ => NoiseHandshake::new_initiator(local_keypair),
+    };
+    noise.map_err(|e| NodeError::Handshake(e.to_string().into()))
+}
+
+/// Start the responder side of a Noise_XX handshake in the encoding `msg1` uses
+fn new_responder_handshake(local_keypair: &NoiseKeypair, msg1: &[u8]) -> Result<NoiseHandshake> {
+    let noise = if NoiseHandshake::is_elligator_message1(msg1, local_keypair.public_key()) {
+        NoiseHandshake::new_responder_elligator(local_keypair)
+    } else {
+        NoiseHandshake::new_responder(local_keypair)
+    };
+    noise.map_err(|e| NodeError::Handshake(e.to_string().into()))
+}
+
 /// Perform Noise_XX handshake as initiator
 ///
 /// Exchanges handshake messages over the transport to establish a secure session.
@@ -377,16 +1369,44 @@ pub struct ConnectionStats {
 /// # Arguments
 ///
 /// * `local_keypair` - Local X25519 keypair for handshake
+/// * `policy` - Cipher suites, wire formats and capabilities this node allows
 /// * `peer_addr` - Remote peer address
 /// * `transport` - Transport layer for sending/receiving handshake messages
 /// * `msg2_rx` - Optional channel to receive msg2. When provided, msg2 is received via the
 ///   channel instead of calling `transport.recv_from()` directly. This prevents race conditions
 ///   with `packet_receive_loop` where both code paths compete for the same socket. If `None`,
 ///   falls back to direct `recv_from()` (for tests or standalone usage).
+/// * `expected_peer_id` - X25519 static key the responder must present. A
+///   different key aborts the handshake with `NodeError::PeerKeyMismatch`
+///   before msg3 reveals our own static key; `None` accepts any key.
 ///
 /// # Returns
 ///
-/// Returns session crypto, session ID, and peer's X25519 public key on success.
+/// Returns session crypto, session ID, peer's X25519 public key and the negotiated
+/// cipher suite, wire format and capabilities on success.
+///
+/// # Suite Negotiation
+///
+/// msg1 carries the initiator's suite list and, when a post-quantum suite is
+/// offered, an ephemeral X25519 + ML-KEM-768 public key. The responder picks
+/// the strongest common suite and returns it in msg2 together with the KEM
+/// ciphertext. The hybrid shared secret is mixed with the Noise chaining key
+/// into the session keys, so recorded traffic stays confidential even if
+/// X25519 is later broken. Both payloads are bound to the Noise transcript,
+/// and a selection weaker than the strongest suite both lists allow is
+/// rejected as a downgrade.
+///
+/// The frame wire format is negotiated the same way: msg1 carries the
+/// formats the initiator allows and msg2 the responder's choice, which must
+/// be the strongest format both sides allow. Peers limited to the v1 format
+/// keep exchanging v1 frames; everyone else switches to v2. Optional
+/// features such as chunk compression are enabled only when both sides
+/// advertise them.
+///
+/// With [`HandshakePolicy::elligator`] set and `expected_peer_id` known, the
+/// ephemeral keys in msg1 and msg2 travel as Elligator2 representatives and
+/// the msg1 payload is masked under the responder's static key, so no
+/// handshake byte can be told apart from random data.
 ///
 /// # Race Condition Prevention
 ///
@@ -394,29 +1414,31 @@ pub struct ConnectionStats {
 /// same socket, causing a race where whichever wins receives msg2 and the other times out.
 /// With channeling, only `packet_receive_loop` receives packets and forwards handshake packets
 /// to the appropriate channel.
-pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
+pub async fn perform_handshake_initiator<T: Transport + Send + Sync + ?Sized>(
     local_keypair: &NoiseKeypair,
+    policy: &HandshakePolicy,
     peer_addr: SocketAddr,
     transport: &T,
     msg2_rx: Option<oneshot::Receiver<HandshakePacket>>,
-) -> Result<(SessionCrypto, SessionId, PeerId)> {
+    expected_peer_id: Option<&PeerId>,
+) -> Result<(SessionCrypto, SessionId, PeerId, Negotiated)> {
     tracing::debug!(
         "Starting Noise_XX handshake as initiator with {}",
         peer_addr
     );
 
     // Create Noise handshake state
-    let mut noise = NoiseHandshake::new_initiator(local_keypair)
-        .map_err(|e| NodeError::Handshake(e.to_string().into()))?;
+    let mut noise = new_initiator_handshake(local_keypair, policy, expected_peer_id)?;
 
     // Noise_XX handshake pattern:
     // -> e (initiator sends ephemeral key)
     // <- e, ee, s, es (responder sends ephemeral, performs DH, sends static, performs DH)
     // -> s, se (initiator sends static, performs DH)
 
-    // 1. Send message 1 (-> e)
+    // 1. Send message 1 (-> e, suite offer)
+    let (offer, kem) = build_suite_offer(policy)?;
     let msg1 = noise
-        .write_message(&[])
+        .write_message(&offer)
         .map_err(|e| NodeError::Handshake(format!("Failed to create msg1: {e}").into()))?;
 
     tracing::trace!(
@@ -472,9 +1494,24 @@ pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
         from
     );
 
-    let _payload2 = noise
+    let payload2 = noise
         .read_message(&msg2_data)
         .map_err(|e| NodeError::Handshake(format!("Failed to process msg2: {e}").into()))?;
+    let (negotiated, hybrid_secret) = accept_suite_selection(policy, kem.as_ref(), &payload2)?;
+
+    // msg2 carries the responder's static key; refuse an unexpected one
+    // before revealing ours in msg3
+    if let Some(expected) = expected_peer_id {
+        let presented = noise.get_remote_static().ok_or_else(|| {
+            NodeError::Handshake("Failed to get remote static key from msg2".into())
+        })?;
+        if presented != *expected {
+            return Err(NodeError::PeerKeyMismatch {
+                expected: *expected,
+                presented,
+            });
+        }
+    }
 
     // 3. Send message 3 (-> s, se)
     let msg3 = noise
@@ -504,12 +1541,10 @@ pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
         NodeError::Handshake("Failed to get remote static key after handshake".into())
     })?;
 
-    let keys = noise
-        .into_session_keys()
-        .map_err(|e| NodeError::Handshake(format!("Failed to extract keys: {e}").into()))?;
+    let keys = derive_session_keys(noise, &negotiated, hybrid_secret.as_ref())?;
 
     // Create session crypto (initiator: send=send_key, recv=recv_key)
-    let crypto = SessionCrypto::new(keys.send_key, keys.recv_key, &keys.chain_key);
+    let crypto = negotiated.session_crypto(keys.send_key, keys.recv_key, &keys.chain_key);
 
     // Derive session ID from keys (extend 8-byte CID to 32-byte session ID)
     let cid = keys.derive_connection_id();
@@ -519,12 +1554,14 @@ pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
     session_id[8..].copy_from_slice(&keys.chain_key[..24]);
 
     tracing::info!(
-        "Noise_XX handshake complete as initiator, session: {:?}, peer: {}",
+        "Noise_XX handshake complete as initiator, session: {:?}, peer: {}, suite: {}, format: {:?}",
         hex::encode(&session_id[..8]),
-        hex::encode(&peer_id[..8])
+        hex::encode(&peer_id[..8]),
+        negotiated.suite,
+        negotiated.wire_format
     );
 
-    Ok((crypto, session_id, peer_id))
+    Ok((crypto, session_id, peer_id, negotiated))
 }
 
 /// Perform Noise_XX handshake as responder
@@ -534,6 +1571,7 @@ pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
 /// # Arguments
 ///
 /// * `local_keypair` - Local X25519 keypair for handshake
+/// * `policy` - Cipher suites, wire formats and capabilities this node allows
 /// * `msg1` - First handshake message from initiator
 /// * `peer_addr` - Remote peer address
 /// * `transport` - Transport layer for sending/receiving handshake messages
@@ -541,22 +1579,23 @@ pub async fn perform_handshake_initiator<T: Transport + Send + Sync>(
 ///
 /// # Returns
 ///
-/// Returns session crypto, session ID, and peer's public key on success.
-pub async fn perform_handshake_responder<T: Transport + Send + Sync>(
+/// Returns session crypto, session ID, peer's public key and the negotiated
+/// cipher suite, wire format and capabilities on success.
+pub async fn perform_handshake_responder<T: Transport + Send + Sync + ?Sized>(
     local_keypair: &NoiseKeypair,
+    policy: &HandshakePolicy,
     msg1: &[u8],
     peer_addr: SocketAddr,
     transport: &T,
     msg3_rx: Option<oneshot::Receiver<HandshakePacket>>,
-) -> Result<(SessionCrypto, SessionId, PeerId)> {
+) -> Result<(SessionCrypto, SessionId, PeerId, Negotiated)> {
     tracing::debug!(
         "Starting Noise_XX handshake as responder with {}",
         peer_addr
     );
 
-    // Create Noise handshake state
-    let mut noise = NoiseHandshake::new_responder(local_keypair)
-        .map_err(|e| NodeError::Handshake(e.to_string().into()))?;
+    // Create Noise handshake state, answering in the initiator's key encoding
+    let mut noise = new_responder_handshake(local_keypair, msg1)?;
 
     // Noise_XX handshake pattern (from responder perspective):
     // <- e (receive initiator's ephemeral key)
@@ -570,13 +1609,14 @@ pub async fn perform_handshake_responder<T: Transport + Send + Sync>(
         peer_addr
     );
 
-    let _payload1 = noise
+    let payload1 = noise
         .read_message(msg1)
         .map_err(|e| NodeError::Handshake(format!("Failed to process msg1: {e}").into()))?;
+    let (selection, negotiated, hybrid_secret) = answer_suite_offer(policy, &payload1)?;
 
-    // 2. Send message 2 (-> e, ee, s, es)
+    // 2. Send message 2 (-> e, ee, s, es, suite selection)
     let msg2 = noise
-        .write_message(&[])
+        .write_message(&selection)
         .map_err(|e| NodeError::Handshake(format!("Failed to create msg2: {e}").into()))?;
 
     tracing::trace!(
@@ -648,12 +1688,11 @@ pub async fn perform_handshake_responder<T: Transport + Send + Sync>(
         NodeError::Handshake("Failed to get remote static key after handshake".into())
     })?;
 
-    let keys = noise
-        .into_session_keys()
-        .map_err(|e| NodeError::Handshake(format!("Failed to extract keys: {e}").into()))?;
+    let keys = derive_session_keys(noise, &negotiated, hybrid_secret.as_ref())?;
 
-    // Create session crypto (responder: recv=send_key, send=recv_key - reversed from initiator)
-    let crypto = SessionCrypto::new(keys.recv_key, keys.send_key, &keys.chain_key);
+    // Create session crypto (keys are already assigned by role, so the responder's
+    // send_key is the initiator's recv_key)
+    let crypto = negotiated.session_crypto(keys.send_key, keys.recv_key, &keys.chain_key);
 
     // Derive session ID from keys (extend 8-byte CID to 32-byte session ID)
     let cid = keys.derive_connection_id();
@@ -663,18 +1702,21 @@ pub async fn perform_handshake_responder<T: Transport + Send + Sync>(
     session_id[8..].copy_from_slice(&keys.chain_key[..24]);
 
     tracing::info!(
-        "Noise_XX handshake complete as responder, session: {:?}, peer: {}",
+        "Noise_XX handshake complete as responder, session: {:?}, peer: {}, suite: {}, format: {:?}",
         hex::encode(&session_id[..8]),
-        hex::encode(&peer_id[..8])
+        hex::encode(&peer_id[..8]),
+        negotiated.suite,
+        negotiated.wire_format
     );
 
-    Ok((crypto, session_id, peer_id))
+    Ok((crypto, session_id, peer_id, negotiated))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use wraith_crypto::noise::NoiseKeypair;
+    use wraith_crypto::suite::AeadAlgorithm;
 
     #[test]
     fn test_peer_connection_creation() {
@@ -882,6 +1924,67 @@ mod tests {
         // (In real code this would happen after 1M messages)
     }
 
+    #[tokio::test]
+    async fn test_frames_cross_in_band_rekey() {
+        use crate::FRAME_HEADER_SIZE;
+        use crate::frame::{Frame, FrameBuilder, FrameType};
+
+        let peer_addr = "127.0.0.1:5000".parse().unwrap();
+        let connection_id = ConnectionId::from_bytes([3u8; 8]);
+        let alice = PeerConnection::new(
+            [1u8; 32],
+            [2u8; 32],
+            peer_addr,
+            connection_id,
+            SessionCrypto::new([4u8; 32], [5u8; 32], &[6u8; 32]),
+        );
+        let bob = PeerConnection::new(
+            [1u8; 32],
+            [2u8; 32],
+            peer_addr,
+            connection_id,
+            SessionCrypto::new([5u8; 32], [4u8; 32], &[6u8; 32]),
+        );
+        let frame = |payload: &[u8]| {
+            FrameBuilder::new()
+                .frame_type(FrameType::Data)
+                .stream_id(42)
+                .payload(payload)
+                .build(FRAME_HEADER_SIZE + payload.len())
+                .unwrap()
+        };
+
+        let in_flight = alice.encrypt_frame(&frame(b"before")).await.unwrap();
+
+        let Some(RekeyMessage::Init { epoch, public_key }) = alice.begin_rekey() else {
+            panic!("expected INIT");
+        };
+        let Some(RekeyMessage::Response {
+            public_key: response_key,
+            ..
+        }) = bob.respond_to_rekey(epoch, public_key).await
+        else {
+            panic!("expected RESPONSE");
+        };
+        assert!(alice.complete_rekey(epoch, response_key).await);
+        assert_eq!(alice.key_epoch(), 1);
+        assert_eq!(bob.key_epoch(), 0);
+
+        // Alice's first packet under the new keys switches Bob over
+        let after = alice.encrypt_frame(&frame(b"after")).await.unwrap();
+        let decrypted = bob.decrypt_frame(&after).await.unwrap();
+        assert_eq!(Frame::parse(&decrypted).unwrap().payload(), b"after");
+        assert_eq!(bob.key_epoch(), 1);
+
+        // The packet sealed before the rekey still opens within the grace window
+        let decrypted = bob.decrypt_frame(&in_flight).await.unwrap();
+        assert_eq!(Frame::parse(&decrypted).unwrap().payload(), b"before");
+
+        let reply = bob.encrypt_frame(&frame(b"reply")).await.unwrap();
+        let decrypted = alice.decrypt_frame(&reply).await.unwrap();
+        assert_eq!(Frame::parse(&decrypted).unwrap().payload(), b"reply");
+    }
+
     #[test]
     fn test_peer_connection_clone() {
         let session_id = [1u8; 32];
@@ -938,6 +2041,34 @@ mod tests {
         assert_eq!(conn.peer_addr(), new_addr);
     }
 
+    #[test]
+    fn test_update_path() {
+        let udp_addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let tcp_addr: SocketAddr = "127.0.0.1:5001".parse().unwrap();
+
+        let conn = PeerConnection::new(
+            [1u8; 32],
+            [2u8; 32],
+            udp_addr,
+            ConnectionId::from_bytes([3u8; 8]),
+            SessionCrypto::new([4u8; 32], [5u8; 32], &[6u8; 32]),
+        );
+        assert_eq!(conn.transport_type(), TransportType::Udp);
+        assert!(conn.peer_transports().is_empty());
+
+        conn.set_peer_transports(vec![(TransportType::Tcp, tcp_addr)]);
+        conn.update_path(TransportType::Tcp, tcp_addr);
+        assert_eq!(conn.transport_type(), TransportType::Tcp);
+        assert_eq!(conn.peer_addr(), tcp_addr);
+
+        let cloned = conn.clone();
+        assert_eq!(cloned.transport_type(), TransportType::Tcp);
+        assert_eq!(
+            cloned.peer_transports(),
+            vec![(TransportType::Tcp, tcp_addr)]
+        );
+    }
+
     #[test]
     fn test_idle_duration_ms() {
         let conn = PeerConnection::new(
@@ -1111,4 +2242,704 @@ mod tests {
             assert_eq!(bob.recv_counter().await, (i + 1) as u64);
         }
     }
+
+    /// Outcome of an in-memory handshake: the initiator's and responder's
+    /// session crypto and negotiated suites
+    type HandshakeOutcome = (SessionCrypto, CryptoSuite, SessionCrypto, CryptoSuite);
+
+    /// Wire format mask of a default node
+    fn default_formats() -> u8 {
+        wire_format_mask(&FormatNegotiation::default())
+    }
+
+    /// Handshake policy allowing `suites` and `wire_formats`
+    fn policy(suites: &[CryptoSuite], wire_formats: FormatNegotiation) -> HandshakePolicy {
+        HandshakePolicy {
+            suites: suites.to_vec(),
+            wire_formats,
+            capabilities: Capabilities::SUPPORTED,
+            elligator: false,
+        }
+    }
+
+    /// Default handshake policy allowing `suites`
+    fn suites_policy(suites: &[CryptoSuite]) -> HandshakePolicy {
+        policy(suites, FormatNegotiation::default())
+    }
+
+    /// Run the Noise_XX + suite negotiation exchange in memory, letting the
+    /// caller rewrite msg1 on the wire like a man in the middle would
+    fn run_negotiated_handshake(
+        initiator_suites: &[CryptoSuite],
+        responder_suites: &[CryptoSuite],
+        tamper_msg1: impl FnOnce(Vec<u8>) -> Vec<u8>,
+    ) -> Result<HandshakeOutcome> {
+        run_negotiated_handshake_with_policies(
+            &suites_policy(initiator_suites),
+            &suites_policy(responder_suites),
+            tamper_msg1,
+        )
+        .map(|(alice, alice_params, bob, bob_params)| {
+            (alice, alice_params.suite, bob, bob_params.suite)
+        })
+    }
+
+    /// Static keypair of the responder in [`run_negotiated_handshake_with_policies`]
+    fn test_responder_keypair() -> NoiseKeypair {
+        NoiseKeypair::from_bytes([0x42; 32]).unwrap()
+    }
+
+    /// [`run_negotiated_handshake`] with explicit policies, returning the
+    /// full negotiated parameters on both sides
+    fn run_negotiated_handshake_with_policies(
+        initiator_policy: &HandshakePolicy,
+        responder_policy: &HandshakePolicy,
+        tamper_msg1: impl FnOnce(Vec<u8>) -> Vec<u8>,
+    ) -> Result<(SessionCrypto, Negotiated, SessionCrypto, Negotiated)> {
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = test_responder_keypair();
+        let mut initiator = new_initiator_handshake(
+            &initiator_keypair,
+            initiator_policy,
+            Some(responder_keypair.public_key()),
+        )?;
+
+        let (offer, kem) = build_suite_offer(initiator_policy)?;
+        let msg1 = tamper_msg1(initiator.write_message(&offer).unwrap());
+        let mut responder = new_responder_handshake(&responder_keypair, &msg1)?;
+
+        let payload1 = responder
+            .read_message(&msg1)
+            .map_err(|e| NodeError::Handshake(e.to_string().into()))?;
+        let (selection, responder_params, responder_secret) =
+            answer_suite_offer(responder_policy, &payload1)?;
+        let msg2 = responder.write_message(&selection).unwrap();
+
+        let payload2 = initiator
+            .read_message(&msg2)
+            .map_err(|e| NodeError::Handshake(e.to_string().into()))?;
+        let (initiator_params, initiator_secret) =
+            accept_suite_selection(initiator_policy, kem.as_ref(), &payload2)?;
+        let msg3 = initiator.write_message(&[]).unwrap();
+        responder
+            .read_message(&msg3)
+            .map_err(|e| NodeError::Handshake(e.to_string().into()))?;
+
+        let i_keys = initiator
+            .into_hybrid_session_keys(initiator_secret.as_ref().map(HybridSharedSecret::as_bytes))
+            .unwrap();
+        let r_keys = responder
+            .into_hybrid_session_keys(responder_secret.as_ref().map(HybridSharedSecret::as_bytes))
+            .unwrap();
+
+        Ok((
+            initiator_params.session_crypto(i_keys.send_key, i_keys.recv_key, &i_keys.chain_key),
+            initiator_params,
+            responder_params.session_crypto(r_keys.send_key, r_keys.recv_key, &r_keys.chain_key),
+            responder_params,
+        ))
+    }
+
+    #[test]
+    fn test_negotiated_handshake_selects_hybrid_suite() {
+        let (mut alice, alice_suite, mut bob, bob_suite) =
+            run_negotiated_handshake(SUPPORTED_SUITES, SUPPORTED_SUITES, |msg| msg).unwrap();
+
+        assert_eq!(alice_suite, CryptoSuite::SuiteA);
+        assert_eq!(bob_suite, CryptoSuite::SuiteA);
+
+        let ciphertext = alice.encrypt(b"harvest me", &[]).unwrap();
+        assert_eq!(bob.decrypt(&ciphertext, &[]).unwrap(), b"harvest me");
+    }
+
+    #[test]
+    fn test_negotiated_handshake_selects_suite_b_when_both_prefer_it() {
+        let aes_first = [
+            CryptoSuite::SuiteB,
+            CryptoSuite::SuiteA,
+            CryptoSuite::SuiteD,
+        ];
+        let (mut alice, alice_suite, mut bob, bob_suite) =
+            run_negotiated_handshake(&aes_first, &aes_first, |msg| msg).unwrap();
+
+        assert_eq!(alice_suite, CryptoSuite::SuiteB);
+        assert_eq!(bob_suite, CryptoSuite::SuiteB);
+        assert_eq!(alice.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+        assert_eq!(bob.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+
+        let ciphertext = alice.encrypt(b"aes-ni", &[]).unwrap();
+        assert_eq!(bob.decrypt(&ciphertext, &[]).unwrap(), b"aes-ni");
+
+        // One side preferring XChaCha20-Poly1305 keeps Suite A
+        let (_, alice_suite, _, bob_suite) =
+            run_negotiated_handshake(&aes_first, SUPPORTED_SUITES, |msg| msg).unwrap();
+        assert_eq!(alice_suite, CryptoSuite::SuiteA);
+        assert_eq!(bob_suite, CryptoSuite::SuiteA);
+    }
+
+    #[test]
+    fn test_negotiated_handshake_classical_fallback() {
+        let (mut alice, alice_suite, mut bob, bob_suite) =
+            run_negotiated_handshake(SUPPORTED_SUITES, &[CryptoSuite::SuiteD], |msg| msg).unwrap();
+
+        assert_eq!(alice_suite, CryptoSuite::SuiteD);
+        assert_eq!(bob_suite, CryptoSuite::SuiteD);
+
+        let ciphertext = bob.encrypt(b"classical", &[]).unwrap();
+        assert_eq!(alice.decrypt(&ciphertext, &[]).unwrap(), b"classical");
+    }
+
+    #[test]
+    fn test_elligator_handshake_negotiates_hybrid_suite() {
+        let elligator = HandshakePolicy {
+            elligator: true,
+            ..HandshakePolicy::default()
+        };
+        let mut msg1 = Vec::new();
+        let (mut alice, alice_params, mut bob, bob_params) =
+            run_negotiated_handshake_with_policies(&elligator, &HandshakePolicy::default(), |m| {
+                msg1.clone_from(&m);
+                m
+            })
+            .unwrap();
+
+        let responder_static = *test_responder_keypair().public_key();
+        assert!(NoiseHandshake::is_elligator_message1(
+            &msg1,
+            &responder_static
+        ));
+        assert_eq!(alice_params, bob_params);
+        assert!(alice_params.suite.supports_post_quantum());
+
+        // A plain msg1 carries the offer header right after the ephemeral key
+        let (offer, _) = build_suite_offer(&elligator).unwrap();
+        assert_ne!(&msg1[32..36], &offer[..4]);
+
+        let ciphertext = alice.encrypt(b"looks random", &[]).unwrap();
+        assert_eq!(bob.decrypt(&ciphertext, &[]).unwrap(), b"looks random");
+    }
+
+    #[test]
+    fn test_elligator_needs_known_responder_key() {
+        let elligator = HandshakePolicy {
+            elligator: true,
+            ..HandshakePolicy::default()
+        };
+        let keypair = NoiseKeypair::generate().unwrap();
+        let responder = test_responder_keypair();
+
+        let keyed =
+            new_initiator_handshake(&keypair, &elligator, Some(responder.public_key())).unwrap();
+        assert!(keyed.is_elligator());
+        let unkeyed = new_initiator_handshake(&keypair, &elligator, None).unwrap();
+        assert!(!unkeyed.is_elligator());
+    }
+
+    #[test]
+    fn test_elligator_responder_accepts_plain_initiator() {
+        let elligator = HandshakePolicy {
+            elligator: true,
+            ..HandshakePolicy::default()
+        };
+        let mut msg1 = Vec::new();
+        let (mut alice, _, mut bob, _) =
+            run_negotiated_handshake_with_policies(&HandshakePolicy::default(), &elligator, |m| {
+                msg1.clone_from(&m);
+                m
+            })
+            .unwrap();
+
+        let responder_static = *test_responder_keypair().public_key();
+        assert!(!NoiseHandshake::is_elligator_message1(
+            &msg1,
+            &responder_static
+        ));
+        let ciphertext = bob.encrypt(b"plain", &[]).unwrap();
+        assert_eq!(alice.decrypt(&ciphertext, &[]).unwrap(), b"plain");
+    }
+
+    #[test]
+    fn test_negotiated_handshake_no_common_suite() {
+        let result =
+            run_negotiated_handshake(&[CryptoSuite::SuiteA], &[CryptoSuite::SuiteD], |msg| msg);
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_downgrade_stripping_offer_is_detected() {
+        // A man in the middle rewrites the cleartext msg1 offer so that only
+        // the classical suite remains. The responder happily picks Suite D,
+        // but the offer is part of the Noise transcript, so the initiator
+        // cannot authenticate msg2 and the handshake fails.
+        let stripped_offer = SuiteOffer::encode(
+            &[CryptoSuite::SuiteD],
+            None,
+            default_formats(),
+            Capabilities::SUPPORTED,
+        );
+        let result = run_negotiated_handshake(SUPPORTED_SUITES, SUPPORTED_SUITES, |msg| {
+            let mut tampered = msg[..32].to_vec();
+            tampered.extend_from_slice(&stripped_offer);
+            tampered
+        });
+
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_downgrade_weaker_selection_is_rejected() {
+        // A responder that allows Suite A but selects Suite D anyway
+        let (offer, kem) = build_suite_offer(&suites_policy(SUPPORTED_SUITES)).unwrap();
+        assert!(SuiteOffer::decode(&offer).unwrap().kem_public.is_some());
+
+        let selection = SuiteSelection {
+            suite: CryptoSuite::SuiteD,
+            responder_suites: SUPPORTED_SUITES.to_vec(),
+            kem_ciphertext: None,
+            wire_format: WireFormat::V2,
+            responder_wire_formats: default_formats(),
+            responder_capabilities: Capabilities::SUPPORTED,
+        };
+        let result = accept_suite_selection(
+            &suites_policy(SUPPORTED_SUITES),
+            kem.as_ref(),
+            &selection.encode(),
+        );
+
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_post_quantum_selection_requires_ciphertext() {
+        let (_, kem) = build_suite_offer(&suites_policy(SUPPORTED_SUITES)).unwrap();
+        let selection = SuiteSelection {
+            suite: CryptoSuite::SuiteA,
+            responder_suites: SUPPORTED_SUITES.to_vec(),
+            kem_ciphertext: None,
+            wire_format: WireFormat::V2,
+            responder_wire_formats: default_formats(),
+            responder_capabilities: Capabilities::SUPPORTED,
+        };
+
+        let result = accept_suite_selection(
+            &suites_policy(SUPPORTED_SUITES),
+            kem.as_ref(),
+            &selection.encode(),
+        );
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_suite_offer_rejects_malformed_payloads() {
+        assert!(SuiteOffer::decode(&[NEGOTIATION_VERSION + 1, 0, 0, 0]).is_err());
+        assert!(SuiteOffer::decode(&[NEGOTIATION_VERSION, 2, 0x04]).is_err());
+        assert!(SuiteOffer::decode(&[NEGOTIATION_VERSION, 1, 0x04, 0, 0]).is_err());
+        assert!(SuiteOffer::decode(&[NEGOTIATION_VERSION, 1, 0x04, 0, 0, 0x03]).is_err());
+        assert!(
+            SuiteOffer::decode(&[NEGOTIATION_VERSION, 1, 0x04, 0, 0, 0x03, 0x01, 0xFF]).is_err()
+        );
+        assert!(SuiteOffer::decode(&[NEGOTIATION_VERSION, 1, 0x04, 0, 4, 1, 2, 3, 4]).is_err());
+
+        // Unknown suite ids and capability bits are skipped rather than rejected
+        let offer =
+            SuiteOffer::decode(&[NEGOTIATION_VERSION, 2, 0x7F, 0x04, 0, 0, 0x03, 0xFF]).unwrap();
+        assert_eq!(offer.suites, vec![CryptoSuite::SuiteD]);
+        assert!(offer.kem_public.is_none());
+        assert_eq!(offer.wire_formats, 0x03);
+        assert_eq!(offer.capabilities, Capabilities::SUPPORTED);
+    }
+
+    #[test]
+    fn test_empty_suite_offer_is_legacy_classical_v1() {
+        let offer = SuiteOffer::decode(&[]).unwrap();
+        assert_eq!(offer.suites, vec![LEGACY_SUITE]);
+        assert!(offer.kem_public.is_none());
+        assert_eq!(offer.wire_formats, wire_format_bit(WireFormat::V1));
+        assert_eq!(offer.capabilities, Capabilities::empty());
+    }
+
+    #[test]
+    fn test_legacy_initiator_negotiates_classical_v1() {
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+        let responder_policy = suites_policy(SUPPORTED_SUITES);
+
+        // Baseline initiator: empty payloads, keys from the handshake hash
+        let mut initiator = NoiseHandshake::new_initiator(&initiator_keypair).unwrap();
+        let msg1 = initiator.write_message(&[]).unwrap();
+        let mut responder = new_responder_handshake(&responder_keypair, &msg1).unwrap();
+        let payload1 = responder.read_message(&msg1).unwrap();
+        let (selection, negotiated, secret) =
+            answer_suite_offer(&responder_policy, &payload1).unwrap();
+        assert!(selection.is_empty());
+        assert!(secret.is_none());
+        assert_eq!(negotiated.suite, CryptoSuite::SuiteD);
+        assert_eq!(negotiated.wire_format, WireFormat::V1);
+        assert_eq!(negotiated.capabilities, Capabilities::empty());
+        assert!(negotiated.legacy);
+
+        let msg2 = responder.write_message(&selection).unwrap();
+        assert!(initiator.read_message(&msg2).unwrap().is_empty());
+        let msg3 = initiator.write_message(&[]).unwrap();
+        responder.read_message(&msg3).unwrap();
+
+        let i_keys = initiator.into_session_keys().unwrap();
+        let r_keys = derive_session_keys(responder, &negotiated, None).unwrap();
+        let mut legacy = SessionCrypto::new(i_keys.send_key, i_keys.recv_key, &i_keys.chain_key);
+        let mut bob =
+            negotiated.session_crypto(r_keys.send_key, r_keys.recv_key, &r_keys.chain_key);
+
+        let ciphertext = legacy.encrypt(b"baseline", &[]).unwrap();
+        assert_eq!(bob.decrypt(&ciphertext, &[]).unwrap(), b"baseline");
+        let ciphertext = bob.encrypt(b"negotiated", &[]).unwrap();
+        assert_eq!(legacy.decrypt(&ciphertext, &[]).unwrap(), b"negotiated");
+    }
+
+    #[test]
+    fn test_legacy_responder_negotiates_classical_v1() {
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+        let initiator_policy = suites_policy(SUPPORTED_SUITES);
+
+        let mut initiator =
+            new_initiator_handshake(&initiator_keypair, &initiator_policy, None).unwrap();
+        let (offer, kem) = build_suite_offer(&initiator_policy).unwrap();
+        let msg1 = initiator.write_message(&offer).unwrap();
+
+        // Baseline responder: ignores the offer and answers with an empty payload
+        let mut responder = NoiseHandshake::new_responder(&responder_keypair).unwrap();
+        responder.read_message(&msg1).unwrap();
+        let msg2 = responder.write_message(&[]).unwrap();
+
+        let payload2 = initiator.read_message(&msg2).unwrap();
+        let (negotiated, secret) =
+            accept_suite_selection(&initiator_policy, kem.as_ref(), &payload2).unwrap();
+        assert!(secret.is_none());
+        assert_eq!(negotiated.suite, CryptoSuite::SuiteD);
+        assert_eq!(negotiated.wire_format, WireFormat::V1);
+        let msg3 = initiator.write_message(&[]).unwrap();
+        responder.read_message(&msg3).unwrap();
+
+        let i_keys = derive_session_keys(initiator, &negotiated, None).unwrap();
+        let r_keys = responder.into_session_keys().unwrap();
+        let mut alice =
+            negotiated.session_crypto(i_keys.send_key, i_keys.recv_key, &i_keys.chain_key);
+        let mut legacy = SessionCrypto::new(r_keys.send_key, r_keys.recv_key, &r_keys.chain_key);
+
+        let ciphertext = alice.encrypt(b"negotiated", &[]).unwrap();
+        assert_eq!(legacy.decrypt(&ciphertext, &[]).unwrap(), b"negotiated");
+    }
+
+    #[test]
+    fn test_legacy_peer_refused_when_policy_forbids_it() {
+        let no_classical = [CryptoSuite::SuiteA, CryptoSuite::SuiteB];
+        assert!(answer_suite_offer(&suites_policy(&no_classical), &[]).is_err());
+        assert!(
+            answer_suite_offer(&policy(SUPPORTED_SUITES, FormatNegotiation::v2_only()), &[])
+                .is_err()
+        );
+        assert!(accept_suite_selection(&suites_policy(&no_classical), None, &[]).is_err());
+        assert!(
+            accept_suite_selection(
+                &policy(SUPPORTED_SUITES, FormatNegotiation::v2_only()),
+                None,
+                &[]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stripped_suite_offer_fails_handshake() {
+        // Emptying msg1's offer to force the legacy path changes the transcript,
+        // so the initiator cannot decrypt the responder's msg2
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+        let initiator_policy = suites_policy(SUPPORTED_SUITES);
+        let mut initiator =
+            new_initiator_handshake(&initiator_keypair, &initiator_policy, None).unwrap();
+        let (offer, _kem) = build_suite_offer(&initiator_policy).unwrap();
+        let msg1 = initiator.write_message(&offer).unwrap();
+        let stripped = msg1[..msg1.len() - offer.len()].to_vec();
+
+        let mut responder = new_responder_handshake(&responder_keypair, &stripped).unwrap();
+        let payload1 = responder.read_message(&stripped).unwrap();
+        assert!(payload1.is_empty());
+        let (selection, _, _) =
+            answer_suite_offer(&suites_policy(SUPPORTED_SUITES), &payload1).unwrap();
+        let msg2 = responder.write_message(&selection).unwrap();
+        assert!(initiator.read_message(&msg2).is_err());
+    }
+
+    #[test]
+    fn test_handshake_messages_fit_datagram() {
+        let keypair = NoiseKeypair::generate().unwrap();
+        let mut initiator = NoiseHandshake::new_initiator(&keypair).unwrap();
+        let (offer, _) = build_suite_offer(&suites_policy(SUPPORTED_SUITES)).unwrap();
+        let msg1 = initiator.write_message(&offer).unwrap();
+
+        // Leave room for mimicry framing within a 1500-byte Ethernet MTU
+        assert!(msg1.len() <= 1400, "msg1 is {} bytes", msg1.len());
+    }
+
+    #[test]
+    fn test_peer_connection_crypto_suite() {
+        let peer_addr = "127.0.0.1:5000".parse().unwrap();
+        let connection = PeerConnection::new_for_test([7u8; 32], peer_addr);
+        assert_eq!(connection.crypto_suite, None);
+
+        let connection = connection.with_crypto_suite(CryptoSuite::SuiteA);
+        assert_eq!(connection.clone().crypto_suite, Some(CryptoSuite::SuiteA));
+    }
+
+    #[test]
+    fn test_wire_format_mask_excludes_unimplemented_formats() {
+        assert_eq!(wire_format_mask(&FormatNegotiation::default()), 0x03);
+        assert_eq!(wire_format_mask(&FormatNegotiation::v1_only()), 0x01);
+        assert_eq!(wire_format_mask(&FormatNegotiation::v2_only()), 0x02);
+    }
+
+    #[test]
+    fn test_negotiated_handshake_selects_v2() {
+        let (_, alice, _, bob) = run_negotiated_handshake_with_policies(
+            &suites_policy(SUPPORTED_SUITES),
+            &suites_policy(SUPPORTED_SUITES),
+            |msg| msg,
+        )
+        .unwrap();
+
+        assert_eq!(alice.wire_format, WireFormat::V2);
+        assert_eq!(bob, alice);
+    }
+
+    #[test]
+    fn test_negotiated_handshake_falls_back_to_v1() {
+        let (_, alice, _, bob) = run_negotiated_handshake_with_policies(
+            &suites_policy(SUPPORTED_SUITES),
+            &policy(SUPPORTED_SUITES, FormatNegotiation::v1_only()),
+            |msg| msg,
+        )
+        .unwrap();
+
+        assert_eq!(alice.wire_format, WireFormat::V1);
+        assert_eq!(bob.wire_format, WireFormat::V1);
+    }
+
+    #[test]
+    fn test_negotiated_handshake_no_common_wire_format() {
+        let result = run_negotiated_handshake_with_policies(
+            &policy(SUPPORTED_SUITES, FormatNegotiation::v2_only()),
+            &policy(SUPPORTED_SUITES, FormatNegotiation::v1_only()),
+            |msg| msg,
+        );
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_downgrade_weaker_wire_format_is_rejected() {
+        // A responder that allows v2 but selects v1 anyway
+        let (_, kem) = build_suite_offer(&suites_policy(&[CryptoSuite::SuiteD])).unwrap();
+        let selection = SuiteSelection {
+            suite: CryptoSuite::SuiteD,
+            responder_suites: vec![CryptoSuite::SuiteD],
+            kem_ciphertext: None,
+            wire_format: WireFormat::V1,
+            responder_wire_formats: default_formats(),
+            responder_capabilities: Capabilities::SUPPORTED,
+        };
+
+        let result = accept_suite_selection(
+            &suites_policy(&[CryptoSuite::SuiteD]),
+            kem.as_ref(),
+            &selection.encode(),
+        );
+        assert!(matches!(result, Err(NodeError::Handshake(_))));
+    }
+
+    #[test]
+    fn test_suite_selection_rejects_unknown_wire_format() {
+        let mut payload = SuiteSelection {
+            suite: CryptoSuite::SuiteD,
+            responder_suites: vec![CryptoSuite::SuiteD],
+            kem_ciphertext: None,
+            wire_format: WireFormat::V2,
+            responder_wire_formats: default_formats(),
+            responder_capabilities: Capabilities::SUPPORTED,
+        }
+        .encode();
+        let format_pos = payload.len() - 3;
+        payload[format_pos] = 0x03; // two formats at once
+        assert!(SuiteSelection::decode(&payload).is_err());
+    }
+
+    #[test]
+    fn test_negotiated_handshake_agrees_on_capabilities() {
+        let (_, alice, _, bob) = run_negotiated_handshake_with_policies(
+            &suites_policy(SUPPORTED_SUITES),
+            &suites_policy(SUPPORTED_SUITES),
+            |msg| msg,
+        )
+        .unwrap();
+        assert!(alice.capabilities.supports_compression());
+        assert_eq!(alice.capabilities, bob.capabilities);
+
+        // A feature one side does not advertise is off for both
+        let mut plain = suites_policy(SUPPORTED_SUITES);
+        plain.capabilities = Capabilities::empty();
+        let (_, alice, _, bob) = run_negotiated_handshake_with_policies(
+            &suites_policy(SUPPORTED_SUITES),
+            &plain,
+            |msg| msg,
+        )
+        .unwrap();
+        assert!(!alice.capabilities.supports_compression());
+        assert!(!bob.capabilities.supports_compression());
+    }
+
+    #[test]
+    fn test_capabilities_drop_unknown_bits() {
+        assert_eq!(Capabilities::from_bits(0xFF), Capabilities::SUPPORTED);
+        assert_eq!(Capabilities::empty().with(0x80), Capabilities::empty());
+        assert!(
+            !Capabilities::SUPPORTED
+                .without(Capabilities::COMPRESSION)
+                .supports_compression()
+        );
+    }
+
+    /// Build a connected pair of peer connections sharing session keys
+    fn connection_pair(wire_format: WireFormat) -> (PeerConnection, PeerConnection) {
+        let peer_addr = "127.0.0.1:5000".parse().unwrap();
+        let connection_id = ConnectionId::from_bytes([3u8; 8]);
+        let alice_crypto = SessionCrypto::new([4u8; 32], [5u8; 32], &[6u8; 32]);
+        let bob_crypto = SessionCrypto::new([5u8; 32], [4u8; 32], &[6u8; 32]);
+        let alice =
+            PeerConnection::new([1u8; 32], [2u8; 32], peer_addr, connection_id, alice_crypto)
+                .with_wire_format(wire_format);
+        let bob = PeerConnection::new([1u8; 32], [7u8; 32], peer_addr, connection_id, bob_crypto)
+            .with_wire_format(wire_format);
+        (alice, bob)
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_frame_transcodes_to_v2() {
+        use crate::frame::{Frame, FrameBuilder, FrameType};
+
+        let (alice, bob) = connection_pair(WireFormat::V2);
+        let frame = FrameBuilder::new()
+            .frame_type(FrameType::Control)
+            .stream_id(16)
+            .offset(4096)
+            .payload(b"metadata")
+            .build(128)
+            .unwrap();
+
+        let plaintext = bob
+            .decrypt_frame(&alice.encrypt_frame(&frame).await.unwrap())
+            .await
+            .unwrap();
+        let v2 = FrameV2::parse(&plaintext).unwrap();
+        assert_eq!(v2.frame_type(), crate::FrameTypeV2::Control);
+        assert_eq!(v2.offset(), 4096);
+        assert_eq!(v2.payload(), b"metadata");
+
+        let v1 = v2_frame_to_v1(&plaintext).unwrap().unwrap();
+        assert_eq!(Frame::parse(&v1).unwrap().payload(), b"metadata");
+    }
+
+    #[tokio::test]
+    async fn test_v2_session_ratchets_every_packet() {
+        use crate::frame::{FrameBuilder, FrameType};
+
+        let policy = HandshakePolicy::default();
+        let (alice_crypto, alice_params, bob_crypto, bob_params) =
+            run_negotiated_handshake_with_policies(&policy, &policy, |msg| msg).unwrap();
+        assert!(alice_params.wire_format.is_v2());
+        assert!(alice_crypto.packet_ratchet_enabled());
+        assert!(bob_crypto.packet_ratchet_enabled());
+
+        let peer_addr = "127.0.0.1:5000".parse().unwrap();
+        let connection_id = ConnectionId::from_bytes([3u8; 8]);
+        let alice =
+            PeerConnection::new([1u8; 32], [2u8; 32], peer_addr, connection_id, alice_crypto)
+                .with_wire_format(alice_params.wire_format);
+        let bob = PeerConnection::new([1u8; 32], [7u8; 32], peer_addr, connection_id, bob_crypto)
+            .with_wire_format(bob_params.wire_format);
+
+        let mut packets = Vec::new();
+        for stream_id in [16u16, 17, 16] {
+            let frame = FrameBuilder::new()
+                .frame_type(FrameType::Data)
+                .stream_id(stream_id)
+                .payload(b"ratcheted")
+                .build(64)
+                .unwrap();
+            packets.push(alice.encrypt_frame(&frame).await.unwrap());
+        }
+
+        for index in [2, 0, 1] {
+            let plaintext = bob.decrypt_frame(&packets[index]).await.unwrap();
+            assert_eq!(FrameV2::parse(&plaintext).unwrap().payload(), b"ratcheted");
+            assert!(bob.decrypt_frame(&packets[index]).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encrypt_frame_v2_on_v1_session() {
+        use crate::FrameTypeV2;
+        use crate::frame::frame_v2::FrameBuilderV2;
+        use crate::frame::{Frame, FrameType};
+
+        let (alice, bob) = connection_pair(WireFormat::V1);
+
+        // Frames with a v1 equivalent are re-encoded
+        let ping = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Ping)
+            .sequence(9)
+            .build(64)
+            .unwrap();
+        let plaintext = bob
+            .decrypt_frame(&alice.encrypt_frame_v2(&ping).await.unwrap())
+            .await
+            .unwrap();
+        let frame = Frame::parse(&plaintext).unwrap();
+        assert_eq!(frame.frame_type(), FrameType::Ping);
+        assert_eq!(frame.sequence(), 9);
+
+        // v2-only frames cannot be sent to a v1 peer
+        let datagram = FrameBuilderV2::new()
+            .frame_type(FrameTypeV2::Datagram)
+            .build(64)
+            .unwrap();
+        assert!(matches!(
+            alice.encrypt_frame_v2(&datagram).await,
+            Err(NodeError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_seal_frame_prefixes_connection_id() {
+        use crate::frame::FrameBuilder;
+        use crate::node::routing::extract_connection_id;
+
+        let (alice, bob) = connection_pair(WireFormat::V1);
+        let frame = FrameBuilder::new().payload(b"routed").build(64).unwrap();
+
+        let packet = alice.seal_frame(&frame).await.unwrap();
+        assert_eq!(
+            extract_connection_id(&packet),
+            Some(u64::from_be_bytes([3u8; 8]))
+        );
+        assert_eq!(bob.decrypt_frame(&packet[8..]).await.unwrap(), frame);
+    }
+
+    #[test]
+    fn test_peer_connection_wire_format() {
+        let peer_addr = "127.0.0.1:5000".parse().unwrap();
+        let connection = PeerConnection::new_for_test([7u8; 32], peer_addr);
+        assert_eq!(connection.wire_format, WireFormat::V1);
+
+        let connection = connection.with_wire_format(WireFormat::V2);
+        assert_eq!(connection.clone().wire_format, WireFormat::V2);
+    }
 }
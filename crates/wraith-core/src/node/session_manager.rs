@@ -14,21 +14,28 @@
 //! # Handshake Flow
 //!
 //! ```text
-//! Initiator                     Responder
-//!     |                              |
-//!     |------ Noise msg1 (e) ------->|
-//!     |                              |
-//!     |<-- Noise msg2 (e,ee,s,es) ---|
-//!     |                              |
-//!     |------ Noise msg3 (s,se) ---->|
-//!     |                              |
-//!     |    [Session Established]     |
+//! Initiator                                  Responder
+//!     |                                           |
+//!     |--- Noise msg1 (e) + suites, KEM pubkey -->|
+//!     |          + wire formats                   |
+//!     |<-- Noise msg2 (e,ee,s,es) + suite, KEM ct-|
+//!     |          + wire format                    |
+//!     |                                           |
+//!     |------ Noise msg3 (s,se) ----------------->|
+//!     |                                           |
+//!     |          [Session Established]            |
 //! ```
+//!
+//! The hybrid X25519 + ML-KEM-768 secret exchanged in msg1/msg2 is mixed into
+//! the session keys whenever a post-quantum suite is negotiated. The wire
+//! format agreed in the same messages decides whether the session's frames
+//! travel as v1 or v2.
 
+use crate::frame::compat::FormatNegotiation;
 use crate::node::error::{NodeError, Result};
 use crate::node::session::{
-    HandshakePacket, PeerConnection, PeerId, SessionId, perform_handshake_initiator,
-    perform_handshake_responder,
+    HandshakePacket, HandshakePolicy, PeerConnection, PeerId, SessionId,
+    perform_handshake_initiator, perform_handshake_responder,
 };
 use crate::{ConnectionId, HandshakePhase, SessionState};
 use dashmap::DashMap;
@@ -36,6 +43,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{Mutex, oneshot};
 use wraith_crypto::noise::NoiseKeypair;
+use wraith_crypto::suite::CryptoSuite;
 use wraith_transport::udp_async::AsyncUdpTransport;
 
 /// Session manager for WRAITH nodes
@@ -54,6 +62,9 @@ pub struct SessionManager {
 
     /// Transport layer
     transport: Arc<Mutex<Option<Arc<AsyncUdpTransport>>>>,
+
+    /// Suites, wire formats and features offered during handshakes
+    policy: HandshakePolicy,
 }
 
 impl SessionManager {
@@ -69,9 +80,22 @@ impl SessionManager {
             sessions,
             pending_handshakes,
             transport,
+            policy: HandshakePolicy::default(),
         }
     }
 
+    /// Restrict the cipher suites negotiated by this manager's handshakes
+    pub fn with_crypto_suites(mut self, suites: Vec<CryptoSuite>) -> Self {
+        self.policy.suites = suites;
+        self
+    }
+
+    /// Restrict the frame wire formats negotiated by this manager's handshakes
+    pub fn with_wire_formats(mut self, wire_formats: FormatNegotiation) -> Self {
+        self.policy.wire_formats = wire_formats;
+        self
+    }
+
     /// Get the transport layer
     async fn get_transport(&self) -> Result<Arc<AsyncUdpTransport>> {
         let guard = self.transport.lock().await;
@@ -87,7 +111,7 @@ impl SessionManager {
     ///
     /// # Arguments
     ///
-    /// * `_expected_peer_id` - Expected peer's public key (reserved for future validation)
+    /// * `expected_peer_id` - X25519 static key the peer must present
     /// * `peer_addr` - The peer's network address
     /// * `routing` - Routing table for registering the new route
     ///
@@ -95,13 +119,17 @@ impl SessionManager {
     ///
     /// Returns the session ID on success.
     ///
+    /// # Errors
+    ///
+    /// Returns `NodeError::PeerKeyMismatch` if the peer answers with a
+    /// different static key, or an error if the handshake fails.
+    ///
     /// # Note
     ///
-    /// Sessions are stored using the peer's X25519 public key from the Noise handshake,
-    /// not the passed-in Ed25519 key.
+    /// Sessions are stored using the peer's X25519 public key from the Noise handshake.
     pub async fn establish_session_with_addr(
         &self,
-        _expected_peer_id: &PeerId,
+        expected_peer_id: &PeerId,
         peer_addr: SocketAddr,
         routing: &crate::node::routing::RoutingTable,
     ) -> Result<SessionId> {
@@ -118,9 +146,11 @@ impl SessionManager {
         // Perform Noise_XX handshake as initiator
         let handshake_result = perform_handshake_initiator(
             &self.local_keypair,
+            &self.policy,
             peer_addr,
             transport.as_ref(),
             Some(msg2_rx),
+            Some(expected_peer_id),
         )
         .await;
 
@@ -128,7 +158,7 @@ impl SessionManager {
         self.pending_handshakes.remove(&peer_addr);
 
         // Propagate any handshake error
-        let (crypto, session_id, peer_id) = handshake_result?;
+        let (crypto, session_id, peer_id, negotiated) = handshake_result?;
 
         // Check if session already exists with this peer
         if let Some(connection) = self.sessions.get(&peer_id) {
@@ -141,7 +171,11 @@ impl SessionManager {
         let connection_id = ConnectionId::from_bytes(connection_id_bytes);
 
         // Create connection using X25519 peer_id from handshake
-        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto);
+        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto)
+            .with_crypto_suite(negotiated.suite)
+            .with_wire_format(negotiated.wire_format)
+            .with_legacy(negotiated.legacy)
+            .with_capabilities(negotiated.capabilities);
 
         // Transition through handshake states to established
         connection
@@ -208,6 +242,7 @@ impl SessionManager {
         // Perform Noise_XX handshake as responder
         let handshake_result = perform_handshake_responder(
             &self.local_keypair,
+            &self.policy,
             msg1,
             peer_addr,
             transport.as_ref(),
@@ -219,7 +254,7 @@ impl SessionManager {
         self.pending_handshakes.remove(&peer_addr);
 
         // Propagate any handshake error
-        let (crypto, session_id, peer_id) = handshake_result?;
+        let (crypto, session_id, peer_id, negotiated) = handshake_result?;
 
         // Derive connection ID from session ID
         let mut connection_id_bytes = [0u8; 8];
@@ -227,7 +262,11 @@ impl SessionManager {
         let connection_id = ConnectionId::from_bytes(connection_id_bytes);
 
         // Create connection
-        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto);
+        let connection = PeerConnection::new(session_id, peer_id, peer_addr, connection_id, crypto)
+            .with_crypto_suite(negotiated.suite)
+            .with_wire_format(negotiated.wire_format)
+            .with_legacy(negotiated.legacy)
+            .with_capabilities(negotiated.capabilities);
 
         // Transition through handshake states to established
         connection
@@ -2,6 +2,9 @@
 //!
 //! Coordinates file downloads from multiple peers in parallel with chunk assignment.
 
+use crate::node::file_transfer::{
+    ChunkHashLayer, ChunkVerdict, FileTransferContext, MAX_HASH_LAYER_REQUESTS,
+};
 use crate::node::identity::TransferId;
 use crate::node::session::PeerId;
 use crate::node::{Node, NodeError};
@@ -100,17 +103,33 @@ impl Node {
 
         // Store transfer context
         let context = Arc::new(
-            crate::node::file_transfer::FileTransferContext::new_receive(
+            FileTransferContext::new_receive(
                 transfer_id,
                 Arc::new(tokio::sync::RwLock::new(transfer_session)),
                 reassembler.clone(),
                 tree_hash,
-            ),
+            )
+            .with_chunk_hashes(ChunkHashLayer::new(
+                *file_hash,
+                metadata.total_chunks as u64,
+            ))
+            .with_coordinator(Arc::clone(&self.inner.peer_coordinator)),
         );
         self.inner.transfers.insert(transfer_id, context.clone());
 
-        // 4. Assign chunks to peers
-        let chunk_assignments = self.assign_chunks(&metadata, &peers);
+        // Every chunk is verified on arrival, so the layer has to come first
+        self.fetch_chunk_hash_layer(&context, file_hash, &peers)
+            .await?;
+
+        // 4. Assign chunks to peers, leaving out peers known to send corrupt chunks
+        let suspects = self.inner.peer_coordinator.corrupt_peers().await;
+        let trusted: Vec<PeerId> = peers
+            .iter()
+            .filter(|peer_id| !suspects.contains(peer_id))
+            .copied()
+            .collect();
+        let sources = if trusted.is_empty() { &peers } else { &trusted };
+        let chunk_assignments = self.assign_chunks(&metadata, sources);
 
         tracing::debug!(
             "Chunk assignments: {:?}",
@@ -135,10 +154,12 @@ impl Node {
             .collect();
 
         // 6. Wait for all downloads to complete
+        let mut corrupt_chunks = Vec::new();
         for (i, handle) in handles.into_iter().enumerate() {
             match handle.await {
-                Ok(Ok(())) => {
+                Ok(Ok(corrupt)) => {
                     tracing::debug!("Download task {} completed successfully", i);
+                    corrupt_chunks.extend(corrupt);
                 }
                 Ok(Err(e)) => {
                     tracing::error!("Download task {} failed: {}", i, e);
@@ -151,6 +172,20 @@ impl Node {
             }
         }
 
+        // Chunks that failed verification are fetched again from a peer that has sent none
+        if !corrupt_chunks.is_empty() {
+            let suspects = self.inner.peer_coordinator.corrupt_peers().await;
+            let Some(peer_id) = peers.iter().find(|peer_id| !suspects.contains(peer_id)) else {
+                return Err(NodeError::HashMismatch);
+            };
+            let still_corrupt = self
+                .download_chunks_from_peer(*peer_id, corrupt_chunks, context.clone())
+                .await?;
+            if !still_corrupt.is_empty() {
+                return Err(NodeError::HashMismatch);
+            }
+        }
+
         // 7. Verify complete file
         tracing::info!("All chunks downloaded, verifying file integrity");
 
@@ -217,7 +252,7 @@ impl Node {
         // Build Control frame with metadata request
         // Payload format: request_type(1) + file_hash(32)
         let mut payload = Vec::with_capacity(33);
-        payload.push(0x01); // Request type: metadata request
+        payload.push(crate::node::file_transfer::CONTROL_METADATA_REQUEST);
         payload.extend_from_slice(file_hash);
 
         let frame = FrameBuilder::new()
@@ -275,37 +310,28 @@ impl Node {
         &self,
         session: &crate::node::session::PeerConnection,
         chunk_idx: usize,
-        context: &Arc<crate::node::file_transfer::FileTransferContext>,
+        context: &Arc<FileTransferContext>,
     ) -> Result<Vec<u8>, NodeError> {
-        use crate::frame::FrameBuilder;
         use std::time::Duration;
 
         // Compute stream_id from transfer_id (matches handle_data_frame logic)
-        let stream_id = ((context.transfer_id[0] as u16) << 8) | (context.transfer_id[1] as u16);
+        let stream_id = crate::node::file_transfer::transfer_stream_id(&context.transfer_id);
         let chunk_key = (stream_id, chunk_idx as u64);
 
         // Build chunk request Control frame
-        // Payload format: request_type(1) + transfer_id(32) + chunk_index(8)
-        let mut payload = Vec::with_capacity(41);
-        payload.push(0x02); // Request type: chunk request
-        payload.extend_from_slice(&context.transfer_id);
-        payload.extend_from_slice(&(chunk_idx as u64).to_be_bytes());
-
-        let frame = FrameBuilder::new()
-            .frame_type(crate::frame::FrameType::Control)
-            .stream_id(stream_id)
-            .sequence(chunk_idx as u32)
-            .payload(&payload)
-            .build(crate::FRAME_HEADER_SIZE + payload.len())
-            .map_err(|e| {
-                NodeError::InvalidState(format!("Failed to build chunk request: {e}").into())
-            })?;
+        let frame = crate::node::file_transfer::build_chunk_request_frame(
+            stream_id,
+            &context.transfer_id,
+            chunk_idx as u64,
+        )?;
 
         // Create oneshot channel for chunk response
         let (tx, rx) = tokio::sync::oneshot::channel();
 
         // Register pending chunk before sending
-        self.inner.pending_chunks.insert(chunk_key, tx);
+        self.inner
+            .pending_chunks
+            .insert(chunk_key, (session.peer_id, tx));
 
         // Send chunk request
         self.send_encrypted_frame(session, &frame)
@@ -341,13 +367,98 @@ impl Node {
         }
     }
 
+    /// Request the chunk hash layer of `file_hash` from `peers` until it verifies
+    ///
+    /// Peers are asked in turn, each request resuming at the first missing
+    /// segment, for at most [`MAX_HASH_LAYER_REQUESTS`] requests.
+    async fn fetch_chunk_hash_layer(
+        &self,
+        context: &FileTransferContext,
+        file_hash: &[u8; 32],
+        peers: &[PeerId],
+    ) -> Result<(), NodeError> {
+        let Some(layer) = &context.chunk_hashes else {
+            return Ok(());
+        };
+        let stream_id = crate::node::file_transfer::transfer_stream_id(&context.transfer_id);
+        let interval = self.inner.config.transfer.hash_layer_request_interval;
+
+        for peer_id in peers.iter().cycle().take(MAX_HASH_LAYER_REQUESTS as usize) {
+            let start_index = {
+                let layer = layer.lock().await;
+                if layer.is_verified() {
+                    return Ok(());
+                }
+                layer.first_missing()
+            };
+
+            // Seeders know the file by its root hash, not by our transfer ID
+            let frame = crate::node::file_transfer::build_chunk_hashes_request_frame(
+                stream_id,
+                file_hash,
+                start_index,
+            )?;
+            let sent = match self.get_or_establish_session(peer_id).await {
+                Ok(session) => self.send_encrypted_frame(&session, &frame).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = sent {
+                tracing::warn!("Failed to request chunk hashes from {:?}: {}", peer_id, e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+
+        if layer.lock().await.is_verified() {
+            Ok(())
+        } else {
+            Err(NodeError::Transfer(
+                "Chunk hash layer unavailable from any peer".into(),
+            ))
+        }
+    }
+
+    /// Check a pulled chunk against the transfer's chunk hash layer
+    ///
+    /// A chunk that fails is recorded against `peer_id` in the transfer's
+    /// peer coordinator.
+    async fn verify_pulled_chunk(
+        &self,
+        context: &FileTransferContext,
+        peer_id: &PeerId,
+        chunk_idx: usize,
+        chunk_data: &[u8],
+    ) -> bool {
+        let verdict = match &context.chunk_hashes {
+            Some(layer) => layer
+                .lock()
+                .await
+                .verify_chunk(chunk_idx as u64, chunk_data),
+            None => ChunkVerdict::Unverified,
+        };
+        if verdict == ChunkVerdict::Valid {
+            return true;
+        }
+
+        tracing::warn!(
+            "Chunk {} from peer {:?} failed hash verification",
+            chunk_idx,
+            peer_id
+        );
+        if let Some(coordinator) = &context.coordinator {
+            coordinator.record_corrupt_chunk(peer_id, chunk_idx).await;
+        }
+        false
+    }
+
     /// Download chunks from a specific peer
+    ///
+    /// Returns the chunks that failed hash verification; they are not written.
     async fn download_chunks_from_peer(
         &self,
         peer_id: PeerId,
         chunks: Vec<usize>,
-        context: Arc<crate::node::file_transfer::FileTransferContext>,
-    ) -> Result<(), NodeError> {
+        context: Arc<FileTransferContext>,
+    ) -> Result<Vec<usize>, NodeError> {
         tracing::debug!(
             "Downloading {} chunks from peer {:?}",
             chunks.len(),
@@ -356,7 +467,12 @@ impl Node {
 
         // Get or establish session
         let session = self.get_or_establish_session(&peer_id).await?;
+        self.inner
+            .peer_coordinator
+            .ensure_peer(peer_id, session.peer_addr())
+            .await;
 
+        let mut corrupt = Vec::new();
         for chunk_idx in chunks {
             // Request chunk via protocol
             let chunk_data = match self
@@ -376,6 +492,14 @@ impl Node {
                 }
             };
 
+            if !self
+                .verify_pulled_chunk(&context, &peer_id, chunk_idx, &chunk_data)
+                .await
+            {
+                corrupt.push(chunk_idx);
+                continue;
+            }
+
             // Write to reassembler
             if let Some(reassembler) = &context.reassembler {
                 reassembler
@@ -397,7 +521,7 @@ impl Node {
 
         tracing::debug!("All chunks downloaded from peer {:?}", peer_id);
 
-        Ok(())
+        Ok(corrupt)
     }
 
     /// Upload chunks to a requesting peer
@@ -846,4 +970,140 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    /// Receive context pulling `data` from `peers`, registered with `node`
+    fn pulled_context(
+        node: &Node,
+        data: &[u8],
+        chunk_size: usize,
+        peers: &[PeerId],
+        dir: &Path,
+    ) -> Arc<FileTransferContext> {
+        let root = wraith_files::tree_hash::compute_tree_hash_from_data(data, chunk_size).root;
+        let total_chunks = data.len().div_ceil(chunk_size) as u64;
+        let output_path = dir.join("pulled.dat");
+        let mut transfer = TransferSession::new_receive(
+            [0x51; 32],
+            output_path.clone(),
+            data.len() as u64,
+            chunk_size,
+        );
+        for peer_id in peers {
+            transfer.add_peer(*peer_id);
+        }
+        let reassembler =
+            FileReassembler::new(&output_path, data.len() as u64, chunk_size).unwrap();
+        let context = Arc::new(
+            FileTransferContext::new_receive(
+                [0x51; 32],
+                Arc::new(tokio::sync::RwLock::new(transfer)),
+                Arc::new(Mutex::new(reassembler)),
+                wraith_files::tree_hash::FileTreeHash {
+                    root,
+                    chunks: Vec::new(),
+                },
+            )
+            .with_chunk_hashes(ChunkHashLayer::new(root, total_chunks))
+            .with_coordinator(Arc::clone(&node.inner.peer_coordinator)),
+        );
+        node.inner
+            .transfers
+            .insert([0x51; 32], Arc::clone(&context));
+        context
+    }
+
+    #[tokio::test]
+    async fn test_verify_pulled_chunk_records_corrupt_peer() {
+        let node = Node::new_random().await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let data = vec![0x5A; 256];
+        let peer_id = [6u8; 32];
+        let context = pulled_context(&node, &data, 64, &[peer_id], dir.path());
+        node.inner
+            .peer_coordinator
+            .add_peer(peer_id, "127.0.0.1:5000".parse().unwrap())
+            .await;
+
+        // Nothing is trusted before the layer has verified
+        assert!(
+            !node
+                .verify_pulled_chunk(&context, &peer_id, 0, &data[..64])
+                .await
+        );
+
+        let tree = wraith_files::tree_hash::compute_tree_hash_from_data(&data, 64);
+        let layer = context.chunk_hashes.as_ref().unwrap();
+        assert!(layer.lock().await.insert_segment(0, &tree.chunks).unwrap());
+        assert!(
+            node.verify_pulled_chunk(&context, &peer_id, 1, &data[64..128])
+                .await
+        );
+        assert!(
+            !node
+                .verify_pulled_chunk(&context, &peer_id, 1, &[0xEE; 64])
+                .await
+        );
+        assert_eq!(
+            node.inner.peer_coordinator.corrupt_peers().await,
+            vec![peer_id]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_chunk_hash_layer_from_seeder() {
+        use crate::node::NodeConfig;
+        use std::time::Duration;
+
+        let mut config = NodeConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            ..NodeConfig::default()
+        };
+        config.transfer.chunk_size = 1024;
+        config.transfer.hash_layer_request_interval = Duration::from_millis(100);
+        let seeder = Node::new_with_config(config.clone()).await.unwrap();
+        let downloader = Node::new_with_config(config).await.unwrap();
+        seeder.start().await.unwrap();
+        downloader.start().await.unwrap();
+        downloader
+            .establish_session_with_addr(
+                seeder.x25519_public_key(),
+                seeder.listen_addr().await.unwrap(),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // 40 chunks take two CHUNK_HASHES segments
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..40 * 1024u32).map(|i| (i % 251) as u8).collect();
+        let path = dir.path().join("seeded.dat");
+        std::fs::write(&path, &data).unwrap();
+        let root = seeder.announce_file(&path).await.unwrap();
+
+        let seeder_id = *seeder.x25519_public_key();
+        let context = pulled_context(&downloader, &data, 1024, &[seeder_id], dir.path());
+        downloader
+            .fetch_chunk_hash_layer(&context, &root, &[seeder_id])
+            .await
+            .unwrap();
+        let layer = context.chunk_hashes.as_ref().unwrap().lock().await;
+        assert!(layer.is_verified());
+        assert_eq!(
+            layer.verify_chunk(39, &data[39 * 1024..]),
+            ChunkVerdict::Valid
+        );
+        drop(layer);
+
+        // A file nobody seeds has no layer to fetch
+        downloader.inner.transfers.clear();
+        let context = pulled_context(&downloader, &[0x77; 4096], 1024, &[seeder_id], dir.path());
+        let root = context.tree_hash.root;
+        let result = downloader
+            .fetch_chunk_hash_layer(&context, &root, &[seeder_id])
+            .await;
+        assert!(result.unwrap_err().to_string().contains("unavailable"));
+
+        seeder.stop().await.unwrap();
+        downloader.stop().await.unwrap();
+    }
 }
@@ -21,7 +21,7 @@
 //! ```
 
 use crate::node::error::{NodeError, Result};
-use crate::node::file_transfer::FileTransferContext;
+use crate::node::file_transfer::{FileTransferContext, transfer_stream_id};
 use crate::node::identity::TransferId;
 use crate::node::session::PeerConnection;
 use crate::transfer::TransferSession;
@@ -411,11 +411,11 @@ impl TransferManager {
 
     /// Find transfer by stream ID
     ///
-    /// Stream ID is derived from transfer ID: `(transfer_id[0] << 8) | transfer_id[1]`
+    /// Stream ID is derived from transfer ID (see [`transfer_stream_id`])
     pub fn find_transfer_by_stream_id(&self, stream_id: u16) -> Option<Arc<FileTransferContext>> {
         for entry in self.transfers.iter() {
             let tid = entry.key();
-            let derived_stream_id = ((tid[0] as u16) << 8) | (tid[1] as u16);
+            let derived_stream_id = transfer_stream_id(tid);
             if derived_stream_id == stream_id {
                 return Some(Arc::clone(entry.value()));
             }
@@ -598,7 +598,7 @@ mod tests {
         let _ = std::fs::remove_file(&file_path);
 
         // Derive stream_id the same way the code does
-        let stream_id = ((transfer_id[0] as u16) << 8) | (transfer_id[1] as u16);
+        let stream_id = transfer_stream_id(&transfer_id);
 
         let found = manager.find_transfer_by_stream_id(stream_id);
         assert!(found.is_some());
@@ -0,0 +1,1318 @@
+//! Directory (tree) transfers
+//!
+//! A directory is sent as a single transfer. The sender walks the tree into a
+//! [`TreeManifest`] — relative paths, sizes, modes, mtimes, symlink targets and
+//! the Merkle root of every file — signs it with its Ed25519 identity key and
+//! ships it in STREAM_OPEN frames on the transfer's stream. Once the receiver
+//! accepts, every file is streamed on its own child stream inside the transfer
+//! and verified against its chunk hash layer exactly like a single-file
+//! transfer.
+//!
+//! Progress is tracked over one chunk space spanning all files (file `n`
+//! starts at [`TreeManifest::chunk_offsets`]`[n]`), so the whole tree has a
+//! single progress and [`ResumeState`]. Files are assembled in a staging
+//! directory inside the quarantine directory; symlinks, modes and mtimes are
+//! applied and the tree is moved into the download directory only after the
+//! last file has verified.
+//!
+//! # Manifest Segments
+//!
+//! Manifests larger than one frame are split into STREAM_OPEN segments:
+//!
+//! ```text
+//! transfer_id (32) | 0x00 | total_len (u32) | offset (u32) | manifest bytes
+//! ```
+//!
+//! The zero byte sits where a single-file [`FileMetadata`] carries its file
+//! name length, which is never zero for a file.
+
+use crate::FRAME_HEADER_SIZE;
+use crate::frame::{FrameBuilder, FrameType};
+use crate::node::error::{NodeError, Result};
+use crate::node::file_transfer::{
+    FIRST_TRANSFER_STREAM_ID, FileMetadata, MAX_FILE_NAME_LEN, MAX_TRANSFER_CHUNKS,
+    destination_candidates, sanitize_file_name, transfer_stream_id,
+};
+use crate::node::identity::{Identity, TransferId};
+use crate::node::resume::ResumeState;
+use crate::node::session::PeerId;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use wraith_crypto::signatures::{Signature, VerifyingKey};
+use wraith_files::tree_hash::{FileTreeHash, compute_tree_hash};
+
+/// Manifest wire format version
+pub const TREE_MANIFEST_VERSION: u8 = 1;
+
+/// Maximum size in bytes of a serialized manifest
+pub const MAX_MANIFEST_SIZE: usize = 8 * 1024 * 1024;
+
+/// Maximum number of entries (files, directories and symlinks) in a tree
+///
+/// Every entry gets its own stream, so this stays below the number of usable
+/// stream IDs.
+pub const MAX_TREE_ENTRIES: usize = 65_000;
+
+/// Manifest bytes carried per STREAM_OPEN segment
+pub const MANIFEST_SEGMENT_SIZE: usize = 8192;
+
+/// Maximum number of partially received manifests held at once
+pub const MAX_PENDING_MANIFESTS: usize = 4;
+
+/// Maximum number of path components in a tree entry
+const MAX_TREE_DEPTH: usize = 64;
+
+/// Maximum length in bytes of a relative path or symlink target
+const MAX_TREE_PATH_LEN: usize = 4096;
+
+/// transfer_id + marker + total_len + offset
+const MANIFEST_SEGMENT_HEADER_LEN: usize = 41;
+
+/// Number of usable (non-reserved) stream IDs
+const STREAM_ID_SPAN: usize = (u16::MAX - FIRST_TRANSFER_STREAM_ID) as usize + 1;
+
+/// Kind of entry in a tree manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TreeEntryKind {
+    /// Regular file
+    File = 0,
+    /// Directory
+    Directory = 1,
+    /// Symbolic link (never followed by the sender)
+    Symlink = 2,
+}
+
+impl TreeEntryKind {
+    fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::File),
+            1 => Ok(Self::Directory),
+            2 => Ok(Self::Symlink),
+            _ => Err(NodeError::invalid_state("Unknown tree entry kind")),
+        }
+    }
+}
+
+/// A single file, directory or symlink in a tree manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Path relative to the tree root, `/`-separated
+    pub path: String,
+    /// Entry kind
+    pub kind: TreeEntryKind,
+    /// File size in bytes (0 for directories and symlinks)
+    pub size: u64,
+    /// Permission bits (`0o777` mask)
+    pub mode: u32,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: u64,
+    /// Link target (symlinks only), relative to the link's directory
+    pub symlink_target: Option<String>,
+    /// Merkle root of the file's chunk hashes (all zero for empty files)
+    pub root_hash: [u8; 32],
+}
+
+impl TreeEntry {
+    /// Number of chunks this entry contributes to the transfer
+    pub fn chunk_count(&self, chunk_size: u32) -> u64 {
+        match self.kind {
+            TreeEntryKind::File if chunk_size > 0 => self.size.div_ceil(u64::from(chunk_size)),
+            _ => 0,
+        }
+    }
+}
+
+/// A file to stream for an outgoing tree transfer
+#[derive(Debug, Clone)]
+pub struct TreeFile {
+    /// Index of the file's entry in the manifest
+    pub entry_index: usize,
+    /// Local path of the file
+    pub source: PathBuf,
+    /// Chunk hashes and Merkle root of the file
+    pub tree_hash: FileTreeHash,
+}
+
+/// Link from a file's transfer context to the tree transfer it belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeMember {
+    /// Transfer ID of the tree
+    pub tree_id: TransferId,
+    /// Index of the file's entry in the manifest
+    pub entry_index: usize,
+    /// Index of the file's first chunk in the tree-wide chunk space
+    pub first_chunk: u64,
+    /// Stream carrying the file's chunks
+    pub stream_id: u16,
+}
+
+/// Signed description of a directory tree sent as one transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeManifest {
+    /// Transfer ID of the whole tree
+    pub transfer_id: TransferId,
+    /// Name of the tree's root directory
+    pub root_name: String,
+    /// Chunk size used for every file
+    pub chunk_size: u32,
+    /// Entries in depth-first order
+    pub entries: Vec<TreeEntry>,
+    /// Ed25519 public key (node ID) of the signer
+    pub signer: [u8; 32],
+    /// Ed25519 signature over every other field
+    pub signature: [u8; 64],
+}
+
+impl TreeManifest {
+    /// Walk `dir` and build an unsigned manifest for it
+    ///
+    /// Symlinks are recorded, never followed. Sockets, devices and other
+    /// special files are skipped. Returns the manifest together with the
+    /// files that need to be streamed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` is not a directory, cannot be read, contains
+    /// names that cannot be sent safely, or has no file data at all.
+    pub fn from_directory(
+        transfer_id: TransferId,
+        dir: &Path,
+        chunk_size: usize,
+    ) -> Result<(Self, Vec<TreeFile>)> {
+        let metadata = std::fs::metadata(dir).map_err(|e| NodeError::Io(e.to_string()))?;
+        if !metadata.is_dir() {
+            return Err(NodeError::invalid_state("Not a directory"));
+        }
+        let root_name = dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| dir.file_name()?.to_str().map(str::to_string))
+            .ok_or_else(|| NodeError::invalid_state("Invalid directory name"))?;
+
+        let mut manifest = Self {
+            transfer_id,
+            root_name,
+            chunk_size: u32::try_from(chunk_size)
+                .map_err(|_| NodeError::invalid_state("Invalid chunk size"))?,
+            entries: Vec::new(),
+            signer: [0u8; 32],
+            signature: [0u8; 64],
+        };
+        let mut files = Vec::new();
+        manifest.walk(dir, "", 1, &mut files)?;
+        manifest.validate()?;
+
+        Ok((manifest, files))
+    }
+
+    fn walk(
+        &mut self,
+        dir: &Path,
+        prefix: &str,
+        depth: usize,
+        files: &mut Vec<TreeFile>,
+    ) -> Result<()> {
+        if depth > MAX_TREE_DEPTH {
+            return Err(NodeError::invalid_state("Directory tree too deep"));
+        }
+
+        let mut children = std::fs::read_dir(dir)
+            .and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>())
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+        children.sort_by_key(std::fs::DirEntry::file_name);
+
+        for child in children {
+            let name = child.file_name().into_string().map_err(|name| {
+                NodeError::InvalidState(
+                    format!("File name is not UTF-8: {}", name.to_string_lossy()).into(),
+                )
+            })?;
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            let source = child.path();
+            let metadata =
+                std::fs::symlink_metadata(&source).map_err(|e| NodeError::Io(e.to_string()))?;
+            let mut entry = TreeEntry {
+                path,
+                kind: TreeEntryKind::File,
+                size: 0,
+                mode: entry_mode(&metadata),
+                mtime: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+                symlink_target: None,
+                root_hash: [0u8; 32],
+            };
+
+            let file_type = metadata.file_type();
+            if file_type.is_symlink() {
+                let target =
+                    std::fs::read_link(&source).map_err(|e| NodeError::Io(e.to_string()))?;
+                entry.kind = TreeEntryKind::Symlink;
+                entry.symlink_target = Some(
+                    target
+                        .to_str()
+                        .ok_or_else(|| NodeError::invalid_state("Symlink target is not UTF-8"))?
+                        .replace(std::path::MAIN_SEPARATOR, "/"),
+                );
+            } else if file_type.is_dir() {
+                entry.kind = TreeEntryKind::Directory;
+                let prefix = entry.path.clone();
+                self.push_entry(entry)?;
+                self.walk(&source, &prefix, depth + 1, files)?;
+                continue;
+            } else if file_type.is_file() {
+                entry.size = metadata.len();
+                if entry.size > 0 {
+                    let tree_hash = compute_tree_hash(&source, self.chunk_size as usize)
+                        .map_err(|e| NodeError::Io(e.to_string()))?;
+                    entry.root_hash = tree_hash.root;
+                    files.push(TreeFile {
+                        entry_index: self.entries.len(),
+                        source,
+                        tree_hash,
+                    });
+                }
+            } else {
+                tracing::warn!("Skipping special file {}", entry.path);
+                continue;
+            }
+            self.push_entry(entry)?;
+        }
+
+        Ok(())
+    }
+
+    fn push_entry(&mut self, entry: TreeEntry) -> Result<()> {
+        if self.entries.len() >= MAX_TREE_ENTRIES {
+            return Err(NodeError::invalid_state("Too many entries in tree"));
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Total size in bytes of all files
+    pub fn total_size(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == TreeEntryKind::File)
+            .fold(0u64, |total, entry| total.saturating_add(entry.size))
+    }
+
+    /// Total number of chunks across all files
+    pub fn total_chunks(&self) -> u64 {
+        self.entries.iter().fold(0u64, |total, entry| {
+            total.saturating_add(entry.chunk_count(self.chunk_size))
+        })
+    }
+
+    /// Index of each entry's first chunk in the tree-wide chunk space
+    pub fn chunk_offsets(&self) -> Vec<u64> {
+        let mut next = 0u64;
+        self.entries
+            .iter()
+            .map(|entry| {
+                let first = next;
+                next = next.saturating_add(entry.chunk_count(self.chunk_size));
+                first
+            })
+            .collect()
+    }
+
+    /// Transfer ID used for the chunks of a single file in the tree
+    pub fn child_transfer_id(&self, entry_index: usize) -> TransferId {
+        let mut hasher = blake3::Hasher::new_derive_key("wraith tree transfer child v1");
+        hasher.update(&self.transfer_id);
+        hasher.update(&(entry_index as u64).to_be_bytes());
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Stream carrying the chunks of the entry at `entry_index`
+    ///
+    /// Child streams follow the tree's own stream, wrapping around the
+    /// usable stream ID range, so files in one tree never share a stream.
+    pub fn child_stream_id(&self, entry_index: usize) -> u16 {
+        let parent = usize::from(transfer_stream_id(&self.transfer_id) - FIRST_TRANSFER_STREAM_ID);
+        let offset = (parent + 1 + entry_index) % STREAM_ID_SPAN;
+        FIRST_TRANSFER_STREAM_ID + offset as u16
+    }
+
+    /// Links from each entry's transfer context to this tree, by entry index
+    pub fn members(&self) -> Vec<TreeMember> {
+        self.chunk_offsets()
+            .into_iter()
+            .enumerate()
+            .map(|(entry_index, first_chunk)| TreeMember {
+                tree_id: self.transfer_id,
+                entry_index,
+                first_chunk,
+                stream_id: self.child_stream_id(entry_index),
+            })
+            .collect()
+    }
+
+    /// Entry index of the file streamed on `stream_id`, if it carries file data
+    pub fn child_entry_index(&self, stream_id: u16) -> Option<usize> {
+        let offset = usize::from(stream_id.checked_sub(FIRST_TRANSFER_STREAM_ID)?);
+        let parent = usize::from(transfer_stream_id(&self.transfer_id) - FIRST_TRANSFER_STREAM_ID);
+        let index = (offset + STREAM_ID_SPAN - parent - 1) % STREAM_ID_SPAN;
+        self.entries
+            .get(index)
+            .filter(|entry| entry.chunk_count(self.chunk_size) > 0)
+            .map(|_| index)
+    }
+
+    /// BLAKE3 digest of the signed manifest contents
+    ///
+    /// Stands in for the file hash wherever a tree is tracked as one transfer.
+    pub fn digest(&self) -> [u8; 32] {
+        *blake3::hash(&self.signed_bytes()).as_bytes()
+    }
+
+    /// Metadata describing the whole tree, as shown to transfer acceptors
+    pub fn summary_metadata(&self) -> FileMetadata {
+        FileMetadata {
+            transfer_id: self.transfer_id,
+            file_name: self.root_name.clone(),
+            file_size: self.total_size(),
+            chunk_size: self.chunk_size,
+            total_chunks: self.total_chunks(),
+            root_hash: self.digest(),
+        }
+    }
+
+    /// Resume state covering the whole tree
+    pub fn resume_state(&self, peer_id: PeerId, path: PathBuf, is_sender: bool) -> ResumeState {
+        let mut state = ResumeState::new(
+            self.transfer_id,
+            peer_id,
+            self.digest(),
+            self.total_size(),
+            self.chunk_size as usize,
+            path,
+            is_sender,
+        );
+        state.total_chunks = self.total_chunks() as usize;
+        state
+    }
+
+    /// Sign the manifest with the node's identity key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identity has no signing key.
+    pub fn sign(&mut self, identity: &Identity) -> Result<()> {
+        self.signer = *identity.public_key();
+        self.signature = *identity.sign(&self.signed_bytes())?.as_bytes();
+        Ok(())
+    }
+
+    /// Check the signature against the embedded signer key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NodeError::Crypto`] if the signer key or signature is invalid.
+    pub fn verify_signature(&self) -> Result<()> {
+        let key = VerifyingKey::from_bytes(&self.signer)?;
+        key.verify(&self.signed_bytes(), &Signature::from_bytes(self.signature))?;
+        Ok(())
+    }
+
+    /// Validate a manifest received from a remote peer
+    ///
+    /// Every path must be relative, made of components that are safe to
+    /// create as-is (see [`sanitize_file_name`]; a leading dot is allowed),
+    /// unique, and not nested under a file or symlink. Symlink targets must
+    /// stay inside the tree without passing through another symlink.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first violation found.
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(NodeError::invalid_state("Invalid chunk size"));
+        }
+        if self.root_name.len() > MAX_FILE_NAME_LEN {
+            return Err(NodeError::invalid_state("Directory name too long"));
+        }
+        if self.entries.len() > MAX_TREE_ENTRIES {
+            return Err(NodeError::invalid_state("Too many entries in tree"));
+        }
+
+        let mut paths = HashSet::with_capacity(self.entries.len());
+        let mut leaves = HashSet::new();
+        let mut symlinks = HashSet::new();
+        let mut total_chunks = 0u64;
+        for entry in &self.entries {
+            validate_tree_path(&entry.path)?;
+            if !paths.insert(entry.path.as_str()) {
+                return Err(NodeError::InvalidState(
+                    format!("Duplicate path in tree manifest: {:?}", entry.path).into(),
+                ));
+            }
+            match entry.kind {
+                TreeEntryKind::File => {
+                    leaves.insert(entry.path.as_str());
+                    total_chunks = total_chunks.saturating_add(entry.chunk_count(self.chunk_size));
+                }
+                TreeEntryKind::Symlink => {
+                    leaves.insert(entry.path.as_str());
+                    symlinks.insert(entry.path.as_str());
+                }
+                TreeEntryKind::Directory => {}
+            }
+        }
+
+        if total_chunks == 0 {
+            return Err(NodeError::invalid_state("Tree contains no file data"));
+        }
+        if total_chunks > MAX_TRANSFER_CHUNKS {
+            return Err(NodeError::invalid_state("Too many chunks in transfer"));
+        }
+
+        for entry in &self.entries {
+            if entry
+                .path
+                .match_indices('/')
+                .any(|(end, _)| leaves.contains(&entry.path[..end]))
+            {
+                return Err(NodeError::InvalidState(
+                    format!("Tree entry nested under a file: {:?}", entry.path).into(),
+                ));
+            }
+            if entry.kind == TreeEntryKind::Symlink {
+                let target = entry
+                    .symlink_target
+                    .as_deref()
+                    .ok_or_else(|| NodeError::invalid_state("Symlink without target"))?;
+                validate_symlink_target(&entry.path, target, &symlinks)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize every field except the signature
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(80 + self.entries.len() * 96);
+        buf.push(TREE_MANIFEST_VERSION);
+        buf.extend_from_slice(&self.transfer_id);
+        buf.extend_from_slice(&self.signer);
+        buf.extend_from_slice(&self.chunk_size.to_be_bytes());
+        buf.push(self.root_name.len().min(u8::MAX as usize) as u8);
+        buf.extend_from_slice(
+            &self.root_name.as_bytes()[..self.root_name.len().min(u8::MAX as usize)],
+        );
+        buf.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+
+        for entry in &self.entries {
+            let target = entry.symlink_target.as_deref().unwrap_or_default();
+            buf.push(entry.kind as u8);
+            buf.extend_from_slice(&(entry.path.len() as u16).to_be_bytes());
+            buf.extend_from_slice(entry.path.as_bytes());
+            buf.extend_from_slice(&entry.size.to_be_bytes());
+            buf.extend_from_slice(&entry.mode.to_be_bytes());
+            buf.extend_from_slice(&entry.mtime.to_be_bytes());
+            buf.extend_from_slice(&entry.root_hash);
+            buf.extend_from_slice(&(target.len() as u16).to_be_bytes());
+            buf.extend_from_slice(target.as_bytes());
+        }
+
+        buf
+    }
+
+    /// Serialize the manifest
+    ///
+    /// Format (all integers big-endian):
+    /// - 1 byte: version
+    /// - 32 bytes: transfer_id
+    /// - 32 bytes: signer
+    /// - 4 bytes: chunk_size
+    /// - 1 + N bytes: root_name
+    /// - 4 bytes: entry count, then per entry: kind (1), path (2 + N),
+    ///   size (8), mode (4), mtime (8), root_hash (32), symlink target (2 + N)
+    /// - 64 bytes: signature
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = self.signed_bytes();
+        buf.extend_from_slice(&self.signature);
+        buf
+    }
+
+    /// Deserialize a manifest (call [`Self::validate`] and
+    /// [`Self::verify_signature`] before trusting it)
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        let mut reader = ManifestReader { data, pos: 0 };
+
+        if reader.u8()? != TREE_MANIFEST_VERSION {
+            return Err(NodeError::invalid_state(
+                "Unsupported tree manifest version",
+            ));
+        }
+        let transfer_id = reader.array()?;
+        let signer = reader.array()?;
+        let chunk_size = reader.u32()?;
+        let root_name_len = usize::from(reader.u8()?);
+        let root_name = reader.string(root_name_len)?;
+
+        let entry_count = reader.u32()? as usize;
+        if entry_count > MAX_TREE_ENTRIES {
+            return Err(NodeError::invalid_state("Too many entries in tree"));
+        }
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            let kind = TreeEntryKind::from_u8(reader.u8()?)?;
+            let path_len = usize::from(reader.u16()?);
+            let path = reader.string(path_len)?;
+            let size = reader.u64()?;
+            let mode = reader.u32()?;
+            let mtime = reader.u64()?;
+            let root_hash = reader.array()?;
+            let target_len = usize::from(reader.u16()?);
+            let target = reader.string(target_len)?;
+
+            entries.push(TreeEntry {
+                path,
+                kind,
+                size: if kind == TreeEntryKind::File { size } else { 0 },
+                mode: mode & 0o777,
+                mtime,
+                symlink_target: (kind == TreeEntryKind::Symlink).then_some(target),
+                root_hash,
+            });
+        }
+
+        let signature = reader.array()?;
+        if reader.pos != data.len() {
+            return Err(NodeError::invalid_state(
+                "Trailing bytes after tree manifest",
+            ));
+        }
+
+        Ok(Self {
+            transfer_id,
+            root_name,
+            chunk_size,
+            entries,
+            signer,
+            signature,
+        })
+    }
+}
+
+/// Cursor over a serialized manifest
+struct ManifestReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ManifestReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| NodeError::invalid_state("Tree manifest truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_be_bytes)
+    }
+
+    fn string(&mut self, len: usize) -> Result<String> {
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| NodeError::invalid_state("Tree manifest string is not UTF-8"))
+    }
+}
+
+/// Check a manifest path: relative, bounded, made of safe components
+fn validate_tree_path(path: &str) -> Result<()> {
+    let unsafe_path =
+        || NodeError::InvalidState(format!("Unsafe path in tree manifest: {path:?}").into());
+
+    if path.is_empty() || path.len() > MAX_TREE_PATH_LEN {
+        return Err(unsafe_path());
+    }
+    for (depth, component) in path.split('/').enumerate() {
+        if depth >= MAX_TREE_DEPTH || !is_safe_component(component) {
+            return Err(unsafe_path());
+        }
+    }
+    Ok(())
+}
+
+/// A component is safe if sanitizing it is a no-op
+///
+/// One leading dot is allowed: hidden files such as `.gitignore` are part of
+/// most source trees and stay confined to the received directory.
+fn is_safe_component(component: &str) -> bool {
+    let visible = component.strip_prefix('.').unwrap_or(component);
+    !visible.is_empty() && sanitize_file_name(visible) == visible
+}
+
+/// Check that a symlink target resolves inside the tree
+///
+/// The target is resolved lexically from the link's directory. Intermediate
+/// components may not be other symlinks, since `..` after a symlink would be
+/// resolved relative to wherever that link points.
+fn validate_symlink_target(link_path: &str, target: &str, symlinks: &HashSet<&str>) -> Result<()> {
+    let escapes =
+        || NodeError::InvalidState(format!("Symlink escapes the tree: {link_path:?}").into());
+
+    if target.is_empty()
+        || target.len() > MAX_TREE_PATH_LEN
+        || target.starts_with('/')
+        || target.contains(['\\', ':', '\0'])
+    {
+        return Err(escapes());
+    }
+
+    let mut resolved: Vec<&str> = link_path.split('/').collect();
+    resolved.pop();
+    let components: Vec<&str> = target
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    for (i, component) in components.iter().enumerate() {
+        if *component == ".." {
+            resolved.pop().ok_or_else(escapes)?;
+            continue;
+        }
+        resolved.push(component);
+        if i + 1 < components.len() && symlinks.contains(resolved.join("/").as_str()) {
+            return Err(escapes());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn entry_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+fn entry_mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+/// Build the STREAM_OPEN frames carrying a signed manifest
+pub fn build_manifest_frames(stream_id: u16, manifest: &TreeManifest) -> Result<Vec<Vec<u8>>> {
+    let bytes = manifest.serialize();
+    if bytes.len() > MAX_MANIFEST_SIZE {
+        return Err(NodeError::invalid_state("Tree manifest too large"));
+    }
+    let total_len = bytes.len() as u32;
+
+    bytes
+        .chunks(MANIFEST_SEGMENT_SIZE)
+        .enumerate()
+        .map(|(index, segment)| {
+            let mut payload = Vec::with_capacity(MANIFEST_SEGMENT_HEADER_LEN + segment.len());
+            payload.extend_from_slice(&manifest.transfer_id);
+            payload.push(0);
+            payload.extend_from_slice(&total_len.to_be_bytes());
+            payload.extend_from_slice(&((index * MANIFEST_SEGMENT_SIZE) as u32).to_be_bytes());
+            payload.extend_from_slice(segment);
+
+            FrameBuilder::new()
+                .frame_type(FrameType::StreamOpen)
+                .stream_id(stream_id)
+                .sequence(index as u32)
+                .payload(&payload)
+                .build(FRAME_HEADER_SIZE + payload.len())
+                .map_err(|e| {
+                    NodeError::InvalidState(format!("Failed to build manifest frame: {e}").into())
+                })
+        })
+        .collect()
+}
+
+/// Check whether a STREAM_OPEN payload is a manifest segment rather than file metadata
+pub fn is_manifest_segment(payload: &[u8]) -> bool {
+    payload.len() >= MANIFEST_SEGMENT_HEADER_LEN && payload[32] == 0
+}
+
+/// A parsed manifest segment
+#[derive(Debug, Clone, Copy)]
+pub struct ManifestSegment<'a> {
+    /// Transfer ID of the tree
+    pub transfer_id: TransferId,
+    /// Length of the full serialized manifest
+    pub total_len: u32,
+    /// Offset of this segment in the manifest
+    pub offset: u32,
+    /// Segment bytes
+    pub data: &'a [u8],
+}
+
+/// Parse a manifest segment payload
+pub fn parse_manifest_segment(payload: &[u8]) -> Result<ManifestSegment<'_>> {
+    if !is_manifest_segment(payload) {
+        return Err(NodeError::invalid_state("Invalid manifest segment"));
+    }
+
+    let mut transfer_id = [0u8; 32];
+    transfer_id.copy_from_slice(&payload[..32]);
+    let total_len = u32::from_be_bytes([payload[33], payload[34], payload[35], payload[36]]);
+    let offset = u32::from_be_bytes([payload[37], payload[38], payload[39], payload[40]]);
+
+    Ok(ManifestSegment {
+        transfer_id,
+        total_len,
+        offset,
+        data: &payload[MANIFEST_SEGMENT_HEADER_LEN..],
+    })
+}
+
+/// Receiver-side reassembly of a segmented manifest
+pub struct ManifestAssembly {
+    peer_id: PeerId,
+    data: Vec<u8>,
+    received: Vec<bool>,
+    remaining: usize,
+    started: Instant,
+}
+
+impl ManifestAssembly {
+    /// Start collecting a manifest of `total_len` bytes from `peer_id`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `total_len` is zero or exceeds [`MAX_MANIFEST_SIZE`].
+    pub fn new(peer_id: PeerId, total_len: u32) -> Result<Self> {
+        let total_len = total_len as usize;
+        if total_len == 0 || total_len > MAX_MANIFEST_SIZE {
+            return Err(NodeError::invalid_state("Invalid tree manifest length"));
+        }
+        let segments = total_len.div_ceil(MANIFEST_SEGMENT_SIZE);
+
+        Ok(Self {
+            peer_id,
+            data: vec![0u8; total_len],
+            received: vec![false; segments],
+            remaining: segments,
+            started: Instant::now(),
+        })
+    }
+
+    /// Peer sending the manifest
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer_id
+    }
+
+    /// Whether the manifest has been incomplete for longer than `timeout`
+    pub fn is_expired(&self, timeout: Duration) -> bool {
+        self.started.elapsed() > timeout
+    }
+
+    /// Add a segment, returning the full manifest once every segment arrived
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment does not fit the announced layout.
+    pub fn insert(&mut self, segment: &ManifestSegment<'_>) -> Result<Option<Vec<u8>>> {
+        let offset = segment.offset as usize;
+        if segment.total_len as usize != self.data.len()
+            || !offset.is_multiple_of(MANIFEST_SEGMENT_SIZE)
+        {
+            return Err(NodeError::invalid_state(
+                "Manifest segment does not match layout",
+            ));
+        }
+        let index = offset / MANIFEST_SEGMENT_SIZE;
+        let expected_len = MANIFEST_SEGMENT_SIZE.min(self.data.len().saturating_sub(offset));
+        if index >= self.received.len() || segment.data.len() != expected_len {
+            return Err(NodeError::invalid_state(
+                "Manifest segment does not match layout",
+            ));
+        }
+
+        if !self.received[index] {
+            self.data[offset..offset + expected_len].copy_from_slice(segment.data);
+            self.received[index] = true;
+            self.remaining -= 1;
+        }
+
+        Ok((self.remaining == 0).then(|| std::mem::take(&mut self.data)))
+    }
+}
+
+/// Staging directory an incoming tree is assembled in
+///
+/// Lives at `<quarantine>/<transfer id>.tree`. Directories and empty files are
+/// created up front; each file is moved in from its own `.part` file once it
+/// verifies. [`TreeStaging::commit`] applies symlinks and metadata and moves
+/// the tree into place.
+#[derive(Debug)]
+pub struct TreeStaging {
+    /// Manifest the tree is built from
+    pub manifest: Arc<TreeManifest>,
+    /// Directory the tree is assembled in
+    pub staging_dir: PathBuf,
+    /// Where the tree is moved once complete (a free `name (n)` is used if
+    /// this path is taken by then)
+    pub destination: PathBuf,
+    members: Vec<TreeMember>,
+    completed: std::sync::Mutex<HashSet<usize>>,
+}
+
+impl TreeStaging {
+    /// Create the staging directory with every directory and empty file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the staging directory cannot be created.
+    pub fn new(
+        quarantine_dir: &Path,
+        manifest: Arc<TreeManifest>,
+        destination: PathBuf,
+    ) -> Result<Self> {
+        let staging_dir =
+            quarantine_dir.join(format!("{}.tree", hex::encode(manifest.transfer_id)));
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&staging_dir)
+            .map_err(|e| NodeError::Io(e.to_string()))?;
+
+        let staging = Self {
+            members: manifest.members(),
+            manifest,
+            staging_dir,
+            destination,
+            completed: std::sync::Mutex::new(HashSet::new()),
+        };
+        if let Err(e) = staging.populate() {
+            staging.discard();
+            return Err(e);
+        }
+        Ok(staging)
+    }
+
+    fn populate(&self) -> Result<()> {
+        let io_err = |e: std::io::Error| NodeError::Io(e.to_string());
+
+        for (index, entry) in self.manifest.entries.iter().enumerate() {
+            let path = self.entry_path(index);
+            match entry.kind {
+                TreeEntryKind::Directory => std::fs::create_dir_all(&path).map_err(io_err)?,
+                TreeEntryKind::File | TreeEntryKind::Symlink => {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent).map_err(io_err)?;
+                    }
+                    if entry.kind == TreeEntryKind::File && entry.size == 0 {
+                        std::fs::File::create_new(&path).map_err(io_err)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Path of an entry inside the staging directory
+    pub fn entry_path(&self, entry_index: usize) -> PathBuf {
+        self.staging_dir.join(
+            self.manifest.entries[entry_index]
+                .path
+                .replace('/', std::path::MAIN_SEPARATOR_STR),
+        )
+    }
+
+    /// Link from the transfer context of the entry at `entry_index` to this tree
+    pub fn member(&self, entry_index: usize) -> TreeMember {
+        self.members[entry_index]
+    }
+
+    /// Record that a file has been verified and moved into the staging directory
+    pub fn mark_file_complete(&self, entry_index: usize) {
+        self.completed
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(entry_index);
+    }
+
+    /// Whether a file has already been verified and moved into the staging directory
+    pub fn is_file_complete(&self, entry_index: usize) -> bool {
+        self.completed
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .contains(&entry_index)
+    }
+
+    /// Create symlinks, apply modes and mtimes, and move the tree into place
+    ///
+    /// Returns the final path of the tree.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tree cannot be finalized or moved.
+    pub fn commit(&self) -> Result<PathBuf> {
+        let io_err = |e: std::io::Error| NodeError::Io(e.to_string());
+
+        for (index, entry) in self.manifest.entries.iter().enumerate() {
+            let path = self.entry_path(index);
+            match entry.kind {
+                TreeEntryKind::Symlink => {
+                    let target = entry.symlink_target.as_deref().unwrap_or_default();
+                    #[cfg(unix)]
+                    std::os::unix::fs::symlink(target, &path).map_err(io_err)?;
+                    #[cfg(not(unix))]
+                    tracing::warn!("Skipping symlink {} -> {}", entry.path, target);
+                }
+                TreeEntryKind::File => {
+                    let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(entry.mtime);
+                    std::fs::File::options()
+                        .write(true)
+                        .open(&path)
+                        .and_then(|file| file.set_modified(mtime))
+                        .map_err(io_err)?;
+                    set_mode(&path, entry.mode)?;
+                }
+                TreeEntryKind::Directory => {}
+            }
+        }
+
+        // Deepest directories last-to-first so restrictive modes never block the walk;
+        // the owner keeps full access so the tree can still be managed
+        for (index, entry) in self.manifest.entries.iter().enumerate().rev() {
+            if entry.kind == TreeEntryKind::Directory {
+                set_mode(&self.entry_path(index), entry.mode | 0o700)?;
+            }
+        }
+
+        let dir = self.destination.parent().unwrap_or_else(|| Path::new("."));
+        let name = self
+            .destination
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("received_tree");
+        let destination = destination_candidates(dir, name)
+            .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+            .ok_or_else(|| NodeError::invalid_state("Too many files with the same name"))?;
+        std::fs::rename(&self.staging_dir, &destination).map_err(io_err)?;
+
+        Ok(destination)
+    }
+
+    /// Remove the staging directory (failed or abandoned transfer)
+    pub fn discard(&self) {
+        let _ = std::fs::remove_dir_all(&self.staging_dir);
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))
+        .map_err(|e| NodeError::Io(e.to_string()))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::write(root.join("README.md"), b"hello tree").unwrap();
+        std::fs::write(root.join("src/lib.rs"), vec![7u8; 3000]).unwrap();
+        std::fs::write(root.join("src/nested/empty.txt"), b"").unwrap();
+        std::fs::write(root.join(".gitignore"), b"target\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("../README.md", root.join("src/readme")).unwrap();
+        dir
+    }
+
+    fn manifest_with(entries: Vec<TreeEntry>) -> TreeManifest {
+        TreeManifest {
+            transfer_id: [3u8; 32],
+            root_name: "tree".to_string(),
+            chunk_size: 1024,
+            entries,
+            signer: [0u8; 32],
+            signature: [0u8; 64],
+        }
+    }
+
+    fn entry(path: &str, kind: TreeEntryKind, size: u64, target: Option<&str>) -> TreeEntry {
+        TreeEntry {
+            path: path.to_string(),
+            kind,
+            size,
+            mode: 0o644,
+            mtime: 0,
+            symlink_target: target.map(str::to_string),
+            root_hash: [0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_from_directory() {
+        let dir = sample_tree();
+        let (manifest, files) = TreeManifest::from_directory([1u8; 32], dir.path(), 1024).unwrap();
+
+        let paths: Vec<&str> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&".gitignore"));
+        assert!(paths.contains(&"src/nested/empty.txt"));
+        assert_eq!(manifest.total_size(), 10 + 3000 + 7);
+        assert_eq!(manifest.total_chunks(), 1 + 3 + 1);
+        // Empty files are created by the receiver and never streamed
+        assert_eq!(files.len(), 3);
+        for file in &files {
+            assert_eq!(
+                manifest.entries[file.entry_index].root_hash,
+                file.tree_hash.root
+            );
+        }
+        #[cfg(unix)]
+        assert!(
+            manifest
+                .entries
+                .iter()
+                .any(|e| e.kind == TreeEntryKind::Symlink
+                    && e.symlink_target.as_deref() == Some("../README.md"))
+        );
+    }
+
+    #[test]
+    fn test_from_directory_rejects_empty_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+        assert!(TreeManifest::from_directory([1u8; 32], dir.path(), 1024).is_err());
+    }
+
+    #[test]
+    fn test_sign_serialize_roundtrip() {
+        let dir = sample_tree();
+        let identity = Identity::generate().unwrap();
+        let (mut manifest, _) = TreeManifest::from_directory([1u8; 32], dir.path(), 1024).unwrap();
+        manifest.sign(&identity).unwrap();
+
+        let decoded = TreeManifest::deserialize(&manifest.serialize()).unwrap();
+        assert_eq!(decoded, manifest);
+        assert_eq!(decoded.signer, *identity.public_key());
+        decoded.verify_signature().unwrap();
+        decoded.validate().unwrap();
+    }
+
+    #[test]
+    fn test_tampered_manifest_fails_signature() {
+        let dir = sample_tree();
+        let identity = Identity::generate().unwrap();
+        let (mut manifest, _) = TreeManifest::from_directory([1u8; 32], dir.path(), 1024).unwrap();
+        manifest.sign(&identity).unwrap();
+
+        manifest.entries[0].mode = 0o777;
+        assert!(manifest.verify_signature().is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_hostile_paths() {
+        for path in [
+            "../evil",
+            "/etc/passwd",
+            "a/../../b",
+            "a//b",
+            "a\\b",
+            "CON",
+            "..",
+            "a/.",
+        ] {
+            let manifest = manifest_with(vec![entry(path, TreeEntryKind::File, 1, None)]);
+            assert!(manifest.validate().is_err(), "accepted {path:?}");
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_duplicates_and_nesting() {
+        let duplicate = manifest_with(vec![
+            entry("a", TreeEntryKind::File, 1, None),
+            entry("a", TreeEntryKind::File, 1, None),
+        ]);
+        assert!(duplicate.validate().is_err());
+
+        let nested = manifest_with(vec![
+            entry("a", TreeEntryKind::File, 1, None),
+            entry("a/b", TreeEntryKind::File, 1, None),
+        ]);
+        assert!(nested.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_symlink_targets() {
+        let ok = manifest_with(vec![
+            entry("a/file", TreeEntryKind::File, 1, None),
+            entry("a/link", TreeEntryKind::Symlink, 0, Some("../a/./file")),
+        ]);
+        ok.validate().unwrap();
+
+        for target in ["../../x", "/etc/passwd", "..", "C:/x", "a\\..\\.."] {
+            let manifest = manifest_with(vec![
+                entry("file", TreeEntryKind::File, 1, None),
+                entry("link", TreeEntryKind::Symlink, 0, Some(target)),
+            ]);
+            assert!(manifest.validate().is_err(), "accepted {target:?}");
+        }
+
+        // `..` after another symlink resolves relative to that link's target
+        let chained = manifest_with(vec![
+            entry("file", TreeEntryKind::File, 1, None),
+            entry("a/up", TreeEntryKind::Symlink, 0, Some("..")),
+            entry("escape", TreeEntryKind::Symlink, 0, Some("a/up/..")),
+        ]);
+        assert!(chained.validate().is_err());
+    }
+
+    #[test]
+    fn test_child_streams_are_distinct() {
+        let mut manifest = manifest_with(Vec::new());
+        manifest.transfer_id[0] = 0xFF;
+        manifest.transfer_id[1] = 0xFE;
+        manifest.entries = (0..100)
+            .map(|i| entry(&format!("f{i}"), TreeEntryKind::File, 1, None))
+            .collect();
+
+        let parent = transfer_stream_id(&manifest.transfer_id);
+        let streams: HashSet<u16> = (0..100).map(|i| manifest.child_stream_id(i)).collect();
+        assert_eq!(streams.len(), 100);
+        assert!(!streams.contains(&parent));
+        assert!(streams.iter().all(|s| *s >= FIRST_TRANSFER_STREAM_ID));
+        for i in 0..100 {
+            assert_eq!(
+                manifest.child_entry_index(manifest.child_stream_id(i)),
+                Some(i)
+            );
+        }
+        assert_ne!(manifest.child_transfer_id(0), manifest.child_transfer_id(1));
+    }
+
+    #[test]
+    fn test_manifest_segments_reassemble() {
+        let mut manifest = manifest_with(
+            (0..500)
+                .map(|i| {
+                    entry(
+                        &format!("dir/file-{i:04}.bin"),
+                        TreeEntryKind::File,
+                        10,
+                        None,
+                    )
+                })
+                .collect(),
+        );
+        manifest
+            .entries
+            .push(entry("dir", TreeEntryKind::Directory, 0, None));
+        let frames = build_manifest_frames(0x1234, &manifest).unwrap();
+        assert!(frames.len() > 1);
+
+        let mut assembly: Option<ManifestAssembly> = None;
+        let mut result = None;
+        for frame_bytes in frames.iter().rev() {
+            let frame = crate::frame::Frame::parse(frame_bytes).unwrap();
+            assert_eq!(frame.frame_type(), FrameType::StreamOpen);
+            let segment = parse_manifest_segment(frame.payload()).unwrap();
+            let assembly = assembly.get_or_insert_with(|| {
+                ManifestAssembly::new([9u8; 32], segment.total_len).unwrap()
+            });
+            result = assembly.insert(&segment).unwrap();
+        }
+
+        assert_eq!(
+            TreeManifest::deserialize(&result.unwrap()).unwrap(),
+            manifest
+        );
+    }
+
+    #[test]
+    fn test_manifest_segment_not_metadata() {
+        let metadata = FileMetadata {
+            transfer_id: [1u8; 32],
+            file_name: "file.txt".to_string(),
+            file_size: 10,
+            chunk_size: 1024,
+            total_chunks: 1,
+            root_hash: [0u8; 32],
+        };
+        assert!(!is_manifest_segment(&metadata.serialize()));
+    }
+
+    #[test]
+    fn test_staging_commit() {
+        let source = sample_tree();
+        let (manifest, files) =
+            TreeManifest::from_directory([5u8; 32], source.path(), 1024).unwrap();
+        let download = tempfile::tempdir().unwrap();
+        let staging = TreeStaging::new(
+            &download.path().join(".wraith-quarantine"),
+            Arc::new(manifest),
+            download.path().join("tree"),
+        )
+        .unwrap();
+
+        for file in &files {
+            std::fs::copy(&file.source, staging.entry_path(file.entry_index)).unwrap();
+        }
+        let root = staging.commit().unwrap();
+
+        assert_eq!(root, download.path().join("tree"));
+        assert_eq!(
+            std::fs::read(root.join("src/lib.rs")).unwrap(),
+            vec![7u8; 3000]
+        );
+        assert_eq!(std::fs::read(root.join(".gitignore")).unwrap(), b"target\n");
+        assert!(root.join("src/nested/empty.txt").is_file());
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::read(root.join("src/readme")).unwrap(),
+            b"hello tree"
+        );
+        assert!(!staging.staging_dir.exists());
+    }
+}
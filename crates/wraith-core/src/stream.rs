@@ -434,6 +434,47 @@ impl StreamFull {
         self.recv_buffer.pop_front()
     }
 
+    /// Buffer data received from the peer
+    ///
+    /// # Errors
+    ///
+    /// Returns `SessionError::InvalidState` if the stream cannot receive or
+    /// the data exceeds the receive window.
+    pub fn receive(&mut self, data: Vec<u8>) -> Result<(), SessionError> {
+        if !matches!(self.state, StreamState::Open | StreamState::HalfClosedLocal)
+            || self.fin_received
+        {
+            return Err(SessionError::InvalidState);
+        }
+
+        self.consume_recv_window(data.len() as u64)?;
+        self.recv_buffer.push_back(data);
+        Ok(())
+    }
+
+    /// Take up to `max_len` bytes of buffered send data
+    ///
+    /// The amount is limited by the send window, which is consumed. Returns
+    /// `None` if nothing is buffered or the window is exhausted.
+    pub fn take_send_data(&mut self, max_len: usize) -> Option<Vec<u8>> {
+        let limit = max_len.min(usize::try_from(self.send_window).unwrap_or(usize::MAX));
+        if limit == 0 {
+            return None;
+        }
+
+        let front = self.send_buffer.front_mut()?;
+        let data = if front.len() > limit {
+            let rest = front.split_off(limit);
+            std::mem::replace(front, rest)
+        } else {
+            self.send_buffer.pop_front()?
+        };
+
+        self.send_window -= data.len() as u64;
+        self.bytes_sent += data.len() as u64;
+        Some(data)
+    }
+
     /// Peek at receive buffer without removing
     #[must_use]
     pub fn peek(&self) -> Option<&Vec<u8>> {
@@ -568,6 +609,40 @@ mod tests {
         assert!(!stream.is_fully_closed());
     }
 
+    #[test]
+    fn test_stream_receive_consumes_window() {
+        let mut stream = Stream::new(17, 8);
+        stream.open().unwrap();
+
+        stream.receive(vec![1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(stream.recv_window(), 3);
+        assert_eq!(stream.bytes_received(), 5);
+        assert!(stream.receive(vec![0; 4]).is_err());
+
+        assert_eq!(stream.read(), Some(vec![1, 2, 3, 4, 5]));
+        stream.mark_fin_received();
+        assert!(stream.receive(vec![6]).is_err());
+    }
+
+    #[test]
+    fn test_stream_take_send_data_respects_window() {
+        let mut stream = Stream::new(17, 6);
+        stream.open().unwrap();
+        stream.write(vec![1, 2, 3, 4]).unwrap();
+        stream.write(vec![5, 6, 7, 8]).unwrap();
+
+        assert_eq!(stream.take_send_data(3), Some(vec![1, 2, 3]));
+        assert_eq!(stream.take_send_data(16), Some(vec![4]));
+        assert_eq!(stream.take_send_data(16), Some(vec![5, 6]));
+        assert_eq!(stream.send_window(), 0);
+        assert_eq!(stream.take_send_data(16), None);
+
+        stream.update_send_window(10);
+        assert_eq!(stream.take_send_data(16), Some(vec![7, 8]));
+        assert_eq!(stream.bytes_sent(), 8);
+        assert!(!stream.has_data_to_send());
+    }
+
     #[test]
     fn test_stream_client_vs_server_initiated() {
         let client_stream = Stream::new(1, INITIAL_WINDOW);
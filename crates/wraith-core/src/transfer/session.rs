@@ -269,6 +269,18 @@ impl TransferSession {
         }
     }
 
+    /// Override the chunk count derived from the file size
+    ///
+    /// Used for multi-file (tree) transfers, where each file's final chunk may
+    /// be short and the chunk count is the sum of the per-file counts.
+    #[must_use]
+    pub fn with_chunk_count(mut self, total_chunks: u64) -> Self {
+        self.total_chunks = total_chunks;
+        self.chunk_bitmap = vec![0u64; total_chunks.div_ceil(64) as usize];
+        self.requestable_chunks = (0..total_chunks).collect();
+        self
+    }
+
     /// Start the transfer
     pub fn start(&mut self) {
         self.state = TransferState::Transferring;
@@ -629,6 +641,25 @@ mod tests {
         assert_eq!(session.state(), TransferState::Complete);
     }
 
+    #[test]
+    fn test_with_chunk_count() {
+        // Two 300-byte files in 256-byte chunks: 4 chunks, not ceil(600 / 256) = 3
+        let mut session =
+            TransferSession::new_receive([1u8; 32], PathBuf::from("/tmp/tree"), 600, 256)
+                .with_chunk_count(4);
+        session.start();
+
+        assert_eq!(session.total_chunks, 4);
+        assert_eq!(session.missing_count(), 4);
+        for (index, len) in [(0, 256), (1, 44), (2, 256)] {
+            session.mark_chunk_transferred(index, len);
+        }
+        assert!(!session.is_complete());
+        session.mark_chunk_transferred(3, 44);
+        assert!(session.is_complete());
+        assert_eq!(session.progress(), 1.0);
+    }
+
     #[test]
     fn test_missing_chunks() {
         let mut session = TransferSession::new_receive(
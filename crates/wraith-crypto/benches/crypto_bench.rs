@@ -417,6 +417,7 @@ fn bench_message_header_serialize(c: &mut Criterion) {
         dh_public,
         prev_chain_length: 100,
         message_number: 42,
+        #[cfg(feature = "key-commitment")]
         key_commitment: None,
     };
 
@@ -689,6 +690,63 @@ fn bench_packet_ratchet_key_for_packet(c: &mut Criterion) {
     });
 }
 
+/// Session sealing with static traffic keys vs per-packet ratchet keys
+fn bench_session_packet_ratchet(c: &mut Criterion) {
+    use wraith_crypto::aead::SessionCrypto;
+
+    let mut group = c.benchmark_group("session_packet_ratchet");
+
+    for size in [64, 1200, 8192] {
+        let plaintext = vec![0xAA; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for ratcheted in [false, true] {
+            let mode = if ratcheted { "ratcheted" } else { "static" };
+            // Fresh sessions per batch keep the counters clear of the rekey limit
+            let pair = || {
+                let alice = SessionCrypto::new([0x42u8; 32], [0x43u8; 32], &[0x44u8; 32]);
+                let bob = SessionCrypto::new([0x43u8; 32], [0x42u8; 32], &[0x44u8; 32]);
+                if ratcheted {
+                    (alice.with_packet_ratchet(), bob.with_packet_ratchet())
+                } else {
+                    (alice, bob)
+                }
+            };
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("{mode}_encrypt"), size),
+                &size,
+                |b, _| {
+                    b.iter_batched_ref(
+                        pair,
+                        |(alice, _)| alice.encrypt_stream(16, black_box(&plaintext), b""),
+                        criterion::BatchSize::SmallInput,
+                    )
+                },
+            );
+
+            group.bench_with_input(
+                BenchmarkId::new(format!("{mode}_roundtrip"), size),
+                &size,
+                |b, _| {
+                    b.iter_batched_ref(
+                        pair,
+                        |(alice, bob)| {
+                            let ciphertext = alice
+                                .encrypt_stream(16, black_box(&plaintext), b"")
+                                .unwrap();
+                            bob.decrypt_with_counter(0, black_box(&ciphertext), b"")
+                        },
+                        criterion::BatchSize::SmallInput,
+                    )
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
 // ============================================================================
 // KDF v2 Benchmarks
 // ============================================================================
@@ -817,6 +875,7 @@ criterion_group!(
     bench_packet_ratchet_next_send_key,
     bench_packet_ratchet_1000_sequential,
     bench_packet_ratchet_key_for_packet,
+    bench_session_packet_ratchet,
 );
 
 criterion_group!(kdf_v2_benches, bench_kdf_v2,);
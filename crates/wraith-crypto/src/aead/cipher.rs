@@ -26,6 +26,19 @@ pub const NONCE_SIZE: usize = 24;
 /// AEAD key size (32 bytes / 256 bits).
 pub const KEY_SIZE: usize = 32;
 
+/// Compute the 16-byte key commitment shared by every AEAD backend.
+///
+/// `BLAKE3(key || "wraith-key-commitment")[0..16]`
+pub(crate) fn key_commitment(key: &[u8; KEY_SIZE]) -> [u8; 16] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(key);
+    hasher.update(b"wraith-key-commitment");
+    let hash = hasher.finalize();
+    let mut commitment = [0u8; 16];
+    commitment.copy_from_slice(&hash.as_bytes()[..16]);
+    commitment
+}
+
 /// XChaCha20-Poly1305 nonce (24 bytes).
 ///
 /// The extended 192-bit nonce allows safe random nonce generation
@@ -181,13 +194,7 @@ impl AeadKey {
     /// function with constant-time properties when used with fixed-length inputs.
     #[must_use]
     pub fn commitment(&self) -> [u8; 16] {
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(&self.0);
-        hasher.update(b"wraith-key-commitment");
-        let hash = hasher.finalize();
-        let mut commitment = [0u8; 16];
-        commitment.copy_from_slice(&hash.as_bytes()[..16]);
-        commitment
+        key_commitment(&self.0)
     }
 
     /// Verify key commitment in constant time.
@@ -0,0 +1,317 @@
+//! AES-256-GCM AEAD encryption (crypto Suite B).
+//!
+//! Provides the same key API as [`AeadKey`](super::AeadKey) on top of
+//! AES-256-GCM:
+//! - 256-bit keys
+//! - 96-bit nonces (counter-based only; never generate these at random)
+//! - 128-bit authentication tags
+//! - The same BLAKE3 key commitment as the XChaCha20-Poly1305 backend
+//!
+//! The `aes` crate selects AES-NI/CLMUL (x86) or the ARMv8 crypto
+//! extensions at runtime and falls back to a constant-time software
+//! implementation elsewhere. [`hardware_accelerated`] reports which one is in
+//! use so callers can prefer Suite B only where it is actually faster.
+
+use super::cipher::{KEY_SIZE, TAG_SIZE, Tag, key_commitment};
+use crate::CryptoError;
+use aes_gcm::{
+    Aes256Gcm,
+    aead::{Aead, AeadInPlace, KeyInit, Payload},
+};
+use alloc::vec::Vec;
+use rand_core::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+use zeroize::ZeroizeOnDrop;
+
+/// AES-GCM nonce size (12 bytes / 96 bits).
+pub const GCM_NONCE_SIZE: usize = 12;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(aes_hw, "aes", "pclmulqdq");
+
+#[cfg(target_arch = "aarch64")]
+cpufeatures::new!(aes_hw, "aes");
+
+/// Check whether this CPU accelerates AES-GCM in hardware.
+///
+/// True with AES-NI and CLMUL on x86/x86-64, or the ARMv8 AES extension
+/// on aarch64. The result is detected once and cached.
+#[must_use]
+pub fn hardware_accelerated() -> bool {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    {
+        aes_hw::get()
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        false
+    }
+}
+
+/// AES-GCM nonce (12 bytes).
+///
+/// With only 96 bits, random nonces collide after about 2^48 messages per
+/// key, so nonces must come from a counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GcmNonce([u8; GCM_NONCE_SIZE]);
+
+impl GcmNonce {
+    /// Create a nonce from raw bytes.
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; GCM_NONCE_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    /// Create a nonce from a counter value.
+    ///
+    /// The counter is placed in the first 8 bytes (little-endian),
+    /// followed by a 4-byte session salt.
+    #[must_use]
+    pub fn from_counter(counter: u64, salt: &[u8; 4]) -> Self {
+        let mut bytes = [0u8; GCM_NONCE_SIZE];
+        bytes[..8].copy_from_slice(&counter.to_le_bytes());
+        bytes[8..].copy_from_slice(salt);
+        Self(bytes)
+    }
+
+    /// Get raw bytes.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; GCM_NONCE_SIZE] {
+        &self.0
+    }
+
+    fn as_generic(&self) -> &aes_gcm::Nonce<aes_gcm::aead::consts::U12> {
+        aes_gcm::Nonce::from_slice(&self.0)
+    }
+}
+
+/// AES-256-GCM encryption key (32 bytes).
+///
+/// Key is zeroized on drop.
+#[derive(Clone, ZeroizeOnDrop)]
+pub struct Aes256GcmKey([u8; KEY_SIZE]);
+
+impl Aes256GcmKey {
+    /// Create a key from raw bytes.
+    #[must_use]
+    pub fn new(bytes: [u8; KEY_SIZE]) -> Self {
+        Self(bytes)
+    }
+
+    /// Create from slice.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::InvalidKeyLength` if slice length is not 32 bytes.
+    pub fn from_slice(slice: &[u8]) -> Result<Self, CryptoError> {
+        if slice.len() != KEY_SIZE {
+            return Err(CryptoError::InvalidKeyLength {
+                expected: KEY_SIZE,
+                actual: slice.len(),
+            });
+        }
+        let mut bytes = [0u8; KEY_SIZE];
+        bytes.copy_from_slice(slice);
+        Ok(Self(bytes))
+    }
+
+    /// Generate a random key.
+    #[must_use]
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; KEY_SIZE];
+        rng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    /// Get raw key bytes.
+    ///
+    /// # Security
+    ///
+    /// Handle with extreme care - this exposes the raw key material.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; KEY_SIZE] {
+        &self.0
+    }
+
+    /// Compute key commitment for key-committing AEAD.
+    ///
+    /// Identical to [`AeadKey::commitment`](super::AeadKey::commitment).
+    /// GCM on its own is not key-committing, so session encryption binds
+    /// this commitment into the associated data.
+    #[must_use]
+    pub fn commitment(&self) -> [u8; 16] {
+        key_commitment(&self.0)
+    }
+
+    /// Verify key commitment in constant time.
+    #[must_use]
+    pub fn verify_commitment(&self, commitment: &[u8; 16]) -> bool {
+        self.commitment().ct_eq(commitment).into()
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new((&self.0).into())
+    }
+
+    /// Encrypt plaintext with associated data.
+    ///
+    /// Returns ciphertext with appended authentication tag (`plaintext.len()` + 16 bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::EncryptionFailed` if AEAD encryption fails.
+    pub fn encrypt(
+        &self,
+        nonce: &GcmNonce,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        self.cipher()
+            .encrypt(
+                nonce.as_generic(),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| CryptoError::EncryptionFailed)
+    }
+
+    /// Decrypt ciphertext with associated data.
+    ///
+    /// Input must include the authentication tag at the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::DecryptionFailed` on authentication failure.
+    pub fn decrypt(
+        &self,
+        nonce: &GcmNonce,
+        ciphertext_and_tag: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext_and_tag.len() < TAG_SIZE {
+            return Err(CryptoError::DecryptionFailed);
+        }
+
+        self.cipher()
+            .decrypt(
+                nonce.as_generic(),
+                Payload {
+                    msg: ciphertext_and_tag,
+                    aad,
+                },
+            )
+            .map_err(|_| CryptoError::DecryptionFailed)
+    }
+
+    /// Encrypt in-place, returning the authentication tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::EncryptionFailed` if AEAD encryption fails.
+    pub fn encrypt_in_place(
+        &self,
+        nonce: &GcmNonce,
+        buffer: &mut [u8],
+        aad: &[u8],
+    ) -> Result<Tag, CryptoError> {
+        let tag = self
+            .cipher()
+            .encrypt_in_place_detached(nonce.as_generic(), aad, buffer)
+            .map_err(|_| CryptoError::EncryptionFailed)?;
+
+        let mut tag_bytes = [0u8; TAG_SIZE];
+        tag_bytes.copy_from_slice(&tag);
+        Ok(Tag::from_bytes(tag_bytes))
+    }
+
+    /// Decrypt in-place, verifying the authentication tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::DecryptionFailed` on authentication failure.
+    pub fn decrypt_in_place(
+        &self,
+        nonce: &GcmNonce,
+        buffer: &mut [u8],
+        tag: &Tag,
+        aad: &[u8],
+    ) -> Result<(), CryptoError> {
+        self.cipher()
+            .decrypt_in_place_detached(
+                nonce.as_generic(),
+                aad,
+                buffer,
+                aes_gcm::Tag::from_slice(tag.as_bytes()),
+            )
+            .map_err(|_| CryptoError::DecryptionFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aead::AeadKey;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_gcm_roundtrip() {
+        let key = Aes256GcmKey::generate(&mut OsRng);
+        let nonce = GcmNonce::from_counter(7, &[1, 2, 3, 4]);
+
+        let ciphertext = key.encrypt(&nonce, b"secret message", b"header").unwrap();
+        assert_eq!(ciphertext.len(), b"secret message".len() + TAG_SIZE);
+        assert_eq!(
+            key.decrypt(&nonce, &ciphertext, b"header").unwrap(),
+            b"secret message"
+        );
+    }
+
+    #[test]
+    fn test_gcm_rejects_tampering() {
+        let key = Aes256GcmKey::generate(&mut OsRng);
+        let nonce = GcmNonce::from_counter(0, &[0; 4]);
+        let mut ciphertext = key.encrypt(&nonce, b"secret", b"aad").unwrap();
+
+        assert!(key.decrypt(&nonce, &ciphertext, b"other").is_err());
+        assert!(
+            key.decrypt(&GcmNonce::from_counter(1, &[0; 4]), &ciphertext, b"aad")
+                .is_err()
+        );
+        assert!(key.decrypt(&nonce, &ciphertext[..8], b"aad").is_err());
+        ciphertext[0] ^= 1;
+        assert!(key.decrypt(&nonce, &ciphertext, b"aad").is_err());
+    }
+
+    #[test]
+    fn test_gcm_in_place() {
+        let key = Aes256GcmKey::generate(&mut OsRng);
+        let nonce = GcmNonce::from_counter(3, &[9; 4]);
+        let mut buffer = b"hello world".to_vec();
+
+        let tag = key.encrypt_in_place(&nonce, &mut buffer, b"").unwrap();
+        assert_ne!(buffer, b"hello world");
+        key.decrypt_in_place(&nonce, &mut buffer, &tag, b"")
+            .unwrap();
+        assert_eq!(buffer, b"hello world");
+    }
+
+    #[test]
+    fn test_gcm_nonce_layout() {
+        let nonce = GcmNonce::from_counter(0x0102, &[0xAA, 0xBB, 0xCC, 0xDD]);
+        assert_eq!(
+            nonce.as_bytes(),
+            &[0x02, 0x01, 0, 0, 0, 0, 0, 0, 0xAA, 0xBB, 0xCC, 0xDD]
+        );
+    }
+
+    #[test]
+    fn test_commitment_matches_xchacha_backend() {
+        let bytes = [0x42u8; KEY_SIZE];
+        let key = Aes256GcmKey::new(bytes);
+        assert_eq!(key.commitment(), AeadKey::new(bytes).commitment());
+        assert!(key.verify_commitment(&key.commitment()));
+        assert!(!key.verify_commitment(&[0u8; 16]));
+    }
+}
@@ -1,7 +1,8 @@
-//! `XChaCha20-Poly1305` AEAD encryption.
+//! `XChaCha20-Poly1305` and AES-256-GCM AEAD encryption.
 //!
 //! Provides authenticated encryption with associated data (AEAD) using
-//! `XChaCha20-Poly1305`. Features include:
+//! `XChaCha20-Poly1305` (Suites A, C and D) or AES-256-GCM (Suite B).
+//! Features include:
 //! - 256-bit keys
 //! - 192-bit nonces (extended nonce for safe random generation)
 //! - 128-bit authentication tags
@@ -17,6 +18,7 @@
 //! ## Module Organization
 //!
 //! - [`cipher`] - Core AEAD types (Nonce, Tag, AeadKey, AeadCipher)
+//! - [`gcm`] - AES-256-GCM backend (Aes256GcmKey, GcmNonce)
 //! - [`replay`] - Replay protection with sliding window
 //! - [`session`] - Session encryption state (SessionCrypto, BufferPool)
 //!
@@ -33,10 +35,12 @@
 //! ```
 
 pub mod cipher;
+pub mod gcm;
 pub mod replay;
 pub mod session;
 
 // Re-export all public types for backward compatibility
 pub use cipher::{AeadCipher, AeadKey, KEY_SIZE, NONCE_SIZE, Nonce, TAG_SIZE, Tag};
+pub use gcm::{Aes256GcmKey, GCM_NONCE_SIZE, GcmNonce};
 pub use replay::ReplayProtection;
-pub use session::{BufferPool, SessionCrypto};
+pub use session::{BufferPool, STREAM_ID_SIZE, SessionCrypto};
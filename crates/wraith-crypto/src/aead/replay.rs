@@ -101,6 +101,24 @@ impl ReplayProtection {
         true
     }
 
+    /// Check if a sequence number would be accepted without recording it.
+    ///
+    /// Lets callers reject replays cheaply before authenticating a packet,
+    /// and only call [`Self::check_and_update`] once it has verified, so
+    /// forged packets cannot move the window.
+    #[must_use]
+    pub fn check(&self, seq: u64) -> bool {
+        if seq + Self::WINDOW_SIZE <= self.max_seq {
+            return false;
+        }
+        if seq > self.max_seq {
+            return true;
+        }
+        let bit_position = self.max_seq - seq;
+        let word_index = (bit_position / 64) as usize;
+        self.window[word_index] & (1u64 << (bit_position % 64)) == 0
+    }
+
     /// Get the maximum sequence number seen
     #[must_use]
     pub fn max_seq(&self) -> u64 {
@@ -188,6 +206,19 @@ mod tests {
         assert!(!rp.check_and_update(8)); // Replay
     }
 
+    #[test]
+    fn test_replay_protection_check_does_not_update() {
+        let mut rp = ReplayProtection::new();
+
+        assert!(rp.check(5000));
+        assert_eq!(rp.max_seq(), 0);
+        assert!(rp.check_and_update(10));
+        assert!(rp.check(9));
+        assert!(!rp.check(10));
+        assert!(rp.check_and_update(9));
+        assert!(!rp.check(9));
+    }
+
     #[test]
     fn test_replay_protection_window_boundary() {
         let mut rp = ReplayProtection::new();
@@ -1,7 +1,12 @@
 //! Session encryption state for post-handshake communication.
 //!
 //! Provides bidirectional encrypted communication with automatic nonce
-//! management, replay protection, and key-committing AEAD.
+//! management, replay protection, and key-committing AEAD. The AEAD is
+//! chosen by the negotiated suite: XChaCha20-Poly1305 or AES-256-GCM
+//! (Suite B); both use the same counter nonces, replay window and key
+//! commitment. Sessions can additionally seal every packet under its own
+//! key from a per-direction [`PacketRatchet`] for per-packet forward
+//! secrecy.
 //!
 //! # Lint Note
 //!
@@ -12,10 +17,107 @@
 #![allow(unused_assignments)]
 
 use super::cipher::{AeadKey, Nonce};
+use super::gcm::{Aes256GcmKey, GcmNonce};
 use super::replay::ReplayProtection;
 use crate::CryptoError;
+use crate::hash::{hkdf, hkdf_expand};
+use crate::kdf::{derive_stream_key, labels};
+use crate::packet_ratchet::PacketRatchet;
+use crate::suite::AeadAlgorithm;
 use alloc::vec::Vec;
-use zeroize::ZeroizeOnDrop;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size of the masked stream ID that prefixes packets sealed under the
+/// packet ratchet.
+pub const STREAM_ID_SIZE: usize = 4;
+
+/// HKDF info label for deriving a rekeyed generation
+const REKEY_INFO: &[u8] = b"wraith-session-rekey-v1";
+
+/// HKDF info label for the per-generation rekey secret
+const REKEY_SECRET_INFO: &[u8] = b"wraith-session-rekey-secret-v1";
+
+fn derive_rekey_secret(chain_key: &[u8; 32]) -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    hkdf(&[], chain_key, REKEY_SECRET_INFO, &mut secret);
+    secret
+}
+
+/// One direction's key for the negotiated AEAD.
+#[derive(ZeroizeOnDrop)]
+enum DirectionKey {
+    XChaCha20Poly1305(AeadKey),
+    Aes256Gcm(Aes256GcmKey),
+}
+
+impl DirectionKey {
+    fn new(aead: AeadAlgorithm, key: [u8; 32]) -> Self {
+        match aead {
+            AeadAlgorithm::XChaCha20Poly1305 => Self::XChaCha20Poly1305(AeadKey::new(key)),
+            AeadAlgorithm::Aes256Gcm => Self::Aes256Gcm(Aes256GcmKey::new(key)),
+        }
+    }
+
+    fn algorithm(&self) -> AeadAlgorithm {
+        match self {
+            Self::XChaCha20Poly1305(_) => AeadAlgorithm::XChaCha20Poly1305,
+            Self::Aes256Gcm(_) => AeadAlgorithm::Aes256Gcm,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8; 32] {
+        match self {
+            Self::XChaCha20Poly1305(key) => key.as_bytes(),
+            Self::Aes256Gcm(key) => key.as_bytes(),
+        }
+    }
+
+    fn commitment(&self) -> [u8; 16] {
+        match self {
+            Self::XChaCha20Poly1305(key) => key.commitment(),
+            Self::Aes256Gcm(key) => key.commitment(),
+        }
+    }
+
+    /// Encrypt under the counter nonce, with `aad` already carrying the commitment.
+    fn seal(
+        &self,
+        counter: u64,
+        salt: &[u8; 16],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            Self::XChaCha20Poly1305(key) => {
+                key.encrypt(&Nonce::from_counter(counter, salt), plaintext, aad)
+            }
+            Self::Aes256Gcm(key) => key.encrypt(&gcm_nonce(counter, salt), plaintext, aad),
+        }
+    }
+
+    /// Decrypt under the counter nonce, with `aad` already carrying the commitment.
+    fn open(
+        &self,
+        counter: u64,
+        salt: &[u8; 16],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            Self::XChaCha20Poly1305(key) => {
+                key.decrypt(&Nonce::from_counter(counter, salt), ciphertext, aad)
+            }
+            Self::Aes256Gcm(key) => key.decrypt(&gcm_nonce(counter, salt), ciphertext, aad),
+        }
+    }
+}
+
+/// AES-GCM nonces take the first 4 bytes of the session salt after the counter.
+fn gcm_nonce(counter: u64, salt: &[u8; 16]) -> GcmNonce {
+    let mut short_salt = [0u8; 4];
+    short_salt.copy_from_slice(&salt[..4]);
+    GcmNonce::from_counter(counter, &short_salt)
+}
 
 /// Reusable buffer pool to avoid allocation in hot path.
 ///
@@ -72,15 +174,58 @@ impl BufferPool {
     }
 }
 
+/// Traffic keys for both directions of a key generation.
+#[derive(ZeroizeOnDrop)]
+enum TrafficKeys {
+    /// One key per direction, used for every packet
+    Static {
+        send: DirectionKey,
+        recv: DirectionKey,
+    },
+    /// A fresh key per packet from a BLAKE3 ratchet per direction
+    Ratcheted {
+        #[zeroize(skip)]
+        aead: AeadAlgorithm,
+        send: PacketRatchet,
+        recv: PacketRatchet,
+    },
+}
+
+/// Seed a direction's packet ratchet from its traffic key.
+fn ratchet_chain_key(traffic_key: &[u8; 32]) -> [u8; 32] {
+    let mut chain_key = [0u8; 32];
+    hkdf_expand(traffic_key, labels::RATCHET_CHAIN, &mut chain_key);
+    chain_key
+}
+
+/// Per-packet mask hiding the stream ID on the wire.
+fn stream_id_mask(message_key: &[u8; 32]) -> u32 {
+    let mut mask = [0u8; STREAM_ID_SIZE];
+    hkdf_expand(message_key, labels::STREAM_ID_MASK, &mut mask);
+    u32::from_le_bytes(mask)
+}
+
+/// AEAD key for one packet of one stream.
+fn stream_packet_key(aead: AeadAlgorithm, message_key: &[u8; 32], stream_id: u32) -> DirectionKey {
+    let mut subkey = derive_stream_key(message_key, stream_id);
+    let key = DirectionKey::new(aead, subkey);
+    subkey.zeroize();
+    key
+}
+
 /// Session encryption state for post-handshake communication.
 ///
 /// Manages nonce counters and keys for bidirectional encrypted communication.
+///
+/// With [`Self::with_packet_ratchet`] every packet is sealed under its own
+/// key: each direction runs a [`PacketRatchet`] whose message keys are
+/// split into per-stream subkeys, and the traffic keys the ratchets were
+/// seeded from are erased. Packets then carry a masked
+/// [`STREAM_ID_SIZE`]-byte stream ID before the ciphertext.
 #[derive(ZeroizeOnDrop)]
 pub struct SessionCrypto {
-    /// Key for sending messages
-    send_key: AeadKey,
-    /// Key for receiving messages
-    recv_key: AeadKey,
+    /// Keys for sending and receiving messages
+    keys: TrafficKeys,
     /// Nonce salt (derived from session)
     #[zeroize(skip)]
     nonce_salt: [u8; 16],
@@ -96,25 +241,172 @@ pub struct SessionCrypto {
     /// Replay protection for received packets
     #[zeroize(skip)]
     replay_protection: ReplayProtection,
+    /// Secret the next key generation is derived from on rekey
+    rekey_secret: [u8; 32],
 }
 
 impl SessionCrypto {
-    /// Create a new session crypto state from session keys.
+    /// Create a new XChaCha20-Poly1305 session crypto state from session keys.
     #[must_use]
     pub fn new(send_key: [u8; 32], recv_key: [u8; 32], chain_key: &[u8; 32]) -> Self {
+        Self::with_aead(
+            AeadAlgorithm::XChaCha20Poly1305,
+            send_key,
+            recv_key,
+            chain_key,
+        )
+    }
+
+    /// Create a session crypto state using the given AEAD algorithm.
+    ///
+    /// Pass the negotiated suite's [`CryptoSuite::aead_algorithm`]; both
+    /// peers must use the same algorithm.
+    ///
+    /// [`CryptoSuite::aead_algorithm`]: crate::suite::CryptoSuite::aead_algorithm
+    #[must_use]
+    pub fn with_aead(
+        aead: AeadAlgorithm,
+        send_key: [u8; 32],
+        recv_key: [u8; 32],
+        chain_key: &[u8; 32],
+    ) -> Self {
         // Derive nonce salt from chain key
         // SECURITY: Buffer pre-allocation, immediately overwritten with first 16 bytes of chain_key
         let mut nonce_salt = [0u8; 16];
         nonce_salt.copy_from_slice(&chain_key[..16]);
 
         Self {
-            send_key: AeadKey::new(send_key),
-            recv_key: AeadKey::new(recv_key),
+            keys: TrafficKeys::Static {
+                send: DirectionKey::new(aead, send_key),
+                recv: DirectionKey::new(aead, recv_key),
+            },
             nonce_salt,
             send_counter: 0,
             recv_counter: 0,
             max_counter: 1_000_000, // Rekey after 1M messages
             replay_protection: ReplayProtection::new(),
+            rekey_secret: derive_rekey_secret(chain_key),
+        }
+    }
+
+    /// Switch to per-packet keys from a packet ratchet per direction.
+    ///
+    /// Both peers must enable the ratchet before exchanging packets. The
+    /// static traffic keys are erased, so compromising the state later
+    /// does not decrypt packets sealed before it.
+    #[must_use]
+    pub fn with_packet_ratchet(mut self) -> Self {
+        if let TrafficKeys::Static { send, recv } = &self.keys {
+            let mut send_chain = ratchet_chain_key(send.as_bytes());
+            let mut recv_chain = ratchet_chain_key(recv.as_bytes());
+            self.keys = TrafficKeys::Ratcheted {
+                aead: send.algorithm(),
+                send: PacketRatchet::new(send_chain),
+                recv: PacketRatchet::new(recv_chain),
+            };
+            send_chain.zeroize();
+            recv_chain.zeroize();
+        }
+        self
+    }
+
+    /// Whether packets are sealed under per-packet ratchet keys.
+    #[must_use]
+    pub fn packet_ratchet_enabled(&self) -> bool {
+        matches!(self.keys, TrafficKeys::Ratcheted { .. })
+    }
+
+    /// AEAD algorithm protecting this session.
+    #[must_use]
+    pub fn aead_algorithm(&self) -> AeadAlgorithm {
+        match &self.keys {
+            TrafficKeys::Static { send, .. } => send.algorithm(),
+            TrafficKeys::Ratcheted { aead, .. } => *aead,
+        }
+    }
+
+    /// Seal packet `counter` of `stream_id`, using `committed_aad` as scratch.
+    ///
+    /// Static keys ignore the stream ID. Ratcheted keys take the next
+    /// send key, which must belong to `counter`.
+    fn seal(
+        &mut self,
+        counter: u64,
+        stream_id: u32,
+        plaintext: &[u8],
+        aad: &[u8],
+        committed_aad: &mut Vec<u8>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        match &mut self.keys {
+            TrafficKeys::Static { send, .. } => {
+                // Prepend key commitment to AAD for key-committing AEAD
+                committed_aad.extend_from_slice(&send.commitment());
+                committed_aad.extend_from_slice(aad);
+                send.seal(counter, &self.nonce_salt, plaintext, committed_aad)
+            }
+            TrafficKeys::Ratcheted { aead, send, .. } => {
+                if send.packet_number() != counter {
+                    return Err(CryptoError::InvalidState);
+                }
+                let (_, mut message_key) = send.next_send_key();
+                let key = stream_packet_key(*aead, &message_key, stream_id);
+                let masked_stream_id = stream_id ^ stream_id_mask(&message_key);
+                message_key.zeroize();
+
+                committed_aad.extend_from_slice(&key.commitment());
+                committed_aad.extend_from_slice(aad);
+                let ciphertext = key.seal(counter, &self.nonce_salt, plaintext, committed_aad)?;
+
+                let mut packet = Vec::with_capacity(STREAM_ID_SIZE + ciphertext.len());
+                packet.extend_from_slice(&masked_stream_id.to_le_bytes());
+                packet.extend_from_slice(&ciphertext);
+                Ok(packet)
+            }
+        }
+    }
+
+    /// Open packet `counter`, using `committed_aad` as scratch.
+    ///
+    /// A ratcheted packet key is only consumed once the packet
+    /// authenticates, so forged packets cannot burn genuine keys.
+    fn open(
+        &mut self,
+        counter: u64,
+        ciphertext: &[u8],
+        aad: &[u8],
+        committed_aad: &mut Vec<u8>,
+    ) -> Result<Vec<u8>, CryptoError> {
+        match &mut self.keys {
+            TrafficKeys::Static { recv, .. } => {
+                // Prepend key commitment to AAD for key-committing AEAD
+                committed_aad.extend_from_slice(&recv.commitment());
+                committed_aad.extend_from_slice(aad);
+                recv.open(counter, &self.nonce_salt, ciphertext, committed_aad)
+            }
+            TrafficKeys::Ratcheted { aead, recv, .. } => {
+                if ciphertext.len() < STREAM_ID_SIZE {
+                    return Err(CryptoError::DecryptionFailed);
+                }
+                let (masked_stream_id, ciphertext) = ciphertext.split_at(STREAM_ID_SIZE);
+                let mut message_key = recv
+                    .peek_key(counter)
+                    .map_err(|_| CryptoError::DecryptionFailed)?;
+                let stream_id = u32::from_le_bytes(
+                    masked_stream_id
+                        .try_into()
+                        .expect("split at stream ID size"),
+                ) ^ stream_id_mask(&message_key);
+                let key = stream_packet_key(*aead, &message_key, stream_id);
+                message_key.zeroize();
+
+                committed_aad.extend_from_slice(&key.commitment());
+                committed_aad.extend_from_slice(aad);
+                let plaintext = key.open(counter, &self.nonce_salt, ciphertext, committed_aad)?;
+
+                let mut consumed = recv.key_for_packet(counter)?;
+                consumed.zeroize();
+                Ok(plaintext)
+            }
         }
     }
 
@@ -129,20 +421,34 @@ impl SessionCrypto {
     /// Returns `CryptoError::NonceOverflow` if send counter is exhausted.
     /// Returns `CryptoError::EncryptionFailed` on AEAD encryption failure.
     pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.encrypt_stream(0, plaintext, aad)
+    }
+
+    /// Encrypt a message belonging to a stream.
+    ///
+    /// Same as [`Self::encrypt`], but with the packet ratchet enabled the
+    /// packet is sealed under the subkey of `stream_id`. Without the
+    /// ratchet the stream ID is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CryptoError::NonceOverflow` if send counter is exhausted.
+    /// Returns `CryptoError::EncryptionFailed` on AEAD encryption failure.
+    pub fn encrypt_stream(
+        &mut self,
+        stream_id: u32,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, CryptoError> {
         if self.send_counter >= self.max_counter {
             return Err(CryptoError::NonceOverflow);
         }
 
-        let nonce = Nonce::from_counter(self.send_counter, &self.nonce_salt);
+        let counter = self.send_counter;
         self.send_counter += 1;
 
-        // Prepend key commitment to AAD for key-committing AEAD
-        let commitment = self.send_key.commitment();
-        let mut committed_aad = Vec::with_capacity(commitment.len() + aad.len());
-        committed_aad.extend_from_slice(&commitment);
-        committed_aad.extend_from_slice(aad);
-
-        self.send_key.encrypt(&nonce, plaintext, &committed_aad)
+        let mut committed_aad = Vec::with_capacity(16 + aad.len());
+        self.seal(counter, stream_id, plaintext, aad, &mut committed_aad)
     }
 
     /// Encrypt a message with explicit counter and key commitment.
@@ -153,6 +459,8 @@ impl SessionCrypto {
     ///
     /// # Errors
     ///
+    /// Returns `CryptoError::InvalidState` if the packet ratchet is enabled,
+    /// since ratcheted keys can only be used in order.
     /// Returns `CryptoError::EncryptionFailed` on AEAD encryption failure.
     pub fn encrypt_with_counter(
         &self,
@@ -160,15 +468,17 @@ impl SessionCrypto {
         plaintext: &[u8],
         aad: &[u8],
     ) -> Result<Vec<u8>, CryptoError> {
-        let nonce = Nonce::from_counter(counter, &self.nonce_salt);
+        let TrafficKeys::Static { send, .. } = &self.keys else {
+            return Err(CryptoError::InvalidState);
+        };
 
         // Prepend key commitment to AAD for key-committing AEAD
-        let commitment = self.send_key.commitment();
+        let commitment = send.commitment();
         let mut committed_aad = Vec::with_capacity(commitment.len() + aad.len());
         committed_aad.extend_from_slice(&commitment);
         committed_aad.extend_from_slice(aad);
 
-        self.send_key.encrypt(&nonce, plaintext, &committed_aad)
+        send.seal(counter, &self.nonce_salt, plaintext, &committed_aad)
     }
 
     /// Decrypt a message with key commitment verification.
@@ -186,21 +496,17 @@ impl SessionCrypto {
             return Err(CryptoError::NonceOverflow);
         }
 
-        let nonce = Nonce::from_counter(self.recv_counter, &self.nonce_salt);
+        let counter = self.recv_counter;
         self.recv_counter += 1;
 
-        // Prepend key commitment to AAD for key-committing AEAD
-        let commitment = self.recv_key.commitment();
-        let mut committed_aad = Vec::with_capacity(commitment.len() + aad.len());
-        committed_aad.extend_from_slice(&commitment);
-        committed_aad.extend_from_slice(aad);
-
-        self.recv_key.decrypt(&nonce, ciphertext, &committed_aad)
+        let mut committed_aad = Vec::with_capacity(16 + aad.len());
+        self.open(counter, ciphertext, aad, &mut committed_aad)
     }
 
     /// Decrypt a message with explicit counter and key commitment verification.
     ///
-    /// Does NOT automatically increment the counter.
+    /// The receive counter tracks the highest authenticated counter, so
+    /// [`Self::recv_counter`] and rekey checks stay meaningful.
     /// Checks replay protection - packets with duplicate or old sequence numbers are rejected.
     /// Verifies key commitment in AAD to prevent key-commitment attacks.
     ///
@@ -215,19 +521,18 @@ impl SessionCrypto {
         aad: &[u8],
     ) -> Result<Vec<u8>, CryptoError> {
         // Check replay protection first (before decryption to prevent DoS)
-        if !self.replay_protection.check_and_update(counter) {
+        if !self.replay_protection.check(counter) {
             return Err(CryptoError::ReplayDetected);
         }
 
-        let nonce = Nonce::from_counter(counter, &self.nonce_salt);
+        let mut committed_aad = Vec::with_capacity(16 + aad.len());
+        let plaintext = self.open(counter, ciphertext, aad, &mut committed_aad)?;
 
-        // Prepend key commitment to AAD for key-committing AEAD
-        let commitment = self.recv_key.commitment();
-        let mut committed_aad = Vec::with_capacity(commitment.len() + aad.len());
-        committed_aad.extend_from_slice(&commitment);
-        committed_aad.extend_from_slice(aad);
-
-        self.recv_key.decrypt(&nonce, ciphertext, &committed_aad)
+        // Record the counter only once the packet authenticated, so forged
+        // counters cannot advance the window past genuine packets
+        self.replay_protection.check_and_update(counter);
+        self.recv_counter = self.recv_counter.max(counter.saturating_add(1));
+        Ok(plaintext)
     }
 
     /// Get the current send counter.
@@ -249,13 +554,54 @@ impl SessionCrypto {
     }
 
     /// Update keys for a new session (ratchet).
+    ///
+    /// The session keeps its AEAD algorithm and packet ratchet mode.
     pub fn update_keys(&mut self, send_key: [u8; 32], recv_key: [u8; 32], chain_key: &[u8; 32]) {
-        self.send_key = AeadKey::new(send_key);
-        self.recv_key = AeadKey::new(recv_key);
-        self.nonce_salt.copy_from_slice(&chain_key[..16]);
-        self.send_counter = 0;
-        self.recv_counter = 0;
-        self.replay_protection.reset();
+        let mut next = Self::with_aead(self.aead_algorithm(), send_key, recv_key, chain_key);
+        if self.packet_ratchet_enabled() {
+            next = next.with_packet_ratchet();
+        }
+        next.max_counter = self.max_counter;
+        *self = next;
+    }
+
+    /// Derive the next key generation from an in-band rekey exchange.
+    ///
+    /// Both peers mix the Diffie-Hellman output of their fresh ephemeral keys
+    /// into this generation's rekey secret. The peer that started the
+    /// exchange passes `initiator = true`, which decides the key direction.
+    /// The returned state keeps this session's AEAD algorithm and packet
+    /// ratchet mode and starts with fresh counters and replay window;
+    /// dropping the old generation erases the secret it was derived from,
+    /// so compromising later keys does not expose earlier traffic.
+    #[must_use]
+    pub fn next_generation(&self, shared_secret: &[u8; 32], initiator: bool) -> Self {
+        let mut okm = [0u8; 96];
+        hkdf(&self.rekey_secret, shared_secret, REKEY_INFO, &mut okm);
+
+        let mut initiator_key = [0u8; 32];
+        let mut responder_key = [0u8; 32];
+        let mut chain_key = [0u8; 32];
+        initiator_key.copy_from_slice(&okm[..32]);
+        responder_key.copy_from_slice(&okm[32..64]);
+        chain_key.copy_from_slice(&okm[64..]);
+        okm.zeroize();
+
+        let aead = self.aead_algorithm();
+        let mut next = if initiator {
+            Self::with_aead(aead, initiator_key, responder_key, &chain_key)
+        } else {
+            Self::with_aead(aead, responder_key, initiator_key, &chain_key)
+        };
+        if self.packet_ratchet_enabled() {
+            next = next.with_packet_ratchet();
+        }
+        next.max_counter = self.max_counter;
+
+        initiator_key.zeroize();
+        responder_key.zeroize();
+        chain_key.zeroize();
+        next
     }
 
     /// Encrypt a message using a buffer from the pool.
@@ -278,17 +624,14 @@ impl SessionCrypto {
             return Err(CryptoError::NonceOverflow);
         }
 
-        let nonce = Nonce::from_counter(self.send_counter, &self.nonce_salt);
+        let counter = self.send_counter;
         self.send_counter += 1;
 
         // Get buffer from pool for AAD
-        let commitment = self.send_key.commitment();
         let mut committed_aad = pool.get();
-        committed_aad.extend_from_slice(&commitment);
-        committed_aad.extend_from_slice(aad);
 
         // Encrypt
-        let result = self.send_key.encrypt(&nonce, plaintext, &committed_aad);
+        let result = self.seal(counter, 0, plaintext, aad, &mut committed_aad);
 
         // Return buffer to pool
         pool.put(committed_aad);
@@ -316,17 +659,14 @@ impl SessionCrypto {
             return Err(CryptoError::NonceOverflow);
         }
 
-        let nonce = Nonce::from_counter(self.recv_counter, &self.nonce_salt);
+        let counter = self.recv_counter;
         self.recv_counter += 1;
 
         // Get buffer from pool for AAD
-        let commitment = self.recv_key.commitment();
         let mut committed_aad = pool.get();
-        committed_aad.extend_from_slice(&commitment);
-        committed_aad.extend_from_slice(aad);
 
         // Decrypt
-        let result = self.recv_key.decrypt(&nonce, ciphertext, &committed_aad);
+        let result = self.open(counter, ciphertext, aad, &mut committed_aad);
 
         // Return buffer to pool
         pool.put(committed_aad);
@@ -394,6 +734,22 @@ mod tests {
         assert!(bob.decrypt_with_counter(42, &ct, b"aad").is_err());
     }
 
+    #[test]
+    fn test_forged_counter_does_not_poison_replay_window() {
+        let alice = SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]);
+        let mut bob = SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]);
+
+        // A forged packet far ahead must not push genuine packets out of the window
+        assert!(
+            bob.decrypt_with_counter(1_000_000, &[0u8; 32], b"")
+                .is_err()
+        );
+
+        let ct = alice.encrypt_with_counter(7, b"genuine", b"").unwrap();
+        assert_eq!(bob.decrypt_with_counter(7, &ct, b"").unwrap(), b"genuine");
+        assert_eq!(bob.recv_counter(), 8);
+    }
+
     #[test]
     fn test_session_crypto_rekey() {
         let mut session = SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]);
@@ -415,6 +771,208 @@ mod tests {
         assert!(bob.decrypt(&ct2, b"").is_err());
     }
 
+    #[test]
+    fn test_next_generation_keys_match() {
+        let mut alice = SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]);
+        let mut bob = SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]);
+        let shared = [9u8; 32];
+
+        let mut alice_next = alice.next_generation(&shared, true);
+        let mut bob_next = bob.next_generation(&shared, false);
+
+        let ct = alice_next.encrypt(b"after rekey", b"").unwrap();
+        assert_eq!(bob_next.decrypt(&ct, b"").unwrap(), b"after rekey");
+        let ct = bob_next.encrypt(b"reply", b"").unwrap();
+        assert_eq!(alice_next.decrypt(&ct, b"").unwrap(), b"reply");
+
+        // The old generation cannot read the new one
+        let ct = alice_next.encrypt(b"secret", b"").unwrap();
+        assert!(bob.decrypt_with_counter(1, &ct, b"").is_err());
+        let ct = alice.encrypt(b"old", b"").unwrap();
+        assert!(bob_next.decrypt_with_counter(0, &ct, b"").is_err());
+    }
+
+    #[test]
+    fn test_next_generation_depends_on_shared_secret() {
+        let alice = SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]);
+        let bob = SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]);
+
+        let mut alice_next = alice.next_generation(&[9u8; 32], true);
+        let mut bob_next = bob.next_generation(&[8u8; 32], false);
+
+        let ct = alice_next.encrypt(b"mismatch", b"").unwrap();
+        assert!(bob_next.decrypt(&ct, b"").is_err());
+    }
+
+    fn aes_pair() -> (SessionCrypto, SessionCrypto) {
+        (
+            SessionCrypto::with_aead(AeadAlgorithm::Aes256Gcm, [1u8; 32], [2u8; 32], &[3u8; 32]),
+            SessionCrypto::with_aead(AeadAlgorithm::Aes256Gcm, [2u8; 32], [1u8; 32], &[3u8; 32]),
+        )
+    }
+
+    #[test]
+    fn test_aes_gcm_session_roundtrip_and_replay() {
+        let (mut alice, mut bob) = aes_pair();
+        assert_eq!(alice.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+
+        let ct = alice.encrypt(b"suite b", b"hdr").unwrap();
+        assert_eq!(bob.decrypt(&ct, b"hdr").unwrap(), b"suite b");
+
+        let ct = bob.encrypt_with_counter(5, b"out of order", b"").unwrap();
+        assert_eq!(
+            alice.decrypt_with_counter(5, &ct, b"").unwrap(),
+            b"out of order"
+        );
+        assert!(matches!(
+            alice.decrypt_with_counter(5, &ct, b""),
+            Err(CryptoError::ReplayDetected)
+        ));
+
+        let mut pool = BufferPool::new(256, 4);
+        let ct = alice.encrypt_with_pool(&mut pool, b"pooled", b"").unwrap();
+        assert_eq!(
+            bob.decrypt_with_pool(&mut pool, &ct, b"").unwrap(),
+            b"pooled"
+        );
+    }
+
+    #[test]
+    fn test_aead_algorithms_do_not_interoperate() {
+        let (mut alice, _) = aes_pair();
+        let mut chacha_bob = SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]);
+
+        let ct = alice.encrypt(b"mismatch", b"").unwrap();
+        assert!(chacha_bob.decrypt(&ct, b"").is_err());
+    }
+
+    #[test]
+    fn test_next_generation_keeps_aead() {
+        let (mut alice, mut bob) = aes_pair();
+        alice.update_keys([4u8; 32], [5u8; 32], &[6u8; 32]);
+        bob.update_keys([5u8; 32], [4u8; 32], &[6u8; 32]);
+        assert_eq!(alice.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+
+        let mut alice_next = alice.next_generation(&[9u8; 32], true);
+        let mut bob_next = bob.next_generation(&[9u8; 32], false);
+        assert_eq!(bob_next.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+
+        let ct = alice_next.encrypt(b"rekeyed", b"").unwrap();
+        assert_eq!(bob_next.decrypt(&ct, b"").unwrap(), b"rekeyed");
+    }
+
+    fn ratchet_pair(aead: AeadAlgorithm) -> (SessionCrypto, SessionCrypto) {
+        (
+            SessionCrypto::with_aead(aead, [1u8; 32], [2u8; 32], &[3u8; 32]).with_packet_ratchet(),
+            SessionCrypto::with_aead(aead, [2u8; 32], [1u8; 32], &[3u8; 32]).with_packet_ratchet(),
+        )
+    }
+
+    #[test]
+    fn test_packet_ratchet_roundtrip_out_of_order() {
+        for aead in [AeadAlgorithm::XChaCha20Poly1305, AeadAlgorithm::Aes256Gcm] {
+            let (mut alice, mut bob) = ratchet_pair(aead);
+            assert!(alice.packet_ratchet_enabled());
+            assert_eq!(alice.aead_algorithm(), aead);
+
+            let packets: Vec<_> = (0u32..4)
+                .map(|i| alice.encrypt_stream(i + 16, b"ratcheted", b"hdr").unwrap())
+                .collect();
+            assert_eq!(packets[0].len(), STREAM_ID_SIZE + 9 + 16);
+
+            for counter in [2u64, 0, 3, 1] {
+                let packet = &packets[counter as usize];
+                assert_eq!(
+                    bob.decrypt_with_counter(counter, packet, b"hdr").unwrap(),
+                    b"ratcheted"
+                );
+                assert!(bob.decrypt_with_counter(counter, packet, b"hdr").is_err());
+            }
+            assert_eq!(bob.recv_counter(), 4);
+        }
+    }
+
+    #[test]
+    fn test_packet_ratchet_keys_every_packet_and_stream() {
+        let (mut alice, _) = ratchet_pair(AeadAlgorithm::XChaCha20Poly1305);
+        let (mut alice2, _) = ratchet_pair(AeadAlgorithm::XChaCha20Poly1305);
+        let mut static_alice = SessionCrypto::new([1u8; 32], [2u8; 32], &[3u8; 32]);
+
+        let first = alice.encrypt_stream(16, b"same", b"").unwrap();
+        let second = alice.encrypt_stream(16, b"same", b"").unwrap();
+        assert_ne!(first[STREAM_ID_SIZE..], second[STREAM_ID_SIZE..]);
+
+        // The stream ID selects the packet key and is not sent in the clear
+        let other_stream = alice2.encrypt_stream(17, b"same", b"").unwrap();
+        assert_ne!(first[STREAM_ID_SIZE..], other_stream[STREAM_ID_SIZE..]);
+        assert_ne!(first[..STREAM_ID_SIZE], 16u32.to_le_bytes());
+
+        let static_packet = static_alice.encrypt(b"same", b"").unwrap();
+        assert_ne!(first[STREAM_ID_SIZE..], static_packet[..]);
+    }
+
+    #[test]
+    fn test_packet_ratchet_rejects_tampered_stream_id() {
+        let (mut alice, mut bob) = ratchet_pair(AeadAlgorithm::XChaCha20Poly1305);
+        let mut packet = alice.encrypt_stream(20, b"bound", b"").unwrap();
+        packet[0] ^= 1;
+        assert!(bob.decrypt_with_counter(0, &packet, b"").is_err());
+        packet[0] ^= 1;
+        assert_eq!(bob.decrypt_with_counter(0, &packet, b"").unwrap(), b"bound");
+    }
+
+    #[test]
+    fn test_forged_packet_does_not_burn_ratchet_key() {
+        let (mut alice, mut bob) = ratchet_pair(AeadAlgorithm::Aes256Gcm);
+        let packets: Vec<_> = (0..3)
+            .map(|_| alice.encrypt(b"genuine", b"").unwrap())
+            .collect();
+
+        let forged = alloc::vec![0u8; packets[0].len()];
+        assert!(bob.decrypt_with_counter(2, &forged, b"").is_err());
+        assert!(bob.decrypt_with_counter(0, &forged, b"").is_err());
+        assert!(bob.decrypt_with_counter(0, &packets[0][..2], b"").is_err());
+
+        for (counter, packet) in packets.iter().enumerate() {
+            assert_eq!(
+                bob.decrypt_with_counter(counter as u64, packet, b"")
+                    .unwrap(),
+                b"genuine"
+            );
+        }
+    }
+
+    #[test]
+    fn test_packet_ratchet_requires_both_peers() {
+        let (mut alice, _) = ratchet_pair(AeadAlgorithm::XChaCha20Poly1305);
+        let mut static_bob = SessionCrypto::new([2u8; 32], [1u8; 32], &[3u8; 32]);
+
+        let packet = alice.encrypt(b"ratcheted", b"").unwrap();
+        assert!(static_bob.decrypt_with_counter(0, &packet, b"").is_err());
+        assert!(matches!(
+            alice.encrypt_with_counter(1, b"explicit", b""),
+            Err(CryptoError::InvalidState)
+        ));
+    }
+
+    #[test]
+    fn test_packet_ratchet_survives_rekey() {
+        let (alice, bob) = ratchet_pair(AeadAlgorithm::Aes256Gcm);
+        let mut alice_next = alice.next_generation(&[9u8; 32], true);
+        let mut bob_next = bob.next_generation(&[9u8; 32], false);
+        assert!(alice_next.packet_ratchet_enabled());
+        assert_eq!(bob_next.aead_algorithm(), AeadAlgorithm::Aes256Gcm);
+
+        let packet = alice_next.encrypt(b"rekeyed", b"").unwrap();
+        assert_eq!(
+            bob_next.decrypt_with_counter(0, &packet, b"").unwrap(),
+            b"rekeyed"
+        );
+
+        bob_next.update_keys([4u8; 32], [5u8; 32], &[6u8; 32]);
+        assert!(bob_next.packet_ratchet_enabled());
+    }
+
     #[test]
     fn test_buffer_pool() {
         let mut pool = BufferPool::new(1024, 4);
@@ -52,6 +52,7 @@ use core::fmt;
 use curve25519_elligator2::MapToPointVariant;
 use curve25519_elligator2::MontgomeryPoint;
 use curve25519_elligator2::elligator2::Randomized;
+use curve25519_elligator2::{EdwardsPoint, Scalar};
 use rand_core::{CryptoRng, RngCore};
 use subtle::CtOption;
 
@@ -200,6 +201,27 @@ pub fn decode_representative(repr: &Representative) -> PublicKey {
     PublicKey::from_bytes(point.to_bytes())
 }
 
+/// Recover the exact public key behind a representative.
+///
+/// For keys from [`generate_encodable_keypair`], [`decode_representative`]
+/// returns the public key plus a low-order point, which the `Randomized`
+/// variant mixes in so representatives cover the whole curve. X25519 clamping
+/// cancels that component, so key exchange works with either point, but
+/// protocols that hash the public key (such as the Noise transcript) need
+/// the value `private.public_key()` the sender used. This projects the
+/// decoded point back onto the prime-order subgroup to recover it.
+#[must_use]
+pub fn decode_public_key(repr: &Representative) -> PublicKey {
+    let point: Option<EdwardsPoint> = EdwardsPoint::from_representative::<Randomized>(&repr.0);
+    let point = point.expect("Forward Elligator2 map should never fail");
+
+    // [8]P clears the low-order component; [8⁻¹ mod ℓ] undoes the
+    // multiplication on the prime-order part
+    let eight_inverse = Scalar::from(8u8).invert();
+    let public = point.mul_by_cofactor() * eight_inverse;
+    PublicKey::from_bytes(public.to_montgomery().to_bytes())
+}
+
 /// Try to encode a public key as a representative.
 ///
 /// # Limitation
@@ -334,6 +356,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decode_public_key_matches_private_key() {
+        for _ in 0..32 {
+            let keypair = ElligatorKeypair::generate(&mut OsRng);
+            let recovered = decode_public_key(&keypair.representative);
+            assert_eq!(recovered.to_bytes(), keypair.public_key().to_bytes());
+        }
+    }
+
     #[test]
     fn test_any_bytes_decodable() {
         // Any 32 random bytes should decode to a valid point
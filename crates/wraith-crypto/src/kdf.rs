@@ -29,6 +29,12 @@ pub mod labels {
     pub const FORMAT_KEY: &[u8] = b"wraith-v2-format-key";
     /// Label for per-stream subkey derivation.
     pub const STREAM_KEY: &[u8] = b"wraith-v2-stream-key";
+    /// Label for the per-packet mask hiding a packet's stream ID.
+    pub const STREAM_ID_MASK: &[u8] = b"wraith-v2-stream-id-mask";
+    /// Label for the keystream masking an Elligator2-encoded handshake message 1.
+    pub const ELLIGATOR_MASK: &[u8] = b"wraith-v2-elligator-mask";
+    /// Label for the tag marking an Elligator2-encoded handshake message 1.
+    pub const ELLIGATOR_TAG: &[u8] = b"wraith-v2-elligator-tag";
     /// Label for hybrid KEM secret combination.
     pub const HYBRID_COMBINE: &[u8] = b"wraith-v2-hybrid-combine";
     /// Label for group secret derivation (Phase 8).
@@ -215,6 +221,9 @@ mod tests {
             labels::RATCHET_MESSAGE,
             labels::FORMAT_KEY,
             labels::STREAM_KEY,
+            labels::STREAM_ID_MASK,
+            labels::ELLIGATOR_MASK,
+            labels::ELLIGATOR_TAG,
             labels::HYBRID_COMBINE,
             labels::GROUP_SECRET,
             labels::GROUP_APPLICATION,
@@ -24,17 +24,42 @@
 //! - Identity hiding: Static keys encrypted after first DH
 //! - Forward secrecy: Compromise of static keys doesn't reveal past sessions
 //! - Mutual authentication: Both parties prove knowledge of static keys
-
+//!
+//! ## Elligator2 Encoding
+//!
+//! Noise sends ephemeral keys in the clear, and X25519 public keys are easy
+//! to spot: the top bit of the last byte is always zero and only about half
+//! of all 32-byte strings are valid curve points. Handshakes created with
+//! [`NoiseHandshake::new_initiator_elligator`] and
+//! [`NoiseHandshake::new_responder_elligator`] instead generate ephemeral
+//! keys that are Elligator2-encodable and send their
+//! [representatives](crate::elligator::Representative), which are uniform
+//! random strings. The plaintext payload of message 1 is masked as well, so
+//! every byte of the handshake is indistinguishable from random. The mask
+//! and the tag that marks an encoded message 1 are keyed with the
+//! responder's static public key, as in obfs4, so only parties that already
+//! know that key can recognize or unmask the message. Both parties must use
+//! the same encoding; a responder can tell which one a message 1 uses with
+//! [`NoiseHandshake::is_elligator_message1`].
+
+use crate::elligator::{Representative, decode_public_key, generate_encodable_keypair};
+use crate::hash::{hkdf_expand, hkdf_extract};
+use crate::kdf::labels;
 use crate::random::SecureRng;
 use crate::ratchet::{DoubleRatchet, MessageHeader};
 use crate::x25519::{PrivateKey, PublicKey};
 use crate::{CryptoError, SessionKeys};
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::string::ToString;
 use alloc::vec::Vec;
 use alloc::{format, vec};
 use core::fmt;
+use snow::params::{CipherChoice, DHChoice, HashChoice};
+use snow::resolvers::{CryptoResolver, DefaultResolver};
+use snow::types::{Cipher, Dh, Hash, Random};
 use snow::{Builder, HandshakeState};
+use subtle::ConstantTimeEq;
 use zeroize::Zeroize;
 
 /// Noise protocol pattern used by WRAITH.
@@ -44,8 +69,15 @@ const NOISE_PATTERN: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
 /// Message 1: 32 (e) + 0 payload + 0 tag = 32 bytes
 /// Message 2: 32 (e) + 32 (s) + 16 (tag) + 16 (tag) = 96 bytes
 /// Message 3: 32 (s) + 16 (tag) + 16 (tag) = 64 bytes
-/// Add buffer for optional payloads
-const MAX_HANDSHAKE_MSG_SIZE: usize = 256;
+/// Add room for optional payloads, such as the suite negotiation and hybrid
+/// KEM material (an ML-KEM-768 public key is 1184 bytes, a ciphertext 1088)
+const MAX_HANDSHAKE_MSG_SIZE: usize = 2048;
+
+/// Size of an ephemeral public key or representative on the wire.
+const DH_LEN: usize = 32;
+
+/// Size of the tag that ends an Elligator2-encoded message 1.
+const ELLIGATOR_TAG_SIZE: usize = 16;
 
 /// Role in the Noise handshake.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -205,6 +237,16 @@ pub struct NoiseHandshake {
     state: HandshakeState,
     role: Role,
     phase: HandshakePhase,
+    /// Encoding state when ephemeral keys are sent Elligator2-encoded
+    elligator: Option<ElligatorEncoding>,
+}
+
+/// Elligator2 encoding state of a handshake
+struct ElligatorEncoding {
+    /// Representative of the local ephemeral key
+    representative: Representative,
+    /// Responder's static public key, which keys the message 1 mask and tag
+    responder_static: [u8; 32],
 }
 
 impl NoiseHandshake {
@@ -219,23 +261,7 @@ impl NoiseHandshake {
     /// - The local private key is invalid
     /// - Handshake state initialization fails
     pub fn new_initiator(local_keypair: &NoiseKeypair) -> Result<Self, NoiseError> {
-        let builder = Builder::new(
-            NOISE_PATTERN
-                .parse()
-                .map_err(|e| NoiseError::SnowError(format!("Pattern parse error: {e:?}")))?,
-        );
-
-        let state = builder
-            .local_private_key(&local_keypair.private)
-            .map_err(|e| NoiseError::SnowError(format!("Key error: {e:?}")))?
-            .build_initiator()
-            .map_err(|e| NoiseError::SnowError(format!("Build error: {e:?}")))?;
-
-        Ok(Self {
-            state,
-            role: Role::Initiator,
-            phase: HandshakePhase::Initial,
-        })
+        Self::build(local_keypair, Role::Initiator, None)
     }
 
     /// Create a new handshake as the responder.
@@ -249,25 +275,110 @@ impl NoiseHandshake {
     /// - The local private key is invalid
     /// - Handshake state initialization fails
     pub fn new_responder(local_keypair: &NoiseKeypair) -> Result<Self, NoiseError> {
-        let builder = Builder::new(
-            NOISE_PATTERN
-                .parse()
-                .map_err(|e| NoiseError::SnowError(format!("Pattern parse error: {e:?}")))?,
-        );
+        Self::build(local_keypair, Role::Responder, None)
+    }
+
+    /// Create a new initiator that sends its ephemeral key Elligator2-encoded.
+    ///
+    /// `responder_static` is the static public key of the responder, which
+    /// keys the message 1 mask and tag. The responder must be created with
+    /// [`Self::new_responder_elligator`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::new_initiator`].
+    pub fn new_initiator_elligator(
+        local_keypair: &NoiseKeypair,
+        responder_static: &[u8; 32],
+    ) -> Result<Self, NoiseError> {
+        Self::build(local_keypair, Role::Initiator, Some(*responder_static))
+    }
+
+    /// Create a new responder for an Elligator2-encoded handshake.
+    ///
+    /// Use this when [`Self::is_elligator_message1`] accepts the initiator's
+    /// first message.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::new_responder`].
+    pub fn new_responder_elligator(local_keypair: &NoiseKeypair) -> Result<Self, NoiseError> {
+        Self::build(local_keypair, Role::Responder, Some(local_keypair.public))
+    }
 
-        let state = builder
+    /// Build a handshake; `responder_static` selects the Elligator2 encoding
+    fn build(
+        local_keypair: &NoiseKeypair,
+        role: Role,
+        responder_static: Option<[u8; 32]>,
+    ) -> Result<Self, NoiseError> {
+        let params = NOISE_PATTERN
+            .parse()
+            .map_err(|e| NoiseError::SnowError(format!("Pattern parse error: {e:?}")))?;
+
+        // snow generates ephemeral keys itself, and only about half of them
+        // are encodable. Pick an encodable one up front and have the
+        // resolver's X25519 primitive produce it when snow asks for a fresh
+        // key; it is new per handshake, just like the key snow would have
+        // generated.
+        let (builder, elligator) = match responder_static {
+            Some(responder_static) => {
+                let (private, representative) = generate_encodable_keypair(&mut SecureRng::new());
+                let resolver = ElligatorResolver {
+                    ephemeral: private.to_bytes(),
+                };
+                let encoding = ElligatorEncoding {
+                    representative,
+                    responder_static,
+                };
+                (
+                    Builder::with_resolver(params, Box::new(resolver)),
+                    Some(encoding),
+                )
+            }
+            None => (Builder::new(params), None),
+        };
+        let builder = builder
             .local_private_key(&local_keypair.private)
-            .map_err(|e| NoiseError::SnowError(format!("Key error: {e:?}")))?
-            .build_responder()
-            .map_err(|e| NoiseError::SnowError(format!("Build error: {e:?}")))?;
+            .map_err(|e| NoiseError::SnowError(format!("Key error: {e:?}")))?;
+
+        let state = match role {
+            Role::Initiator => builder.build_initiator(),
+            Role::Responder => builder.build_responder(),
+        };
+        let state = state.map_err(|e| NoiseError::SnowError(format!("Build error: {e:?}")))?;
 
         Ok(Self {
             state,
-            role: Role::Responder,
+            role,
             phase: HandshakePhase::Initial,
+            elligator,
         })
     }
 
+    /// Check whether a handshake message 1 is Elligator2-encoded for the
+    /// responder whose static public key is `responder_static`.
+    ///
+    /// An encoded message 1 ends with a tag keyed with that key and the
+    /// representative. A plain message 1, or one meant for another
+    /// responder, matches only by chance (2^-128); without the key the tag
+    /// cannot be told apart from random bytes.
+    #[must_use]
+    pub fn is_elligator_message1(message: &[u8], responder_static: &[u8; 32]) -> bool {
+        if message.len() < DH_LEN + ELLIGATOR_TAG_SIZE {
+            return false;
+        }
+        let (body, tag) = message.split_at(message.len() - ELLIGATOR_TAG_SIZE);
+        let prk = message1_prk(responder_static, &body[..DH_LEN]);
+        message1_tag(&prk, &body[DH_LEN..]).ct_eq(tag).into()
+    }
+
+    /// Check whether ephemeral keys are sent Elligator2-encoded.
+    #[must_use]
+    pub fn is_elligator(&self) -> bool {
+        self.elligator.is_some()
+    }
+
     /// Get the current handshake phase.
     #[must_use]
     pub fn phase(&self) -> HandshakePhase {
@@ -307,6 +418,22 @@ impl NoiseHandshake {
         let len = self.state.write_message(payload, &mut message)?;
         message.truncate(len);
 
+        // Message 1 (initiator) and message 2 (responder) start with our
+        // ephemeral key
+        let sends_ephemeral = matches!(
+            (self.role, self.phase),
+            (Role::Initiator, HandshakePhase::Initial)
+                | (Role::Responder, HandshakePhase::Message1Complete)
+        );
+        if let Some(encoding) = &self.elligator
+            && sends_ephemeral
+        {
+            message[..DH_LEN].copy_from_slice(encoding.representative.as_bytes());
+            if self.role == Role::Initiator {
+                mask_message1(&mut message, &encoding.responder_static);
+            }
+        }
+
         // Update phase
         self.phase = match self.phase {
             HandshakePhase::Initial => HandshakePhase::Message1Complete,
@@ -333,6 +460,23 @@ impl NoiseHandshake {
             _ => return Err(NoiseError::InvalidState),
         }
 
+        let receives_ephemeral = matches!(
+            (self.role, self.phase),
+            (Role::Responder, HandshakePhase::Initial)
+                | (Role::Initiator, HandshakePhase::Message1Complete)
+        );
+        let decoded;
+        let message = if let Some(encoding) = &self.elligator
+            && receives_ephemeral
+        {
+            let responder_static =
+                (self.role == Role::Responder).then_some(&encoding.responder_static);
+            decoded = decode_elligator_message(message, responder_static)?;
+            &decoded[..]
+        } else {
+            message
+        };
+
         let mut payload = vec![0u8; MAX_HANDSHAKE_MSG_SIZE];
         let len = self.state.read_message(message, &mut payload)?;
         payload.truncate(len);
@@ -440,6 +584,236 @@ impl NoiseHandshake {
             chain_key,
         })
     }
+
+    /// Complete the handshake and derive session keys bound to a hybrid KEM secret.
+    ///
+    /// Unlike [`Self::into_session_keys`], the keys are derived from the Noise
+    /// chaining key (the secret output of all DH operations) rather than the
+    /// public handshake hash alone. When `hybrid_secret` is provided (the
+    /// output of an X25519 + ML-KEM-768 exchange carried in the handshake
+    /// payloads) it is mixed in as well, so recorded traffic stays
+    /// confidential unless both the classical and post-quantum exchanges are
+    /// broken. The handshake hash is used as the extraction salt, binding the
+    /// keys to the full transcript including any negotiated parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NoiseError::InvalidState` if the handshake is not yet complete.
+    pub fn into_hybrid_session_keys(
+        mut self,
+        hybrid_secret: Option<&[u8; 32]>,
+    ) -> Result<SessionKeys, NoiseError> {
+        if self.phase != HandshakePhase::Complete {
+            return Err(NoiseError::InvalidState);
+        }
+
+        let h = self.state.get_handshake_hash().to_vec();
+        let (mut split_i_to_r, mut split_r_to_i) = self.state.dangerously_get_raw_split();
+
+        let mut ikm = Vec::with_capacity(96);
+        ikm.extend_from_slice(&split_i_to_r);
+        ikm.extend_from_slice(&split_r_to_i);
+        if let Some(secret) = hybrid_secret {
+            ikm.extend_from_slice(labels::HYBRID_COMBINE);
+            ikm.extend_from_slice(secret);
+        }
+        let mut prk = hkdf_extract(&h, &ikm);
+        ikm.zeroize();
+        split_i_to_r.zeroize();
+        split_r_to_i.zeroize();
+
+        let mut key_i_to_r = [0u8; 32];
+        let mut key_r_to_i = [0u8; 32];
+        let mut chain_key = [0u8; 32];
+        hkdf_expand(&prk, labels::TRAFFIC_KEY_I2R, &mut key_i_to_r);
+        hkdf_expand(&prk, labels::TRAFFIC_KEY_R2I, &mut key_r_to_i);
+        hkdf_expand(&prk, labels::RATCHET_CHAIN, &mut chain_key);
+        prk.zeroize();
+
+        let (send_key, recv_key) = match self.role {
+            Role::Initiator => (key_i_to_r, key_r_to_i),
+            Role::Responder => (key_r_to_i, key_i_to_r),
+        };
+
+        Ok(SessionKeys {
+            send_key,
+            recv_key,
+            chain_key,
+        })
+    }
+}
+
+/// Mask the payload of an Elligator2-encoded message 1 and append its tag.
+///
+/// Noise has no key yet when message 1 is written, so its payload (the suite
+/// offer) would otherwise follow the representative in the clear. It is
+/// XORed with a keystream derived from the responder's static key and the
+/// representative, and a tag over the masked bytes lets the responder
+/// recognize the encoding. An observer who does not know the responder's
+/// key can neither unmask the payload nor check the tag; Noise still
+/// authenticates the payload through the transcript.
+fn mask_message1(message: &mut Vec<u8>, responder_static: &[u8; 32]) {
+    let prk = message1_prk(responder_static, &message[..DH_LEN]);
+    apply_message1_mask(&prk, &mut message[DH_LEN..]);
+    let tag = message1_tag(&prk, &message[DH_LEN..]);
+    message.extend_from_slice(&tag);
+}
+
+/// Key for the mask and tag of a message 1 sent to `responder_static`.
+fn message1_prk(responder_static: &[u8; 32], representative: &[u8]) -> [u8; 32] {
+    let mut ikm = [0u8; 2 * DH_LEN];
+    ikm[..DH_LEN].copy_from_slice(responder_static);
+    ikm[DH_LEN..].copy_from_slice(representative);
+    hkdf_extract(labels::ELLIGATOR_MASK, &ikm)
+}
+
+/// XOR a message 1 payload with the keystream derived from its key.
+fn apply_message1_mask(prk: &[u8; 32], payload: &mut [u8]) {
+    let mut keystream = vec![0u8; payload.len()];
+    hkdf_expand(prk, labels::ELLIGATOR_MASK, &mut keystream);
+    for (byte, mask) in payload.iter_mut().zip(&keystream) {
+        *byte ^= mask;
+    }
+}
+
+/// Tag over the masked payload of an Elligator2-encoded message 1.
+fn message1_tag(prk: &[u8; 32], masked_payload: &[u8]) -> [u8; ELLIGATOR_TAG_SIZE] {
+    let mut key = [0u8; 32];
+    hkdf_expand(prk, labels::ELLIGATOR_TAG, &mut key);
+    let hash = blake3::keyed_hash(&key, masked_payload);
+    let mut tag = [0u8; ELLIGATOR_TAG_SIZE];
+    tag.copy_from_slice(&hash.as_bytes()[..ELLIGATOR_TAG_SIZE]);
+    tag
+}
+
+/// Turn an Elligator2-encoded message back into the form snow expects.
+///
+/// Replaces the leading representative with the public key it encodes and,
+/// for message 1 (which passes the receiving responder's static key),
+/// checks the tag and unmasks the payload.
+fn decode_elligator_message(
+    message: &[u8],
+    message1: Option<&[u8; 32]>,
+) -> Result<Vec<u8>, NoiseError> {
+    let min_len = if message1.is_some() {
+        DH_LEN + ELLIGATOR_TAG_SIZE
+    } else {
+        DH_LEN
+    };
+    if message.len() < min_len {
+        return Err(NoiseError::InvalidMessage);
+    }
+
+    let representative =
+        Representative::from_slice(&message[..DH_LEN]).ok_or(NoiseError::InvalidMessage)?;
+    let mut decoded = Vec::with_capacity(message.len());
+    decoded.extend_from_slice(&decode_public_key(&representative).to_bytes());
+
+    if let Some(responder_static) = message1 {
+        if !NoiseHandshake::is_elligator_message1(message, responder_static) {
+            return Err(NoiseError::InvalidMessage);
+        }
+        let prk = message1_prk(responder_static, representative.as_bytes());
+        decoded.extend_from_slice(&message[DH_LEN..message.len() - ELLIGATOR_TAG_SIZE]);
+        apply_message1_mask(&prk, &mut decoded[DH_LEN..]);
+    } else {
+        decoded.extend_from_slice(&message[DH_LEN..]);
+    }
+    Ok(decoded)
+}
+
+/// Crypto resolver whose X25519 primitive yields a preset ephemeral key.
+///
+/// Used for Elligator2 handshakes: snow asks the primitive to generate the
+/// ephemeral key, and it loads the encodable key picked up front instead.
+/// Everything else comes from snow's default resolver.
+struct ElligatorResolver {
+    ephemeral: [u8; 32],
+}
+
+impl Drop for ElligatorResolver {
+    fn drop(&mut self) {
+        self.ephemeral.zeroize();
+    }
+}
+
+impl CryptoResolver for ElligatorResolver {
+    fn resolve_rng(&self) -> Option<Box<dyn Random>> {
+        DefaultResolver.resolve_rng()
+    }
+
+    fn resolve_dh(&self, choice: &DHChoice) -> Option<Box<dyn Dh>> {
+        let inner = DefaultResolver.resolve_dh(choice)?;
+        Some(Box::new(PresetEphemeralDh {
+            inner,
+            preset: Some(self.ephemeral),
+        }))
+    }
+
+    fn resolve_hash(&self, choice: &HashChoice) -> Option<Box<dyn Hash>> {
+        DefaultResolver.resolve_hash(choice)
+    }
+
+    fn resolve_cipher(&self, choice: &CipherChoice) -> Option<Box<dyn Cipher>> {
+        DefaultResolver.resolve_cipher(choice)
+    }
+}
+
+/// X25519 primitive whose first generated key is a preset one.
+///
+/// snow resolves one primitive for the static key, which it sets, and one
+/// for the ephemeral key, which it generates once per handshake. Later
+/// generations, which Noise_XX never asks for, fall back to random keys.
+struct PresetEphemeralDh {
+    inner: Box<dyn Dh>,
+    preset: Option<[u8; 32]>,
+}
+
+impl Drop for PresetEphemeralDh {
+    fn drop(&mut self) {
+        self.preset.zeroize();
+    }
+}
+
+impl Dh for PresetEphemeralDh {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn pub_len(&self) -> usize {
+        self.inner.pub_len()
+    }
+
+    fn priv_len(&self) -> usize {
+        self.inner.priv_len()
+    }
+
+    fn set(&mut self, privkey: &[u8]) {
+        self.inner.set(privkey);
+    }
+
+    fn generate(&mut self, rng: &mut dyn Random) -> Result<(), snow::Error> {
+        match self.preset.take() {
+            Some(mut preset) => {
+                self.inner.set(&preset);
+                preset.zeroize();
+                Ok(())
+            }
+            None => self.inner.generate(rng),
+        }
+    }
+
+    fn pubkey(&self) -> &[u8] {
+        self.inner.pubkey()
+    }
+
+    fn privkey(&self) -> &[u8] {
+        self.inner.privkey()
+    }
+
+    fn dh(&self, pubkey: &[u8], out: &mut [u8]) -> Result<(), snow::Error> {
+        self.inner.dh(pubkey, out)
+    }
 }
 
 /// Derive a key using BLAKE3 keyed mode.
@@ -629,4 +1003,266 @@ mod tests {
         let decrypted2 = initiator_transport.read_message(&ciphertext2).unwrap();
         assert_eq!(decrypted2, plaintext2);
     }
+
+    fn completed_handshake_pair() -> (NoiseHandshake, NoiseHandshake) {
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+
+        let mut initiator = NoiseHandshake::new_initiator(&initiator_keypair).unwrap();
+        let mut responder = NoiseHandshake::new_responder(&responder_keypair).unwrap();
+
+        let msg1 = initiator.write_message(&[]).unwrap();
+        responder.read_message(&msg1).unwrap();
+        let msg2 = responder.write_message(&[]).unwrap();
+        initiator.read_message(&msg2).unwrap();
+        let msg3 = initiator.write_message(&[]).unwrap();
+        responder.read_message(&msg3).unwrap();
+
+        (initiator, responder)
+    }
+
+    #[test]
+    fn test_hybrid_session_keys_agree() {
+        let secret = [0x5Au8; 32];
+        let (initiator, responder) = completed_handshake_pair();
+
+        let i_keys = initiator.into_hybrid_session_keys(Some(&secret)).unwrap();
+        let r_keys = responder.into_hybrid_session_keys(Some(&secret)).unwrap();
+
+        assert_eq!(i_keys.send_key, r_keys.recv_key);
+        assert_eq!(i_keys.recv_key, r_keys.send_key);
+        assert_eq!(i_keys.chain_key, r_keys.chain_key);
+        assert_ne!(i_keys.send_key, i_keys.recv_key);
+    }
+
+    #[test]
+    fn test_hybrid_session_keys_depend_on_secret() {
+        let (initiator, responder) = completed_handshake_pair();
+
+        let i_keys = initiator
+            .into_hybrid_session_keys(Some(&[1u8; 32]))
+            .unwrap();
+        let r_keys = responder
+            .into_hybrid_session_keys(Some(&[2u8; 32]))
+            .unwrap();
+        assert_ne!(i_keys.send_key, r_keys.recv_key);
+
+        let (initiator, responder) = completed_handshake_pair();
+        let i_keys = initiator.into_hybrid_session_keys(None).unwrap();
+        let r_keys = responder
+            .into_hybrid_session_keys(Some(&[0u8; 32]))
+            .unwrap();
+        assert_ne!(i_keys.send_key, r_keys.recv_key);
+    }
+
+    #[test]
+    fn test_hybrid_session_keys_not_derived_from_handshake_hash_alone() {
+        let (initiator, responder) = completed_handshake_pair();
+
+        let legacy = initiator.into_session_keys().unwrap();
+        let hybrid = responder.into_hybrid_session_keys(None).unwrap();
+        assert_ne!(legacy.send_key, hybrid.recv_key);
+    }
+
+    #[test]
+    fn test_hybrid_session_keys_require_complete_handshake() {
+        let keypair = NoiseKeypair::generate().unwrap();
+        let initiator = NoiseHandshake::new_initiator(&keypair).unwrap();
+        assert!(matches!(
+            initiator.into_hybrid_session_keys(None),
+            Err(NoiseError::InvalidState)
+        ));
+    }
+
+    /// Run a full handshake, returning both sides and the three messages.
+    fn elligator_handshake(payload1: &[u8]) -> (NoiseHandshake, NoiseHandshake, [Vec<u8>; 3]) {
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+
+        let mut initiator = NoiseHandshake::new_initiator_elligator(
+            &initiator_keypair,
+            responder_keypair.public_key(),
+        )
+        .unwrap();
+        let msg1 = initiator.write_message(payload1).unwrap();
+        assert!(NoiseHandshake::is_elligator_message1(
+            &msg1,
+            responder_keypair.public_key()
+        ));
+
+        let mut responder = NoiseHandshake::new_responder_elligator(&responder_keypair).unwrap();
+        assert_eq!(responder.read_message(&msg1).unwrap(), payload1);
+        let msg2 = responder.write_message(b"selection").unwrap();
+        assert_eq!(initiator.read_message(&msg2).unwrap(), b"selection");
+        let msg3 = initiator.write_message(&[]).unwrap();
+        responder.read_message(&msg3).unwrap();
+
+        (initiator, responder, [msg1, msg2, msg3])
+    }
+
+    #[test]
+    fn test_elligator_handshake() {
+        let (initiator, responder, _) = elligator_handshake(b"suite offer");
+        assert!(initiator.is_elligator() && responder.is_elligator());
+        assert!(initiator.get_remote_static().is_some());
+        assert!(responder.get_remote_static().is_some());
+
+        let i_keys = initiator.into_hybrid_session_keys(None).unwrap();
+        let r_keys = responder.into_hybrid_session_keys(None).unwrap();
+        assert_eq!(i_keys.send_key, r_keys.recv_key);
+        assert_eq!(i_keys.recv_key, r_keys.send_key);
+    }
+
+    #[test]
+    fn test_elligator_message1_detection() {
+        let keypair = NoiseKeypair::generate().unwrap();
+
+        let plain = NoiseHandshake::new_initiator(&keypair)
+            .unwrap()
+            .write_message(b"suite offer")
+            .unwrap();
+        let responder_static = keypair.public_key();
+        assert!(!NoiseHandshake::is_elligator_message1(
+            &plain,
+            responder_static
+        ));
+        assert!(!NoiseHandshake::is_elligator_message1(
+            &plain[..8],
+            responder_static
+        ));
+        let mut responder = NoiseHandshake::new_responder_elligator(&keypair).unwrap();
+        assert!(matches!(
+            responder.read_message(&plain),
+            Err(NoiseError::InvalidMessage)
+        ));
+
+        let mut encoded = NoiseHandshake::new_initiator_elligator(&keypair, responder_static)
+            .unwrap()
+            .write_message(b"suite offer")
+            .unwrap();
+        assert_eq!(encoded.len(), plain.len() + ELLIGATOR_TAG_SIZE);
+        assert!(!encoded.windows(11).any(|w| w == b"suite offer"));
+        assert!(NoiseHandshake::is_elligator_message1(
+            &encoded,
+            responder_static
+        ));
+        encoded[DH_LEN] ^= 1;
+        assert!(!NoiseHandshake::is_elligator_message1(
+            &encoded,
+            responder_static
+        ));
+    }
+
+    #[test]
+    fn test_elligator_message1_needs_responder_key() {
+        use rand_core::RngCore;
+
+        const MESSAGES: usize = 64;
+
+        let initiator_keypair = NoiseKeypair::generate().unwrap();
+        let responder_keypair = NoiseKeypair::generate().unwrap();
+        let observer_keypair = NoiseKeypair::generate().unwrap();
+        let mut rng = SecureRng::new();
+
+        for _ in 0..MESSAGES {
+            let msg1 = NoiseHandshake::new_initiator_elligator(
+                &initiator_keypair,
+                responder_keypair.public_key(),
+            )
+            .unwrap()
+            .write_message(b"suite offer")
+            .unwrap();
+            let mut random = vec![0u8; msg1.len()];
+            rng.fill_bytes(&mut random);
+
+            // Only the responder recognizes its messages
+            assert!(NoiseHandshake::is_elligator_message1(
+                &msg1,
+                responder_keypair.public_key()
+            ));
+
+            // Anyone else sees the same verdict for msg1 as for random bytes,
+            // whatever key they guess
+            for guess in [observer_keypair.public_key(), &[0u8; 32]] {
+                assert_eq!(
+                    NoiseHandshake::is_elligator_message1(&msg1, guess),
+                    NoiseHandshake::is_elligator_message1(&random, guess)
+                );
+            }
+
+            // and a responder under another key refuses the message
+            let mut other = NoiseHandshake::new_responder_elligator(&observer_keypair).unwrap();
+            assert!(matches!(
+                other.read_message(&msg1),
+                Err(NoiseError::InvalidMessage)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_elligator_handshake_bytes_look_random() {
+        const HANDSHAKES: usize = 200;
+
+        // A structured payload is the worst case for message 1
+        let mut byte_counts = [0u64; 256];
+        let mut bit_counts = [0u64; 8];
+        let mut total = 0u64;
+        let mut ephemeral_high_bits = 0;
+        for _ in 0..HANDSHAKES {
+            let (_, _, messages) = elligator_handshake(&[0u8; 64]);
+            for message in &messages {
+                for &byte in message {
+                    byte_counts[usize::from(byte)] += 1;
+                    for (bit, count) in bit_counts.iter_mut().enumerate() {
+                        *count += u64::from((byte >> bit) & 1);
+                    }
+                    total += 1;
+                }
+            }
+            // X25519 public keys never set the top bit of their last byte
+            ephemeral_high_bits += usize::from(messages[0][31] >> 7);
+            ephemeral_high_bits += usize::from(messages[1][31] >> 7);
+        }
+
+        // Chi-square over byte values: 255 degrees of freedom, mean 255 and
+        // standard deviation ~22.6, so 400 is a > 6 sigma bound
+        #[allow(clippy::cast_precision_loss)]
+        let expected = total as f64 / 256.0;
+        #[allow(clippy::cast_precision_loss)]
+        let chi_square: f64 = byte_counts
+            .iter()
+            .map(|&count| {
+                let diff = count as f64 - expected;
+                diff * diff / expected
+            })
+            .sum();
+        assert!(chi_square < 400.0, "chi-square {chi_square} too high");
+
+        for (bit, &ones) in bit_counts.iter().enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            let ratio = ones as f64 / total as f64;
+            assert!(
+                (0.48..0.52).contains(&ratio),
+                "bit {bit} set in {ratio} of bytes"
+            );
+        }
+
+        assert!(
+            (HANDSHAKES / 2..HANDSHAKES * 3 / 2).contains(&ephemeral_high_bits),
+            "ephemeral key high bit set {ephemeral_high_bits} times out of {}",
+            HANDSHAKES * 2
+        );
+    }
+
+    #[test]
+    fn test_plain_handshake_ephemeral_is_recognizable() {
+        let keypair = NoiseKeypair::generate().unwrap();
+        for _ in 0..32 {
+            let msg1 = NoiseHandshake::new_initiator(&keypair)
+                .unwrap()
+                .write_message(&[])
+                .unwrap();
+            assert_eq!(msg1[31] >> 7, 0);
+        }
+    }
 }
@@ -12,7 +12,7 @@
 //! that arrive out of sequence, up to a configurable window size.
 
 use alloc::collections::BTreeMap;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::error::CryptoError;
 
@@ -140,6 +140,41 @@ impl PacketRatchet {
         Ok(message_key)
     }
 
+    /// Look up the decryption key for a packet without consuming it.
+    ///
+    /// Returns the same key [`key_for_packet`](Self::key_for_packet) would,
+    /// but leaves the ratchet and its cache untouched, so a packet can be
+    /// authenticated before its key is consumed. Skipping ahead costs two
+    /// hashes per skipped packet, bounded by the window size.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`key_for_packet`](Self::key_for_packet).
+    pub fn peek_key(&self, pn: u64) -> Result<[u8; 32], CryptoError> {
+        if let Some(key) = self.key_cache.get(&pn) {
+            return Ok(*key);
+        }
+
+        if pn < self.packet_number {
+            return Err(CryptoError::InvalidState);
+        }
+
+        let gap = pn - self.packet_number;
+        if gap as usize > self.max_cache_size {
+            return Err(CryptoError::InvalidState);
+        }
+
+        let mut chain_key = self.chain_key;
+        for _ in 0..gap {
+            let next_chain = derive_next_chain_key(&chain_key);
+            chain_key.zeroize();
+            chain_key = next_chain;
+        }
+        let message_key = derive_message_key(&chain_key);
+        chain_key.zeroize();
+        Ok(message_key)
+    }
+
     /// Get the current packet number (next to be sent/expected).
     #[must_use]
     pub fn packet_number(&self) -> u64 {
@@ -155,6 +190,7 @@ impl PacketRatchet {
 
 impl Drop for PacketRatchet {
     fn drop(&mut self) {
+        self.chain_key.zeroize();
         // Zeroize all cached keys
         for (_, key) in self.key_cache.iter_mut() {
             key.zeroize();
@@ -163,6 +199,8 @@ impl Drop for PacketRatchet {
     }
 }
 
+impl ZeroizeOnDrop for PacketRatchet {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,6 +320,30 @@ mod tests {
         assert_eq!(r.cached_key_count(), 4);
     }
 
+    #[test]
+    fn test_peek_key_does_not_consume() {
+        let chain = [0x42u8; 32];
+        let mut sender = PacketRatchet::new(chain);
+        let keys: alloc::vec::Vec<_> = (0..4).map(|_| sender.next_send_key().1).collect();
+
+        let mut recv = PacketRatchet::new(chain);
+        assert_eq!(recv.peek_key(3).unwrap(), keys[3]);
+        assert_eq!(recv.peek_key(0).unwrap(), keys[0]);
+        assert_eq!(recv.packet_number(), 0);
+        assert_eq!(recv.cached_key_count(), 0);
+
+        // Peeking a cached key leaves it in the cache
+        assert_eq!(recv.key_for_packet(3).unwrap(), keys[3]);
+        assert_eq!(recv.peek_key(1).unwrap(), keys[1]);
+        assert_eq!(recv.cached_key_count(), 3);
+        assert_eq!(recv.key_for_packet(1).unwrap(), keys[1]);
+
+        // Consumed and too-far-ahead keys cannot be peeked
+        assert!(recv.peek_key(1).is_err());
+        assert!(recv.peek_key(3).is_err());
+        assert!(recv.peek_key(4 + DEFAULT_WINDOW_SIZE as u64 + 1).is_err());
+    }
+
     #[test]
     fn test_derive_functions_distinct() {
         let chain = [0x42u8; 32];
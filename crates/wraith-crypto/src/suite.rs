@@ -48,20 +48,31 @@ pub enum CryptoSuite {
 impl CryptoSuite {
     /// Negotiate the strongest common suite between local and remote supported sets.
     ///
-    /// Both lists are treated as sets of supported suites. The strongest suite
-    /// present in both sets is selected, using the priority order:
-    /// C > A > B > D (strongest to weakest).
+    /// The strongest suite present in both lists is selected, using the
+    /// priority order C > A > B > D (strongest to weakest). Suites A and B
+    /// differ only in their AEAD, so B takes precedence over A when *both*
+    /// lists name B before A (see [`CryptoSuite::local_preference`]); the
+    /// outcome is the same whichever side runs the negotiation.
     ///
     /// Returns `None` if no common suite exists.
     #[must_use]
     pub fn negotiate(local: &[CryptoSuite], remote: &[CryptoSuite]) -> Option<CryptoSuite> {
         // Priority order: C (max security) > A (default) > B (hw accel) > D (classical)
-        let priority = [
-            CryptoSuite::SuiteC,
-            CryptoSuite::SuiteA,
-            CryptoSuite::SuiteB,
-            CryptoSuite::SuiteD,
-        ];
+        let priority = if prefers_suite_b(local) && prefers_suite_b(remote) {
+            [
+                CryptoSuite::SuiteC,
+                CryptoSuite::SuiteB,
+                CryptoSuite::SuiteA,
+                CryptoSuite::SuiteD,
+            ]
+        } else {
+            [
+                CryptoSuite::SuiteC,
+                CryptoSuite::SuiteA,
+                CryptoSuite::SuiteB,
+                CryptoSuite::SuiteD,
+            ]
+        };
 
         for suite in &priority {
             if local.contains(suite) && remote.contains(suite) {
@@ -125,6 +136,27 @@ impl CryptoSuite {
         }
     }
 
+    /// Default suite preference for this machine, most preferred first.
+    ///
+    /// Lists Suite B ahead of Suite A when the CPU accelerates AES-GCM
+    /// ([`aead::gcm::hardware_accelerated`]), so two such machines negotiate
+    /// AES-256-GCM while everyone else keeps XChaCha20-Poly1305.
+    ///
+    /// [`aead::gcm::hardware_accelerated`]: crate::aead::gcm::hardware_accelerated
+    #[must_use]
+    pub fn local_preference() -> &'static [CryptoSuite] {
+        if crate::aead::gcm::hardware_accelerated() {
+            &[
+                CryptoSuite::SuiteC,
+                CryptoSuite::SuiteB,
+                CryptoSuite::SuiteA,
+                CryptoSuite::SuiteD,
+            ]
+        } else {
+            Self::all()
+        }
+    }
+
     /// Return all defined suites in priority order (strongest first).
     #[must_use]
     pub fn all() -> &'static [CryptoSuite] {
@@ -137,6 +169,15 @@ impl CryptoSuite {
     }
 }
 
+/// Check whether a suite list names Suite B before Suite A
+fn prefers_suite_b(suites: &[CryptoSuite]) -> bool {
+    let position = |wanted| suites.iter().position(|suite| *suite == wanted);
+    matches!(
+        (position(CryptoSuite::SuiteB), position(CryptoSuite::SuiteA)),
+        (Some(b), Some(a)) if b < a
+    )
+}
+
 impl fmt::Display for CryptoSuite {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -178,6 +219,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_negotiate_prefers_b_only_when_both_sides_do() {
+        let aes_first = [
+            CryptoSuite::SuiteB,
+            CryptoSuite::SuiteA,
+            CryptoSuite::SuiteD,
+        ];
+        let chacha_first = [
+            CryptoSuite::SuiteA,
+            CryptoSuite::SuiteB,
+            CryptoSuite::SuiteD,
+        ];
+
+        assert_eq!(
+            CryptoSuite::negotiate(&aes_first, &aes_first),
+            Some(CryptoSuite::SuiteB)
+        );
+        assert_eq!(
+            CryptoSuite::negotiate(&aes_first, &chacha_first),
+            Some(CryptoSuite::SuiteA)
+        );
+        assert_eq!(
+            CryptoSuite::negotiate(&chacha_first, &aes_first),
+            Some(CryptoSuite::SuiteA)
+        );
+        // B preferred but A not offered by the peer at all
+        assert_eq!(
+            CryptoSuite::negotiate(&aes_first, &[CryptoSuite::SuiteB]),
+            Some(CryptoSuite::SuiteB)
+        );
+    }
+
+    #[test]
+    fn test_local_preference_follows_hardware() {
+        let preference = CryptoSuite::local_preference();
+        assert_eq!(preference.len(), CryptoSuite::all().len());
+        assert_eq!(
+            prefers_suite_b(preference),
+            crate::aead::gcm::hardware_accelerated()
+        );
+    }
+
     #[test]
     fn test_negotiate_no_common() {
         let local = [CryptoSuite::SuiteA];
@@ -3,6 +3,7 @@
 //! This module contains test vectors from:
 //! - RFC 7748 (X25519)
 //! - RFC 8439 (ChaCha20-Poly1305)
+//! - The GCM specification (McGrew & Viega, AES-256-GCM)
 //! - BLAKE3 official test vectors
 //!
 //! These vectors ensure our implementations match the specifications exactly.
@@ -10,7 +11,7 @@
 //! SECURITY NOTE: All hard-coded cryptographic values in this file are intentional
 //! test vectors from RFCs and official specifications, NOT production keys.
 
-use wraith_crypto::aead::{AeadKey, Nonce};
+use wraith_crypto::aead::{AeadKey, Aes256GcmKey, GcmNonce, Nonce, SessionCrypto};
 use wraith_crypto::hash;
 use wraith_crypto::x25519::{PrivateKey, PublicKey};
 
@@ -319,6 +320,115 @@ fn test_xchacha_large_message() {
     assert_eq!(plaintext, decrypted);
 }
 
+// ============================================================================
+// AES-256-GCM Test Vectors (GCM specification, test cases 14 and 16)
+// ============================================================================
+
+#[test]
+fn test_aes256gcm_spec_test_case_14() {
+    let key = Aes256GcmKey::new([0u8; 32]);
+    let nonce = GcmNonce::from_bytes([0u8; 12]);
+
+    let ciphertext = key.encrypt(&nonce, &[0u8; 16], b"").unwrap();
+    assert_eq!(
+        ciphertext,
+        decode_hex("cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919")
+    );
+    assert_eq!(key.decrypt(&nonce, &ciphertext, b"").unwrap(), [0u8; 16]);
+}
+
+#[test]
+fn test_aes256gcm_spec_test_case_16() {
+    let key = Aes256GcmKey::from_slice(&decode_hex(
+        "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+    ))
+    .unwrap();
+    let mut nonce_bytes = [0u8; 12];
+    nonce_bytes.copy_from_slice(&decode_hex("cafebabefacedbaddecaf888"));
+    let nonce = GcmNonce::from_bytes(nonce_bytes);
+    let plaintext = decode_hex(
+        "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+         1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+    );
+    let aad = decode_hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let expected = decode_hex(
+        "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+         8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+         76fc6ece0f4e1768cddf8853bb2d551b",
+    );
+
+    let ciphertext = key.encrypt(&nonce, &plaintext, &aad).unwrap();
+    assert_eq!(ciphertext, expected);
+    assert_eq!(key.decrypt(&nonce, &ciphertext, &aad).unwrap(), plaintext);
+
+    let mut tampered = ciphertext;
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0x01;
+    assert!(key.decrypt(&nonce, &tampered, &aad).is_err());
+}
+
+// ============================================================================
+// Cross-Suite Session Vectors
+// ============================================================================
+//
+// Pinned outputs of `SessionCrypto` (counter nonce, key commitment in the AAD)
+// for each AEAD, so that peers built from different revisions keep agreeing
+// on the packet format of every suite.
+
+#[test]
+fn test_session_vectors_per_suite() {
+    use wraith_crypto::suite::CryptoSuite;
+
+    let vectors = [
+        (
+            CryptoSuite::SuiteA,
+            "f8cfdec79eea816d3db2dc357f6285be56cb3a726629fa93c5e984354f8ed945690174167556128a98",
+        ),
+        (
+            CryptoSuite::SuiteB,
+            "a2005ff350257cbab5fbdd85d48d99437a03e40d999d1bdac1a8c15cbd393118ca75ba8bebc0d051d2",
+        ),
+    ];
+
+    for (suite, expected) in vectors {
+        let aead = suite.aead_algorithm();
+        let sender = SessionCrypto::with_aead(aead, [0x11; 32], [0x22; 32], &[0x33; 32]);
+        let mut receiver = SessionCrypto::with_aead(aead, [0x22; 32], [0x11; 32], &[0x33; 32]);
+
+        let ciphertext = sender
+            .encrypt_with_counter(42, b"WRAITH cross-suite vector", b"cid")
+            .unwrap();
+        assert_eq!(ciphertext, decode_hex(expected), "{suite}");
+        assert_eq!(
+            receiver
+                .decrypt_with_counter(42, &ciphertext, b"cid")
+                .unwrap(),
+            b"WRAITH cross-suite vector"
+        );
+    }
+}
+
+#[test]
+fn test_session_ciphertext_does_not_cross_suites() {
+    use wraith_crypto::suite::AeadAlgorithm;
+
+    let chacha = SessionCrypto::with_aead(
+        AeadAlgorithm::XChaCha20Poly1305,
+        [0x11; 32],
+        [0x22; 32],
+        &[0x33; 32],
+    );
+    let mut aes = SessionCrypto::with_aead(
+        AeadAlgorithm::Aes256Gcm,
+        [0x22; 32],
+        [0x11; 32],
+        &[0x33; 32],
+    );
+
+    let ciphertext = chacha.encrypt_with_counter(0, b"suite A", b"").unwrap();
+    assert!(aes.decrypt_with_counter(0, &ciphertext, b"").is_err());
+}
+
 // ============================================================================
 // Noise Protocol Test Vectors
 // ============================================================================
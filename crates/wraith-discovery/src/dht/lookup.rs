@@ -0,0 +1,305 @@
+//! Disjoint-Path Node Lookup
+//!
+//! Implements the S/Kademlia lookup: the closest known peers are split
+//! between `d` paths, and each path runs its own iterative FIND_NODE. A
+//! node is queried by at most one path, so a path made up entirely of
+//! honest nodes cannot be steered by malicious nodes met on another path.
+//! The lookup succeeds if any path reaches the target's neighbourhood.
+//!
+//! Peers returned by other nodes are only followed if their NodeId is
+//! derived from their public key, which stops attackers from inventing
+//! identities next to the target.
+
+use super::messages::{CompactPeer, FoundValueResponse};
+use super::node_id::NodeId;
+use super::operations::{ALPHA, MAX_ITERATIONS, OperationError};
+use super::routing::{DhtPeer, K};
+use std::collections::HashSet;
+use std::future::Future;
+
+/// Default number of disjoint lookup paths
+pub const DISJOINT_PATHS: usize = 3;
+
+/// Transport for FIND_NODE requests issued during a lookup
+pub trait FindNodeRpc {
+    /// Ask `peer` for the peers it knows closest to `target`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer does not answer; the lookup counts this
+    /// as a failure of the peer.
+    fn find_node(
+        &self,
+        peer: &DhtPeer,
+        target: &NodeId,
+    ) -> impl Future<Output = Result<Vec<CompactPeer>, OperationError>> + Send;
+}
+
+/// Transport for FIND_VALUE requests issued during a value lookup
+pub trait FindValueRpc {
+    /// Ask `peer` for the record stored under `key`, or the peers it knows
+    /// closest to it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the peer does not answer; the lookup counts this
+    /// as a failure of the peer.
+    fn find_value(
+        &self,
+        peer: &DhtPeer,
+        key: &[u8; 32],
+    ) -> impl Future<Output = Result<FoundValueResponse, OperationError>> + Send;
+}
+
+/// Disjoint-path lookup parameters
+#[derive(Debug, Clone)]
+pub struct LookupConfig {
+    /// Number of disjoint paths (`d`); 1 gives a plain Kademlia lookup
+    pub paths: usize,
+    /// Peers queried per path in each round
+    pub alpha: usize,
+    /// Number of closest peers each path tracks and the lookup returns
+    pub k: usize,
+    /// Maximum rounds before the lookup gives up
+    pub max_rounds: usize,
+    /// Only follow peers whose NodeId is derived from their public key
+    pub require_bound_ids: bool,
+}
+
+impl Default for LookupConfig {
+    fn default() -> Self {
+        Self {
+            paths: DISJOINT_PATHS,
+            alpha: ALPHA,
+            k: K,
+            max_rounds: MAX_ITERATIONS,
+            require_bound_ids: true,
+        }
+    }
+}
+
+/// Outcome of a lookup
+#[derive(Debug, Clone, Default)]
+pub struct LookupResult {
+    /// Up to `k` peers that answered, closest to the target first
+    pub closest: Vec<DhtPeer>,
+    /// Every peer that answered
+    pub responded: Vec<DhtPeer>,
+    /// Peers that failed to answer
+    pub failed: Vec<NodeId>,
+    /// Number of requests sent
+    pub queries: usize,
+}
+
+/// State of one lookup path
+#[derive(Default)]
+struct Path {
+    /// Peers assigned to this path, closest to the target first
+    candidates: Vec<DhtPeer>,
+    /// Peers this path has already queried
+    queried: HashSet<NodeId>,
+}
+
+/// Run a disjoint-path lookup for `target`
+///
+/// `seeds` are the locally known peers to start from, typically the K
+/// closest in the routing table. They are dealt round-robin to the paths in
+/// order of distance, so every path starts close to the target.
+pub async fn disjoint_lookup<R: FindNodeRpc>(
+    local_id: &NodeId,
+    mut seeds: Vec<DhtPeer>,
+    target: &NodeId,
+    rpc: &R,
+    config: &LookupConfig,
+) -> LookupResult {
+    let mut result = LookupResult::default();
+    let mut paths: Vec<Path> = (0..config.paths.max(1)).map(|_| Path::default()).collect();
+
+    // Every peer belongs to at most one path
+    let mut claimed = HashSet::from([*local_id]);
+    seeds.sort_by_key(|p| p.id.distance(target));
+    seeds.retain(|p| claimed.insert(p.id));
+    let path_count = paths.len();
+    for (i, peer) in seeds.into_iter().enumerate() {
+        paths[i % path_count].candidates.push(peer);
+    }
+
+    for _round in 0..config.max_rounds {
+        let mut progressed = false;
+
+        for path in &mut paths {
+            // Query the closest unqueried peers among the path's k closest
+            let batch: Vec<DhtPeer> = path
+                .candidates
+                .iter()
+                .take(config.k)
+                .filter(|p| !path.queried.contains(&p.id))
+                .take(config.alpha)
+                .cloned()
+                .collect();
+
+            for peer in batch {
+                progressed = true;
+                path.queried.insert(peer.id);
+                result.queries += 1;
+
+                match rpc.find_node(&peer, target).await {
+                    Ok(found) => {
+                        for compact in found {
+                            let Some(learned) = verified_peer(compact, config) else {
+                                continue;
+                            };
+                            if claimed.insert(learned.id) {
+                                path.candidates.push(learned);
+                            }
+                        }
+                        result.responded.push(peer);
+                    }
+                    Err(_) => {
+                        path.candidates.retain(|p| p.id != peer.id);
+                        result.failed.push(peer.id);
+                    }
+                }
+            }
+
+            path.candidates.sort_by_key(|p| p.id.distance(target));
+        }
+
+        if !progressed {
+            break;
+        }
+    }
+
+    result.closest = result.responded.clone();
+    result.closest.sort_by_key(|p| p.id.distance(target));
+    result.closest.truncate(config.k);
+    result
+}
+
+/// Convert a peer from a FIND_NODE response, enforcing key binding
+fn verified_peer(compact: CompactPeer, config: &LookupConfig) -> Option<DhtPeer> {
+    let mut peer = DhtPeer::new(compact.id, compact.addr);
+    peer.public_key = compact.public_key;
+    if config.require_bound_ids && !peer.is_bound() {
+        return None;
+    }
+    Some(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Network where each node answers with a fixed peer list
+    #[derive(Default)]
+    struct StaticNetwork {
+        answers: HashMap<NodeId, Vec<CompactPeer>>,
+        asked: Mutex<Vec<NodeId>>,
+    }
+
+    impl FindNodeRpc for StaticNetwork {
+        async fn find_node(
+            &self,
+            peer: &DhtPeer,
+            _target: &NodeId,
+        ) -> Result<Vec<CompactPeer>, OperationError> {
+            self.asked.lock().unwrap().push(peer.id);
+            self.answers
+                .get(&peer.id)
+                .cloned()
+                .ok_or(OperationError::Timeout)
+        }
+    }
+
+    fn peer(seed: u8) -> DhtPeer {
+        DhtPeer::from_public_key([seed; 32], format!("192.0.2.{seed}:8000").parse().unwrap())
+    }
+
+    fn compact(peer: &DhtPeer) -> CompactPeer {
+        CompactPeer {
+            id: peer.id,
+            addr: peer.addr,
+            public_key: peer.public_key,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookup_follows_bound_peers_only() {
+        let seed = peer(1);
+        let bound = peer(2);
+        let mut unbound = compact(&peer(3));
+        unbound.id = NodeId::from_bytes([0u8; 32]);
+        let target = unbound.id;
+
+        let mut network = StaticNetwork::default();
+        network
+            .answers
+            .insert(seed.id, vec![compact(&bound), unbound]);
+        network.answers.insert(bound.id, vec![]);
+
+        let result = disjoint_lookup(
+            &NodeId::random(),
+            vec![seed.clone()],
+            &target,
+            &network,
+            &LookupConfig::default(),
+        )
+        .await;
+
+        let responded: HashSet<_> = result.responded.iter().map(|p| p.id).collect();
+        assert_eq!(responded, HashSet::from([seed.id, bound.id]));
+        assert_eq!(result.queries, 2);
+        assert!(result.failed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_paths_never_share_nodes() {
+        let seeds: Vec<_> = (1..=6).map(peer).collect();
+        let shared = peer(50);
+
+        // Every seed points at the same node
+        let mut network = StaticNetwork::default();
+        for seed in &seeds {
+            network.answers.insert(seed.id, vec![compact(&shared)]);
+        }
+        network.answers.insert(shared.id, vec![]);
+
+        let result = disjoint_lookup(
+            &NodeId::random(),
+            seeds,
+            &NodeId::random(),
+            &network,
+            &LookupConfig::default(),
+        )
+        .await;
+
+        let asked = network.asked.lock().unwrap();
+        assert_eq!(asked.iter().filter(|id| **id == shared.id).count(), 1);
+        assert_eq!(asked.len(), 7);
+        assert_eq!(result.closest.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_peers_are_reported() {
+        let alive = peer(1);
+        let dead = peer(2);
+
+        let mut network = StaticNetwork::default();
+        network.answers.insert(alive.id, vec![]);
+
+        let result = disjoint_lookup(
+            &NodeId::random(),
+            vec![alive.clone(), dead.clone()],
+            &NodeId::random(),
+            &network,
+            &LookupConfig::default(),
+        )
+        .await;
+
+        assert_eq!(result.failed, vec![dead.id]);
+        assert_eq!(result.closest.len(), 1);
+        assert_eq!(result.closest[0].id, alive.id);
+    }
+}
@@ -1,15 +1,33 @@
 //! DHT Protocol Messages
 //!
 //! This module defines the message types used in the Kademlia DHT protocol:
-//! - PING/PONG: Liveness checks and RTT measurement
+//! - PING/PONG: Liveness checks, RTT measurement and proof of key possession
 //! - FIND_NODE: Locate peers close to a target NodeId
 //! - FIND_VALUE: Retrieve a stored value or closest peers
-//! - STORE: Store a key-value pair in the DHT
+//! - STORE: Store a signed record in the DHT
 
 use super::node_id::NodeId;
+use super::record::SignedRecord;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use thiserror::Error;
+use wraith_crypto::signatures::{Signature, VerifyingKey};
+
+/// Domain separation prefix for PONG signatures
+const PONG_CONTEXT: &[u8] = b"wraith-dht-pong-v1";
+
+/// Bytes a node signs to answer a PING
+///
+/// The signature covers the PING's nonce and the pinging node's ID, so a
+/// PONG cannot be replayed to answer another challenge.
+#[must_use]
+pub fn pong_message(challenger: &NodeId, nonce: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(PONG_CONTEXT.len() + 40);
+    message.extend_from_slice(PONG_CONTEXT);
+    message.extend_from_slice(challenger.as_bytes());
+    message.extend_from_slice(&nonce.to_be_bytes());
+    message
+}
 
 /// DHT RPC message envelope
 ///
@@ -52,6 +70,7 @@ impl DhtMessage {
     /// let msg = DhtMessage::Ping(PingRequest {
     ///     sender_id: NodeId::random(),
     ///     sender_addr: "127.0.0.1:8000".parse().unwrap(),
+    ///     sender_key: [0u8; 32],
     ///     nonce: 12345,
     /// });
     ///
@@ -81,6 +100,7 @@ impl DhtMessage {
     /// let msg = DhtMessage::Ping(PingRequest {
     ///     sender_id: NodeId::random(),
     ///     sender_addr: "127.0.0.1:8000".parse().unwrap(),
+    ///     sender_key: [0u8; 32],
     ///     nonce: 12345,
     /// });
     ///
@@ -189,19 +209,42 @@ pub struct PingRequest {
     pub sender_id: NodeId,
     /// Sender's network address
     pub sender_addr: SocketAddr,
+    /// Sender's public key; `sender_id` must be derived from it
+    pub sender_key: [u8; 32],
     /// Nonce for matching response
     pub nonce: u64,
 }
 
 /// Pong response
 ///
-/// Response to a ping request.
+/// Response to a ping request. A signed PONG proves that the responder holds
+/// the key its ID is derived from.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PongResponse {
     /// Responder's node ID
     pub sender_id: NodeId,
     /// Echoed nonce from ping request
     pub nonce: u64,
+    /// Responder's Ed25519 public key; `sender_id` must be derived from it
+    pub sender_key: [u8; 32],
+    /// Signature over [`pong_message`]; empty if the responder has no key
+    pub signature: Vec<u8>,
+}
+
+impl PongResponse {
+    /// Check that this PONG answers the PING `challenger` sent with `nonce`
+    /// and is signed by the key `sender_id` is derived from
+    #[must_use]
+    pub fn verify(&self, challenger: &NodeId, nonce: u64) -> bool {
+        self.nonce == nonce
+            && NodeId::from_public_key(&self.sender_key) == self.sender_id
+            && VerifyingKey::from_bytes(&self.sender_key).is_ok_and(|key| {
+                Signature::from_slice(&self.signature).is_ok_and(|signature| {
+                    key.verify(&pong_message(challenger, nonce), &signature)
+                        .is_ok()
+                })
+            })
+    }
 }
 
 /// Find node request
@@ -213,6 +256,8 @@ pub struct FindNodeRequest {
     pub sender_id: NodeId,
     /// Sender's network address
     pub sender_addr: SocketAddr,
+    /// Sender's public key; `sender_id` must be derived from it
+    pub sender_key: [u8; 32],
     /// Target node ID to find
     pub target_id: NodeId,
 }
@@ -237,21 +282,28 @@ pub struct CompactPeer {
     pub id: NodeId,
     /// Peer's network address
     pub addr: SocketAddr,
+    /// Peer's public key, if the responder knows it
+    ///
+    /// Hardened lookups only follow peers whose ID is derived from this key.
+    pub public_key: Option<[u8; 32]>,
 }
 
 /// Store request
 ///
-/// Requests that a peer store a key-value pair.
+/// Requests that a peer store a signed record. The record must verify under
+/// `key` and supersede any record the peer already holds there.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreRequest {
     /// Sender's node ID
     pub sender_id: NodeId,
     /// Sender's network address
     pub sender_addr: SocketAddr,
-    /// 32-byte storage key
+    /// Sender's public key; `sender_id` must be derived from it
+    pub sender_key: [u8; 32],
+    /// 32-byte storage key (see [`SignedRecord::storage_key`])
     pub key: [u8; 32],
-    /// Value data to store
-    pub value: Vec<u8>,
+    /// Record to store
+    pub record: SignedRecord,
     /// Time-to-live in seconds
     pub ttl: u64,
 }
@@ -276,6 +328,8 @@ pub struct FindValueRequest {
     pub sender_id: NodeId,
     /// Sender's network address
     pub sender_addr: SocketAddr,
+    /// Sender's public key; `sender_id` must be derived from it
+    pub sender_key: [u8; 32],
     /// 32-byte key to look up
     pub key: [u8; 32],
 }
@@ -289,8 +343,8 @@ pub enum FoundValueResponse {
     Value {
         /// Responder's node ID
         sender_id: NodeId,
-        /// The stored value
-        value: Vec<u8>,
+        /// The stored record; requesters must verify it against the key
+        record: SignedRecord,
     },
     /// Value not found, here are closer peers
     Peers {
@@ -328,12 +382,18 @@ pub enum MessageError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wraith_crypto::signatures::SigningKey;
+
+    fn record(value: Vec<u8>) -> SignedRecord {
+        SignedRecord::sign(&SigningKey::from_bytes(&[5u8; 32]), vec![], 1, value)
+    }
 
     #[test]
     fn test_ping_serialization() {
         let msg = DhtMessage::Ping(PingRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         });
 
@@ -352,6 +412,7 @@ mod tests {
         let msg = DhtMessage::FindNode(FindNodeRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             target_id: target,
         });
 
@@ -366,14 +427,15 @@ mod tests {
 
     #[test]
     fn test_store_serialization() {
-        let key = [42u8; 32];
-        let value = vec![1, 2, 3, 4, 5];
+        let record = record(vec![1, 2, 3, 4, 5]);
+        let key = record.key();
 
         let msg = DhtMessage::Store(StoreRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             key,
-            value: value.clone(),
+            record: record.clone(),
             ttl: 3600,
         });
 
@@ -383,7 +445,8 @@ mod tests {
         match decoded {
             DhtMessage::Store(store) => {
                 assert_eq!(store.key, key);
-                assert_eq!(store.value, value);
+                assert_eq!(store.record, record);
+                assert!(store.record.verify_for_key(&key).is_ok());
                 assert_eq!(store.ttl, 3600);
             }
             _ => panic!("Wrong message type"),
@@ -394,15 +457,15 @@ mod tests {
     fn test_found_value_response() {
         let value_resp = DhtMessage::FoundValue(FoundValueResponse::Value {
             sender_id: NodeId::random(),
-            value: vec![1, 2, 3],
+            record: record(vec![1, 2, 3]),
         });
 
         let bytes = value_resp.to_bytes().unwrap();
         let decoded = DhtMessage::from_bytes(&bytes).unwrap();
 
         match decoded {
-            DhtMessage::FoundValue(FoundValueResponse::Value { value, .. }) => {
-                assert_eq!(value, vec![1, 2, 3]);
+            DhtMessage::FoundValue(FoundValueResponse::Value { record, .. }) => {
+                assert_eq!(record.value, vec![1, 2, 3]);
             }
             _ => panic!("Wrong message type"),
         }
@@ -412,6 +475,7 @@ mod tests {
             peers: vec![CompactPeer {
                 id: NodeId::random(),
                 addr: "127.0.0.1:8000".parse().unwrap(),
+                public_key: None,
             }],
         });
 
@@ -431,6 +495,7 @@ mod tests {
         let msg = DhtMessage::Ping(PingRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         });
 
@@ -450,6 +515,7 @@ mod tests {
         let msg = DhtMessage::Ping(PingRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         });
 
@@ -476,6 +542,7 @@ mod tests {
         let ping = DhtMessage::Ping(PingRequest {
             sender_id: sender,
             sender_addr: "127.0.0.1:8000".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         });
 
@@ -484,6 +551,8 @@ mod tests {
         let pong = DhtMessage::Pong(PongResponse {
             sender_id: sender,
             nonce: 12345,
+            sender_key: [0u8; 32],
+            signature: Vec::new(),
         });
 
         assert_eq!(pong.sender_id(), Some(sender));
@@ -495,15 +564,19 @@ mod tests {
             DhtMessage::Ping(PingRequest {
                 sender_id: NodeId::random(),
                 sender_addr: "127.0.0.1:8000".parse().unwrap(),
+                sender_key: [0u8; 32],
                 nonce: 1,
             }),
             DhtMessage::Pong(PongResponse {
                 sender_id: NodeId::random(),
                 nonce: 1,
+                sender_key: [0u8; 32],
+                signature: vec![0u8; 64],
             }),
             DhtMessage::FindNode(FindNodeRequest {
                 sender_id: NodeId::random(),
                 sender_addr: "127.0.0.1:8000".parse().unwrap(),
+                sender_key: [0u8; 32],
                 target_id: NodeId::random(),
             }),
             DhtMessage::FoundNodes(FoundNodesResponse {
@@ -513,8 +586,9 @@ mod tests {
             DhtMessage::Store(StoreRequest {
                 sender_id: NodeId::random(),
                 sender_addr: "127.0.0.1:8000".parse().unwrap(),
-                key: [0u8; 32],
-                value: vec![],
+                sender_key: [0u8; 32],
+                key: record(vec![]).key(),
+                record: record(vec![]),
                 ttl: 3600,
             }),
             DhtMessage::StoreAck(StoreAckResponse {
@@ -524,11 +598,12 @@ mod tests {
             DhtMessage::FindValue(FindValueRequest {
                 sender_id: NodeId::random(),
                 sender_addr: "127.0.0.1:8000".parse().unwrap(),
+                sender_key: [0u8; 32],
                 key: [0u8; 32],
             }),
             DhtMessage::FoundValue(FoundValueResponse::Value {
                 sender_id: NodeId::random(),
-                value: vec![1, 2, 3],
+                record: record(vec![1, 2, 3]),
             }),
             DhtMessage::FoundValue(FoundValueResponse::Peers {
                 sender_id: NodeId::random(),
@@ -10,7 +10,10 @@
 //! - Iterative lookup with alpha parallelism (α=3)
 //! - Bootstrap mechanism for network join
 //! - S/Kademlia Sybil resistance with crypto puzzles (SEC-001)
+//! - Eclipse resistance: per-subnet bucket limits, key-bound node IDs,
+//!   long-lived peer preference and disjoint-path lookups
 //! - Privacy-enhanced key derivation with group secrets (SEC-002)
+//! - Signed mutable records with sequence numbers (BEP-44 style)
 //!
 //! # Example Usage
 //!
@@ -38,14 +41,19 @@ use zeroize::{Zeroize, ZeroizeOnDrop};
 
 // Module declarations
 pub mod bootstrap;
+pub mod lookup;
 pub mod messages;
 pub mod node;
 pub mod node_id;
 pub mod operations;
+pub mod record;
 pub mod routing;
 
 // Re-exports for convenience
 pub use bootstrap::{Bootstrap, BootstrapConfig, BootstrapError, BootstrapNode};
+pub use lookup::{
+    DISJOINT_PATHS, FindNodeRpc, FindValueRpc, LookupConfig, LookupResult, disjoint_lookup,
+};
 pub use messages::{
     CompactPeer, DhtMessage, FindNodeRequest, FindValueRequest, FoundNodesResponse,
     FoundValueResponse, MessageError, PingRequest, PongResponse, StoreAckResponse, StoreRequest,
@@ -53,7 +61,8 @@ pub use messages::{
 pub use node::{DhtNode, NodeState, StoredValue};
 pub use node_id::{NodeId, SybilResistance};
 pub use operations::{ALPHA, DhtOperations, OperationError};
-pub use routing::{DhtError, DhtPeer, K, KBucket, NUM_BUCKETS, RoutingTable};
+pub use record::{MAX_SALT_SIZE, MAX_VALUE_SIZE, RecordError, SignedRecord};
+pub use routing::{DhtError, DhtPeer, K, KBucket, MAX_PEERS_PER_SUBNET, NUM_BUCKETS, RoutingTable};
 
 // SEC-002: Privacy exports (DhtPrivacy and GroupSecret are defined below in this file)
 
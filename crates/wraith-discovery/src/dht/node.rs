@@ -3,14 +3,25 @@
 //! This module defines the main DhtNode structure which maintains:
 //! - Node identity and network address
 //! - Routing table for peer discovery
-//! - Local key-value storage
+//! - Local key-value storage and signed records
+//! - Challenges proving that senders hold their keys
 //! - Node state tracking
 
+use super::messages::{DhtMessage, PingRequest, PongResponse, pong_message};
 use super::node_id::NodeId;
-use super::routing::RoutingTable;
+use super::record::{RecordError, SignedRecord};
+use super::routing::{DhtPeer, RoutingTable};
 use std::collections::HashMap;
+use std::fmt;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
+use wraith_crypto::signatures::SigningKey;
+
+/// Time a sender has to answer a key-possession challenge
+pub const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum challenges awaiting an answer
+pub const MAX_PENDING_CHALLENGES: usize = 256;
 
 /// DHT node state
 ///
@@ -46,6 +57,8 @@ pub struct StoredValue {
     pub stored_at: Instant,
     /// Time-to-live for this value
     pub ttl: Duration,
+    /// Signed record the data came from; `None` for local-only values
+    pub record: Option<SignedRecord>,
 }
 
 impl StoredValue {
@@ -70,6 +83,18 @@ impl StoredValue {
             data,
             stored_at: Instant::now(),
             ttl,
+            record: None,
+        }
+    }
+
+    /// Create a stored value holding a signed record
+    #[must_use]
+    pub fn signed(record: SignedRecord, ttl: Duration) -> Self {
+        Self {
+            data: record.value.clone(),
+            stored_at: Instant::now(),
+            ttl,
+            record: Some(record),
         }
     }
 
@@ -105,20 +130,48 @@ impl StoredValue {
     }
 }
 
+/// Key-possession challenge sent to a sender
+#[derive(Debug)]
+struct PendingChallenge {
+    /// Peer as claimed by the sender
+    peer: DhtPeer,
+    /// Nonce the PONG must be signed over
+    nonce: u64,
+    /// When the challenge was sent
+    issued: Instant,
+}
+
 /// DHT node
 ///
 /// The main DHT node structure that maintains routing state, local storage,
 /// and handles DHT operations.
-#[derive(Debug)]
 pub struct DhtNode {
     /// This node's identifier
     id: NodeId,
     /// This node's network address
     addr: SocketAddr,
+    /// Key this node's ID is derived from, used to answer challenges
+    signing_key: Option<SigningKey>,
     /// Routing table for peer discovery
     routing_table: RoutingTable,
     /// Local key-value storage
     storage: HashMap<[u8; 32], StoredValue>,
+    /// Challenges awaiting a PONG, by the address they were sent to
+    challenges: HashMap<SocketAddr, PendingChallenge>,
+    /// Challenge PINGs waiting to be sent
+    outbox: Vec<(SocketAddr, DhtMessage)>,
+}
+
+impl fmt::Debug for DhtNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DhtNode")
+            .field("id", &self.id)
+            .field("addr", &self.addr)
+            .field("routing_table", &self.routing_table)
+            .field("storage", &self.storage)
+            .field("challenges", &self.challenges)
+            .finish_non_exhaustive()
+    }
 }
 
 impl DhtNode {
@@ -143,11 +196,48 @@ impl DhtNode {
         Self {
             id,
             addr,
+            signing_key: None,
             routing_table: RoutingTable::new(id),
             storage: HashMap::new(),
+            challenges: HashMap::new(),
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Create a DHT node whose ID is derived from an Ed25519 key
+    ///
+    /// Only such nodes can answer the challenges other nodes send before
+    /// adding them to their routing tables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wraith_discovery::dht::{DhtNode, NodeId};
+    /// use wraith_crypto::signatures::SigningKey;
+    ///
+    /// let signing_key = SigningKey::from_bytes(&[1u8; 32]);
+    /// let public_key = signing_key.verifying_key().to_bytes();
+    /// let node = DhtNode::with_signing_key(signing_key, "127.0.0.1:8000".parse().unwrap());
+    /// assert_eq!(node.id(), &NodeId::from_public_key(&public_key));
+    /// assert_eq!(node.public_key(), Some(public_key));
+    /// ```
+    #[must_use]
+    pub fn with_signing_key(signing_key: SigningKey, addr: SocketAddr) -> Self {
+        let id = NodeId::from_public_key(&signing_key.verifying_key().to_bytes());
+        Self {
+            signing_key: Some(signing_key),
+            ..Self::new(id, addr)
         }
     }
 
+    /// Public key this node's ID is derived from, if it has one
+    #[must_use]
+    pub fn public_key(&self) -> Option<[u8; 32]> {
+        self.signing_key
+            .as_ref()
+            .map(|key| key.verifying_key().to_bytes())
+    }
+
     /// Get this node's identifier
     ///
     /// # Returns
@@ -207,7 +297,11 @@ impl DhtNode {
         &mut self.routing_table
     }
 
-    /// Store a value in the DHT
+    /// Store a value in local storage
+    ///
+    /// Values stored this way are unsigned and are never served to other
+    /// nodes; use [`put_record`](Self::put_record) for values published in
+    /// the DHT.
     ///
     /// # Arguments
     ///
@@ -264,6 +358,57 @@ impl DhtNode {
         })
     }
 
+    /// Store a signed record
+    ///
+    /// The record must verify under `key` and supersede any record already
+    /// stored there.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record is invalid, belongs under a different
+    /// key, or is stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wraith_discovery::dht::{DhtNode, NodeId, SignedRecord};
+    /// use wraith_crypto::signatures::SigningKey;
+    /// use std::time::Duration;
+    ///
+    /// let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+    /// let record = SignedRecord::sign(&SigningKey::from_bytes(&[1u8; 32]), vec![], 1, vec![1, 2, 3]);
+    /// let key = record.key();
+    ///
+    /// node.put_record(key, record, Duration::from_secs(3600)).unwrap();
+    /// assert_eq!(node.get_record(&key).unwrap().value, vec![1, 2, 3]);
+    /// ```
+    pub fn put_record(
+        &mut self,
+        key: [u8; 32],
+        record: SignedRecord,
+        ttl: Duration,
+    ) -> Result<(), RecordError> {
+        record.verify_for_key(&key)?;
+        if let Some(stored) = self.get_record(&key) {
+            record.check_supersedes(&stored)?;
+        }
+        self.storage.insert(key, StoredValue::signed(record, ttl));
+        Ok(())
+    }
+
+    /// Retrieve a signed record from local storage
+    ///
+    /// # Returns
+    ///
+    /// The record if one is stored under `key` and has not expired
+    #[must_use]
+    pub fn get_record(&self, key: &[u8; 32]) -> Option<SignedRecord> {
+        self.storage
+            .get(key)
+            .filter(|stored| !stored.is_expired())
+            .and_then(|stored| stored.record.clone())
+    }
+
     /// Remove a value from local storage
     ///
     /// # Arguments
@@ -317,6 +462,92 @@ impl DhtNode {
         self.storage.len()
     }
 
+    /// Answer a PING, signing the nonce if this node has a key
+    #[must_use]
+    pub(crate) fn pong(&self, request: &PingRequest) -> PongResponse {
+        PongResponse {
+            sender_id: self.id,
+            nonce: request.nonce,
+            sender_key: self.public_key().unwrap_or_default(),
+            signature: self
+                .signing_key
+                .as_ref()
+                .map(|key| {
+                    key.sign(&pong_message(&request.sender_id, request.nonce))
+                        .as_bytes()
+                        .to_vec()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Challenge `peer` to prove that it holds its key at its address
+    ///
+    /// Queues a PING with a fresh nonce for [`take_challenges`](Self::take_challenges).
+    /// At most one challenge is outstanding per address.
+    pub(crate) fn challenge(&mut self, peer: DhtPeer) {
+        self.challenges
+            .retain(|_, pending| pending.issued.elapsed() < CHALLENGE_TIMEOUT);
+        if self.challenges.contains_key(&peer.addr)
+            || self.challenges.len() >= MAX_PENDING_CHALLENGES
+        {
+            return;
+        }
+
+        let nonce = rand::random();
+        let addr = peer.addr;
+        self.outbox.push((
+            addr,
+            DhtMessage::Ping(PingRequest {
+                sender_id: self.id,
+                sender_addr: self.addr,
+                sender_key: self.public_key().unwrap_or_default(),
+                nonce,
+            }),
+        ));
+        self.challenges.insert(
+            addr,
+            PendingChallenge {
+                peer,
+                nonce,
+                issued: Instant::now(),
+            },
+        );
+    }
+
+    /// Complete the challenge sent to `from` if `pong` answers it
+    ///
+    /// # Returns
+    ///
+    /// The challenged peer, marked verified, if the PONG is signed by the
+    /// claimed key over the challenge nonce
+    pub(crate) fn complete_challenge(
+        &mut self,
+        pong: &PongResponse,
+        from: SocketAddr,
+    ) -> Option<DhtPeer> {
+        let pending = self.challenges.get(&from)?;
+        if pending.nonce != pong.nonce || pending.peer.id != pong.sender_id {
+            return None;
+        }
+        let pending = self.challenges.remove(&from)?;
+        if pending.issued.elapsed() >= CHALLENGE_TIMEOUT || !pong.verify(&self.id, pending.nonce) {
+            return None;
+        }
+        Some(DhtPeer {
+            verified: true,
+            ..pending.peer
+        })
+    }
+
+    /// Take the challenge PINGs queued while handling messages
+    ///
+    /// The transport should send each message to its address. A sender is
+    /// only added to the routing table once it answers with a signed PONG.
+    pub fn take_challenges(&mut self) -> Vec<(SocketAddr, DhtMessage)> {
+        std::mem::take(&mut self.outbox)
+    }
+
     /// Prune the routing table and storage
     ///
     /// Removes dead peers from the routing table and expired values
@@ -410,6 +641,38 @@ mod tests {
         assert_eq!(retrieved, None);
     }
 
+    #[test]
+    fn test_dht_node_put_record() {
+        use wraith_crypto::signatures::SigningKey;
+
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        let signing_key = SigningKey::from_bytes(&[3u8; 32]);
+        let v1 = SignedRecord::sign(&signing_key, b"addr".to_vec(), 1, vec![1]);
+        let v2 = SignedRecord::sign(&signing_key, b"addr".to_vec(), 2, vec![2]);
+        let key = v1.key();
+        let ttl = Duration::from_secs(3600);
+
+        node.put_record(key, v1.clone(), ttl).unwrap();
+        assert_eq!(node.get(&key), Some(vec![1]));
+
+        node.put_record(key, v2.clone(), ttl).unwrap();
+        assert_eq!(node.get_record(&key), Some(v2));
+
+        // Older sequence numbers cannot roll the record back
+        assert_eq!(
+            node.put_record(key, v1, ttl),
+            Err(RecordError::StaleSequence {
+                stored: 2,
+                received: 1
+            })
+        );
+        assert_eq!(node.get(&key), Some(vec![2]));
+
+        // Local unsigned values are not records
+        node.store([9u8; 32], vec![9], ttl);
+        assert_eq!(node.get_record(&[9u8; 32]), None);
+    }
+
     #[test]
     fn test_dht_node_remove() {
         let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
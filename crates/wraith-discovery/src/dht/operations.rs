@@ -6,13 +6,17 @@
 //! - Value retrieval (FIND_VALUE)
 //!
 //! All operations use the iterative lookup algorithm with alpha parallelism.
+//! Values are [`SignedRecord`]s, verified when stored, again when served, and
+//! by the requester on every FIND_VALUE response.
 
+use super::lookup::{FindNodeRpc, FindValueRpc, LookupConfig, LookupResult, disjoint_lookup};
 use super::messages::*;
 use super::node::DhtNode;
 use super::node_id::NodeId;
+use super::record::SignedRecord;
 use super::routing::{DhtError, DhtPeer, K};
-use std::collections::HashSet;
 use std::net::SocketAddr;
+use std::sync::{Mutex, PoisonError};
 use std::time::Duration;
 use thiserror::Error;
 
@@ -25,7 +29,7 @@ pub const ALPHA: usize = 3;
 /// Maximum iterations for iterative lookup
 ///
 /// Prevents infinite loops in pathological cases.
-const MAX_ITERATIONS: usize = 20;
+pub(crate) const MAX_ITERATIONS: usize = 20;
 
 /// Timeout for individual RPC requests
 #[allow(dead_code)]
@@ -81,31 +85,31 @@ pub trait DhtOperations {
         target: &NodeId,
     ) -> impl std::future::Future<Output = Result<Vec<DhtPeer>, OperationError>> + Send;
 
-    /// Store a value in the DHT
+    /// Store a signed record in the DHT
     ///
-    /// Finds the K closest nodes to the key and stores the value on all of them.
-    /// Succeeds if at least one node confirms storage.
+    /// Finds the K closest nodes to the record's key and stores it on all of
+    /// them. Succeeds if at least one node confirms storage.
     ///
     /// # Arguments
     ///
-    /// * `key` - 32-byte storage key
-    /// * `value` - Value data
-    /// * `ttl` - Time-to-live for the value
+    /// * `record` - Record to publish
+    /// * `ttl` - Time-to-live for the record
     ///
     /// # Errors
     ///
     /// Returns error if no nodes confirm storage
     fn store(
         &mut self,
-        key: [u8; 32],
-        value: Vec<u8>,
+        record: SignedRecord,
         ttl: Duration,
     ) -> impl std::future::Future<Output = Result<(), OperationError>> + Send;
 
-    /// Retrieve a value from the DHT
+    /// Retrieve a signed record from the DHT
     ///
-    /// Performs iterative lookup for the value, querying nodes until
-    /// the value is found or all close nodes have been queried.
+    /// Performs iterative lookup for the record, querying nodes until
+    /// the record is found or all close nodes have been queried. Records
+    /// that fail verification against `key` are discarded, and the highest
+    /// sequence number seen wins.
     ///
     /// # Arguments
     ///
@@ -113,28 +117,24 @@ pub trait DhtOperations {
     ///
     /// # Returns
     ///
-    /// The stored value if found
+    /// The newest valid record if found
     ///
     /// # Errors
     ///
-    /// Returns error if value not found or lookup fails
+    /// Returns error if no valid record is found or lookup fails
     fn find_value(
         &mut self,
         key: [u8; 32],
-    ) -> impl std::future::Future<Output = Result<Vec<u8>, OperationError>> + Send;
+    ) -> impl std::future::Future<Output = Result<SignedRecord, OperationError>> + Send;
 }
 
 impl DhtNode {
-    /// Perform iterative node lookup
-    ///
-    /// This is a reference implementation showing the iterative lookup algorithm.
-    /// In a real implementation, this would use actual network transport.
+    /// Find the closest known peers to a target
     ///
-    /// Algorithm:
-    /// 1. Start with K closest known peers
-    /// 2. Query up to α closest unqueried peers in parallel
-    /// 3. Merge responses into closest list
-    /// 4. Repeat until no closer peers found or all queried
+    /// Without a network transport this returns the K closest peers in the
+    /// routing table. Use
+    /// [`iterative_find_node_with`](Self::iterative_find_node_with) to run a
+    /// lookup over the network.
     ///
     /// # Arguments
     ///
@@ -143,46 +143,90 @@ impl DhtNode {
     /// # Returns
     ///
     /// Vector of up to K closest peers found
-    #[allow(clippy::never_loop)]
     pub async fn iterative_find_node(&mut self, target: &NodeId) -> Vec<DhtPeer> {
-        let mut queried = HashSet::new();
-        let closest = self.routing_table().closest_peers(target, K);
-
-        for _iteration in 0..MAX_ITERATIONS {
-            // Find up to α unqueried peers
-            let to_query: Vec<_> = closest
-                .iter()
-                .filter(|p| !queried.contains(&p.id))
-                .take(ALPHA)
-                .cloned()
-                .collect();
-
-            if to_query.is_empty() {
-                break; // No more peers to query
-            }
+        self.routing_table().closest_peers(target, K)
+    }
 
-            // Mark as queried
-            for peer in &to_query {
-                queried.insert(peer.id);
-            }
+    /// Perform an iterative node lookup over the network
+    ///
+    /// Runs a disjoint-path lookup (see [`lookup`](super::lookup)) seeded
+    /// from the routing table. Bound peers that answer are added to the
+    /// routing table, and peers that fail to answer are recorded as failures
+    /// so repeatedly unresponsive peers give way to cached replacements.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The NodeId to find closest nodes to
+    /// * `rpc` - Transport used to send FIND_NODE requests
+    /// * `config` - Lookup parameters
+    ///
+    /// # Returns
+    ///
+    /// Up to `config.k` peers that answered, closest to the target first
+    pub async fn iterative_find_node_with<R: FindNodeRpc>(
+        &mut self,
+        target: &NodeId,
+        rpc: &R,
+        config: &LookupConfig,
+    ) -> Vec<DhtPeer> {
+        let seeds = self.routing_table().closest_peers(target, config.k);
+        let result = disjoint_lookup(self.id(), seeds, target, rpc, config).await;
+        self.apply_lookup_result(result)
+    }
 
-            // In a real implementation, we would send FIND_NODE RPCs here
-            // and wait for responses. For now, this is a stub that shows
-            // the algorithm structure.
-            //
-            // let mut responses = Vec::new();
-            // for peer in &to_query {
-            //     if let Ok(response) = self.send_find_node_rpc(peer, target).await {
-            //         responses.push(response);
-            //     }
-            // }
-
-            // Merge responses would happen here
-            // For now, we just return what we have
-            break;
+    /// Look up a signed record over the network
+    ///
+    /// Runs the same disjoint-path lookup as
+    /// [`iterative_find_node_with`](Self::iterative_find_node_with) with
+    /// FIND_VALUE requests. Every record a peer returns is verified against
+    /// `key`; a peer answering with a forged or misfiled record is treated
+    /// as having failed to answer. Of the valid records, and any stored
+    /// locally, the one with the highest sequence number is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - 32-byte key to look up
+    /// * `rpc` - Transport used to send FIND_VALUE requests
+    /// * `config` - Lookup parameters
+    ///
+    /// # Errors
+    ///
+    /// Returns [`OperationError::ValueNotFound`] if no valid record is found
+    pub async fn iterative_find_value_with<R: FindValueRpc + Sync>(
+        &mut self,
+        key: [u8; 32],
+        rpc: &R,
+        config: &LookupConfig,
+    ) -> Result<SignedRecord, OperationError> {
+        let target = NodeId::from_bytes(key);
+        let lookup = ValueLookup {
+            key,
+            rpc,
+            newest: Mutex::new(self.get_record(&key)),
+        };
+        let seeds = self.routing_table().closest_peers(&target, config.k);
+        let result = disjoint_lookup(self.id(), seeds, &target, &lookup, config).await;
+        self.apply_lookup_result(result);
+
+        lookup
+            .newest
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .ok_or(OperationError::ValueNotFound)
+    }
+
+    /// Learn responders and charge failures from a finished lookup
+    fn apply_lookup_result(&mut self, result: LookupResult) -> Vec<DhtPeer> {
+        for id in &result.failed {
+            self.routing_table_mut().record_failure(id);
+        }
+        for peer in result.responded {
+            if peer.is_bound() || self.routing_table().get_peer(&peer.id).is_some() {
+                let _ = self.routing_table_mut().insert(peer);
+            }
         }
 
-        closest.into_iter().take(K).collect()
+        result.closest
     }
 
     /// Handle incoming FIND_NODE request
@@ -205,6 +249,7 @@ impl DhtNode {
             .map(|p| CompactPeer {
                 id: p.id,
                 addr: p.addr,
+                public_key: p.public_key,
             })
             .collect();
 
@@ -216,7 +261,9 @@ impl DhtNode {
 
     /// Handle incoming STORE request
     ///
-    /// Stores the value in local storage if there's capacity.
+    /// Stores the record if it is correctly signed, belongs under the
+    /// requested key and supersedes any record already stored there.
+    /// Unsigned, forged and stale records are rejected.
     ///
     /// # Arguments
     ///
@@ -228,17 +275,24 @@ impl DhtNode {
     #[must_use]
     pub fn handle_store(&mut self, request: StoreRequest) -> StoreAckResponse {
         let ttl = Duration::from_secs(request.ttl);
-        self.store(request.key, request.value, ttl);
+        let stored = match self.put_record(request.key, request.record, ttl) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!("Rejected STORE from {}: {}", request.sender_id, e);
+                false
+            }
+        };
 
         StoreAckResponse {
             sender_id: *self.id(),
-            stored: true,
+            stored,
         }
     }
 
     /// Handle incoming FIND_VALUE request
     ///
-    /// Returns the value if stored locally, otherwise returns closest peers.
+    /// Returns the record if one is stored locally and still verifies,
+    /// otherwise returns closest peers. Local unsigned values are never served.
     ///
     /// # Arguments
     ///
@@ -249,11 +303,13 @@ impl DhtNode {
     /// Response with either the value or closest peers
     #[must_use]
     pub fn handle_find_value(&self, request: FindValueRequest) -> FoundValueResponse {
-        // Check if we have the value
-        if let Some(value) = self.get(&request.key) {
+        // Check if we have a valid record
+        if let Some(record) = self.get_record(&request.key)
+            && record.verify_for_key(&request.key).is_ok()
+        {
             return FoundValueResponse::Value {
                 sender_id: *self.id(),
-                value,
+                record,
             };
         }
 
@@ -266,6 +322,7 @@ impl DhtNode {
             .map(|p| CompactPeer {
                 id: p.id,
                 addr: p.addr,
+                public_key: p.public_key,
             })
             .collect();
 
@@ -277,7 +334,8 @@ impl DhtNode {
 
     /// Handle incoming PING request
     ///
-    /// Returns a PONG response with the echoed nonce.
+    /// Returns a PONG response with the echoed nonce, signed together with
+    /// the requester's ID if this node has a signing key.
     ///
     /// # Arguments
     ///
@@ -288,15 +346,16 @@ impl DhtNode {
     /// PONG response
     #[must_use]
     pub fn handle_ping(&self, request: PingRequest) -> PongResponse {
-        PongResponse {
-            sender_id: *self.id(),
-            nonce: request.nonce,
-        }
+        self.pong(&request)
     }
 
     /// Handle an incoming DHT message
     ///
     /// Routes the message to the appropriate handler and returns a response.
+    /// A sender whose NodeId is derived from its public key is challenged to
+    /// prove it holds that key (see [`take_challenges`](Self::take_challenges)),
+    /// and is added to the routing table, at the address the message came
+    /// from, once it answers with a signed PONG.
     ///
     /// # Arguments
     ///
@@ -307,45 +366,88 @@ impl DhtNode {
     ///
     /// Response message if one should be sent
     #[must_use]
-    pub fn handle_message(&mut self, message: DhtMessage, _from: SocketAddr) -> Option<DhtMessage> {
+    pub fn handle_message(&mut self, message: DhtMessage, from: SocketAddr) -> Option<DhtMessage> {
         match message {
             DhtMessage::Ping(ping) => {
-                // Update routing table
-                let peer = DhtPeer::new(ping.sender_id, ping.sender_addr);
-                let _ = self.routing_table_mut().insert(peer);
-
+                self.observe_sender(ping.sender_id, ping.sender_key, from);
                 Some(DhtMessage::Pong(self.handle_ping(ping)))
             }
 
             DhtMessage::FindNode(find) => {
-                // Update routing table
-                let peer = DhtPeer::new(find.sender_id, find.sender_addr);
-                let _ = self.routing_table_mut().insert(peer);
-
+                self.observe_sender(find.sender_id, find.sender_key, from);
                 Some(DhtMessage::FoundNodes(self.handle_find_node(find)))
             }
 
             DhtMessage::Store(store) => {
-                // Update routing table
-                let peer = DhtPeer::new(store.sender_id, store.sender_addr);
-                let _ = self.routing_table_mut().insert(peer);
-
+                self.observe_sender(store.sender_id, store.sender_key, from);
                 Some(DhtMessage::StoreAck(self.handle_store(store)))
             }
 
             DhtMessage::FindValue(find) => {
-                // Update routing table
-                let peer = DhtPeer::new(find.sender_id, find.sender_addr);
-                let _ = self.routing_table_mut().insert(peer);
-
+                self.observe_sender(find.sender_id, find.sender_key, from);
                 Some(DhtMessage::FoundValue(self.handle_find_value(find)))
             }
 
-            // Response messages don't generate new responses
-            DhtMessage::Pong(_)
-            | DhtMessage::FoundNodes(_)
-            | DhtMessage::StoreAck(_)
-            | DhtMessage::FoundValue(_) => None,
+            DhtMessage::Pong(pong) => {
+                if let Some(peer) = self.complete_challenge(&pong, from) {
+                    let _ = self.routing_table_mut().insert(peer);
+                }
+                None
+            }
+
+            // Other responses don't generate new responses
+            DhtMessage::FoundNodes(_) | DhtMessage::StoreAck(_) | DhtMessage::FoundValue(_) => None,
+        }
+    }
+
+    /// Refresh a verified sender of a request, or challenge an unverified one
+    fn observe_sender(&mut self, sender_id: NodeId, sender_key: [u8; 32], from: SocketAddr) {
+        let peer = DhtPeer::from_public_key(sender_key, from);
+        if peer.id != sender_id {
+            tracing::debug!("Ignoring DHT sender {} with unbound NodeId", sender_id);
+            return;
+        }
+        let proven = self
+            .routing_table()
+            .get_peer(&peer.id)
+            .is_some_and(|known| known.verified && known.addr == from);
+        if proven {
+            let _ = self.routing_table_mut().insert(DhtPeer {
+                verified: true,
+                ..peer
+            });
+        } else {
+            self.challenge(peer);
+        }
+    }
+}
+
+/// FIND_VALUE driven through the node lookup, keeping the newest valid record
+struct ValueLookup<'a, R> {
+    key: [u8; 32],
+    rpc: &'a R,
+    newest: Mutex<Option<SignedRecord>>,
+}
+
+impl<R: FindValueRpc + Sync> FindNodeRpc for ValueLookup<'_, R> {
+    async fn find_node(
+        &self,
+        peer: &DhtPeer,
+        _target: &NodeId,
+    ) -> Result<Vec<CompactPeer>, OperationError> {
+        match self.rpc.find_value(peer, &self.key).await? {
+            FoundValueResponse::Peers { peers, .. } => Ok(peers),
+            FoundValueResponse::Value { record, .. } => {
+                if let Err(e) = record.verify_for_key(&self.key) {
+                    tracing::debug!("Dropping record from DHT peer {}: {}", peer.id, e);
+                    return Err(OperationError::RpcFailed(format!("invalid record: {e}")));
+                }
+                let mut newest = self.newest.lock().unwrap_or_else(PoisonError::into_inner);
+                if newest.as_ref().is_none_or(|stored| record.seq > stored.seq) {
+                    *newest = Some(record);
+                }
+                Ok(Vec::new())
+            }
         }
     }
 }
@@ -353,6 +455,29 @@ impl DhtNode {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dht::record::RecordError;
+    use std::collections::{HashMap, HashSet};
+    use wraith_crypto::signatures::SigningKey;
+
+    fn signed(salt: &[u8], seq: u64, value: Vec<u8>) -> SignedRecord {
+        SignedRecord::sign(
+            &SigningKey::from_bytes(&[8u8; 32]),
+            salt.to_vec(),
+            seq,
+            value,
+        )
+    }
+
+    fn store_request(key: [u8; 32], record: SignedRecord) -> StoreRequest {
+        StoreRequest {
+            sender_id: NodeId::random(),
+            sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
+            key,
+            record,
+            ttl: 3600,
+        }
+    }
 
     #[test]
     fn test_handle_ping() {
@@ -361,6 +486,7 @@ mod tests {
         let request = PingRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:8001".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         };
 
@@ -386,6 +512,7 @@ mod tests {
         let request = FindNodeRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
             target_id: target,
         };
 
@@ -398,18 +525,11 @@ mod tests {
     fn test_handle_store() {
         let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
 
-        let key = [42u8; 32];
         let value = vec![1, 2, 3, 4, 5];
+        let record = signed(b"", 1, value.clone());
+        let key = record.key();
 
-        let request = StoreRequest {
-            sender_id: NodeId::random(),
-            sender_addr: "127.0.0.1:9000".parse().unwrap(),
-            key,
-            value: value.clone(),
-            ttl: 3600,
-        };
-
-        let response = node.handle_store(request);
+        let response = node.handle_store(store_request(key, record));
         assert!(response.stored);
 
         // Verify value was stored
@@ -417,31 +537,99 @@ mod tests {
         assert_eq!(retrieved, Some(value));
     }
 
+    #[test]
+    fn test_handle_store_rejects_invalid_records() {
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        let record = signed(b"", 1, vec![1, 2, 3]);
+        let key = record.key();
+
+        // Unsigned
+        let mut unsigned = record.clone();
+        unsigned.signature.clear();
+        assert!(!node.handle_store(store_request(key, unsigned)).stored);
+
+        // Value altered after signing
+        let mut tampered = record.clone();
+        tampered.value = vec![6, 6, 6];
+        assert!(!node.handle_store(store_request(key, tampered)).stored);
+
+        // Valid record sent under someone else's key
+        assert!(!node.handle_store(store_request([42u8; 32], record)).stored);
+
+        assert_eq!(node.storage_count(), 0);
+    }
+
+    #[test]
+    fn test_handle_store_rejects_stale_records() {
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        let v1 = signed(b"", 1, vec![1]);
+        let v2 = signed(b"", 2, vec![2]);
+        let key = v1.key();
+
+        assert!(node.handle_store(store_request(key, v2.clone())).stored);
+        assert!(!node.handle_store(store_request(key, v1.clone())).stored);
+        assert_eq!(
+            node.put_record(key, v1, Duration::from_secs(60)),
+            Err(RecordError::StaleSequence {
+                stored: 2,
+                received: 1
+            })
+        );
+
+        // Republishing the same record refreshes it
+        assert!(node.handle_store(store_request(key, v2)).stored);
+        assert_eq!(node.get(&key), Some(vec![2]));
+    }
+
     #[test]
     fn test_handle_find_value_found() {
         let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
 
-        let key = [42u8; 32];
         let value = vec![1, 2, 3, 4, 5];
+        let record = signed(b"", 1, value.clone());
+        let key = record.key();
 
         // Store the value
-        node.store(key, value.clone(), Duration::from_secs(3600));
+        node.put_record(key, record, Duration::from_secs(3600))
+            .unwrap();
 
         let request = FindValueRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
             key,
         };
 
         let response = node.handle_find_value(request);
         match response {
-            FoundValueResponse::Value { value: v, .. } => {
-                assert_eq!(v, value);
+            FoundValueResponse::Value { record, .. } => {
+                assert_eq!(record.value, value);
+                assert!(record.verify_for_key(&key).is_ok());
             }
             _ => panic!("Expected Value response"),
         }
     }
 
+    #[test]
+    fn test_handle_find_value_skips_unsigned_values() {
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+
+        let key = [42u8; 32];
+        node.store(key, vec![1, 2, 3], Duration::from_secs(3600));
+
+        let request = FindValueRequest {
+            sender_id: NodeId::random(),
+            sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
+            key,
+        };
+
+        assert!(matches!(
+            node.handle_find_value(request),
+            FoundValueResponse::Peers { .. }
+        ));
+    }
+
     #[test]
     fn test_handle_find_value_not_found() {
         let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
@@ -459,6 +647,7 @@ mod tests {
         let request = FindValueRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
             key,
         };
 
@@ -478,6 +667,7 @@ mod tests {
         let ping = DhtMessage::Ping(PingRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
             nonce: 12345,
         });
 
@@ -490,23 +680,304 @@ mod tests {
         }
     }
 
+    fn keyed_node(seed: u8, addr: &str) -> DhtNode {
+        DhtNode::with_signing_key(SigningKey::from_bytes(&[seed; 32]), addr.parse().unwrap())
+    }
+
+    fn ping_from(sender: &DhtNode, nonce: u64) -> DhtMessage {
+        DhtMessage::Ping(PingRequest {
+            sender_id: *sender.id(),
+            sender_addr: sender.addr(),
+            sender_key: sender.public_key().unwrap(),
+            nonce,
+        })
+    }
+
+    /// Deliver `node`'s pending challenges to `peer` and the answers back
+    fn answer_challenges(node: &mut DhtNode, peer: &mut DhtNode, from: SocketAddr) {
+        for (to, challenge) in node.take_challenges() {
+            assert_eq!(to, from);
+            if let Some(pong) = peer.handle_message(challenge, node.addr()) {
+                assert!(node.handle_message(pong, from).is_none());
+            }
+        }
+    }
+
     #[test]
     fn test_handle_message_updates_routing_table() {
-        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        let mut node = keyed_node(1, "127.0.0.1:8000");
+        let mut sender = keyed_node(7, "127.0.0.1:9000");
+        let from = sender.addr();
+
+        let _ = node.handle_message(ping_from(&sender, 12345), from);
+
+        // Not added until the sender proves it holds its key
+        assert_eq!(node.routing_table().peer_count(), 0);
+        answer_challenges(&mut node, &mut sender, from);
+
+        assert_eq!(node.routing_table().peer_count(), 1);
+        let peer = node.routing_table().get_peer(sender.id()).unwrap();
+        assert!(peer.is_bound());
+        assert!(peer.verified);
+
+        // A verified sender is refreshed without another challenge
+        let _ = node.handle_message(ping_from(&sender, 12346), from);
+        assert!(node.take_challenges().is_empty());
+    }
+
+    #[test]
+    fn test_handle_message_ignores_unbound_senders() {
+        let mut node = keyed_node(1, "127.0.0.1:8000");
+        let from = "198.51.100.7:9000".parse().unwrap();
 
-        let sender_id = NodeId::random();
+        // ID chosen freely rather than derived from the key
         let ping = DhtMessage::Ping(PingRequest {
-            sender_id,
-            sender_addr: "127.0.0.1:9000".parse().unwrap(),
-            nonce: 12345,
+            sender_id: NodeId::random(),
+            sender_addr: from,
+            sender_key: [7u8; 32],
+            nonce: 1,
         });
-
+        assert!(node.handle_message(ping, from).is_some());
+        assert!(node.take_challenges().is_empty());
         assert_eq!(node.routing_table().peer_count(), 0);
 
-        let _ = node.handle_message(ping, "127.0.0.1:9000".parse().unwrap());
+        // The claimed address is ignored in favour of the observed one
+        let mut sender = keyed_node(8, "203.0.113.1:9000");
+        let _ = node.handle_message(ping_from(&sender, 2), from);
+        answer_challenges(&mut node, &mut sender, from);
+        assert_eq!(
+            node.routing_table().get_peer(sender.id()).unwrap().addr,
+            from
+        );
+    }
+
+    #[test]
+    fn test_impostor_cannot_bind_victim_key() {
+        let mut node = keyed_node(1, "192.0.2.1:8000");
+        let mut victim = keyed_node(2, "192.0.2.2:8000");
+        let impostor = keyed_node(3, "192.0.2.66:8000");
+        let impostor_addr = impostor.addr();
+
+        // The impostor presents the victim's ID and key from its own address
+        let claim = ping_from(&victim, 1);
+        let _ = node.handle_message(claim, impostor_addr);
+        let challenges = node.take_challenges();
+        assert_eq!(challenges.len(), 1);
+        let DhtMessage::Ping(challenge) = &challenges[0].1 else {
+            panic!("Expected a challenge PING");
+        };
 
-        assert_eq!(node.routing_table().peer_count(), 1);
-        assert!(node.routing_table().get_peer(&sender_id).is_some());
+        // It cannot sign the nonce with the victim's key...
+        let mut forged = impostor.handle_ping(challenge.clone());
+        forged.sender_id = *victim.id();
+        forged.sender_key = victim.public_key().unwrap();
+        assert!(
+            node.handle_message(DhtMessage::Pong(forged), impostor_addr)
+                .is_none()
+        );
+        assert!(node.routing_table().get_peer(victim.id()).is_none());
+
+        // ...nor replay a PONG the victim signed for someone else's challenge
+        let _ = node.handle_message(ping_from(&victim, 2), impostor_addr);
+        let challenge_nonce = match &node.take_challenges()[0].1 {
+            DhtMessage::Ping(ping) => ping.nonce,
+            _ => panic!("Expected a challenge PING"),
+        };
+        let Some(DhtMessage::Pong(replayed)) =
+            victim.handle_message(ping_from(&impostor, challenge_nonce), impostor_addr)
+        else {
+            panic!("Expected a PONG");
+        };
+        let _ = node.handle_message(DhtMessage::Pong(replayed), impostor_addr);
+        assert!(node.routing_table().get_peer(victim.id()).is_none());
+
+        // An unproven binding learned elsewhere does not block the victim
+        node.routing_table_mut()
+            .insert(DhtPeer::from_public_key(
+                victim.public_key().unwrap(),
+                impostor_addr,
+            ))
+            .unwrap();
+        let victim_addr = victim.addr();
+        let _ = node.handle_message(ping_from(&victim, 3), victim_addr);
+        answer_challenges(&mut node, &mut victim, victim_addr);
+        let bound = node.routing_table().get_peer(victim.id()).unwrap();
+        assert_eq!(bound.addr, victim_addr);
+        assert!(bound.verified);
+    }
+
+    /// Network of bound peers that each know every other peer
+    struct FullMesh {
+        peers: Vec<DhtPeer>,
+        offline: HashSet<NodeId>,
+    }
+
+    impl FindNodeRpc for FullMesh {
+        async fn find_node(
+            &self,
+            peer: &DhtPeer,
+            target: &NodeId,
+        ) -> Result<Vec<CompactPeer>, OperationError> {
+            if self.offline.contains(&peer.id) {
+                return Err(OperationError::Timeout);
+            }
+            let mut known = self.peers.clone();
+            known.sort_by_key(|p| p.id.distance(target));
+            Ok(known
+                .into_iter()
+                .take(K)
+                .map(|p| CompactPeer {
+                    id: p.id,
+                    addr: p.addr,
+                    public_key: p.public_key,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_iterative_find_node_with_rpc() {
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        let peers: Vec<_> = (0..100u8)
+            .map(|i| DhtPeer::from_public_key([i; 32], format!("10.0.0.{i}:8000").parse().unwrap()))
+            .collect();
+        let offline = peers[..3].iter().map(|p| p.id).collect();
+        for peer in &peers[..3] {
+            node.routing_table_mut().insert(peer.clone()).unwrap();
+        }
+        let _ = node.routing_table_mut().insert(peers[3].clone());
+
+        let target = peers[50].id;
+        let mesh = FullMesh { peers, offline };
+        let closest = node
+            .iterative_find_node_with(&target, &mesh, &LookupConfig::default())
+            .await;
+
+        assert_eq!(closest[0].id, target);
+        assert!(closest.len() <= K);
+        for pair in closest.windows(2) {
+            assert!(pair[0].id.distance(&target) <= pair[1].id.distance(&target));
+        }
+
+        // Responders were learned, offline peers were charged a failure
+        assert!(node.routing_table().get_peer(&target).is_some());
+        assert_eq!(
+            node.routing_table()
+                .get_peer(&mesh.peers[0].id)
+                .unwrap()
+                .failures,
+            1
+        );
+    }
+
+    /// Network where some peers answer FIND_VALUE with a record
+    struct ValueMesh {
+        peers: Vec<DhtPeer>,
+        records: HashMap<NodeId, SignedRecord>,
+    }
+
+    impl FindValueRpc for ValueMesh {
+        async fn find_value(
+            &self,
+            peer: &DhtPeer,
+            _key: &[u8; 32],
+        ) -> Result<FoundValueResponse, OperationError> {
+            if let Some(record) = self.records.get(&peer.id) {
+                return Ok(FoundValueResponse::Value {
+                    sender_id: peer.id,
+                    record: record.clone(),
+                });
+            }
+            Ok(FoundValueResponse::Peers {
+                sender_id: peer.id,
+                peers: self
+                    .peers
+                    .iter()
+                    .map(|p| CompactPeer {
+                        id: p.id,
+                        addr: p.addr,
+                        public_key: p.public_key,
+                    })
+                    .collect(),
+            })
+        }
+    }
+
+    fn value_mesh_node(mesh: &ValueMesh) -> DhtNode {
+        let mut node = DhtNode::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        for peer in &mesh.peers {
+            let _ = node.routing_table_mut().insert(peer.clone());
+        }
+        node
+    }
+
+    #[tokio::test]
+    async fn test_iterative_find_value_drops_forged_records() {
+        let peers: Vec<_> = (1..=12u8)
+            .map(|i| DhtPeer::from_public_key([i; 32], format!("10.0.0.{i}:8000").parse().unwrap()))
+            .collect();
+        let v1 = signed(b"", 1, vec![1]);
+        let v2 = signed(b"", 2, vec![2]);
+        let key = v2.key();
+
+        // A malicious responder claims a newer record under the same key
+        let mut forged = v2.clone();
+        forged.seq = 99;
+        forged.value = b"203.0.113.66:8420".to_vec();
+
+        let mut records = HashMap::new();
+        records.insert(peers[0].id, forged.clone());
+        records.insert(peers[1].id, v1);
+        records.insert(peers[2].id, v2.clone());
+        let mesh = ValueMesh { peers, records };
+        let mut node = value_mesh_node(&mesh);
+
+        let found = node
+            .iterative_find_value_with(key, &mesh, &LookupConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(found, v2);
+
+        // The forger is charged a failure like an unresponsive peer
+        let forger = node.routing_table().get_peer(&mesh.peers[0].id).unwrap();
+        assert_eq!(forger.failures, 1);
+
+        // With only the forger holding the key, nothing is found
+        let mesh = ValueMesh {
+            records: HashMap::from([(mesh.peers[0].id, forged)]),
+            peers: mesh.peers,
+        };
+        let mut node = value_mesh_node(&mesh);
+        assert!(matches!(
+            node.iterative_find_value_with(key, &mesh, &LookupConfig::default())
+                .await,
+            Err(OperationError::ValueNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_iterative_find_value_keeps_newer_local_record() {
+        let peers: Vec<_> = (1..=4u8)
+            .map(|i| DhtPeer::from_public_key([i; 32], format!("10.0.0.{i}:8000").parse().unwrap()))
+            .collect();
+        let v1 = signed(b"", 1, vec![1]);
+        let v2 = signed(b"", 2, vec![2]);
+        let key = v2.key();
+
+        let mesh = ValueMesh {
+            records: HashMap::from([(peers[0].id, v1)]),
+            peers,
+        };
+        let mut node = value_mesh_node(&mesh);
+        node.put_record(key, v2.clone(), Duration::from_secs(3600))
+            .unwrap();
+
+        // A valid but older record does not roll the result back
+        let found = node
+            .iterative_find_value_with(key, &mesh, &LookupConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(found, v2);
     }
 
     #[tokio::test]
@@ -553,19 +1024,11 @@ mod tests {
 
         // Store multiple values
         for i in 0..10 {
-            let mut key = [0u8; 32];
-            key[0] = i;
             let value = vec![i, i + 1, i + 2];
+            let record = signed(&[i], 1, value.clone());
+            let key = record.key();
 
-            let request = StoreRequest {
-                sender_id: NodeId::random(),
-                sender_addr: "127.0.0.1:9000".parse().unwrap(),
-                key,
-                value: value.clone(),
-                ttl: 3600,
-            };
-
-            let response = node.handle_store(request);
+            let response = node.handle_store(store_request(key, record));
             assert!(response.stored);
 
             // Verify value was stored
@@ -581,6 +1044,7 @@ mod tests {
         let request = FindNodeRequest {
             sender_id: NodeId::random(),
             sender_addr: "127.0.0.1:9000".parse().unwrap(),
+            sender_key: [0u8; 32],
             target_id: target,
         };
 
@@ -609,6 +1073,7 @@ mod tests {
         let msg = DhtMessage::FindNode(FindNodeRequest {
             sender_id: NodeId::random(),
             sender_addr,
+            sender_key: [0u8; 32],
             target_id: NodeId::random(),
         });
         let response = node.handle_message(msg, sender_addr);
@@ -616,11 +1081,13 @@ mod tests {
         assert!(matches!(response.unwrap(), DhtMessage::FoundNodes(_)));
 
         // Test Store
+        let record = signed(b"", 1, vec![1, 2, 3]);
         let msg = DhtMessage::Store(StoreRequest {
             sender_id: NodeId::random(),
             sender_addr,
-            key: [1u8; 32],
-            value: vec![1, 2, 3],
+            sender_key: [0u8; 32],
+            key: record.key(),
+            record,
             ttl: 3600,
         });
         let response = node.handle_message(msg, sender_addr);
@@ -631,6 +1098,7 @@ mod tests {
         let msg = DhtMessage::FindValue(FindValueRequest {
             sender_id: NodeId::random(),
             sender_addr,
+            sender_key: [0u8; 32],
             key: [99u8; 32], // Non-existent key
         });
         let response = node.handle_message(msg, sender_addr);
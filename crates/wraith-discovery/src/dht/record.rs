@@ -0,0 +1,290 @@
+//! Signed Mutable DHT Records
+//!
+//! Records are owned by an Ed25519 key, in the manner of BitTorrent's BEP-44
+//! mutable items:
+//! - The storage key is `BLAKE3(public_key ‖ salt)`, so only the key holder
+//!   can publish under it, and one key can publish several records by
+//!   varying the salt
+//! - Every record carries a sequence number; a stored record is replaced
+//!   only by one with a higher sequence number
+//! - The signature covers the salt, sequence number and value
+//!
+//! Nodes verify records before storing them and again before serving them, and
+//! lookups verify every record a responder returns.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use wraith_crypto::signatures::{Signature, SigningKey, VerifyingKey};
+
+/// Largest record value accepted, in bytes
+pub const MAX_VALUE_SIZE: usize = 1000;
+
+/// Largest salt accepted, in bytes
+pub const MAX_SALT_SIZE: usize = 64;
+
+/// Domain separation prefix for record signatures
+const SIGNATURE_CONTEXT: &[u8] = b"wraith-dht-record-v1";
+
+/// Record validation errors
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RecordError {
+    /// Public key is not a valid Ed25519 point
+    #[error("Invalid record public key")]
+    InvalidPublicKey,
+
+    /// Signature is malformed or does not match the record
+    #[error("Invalid record signature")]
+    InvalidSignature,
+
+    /// Value exceeds [`MAX_VALUE_SIZE`]
+    #[error("Record value too large: {0} bytes")]
+    ValueTooLarge(usize),
+
+    /// Salt exceeds [`MAX_SALT_SIZE`]
+    #[error("Record salt too large: {0} bytes")]
+    SaltTooLarge(usize),
+
+    /// Storage key does not match the record's public key and salt
+    #[error("Storage key does not match record")]
+    KeyMismatch,
+
+    /// A record with an equal or higher sequence number is already stored
+    #[error("Stale record: sequence {received} does not supersede {stored}")]
+    StaleSequence {
+        /// Sequence number already stored
+        stored: u64,
+        /// Sequence number received
+        received: u64,
+    },
+}
+
+/// A mutable DHT record signed by its publisher
+///
+/// # Examples
+///
+/// ```
+/// use wraith_discovery::dht::SignedRecord;
+/// use wraith_crypto::signatures::SigningKey;
+///
+/// let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+/// let record = SignedRecord::sign(&signing_key, b"peer".to_vec(), 1, b"192.0.2.1:8420".to_vec());
+///
+/// assert!(record.verify().is_ok());
+/// assert_eq!(record.key(), SignedRecord::storage_key(&record.public_key, b"peer"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedRecord {
+    /// Publisher's Ed25519 public key
+    pub public_key: [u8; 32],
+    /// Salt distinguishing records published under the same key
+    pub salt: Vec<u8>,
+    /// Sequence number; higher numbers supersede lower ones
+    pub seq: u64,
+    /// Record payload
+    pub value: Vec<u8>,
+    /// Ed25519 signature over the salt, sequence number and value
+    pub signature: Vec<u8>,
+}
+
+impl SignedRecord {
+    /// Create and sign a record
+    #[must_use]
+    pub fn sign(signing_key: &SigningKey, salt: Vec<u8>, seq: u64, value: Vec<u8>) -> Self {
+        let signature = signing_key.sign(&signed_message(&salt, seq, &value));
+        Self {
+            public_key: signing_key.verifying_key().to_bytes(),
+            salt,
+            seq,
+            value,
+            signature: signature.as_bytes().to_vec(),
+        }
+    }
+
+    /// Storage key for records published by `public_key` with `salt`
+    #[must_use]
+    pub fn storage_key(public_key: &[u8; 32], salt: &[u8]) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(public_key);
+        hasher.update(salt);
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Storage key of this record
+    #[must_use]
+    pub fn key(&self) -> [u8; 32] {
+        Self::storage_key(&self.public_key, &self.salt)
+    }
+
+    /// Check sizes and signature
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value or salt is too large, or the signature
+    /// does not verify under the record's public key.
+    pub fn verify(&self) -> Result<(), RecordError> {
+        if self.value.len() > MAX_VALUE_SIZE {
+            return Err(RecordError::ValueTooLarge(self.value.len()));
+        }
+        if self.salt.len() > MAX_SALT_SIZE {
+            return Err(RecordError::SaltTooLarge(self.salt.len()));
+        }
+
+        let public_key = VerifyingKey::from_bytes(&self.public_key)
+            .map_err(|_| RecordError::InvalidPublicKey)?;
+        let signature =
+            Signature::from_slice(&self.signature).map_err(|_| RecordError::InvalidSignature)?;
+        public_key
+            .verify(
+                &signed_message(&self.salt, self.seq, &self.value),
+                &signature,
+            )
+            .map_err(|_| RecordError::InvalidSignature)
+    }
+
+    /// Verify the record and check that it belongs under `key`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RecordError::KeyMismatch`] if `key` is not this record's
+    /// storage key, or any error from [`verify`](Self::verify).
+    pub fn verify_for_key(&self, key: &[u8; 32]) -> Result<(), RecordError> {
+        if self.key() != *key {
+            return Err(RecordError::KeyMismatch);
+        }
+        self.verify()
+    }
+
+    /// Check whether this record may replace `stored`
+    ///
+    /// A record supersedes another with a lower sequence number. Re-storing
+    /// an identical record is allowed so publishers can refresh the TTL.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RecordError::StaleSequence`] otherwise.
+    pub fn check_supersedes(&self, stored: &Self) -> Result<(), RecordError> {
+        if self.seq > stored.seq || self == stored {
+            Ok(())
+        } else {
+            Err(RecordError::StaleSequence {
+                stored: stored.seq,
+                received: self.seq,
+            })
+        }
+    }
+}
+
+/// Bytes covered by a record signature
+fn signed_message(salt: &[u8], seq: u64, value: &[u8]) -> Vec<u8> {
+    let mut message =
+        Vec::with_capacity(SIGNATURE_CONTEXT.len() + 1 + salt.len() + 8 + value.len());
+    message.extend_from_slice(SIGNATURE_CONTEXT);
+    // Salt length is bounded by MAX_SALT_SIZE; longer salts fail verification
+    // before the signature is checked
+    message.push(u8::try_from(salt.len()).unwrap_or(u8::MAX));
+    message.extend_from_slice(salt);
+    message.extend_from_slice(&seq.to_be_bytes());
+    message.extend_from_slice(value);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[42u8; 32])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let record = SignedRecord::sign(&signing_key(), b"salt".to_vec(), 5, vec![1, 2, 3]);
+        assert!(record.verify().is_ok());
+        assert!(record.verify_for_key(&record.key()).is_ok());
+        assert_eq!(
+            record.verify_for_key(&[0u8; 32]),
+            Err(RecordError::KeyMismatch)
+        );
+    }
+
+    #[test]
+    fn test_storage_key_depends_on_salt() {
+        let key = signing_key();
+        let a = SignedRecord::sign(&key, b"a".to_vec(), 1, vec![]);
+        let b = SignedRecord::sign(&key, b"b".to_vec(), 1, vec![]);
+        assert_ne!(a.key(), b.key());
+
+        let other = SignedRecord::sign(
+            &SigningKey::from_bytes(&[1u8; 32]),
+            b"a".to_vec(),
+            1,
+            vec![],
+        );
+        assert_ne!(a.key(), other.key());
+    }
+
+    #[test]
+    fn test_tampered_record_rejected() {
+        let record = SignedRecord::sign(&signing_key(), vec![], 1, vec![1, 2, 3]);
+
+        let mut value = record.clone();
+        value.value.push(4);
+        assert_eq!(value.verify(), Err(RecordError::InvalidSignature));
+
+        let mut seq = record.clone();
+        seq.seq = 2;
+        assert_eq!(seq.verify(), Err(RecordError::InvalidSignature));
+
+        let mut salt = record.clone();
+        salt.salt = b"x".to_vec();
+        assert_eq!(salt.verify(), Err(RecordError::InvalidSignature));
+
+        let mut truncated = record.clone();
+        truncated.signature.pop();
+        assert_eq!(truncated.verify(), Err(RecordError::InvalidSignature));
+
+        // Re-signed by another key but claiming the original publisher
+        let mut forged =
+            SignedRecord::sign(&SigningKey::from_bytes(&[9u8; 32]), vec![], 1, vec![1]);
+        forged.public_key = record.public_key;
+        assert_eq!(forged.verify(), Err(RecordError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let key = signing_key();
+        let large = SignedRecord::sign(&key, vec![], 1, vec![0; MAX_VALUE_SIZE + 1]);
+        assert_eq!(
+            large.verify(),
+            Err(RecordError::ValueTooLarge(MAX_VALUE_SIZE + 1))
+        );
+
+        let salted = SignedRecord::sign(&key, vec![0; MAX_SALT_SIZE + 1], 1, vec![]);
+        assert_eq!(
+            salted.verify(),
+            Err(RecordError::SaltTooLarge(MAX_SALT_SIZE + 1))
+        );
+
+        let max = SignedRecord::sign(&key, vec![0; MAX_SALT_SIZE], 1, vec![0; MAX_VALUE_SIZE]);
+        assert!(max.verify().is_ok());
+    }
+
+    #[test]
+    fn test_sequence_ordering() {
+        let key = signing_key();
+        let v1 = SignedRecord::sign(&key, vec![], 1, vec![1]);
+        let v2 = SignedRecord::sign(&key, vec![], 2, vec![2]);
+        let v2_other = SignedRecord::sign(&key, vec![], 2, vec![3]);
+
+        assert!(v2.check_supersedes(&v1).is_ok());
+        assert!(v2.check_supersedes(&v2).is_ok());
+        assert_eq!(
+            v1.check_supersedes(&v2),
+            Err(RecordError::StaleSequence {
+                stored: 2,
+                received: 1
+            })
+        );
+        assert!(v2_other.check_supersedes(&v2).is_err());
+    }
+}
@@ -3,10 +3,21 @@
 //! This module implements the k-bucket routing table used in Kademlia DHT.
 //! The routing table organizes peers by their XOR distance from the local node,
 //! enabling efficient O(log n) lookups.
+//!
+//! Buckets resist Sybil and eclipse attacks in the manner of S/Kademlia:
+//! - At most [`MAX_PEERS_PER_SUBNET`] peers per IPv4 /24 or IPv6 /64 in
+//!   each bucket, so one network cannot fill a bucket with identities
+//! - Live peers are never evicted in favour of newcomers; a full bucket only
+//!   replaces stale peers, youngest first, and parks newcomers in a
+//!   replacement cache
+//! - A live peer's address cannot be changed by another node claiming its ID
+//!
+//! Peers learned from the network are only inserted if their ID is derived
+//! from their public key (see [`DhtPeer::is_bound`]).
 
 use super::node_id::NodeId;
 use std::collections::VecDeque;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 
@@ -25,6 +36,55 @@ pub const NUM_BUCKETS: usize = 256;
 /// and may be replaced in k-buckets.
 const PEER_TIMEOUT: Duration = Duration::from_secs(15 * 60);
 
+/// Maximum peers from one IPv4 /24 or IPv6 /64 in a single k-bucket
+///
+/// Loopback, private and link-local addresses are exempt so that local
+/// networks and tests are not limited.
+pub const MAX_PEERS_PER_SUBNET: usize = 2;
+
+/// Consecutive failed requests after which a peer is considered stale
+const MAX_FAILURES: u32 = 3;
+
+/// Network prefix used for IP diversity limits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Subnet {
+    /// IPv4 /24
+    V4([u8; 3]),
+    /// IPv6 /64
+    V6([u8; 8]),
+}
+
+impl Subnet {
+    /// Prefix `addr` belongs to, or `None` if it is exempt from limits
+    fn of(addr: &SocketAddr) -> Option<Self> {
+        match addr.ip() {
+            IpAddr::V4(ip) => Self::of_v4(ip),
+            IpAddr::V6(ip) => {
+                if let Some(mapped) = ip.to_ipv4_mapped() {
+                    return Self::of_v4(mapped);
+                }
+                let first = ip.segments()[0];
+                let unique_local = first & 0xfe00 == 0xfc00;
+                let link_local = first & 0xffc0 == 0xfe80;
+                if ip.is_loopback() || ip.is_unspecified() || unique_local || link_local {
+                    return None;
+                }
+                let mut prefix = [0u8; 8];
+                prefix.copy_from_slice(&ip.octets()[..8]);
+                Some(Self::V6(prefix))
+            }
+        }
+    }
+
+    fn of_v4(ip: std::net::Ipv4Addr) -> Option<Self> {
+        if ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() {
+            return None;
+        }
+        let [a, b, c, _] = ip.octets();
+        Some(Self::V4([a, b, c]))
+    }
+}
+
 /// DHT peer information
 ///
 /// Stores metadata about a peer in the DHT, including their NodeId,
@@ -39,6 +99,14 @@ pub struct DhtPeer {
     pub last_seen: Instant,
     /// Round-trip time measurement (if available)
     pub rtt: Option<Duration>,
+    /// Public key the peer's ID should be derived from, if known
+    pub public_key: Option<[u8; 32]>,
+    /// When this peer was first added
+    pub first_seen: Instant,
+    /// Consecutive failed requests since the peer last responded
+    pub failures: u32,
+    /// Whether the peer proved, from `addr`, that it holds `public_key`
+    pub verified: bool,
 }
 
 impl DhtPeer {
@@ -61,14 +129,48 @@ impl DhtPeer {
     /// ```
     #[must_use]
     pub fn new(id: NodeId, addr: SocketAddr) -> Self {
+        let now = Instant::now();
         Self {
             id,
             addr,
-            last_seen: Instant::now(),
+            last_seen: now,
             rtt: None,
+            public_key: None,
+            first_seen: now,
+            failures: 0,
+            verified: false,
+        }
+    }
+
+    /// Create a peer whose NodeId is derived from its public key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wraith_discovery::dht::{DhtPeer, NodeId};
+    ///
+    /// let peer = DhtPeer::from_public_key([7u8; 32], "192.0.2.1:8000".parse().unwrap());
+    /// assert_eq!(peer.id, NodeId::from_public_key(&[7u8; 32]));
+    /// assert!(peer.is_bound());
+    /// ```
+    #[must_use]
+    pub fn from_public_key(public_key: [u8; 32], addr: SocketAddr) -> Self {
+        Self {
+            public_key: Some(public_key),
+            ..Self::new(NodeId::from_public_key(&public_key), addr)
         }
     }
 
+    /// Check that the peer's NodeId is derived from its public key
+    ///
+    /// Unbound IDs can be chosen freely, which lets an attacker place nodes
+    /// next to any target; lookups and the message handlers ignore them.
+    #[must_use]
+    pub fn is_bound(&self) -> bool {
+        self.public_key
+            .is_some_and(|key| NodeId::from_public_key(&key) == self.id)
+    }
+
     /// Check if the peer is considered alive
     ///
     /// A peer is alive if they responded within the last 15 minutes.
@@ -81,11 +183,33 @@ impl DhtPeer {
         self.last_seen.elapsed() < PEER_TIMEOUT
     }
 
+    /// Check if the peer may be replaced
+    ///
+    /// A peer is stale once it is no longer alive or has failed several
+    /// requests in a row.
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        !self.is_alive() || self.failures >= MAX_FAILURES
+    }
+
+    /// Time since the peer was first added
+    #[must_use]
+    pub fn uptime(&self) -> Duration {
+        self.first_seen.elapsed()
+    }
+
     /// Update the last seen timestamp
     ///
     /// Should be called whenever we receive a response from this peer.
+    /// Clears the failure count.
     pub fn touch(&mut self) {
         self.last_seen = Instant::now();
+        self.failures = 0;
+    }
+
+    /// Record a request the peer failed to answer
+    pub fn record_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
     }
 
     /// Update the RTT measurement
@@ -100,12 +224,15 @@ impl DhtPeer {
 
 /// K-bucket for storing peers at a specific distance range
 ///
-/// K-buckets use LRU eviction policy: when full, the least recently seen
-/// alive peer is replaced. Dead peers are always replaced immediately.
+/// K-buckets prefer long-lived peers: when full, live peers are kept and the
+/// newcomer goes to a replacement cache. Stale peers are replaced, youngest
+/// first, and cached peers are promoted when a slot frees up.
 #[derive(Clone, Debug)]
 pub struct KBucket {
     /// Peers in this bucket, ordered by last-seen (LRU)
     peers: VecDeque<DhtPeer>,
+    /// Peers waiting for a slot, most recently seen first
+    replacements: VecDeque<DhtPeer>,
     /// Maximum number of peers this bucket can hold
     capacity: usize,
 }
@@ -129,6 +256,7 @@ impl KBucket {
     pub fn new(capacity: usize) -> Self {
         Self {
             peers: VecDeque::with_capacity(capacity),
+            replacements: VecDeque::new(),
             capacity,
         }
     }
@@ -136,10 +264,16 @@ impl KBucket {
     /// Insert a peer into the bucket
     ///
     /// Insertion follows these rules:
-    /// 1. If peer already exists, move to front (most recently seen)
-    /// 2. If bucket not full, append peer
-    /// 3. If bucket full, try to replace dead peer
-    /// 4. If all peers alive, reject insertion (bucket full)
+    /// 1. If peer already exists at the same address, move to front (most
+    ///    recently seen); a different address is only accepted once the
+    ///    existing entry is stale, or if the newcomer is verified and the
+    ///    existing entry is not
+    /// 2. If the peer's subnet already has [`MAX_PEERS_PER_SUBNET`] entries,
+    ///    replace a stale one or reject the peer
+    /// 3. If bucket not full, append peer
+    /// 4. If bucket full, replace the youngest stale peer
+    /// 5. If all peers are live, keep the newcomer in the replacement cache
+    ///    and reject insertion (bucket full)
     ///
     /// # Arguments
     ///
@@ -147,7 +281,10 @@ impl KBucket {
     ///
     /// # Errors
     ///
-    /// Returns `DhtError::BucketFull` if bucket is full with all alive peers
+    /// Returns `DhtError::BucketFull` if bucket is full with all alive peers,
+    /// `DhtError::SubnetLimit` if the peer's subnet is at its limit, and
+    /// `DhtError::AddressConflict` if a live peer already holds the ID at
+    /// another address with at least the newcomer's verification.
     ///
     /// # Examples
     ///
@@ -161,14 +298,43 @@ impl KBucket {
     pub fn insert(&mut self, peer: DhtPeer) -> Result<(), DhtError> {
         // Check if peer already exists
         if let Some(pos) = self.peers.iter().position(|p| p.id == peer.id) {
-            // Move to front (most recently seen)
-            let mut existing = self.peers.remove(pos).unwrap();
-            existing.touch();
-            if let Some(rtt) = peer.rtt {
-                existing.update_rtt(rtt);
+            if self.peers[pos].addr != peer.addr {
+                // Another node claiming a live peer's ID must not redirect it,
+                // but a proven binding replaces an unproven one
+                let existing = &self.peers[pos];
+                if !existing.is_stale() && (existing.verified || !peer.verified) {
+                    return Err(DhtError::AddressConflict);
+                }
+                self.peers.remove(pos);
+            } else {
+                // Move to front (most recently seen)
+                let mut existing = self.peers.remove(pos).unwrap();
+                existing.touch();
+                if let Some(rtt) = peer.rtt {
+                    existing.update_rtt(rtt);
+                }
+                if existing.public_key.is_none() {
+                    existing.public_key = peer.public_key;
+                }
+                existing.verified |= peer.verified;
+                self.peers.push_front(existing);
+                return Ok(());
+            }
+        }
+
+        // Limit how many entries one subnet can hold
+        if let Some(subnet) = Subnet::of(&peer.addr) {
+            let same_subnet = |p: &DhtPeer| Subnet::of(&p.addr) == Some(subnet);
+            if self.peers.iter().filter(|p| same_subnet(p)).count() >= MAX_PEERS_PER_SUBNET {
+                let pos = self
+                    .peers
+                    .iter()
+                    .position(|p| same_subnet(p) && p.is_stale())
+                    .ok_or(DhtError::SubnetLimit)?;
+                self.peers.remove(pos);
+                self.peers.push_front(peer);
+                return Ok(());
             }
-            self.peers.push_front(existing);
-            return Ok(());
         }
 
         // If bucket not full, add peer
@@ -177,17 +343,67 @@ impl KBucket {
             return Ok(());
         }
 
-        // Bucket full - try to replace dead peer
-        if let Some(pos) = self.peers.iter().position(|p| !p.is_alive()) {
+        // Bucket full - replace the youngest stale peer
+        if let Some((pos, _)) = self
+            .peers
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_stale())
+            .max_by_key(|(_, p)| p.first_seen)
+        {
             self.peers.remove(pos);
             self.peers.push_front(peer);
             return Ok(());
         }
 
-        // Bucket full with all alive peers
+        // Bucket full with all alive peers - keep the newcomer for later
+        self.replacements.retain(|p| p.id != peer.id);
+        self.replacements.push_front(peer);
+        self.replacements.truncate(self.capacity);
         Err(DhtError::BucketFull)
     }
 
+    /// Record a failed request to a peer
+    ///
+    /// Once the peer becomes stale it is replaced by the most recently seen
+    /// peer in the replacement cache, if any.
+    pub fn record_failure(&mut self, id: &NodeId) {
+        let Some(pos) = self.peers.iter().position(|p| p.id == *id) else {
+            return;
+        };
+        self.peers[pos].record_failure();
+        if self.peers[pos].is_stale() && self.promote_replacement() {
+            self.peers.retain(|p| p.id != *id);
+        }
+    }
+
+    /// Move the best cached replacement into the bucket
+    ///
+    /// Skips dead replacements and those whose subnet is already at its limit.
+    /// Returns `true` if a peer was promoted.
+    fn promote_replacement(&mut self) -> bool {
+        self.replacements.retain(DhtPeer::is_alive);
+        let subnet_full = |peers: &VecDeque<DhtPeer>, candidate: &DhtPeer| {
+            Subnet::of(&candidate.addr).is_some_and(|subnet| {
+                peers
+                    .iter()
+                    .filter(|p| !p.is_stale() && Subnet::of(&p.addr) == Some(subnet))
+                    .count()
+                    >= MAX_PEERS_PER_SUBNET
+            })
+        };
+        let Some(pos) = self
+            .replacements
+            .iter()
+            .position(|candidate| !subnet_full(&self.peers, candidate))
+        else {
+            return false;
+        };
+        let peer = self.replacements.remove(pos).unwrap();
+        self.peers.push_back(peer);
+        true
+    }
+
     /// Get a peer by NodeId
     ///
     /// # Arguments
@@ -229,11 +445,23 @@ impl KBucket {
         peers.into_iter().take(count).collect()
     }
 
-    /// Remove dead peers from the bucket
+    /// Get the replacement cache
     ///
-    /// Peers that haven't responded within the timeout are removed.
+    /// # Returns
+    ///
+    /// Peers waiting for a slot, most recently seen first
+    #[must_use]
+    pub fn replacements(&self) -> &VecDeque<DhtPeer> {
+        &self.replacements
+    }
+
+    /// Remove stale peers from the bucket
+    ///
+    /// Peers that haven't responded within the timeout, or have failed
+    /// repeatedly, are removed and replaced from the replacement cache.
     pub fn prune(&mut self) {
-        self.peers.retain(|p| p.is_alive());
+        self.peers.retain(|p| !p.is_stale());
+        while self.peers.len() < self.capacity && self.promote_replacement() {}
     }
 
     /// Get the number of peers in this bucket
@@ -350,6 +578,20 @@ impl RoutingTable {
         self.buckets[bucket_idx].insert(peer)
     }
 
+    /// Record a failed request to a peer
+    ///
+    /// Peers that fail repeatedly become stale and are replaced from their
+    /// bucket's replacement cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - NodeId of the peer that failed to respond
+    pub fn record_failure(&mut self, id: &NodeId) {
+        if let Some(bucket_idx) = self.bucket_index(id) {
+            self.buckets[bucket_idx].record_failure(id);
+        }
+    }
+
     /// Find the K closest peers to a target NodeId
     ///
     /// Searches all buckets and returns the K closest peers to the target,
@@ -449,7 +691,7 @@ impl RoutingTable {
         self.buckets.iter().map(|b| b.len()).sum()
     }
 
-    /// Remove dead peers from all buckets
+    /// Remove stale peers from all buckets
     ///
     /// Should be called periodically to maintain routing table health.
     pub fn prune(&mut self) {
@@ -484,6 +726,14 @@ pub enum DhtError {
     #[error("K-bucket is full")]
     BucketFull,
 
+    /// Peer's subnet already has the maximum number of entries in its bucket
+    #[error("Too many peers from the same subnet")]
+    SubnetLimit,
+
+    /// Another live peer already holds this NodeId at a different address
+    #[error("NodeId already in use at another address")]
+    AddressConflict,
+
     /// Attempted to insert local node into routing table
     #[error("Cannot insert local node into routing table")]
     SelfInsert,
@@ -575,6 +825,230 @@ mod tests {
         // If checked_sub fails (system uptime < 20 min), skip test as we can't simulate stale peers
     }
 
+    /// A peer that has been failing requests long enough to be stale
+    fn stale(mut peer: DhtPeer) -> DhtPeer {
+        for _ in 0..MAX_FAILURES {
+            peer.record_failure();
+        }
+        peer
+    }
+
+    #[test]
+    fn test_dht_peer_binding() {
+        let peer = DhtPeer::from_public_key([1u8; 32], "192.0.2.1:8000".parse().unwrap());
+        assert!(peer.is_bound());
+
+        let mut forged = peer.clone();
+        forged.id = NodeId::random();
+        assert!(!forged.is_bound());
+
+        assert!(!DhtPeer::new(peer.id, peer.addr).is_bound());
+    }
+
+    #[test]
+    fn test_dht_peer_failures() {
+        let mut peer = DhtPeer::new(NodeId::random(), "127.0.0.1:8000".parse().unwrap());
+        peer.record_failure();
+        assert!(!peer.is_stale());
+
+        let mut peer = stale(peer);
+        assert!(peer.is_stale());
+
+        peer.touch();
+        assert_eq!(peer.failures, 0);
+        assert!(!peer.is_stale());
+    }
+
+    #[test]
+    fn test_subnet_exemptions() {
+        let subnet = |addr: &str| Subnet::of(&addr.parse().unwrap());
+
+        assert_eq!(subnet("203.0.113.9:1"), Some(Subnet::V4([203, 0, 113])));
+        assert_eq!(subnet("203.0.113.200:2"), subnet("203.0.113.9:1"));
+        assert_eq!(subnet("[::ffff:203.0.113.9]:1"), subnet("203.0.113.9:1"));
+        assert_eq!(
+            subnet("[2001:db8:1:2:3::1]:1"),
+            subnet("[2001:db8:1:2:ffff::9]:1")
+        );
+        assert_ne!(subnet("[2001:db8:1:2::1]:1"), subnet("[2001:db8:1:3::1]:1"));
+
+        for exempt in [
+            "127.0.0.1:1",
+            "10.1.2.3:1",
+            "192.168.1.1:1",
+            "169.254.0.1:1",
+            "[::1]:1",
+            "[fd00::1]:1",
+            "[fe80::1]:1",
+        ] {
+            assert_eq!(subnet(exempt), None, "{exempt}");
+        }
+    }
+
+    #[test]
+    fn test_kbucket_subnet_limit() {
+        let mut bucket = KBucket::new(K);
+        let peer = |host: u8| {
+            DhtPeer::new(
+                NodeId::random(),
+                format!("198.51.100.{host}:8000").parse().unwrap(),
+            )
+        };
+
+        for host in 0..MAX_PEERS_PER_SUBNET as u8 {
+            bucket.insert(peer(host)).unwrap();
+        }
+        assert!(matches!(
+            bucket.insert(peer(99)),
+            Err(DhtError::SubnetLimit)
+        ));
+
+        // IPv6 peers are limited per /64
+        for host in 0..MAX_PEERS_PER_SUBNET {
+            let addr = format!("[2001:db8::{host:x}]:8000").parse().unwrap();
+            bucket.insert(DhtPeer::new(NodeId::random(), addr)).unwrap();
+        }
+        let addr = "[2001:db8::ffff:1]:8000".parse().unwrap();
+        assert!(matches!(
+            bucket.insert(DhtPeer::new(NodeId::random(), addr)),
+            Err(DhtError::SubnetLimit)
+        ));
+
+        // Other subnets are unaffected
+        let other = DhtPeer::new(NodeId::random(), "198.51.101.1:8000".parse().unwrap());
+        bucket.insert(other).unwrap();
+        assert_eq!(bucket.len(), 2 * MAX_PEERS_PER_SUBNET + 1);
+
+        // A stale peer frees its subnet slot
+        let id = bucket
+            .peers()
+            .iter()
+            .find(|p| p.addr.ip().to_string() == "198.51.100.0")
+            .unwrap()
+            .id;
+        for _ in 0..MAX_FAILURES {
+            bucket.record_failure(&id);
+        }
+        bucket.insert(peer(99)).unwrap();
+        assert!(bucket.get(&id).is_none());
+    }
+
+    #[test]
+    fn test_kbucket_address_conflict() {
+        let mut bucket = KBucket::new(3);
+        let id = NodeId::random();
+        bucket
+            .insert(DhtPeer::new(id, "192.0.2.1:8000".parse().unwrap()))
+            .unwrap();
+
+        let hijack = DhtPeer::new(id, "192.0.2.66:8000".parse().unwrap());
+        assert!(matches!(
+            bucket.insert(hijack.clone()),
+            Err(DhtError::AddressConflict)
+        ));
+        assert_eq!(
+            bucket.get(&id).unwrap().addr,
+            "192.0.2.1:8000".parse().unwrap()
+        );
+
+        // Once the original stops answering, the ID may move
+        for _ in 0..MAX_FAILURES {
+            bucket.record_failure(&id);
+        }
+        bucket.insert(hijack).unwrap();
+        assert_eq!(
+            bucket.get(&id).unwrap().addr,
+            "192.0.2.66:8000".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_kbucket_verified_peer_displaces_unverified_claim() {
+        let mut bucket = KBucket::new(3);
+        let victim = DhtPeer::from_public_key([5u8; 32], "192.0.2.1:8000".parse().unwrap());
+
+        // An impostor advertises the victim's key at its own address
+        let impostor = DhtPeer {
+            addr: "192.0.2.66:8000".parse().unwrap(),
+            ..victim.clone()
+        };
+        bucket.insert(impostor.clone()).unwrap();
+
+        // The unproven claim does not block the victim's verified binding
+        let verified = DhtPeer {
+            verified: true,
+            ..victim
+        };
+        bucket.insert(verified.clone()).unwrap();
+        assert_eq!(bucket.get(&verified.id).unwrap().addr, verified.addr);
+        assert!(bucket.get(&verified.id).unwrap().verified);
+
+        // And an unproven claim cannot displace the verified one
+        assert!(matches!(
+            bucket.insert(impostor),
+            Err(DhtError::AddressConflict)
+        ));
+        assert_eq!(bucket.get(&verified.id).unwrap().addr, verified.addr);
+    }
+
+    #[test]
+    fn test_kbucket_prefers_long_lived_peers() {
+        let mut bucket = KBucket::new(3);
+        let peers: Vec<_> = (0..3)
+            .map(|i| {
+                DhtPeer::new(
+                    NodeId::random(),
+                    format!("127.0.0.1:{}", 8000 + i).parse().unwrap(),
+                )
+            })
+            .collect();
+        for peer in &peers {
+            bucket.insert(peer.clone()).unwrap();
+        }
+
+        // Live peers are kept; the newcomer waits in the replacement cache
+        let newcomer = DhtPeer::new(NodeId::random(), "127.0.0.1:9000".parse().unwrap());
+        assert!(matches!(
+            bucket.insert(newcomer.clone()),
+            Err(DhtError::BucketFull)
+        ));
+        assert_eq!(bucket.replacements().front().unwrap().id, newcomer.id);
+
+        // Failing peers are replaced from the cache
+        for _ in 0..MAX_FAILURES {
+            bucket.record_failure(&peers[1].id);
+        }
+        assert!(bucket.get(&peers[1].id).is_none());
+        assert!(bucket.get(&newcomer.id).is_some());
+        assert!(bucket.replacements().is_empty());
+    }
+
+    #[test]
+    fn test_kbucket_evicts_youngest_stale_peer() {
+        let mut bucket = KBucket::new(2);
+        let Some(long_ago) = Instant::now().checked_sub(Duration::from_secs(3600)) else {
+            return;
+        };
+
+        let mut veteran = stale(DhtPeer::new(
+            NodeId::random(),
+            "127.0.0.1:8000".parse().unwrap(),
+        ));
+        veteran.first_seen = long_ago;
+        let junior = stale(DhtPeer::new(
+            NodeId::random(),
+            "127.0.0.1:8001".parse().unwrap(),
+        ));
+        bucket.insert(veteran.clone()).unwrap();
+        bucket.insert(junior.clone()).unwrap();
+
+        let newcomer = DhtPeer::new(NodeId::random(), "127.0.0.1:9000".parse().unwrap());
+        bucket.insert(newcomer).unwrap();
+        assert!(bucket.get(&veteran.id).is_some());
+        assert!(bucket.get(&junior.id).is_none());
+        assert!(veteran.uptime() > junior.uptime());
+    }
+
     #[test]
     fn test_routing_table_insert() {
         let local_id = NodeId::random();
@@ -6,6 +6,8 @@
 //! - Privacy-enhanced DHT (encrypted announcements)
 //! - DERP-style relay network for NAT traversal
 //! - NAT type detection and hole punching
+//! - Gateway port mapping (PCP, NAT-PMP, UPnP IGD)
+//! - Local network discovery (mDNS/DNS-SD)
 //! - Endpoint discovery
 //!
 //! ## Kademlia DHT
@@ -36,6 +38,7 @@
 
 pub mod dht;
 pub mod manager;
+pub mod mdns;
 pub mod nat;
 pub mod relay;
 
@@ -44,9 +47,11 @@ pub use manager::{
     ConnectionType, DiscoveryConfig, DiscoveryError, DiscoveryManager, DiscoveryState,
     PeerConnection, RelayInfo,
 };
+pub use mdns::{LanPeer, MdnsConfig, MdnsError, MdnsService};
 pub use nat::{
-    Candidate, CandidateType, DnsError, HolePuncher, IceGatherer, NatDetector, NatError, NatType,
-    PunchError, StunClient, StunDnsResolver, StunError, StunServerSpec, default_stun_servers,
+    Candidate, CandidateType, DnsError, HolePuncher, IceGatherer, MappingProtocol, NatDetector,
+    NatError, NatType, PortMapper, PortMapping, PortMappingConfig, PortMappingError, PunchError,
+    StunClient, StunDnsResolver, StunError, StunServerSpec, default_stun_servers,
     fallback_stun_ips,
 };
 
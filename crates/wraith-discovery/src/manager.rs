@@ -4,16 +4,18 @@
 //! to provide seamless peer discovery and connection establishment.
 
 use crate::dht::{DhtNode, NodeId};
+use crate::mdns::{LanPeer, MdnsConfig, MdnsService};
 use crate::nat::{
-    Candidate, HolePuncher, IceGatherer, NatDetector, NatType, StunDnsResolver, StunServerSpec,
-    default_stun_servers, fallback_stun_ips,
+    Candidate, HolePuncher, IceGatherer, NatDetector, NatType, PortMapper, PortMapping,
+    PortMappingConfig, StunDnsResolver, StunServerSpec, default_stun_servers, fallback_stun_ips,
 };
-use crate::relay::client::{RelayClient, RelayClientState};
+use crate::relay::client::{RelayClient, RelayClientState, RelayIdentity};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, watch};
+use tokio::task::JoinHandle;
 
 /// Discovery manager errors
 #[derive(Debug, Error)]
@@ -64,6 +66,13 @@ pub struct DiscoveryConfig {
     pub nat_detection_enabled: bool,
     /// Enable relay fallback
     pub relay_enabled: bool,
+    /// Signing key proving ownership of `node_id` to relay servers (relays
+    /// are skipped without it)
+    pub relay_identity: Option<RelayIdentity>,
+    /// Gateway port mapping (`None` disables it)
+    pub port_mapping: Option<PortMappingConfig>,
+    /// Local network discovery over mDNS (`None` disables it)
+    pub mdns: Option<MdnsConfig>,
     /// Connection timeout
     pub connection_timeout: Duration,
 }
@@ -97,6 +106,9 @@ impl DiscoveryConfig {
             relay_servers: Vec::new(),
             nat_detection_enabled: true,
             relay_enabled: true,
+            relay_identity: None,
+            port_mapping: None,
+            mdns: Some(MdnsConfig::default()),
             connection_timeout: Duration::from_secs(10),
         }
     }
@@ -163,6 +175,9 @@ impl DiscoveryConfig {
             relay_servers: Vec::new(),
             nat_detection_enabled: true,
             relay_enabled: true,
+            relay_identity: None,
+            port_mapping: None,
+            mdns: Some(MdnsConfig::default()),
             connection_timeout: Duration::from_secs(10),
         })
     }
@@ -278,6 +293,12 @@ pub struct DiscoveryManager {
     relay_clients: Arc<RwLock<Vec<RelayClient>>>,
     /// Detected NAT type
     nat_type: Arc<RwLock<Option<NatType>>>,
+    /// Current gateway port mapping
+    port_mapping: Arc<watch::Sender<Option<PortMapping>>>,
+    /// Task keeping the port mapping alive
+    port_mapping_task: RwLock<Option<JoinHandle<()>>>,
+    /// Local network announcer and browser
+    mdns: RwLock<Option<MdnsService>>,
     /// Manager state
     state: Arc<RwLock<DiscoveryState>>,
 }
@@ -305,8 +326,10 @@ impl DiscoveryManager {
             None
         };
 
-        // Create ICE gatherer
-        let ice_gatherer = IceGatherer::with_stun_servers(config.stun_servers.clone());
+        // Create ICE gatherer, offering the port mapping once one is obtained
+        let (port_mapping, mapping_updates) = watch::channel(None);
+        let ice_gatherer = IceGatherer::with_stun_servers(config.stun_servers.clone())
+            .with_port_mapping(mapping_updates);
 
         // Create hole puncher
         let hole_puncher = HolePuncher::new(config.listen_addr)
@@ -322,6 +345,9 @@ impl DiscoveryManager {
             hole_puncher,
             relay_clients: Arc::new(RwLock::new(Vec::new())),
             nat_type: Arc::new(RwLock::new(None)),
+            port_mapping: Arc::new(port_mapping),
+            port_mapping_task: RwLock::new(None),
+            mdns: RwLock::new(None),
             state: Arc::new(RwLock::new(DiscoveryState::Stopped)),
         })
     }
@@ -394,6 +420,8 @@ impl DiscoveryManager {
     /// Performs:
     /// - DHT bootstrap
     /// - NAT type detection
+    /// - Gateway port mapping (in the background, if configured)
+    /// - Local network announcement and browsing (if configured)
     /// - Relay registration
     ///
     /// # Errors
@@ -425,7 +453,17 @@ impl DiscoveryManager {
             }
         }
 
-        // 3. Connect to relay servers
+        // 3. Map the listen port on the gateway
+        if let Some(mapping_config) = &self.config.port_mapping {
+            self.start_port_mapping(mapping_config.clone()).await;
+        }
+
+        // 4. Announce and browse on the local network (failure is not fatal)
+        if let Some(mdns_config) = &self.config.mdns {
+            self.start_mdns(mdns_config.clone()).await;
+        }
+
+        // 5. Connect to relay servers
         if self.config.relay_enabled {
             self.connect_relays().await?;
         }
@@ -447,15 +485,52 @@ impl DiscoveryManager {
         Ok(())
     }
 
+    /// Start keeping a gateway port mapping for the listen port
+    async fn start_port_mapping(&self, mapping_config: PortMappingConfig) {
+        let port = mapping_config
+            .internal_port
+            .unwrap_or(self.config.listen_addr.port());
+        if port == 0 {
+            tracing::warn!("Port mapping skipped: listen port is not known yet");
+            return;
+        }
+
+        let mapper = PortMapper::new(mapping_config);
+        let updates = Arc::clone(&self.port_mapping);
+        let task = tokio::spawn(async move { mapper.maintain(port, &updates).await });
+        if let Some(previous) = self.port_mapping_task.write().await.replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// Start mDNS announcement and browsing
+    async fn start_mdns(&self, mdns_config: MdnsConfig) {
+        match MdnsService::start(mdns_config, self.config.node_id, self.config.listen_addr).await {
+            Ok(service) => {
+                if let Some(previous) = self.mdns.write().await.replace(service) {
+                    previous.shutdown().await;
+                }
+            }
+            Err(e) => tracing::warn!("Local network discovery unavailable: {}", e),
+        }
+    }
+
     /// Connect to all relay servers
     async fn connect_relays(&self) -> Result<(), DiscoveryError> {
         let mut clients = Vec::new();
 
+        let Some(identity) = &self.config.relay_identity else {
+            if !self.config.relay_servers.is_empty() {
+                tracing::warn!("No relay identity configured, skipping relay servers");
+            }
+            return Ok(());
+        };
+
         for relay_info in &self.config.relay_servers {
             match RelayClient::connect(relay_info.addr, *self.config.node_id.as_bytes()).await {
                 Ok(mut client) => {
                     // Register with relay
-                    if let Err(e) = client.register(&relay_info.public_key).await {
+                    if let Err(e) = client.register(identity).await {
                         eprintln!("Failed to register with relay {}: {:?}", relay_info.addr, e);
                         continue;
                     }
@@ -484,6 +559,9 @@ impl DiscoveryManager {
     /// 3. Hole punching (if both behind NAT)
     /// 4. Relay fallback (if direct fails)
     ///
+    /// Peers announced on the local network are not considered here; see
+    /// [`Self::peer_candidates`].
+    ///
     /// # Errors
     ///
     /// Returns error if all connection methods fail
@@ -528,6 +606,59 @@ impl DiscoveryManager {
         Err(DiscoveryError::ConnectionFailed)
     }
 
+    /// Candidate connections to a peer, best first
+    ///
+    /// Addresses the peer announced on the local network come first,
+    /// followed by the result of [`Self::connect_to_peer`]. mDNS
+    /// announcements are not authenticated, so the caller must confirm the
+    /// peer's identity at a LAN address (the handshake does) and move on to
+    /// the next candidate when that fails.
+    ///
+    /// `peer_id` may be either the peer's node ID or, for peers found on the
+    /// local network, its handshake static key.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of [`Self::connect_to_peer`] if the peer is not on
+    /// the local network either.
+    pub async fn peer_candidates(
+        &self,
+        peer_id: NodeId,
+    ) -> Result<Vec<PeerConnection>, DiscoveryError> {
+        let mut candidates = self.lan_candidates(&peer_id).await;
+        match self.connect_to_peer(peer_id).await {
+            Ok(conn) => candidates.push(conn),
+            Err(e) if candidates.is_empty() => return Err(e),
+            Err(e) => tracing::debug!("No candidates beyond the local network: {}", e),
+        }
+        Ok(candidates)
+    }
+
+    /// Candidate connections to a peer announced on the local network
+    async fn lan_candidates(&self, peer_id: &NodeId) -> Vec<PeerConnection> {
+        let Some(peer) = self.lan_lookup(peer_id).await else {
+            return Vec::new();
+        };
+        peer.addrs
+            .iter()
+            .map(|&addr| PeerConnection {
+                peer_id: peer.node_id,
+                addr,
+                connection_type: ConnectionType::Direct,
+            })
+            .collect()
+    }
+
+    /// Find a peer announced on the local network
+    async fn lan_lookup(&self, peer_id: &NodeId) -> Option<LanPeer> {
+        self.mdns
+            .read()
+            .await
+            .as_ref()?
+            .lookup(peer_id.as_bytes())
+            .await
+    }
+
     /// Perform DHT lookup for peer
     async fn dht_lookup(&self, peer_id: NodeId) -> Result<Vec<SocketAddr>, DiscoveryError> {
         let mut dht = self.dht.write().await;
@@ -625,11 +756,64 @@ impl DiscoveryManager {
             let _ = client.disconnect().await;
         }
         clients.clear();
+        drop(clients);
+
+        // Announce departure from the local network
+        if let Some(mdns) = self.mdns.write().await.take() {
+            mdns.shutdown().await;
+        }
+
+        // Stop renewing the port mapping and remove it from the gateway
+        if let Some(task) = self.port_mapping_task.write().await.take() {
+            task.abort();
+        }
+        if let Some(mapping) = self.port_mapping.send_replace(None)
+            && let Some(mapping_config) = &self.config.port_mapping
+            && let Err(e) = PortMapper::new(mapping_config.clone())
+                .unmap(&mapping)
+                .await
+        {
+            tracing::debug!("Failed to delete port mapping: {}", e);
+        }
 
         *self.state.write().await = DiscoveryState::Stopped;
         Ok(())
     }
 
+    /// Get the current gateway port mapping, if one is held
+    #[must_use]
+    pub fn port_mapping(&self) -> Option<PortMapping> {
+        self.port_mapping.borrow().clone()
+    }
+
+    /// Get the external address of the gateway port mapping, if one is held
+    #[must_use]
+    pub fn mapped_address(&self) -> Option<SocketAddr> {
+        self.port_mapping
+            .borrow()
+            .as_ref()
+            .map(|mapping| mapping.external_addr)
+    }
+
+    /// Subscribe to gateway port mapping changes
+    #[must_use]
+    pub fn subscribe_port_mapping(&self) -> watch::Receiver<Option<PortMapping>> {
+        self.port_mapping.subscribe()
+    }
+
+    /// Get the peers currently announced on the local network
+    pub async fn lan_peers(&self) -> Vec<LanPeer> {
+        match self.mdns.read().await.as_ref() {
+            Some(mdns) => mdns.peers().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether local network announcement and browsing is running
+    pub async fn mdns_running(&self) -> bool {
+        self.mdns.read().await.is_some()
+    }
+
     /// Get current manager state
     #[must_use]
     pub async fn state(&self) -> DiscoveryState {
@@ -649,6 +833,14 @@ impl DiscoveryManager {
     }
 }
 
+impl Drop for DiscoveryManager {
+    fn drop(&mut self) {
+        if let Some(task) = self.port_mapping_task.get_mut().take() {
+            task.abort();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,569 @@
+//! DNS Message Encoding
+//!
+//! The subset of the DNS wire format (RFC 1035) that mDNS service discovery
+//! needs: questions plus A, AAAA, PTR, SRV and TXT records. Names are
+//! written uncompressed; compression pointers are followed when reading.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+use thiserror::Error;
+
+/// Record type A (IPv4 address)
+pub const TYPE_A: u16 = 1;
+/// Record type PTR (domain name pointer)
+pub const TYPE_PTR: u16 = 12;
+/// Record type TXT (text strings)
+pub const TYPE_TXT: u16 = 16;
+/// Record type AAAA (IPv6 address)
+pub const TYPE_AAAA: u16 = 28;
+/// Record type SRV (service location)
+pub const TYPE_SRV: u16 = 33;
+/// Query type ANY
+pub const TYPE_ANY: u16 = 255;
+
+/// Class IN
+const CLASS_IN: u16 = 1;
+
+/// Top bit of the class field: unicast-response in questions, cache-flush
+/// in records (RFC 6762 §5.4, §10.2)
+const CLASS_TOP_BIT: u16 = 0x8000;
+
+/// Header flags for an authoritative response
+const FLAGS_RESPONSE: u16 = 0x8400;
+
+/// Longest encoded name (RFC 1035 §2.3.4)
+const MAX_NAME_LEN: usize = 255;
+
+/// Longest label (RFC 1035 §2.3.4)
+const MAX_LABEL_LEN: usize = 63;
+
+/// Compression pointers followed before giving up on a name
+const MAX_POINTERS: usize = 16;
+
+/// DNS message errors
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MessageError {
+    /// Message ended before a field was complete
+    #[error("Truncated DNS message")]
+    Truncated,
+
+    /// A name was malformed (bad label, pointer loop, too long)
+    #[error("Invalid DNS name")]
+    InvalidName,
+
+    /// A name or label is too long to encode
+    #[error("Name too long to encode: {0}")]
+    NameTooLong(String),
+}
+
+/// A question
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// Name asked about
+    pub name: String,
+    /// Record type asked for
+    pub qtype: u16,
+    /// Whether the querier asked for a unicast response
+    pub unicast_response: bool,
+}
+
+/// Record data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    /// IPv4 address
+    A(Ipv4Addr),
+    /// IPv6 address
+    Aaaa(Ipv6Addr),
+    /// Pointer to another name
+    Ptr(String),
+    /// Service location
+    Srv {
+        /// Priority (lower first)
+        priority: u16,
+        /// Weight among equal priorities
+        weight: u16,
+        /// Port the service listens on
+        port: u16,
+        /// Host providing the service
+        target: String,
+    },
+    /// Text strings, usually `key=value`
+    Txt(Vec<String>),
+    /// Any other record type, kept only by type
+    Other(u16),
+}
+
+impl RecordData {
+    /// Record type code
+    #[must_use]
+    pub fn record_type(&self) -> u16 {
+        match self {
+            Self::A(_) => TYPE_A,
+            Self::Aaaa(_) => TYPE_AAAA,
+            Self::Ptr(_) => TYPE_PTR,
+            Self::Srv { .. } => TYPE_SRV,
+            Self::Txt(_) => TYPE_TXT,
+            Self::Other(rtype) => *rtype,
+        }
+    }
+}
+
+/// A resource record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Owner name
+    pub name: String,
+    /// Time to live in seconds (0 announces removal)
+    pub ttl: u32,
+    /// Whether other cached records of this name and type are replaced
+    pub cache_flush: bool,
+    /// Record data
+    pub data: RecordData,
+}
+
+/// A DNS message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Message {
+    /// Transaction ID (zero for multicast)
+    pub id: u16,
+    /// Header flags
+    pub flags: u16,
+    /// Questions
+    pub questions: Vec<Question>,
+    /// Answer records
+    pub answers: Vec<Record>,
+    /// Additional records (authority records are read into this list too)
+    pub additionals: Vec<Record>,
+}
+
+impl Message {
+    /// Create a query
+    #[must_use]
+    pub fn query(questions: Vec<Question>) -> Self {
+        Self {
+            questions,
+            ..Self::default()
+        }
+    }
+
+    /// Create an authoritative response
+    #[must_use]
+    pub fn response(answers: Vec<Record>, additionals: Vec<Record>) -> Self {
+        Self {
+            flags: FLAGS_RESPONSE,
+            answers,
+            additionals,
+            ..Self::default()
+        }
+    }
+
+    /// Whether this is a response (QR bit set)
+    #[must_use]
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Answer and additional records together
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.answers.iter().chain(&self.additionals)
+    }
+
+    /// Encode to wire format
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name is too long to encode.
+    pub fn encode(&self) -> Result<Vec<u8>, MessageError> {
+        let mut out = Vec::with_capacity(512);
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.flags.to_be_bytes());
+        for count in [
+            self.questions.len(),
+            self.answers.len(),
+            0,
+            self.additionals.len(),
+        ] {
+            let count = u16::try_from(count).unwrap_or(u16::MAX);
+            out.extend_from_slice(&count.to_be_bytes());
+        }
+
+        for question in &self.questions {
+            encode_name(&mut out, &question.name)?;
+            out.extend_from_slice(&question.qtype.to_be_bytes());
+            let class = if question.unicast_response {
+                CLASS_IN | CLASS_TOP_BIT
+            } else {
+                CLASS_IN
+            };
+            out.extend_from_slice(&class.to_be_bytes());
+        }
+        for record in self.records() {
+            encode_record(&mut out, record)?;
+        }
+        Ok(out)
+    }
+
+    /// Decode from wire format
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is truncated or a name is malformed.
+    pub fn decode(data: &[u8]) -> Result<Self, MessageError> {
+        let mut reader = Reader { data, pos: 0 };
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let qdcount = reader.u16()?;
+        let ancount = reader.u16()?;
+        let nscount = reader.u16()?;
+        let arcount = reader.u16()?;
+
+        let mut message = Self {
+            id,
+            flags,
+            ..Self::default()
+        };
+        for _ in 0..qdcount {
+            let name = reader.name()?;
+            let qtype = reader.u16()?;
+            let class = reader.u16()?;
+            message.questions.push(Question {
+                name,
+                qtype,
+                unicast_response: class & CLASS_TOP_BIT != 0,
+            });
+        }
+        for _ in 0..ancount {
+            message.answers.push(reader.record()?);
+        }
+        for _ in 0..(u32::from(nscount) + u32::from(arcount)) {
+            message.additionals.push(reader.record()?);
+        }
+        Ok(message)
+    }
+}
+
+/// Compare DNS names case-insensitively, ignoring a trailing dot
+#[must_use]
+pub fn names_equal(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) -> Result<(), MessageError> {
+    let start = out.len();
+    for label in name
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|l| !l.is_empty())
+    {
+        if label.len() > MAX_LABEL_LEN {
+            return Err(MessageError::NameTooLong(name.to_string()));
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    if out.len() - start > MAX_NAME_LEN {
+        return Err(MessageError::NameTooLong(name.to_string()));
+    }
+    Ok(())
+}
+
+fn encode_record(out: &mut Vec<u8>, record: &Record) -> Result<(), MessageError> {
+    encode_name(out, &record.name)?;
+    out.extend_from_slice(&record.data.record_type().to_be_bytes());
+    let class = if record.cache_flush {
+        CLASS_IN | CLASS_TOP_BIT
+    } else {
+        CLASS_IN
+    };
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&record.ttl.to_be_bytes());
+
+    let length_pos = out.len();
+    out.extend_from_slice(&[0, 0]);
+    match &record.data {
+        RecordData::A(ip) => out.extend_from_slice(&ip.octets()),
+        RecordData::Aaaa(ip) => out.extend_from_slice(&ip.octets()),
+        RecordData::Ptr(target) => encode_name(out, target)?,
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            target,
+        } => {
+            out.extend_from_slice(&priority.to_be_bytes());
+            out.extend_from_slice(&weight.to_be_bytes());
+            out.extend_from_slice(&port.to_be_bytes());
+            encode_name(out, target)?;
+        }
+        RecordData::Txt(strings) => {
+            // An empty TXT record holds a single empty string (RFC 6763 §6.1)
+            if strings.is_empty() {
+                out.push(0);
+            }
+            for string in strings {
+                let bytes = &string.as_bytes()[..string.len().min(255)];
+                out.push(bytes.len() as u8);
+                out.extend_from_slice(bytes);
+            }
+        }
+        RecordData::Other(_) => {}
+    }
+    let rdlength = u16::try_from(out.len() - length_pos - 2).unwrap_or(u16::MAX);
+    out[length_pos..length_pos + 2].copy_from_slice(&rdlength.to_be_bytes());
+    Ok(())
+}
+
+/// Cursor over a message being decoded
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], MessageError> {
+        let end = self.pos.checked_add(len).ok_or(MessageError::Truncated)?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or(MessageError::Truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, MessageError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, MessageError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, MessageError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a possibly compressed name
+    fn name(&mut self) -> Result<String, MessageError> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut pos = self.pos;
+        let mut resume = None;
+        let mut pointers = 0;
+        let mut length = 0;
+
+        loop {
+            let len = *self.data.get(pos).ok_or(MessageError::Truncated)?;
+            match len {
+                0 => {
+                    pos += 1;
+                    break;
+                }
+                len if len & 0xC0 == 0xC0 => {
+                    let low = *self.data.get(pos + 1).ok_or(MessageError::Truncated)?;
+                    pointers += 1;
+                    if pointers > MAX_POINTERS {
+                        return Err(MessageError::InvalidName);
+                    }
+                    resume.get_or_insert(pos + 2);
+                    pos = (usize::from(len & 0x3F) << 8) | usize::from(low);
+                }
+                len if len & 0xC0 == 0 => {
+                    let len = usize::from(len);
+                    let label = self
+                        .data
+                        .get(pos + 1..pos + 1 + len)
+                        .ok_or(MessageError::Truncated)?;
+                    length += len + 1;
+                    if length > MAX_NAME_LEN {
+                        return Err(MessageError::InvalidName);
+                    }
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    pos += 1 + len;
+                }
+                _ => return Err(MessageError::InvalidName),
+            }
+        }
+
+        self.pos = resume.unwrap_or(pos);
+        Ok(labels.join("."))
+    }
+
+    fn record(&mut self) -> Result<Record, MessageError> {
+        let name = self.name()?;
+        let rtype = self.u16()?;
+        let class = self.u16()?;
+        let ttl = self.u32()?;
+        let rdlength = usize::from(self.u16()?);
+        let rdata_end = self.pos + rdlength;
+        if rdata_end > self.data.len() {
+            return Err(MessageError::Truncated);
+        }
+
+        let data = match rtype {
+            TYPE_A if rdlength == 4 => {
+                let b = self.bytes(4)?;
+                RecordData::A(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+            }
+            TYPE_AAAA if rdlength == 16 => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(self.bytes(16)?);
+                RecordData::Aaaa(Ipv6Addr::from(octets))
+            }
+            TYPE_PTR => RecordData::Ptr(self.name()?),
+            TYPE_SRV => RecordData::Srv {
+                priority: self.u16()?,
+                weight: self.u16()?,
+                port: self.u16()?,
+                target: self.name()?,
+            },
+            TYPE_TXT => {
+                let mut strings = Vec::new();
+                while self.pos < rdata_end {
+                    let len = usize::from(self.u8()?);
+                    let bytes = self.bytes(len)?;
+                    if !bytes.is_empty() {
+                        strings.push(String::from_utf8_lossy(bytes).into_owned());
+                    }
+                }
+                RecordData::Txt(strings)
+            }
+            other => RecordData::Other(other),
+        };
+        if self.pos > rdata_end {
+            return Err(MessageError::Truncated);
+        }
+        self.pos = rdata_end;
+
+        Ok(Record {
+            name,
+            ttl,
+            cache_flush: class & CLASS_TOP_BIT != 0,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_response() -> Message {
+        Message::response(
+            vec![Record {
+                name: "_wraith._udp.local".to_string(),
+                ttl: 4500,
+                cache_flush: false,
+                data: RecordData::Ptr("node._wraith._udp.local".to_string()),
+            }],
+            vec![
+                Record {
+                    name: "node._wraith._udp.local".to_string(),
+                    ttl: 120,
+                    cache_flush: true,
+                    data: RecordData::Srv {
+                        priority: 0,
+                        weight: 0,
+                        port: 8420,
+                        target: "node.local".to_string(),
+                    },
+                },
+                Record {
+                    name: "node._wraith._udp.local".to_string(),
+                    ttl: 4500,
+                    cache_flush: true,
+                    data: RecordData::Txt(vec!["v=1".to_string(), "id=abcd".to_string()]),
+                },
+                Record {
+                    name: "node.local".to_string(),
+                    ttl: 120,
+                    cache_flush: true,
+                    data: RecordData::A(Ipv4Addr::new(192, 168, 1, 20)),
+                },
+                Record {
+                    name: "node.local".to_string(),
+                    ttl: 120,
+                    cache_flush: true,
+                    data: RecordData::Aaaa("fe80::1".parse().unwrap()),
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let message = sample_response();
+        let decoded = Message::decode(&message.encode().unwrap()).unwrap();
+        assert_eq!(decoded, message);
+        assert!(decoded.is_response());
+        assert_eq!(decoded.records().count(), 5);
+    }
+
+    #[test]
+    fn test_query_roundtrip() {
+        let message = Message::query(vec![Question {
+            name: "_wraith._udp.local".to_string(),
+            qtype: TYPE_PTR,
+            unicast_response: true,
+        }]);
+        let bytes = message.encode().unwrap();
+        assert_eq!(&bytes[..12], &[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+
+        let decoded = Message::decode(&bytes).unwrap();
+        assert_eq!(decoded, message);
+        assert!(!decoded.is_response());
+    }
+
+    #[test]
+    fn test_decode_compressed_names() {
+        // Response with one PTR answer whose name and target use pointers
+        let mut bytes = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        // Owner at offset 12: _wraith._udp.local
+        bytes.extend_from_slice(b"\x07_wraith\x04_udp\x05local\x00");
+        bytes.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        bytes.extend_from_slice(&CLASS_IN.to_be_bytes());
+        bytes.extend_from_slice(&120u32.to_be_bytes());
+        // rdata: "node" + pointer to offset 12
+        bytes.extend_from_slice(&7u16.to_be_bytes());
+        bytes.extend_from_slice(b"\x04node\xC0\x0C");
+
+        let message = Message::decode(&bytes).unwrap();
+        assert_eq!(
+            message.answers[0].data,
+            RecordData::Ptr("node._wraith._udp.local".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed() {
+        let bytes = sample_response().encode().unwrap();
+        for len in [0, 5, 12, 30, bytes.len() - 1] {
+            assert!(Message::decode(&bytes[..len]).is_err(), "length {len}");
+        }
+
+        // A pointer to itself must not loop forever
+        let mut looped = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        looped.extend_from_slice(&[0xC0, 0x0C, 0, 12, 0, 1]);
+        assert_eq!(Message::decode(&looped), Err(MessageError::InvalidName));
+    }
+
+    #[test]
+    fn test_encode_rejects_long_label() {
+        let message = Message::query(vec![Question {
+            name: format!("{}.local", "x".repeat(64)),
+            qtype: TYPE_PTR,
+            unicast_response: false,
+        }]);
+        assert!(matches!(
+            message.encode(),
+            Err(MessageError::NameTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_names_equal() {
+        assert!(names_equal("_WRAITH._udp.local.", "_wraith._udp.local"));
+        assert!(!names_equal("_wraith._tcp.local", "_wraith._udp.local"));
+    }
+}
@@ -0,0 +1,21 @@
+//! Local Network Discovery
+//!
+//! Announces this node as a DNS-SD service instance of type
+//! `_wraith._udp.local` over multicast DNS (RFC 6762, RFC 6763) and browses
+//! for other instances, so peers on the same link find each other without a
+//! DHT bootstrap node or relay.
+//!
+//! Each instance publishes:
+//! - PTR `_wraith._udp.local` → `wraith-<id prefix>._wraith._udp.local`
+//! - SRV with the node's listen port and host name
+//! - TXT `v=1`, `id=<node ID>` and, if configured, `key=<handshake key>`
+//! - A/AAAA records for the announced addresses
+//!
+//! Instance names are derived from the node ID, so no probing for name
+//! conflicts is done. Announcing can be disabled with
+//! [`MdnsConfig::announce`] to browse without revealing the node.
+
+pub mod message;
+mod service;
+
+pub use service::{LanPeer, MDNS_MULTICAST_ADDR, MdnsConfig, MdnsError, MdnsService, SERVICE_TYPE};
@@ -0,0 +1,860 @@
+//! mDNS Announcer and Browser
+//!
+//! A single [`MdnsService`] both answers queries for this node's service
+//! instance and browses for other instances, caching what it learns until the
+//! records' TTL runs out.
+
+use super::message::{
+    Message, MessageError, Question, Record, RecordData, TYPE_ANY, TYPE_PTR, TYPE_SRV, TYPE_TXT,
+    names_equal,
+};
+use crate::dht::NodeId;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+/// mDNS IPv4 multicast group and port (RFC 6762)
+pub const MDNS_MULTICAST_ADDR: SocketAddrV4 =
+    SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+/// DNS-SD service type for WRAITH nodes
+pub const SERVICE_TYPE: &str = "_wraith._udp.local";
+
+/// DNS-SD service type enumeration name (RFC 6763 §9)
+const SERVICE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+/// TXT record format version
+const TXT_VERSION: &str = "1";
+
+/// Delay between the two announcements sent at startup (RFC 6762 §8.3)
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Largest mDNS message accepted (RFC 6762 §17)
+const MAX_MESSAGE_SIZE: usize = 9000;
+
+/// Longest a received record is cached, whatever TTL it carries (RFC 6762 §10)
+const MAX_RECORD_TTL: u32 = 4500;
+
+/// Most LAN peers cached; the soonest-expiring entry makes room for a new one
+const MAX_LAN_PEERS: usize = 256;
+
+/// mDNS errors
+#[derive(Debug, Error)]
+pub enum MdnsError {
+    /// Socket error
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Message could not be encoded
+    #[error("DNS message error: {0}")]
+    Message(#[from] MessageError),
+}
+
+/// mDNS configuration
+#[derive(Debug, Clone)]
+pub struct MdnsConfig {
+    /// DNS-SD service type to announce and browse
+    pub service_type: String,
+    /// Multicast group and port
+    pub multicast_addr: SocketAddrV4,
+    /// Answer queries for this node; `false` browses without revealing it
+    pub announce: bool,
+    /// Port to announce; `None` announces the discovery listen port
+    pub listen_port: Option<u16>,
+    /// Addresses to announce; empty uses the listen address, or the address
+    /// of the interface multicast goes out on when listening on all interfaces
+    pub addresses: Vec<IpAddr>,
+    /// Handshake static key, published so peers can look the node up by it
+    pub peer_key: Option<[u8; 32]>,
+    /// TTL of announced records
+    pub ttl: Duration,
+    /// Interval between browse queries
+    pub query_interval: Duration,
+}
+
+impl Default for MdnsConfig {
+    fn default() -> Self {
+        Self {
+            service_type: SERVICE_TYPE.to_string(),
+            multicast_addr: MDNS_MULTICAST_ADDR,
+            announce: true,
+            listen_port: None,
+            addresses: Vec::new(),
+            peer_key: None,
+            ttl: Duration::from_secs(120),
+            query_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A peer found on the local network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanPeer {
+    /// Peer node ID
+    pub node_id: NodeId,
+    /// Peer handshake static key, if published
+    pub peer_key: Option<[u8; 32]>,
+    /// Announced addresses
+    pub addrs: Vec<SocketAddr>,
+    /// When the announcement expires
+    pub expires: Instant,
+}
+
+impl LanPeer {
+    /// Whether `id` is this peer's node ID or handshake key
+    #[must_use]
+    pub fn matches(&self, id: &[u8; 32]) -> bool {
+        self.node_id.as_bytes() == id || self.peer_key.as_ref() == Some(id)
+    }
+}
+
+/// Names and records describing this node's service instance
+#[derive(Debug)]
+struct LocalService {
+    service_type: String,
+    instance: String,
+    host: String,
+    port: u16,
+    addresses: Vec<IpAddr>,
+    txt: Vec<String>,
+    ttl: u32,
+}
+
+impl LocalService {
+    fn new(config: &MdnsConfig, node_id: &NodeId, listen_addr: SocketAddr) -> Self {
+        let label = format!("wraith-{}", hex_encode(&node_id.as_bytes()[..8]));
+        let mut txt = vec![
+            format!("v={TXT_VERSION}"),
+            format!("id={}", hex_encode(node_id.as_bytes())),
+        ];
+        if let Some(key) = &config.peer_key {
+            txt.push(format!("key={}", hex_encode(key)));
+        }
+
+        let addresses = if !config.addresses.is_empty() {
+            config.addresses.clone()
+        } else if !listen_addr.ip().is_unspecified() {
+            vec![listen_addr.ip()]
+        } else {
+            outgoing_interface(config.multicast_addr)
+                .into_iter()
+                .collect()
+        };
+
+        Self {
+            instance: format!("{label}.{}", config.service_type),
+            host: format!("{label}.local"),
+            service_type: config.service_type.clone(),
+            port: config.listen_port.unwrap_or(listen_addr.port()),
+            addresses,
+            txt,
+            ttl: u32::try_from(config.ttl.as_secs()).unwrap_or(u32::MAX),
+        }
+    }
+
+    fn ptr(&self, ttl: u32) -> Record {
+        Record {
+            name: self.service_type.clone(),
+            ttl,
+            cache_flush: false,
+            data: RecordData::Ptr(self.instance.clone()),
+        }
+    }
+
+    fn srv(&self, ttl: u32) -> Record {
+        Record {
+            name: self.instance.clone(),
+            ttl,
+            cache_flush: true,
+            data: RecordData::Srv {
+                priority: 0,
+                weight: 0,
+                port: self.port,
+                target: self.host.clone(),
+            },
+        }
+    }
+
+    fn txt(&self, ttl: u32) -> Record {
+        Record {
+            name: self.instance.clone(),
+            ttl,
+            cache_flush: true,
+            data: RecordData::Txt(self.txt.clone()),
+        }
+    }
+
+    fn address_records(&self, ttl: u32) -> Vec<Record> {
+        self.addresses
+            .iter()
+            .map(|ip| Record {
+                name: self.host.clone(),
+                ttl,
+                cache_flush: true,
+                data: match ip {
+                    IpAddr::V4(v4) => RecordData::A(*v4),
+                    IpAddr::V6(v6) => RecordData::Aaaa(*v6),
+                },
+            })
+            .collect()
+    }
+
+    /// Full announcement: PTR answer with SRV, TXT and addresses attached
+    fn announcement(&self, ttl: u32) -> Message {
+        let mut additionals = vec![self.srv(ttl), self.txt(ttl)];
+        additionals.extend(self.address_records(ttl));
+        Message::response(vec![self.ptr(ttl)], additionals)
+    }
+
+    /// Build the answer to `query`, or `None` if nothing in it concerns us
+    fn answer(&self, query: &Message) -> Option<Message> {
+        let ttl = self.ttl;
+        let mut answers = Vec::new();
+        let mut full_set = false;
+
+        for question in &query.questions {
+            let qtype = question.qtype;
+            if names_equal(&question.name, &self.service_type)
+                && matches!(qtype, TYPE_PTR | TYPE_ANY)
+                && !self.known_to(query)
+            {
+                answers.push(self.ptr(ttl));
+                full_set = true;
+            } else if names_equal(&question.name, &self.instance) {
+                if matches!(qtype, TYPE_SRV | TYPE_ANY) {
+                    answers.push(self.srv(ttl));
+                }
+                if matches!(qtype, TYPE_TXT | TYPE_ANY) {
+                    answers.push(self.txt(ttl));
+                }
+                full_set = true;
+            } else if names_equal(&question.name, &self.host) {
+                answers.extend(
+                    self.address_records(ttl)
+                        .into_iter()
+                        .filter(|r| qtype == TYPE_ANY || r.data.record_type() == qtype),
+                );
+            } else if names_equal(&question.name, SERVICE_ENUMERATION) && qtype == TYPE_PTR {
+                answers.push(Record {
+                    name: SERVICE_ENUMERATION.to_string(),
+                    ttl,
+                    cache_flush: false,
+                    data: RecordData::Ptr(self.service_type.clone()),
+                });
+            }
+        }
+        if answers.is_empty() {
+            return None;
+        }
+
+        // Attach everything needed to reach the instance (RFC 6763 §12)
+        let mut additionals = Vec::new();
+        if full_set {
+            for record in [self.srv(ttl), self.txt(ttl)] {
+                if !answers.contains(&record) {
+                    additionals.push(record);
+                }
+            }
+            additionals.extend(self.address_records(ttl));
+        }
+        Some(Message::response(answers, additionals))
+    }
+
+    /// Known-answer suppression: the querier already holds our PTR record
+    /// with at least half its TTL left (RFC 6762 §7.1)
+    fn known_to(&self, query: &Message) -> bool {
+        query.answers.iter().any(|record| {
+            names_equal(&record.name, &self.service_type)
+                && matches!(&record.data, RecordData::Ptr(target) if names_equal(target, &self.instance))
+                && record.ttl >= self.ttl / 2
+        })
+    }
+}
+
+/// Local network announcer and browser for WRAITH nodes
+///
+/// Dropping the service stops it without announcing departure; call
+/// [`shutdown`](Self::shutdown) to send goodbye records first.
+pub struct MdnsService {
+    config: MdnsConfig,
+    node_id: NodeId,
+    socket: Arc<UdpSocket>,
+    local: Arc<LocalService>,
+    peers: Arc<RwLock<HashMap<NodeId, LanPeer>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MdnsService {
+    /// Start announcing (if enabled) and browsing
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the multicast socket cannot be set up.
+    pub async fn start(
+        config: MdnsConfig,
+        node_id: NodeId,
+        listen_addr: SocketAddr,
+    ) -> Result<Self, MdnsError> {
+        let socket = Arc::new(bind_multicast(config.multicast_addr)?);
+        let local = Arc::new(LocalService::new(&config, &node_id, listen_addr));
+        let peers = Arc::new(RwLock::new(HashMap::new()));
+
+        let receiver = tokio::spawn(receive_loop(
+            Arc::clone(&socket),
+            Arc::clone(&local),
+            Arc::clone(&peers),
+            node_id,
+            config.clone(),
+        ));
+        let sender = tokio::spawn(query_loop(
+            Arc::clone(&socket),
+            Arc::clone(&local),
+            Arc::clone(&peers),
+            config.clone(),
+        ));
+
+        tracing::debug!(
+            "mDNS {} {} on port {}",
+            if config.announce {
+                "announcing"
+            } else {
+                "browsing for"
+            },
+            local.instance,
+            local.port
+        );
+
+        Ok(Self {
+            config,
+            node_id,
+            socket,
+            local,
+            peers,
+            tasks: vec![receiver, sender],
+        })
+    }
+
+    /// Get the node ID being announced
+    #[must_use]
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    /// Get the announced service instance name
+    #[must_use]
+    pub fn instance_name(&self) -> &str {
+        &self.local.instance
+    }
+
+    /// Peers currently announced on the local network
+    pub async fn peers(&self) -> Vec<LanPeer> {
+        let now = Instant::now();
+        self.peers
+            .read()
+            .await
+            .values()
+            .filter(|peer| peer.expires > now)
+            .cloned()
+            .collect()
+    }
+
+    /// Find a peer by node ID or handshake key
+    pub async fn lookup(&self, id: &[u8; 32]) -> Option<LanPeer> {
+        let now = Instant::now();
+        self.peers
+            .read()
+            .await
+            .values()
+            .find(|peer| peer.expires > now && peer.matches(id))
+            .cloned()
+    }
+
+    /// Send a browse query now instead of waiting for the next interval
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query cannot be sent.
+    pub async fn query(&self) -> Result<(), MdnsError> {
+        send_query(&self.socket, &self.config).await
+    }
+
+    /// Announce departure and stop
+    pub async fn shutdown(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        if self.config.announce {
+            let goodbye = self.local.announcement(0);
+            if let Err(e) = send(&self.socket, &goodbye, self.config.multicast_addr.into()).await {
+                tracing::debug!("Failed to send mDNS goodbye: {}", e);
+            }
+        }
+    }
+}
+
+impl Drop for MdnsService {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Answer queries and record announcements
+async fn receive_loop(
+    socket: Arc<UdpSocket>,
+    local: Arc<LocalService>,
+    peers: Arc<RwLock<HashMap<NodeId, LanPeer>>>,
+    node_id: NodeId,
+    config: MdnsConfig,
+) {
+    let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::debug!("mDNS receive error: {}", e);
+                continue;
+            }
+        };
+        let Ok(message) = Message::decode(&buf[..len]) else {
+            continue;
+        };
+
+        if message.is_response() {
+            record_peers(&message, from, &config.service_type, &node_id, &peers).await;
+        } else if config.announce
+            && let Some(mut response) = local.answer(&message)
+        {
+            // Queries from a port other than 5353 come from simple resolvers
+            // that expect a unicast reply echoing the query (RFC 6762 §6.7)
+            let legacy = from.port() != config.multicast_addr.port();
+            let unicast = legacy || message.questions.iter().any(|q| q.unicast_response);
+            let dest = if unicast {
+                from
+            } else {
+                config.multicast_addr.into()
+            };
+            if legacy {
+                response.id = message.id;
+                response.questions = message.questions.clone();
+            }
+            if let Err(e) = send(&socket, &response, dest).await {
+                tracing::debug!("Failed to send mDNS response to {}: {}", dest, e);
+            }
+        }
+    }
+}
+
+/// Announce at startup, then browse periodically
+async fn query_loop(
+    socket: Arc<UdpSocket>,
+    local: Arc<LocalService>,
+    peers: Arc<RwLock<HashMap<NodeId, LanPeer>>>,
+    config: MdnsConfig,
+) {
+    let multicast: SocketAddr = config.multicast_addr.into();
+    if config.announce {
+        for _ in 0..2 {
+            if let Err(e) = send(&socket, &local.announcement(local.ttl), multicast).await {
+                tracing::debug!("Failed to send mDNS announcement: {}", e);
+            }
+            tokio::time::sleep(ANNOUNCE_INTERVAL).await;
+        }
+    }
+
+    loop {
+        if let Err(e) = send_query(&socket, &config).await {
+            tracing::debug!("Failed to send mDNS query: {}", e);
+        }
+        tokio::time::sleep(config.query_interval).await;
+
+        let now = Instant::now();
+        peers.write().await.retain(|_, peer| peer.expires > now);
+    }
+}
+
+/// Browse for the service type
+async fn send_query(socket: &UdpSocket, config: &MdnsConfig) -> Result<(), MdnsError> {
+    let query = Message::query(vec![Question {
+        name: config.service_type.clone(),
+        qtype: TYPE_PTR,
+        unicast_response: false,
+    }]);
+    send(socket, &query, config.multicast_addr.into()).await
+}
+
+/// Extract service instances from a response into the peer cache
+async fn record_peers(
+    message: &Message,
+    from: SocketAddr,
+    service_type: &str,
+    own_id: &NodeId,
+    peers: &RwLock<HashMap<NodeId, LanPeer>>,
+) {
+    let instances = message.records().filter_map(|record| match &record.data {
+        RecordData::Ptr(instance) if names_equal(&record.name, service_type) => {
+            Some((instance, record.ttl))
+        }
+        _ => None,
+    });
+
+    for (instance, ptr_ttl) in instances {
+        let Some(txt) = message.records().find_map(|r| match &r.data {
+            RecordData::Txt(strings) if names_equal(&r.name, instance) => Some(strings),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let Some(node_id) = txt_value(txt, "id")
+            .and_then(hex_decode_32)
+            .map(NodeId::from_bytes)
+        else {
+            continue;
+        };
+        if node_id == *own_id {
+            continue;
+        }
+        if ptr_ttl == 0 {
+            peers.write().await.remove(&node_id);
+            tracing::debug!("LAN peer {} left", node_id);
+            continue;
+        }
+
+        let Some((port, target, ttl)) = message.records().find_map(|r| match &r.data {
+            RecordData::Srv { port, target, .. } if names_equal(&r.name, instance) => {
+                Some((*port, target, r.ttl))
+            }
+            _ => None,
+        }) else {
+            continue;
+        };
+
+        let mut addrs: Vec<SocketAddr> = message
+            .records()
+            .filter(|r| names_equal(&r.name, target))
+            .filter_map(|r| match r.data {
+                RecordData::A(ip) => Some(IpAddr::V4(ip)),
+                // Link-local IPv6 addresses need a scope we cannot infer
+                RecordData::Aaaa(ip) if !ip.is_unicast_link_local() => Some(IpAddr::V6(ip)),
+                _ => None,
+            })
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        if addrs.is_empty() {
+            addrs.push(SocketAddr::new(from.ip(), port));
+        }
+
+        let ttl = ttl.min(ptr_ttl).min(MAX_RECORD_TTL);
+        let peer = LanPeer {
+            node_id,
+            peer_key: txt_value(txt, "key").and_then(hex_decode_32),
+            addrs,
+            expires: Instant::now() + Duration::from_secs(u64::from(ttl)),
+        };
+        let mut peers = peers.write().await;
+        if !peers.contains_key(&node_id) && peers.len() >= MAX_LAN_PEERS {
+            let now = Instant::now();
+            peers.retain(|_, peer| peer.expires > now);
+            if peers.len() >= MAX_LAN_PEERS
+                && let Some(evicted) = peers
+                    .values()
+                    .min_by_key(|peer| peer.expires)
+                    .map(|peer| peer.node_id)
+            {
+                peers.remove(&evicted);
+                tracing::debug!("LAN peer cache full, evicted {}", evicted);
+            }
+        }
+        if peers.insert(node_id, peer.clone()).is_none() {
+            tracing::debug!("Found LAN peer {} at {:?}", node_id, peer.addrs);
+        }
+    }
+}
+
+async fn send(socket: &UdpSocket, message: &Message, dest: SocketAddr) -> Result<(), MdnsError> {
+    socket.send_to(&message.encode()?, dest).await?;
+    Ok(())
+}
+
+/// Bind a socket to the mDNS port, shared with any other responders on the
+/// host, and join the multicast group
+fn bind_multicast(group: SocketAddrV4) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, group.port())).into())?;
+    socket.join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Address of the interface multicast to `group` leaves from
+fn outgoing_interface(group: SocketAddrV4) -> Option<IpAddr> {
+    let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect(group).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+/// Value of `key=value` in a TXT record
+fn txt_value<'a>(txt: &'a [String], key: &str) -> Option<&'a str> {
+    txt.iter().find_map(|entry| {
+        let (k, v) = entry.split_once('=')?;
+        k.eq_ignore_ascii_case(key).then_some(v)
+    })
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode_32(text: &str) -> Option<[u8; 32]> {
+    if text.len() != 64 || !text.is_ascii() {
+        return None;
+    }
+    let mut out = [0u8; 32];
+    for (i, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdns::message::{TYPE_A, TYPE_AAAA};
+
+    fn local_service(node_id: &NodeId, key: Option<[u8; 32]>) -> LocalService {
+        let config = MdnsConfig {
+            peer_key: key,
+            addresses: vec!["192.168.1.20".parse().unwrap()],
+            ..MdnsConfig::default()
+        };
+        LocalService::new(&config, node_id, "0.0.0.0:8420".parse().unwrap())
+    }
+
+    fn browse_query() -> Message {
+        Message::query(vec![Question {
+            name: SERVICE_TYPE.to_string(),
+            qtype: TYPE_PTR,
+            unicast_response: false,
+        }])
+    }
+
+    #[test]
+    fn test_local_service_names() {
+        let node_id = NodeId::from_bytes([0xAB; 32]);
+        let local = local_service(&node_id, None);
+        assert_eq!(local.instance, "wraith-abababababababab._wraith._udp.local");
+        assert_eq!(local.host, "wraith-abababababababab.local");
+        assert_eq!(local.port, 8420);
+        assert_eq!(txt_value(&local.txt, "v"), Some("1"));
+        assert_eq!(
+            txt_value(&local.txt, "id").and_then(hex_decode_32),
+            Some([0xAB; 32])
+        );
+        assert_eq!(txt_value(&local.txt, "key"), None);
+    }
+
+    #[test]
+    fn test_answer_browse_query() {
+        let local = local_service(&NodeId::from_bytes([1; 32]), Some([2; 32]));
+        let response = local.answer(&browse_query()).unwrap();
+
+        assert!(response.is_response());
+        assert_eq!(response.answers, vec![local.ptr(120)]);
+        assert!(response.additionals.contains(&local.srv(120)));
+        assert!(response.additionals.contains(&local.txt(120)));
+        assert_eq!(response.additionals.len(), 3);
+
+        // Other service types and names are ignored
+        let other = Message::query(vec![Question {
+            name: "_http._tcp.local".to_string(),
+            qtype: TYPE_PTR,
+            unicast_response: false,
+        }]);
+        assert!(local.answer(&other).is_none());
+    }
+
+    #[test]
+    fn test_answer_instance_and_host_queries() {
+        let local = local_service(&NodeId::from_bytes([1; 32]), None);
+
+        let srv = Message::query(vec![Question {
+            name: local.instance.to_uppercase(),
+            qtype: TYPE_SRV,
+            unicast_response: true,
+        }]);
+        let response = local.answer(&srv).unwrap();
+        assert_eq!(response.answers, vec![local.srv(120)]);
+
+        let aaaa = Message::query(vec![Question {
+            name: local.host.clone(),
+            qtype: TYPE_AAAA,
+            unicast_response: false,
+        }]);
+        assert!(local.answer(&aaaa).is_none());
+
+        let a = Message::query(vec![Question {
+            name: local.host.clone(),
+            qtype: TYPE_A,
+            unicast_response: false,
+        }]);
+        assert_eq!(
+            local.answer(&a).unwrap().answers,
+            local.address_records(120)
+        );
+
+        let enumerate = Message::query(vec![Question {
+            name: SERVICE_ENUMERATION.to_string(),
+            qtype: TYPE_PTR,
+            unicast_response: false,
+        }]);
+        assert_eq!(
+            local.answer(&enumerate).unwrap().answers[0].data,
+            RecordData::Ptr(SERVICE_TYPE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_known_answer_suppression() {
+        let local = local_service(&NodeId::from_bytes([1; 32]), None);
+        let mut query = browse_query();
+        query.answers.push(local.ptr(100));
+        assert!(local.answer(&query).is_none());
+
+        // A nearly expired known answer does not suppress the response
+        query.answers[0].ttl = 10;
+        assert!(local.answer(&query).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_record_peers_from_announcement() {
+        let own = NodeId::from_bytes([1; 32]);
+        let peer_id = NodeId::from_bytes([7; 32]);
+        let remote = local_service(&peer_id, Some([9; 32]));
+        let from: SocketAddr = "192.168.1.20:5353".parse().unwrap();
+        let peers = RwLock::new(HashMap::new());
+
+        record_peers(&remote.announcement(120), from, SERVICE_TYPE, &own, &peers).await;
+        let peer = peers.read().await.get(&peer_id).cloned().unwrap();
+        assert_eq!(peer.addrs, vec!["192.168.1.20:8420".parse().unwrap()]);
+        assert_eq!(peer.peer_key, Some([9; 32]));
+        assert!(peer.matches(&[7; 32]));
+        assert!(peer.matches(&[9; 32]));
+        assert!(!peer.matches(&[1; 32]));
+
+        // Goodbye removes the peer
+        record_peers(&remote.announcement(0), from, SERVICE_TYPE, &own, &peers).await;
+        assert!(peers.read().await.is_empty());
+
+        // Our own announcement is ignored
+        let ours = local_service(&own, None);
+        record_peers(&ours.announcement(120), from, SERVICE_TYPE, &own, &peers).await;
+        assert!(peers.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_peers_falls_back_to_source_address() {
+        let peer_id = NodeId::from_bytes([7; 32]);
+        let mut remote = local_service(&peer_id, None);
+        remote.addresses.clear();
+        let from: SocketAddr = "10.1.2.3:5353".parse().unwrap();
+        let peers = RwLock::new(HashMap::new());
+
+        record_peers(
+            &remote.announcement(120),
+            from,
+            SERVICE_TYPE,
+            &NodeId::from_bytes([1; 32]),
+            &peers,
+        )
+        .await;
+        assert_eq!(
+            peers.read().await[&peer_id].addrs,
+            vec!["10.1.2.3:8420".parse().unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_peers_clamps_ttl() {
+        let peer_id = NodeId::from_bytes([7; 32]);
+        let remote = local_service(&peer_id, None);
+        let from: SocketAddr = "192.168.1.20:5353".parse().unwrap();
+        let peers = RwLock::new(HashMap::new());
+
+        let before = Instant::now();
+        record_peers(
+            &remote.announcement(u32::MAX),
+            from,
+            SERVICE_TYPE,
+            &NodeId::from_bytes([1; 32]),
+            &peers,
+        )
+        .await;
+        let expires = peers.read().await[&peer_id].expires;
+        let limit = Duration::from_secs(u64::from(MAX_RECORD_TTL));
+        assert!(expires >= before + limit);
+        assert!(expires <= Instant::now() + limit);
+    }
+
+    #[tokio::test]
+    async fn test_record_peers_evicts_soonest_expiring() {
+        let own = NodeId::from_bytes([0; 32]);
+        let from: SocketAddr = "192.168.1.20:5353".parse().unwrap();
+        let peers = RwLock::new(HashMap::new());
+
+        let id = |i: usize| {
+            let mut bytes = [0xFF; 32];
+            bytes[..8].copy_from_slice(&(i as u64).to_be_bytes());
+            NodeId::from_bytes(bytes)
+        };
+        // The first peer announces the shortest TTL
+        for i in 0..MAX_LAN_PEERS {
+            let ttl = if i == 0 { 60 } else { 120 };
+            let remote = local_service(&id(i), None);
+            record_peers(&remote.announcement(ttl), from, SERVICE_TYPE, &own, &peers).await;
+        }
+        assert_eq!(peers.read().await.len(), MAX_LAN_PEERS);
+
+        let newcomer = local_service(&id(MAX_LAN_PEERS), None);
+        record_peers(
+            &newcomer.announcement(120),
+            from,
+            SERVICE_TYPE,
+            &own,
+            &peers,
+        )
+        .await;
+        {
+            let peers = peers.read().await;
+            assert_eq!(peers.len(), MAX_LAN_PEERS);
+            assert!(!peers.contains_key(&id(0)));
+            assert!(peers.contains_key(&id(MAX_LAN_PEERS)));
+        }
+
+        // Refreshing a cached peer does not evict another
+        let cached = local_service(&id(1), None);
+        record_peers(&cached.announcement(120), from, SERVICE_TYPE, &own, &peers).await;
+        assert_eq!(peers.read().await.len(), MAX_LAN_PEERS);
+        assert!(peers.read().await.contains_key(&id(MAX_LAN_PEERS)));
+    }
+
+    #[test]
+    fn test_hex_decode_32() {
+        let bytes = [0x5Au8; 32];
+        assert_eq!(hex_decode_32(&hex_encode(&bytes)), Some(bytes));
+        assert_eq!(hex_decode_32("abcd"), None);
+        assert_eq!(hex_decode_32(&"zz".repeat(32)), None);
+    }
+}
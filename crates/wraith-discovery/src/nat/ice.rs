@@ -4,8 +4,10 @@
 //! gathering for peer-to-peer connection establishment.
 
 use super::dns::{StunDnsResolver, StunServerSpec, default_stun_servers, fallback_stun_ips};
+use super::port_mapping::PortMapping;
 use super::stun::StunClient;
 use std::net::SocketAddr;
+use tokio::sync::watch;
 
 /// ICE candidate type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -166,6 +168,7 @@ impl From<IceCandidate> for Candidate {
 /// ICE candidate gatherer
 pub struct IceGatherer {
     stun_servers: Vec<SocketAddr>,
+    port_mapping: Option<watch::Receiver<Option<PortMapping>>>,
 }
 
 impl IceGatherer {
@@ -177,6 +180,7 @@ impl IceGatherer {
     pub fn new() -> Self {
         Self {
             stun_servers: fallback_stun_ips(),
+            port_mapping: None,
         }
     }
 
@@ -185,9 +189,21 @@ impl IceGatherer {
     pub fn with_stun_servers(servers: Vec<SocketAddr>) -> Self {
         Self {
             stun_servers: servers,
+            port_mapping: None,
         }
     }
 
+    /// Include the gateway port mapping in gathered candidates
+    ///
+    /// While `mapping` holds a mapping for the gathered address's port, its
+    /// external address is offered as a server reflexive candidate. See
+    /// [`PortMapper::spawn`](super::PortMapper::spawn).
+    #[must_use]
+    pub fn with_port_mapping(mut self, mapping: watch::Receiver<Option<PortMapping>>) -> Self {
+        self.port_mapping = Some(mapping);
+        self
+    }
+
     /// Create an ICE gatherer with DNS resolution for STUN servers
     ///
     /// Resolves STUN server hostnames (like stun.l.google.com) using DNS,
@@ -230,6 +246,7 @@ impl IceGatherer {
 
         Ok(Self {
             stun_servers: resolved,
+            port_mapping: None,
         })
     }
 
@@ -250,11 +267,25 @@ impl IceGatherer {
         let host_cand = IceCandidate::host(local_addr);
         candidates.push(host_cand.into());
 
+        // The gateway port mapping, if one is held for this port
+        let port_mapped = self.port_mapping.as_ref().and_then(|mapping| {
+            mapping
+                .borrow()
+                .as_ref()
+                .filter(|m| m.internal_port == local_addr.port())
+                .map(|m| m.external_addr)
+        });
+        if let Some(mapped_addr) = port_mapped {
+            let srflx_cand = IceCandidate::server_reflexive(mapped_addr, local_addr);
+            candidates.push(srflx_cand.into());
+        }
+
         // Gather server reflexive candidates from STUN
         for stun_server in &self.stun_servers {
             if let Ok(client) = StunClient::bind("0.0.0.0:0").await
                 && let Ok(mapped_addr) = client.get_mapped_address(*stun_server).await
                 && mapped_addr != local_addr
+                && Some(mapped_addr) != port_mapped
             {
                 // Only add if different from host candidate
                 let srflx_cand = IceCandidate::server_reflexive(mapped_addr, local_addr);
@@ -585,4 +616,36 @@ mod tests {
         assert_eq!(cand.foundation.len(), 8);
         assert!(cand.foundation.chars().all(|c| c.is_ascii_hexdigit()));
     }
+
+    #[tokio::test]
+    async fn test_gather_includes_port_mapping() {
+        use crate::nat::MappingProtocol;
+        use crate::nat::port_mapping::GatewayState;
+
+        let local: SocketAddr = "192.168.1.100:5000".parse().unwrap();
+        let external: SocketAddr = "203.0.113.7:45000".parse().unwrap();
+        let (tx, rx) = watch::channel(None);
+        let gatherer = IceGatherer::with_stun_servers(Vec::new()).with_port_mapping(rx);
+
+        let candidates = gatherer.gather(local).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+
+        tx.send_replace(Some(PortMapping {
+            protocol: MappingProtocol::NatPmp,
+            internal_port: 5000,
+            external_addr: external,
+            lifetime: std::time::Duration::from_secs(3600),
+            gateway: GatewayState::NatPmp {
+                server: "192.168.1.1:5351".parse().unwrap(),
+            },
+        }));
+        let candidates = gatherer.gather(local).await.unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[1].candidate_type, CandidateType::ServerReflexive);
+        assert_eq!(candidates[1].address, external);
+
+        // A mapping for another port does not apply
+        let other: SocketAddr = "192.168.1.100:6000".parse().unwrap();
+        assert_eq!(gatherer.gather(other).await.unwrap().len(), 1);
+    }
 }
@@ -9,6 +9,7 @@
 //! - **STUN Client**: Implements STUN protocol (RFC 5389) for server reflexive address discovery
 //! - **ICE Candidate Gathering**: Collects host, server reflexive, and relay candidates
 //! - **UDP Hole Punching**: Establishes direct connections through NAT using simultaneous open
+//! - **Port Mapping**: Requests a forwarded port from the gateway via PCP, NAT-PMP or UPnP IGD
 //!
 //! # NAT Types
 //!
@@ -45,14 +46,22 @@
 pub mod dns;
 pub mod hole_punch;
 pub mod ice;
+mod natpmp;
+mod pcp;
+pub mod port_mapping;
 pub mod signaling;
 pub mod stun;
 pub mod types;
+mod upnp;
 
 // Re-exports
 pub use dns::{DnsError, StunDnsResolver, StunServerSpec, default_stun_servers, fallback_stun_ips};
 pub use hole_punch::{HolePuncher, PunchError};
 pub use ice::{Candidate, CandidateType, IceCandidate, IceGatherer};
+pub use port_mapping::{
+    MappingProtocol, PortMapper, PortMapping, PortMappingConfig, PortMappingError,
+    PortMappingHandle,
+};
 pub use signaling::{
     CandidatePair, ConnectivityChecker, NatSignaling, PairState, SerializableCandidate,
     SignalingError, SignalingMessage,
@@ -0,0 +1,190 @@
+//! NAT Port Mapping Protocol client (RFC 6886)
+//!
+//! NAT-PMP reports the external address and the mapped port in separate
+//! requests, so a mapping costs two round trips.
+
+use super::port_mapping::{
+    GatewayState, MappingProtocol, PortMapping, PortMappingError, exchange, gateway_socket,
+};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+/// NAT-PMP protocol version
+const VERSION: u8 = 0;
+
+/// External address request opcode
+const OP_EXTERNAL_ADDRESS: u8 = 0;
+
+/// UDP mapping request opcode
+const OP_MAP_UDP: u8 = 1;
+
+/// Added to the opcode in responses
+const RESPONSE_OFFSET: u8 = 128;
+
+/// Result code for success
+const RESULT_SUCCESS: u16 = 0;
+
+/// A decoded mapping response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MapResponse {
+    external_port: u16,
+    lifetime: u32,
+}
+
+/// Request (or renew) a UDP mapping for `internal_port`
+pub(crate) async fn map(
+    server: SocketAddr,
+    internal_port: u16,
+    suggested_port: u16,
+    lifetime: Duration,
+    timeout: Duration,
+) -> Result<PortMapping, PortMappingError> {
+    let socket = gateway_socket(server).await?;
+
+    let external_ip = exchange(&socket, &[VERSION, OP_EXTERNAL_ADDRESS], timeout, |data| {
+        decode_external_address(data)
+    })
+    .await?;
+
+    let lifetime_secs = u32::try_from(lifetime.as_secs()).unwrap_or(u32::MAX);
+    let request = encode_map_request(internal_port, suggested_port, lifetime_secs);
+    let response = exchange(&socket, &request, timeout, |data| {
+        decode_map_response(data, internal_port)
+    })
+    .await?;
+
+    Ok(PortMapping {
+        protocol: MappingProtocol::NatPmp,
+        internal_port,
+        external_addr: SocketAddr::new(IpAddr::V4(external_ip), response.external_port),
+        lifetime: Duration::from_secs(u64::from(response.lifetime)),
+        gateway: GatewayState::NatPmp { server },
+    })
+}
+
+/// Delete the mapping for `internal_port` (zero lifetime and external port)
+pub(crate) async fn delete(
+    server: SocketAddr,
+    internal_port: u16,
+    timeout: Duration,
+) -> Result<(), PortMappingError> {
+    let socket = gateway_socket(server).await?;
+    let request = encode_map_request(internal_port, 0, 0);
+    exchange(&socket, &request, timeout, |data| {
+        decode_map_response(data, internal_port)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Encode a UDP mapping request
+fn encode_map_request(internal_port: u16, suggested_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut packet = [0u8; 12];
+    packet[0] = VERSION;
+    packet[1] = OP_MAP_UDP;
+    packet[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    packet[6..8].copy_from_slice(&suggested_port.to_be_bytes());
+    packet[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    packet
+}
+
+/// Check the common response header, returning `None` if `data` is not a
+/// response to `opcode`
+fn check_header(data: &[u8], opcode: u8, len: usize) -> Option<Result<(), PortMappingError>> {
+    if data.len() < 4 || data[0] != VERSION || data[1] != opcode + RESPONSE_OFFSET {
+        return None;
+    }
+    let result = u16::from_be_bytes([data[2], data[3]]);
+    if result != RESULT_SUCCESS {
+        return Some(Err(PortMappingError::Rejected {
+            protocol: MappingProtocol::NatPmp,
+            code: result,
+        }));
+    }
+    if data.len() < len {
+        return Some(Err(PortMappingError::InvalidResponse {
+            protocol: MappingProtocol::NatPmp,
+            reason: format!("{} byte response, expected {}", data.len(), len),
+        }));
+    }
+    Some(Ok(()))
+}
+
+/// Decode an external address response
+fn decode_external_address(data: &[u8]) -> Option<Result<Ipv4Addr, PortMappingError>> {
+    Some(
+        check_header(data, OP_EXTERNAL_ADDRESS, 12)?
+            .map(|()| Ipv4Addr::new(data[8], data[9], data[10], data[11])),
+    )
+}
+
+/// Decode a UDP mapping response
+fn decode_map_response(
+    data: &[u8],
+    internal_port: u16,
+) -> Option<Result<MapResponse, PortMappingError>> {
+    if let Err(e) = check_header(data, OP_MAP_UDP, 16)? {
+        return Some(Err(e));
+    }
+    if u16::from_be_bytes([data[8], data[9]]) != internal_port {
+        return None;
+    }
+    Some(Ok(MapResponse {
+        external_port: u16::from_be_bytes([data[10], data[11]]),
+        lifetime: u32::from_be_bytes([data[12], data[13], data[14], data[15]]),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_map_request() {
+        assert_eq!(
+            encode_map_request(8420, 8421, 7200),
+            [0, 1, 0, 0, 0x20, 0xE4, 0x20, 0xE5, 0, 0, 0x1C, 0x20]
+        );
+    }
+
+    #[test]
+    fn test_decode_external_address() {
+        let response = [0, 128, 0, 0, 0, 0, 0, 9, 198, 51, 100, 7];
+        assert_eq!(
+            decode_external_address(&response).unwrap().unwrap(),
+            Ipv4Addr::new(198, 51, 100, 7)
+        );
+
+        // Result code 3: network failure
+        let failure = [0, 128, 0, 3, 0, 0, 0, 9, 0, 0, 0, 0];
+        assert!(matches!(
+            decode_external_address(&failure),
+            Some(Err(PortMappingError::Rejected { code: 3, .. }))
+        ));
+
+        // Not an external address response
+        assert!(decode_external_address(&[0, 129, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_decode_map_response() {
+        let mut response = [0u8; 16];
+        response[1] = 129;
+        response[8..10].copy_from_slice(&8420u16.to_be_bytes());
+        response[10..12].copy_from_slice(&51000u16.to_be_bytes());
+        response[12..16].copy_from_slice(&3600u32.to_be_bytes());
+
+        assert_eq!(
+            decode_map_response(&response, 8420).unwrap().unwrap(),
+            MapResponse {
+                external_port: 51000,
+                lifetime: 3600
+            }
+        );
+        assert!(decode_map_response(&response, 9000).is_none());
+        assert!(matches!(
+            decode_map_response(&response[..12], 8420),
+            Some(Err(PortMappingError::InvalidResponse { .. }))
+        ));
+    }
+}
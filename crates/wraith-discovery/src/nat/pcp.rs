@@ -0,0 +1,254 @@
+//! Port Control Protocol client (RFC 6887)
+//!
+//! Only the MAP opcode is implemented, for UDP. A mapping is owned by the
+//! random nonce it was created with; renewals and deletions must reuse it.
+
+use super::port_mapping::{
+    GatewayState, MappingProtocol, PortMapping, PortMappingError, exchange, gateway_socket,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// PCP protocol version
+const VERSION: u8 = 2;
+
+/// MAP opcode
+const OPCODE_MAP: u8 = 1;
+
+/// Bit set in the opcode of responses
+const RESPONSE_BIT: u8 = 0x80;
+
+/// IANA protocol number for UDP
+const PROTOCOL_UDP: u8 = 17;
+
+/// Size of a MAP request or response (24-byte header + 36-byte MAP payload)
+const MAP_PACKET_SIZE: usize = 60;
+
+/// Size of the mapping nonce
+pub(crate) const NONCE_SIZE: usize = 12;
+
+/// Result code for success
+const RESULT_SUCCESS: u8 = 0;
+
+/// A decoded MAP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MapResponse {
+    lifetime: u32,
+    internal_port: u16,
+    external_addr: SocketAddr,
+}
+
+/// Request (or renew) a UDP mapping for `internal_port`
+///
+/// Pass the nonce and external address of an existing mapping to renew it.
+pub(crate) async fn map(
+    server: SocketAddr,
+    nonce: Option<[u8; NONCE_SIZE]>,
+    internal_port: u16,
+    suggested: Option<SocketAddr>,
+    lifetime: Duration,
+    timeout: Duration,
+) -> Result<PortMapping, PortMappingError> {
+    let nonce = nonce.unwrap_or_else(rand::random);
+    let socket = gateway_socket(server).await?;
+    let client_ip = socket.local_addr()?.ip();
+    let lifetime_secs = u32::try_from(lifetime.as_secs()).unwrap_or(u32::MAX);
+
+    let request = encode_map_request(client_ip, &nonce, internal_port, suggested, lifetime_secs);
+    let response = exchange(&socket, &request, timeout, |data| {
+        decode_map_response(data, &nonce, internal_port)
+    })
+    .await?;
+
+    Ok(PortMapping {
+        protocol: MappingProtocol::Pcp,
+        internal_port: response.internal_port,
+        external_addr: response.external_addr,
+        lifetime: Duration::from_secs(u64::from(response.lifetime)),
+        gateway: GatewayState::Pcp { server, nonce },
+    })
+}
+
+/// Delete the mapping owned by `nonce` (a MAP request with zero lifetime)
+pub(crate) async fn delete(
+    server: SocketAddr,
+    nonce: [u8; NONCE_SIZE],
+    internal_port: u16,
+    timeout: Duration,
+) -> Result<(), PortMappingError> {
+    let socket = gateway_socket(server).await?;
+    let client_ip = socket.local_addr()?.ip();
+    let request = encode_map_request(client_ip, &nonce, internal_port, None, 0);
+    exchange(&socket, &request, timeout, |data| {
+        decode_map_response(data, &nonce, internal_port)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Encode a MAP request
+fn encode_map_request(
+    client_ip: IpAddr,
+    nonce: &[u8; NONCE_SIZE],
+    internal_port: u16,
+    suggested: Option<SocketAddr>,
+    lifetime: u32,
+) -> [u8; MAP_PACKET_SIZE] {
+    let mut packet = [0u8; MAP_PACKET_SIZE];
+    packet[0] = VERSION;
+    packet[1] = OPCODE_MAP;
+    packet[4..8].copy_from_slice(&lifetime.to_be_bytes());
+    packet[8..24].copy_from_slice(&to_ipv6(client_ip).octets());
+
+    packet[24..36].copy_from_slice(nonce);
+    packet[36] = PROTOCOL_UDP;
+    packet[40..42].copy_from_slice(&internal_port.to_be_bytes());
+
+    // With no suggestion, ask for any address of the client's family
+    let unspecified = match client_ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let (port, ip) = suggested.map_or((0, unspecified), |addr| (addr.port(), addr.ip()));
+    packet[42..44].copy_from_slice(&port.to_be_bytes());
+    packet[44..60].copy_from_slice(&to_ipv6(ip).octets());
+    packet
+}
+
+/// Decode a MAP response
+///
+/// Returns `None` for datagrams that do not answer our request (wrong opcode,
+/// nonce or port) so the caller keeps waiting.
+fn decode_map_response(
+    data: &[u8],
+    nonce: &[u8; NONCE_SIZE],
+    internal_port: u16,
+) -> Option<Result<MapResponse, PortMappingError>> {
+    if data.len() < 4 {
+        return None;
+    }
+    if data[0] != VERSION {
+        // NAT-PMP-only servers answer with version 0 and UNSUPP_VERSION
+        return Some(Err(PortMappingError::Rejected {
+            protocol: MappingProtocol::Pcp,
+            code: 1,
+        }));
+    }
+    if data[1] != OPCODE_MAP | RESPONSE_BIT {
+        return None;
+    }
+    if data[3] != RESULT_SUCCESS {
+        // Error responses may be truncated, so don't require the nonce
+        return Some(Err(PortMappingError::Rejected {
+            protocol: MappingProtocol::Pcp,
+            code: u16::from(data[3]),
+        }));
+    }
+    if data.len() < MAP_PACKET_SIZE
+        || data[24..36] != nonce[..]
+        || data[36] != PROTOCOL_UDP
+        || u16::from_be_bytes([data[40], data[41]]) != internal_port
+    {
+        return None;
+    }
+
+    let lifetime = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let port = u16::from_be_bytes([data[42], data[43]]);
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(&data[44..60]);
+    let ip = Ipv6Addr::from(octets);
+    let ip = ip.to_ipv4_mapped().map_or(IpAddr::V6(ip), IpAddr::V4);
+
+    Some(Ok(MapResponse {
+        lifetime,
+        internal_port,
+        external_addr: SocketAddr::new(ip, port),
+    }))
+}
+
+/// PCP carries IPv4 addresses as IPv4-mapped IPv6 addresses
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: [u8; NONCE_SIZE] = [7; NONCE_SIZE];
+
+    /// Build the response a server would send to `request`
+    fn response_to(request: &[u8], result: u8, lifetime: u32, external: SocketAddr) -> Vec<u8> {
+        let mut response = request.to_vec();
+        response[1] |= RESPONSE_BIT;
+        response[3] = result;
+        response[4..8].copy_from_slice(&lifetime.to_be_bytes());
+        response[8..24].fill(0);
+        response[42..44].copy_from_slice(&external.port().to_be_bytes());
+        response[44..60].copy_from_slice(&to_ipv6(external.ip()).octets());
+        response
+    }
+
+    #[test]
+    fn test_encode_map_request() {
+        let request = encode_map_request("192.168.1.20".parse().unwrap(), &NONCE, 8420, None, 7200);
+        assert_eq!(request[0], VERSION);
+        assert_eq!(request[1], OPCODE_MAP);
+        assert_eq!(&request[4..8], &7200u32.to_be_bytes());
+        assert_eq!(
+            &request[8..24],
+            &Ipv4Addr::new(192, 168, 1, 20).to_ipv6_mapped().octets()
+        );
+        assert_eq!(&request[24..36], &NONCE);
+        assert_eq!(request[36], PROTOCOL_UDP);
+        assert_eq!(&request[40..42], &8420u16.to_be_bytes());
+        assert_eq!(&request[42..44], &[0, 0]);
+        assert_eq!(
+            &request[44..60],
+            &Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets()
+        );
+    }
+
+    #[test]
+    fn test_decode_map_response() {
+        let request = encode_map_request("10.0.0.2".parse().unwrap(), &NONCE, 8420, None, 7200);
+        let external: SocketAddr = "203.0.113.9:40000".parse().unwrap();
+        let response = response_to(&request, RESULT_SUCCESS, 3600, external);
+
+        let decoded = decode_map_response(&response, &NONCE, 8420)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.external_addr, external);
+        assert_eq!(decoded.lifetime, 3600);
+        assert_eq!(decoded.internal_port, 8420);
+
+        // Answers to other requests are ignored
+        assert!(decode_map_response(&response, &[0; NONCE_SIZE], 8420).is_none());
+        assert!(decode_map_response(&response, &NONCE, 9000).is_none());
+        assert!(decode_map_response(&request, &NONCE, 8420).is_none());
+    }
+
+    #[test]
+    fn test_decode_error_and_version_mismatch() {
+        let request = encode_map_request("10.0.0.2".parse().unwrap(), &NONCE, 8420, None, 7200);
+        let external: SocketAddr = "0.0.0.0:0".parse().unwrap();
+        let refused = response_to(&request, 8, 0, external);
+        assert!(matches!(
+            decode_map_response(&refused, &NONCE, 8420),
+            Some(Err(PortMappingError::Rejected { code: 8, .. }))
+        ));
+
+        // NAT-PMP server: version 0, result UNSUPP_VERSION
+        let natpmp = [0u8, 0x81, 0, 1, 0, 0, 0, 0];
+        assert!(matches!(
+            decode_map_response(&natpmp, &NONCE, 8420),
+            Some(Err(PortMappingError::Rejected {
+                protocol: MappingProtocol::Pcp,
+                code: 1
+            }))
+        ));
+    }
+}
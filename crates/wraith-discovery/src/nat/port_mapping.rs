@@ -0,0 +1,574 @@
+//! Automatic Port Mapping
+//!
+//! Asks the local gateway to forward a public port to the node's listen port,
+//! so peers can reach it directly without hole punching. Three protocols are
+//! supported and tried in order:
+//!
+//! - **PCP** (RFC 6887): the current IETF protocol, UDP port 5351
+//! - **NAT-PMP** (RFC 6886): PCP's predecessor, still common on home routers
+//! - **UPnP IGD**: gateway found via SSDP, mappings added over SOAP
+//!
+//! Mappings expire, so [`PortMapper::spawn`] keeps one alive in the
+//! background, renewing it at half its lifetime and publishing the current
+//! external address. The [`IceGatherer`](super::IceGatherer) turns that
+//! address into a server reflexive candidate.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use wraith_discovery::nat::{PortMapper, PortMappingConfig};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let mapper = PortMapper::new(PortMappingConfig::default());
+//! let mapping = mapper.map(8420).await?;
+//! println!("Reachable at {} via {}", mapping.external_addr, mapping.protocol);
+//! mapper.unmap(&mapping).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{natpmp, pcp, upnp};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// UDP port PCP and NAT-PMP servers listen on
+pub const PCP_SERVER_PORT: u16 = 5351;
+
+/// Initial retransmission interval for PCP and NAT-PMP requests (RFC 6886 §3.1)
+const INITIAL_RETRANSMIT: Duration = Duration::from_millis(250);
+
+/// Longest wait between attempts to obtain a mapping after failures
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Shortest interval between renewals
+const MIN_RENEW_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often permanent UPnP mappings are refreshed, in case the gateway
+/// rebooted or its external address changed
+const PERMANENT_REFRESH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Port mapping errors
+#[derive(Debug, Error)]
+pub enum PortMappingError {
+    /// I/O error talking to the gateway
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The gateway did not answer in time
+    #[error("Gateway did not respond")]
+    Timeout,
+
+    /// No gateway to ask (no default route and none configured)
+    #[error("No gateway found")]
+    NoGateway,
+
+    /// The gateway refused the request
+    #[error("{protocol} request rejected with result code {code}")]
+    Rejected {
+        /// Protocol that was refused
+        protocol: MappingProtocol,
+        /// Protocol-specific result or error code
+        code: u16,
+    },
+
+    /// The gateway sent a malformed or unexpected response
+    #[error("Invalid {protocol} response: {reason}")]
+    InvalidResponse {
+        /// Protocol the response belongs to
+        protocol: MappingProtocol,
+        /// What was wrong with it
+        reason: String,
+    },
+}
+
+/// Port mapping protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappingProtocol {
+    /// Port Control Protocol (RFC 6887)
+    Pcp,
+    /// NAT Port Mapping Protocol (RFC 6886)
+    NatPmp,
+    /// UPnP Internet Gateway Device
+    Upnp,
+}
+
+impl std::fmt::Display for MappingProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pcp => write!(f, "PCP"),
+            Self::NatPmp => write!(f, "NAT-PMP"),
+            Self::Upnp => write!(f, "UPnP IGD"),
+        }
+    }
+}
+
+/// Port mapping configuration
+#[derive(Debug, Clone)]
+pub struct PortMappingConfig {
+    /// Port to map; `None` maps the discovery listen port
+    pub internal_port: Option<u16>,
+    /// PCP/NAT-PMP server; `None` uses the default route's gateway on port 5351
+    pub gateway: Option<SocketAddr>,
+    /// Where to send SSDP searches for a UPnP gateway
+    pub ssdp_addr: SocketAddr,
+    /// Protocols to try, in order
+    pub protocols: Vec<MappingProtocol>,
+    /// Lifetime to request for each mapping
+    pub lifetime: Duration,
+    /// How long to wait for each protocol to answer
+    pub timeout: Duration,
+    /// Wait before retrying after every protocol failed (doubles up to 30 minutes)
+    pub retry_interval: Duration,
+}
+
+impl Default for PortMappingConfig {
+    fn default() -> Self {
+        Self {
+            internal_port: None,
+            gateway: None,
+            ssdp_addr: upnp::SSDP_MULTICAST_ADDR,
+            protocols: vec![
+                MappingProtocol::Pcp,
+                MappingProtocol::NatPmp,
+                MappingProtocol::Upnp,
+            ],
+            lifetime: Duration::from_secs(2 * 60 * 60),
+            timeout: Duration::from_secs(3),
+            retry_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Gateway state needed to renew or delete a mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GatewayState {
+    /// PCP server and the nonce that owns the mapping
+    Pcp {
+        server: SocketAddr,
+        nonce: [u8; pcp::NONCE_SIZE],
+    },
+    /// NAT-PMP server
+    NatPmp { server: SocketAddr },
+    /// UPnP gateway control endpoint
+    Upnp(upnp::Gateway),
+}
+
+/// A port mapping granted by the gateway
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    /// Protocol that created the mapping
+    pub protocol: MappingProtocol,
+    /// Local port traffic is forwarded to
+    pub internal_port: u16,
+    /// Public address peers can reach the node at
+    pub external_addr: SocketAddr,
+    /// Lifetime granted by the gateway (zero for a permanent UPnP mapping)
+    pub lifetime: Duration,
+    pub(crate) gateway: GatewayState,
+}
+
+impl PortMapping {
+    /// How long to wait before renewing: half the granted lifetime
+    #[must_use]
+    pub fn renew_after(&self) -> Duration {
+        if self.lifetime.is_zero() {
+            return PERMANENT_REFRESH_INTERVAL;
+        }
+        (self.lifetime / 2).max(MIN_RENEW_INTERVAL)
+    }
+}
+
+/// Port mapping client
+#[derive(Debug, Clone)]
+pub struct PortMapper {
+    config: PortMappingConfig,
+}
+
+impl PortMapper {
+    /// Create a port mapper
+    #[must_use]
+    pub fn new(config: PortMappingConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the configuration
+    #[must_use]
+    pub fn config(&self) -> &PortMappingConfig {
+        &self.config
+    }
+
+    /// Map `internal_port`, trying each configured protocol in turn
+    ///
+    /// # Errors
+    ///
+    /// Returns the last protocol's error if no protocol obtained a mapping.
+    pub async fn map(&self, internal_port: u16) -> Result<PortMapping, PortMappingError> {
+        let mut last_error = PortMappingError::NoGateway;
+        for &protocol in &self.config.protocols {
+            match self.request(protocol, internal_port, None).await {
+                Ok(mapping) => {
+                    tracing::info!(
+                        "Mapped port {} to {} via {}",
+                        internal_port,
+                        mapping.external_addr,
+                        protocol
+                    );
+                    return Ok(mapping);
+                }
+                Err(e) => {
+                    tracing::debug!("{} port mapping failed: {}", protocol, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Renew `mapping` with the gateway that granted it
+    ///
+    /// The gateway is asked to keep the same external port, but may assign a
+    /// different one; check the returned mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the gateway does not renew the mapping.
+    pub async fn renew(&self, mapping: &PortMapping) -> Result<PortMapping, PortMappingError> {
+        self.request(mapping.protocol, mapping.internal_port, Some(mapping))
+            .await
+    }
+
+    /// Delete `mapping` from the gateway
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the gateway does not confirm the deletion.
+    pub async fn unmap(&self, mapping: &PortMapping) -> Result<(), PortMappingError> {
+        let timeout = self.config.timeout;
+        match &mapping.gateway {
+            GatewayState::Pcp { server, nonce } => {
+                pcp::delete(*server, *nonce, mapping.internal_port, timeout).await
+            }
+            GatewayState::NatPmp { server } => {
+                natpmp::delete(*server, mapping.internal_port, timeout).await
+            }
+            GatewayState::Upnp(gateway) => {
+                upnp::delete_port_mapping(gateway, mapping.external_addr.port(), timeout).await
+            }
+        }
+    }
+
+    /// Obtain and keep renewing a mapping for `internal_port` in the background
+    #[must_use]
+    pub fn spawn(self, internal_port: u16) -> PortMappingHandle {
+        let (updates, current) = watch::channel(None);
+        let mapper = self.clone();
+        let task = tokio::spawn(async move { mapper.maintain(internal_port, &updates).await });
+        PortMappingHandle {
+            mapper: self,
+            current,
+            task,
+        }
+    }
+
+    /// Keep a mapping for `internal_port` alive, publishing it to `updates`
+    ///
+    /// Runs until the task is cancelled. Publishes `None` while no mapping is
+    /// held, and retries with exponential backoff when every protocol fails.
+    pub async fn maintain(&self, internal_port: u16, updates: &watch::Sender<Option<PortMapping>>) {
+        let mut current: Option<PortMapping> = None;
+        let mut retry = self.config.retry_interval;
+
+        loop {
+            let result = match &current {
+                Some(mapping) => match self.renew(mapping).await {
+                    Ok(renewed) => Ok(renewed),
+                    Err(e) => {
+                        tracing::debug!("Port mapping renewal failed ({}), remapping", e);
+                        self.map(internal_port).await
+                    }
+                },
+                None => self.map(internal_port).await,
+            };
+
+            match result {
+                Ok(mapping) => {
+                    let wait = mapping.renew_after();
+                    updates.send_if_modified(|published| {
+                        let changed = published.as_ref().map(|m| m.external_addr)
+                            != Some(mapping.external_addr);
+                        *published = Some(mapping.clone());
+                        changed
+                    });
+                    current = Some(mapping);
+                    retry = self.config.retry_interval;
+                    tokio::time::sleep(wait).await;
+                }
+                Err(e) => {
+                    tracing::warn!("Port mapping for {} unavailable: {}", internal_port, e);
+                    if current.take().is_some() {
+                        updates.send_replace(None);
+                    }
+                    tokio::time::sleep(retry).await;
+                    retry = (retry * 2).min(MAX_RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Request or renew a mapping with a single protocol
+    async fn request(
+        &self,
+        protocol: MappingProtocol,
+        internal_port: u16,
+        previous: Option<&PortMapping>,
+    ) -> Result<PortMapping, PortMappingError> {
+        let lifetime = self.config.lifetime;
+        let timeout = self.config.timeout;
+        match protocol {
+            MappingProtocol::Pcp => {
+                let (server, nonce) = match previous.map(|m| &m.gateway) {
+                    Some(GatewayState::Pcp { server, nonce }) => (*server, Some(*nonce)),
+                    _ => (self.gateway()?, None),
+                };
+                let suggested = previous.map(|m| m.external_addr);
+                pcp::map(server, nonce, internal_port, suggested, lifetime, timeout).await
+            }
+            MappingProtocol::NatPmp => {
+                let server = match previous.map(|m| &m.gateway) {
+                    Some(GatewayState::NatPmp { server }) => *server,
+                    _ => self.gateway()?,
+                };
+                let suggested = previous.map_or(internal_port, |m| m.external_addr.port());
+                natpmp::map(server, internal_port, suggested, lifetime, timeout).await
+            }
+            MappingProtocol::Upnp => {
+                let gateway = match previous.map(|m| &m.gateway) {
+                    Some(GatewayState::Upnp(gateway)) => gateway.clone(),
+                    _ => upnp::discover(self.config.ssdp_addr, timeout).await?,
+                };
+                let suggested = previous.map_or(internal_port, |m| m.external_addr.port());
+                upnp::map(gateway, internal_port, suggested, lifetime, timeout).await
+            }
+        }
+    }
+
+    /// PCP/NAT-PMP server address
+    fn gateway(&self) -> Result<SocketAddr, PortMappingError> {
+        self.config
+            .gateway
+            .or_else(|| {
+                default_gateway().map(|ip| SocketAddr::new(IpAddr::V4(ip), PCP_SERVER_PORT))
+            })
+            .ok_or(PortMappingError::NoGateway)
+    }
+}
+
+/// Handle to a mapping maintained in the background by [`PortMapper::spawn`]
+///
+/// Dropping the handle stops renewal; call [`shutdown`](Self::shutdown) to
+/// also delete the mapping from the gateway.
+#[derive(Debug)]
+pub struct PortMappingHandle {
+    mapper: PortMapper,
+    current: watch::Receiver<Option<PortMapping>>,
+    task: JoinHandle<()>,
+}
+
+impl PortMappingHandle {
+    /// The mapping currently held, if any
+    #[must_use]
+    pub fn current(&self) -> Option<PortMapping> {
+        self.current.borrow().clone()
+    }
+
+    /// The external address currently mapped, if any
+    #[must_use]
+    pub fn external_addr(&self) -> Option<SocketAddr> {
+        self.current.borrow().as_ref().map(|m| m.external_addr)
+    }
+
+    /// Subscribe to mapping changes
+    #[must_use]
+    pub fn subscribe(&self) -> watch::Receiver<Option<PortMapping>> {
+        self.current.clone()
+    }
+
+    /// Stop renewing and delete the current mapping from the gateway
+    pub async fn shutdown(self) {
+        self.task.abort();
+        if let Some(mapping) = self.current()
+            && let Err(e) = self.mapper.unmap(&mapping).await
+        {
+            tracing::debug!("Failed to delete port mapping: {}", e);
+        }
+    }
+}
+
+impl Drop for PortMappingHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Find the IPv4 default gateway
+///
+/// Reads the kernel routing table on Linux. Other platforms return `None`;
+/// configure [`PortMappingConfig::gateway`] there, or rely on UPnP, which
+/// finds the gateway by multicast.
+#[must_use]
+pub fn default_gateway() -> Option<Ipv4Addr> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/proc/net/route")
+            .ok()
+            .and_then(|table| parse_proc_net_route(&table))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Extract the default route's gateway from `/proc/net/route`
+///
+/// Addresses are printed as the hex value of the network-order address read
+/// as a native-endian integer.
+fn parse_proc_net_route(table: &str) -> Option<Ipv4Addr> {
+    table.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (_iface, destination, gateway) = (fields.next()?, fields.next()?, fields.next()?);
+        if destination != "00000000" || gateway == "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+/// Open a UDP socket connected to a PCP/NAT-PMP server
+///
+/// Connecting lets the kernel pick the source address the server will see,
+/// which PCP needs to put in its requests.
+pub(crate) async fn gateway_socket(server: SocketAddr) -> Result<UdpSocket, PortMappingError> {
+    let bind_addr: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(server).await?;
+    Ok(socket)
+}
+
+/// Send `request` and wait for a response that `parse` accepts
+///
+/// Retransmits with the RFC 6886 schedule (250 ms, doubling) until `timeout`
+/// expires. `parse` returns `None` for datagrams that do not answer this
+/// request, which are ignored.
+pub(crate) async fn exchange<T>(
+    socket: &UdpSocket,
+    request: &[u8],
+    timeout: Duration,
+    mut parse: impl FnMut(&[u8]) -> Option<Result<T, PortMappingError>>,
+) -> Result<T, PortMappingError> {
+    let deadline = Instant::now() + timeout;
+    let mut wait = INITIAL_RETRANSMIT;
+    let mut buf = [0u8; 1100];
+
+    loop {
+        socket.send(request).await?;
+        let attempt_deadline = (Instant::now() + wait).min(deadline);
+        while let Ok(received) =
+            tokio::time::timeout_at(attempt_deadline, socket.recv(&mut buf)).await
+        {
+            let len = received?;
+            if let Some(result) = parse(&buf[..len]) {
+                return result;
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(PortMappingError::Timeout);
+        }
+        wait *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn test_parse_proc_net_route() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                     eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+                     eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n";
+        assert_eq!(
+            parse_proc_net_route(table),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_proc_net_route("Iface\tDestination\tGateway\n"), None);
+    }
+
+    #[test]
+    fn test_renew_after_is_half_lifetime() {
+        let mapping = PortMapping {
+            protocol: MappingProtocol::NatPmp,
+            internal_port: 8420,
+            external_addr: "203.0.113.5:8420".parse().unwrap(),
+            lifetime: Duration::from_secs(7200),
+            gateway: GatewayState::NatPmp {
+                server: "192.168.1.1:5351".parse().unwrap(),
+            },
+        };
+        assert_eq!(mapping.renew_after(), Duration::from_secs(3600));
+
+        let permanent = PortMapping {
+            lifetime: Duration::ZERO,
+            ..mapping
+        };
+        assert_eq!(permanent.renew_after(), PERMANENT_REFRESH_INTERVAL);
+
+        let short = PortMapping {
+            lifetime: Duration::from_secs(1),
+            ..permanent
+        };
+        assert_eq!(short.renew_after(), MIN_RENEW_INTERVAL);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_retransmits_and_skips_unrelated() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            // Drop the first request so the client has to retransmit
+            let _ = server.recv_from(&mut buf).await.unwrap();
+            let (_, from) = server.recv_from(&mut buf).await.unwrap();
+            server.send_to(b"noise", from).await.unwrap();
+            server.send_to(b"answer", from).await.unwrap();
+        });
+
+        let socket = gateway_socket(server_addr).await.unwrap();
+        let reply = exchange(&socket, b"request", Duration::from_secs(2), |data| {
+            (data == b"answer").then(|| Ok(data.to_vec()))
+        })
+        .await
+        .unwrap();
+        assert_eq!(reply, b"answer");
+    }
+
+    #[tokio::test]
+    async fn test_exchange_times_out() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket = gateway_socket(server.local_addr().unwrap()).await.unwrap();
+        let result: Result<(), _> =
+            exchange(&socket, b"request", Duration::from_millis(300), |_| None).await;
+        assert!(matches!(result, Err(PortMappingError::Timeout)));
+    }
+}
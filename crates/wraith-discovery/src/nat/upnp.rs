@@ -0,0 +1,614 @@
+//! UPnP Internet Gateway Device client
+//!
+//! Finds the gateway with an SSDP M-SEARCH, reads its device description to
+//! locate the WAN connection service, and manages mappings with SOAP calls.
+//! Gateways speak a small, predictable subset of HTTP and XML, so both are
+//! handled here directly rather than with general-purpose parsers.
+
+use super::port_mapping::{GatewayState, MappingProtocol, PortMapping, PortMappingError};
+use rand::Rng;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::Instant;
+
+/// SSDP multicast group and port
+pub(crate) const SSDP_MULTICAST_ADDR: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), 1900);
+
+/// Device types searched for
+const SEARCH_TARGETS: [&str; 2] = [
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:2",
+    "urn:schemas-upnp-org:device:InternetGatewayDevice:1",
+];
+
+/// WAN connection services that can add port mappings, in preference order
+const WAN_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// Description attached to mappings created by this client
+const MAPPING_DESCRIPTION: &str = "WRAITH";
+
+/// Largest HTTP response accepted from a gateway
+const MAX_RESPONSE_SIZE: usize = 256 * 1024;
+
+/// UPnP error: the gateway only supports permanent leases
+const ERROR_ONLY_PERMANENT_LEASES: u16 = 725;
+
+/// UPnP error: the external port is mapped to another client
+const ERROR_CONFLICT_IN_MAPPING: u16 = 718;
+
+/// Random external ports tried after a conflict
+const CONFLICT_RETRIES: usize = 3;
+
+/// A gateway's WAN connection control endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Gateway {
+    /// Address of the gateway's HTTP server
+    addr: SocketAddr,
+    /// Path of the control URL
+    control_path: String,
+    /// Service type of the WAN connection service
+    service_type: String,
+    /// Our address on the gateway's network, used as the mapping target
+    local_ip: IpAddr,
+}
+
+/// Find a UPnP gateway by sending an SSDP search to `ssdp_addr`
+pub(crate) async fn discover(
+    ssdp_addr: SocketAddr,
+    timeout: Duration,
+) -> Result<Gateway, PortMappingError> {
+    let deadline = Instant::now() + timeout;
+    let bind_addr: SocketAddr = if ssdp_addr.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    if ssdp_addr.ip().is_multicast() && ssdp_addr.is_ipv4() {
+        socket.set_multicast_ttl_v4(2)?;
+    }
+    for target in SEARCH_TARGETS {
+        socket
+            .send_to(search_request(ssdp_addr, target).as_bytes(), ssdp_addr)
+            .await?;
+    }
+
+    let mut tried = HashSet::new();
+    let mut last_error = PortMappingError::Timeout;
+    let mut buf = [0u8; 2048];
+    loop {
+        let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await
+        else {
+            return Err(last_error);
+        };
+        let (len, from) = received?;
+        let Some(location) = parse_search_response(&buf[..len]) else {
+            continue;
+        };
+        if !tried.insert(location.clone()) {
+            continue;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match with_timeout(remaining, gateway_from_location(&location)).await {
+            Ok(gateway) => return Ok(gateway),
+            Err(e) => {
+                tracing::debug!("Ignoring UPnP device {} at {}: {}", location, from, e);
+                last_error = e;
+            }
+        }
+    }
+}
+
+/// Add (or refresh) a UDP mapping from `external_port` to `internal_port`
+pub(crate) async fn map(
+    gateway: Gateway,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: Duration,
+    timeout: Duration,
+) -> Result<PortMapping, PortMappingError> {
+    with_timeout(timeout, async {
+        let external_ip = external_ip_address(&gateway).await?;
+        let mut lease = u32::try_from(lifetime.as_secs()).unwrap_or(u32::MAX);
+        let mut external_port = external_port;
+        let mut conflicts = 0;
+
+        loop {
+            match add_port_mapping(&gateway, internal_port, external_port, lease).await {
+                Ok(()) => break,
+                Err(PortMappingError::Rejected { code, .. })
+                    if code == ERROR_ONLY_PERMANENT_LEASES && lease != 0 =>
+                {
+                    lease = 0;
+                }
+                Err(PortMappingError::Rejected { code, .. })
+                    if code == ERROR_CONFLICT_IN_MAPPING && conflicts < CONFLICT_RETRIES =>
+                {
+                    conflicts += 1;
+                    external_port = rand::thread_rng().gen_range(1024..=u16::MAX);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(PortMapping {
+            protocol: MappingProtocol::Upnp,
+            internal_port,
+            external_addr: SocketAddr::new(external_ip, external_port),
+            lifetime: Duration::from_secs(u64::from(lease)),
+            gateway: GatewayState::Upnp(gateway),
+        })
+    })
+    .await
+}
+
+/// Delete the UDP mapping for `external_port`
+pub(crate) async fn delete_port_mapping(
+    gateway: &Gateway,
+    external_port: u16,
+    timeout: Duration,
+) -> Result<(), PortMappingError> {
+    let args = [
+        ("NewRemoteHost", String::new()),
+        ("NewExternalPort", external_port.to_string()),
+        ("NewProtocol", "UDP".to_string()),
+    ];
+    with_timeout(timeout, soap_call(gateway, "DeletePortMapping", &args)).await?;
+    Ok(())
+}
+
+/// Ask the gateway for its external address
+async fn external_ip_address(gateway: &Gateway) -> Result<IpAddr, PortMappingError> {
+    let response = soap_call(gateway, "GetExternalIPAddress", &[]).await?;
+    let ip = tag_text(&response, "NewExternalIPAddress")
+        .and_then(|text| text.parse::<IpAddr>().ok())
+        .filter(|ip| !ip.is_unspecified())
+        .ok_or_else(|| invalid("no usable NewExternalIPAddress in response"))?;
+    Ok(ip)
+}
+
+/// Issue an AddPortMapping call
+async fn add_port_mapping(
+    gateway: &Gateway,
+    internal_port: u16,
+    external_port: u16,
+    lease: u32,
+) -> Result<(), PortMappingError> {
+    let args = [
+        ("NewRemoteHost", String::new()),
+        ("NewExternalPort", external_port.to_string()),
+        ("NewProtocol", "UDP".to_string()),
+        ("NewInternalPort", internal_port.to_string()),
+        ("NewInternalClient", gateway.local_ip.to_string()),
+        ("NewEnabled", "1".to_string()),
+        ("NewPortMappingDescription", MAPPING_DESCRIPTION.to_string()),
+        ("NewLeaseDuration", lease.to_string()),
+    ];
+    soap_call(gateway, "AddPortMapping", &args).await?;
+    Ok(())
+}
+
+/// Fetch a device description and find its WAN connection service
+async fn gateway_from_location(location: &str) -> Result<Gateway, PortMappingError> {
+    let (authority, path) = split_url(location)?;
+    let addr = resolve(authority).await?;
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {authority}\r\nConnection: close\r\n\r\n");
+    let (status, body, local_ip) = http_request(addr, &request).await?;
+    if status != 200 {
+        return Err(invalid(&format!(
+            "device description returned HTTP {status}"
+        )));
+    }
+
+    let (service_type, control_url) =
+        find_wan_service(&body).ok_or_else(|| invalid("no WAN connection service"))?;
+    let (addr, control_path) = if control_url.starts_with("http://") {
+        let (authority, path) = split_url(&control_url)?;
+        (resolve(authority).await?, path.to_string())
+    } else if control_url.starts_with('/') {
+        (addr, control_url)
+    } else {
+        (addr, format!("/{control_url}"))
+    };
+
+    Ok(Gateway {
+        addr,
+        control_path,
+        service_type,
+        local_ip,
+    })
+}
+
+/// Call a SOAP action on the gateway's WAN connection service
+///
+/// Returns the response body, or the UPnP error code as
+/// [`PortMappingError::Rejected`].
+async fn soap_call(
+    gateway: &Gateway,
+    action: &str,
+    args: &[(&str, String)],
+) -> Result<String, PortMappingError> {
+    let mut arguments = String::new();
+    for (name, value) in args {
+        arguments.push_str(&format!("<{name}>{}</{name}>", escape(value)));
+    }
+    let body = format!(
+        "<?xml version=\"1.0\"?>\r\n\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service}\">{arguments}</u:{action}></s:Body>\
+         </s:Envelope>\r\n",
+        service = gateway.service_type,
+    );
+    let request = format!(
+        "POST {path} HTTP/1.1\r\n\
+         Host: {host}\r\n\
+         Content-Type: text/xml; charset=\"utf-8\"\r\n\
+         SOAPAction: \"{service}#{action}\"\r\n\
+         Content-Length: {length}\r\n\
+         Connection: close\r\n\r\n{body}",
+        path = gateway.control_path,
+        host = gateway.addr,
+        service = gateway.service_type,
+        length = body.len(),
+    );
+
+    let (status, response, _) = http_request(gateway.addr, &request).await?;
+    match status {
+        200 => Ok(response),
+        _ => match tag_text(&response, "errorCode").and_then(|code| code.parse().ok()) {
+            Some(code) => Err(PortMappingError::Rejected {
+                protocol: MappingProtocol::Upnp,
+                code,
+            }),
+            None => Err(invalid(&format!("{action} returned HTTP {status}"))),
+        },
+    }
+}
+
+/// Send an HTTP request and read the whole response
+///
+/// Returns the status code, the body and the local address of the connection.
+async fn http_request(
+    addr: SocketAddr,
+    request: &str,
+) -> Result<(u16, String, IpAddr), PortMappingError> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let local_ip = stream.local_addr()?.ip();
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut reader = (&mut stream).take(MAX_RESPONSE_SIZE as u64 + 1);
+    reader.read_to_end(&mut response).await?;
+    if response.len() > MAX_RESPONSE_SIZE {
+        return Err(invalid("HTTP response too large"));
+    }
+
+    let (status, body) = parse_http_response(&response)?;
+    Ok((status, body, local_ip))
+}
+
+/// Split an HTTP response into status code and (de-chunked) body
+fn parse_http_response(response: &[u8]) -> Result<(u16, String), PortMappingError> {
+    let header_end =
+        find(response, b"\r\n\r\n").ok_or_else(|| invalid("truncated HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid("bad HTTP status line"))?;
+    let chunked = lines.any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+    Ok((status, String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// Decode a chunked transfer-encoded body
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, PortMappingError> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = find(data, b"\r\n").ok_or_else(|| invalid("truncated chunk"))?;
+        let size_field = String::from_utf8_lossy(&data[..line_end]);
+        let size_field = size_field.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_field, 16).map_err(|_| invalid("bad chunk size"))?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size {
+            return Err(invalid("truncated chunk"));
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data[size..].strip_prefix(b"\r\n").unwrap_or(&data[size..]);
+    }
+}
+
+/// Build an SSDP M-SEARCH request
+fn search_request(ssdp_addr: SocketAddr, target: &str) -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\n\
+         HOST: {ssdp_addr}\r\n\
+         MAN: \"ssdp:discover\"\r\n\
+         MX: 2\r\n\
+         ST: {target}\r\n\r\n"
+    )
+}
+
+/// Extract the LOCATION header from an SSDP search response
+fn parse_search_response(data: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+    if !lines.next()?.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// Find the preferred WAN connection service in a device description,
+/// returning its service type and control URL
+fn find_wan_service(description: &str) -> Option<(String, String)> {
+    let services: Vec<(&str, &str)> = elements(description, "service")
+        .into_iter()
+        .filter_map(|service| {
+            Some((
+                tag_text(service, "serviceType")?,
+                tag_text(service, "controlURL")?,
+            ))
+        })
+        .collect();
+
+    WAN_SERVICES.iter().find_map(|wanted| {
+        services
+            .iter()
+            .find(|(service_type, _)| service_type == wanted)
+            .map(|(service_type, url)| (service_type.to_string(), unescape(url)))
+    })
+}
+
+/// Split `http://authority/path` into authority and path
+fn split_url(url: &str) -> Result<(&str, &str), PortMappingError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid(&format!("unsupported URL {url}")))?;
+    Ok(match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    })
+}
+
+/// Resolve a URL authority, defaulting to port 80
+async fn resolve(authority: &str) -> Result<SocketAddr, PortMappingError> {
+    let has_port = authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.ends_with(']'));
+    let target = if has_port {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    tokio::net::lookup_host(target)
+        .await?
+        .next()
+        .ok_or_else(|| invalid(&format!("cannot resolve {authority}")))
+}
+
+/// Contents of every `name` element in `xml`, ignoring namespace prefixes
+///
+/// Elements with the same name must not nest.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        if tag.starts_with('/') || tag.ends_with('/') || local_name(tag) != name {
+            continue;
+        }
+        // Find the matching close tag
+        let mut search = rest;
+        while let Some(close) = search.find("</") {
+            let after = &search[close + 2..];
+            let Some(end) = after.find('>') else { break };
+            if local_name(&after[..end]) == name {
+                let content_len = rest.len() - search.len() + close;
+                found.push(&rest[..content_len]);
+                rest = &after[end + 1..];
+                break;
+            }
+            search = &after[end + 1..];
+        }
+    }
+    found
+}
+
+/// Trimmed text of the first `name` element in `xml`
+fn tag_text<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    elements(xml, name).first().map(|text| text.trim())
+}
+
+/// Tag name without attributes or namespace prefix
+fn local_name(tag: &str) -> &str {
+    let name = tag.split_whitespace().next().unwrap_or_default();
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Escape text for inclusion in XML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Undo the predefined XML entities
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Position of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Run `future` with a deadline, mapping expiry to [`PortMappingError::Timeout`]
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl std::future::Future<Output = Result<T, PortMappingError>>,
+) -> Result<T, PortMappingError> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| PortMappingError::Timeout)?
+}
+
+fn invalid(reason: &str) -> PortMappingError {
+    PortMappingError::InvalidResponse {
+        protocol: MappingProtocol::Upnp,
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <serviceList>
+      <service>
+        <serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>
+        <controlURL>/ctl/L3F</controlURL>
+      </service>
+    </serviceList>
+    <deviceList>
+      <device>
+        <serviceList>
+          <service>
+            <serviceType>urn:schemas-upnp-org:service:WANPPPConnection:1</serviceType>
+            <controlURL>/ctl/PPP</controlURL>
+          </service>
+          <service>
+            <serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>
+            <controlURL>/ctl/IPConn?a=1&amp;b=2</controlURL>
+          </service>
+        </serviceList>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn test_find_wan_service_prefers_ip_connection() {
+        assert_eq!(
+            find_wan_service(DESCRIPTION),
+            Some((
+                "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+                "/ctl/IPConn?a=1&b=2".to_string()
+            ))
+        );
+        assert_eq!(find_wan_service("<root></root>"), None);
+    }
+
+    #[test]
+    fn test_tag_text_ignores_namespace_prefix() {
+        let fault = "<s:Envelope><s:Body><s:Fault><detail><UPnPError \
+                     xmlns=\"urn:schemas-upnp-org:control-1-0\"><errorCode> 718 </errorCode>\
+                     </UPnPError></detail></s:Fault></s:Body></s:Envelope>";
+        assert_eq!(tag_text(fault, "errorCode"), Some("718"));
+        assert_eq!(
+            tag_text(fault, "Fault").map(|f| f.starts_with("<detail>")),
+            Some(true)
+        );
+        assert_eq!(tag_text(fault, "missing"), None);
+        assert_eq!(
+            tag_text(
+                "<m:Resp><NewExternalIPAddress/></m:Resp>",
+                "NewExternalIPAddress"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_search_response() {
+        let response = b"HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\n\
+                         Location: http://192.168.1.1:5000/rootDesc.xml\r\n\
+                         ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n";
+        assert_eq!(
+            parse_search_response(response).as_deref(),
+            Some("http://192.168.1.1:5000/rootDesc.xml")
+        );
+        assert_eq!(parse_search_response(b"NOTIFY * HTTP/1.1\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_parse_http_response_chunked() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        let (status, body) = parse_http_response(response).unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, "hello, world");
+
+        let (status, body) = parse_http_response(b"HTTP/1.0 500 Error\r\n\r\nfault").unwrap();
+        assert_eq!(status, 500);
+        assert_eq!(body, "fault");
+
+        assert!(parse_http_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("http://192.168.1.1:5000/rootDesc.xml").unwrap(),
+            ("192.168.1.1:5000", "/rootDesc.xml")
+        );
+        assert_eq!(split_url("http://router").unwrap(), ("router", "/"));
+        assert!(split_url("https://router/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_default_port() {
+        assert_eq!(
+            resolve("127.0.0.1").await.unwrap(),
+            "127.0.0.1:80".parse().unwrap()
+        );
+        assert_eq!(resolve("[::1]").await.unwrap(), "[::1]:80".parse().unwrap());
+        assert_eq!(
+            resolve("127.0.0.1:5000").await.unwrap(),
+            "127.0.0.1:5000".parse().unwrap()
+        );
+    }
+}
@@ -1,12 +1,14 @@
 //! Relay client implementation for connecting to relay servers.
 
-use super::protocol::{NodeId, RelayError, RelayMessage};
+use super::protocol::{NodeId, RelayError, RelayMessage, registration_message};
+use std::fmt;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
 use tokio::sync::{Mutex, mpsc};
 use tokio::time;
+use wraith_crypto::signatures::SigningKey;
 
 /// Type alias for the message receiver
 type MessageReceiver = Arc<Mutex<mpsc::UnboundedReceiver<(NodeId, Vec<u8>)>>>;
@@ -26,6 +28,47 @@ pub enum RelayClientState {
     Error,
 }
 
+/// Ed25519 key a client proves ownership of when registering with a relay
+///
+/// The registered node ID must be the key's public key or the DHT node ID
+/// derived from it.
+#[derive(Clone)]
+pub struct RelayIdentity {
+    signing_key: Arc<SigningKey>,
+}
+
+impl RelayIdentity {
+    /// Create an identity from a signing key
+    #[must_use]
+    pub fn new(signing_key: SigningKey) -> Self {
+        Self {
+            signing_key: Arc::new(signing_key),
+        }
+    }
+
+    /// Ed25519 public key sent in `Register`
+    #[must_use]
+    pub fn public_key(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    /// Sign a registration challenge
+    fn sign_challenge(&self, relay_id: &[u8; 32], nonce: &[u8; 32], node_id: &NodeId) -> Vec<u8> {
+        self.signing_key
+            .sign(&registration_message(relay_id, nonce, node_id))
+            .as_bytes()
+            .to_vec()
+    }
+}
+
+impl fmt::Debug for RelayIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelayIdentity")
+            .field("public_key", &&self.public_key()[..8])
+            .finish_non_exhaustive()
+    }
+}
+
 /// Relay client for communicating with relay servers
 pub struct RelayClient {
     /// Local node ID
@@ -80,57 +123,74 @@ impl RelayClient {
 
     /// Register with the relay server
     ///
+    /// Sends the identity's public key, signs the relay's challenge and
+    /// waits for the acknowledgment.
+    ///
     /// # Arguments
     ///
-    /// * `public_key` - Client's public key for verification
+    /// * `identity` - Key the client's node ID belongs to
     ///
     /// # Errors
     ///
     /// Returns error if registration fails or times out.
-    pub async fn register(&mut self, public_key: &[u8; 32]) -> Result<(), RelayError> {
+    pub async fn register(&mut self, identity: &RelayIdentity) -> Result<(), RelayError> {
         *self.state.lock().await = RelayClientState::Registering;
 
-        let msg = RelayMessage::Register {
+        let result = self.exchange_registration(identity).await;
+        *self.state.lock().await = match result {
+            Ok(()) => {
+                *self.last_keepalive.lock().await = Instant::now();
+                RelayClientState::Connected
+            }
+            Err(_) => RelayClientState::Error,
+        };
+        result
+    }
+
+    /// Run the registration handshake
+    async fn exchange_registration(&self, identity: &RelayIdentity) -> Result<(), RelayError> {
+        let register = RelayMessage::Register {
             node_id: self.node_id,
-            public_key: *public_key,
+            public_key: identity.public_key(),
         };
+        self.socket.send(&register.to_bytes()?).await?;
 
-        let bytes = msg.to_bytes()?;
-        self.socket.send(&bytes).await?;
+        let (relay_id, nonce) = match self.recv_registration_reply().await? {
+            RelayMessage::Challenge { relay_id, nonce } => (relay_id, nonce),
+            other => return Err(Self::registration_failure(other)),
+        };
+
+        let response = RelayMessage::ChallengeResponse {
+            signature: identity.sign_challenge(&relay_id, &nonce, &self.node_id),
+        };
+        self.socket.send(&response.to_bytes()?).await?;
+
+        match self.recv_registration_reply().await? {
+            RelayMessage::RegisterAck { success: true, .. } => Ok(()),
+            other => Err(Self::registration_failure(other)),
+        }
+    }
 
-        // Wait for RegisterAck with timeout
+    /// Wait for the relay's next registration message
+    async fn recv_registration_reply(&self) -> Result<RelayMessage, RelayError> {
         let mut buf = vec![0u8; 65536];
         let len = time::timeout(Duration::from_secs(10), self.socket.recv(&mut buf))
             .await
             .map_err(|_| RelayError::Timeout)??;
 
-        let response = RelayMessage::from_bytes(&buf[..len])?;
+        RelayMessage::from_bytes(&buf[..len])
+    }
 
-        match response {
+    /// Error for a registration reply other than the one expected
+    fn registration_failure(reply: RelayMessage) -> RelayError {
+        match reply {
             RelayMessage::RegisterAck {
-                success,
+                success: false,
                 error,
-                relay_id: _,
-            } => {
-                if success {
-                    *self.state.lock().await = RelayClientState::Connected;
-                    *self.last_keepalive.lock().await = Instant::now();
-                    Ok(())
-                } else {
-                    *self.state.lock().await = RelayClientState::Error;
-                    Err(RelayError::Internal(
-                        error.unwrap_or_else(|| "Registration failed".to_string()),
-                    ))
-                }
-            }
-            RelayMessage::Error { code, message: _ } => {
-                *self.state.lock().await = RelayClientState::Error;
-                Err(code.into())
-            }
-            _ => {
-                *self.state.lock().await = RelayClientState::Error;
-                Err(RelayError::InvalidMessage)
-            }
+                ..
+            } => RelayError::Internal(error.unwrap_or_else(|| "Registration failed".to_string())),
+            RelayMessage::Error { code, message: _ } => code.into(),
+            _ => RelayError::InvalidMessage,
         }
     }
 
@@ -274,6 +334,11 @@ impl RelayClient {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wraith_crypto::signatures::{Signature, VerifyingKey};
+
+    fn identity() -> RelayIdentity {
+        RelayIdentity::new(SigningKey::from_bytes(&[2u8; 32]))
+    }
 
     #[tokio::test]
     async fn test_relay_client_creation() {
@@ -424,7 +489,7 @@ mod tests {
 
         // Register should timeout since server never sends RegisterAck
         let result =
-            tokio::time::timeout(Duration::from_secs(12), client.register(&[2u8; 32])).await;
+            tokio::time::timeout(Duration::from_secs(12), client.register(&identity())).await;
 
         // Either inner timeout (RelayError::Timeout) or outer timeout
         match result {
@@ -459,7 +524,7 @@ mod tests {
 
         let node_id = [1u8; 32];
         let mut client = RelayClient::connect(server_addr, node_id).await.unwrap();
-        let result = client.register(&[2u8; 32]).await;
+        let result = client.register(&identity()).await;
         assert!(result.is_err());
         assert!(matches!(result, Err(RelayError::ServerFull)));
     }
@@ -488,7 +553,7 @@ mod tests {
 
         let node_id = [1u8; 32];
         let mut client = RelayClient::connect(server_addr, node_id).await.unwrap();
-        let result = client.register(&[2u8; 32]).await;
+        let result = client.register(&identity()).await;
         assert!(result.is_err());
         if let Err(RelayError::Internal(msg)) = result {
             assert!(msg.contains("Denied"));
@@ -517,44 +582,105 @@ mod tests {
 
         let node_id = [1u8; 32];
         let mut client = RelayClient::connect(server_addr, node_id).await.unwrap();
-        let result = client.register(&[2u8; 32]).await;
+        let result = client.register(&identity()).await;
         assert!(matches!(result, Err(RelayError::InvalidMessage)));
     }
 
     #[tokio::test]
     async fn test_relay_client_register_success_and_send() {
-        // Set up a fake relay that responds with successful RegisterAck
+        // Set up a fake relay that challenges the client and checks the answer
         let server_socket = Arc::new(tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap());
         let server_addr = server_socket.local_addr().unwrap();
 
         let server = server_socket.clone();
-        tokio::spawn(async move {
+        let verified = tokio::spawn(async move {
             let mut buf = vec![0u8; 65536];
-            if let Ok((len, from)) = server.recv_from(&mut buf).await {
-                if let Ok(RelayMessage::Register { .. }) = RelayMessage::from_bytes(&buf[..len]) {
-                    let ack = RelayMessage::RegisterAck {
-                        relay_id: [42u8; 32],
-                        success: true,
-                        error: None,
-                    };
-                    let bytes = ack.to_bytes().unwrap();
-                    let _ = server.send_to(&bytes, from).await;
-                }
-            }
+            let (len, from) = server.recv_from(&mut buf).await.unwrap();
+            let Ok(RelayMessage::Register {
+                node_id,
+                public_key,
+            }) = RelayMessage::from_bytes(&buf[..len])
+            else {
+                return false;
+            };
+
+            let challenge = RelayMessage::Challenge {
+                relay_id: [42u8; 32],
+                nonce: [7u8; 32],
+            };
+            let bytes = challenge.to_bytes().unwrap();
+            server.send_to(&bytes, from).await.unwrap();
+
+            let (len, from) = server.recv_from(&mut buf).await.unwrap();
+            let Ok(RelayMessage::ChallengeResponse { signature }) =
+                RelayMessage::from_bytes(&buf[..len])
+            else {
+                return false;
+            };
+            let valid = VerifyingKey::from_bytes(&public_key)
+                .unwrap()
+                .verify(
+                    &registration_message(&[42u8; 32], &[7u8; 32], &node_id),
+                    &Signature::from_slice(&signature).unwrap(),
+                )
+                .is_ok();
+
+            let ack = RelayMessage::RegisterAck {
+                relay_id: [42u8; 32],
+                success: valid,
+                error: None,
+            };
+            let bytes = ack.to_bytes().unwrap();
+            server.send_to(&bytes, from).await.unwrap();
+
             // Also consume the SendPacket that follows
-            let mut buf = vec![0u8; 65536];
             let _ = server.recv_from(&mut buf).await;
+            valid
         });
 
         let node_id = [1u8; 32];
         let mut client = RelayClient::connect(server_addr, node_id).await.unwrap();
-        let result = client.register(&[2u8; 32]).await;
+        let result = client.register(&identity()).await;
         assert!(result.is_ok());
         assert_eq!(client.state().await, RelayClientState::Connected);
 
         // Now send_to_peer should work (Connected state)
         let send_result = client.send_to_peer([3u8; 32], b"hello").await;
         assert!(send_result.is_ok());
+        assert!(verified.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_relay_client_register_requires_challenge() {
+        // A relay that acknowledges without challenging is not trusted
+        let server_socket = Arc::new(tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let server_addr = server_socket.local_addr().unwrap();
+
+        let server = server_socket.clone();
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65536];
+            if let Ok((_, from)) = server.recv_from(&mut buf).await {
+                let ack = RelayMessage::RegisterAck {
+                    relay_id: [42u8; 32],
+                    success: true,
+                    error: None,
+                };
+                let bytes = ack.to_bytes().unwrap();
+                let _ = server.send_to(&bytes, from).await;
+            }
+        });
+
+        let mut client = RelayClient::connect(server_addr, [1u8; 32]).await.unwrap();
+        let result = client.register(&identity()).await;
+        assert!(matches!(result, Err(RelayError::InvalidMessage)));
+        assert_eq!(client.state().await, RelayClientState::Error);
+    }
+
+    #[test]
+    fn test_relay_identity_debug_hides_secret() {
+        let debug = format!("{:?}", identity());
+        assert!(debug.contains("RelayIdentity"));
+        assert!(!debug.contains("signing_key"));
     }
 
     #[tokio::test]
@@ -581,7 +707,7 @@ mod tests {
 
         let node_id = [1u8; 32];
         let mut client = RelayClient::connect(server_addr, node_id).await.unwrap();
-        let result = client.register(&[2u8; 32]).await;
+        let result = client.register(&identity()).await;
         assert!(result.is_err());
         if let Err(RelayError::Internal(msg)) = result {
             assert_eq!(msg, "Registration failed");
@@ -5,10 +5,12 @@
 //!
 //! ## Features
 //!
-//! - Client registration with relay servers
+//! - Proof-of-key client registration (challenge-response)
+//! - Per-client rate, bandwidth and byte quotas with fair queuing
 //! - Encrypted packet forwarding between peers
 //! - Geographic and latency-based relay selection
 //! - Automatic failover to backup relays
+//! - Local network relay discovery over mDNS
 //! - End-to-end encryption (relay cannot decrypt)
 //!
 //! ## Architecture
@@ -31,8 +33,8 @@
 //! ## Example
 //!
 //! ```rust,no_run
-//! use wraith_discovery::relay::{RelayClient, RelaySelector, RelayInfo};
-//! use std::net::SocketAddr;
+//! use wraith_discovery::relay::{RelayClient, RelayIdentity, RelaySelector, RelayInfo};
+//! use wraith_crypto::signatures::SigningKey;
 //!
 //! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 //! // Select best relay
@@ -46,12 +48,12 @@
 //!
 //! let relay_info = selector.select_best().unwrap();
 //!
-//! // Connect to relay
-//! let node_id = [1u8; 32]; // Your node ID
-//! let mut client = RelayClient::connect(relay_info.addr, node_id).await?;
+//! // Connect to relay; the node ID is the Ed25519 public key
+//! let identity = RelayIdentity::new(SigningKey::from_bytes(&[1u8; 32]));
+//! let mut client = RelayClient::connect(relay_info.addr, identity.public_key()).await?;
 //!
-//! let public_key = [2u8; 32];
-//! client.register(&public_key).await?;
+//! // Prove ownership of the key by signing the relay's challenge
+//! client.register(&identity).await?;
 //!
 //! // Send packet through relay
 //! let dest_id = [3u8; 32];
@@ -68,10 +70,13 @@ pub mod protocol;
 pub mod selection;
 pub mod server;
 
-pub use client::RelayClient;
+pub use client::{RelayClient, RelayIdentity};
 pub use protocol::{RelayError, RelayErrorCode, RelayMessage};
-pub use selection::{RelayInfo, RelaySelector, SelectionStrategy};
-pub use server::{RelayServer, RelayServerConfig};
+pub use selection::{LAN_REGION, RelayInfo, RelaySelector, SelectionStrategy};
+pub use server::{ClientStats, RelayServer, RelayServerConfig, RelayStats};
+
+/// DNS-SD service type relay servers announce themselves under
+pub const RELAY_SERVICE_TYPE: &str = "_wraith-relay._udp.local";
 
 /// Default relay port (HTTPS)
 pub const DEFAULT_RELAY_PORT: u16 = 443;
@@ -1,11 +1,54 @@
 //! Relay protocol message definitions.
-
+//!
+//! Registration is a challenge-response exchange that proves the client
+//! holds the private key for the node ID it registers:
+//!
+//! ```text
+//! Client                                   Relay
+//!   | Register { node_id, public_key }       |
+//!   |--------------------------------------->|
+//!   |            Challenge { relay_id, nonce }|
+//!   |<---------------------------------------|
+//!   | ChallengeResponse { signature }        |
+//!   |--------------------------------------->|
+//!   |                       RegisterAck      |
+//!   |<---------------------------------------|
+//! ```
+//!
+//! The signature covers the relay ID, nonce and node ID (see
+//! [`registration_message`]), so a response cannot be replayed to another
+//! relay or for another registration.
+
+use crate::dht;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Node identifier (32-byte public key or derived ID)
 pub type NodeId = [u8; 32];
 
+/// Domain separation prefix for registration signatures
+const REGISTRATION_CONTEXT: &[u8] = b"wraith-relay-register-v1";
+
+/// Bytes a client signs to answer a registration challenge
+#[must_use]
+pub fn registration_message(relay_id: &[u8; 32], nonce: &[u8; 32], node_id: &NodeId) -> Vec<u8> {
+    let mut message = Vec::with_capacity(REGISTRATION_CONTEXT.len() + 96);
+    message.extend_from_slice(REGISTRATION_CONTEXT);
+    message.extend_from_slice(relay_id);
+    message.extend_from_slice(nonce);
+    message.extend_from_slice(node_id);
+    message
+}
+
+/// Check that `node_id` belongs to the Ed25519 `public_key`
+///
+/// A node ID is either the public key itself, as used by WRAITH nodes, or
+/// the DHT node ID derived from it.
+#[must_use]
+pub fn node_id_matches_key(node_id: &NodeId, public_key: &[u8; 32]) -> bool {
+    node_id == public_key || dht::NodeId::from_public_key(public_key).as_bytes() == node_id
+}
+
 /// Relay protocol messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum RelayMessage {
@@ -13,10 +56,24 @@ pub enum RelayMessage {
     Register {
         /// Client's node ID
         node_id: NodeId,
-        /// Client's public key for verification
+        /// Client's Ed25519 public key; `node_id` must belong to it
         public_key: [u8; 32],
     },
 
+    /// Relay asks the client to prove it holds the registered key
+    Challenge {
+        /// Relay's unique identifier
+        relay_id: [u8; 32],
+        /// Random nonce to sign
+        nonce: [u8; 32],
+    },
+
+    /// Client answers a registration challenge
+    ChallengeResponse {
+        /// Ed25519 signature over [`registration_message`]
+        signature: Vec<u8>,
+    },
+
     /// Relay acknowledges registration
     RegisterAck {
         /// Relay's unique identifier
@@ -87,6 +144,8 @@ pub enum RelayErrorCode {
     AuthFailed = 6,
     /// Internal server error
     InternalError = 7,
+    /// Client used up its byte quota
+    QuotaExceeded = 8,
 }
 
 impl RelayMessage {
@@ -107,6 +166,8 @@ impl RelayMessage {
     pub fn message_type(&self) -> &'static str {
         match self {
             RelayMessage::Register { .. } => "Register",
+            RelayMessage::Challenge { .. } => "Challenge",
+            RelayMessage::ChallengeResponse { .. } => "ChallengeResponse",
             RelayMessage::RegisterAck { .. } => "RegisterAck",
             RelayMessage::SendPacket { .. } => "SendPacket",
             RelayMessage::RecvPacket { .. } => "RecvPacket",
@@ -142,6 +203,8 @@ pub enum RelayError {
     ServerFull,
     /// Authentication failed
     AuthFailed,
+    /// Byte quota exhausted
+    QuotaExceeded,
     /// Internal error
     Internal(String),
 }
@@ -159,6 +222,7 @@ impl fmt::Display for RelayError {
             RelayError::InvalidMessage => write!(f, "Invalid message"),
             RelayError::ServerFull => write!(f, "Server at capacity"),
             RelayError::AuthFailed => write!(f, "Authentication failed"),
+            RelayError::QuotaExceeded => write!(f, "Quota exceeded"),
             RelayError::Internal(e) => write!(f, "Internal error: {e}"),
         }
     }
@@ -181,6 +245,7 @@ impl From<RelayErrorCode> for RelayError {
             RelayErrorCode::InvalidMessage => RelayError::InvalidMessage,
             RelayErrorCode::ServerFull => RelayError::ServerFull,
             RelayErrorCode::AuthFailed => RelayError::AuthFailed,
+            RelayErrorCode::QuotaExceeded => RelayError::QuotaExceeded,
             RelayErrorCode::InternalError => RelayError::Internal("Unknown error".to_string()),
         }
     }
@@ -296,6 +361,41 @@ mod tests {
         assert_eq!(msg, decoded);
     }
 
+    #[test]
+    fn test_message_serialization_challenge() {
+        let challenge = RelayMessage::Challenge {
+            relay_id: [3u8; 32],
+            nonce: [4u8; 32],
+        };
+        let response = RelayMessage::ChallengeResponse {
+            signature: vec![5u8; 64],
+        };
+
+        for msg in [challenge, response] {
+            let bytes = msg.to_bytes().unwrap();
+            assert_eq!(RelayMessage::from_bytes(&bytes).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_registration_message_binds_fields() {
+        let base = registration_message(&[1; 32], &[2; 32], &[3; 32]);
+        assert_ne!(base, registration_message(&[9; 32], &[2; 32], &[3; 32]));
+        assert_ne!(base, registration_message(&[1; 32], &[9; 32], &[3; 32]));
+        assert_ne!(base, registration_message(&[1; 32], &[2; 32], &[9; 32]));
+    }
+
+    #[test]
+    fn test_node_id_matches_key() {
+        let public_key = [7u8; 32];
+        assert!(node_id_matches_key(&public_key, &public_key));
+        assert!(node_id_matches_key(
+            dht::NodeId::from_public_key(&public_key).as_bytes(),
+            &public_key
+        ));
+        assert!(!node_id_matches_key(&[8u8; 32], &public_key));
+    }
+
     #[test]
     fn test_message_type() {
         let msg = RelayMessage::Register {
@@ -342,6 +442,9 @@ mod tests {
 
         let err: RelayError = RelayErrorCode::InternalError.into();
         assert!(matches!(err, RelayError::Internal(_)));
+
+        let err: RelayError = RelayErrorCode::QuotaExceeded.into();
+        assert!(matches!(err, RelayError::QuotaExceeded));
     }
 
     #[test]
@@ -368,6 +471,7 @@ mod tests {
         assert_eq!(RelayError::InvalidMessage.to_string(), "Invalid message");
         assert_eq!(RelayError::ServerFull.to_string(), "Server at capacity");
         assert_eq!(RelayError::AuthFailed.to_string(), "Authentication failed");
+        assert_eq!(RelayError::QuotaExceeded.to_string(), "Quota exceeded");
         assert_eq!(
             RelayError::Internal("oops".to_string()).to_string(),
             "Internal error: oops"
@@ -527,6 +631,7 @@ mod tests {
             RelayErrorCode::ServerFull,
             RelayErrorCode::AuthFailed,
             RelayErrorCode::InternalError,
+            RelayErrorCode::QuotaExceeded,
         ];
         for code in codes {
             let msg = RelayMessage::Error {
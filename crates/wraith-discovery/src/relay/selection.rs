@@ -1,5 +1,6 @@
 //! Relay server selection algorithm.
 
+use crate::mdns::LanPeer;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
@@ -17,6 +18,9 @@ pub struct RelayInfo {
     pub priority: u32,
 }
 
+/// Region given to relays found on the local network
+pub const LAN_REGION: &str = "lan";
+
 impl RelayInfo {
     /// Create a new relay info
     #[must_use]
@@ -97,6 +101,27 @@ impl RelaySelector {
         self.relays.push(relay);
     }
 
+    /// Add relays announced on the local network
+    ///
+    /// `relays` are the peers seen by an [`MdnsService`](crate::mdns::MdnsService)
+    /// browsing [`RELAY_SERVICE_TYPE`](super::RELAY_SERVICE_TYPE). Each is
+    /// added under [`LAN_REGION`] at its first announced address, unless a
+    /// relay with that address is already known. Returns the number added.
+    pub fn add_lan_relays(&mut self, relays: &[LanPeer]) -> usize {
+        let mut added = 0;
+        for relay in relays {
+            let Some(&addr) = relay.addrs.first() else {
+                continue;
+            };
+            if self.relays.iter().any(|r| r.addr == addr) {
+                continue;
+            }
+            self.add_relay(RelayInfo::new(addr, LAN_REGION.to_string()));
+            added += 1;
+        }
+        added
+    }
+
     /// Remove a relay server by address
     pub fn remove_relay(&mut self, addr: &SocketAddr) {
         self.relays.retain(|r| r.addr != *addr);
@@ -437,6 +462,33 @@ mod tests {
         assert_eq!(eu.len(), 1);
     }
 
+    #[test]
+    fn test_add_lan_relays() {
+        let mut selector = RelaySelector::new();
+        let known = "192.168.1.10:443".parse().unwrap();
+        selector.add_relay(RelayInfo::new(known, "us-west".to_string()));
+
+        let lan_relay = |seed: u8, addrs: Vec<SocketAddr>| LanPeer {
+            node_id: crate::dht::NodeId::from_bytes([seed; 32]),
+            peer_key: None,
+            addrs,
+            expires: Instant::now() + Duration::from_secs(120),
+        };
+        let new_addr = "192.168.1.11:443".parse().unwrap();
+        let relays = [
+            lan_relay(1, vec![known]),
+            lan_relay(2, vec![new_addr]),
+            lan_relay(3, vec![]),
+        ];
+
+        assert_eq!(selector.add_lan_relays(&relays), 1);
+        assert_eq!(selector.relay_count(), 2);
+        assert_eq!(selector.find_by_region(LAN_REGION)[0].addr, new_addr);
+
+        // Seeing the same announcements again adds nothing
+        assert_eq!(selector.add_lan_relays(&relays), 0);
+    }
+
     #[test]
     fn test_is_measurement_stale() {
         let mut selector = RelaySelector::new();
@@ -1,32 +1,113 @@
 //! Relay server for forwarding packets between peers.
-
-use super::protocol::{NodeId, RelayError, RelayErrorCode, RelayMessage};
-use std::collections::HashMap;
+//!
+//! Clients register with a challenge-response proof of key (see
+//! [`protocol`](super::protocol)), so a client can only receive traffic for
+//! node IDs whose private key it holds. Each client has its own packet-rate,
+//! bandwidth and byte quotas, and forwarded packets wait in per-sender
+//! queues served in deficit round-robin order, so one busy client cannot
+//! starve the others.
+//!
+//! A relay can [`announce`](RelayServer::announce) itself over mDNS so
+//! clients on the same network find it without configuration.
+
+use super::RELAY_SERVICE_TYPE;
+use super::protocol::{
+    NodeId, RelayError, RelayErrorCode, RelayMessage, node_id_matches_key, registration_message,
+};
+use crate::dht::NodeId as DhtNodeId;
+use crate::mdns::{MdnsConfig, MdnsError, MdnsService};
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
+use tokio::task::JoinHandle;
+use wraith_crypto::signatures::{Signature, VerifyingKey};
+
+/// Bytes of credit a sender's queue earns per round-robin turn
+const QUANTUM: usize = 1500;
+
+/// Token bucket holding at most one second's worth of tokens
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    /// Tokens added per second
+    rate: f64,
+    /// Tokens currently available
+    tokens: f64,
+    /// Last refill time
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket refilling at `rate` tokens per second
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take `amount` tokens if available
+    fn try_take(&mut self, amount: f64) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+
+        if self.tokens < amount {
+            return false;
+        }
+        self.tokens -= amount;
+        true
+    }
+}
+
+/// Traffic accounting for one registered client
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Packets accepted from the client for forwarding
+    pub packets_sent: u64,
+    /// Payload bytes accepted from the client for forwarding
+    pub bytes_sent: u64,
+    /// Packets delivered to the client
+    pub packets_received: u64,
+    /// Payload bytes delivered to the client
+    pub bytes_received: u64,
+    /// Packets from the client dropped by rate limits, quotas or a full queue
+    pub packets_dropped: u64,
+}
 
 /// Client connection information
 #[derive(Debug, Clone)]
 struct ClientConnection {
     /// Client's socket address
     addr: SocketAddr,
-    /// Client's public key (reserved for future authentication)
-    #[allow(dead_code)]
+    /// Client's Ed25519 public key, proven at registration
     public_key: [u8; 32],
     /// Last seen time
     last_seen: Instant,
+    /// Packet rate limit
+    packet_rate: TokenBucket,
+    /// Bandwidth limit in bytes
+    bandwidth: TokenBucket,
+    /// Traffic counters
+    stats: ClientStats,
 }
 
 impl ClientConnection {
-    /// Create a new client connection
-    fn new(addr: SocketAddr, public_key: [u8; 32]) -> Self {
+    /// Create a new client connection with quotas from `config`
+    #[allow(clippy::cast_precision_loss)]
+    fn new(addr: SocketAddr, public_key: [u8; 32], config: &RelayServerConfig) -> Self {
         Self {
             addr,
             public_key,
             last_seen: Instant::now(),
+            packet_rate: TokenBucket::new(config.rate_limit as f64),
+            bandwidth: TokenBucket::new(config.bandwidth_limit as f64),
+            stats: ClientStats::default(),
         }
     }
 
@@ -41,55 +122,145 @@ impl ClientConnection {
     }
 }
 
-/// Simple rate limiter
-struct RateLimiter {
-    /// Packets per client per second
+/// Registration waiting for the client's challenge response
+#[derive(Debug, Clone)]
+struct PendingRegistration {
+    /// Node ID being registered
+    node_id: NodeId,
+    /// Key the client must prove it holds
+    public_key: [u8; 32],
+    /// Nonce the client must sign
+    nonce: [u8; 32],
+    /// When the challenge was sent
+    issued: Instant,
+}
+
+/// Packet waiting to be forwarded
+#[derive(Debug)]
+struct QueuedPacket {
+    /// Destination node ID
+    dest_id: NodeId,
+    /// Destination address
+    dest_addr: SocketAddr,
+    /// Payload size, for accounting
+    payload_len: usize,
+    /// Encoded `RecvPacket` message
+    bytes: Vec<u8>,
+}
+
+/// Per-sender packet queues served in deficit round-robin order
+#[derive(Debug)]
+struct FairQueue {
+    /// Queued packets per sender
+    queues: HashMap<NodeId, VecDeque<QueuedPacket>>,
+    /// Senders with queued packets, in service order
+    active: VecDeque<NodeId>,
+    /// Unused byte credit per active sender
+    deficits: HashMap<NodeId, usize>,
+    /// Maximum queued packets per sender
     limit: usize,
-    /// Packet counts for each client
-    counts: HashMap<NodeId, (Instant, usize)>,
-    /// Window duration
-    window: Duration,
 }
 
-impl RateLimiter {
-    /// Create a new rate limiter
-    fn new(limit: usize, window: Duration) -> Self {
+impl FairQueue {
+    /// Create an empty queue holding up to `limit` packets per sender
+    fn new(limit: usize) -> Self {
         Self {
+            queues: HashMap::new(),
+            active: VecDeque::new(),
+            deficits: HashMap::new(),
             limit,
-            counts: HashMap::new(),
-            window,
         }
     }
 
-    /// Check if request is allowed
-    fn check(&mut self, node_id: NodeId) -> bool {
-        let now = Instant::now();
-
-        let entry = self.counts.entry(node_id).or_insert((now, 0));
-
-        // Reset counter if window expired
-        if now.duration_since(entry.0) >= self.window {
-            entry.0 = now;
-            entry.1 = 0;
+    /// Queue a packet from `src`; returns `false` if its queue is full
+    fn push(&mut self, src: NodeId, packet: QueuedPacket) -> bool {
+        let queue = self.queues.entry(src).or_default();
+        if queue.len() >= self.limit {
+            return false;
+        }
+        if queue.is_empty() {
+            self.active.push_back(src);
+            self.deficits.insert(src, 0);
         }
+        queue.push_back(packet);
+        true
+    }
 
-        // Check limit
-        if entry.1 >= self.limit {
-            return false;
+    /// Take the next packet to send
+    fn pop(&mut self) -> Option<(NodeId, QueuedPacket)> {
+        loop {
+            let src = *self.active.front()?;
+            let queue = self.queues.get_mut(&src)?;
+            let deficit = self.deficits.entry(src).or_insert(0);
+
+            let size = queue.front().map_or(0, |packet| packet.bytes.len());
+            if size > *deficit {
+                // Out of credit: earn a quantum and yield to the next sender
+                *deficit += QUANTUM;
+                self.active.rotate_left(1);
+                continue;
+            }
+
+            *deficit -= size;
+            let packet = queue.pop_front()?;
+            if queue.is_empty() {
+                self.queues.remove(&src);
+                self.deficits.remove(&src);
+                self.active.pop_front();
+            }
+            return Some((src, packet));
         }
+    }
 
-        entry.1 += 1;
-        true
+    /// Total queued packets
+    fn len(&self) -> usize {
+        self.queues.values().map(VecDeque::len).sum()
     }
+}
 
-    /// Clean up expired entries
-    fn cleanup(&mut self) {
-        let now = Instant::now();
-        self.counts
-            .retain(|_, (time, _)| now.duration_since(*time) < self.window * 2);
+/// Server-wide counters
+#[derive(Debug, Default)]
+struct RelayCounters {
+    registrations: AtomicU64,
+    auth_failures: AtomicU64,
+    packets_relayed: AtomicU64,
+    bytes_relayed: AtomicU64,
+    packets_dropped: AtomicU64,
+    rate_limited: AtomicU64,
+    quota_exceeded: AtomicU64,
+}
+
+impl RelayCounters {
+    fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
     }
 }
 
+/// Snapshot of relay server accounting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    /// Currently registered clients
+    pub clients: usize,
+    /// Registrations awaiting a challenge response
+    pub pending_registrations: usize,
+    /// Packets waiting to be forwarded
+    pub queued_packets: usize,
+    /// Successful registrations
+    pub registrations: u64,
+    /// Registrations rejected for a bad key or signature
+    pub auth_failures: u64,
+    /// Packets forwarded
+    pub packets_relayed: u64,
+    /// Payload bytes forwarded
+    pub bytes_relayed: u64,
+    /// Packets dropped for any reason
+    pub packets_dropped: u64,
+    /// Packets dropped by per-client rate or bandwidth limits
+    pub rate_limited: u64,
+    /// Packets dropped because the sender used up its byte quota
+    pub quota_exceeded: u64,
+}
+
 /// Relay server configuration
 #[derive(Debug, Clone)]
 pub struct RelayServerConfig {
@@ -97,6 +268,17 @@ pub struct RelayServerConfig {
     pub max_clients: usize,
     /// Rate limit (packets per client per second)
     pub rate_limit: usize,
+    /// Bandwidth limit (payload bytes per client per second)
+    pub bandwidth_limit: u64,
+    /// Total payload bytes a client may relay while registered (`None` for
+    /// no limit)
+    pub byte_quota: Option<u64>,
+    /// Maximum packets queued per client before new ones are dropped
+    pub queue_limit: usize,
+    /// Maximum registrations awaiting a challenge response
+    pub max_pending: usize,
+    /// Time a client has to answer a registration challenge
+    pub challenge_timeout: Duration,
     /// Client timeout duration
     pub client_timeout: Duration,
     /// Cleanup interval
@@ -108,22 +290,85 @@ impl Default for RelayServerConfig {
         Self {
             max_clients: 10_000,
             rate_limit: 100,
+            bandwidth_limit: 1024 * 1024,
+            byte_quota: None,
+            queue_limit: 64,
+            max_pending: 1024,
+            challenge_timeout: Duration::from_secs(10),
             client_timeout: Duration::from_secs(60),
             cleanup_interval: Duration::from_secs(30),
         }
     }
 }
 
+/// Sends queued packets
+#[derive(Clone)]
+struct Forwarder {
+    socket: Arc<UdpSocket>,
+    queue: Arc<Mutex<FairQueue>>,
+    clients: Arc<RwLock<HashMap<NodeId, ClientConnection>>>,
+    counters: Arc<RelayCounters>,
+}
+
+impl Forwarder {
+    /// Send every queued packet
+    async fn drain(&self) {
+        loop {
+            let next = self.queue.lock().expect("relay queue poisoned").pop();
+            let Some((_src, packet)) = next else {
+                return;
+            };
+
+            if self
+                .socket
+                .send_to(&packet.bytes, packet.dest_addr)
+                .await
+                .is_err()
+            {
+                RelayCounters::increment(&self.counters.packets_dropped);
+                continue;
+            }
+
+            let payload_len = packet.payload_len as u64;
+            RelayCounters::increment(&self.counters.packets_relayed);
+            self.counters
+                .bytes_relayed
+                .fetch_add(payload_len, Ordering::Relaxed);
+            if let Some(dest) = self.clients.write().await.get_mut(&packet.dest_id) {
+                dest.stats.packets_received += 1;
+                dest.stats.bytes_received += payload_len;
+            }
+        }
+    }
+}
+
+/// Aborts background tasks when dropped
+struct TaskGuard(Vec<JoinHandle<()>>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
 /// DERP-style relay server
 pub struct RelayServer {
     /// Bind address
     bind_addr: SocketAddr,
     /// Registered clients (NodeId -> ClientConnection)
     clients: Arc<RwLock<HashMap<NodeId, ClientConnection>>>,
+    /// Registrations awaiting a challenge response, by client address
+    pending: Arc<RwLock<HashMap<SocketAddr, PendingRegistration>>>,
     /// UDP socket
     socket: Arc<UdpSocket>,
-    /// Rate limiter
-    rate_limiter: Arc<RwLock<RateLimiter>>,
+    /// Packets waiting to be forwarded
+    queue: Arc<Mutex<FairQueue>>,
+    /// Wakes the forwarding task when packets are queued
+    queue_ready: Arc<Notify>,
+    /// Accounting counters
+    counters: Arc<RelayCounters>,
     /// Server configuration
     config: RelayServerConfig,
     /// Server relay ID
@@ -171,11 +416,11 @@ impl RelayServer {
         Ok(Self {
             bind_addr,
             clients: Arc::new(RwLock::new(HashMap::new())),
+            pending: Arc::new(RwLock::new(HashMap::new())),
             socket: Arc::new(socket),
-            rate_limiter: Arc::new(RwLock::new(RateLimiter::new(
-                config.rate_limit,
-                Duration::from_secs(1),
-            ))),
+            queue: Arc::new(Mutex::new(FairQueue::new(config.queue_limit))),
+            queue_ready: Arc::new(Notify::new()),
+            counters: Arc::new(RelayCounters::default()),
             config,
             relay_id,
         })
@@ -183,7 +428,9 @@ impl RelayServer {
 
     /// Run the relay server
     ///
-    /// This is the main server loop that processes incoming messages.
+    /// This is the main server loop that processes incoming messages. The
+    /// cleanup and forwarding tasks it starts stop when the returned future
+    /// is dropped, so aborting the task running it shuts the relay down.
     ///
     /// # Errors
     ///
@@ -195,8 +442,11 @@ impl RelayServer {
             &self.relay_id[..8]
         );
 
-        // Spawn cleanup task
-        self.spawn_cleanup_task();
+        // Spawn cleanup and forwarding tasks
+        let _tasks = TaskGuard(vec![
+            self.spawn_cleanup_task(),
+            self.spawn_forwarding_task(),
+        ]);
 
         let mut buf = vec![0u8; 65536];
 
@@ -225,6 +475,9 @@ impl RelayServer {
             } => {
                 self.handle_register(node_id, public_key, from).await;
             }
+            RelayMessage::ChallengeResponse { signature } => {
+                self.handle_challenge_response(&signature, from).await;
+            }
             RelayMessage::SendPacket { dest_id, payload } => {
                 // Extract sender's node_id by reverse lookup
                 if let Some(sender_id) = self.find_node_id_by_addr(from).await {
@@ -255,22 +508,120 @@ impl RelayServer {
         }
     }
 
-    /// Handle client registration
+    /// Handle a registration request by challenging the client
     async fn handle_register(&self, node_id: NodeId, public_key: [u8; 32], from: SocketAddr) {
+        if !node_id_matches_key(&node_id, &public_key)
+            || VerifyingKey::from_bytes(&public_key).is_err()
+        {
+            RelayCounters::increment(&self.counters.auth_failures);
+            self.send_error(
+                from,
+                RelayErrorCode::AuthFailed,
+                "Node ID does not match public key",
+            )
+            .await;
+            return;
+        }
+
+        // Check if server is full
+        {
+            let clients = self.clients.read().await;
+            if clients.len() >= self.config.max_clients && !clients.contains_key(&node_id) {
+                drop(clients);
+                self.send_error(from, RelayErrorCode::ServerFull, "Server at capacity")
+                    .await;
+                return;
+            }
+        }
+
+        let nonce = {
+            let mut nonce = [0u8; 32];
+            use rand::Rng;
+            rand::thread_rng().fill(&mut nonce[..]);
+            nonce
+        };
+
+        {
+            let mut pending = self.pending.write().await;
+            if pending.len() >= self.config.max_pending && !pending.contains_key(&from) {
+                drop(pending);
+                self.send_error(from, RelayErrorCode::ServerFull, "Too many registrations")
+                    .await;
+                return;
+            }
+            pending.insert(
+                from,
+                PendingRegistration {
+                    node_id,
+                    public_key,
+                    nonce,
+                    issued: Instant::now(),
+                },
+            );
+        }
+
+        let challenge = RelayMessage::Challenge {
+            relay_id: self.relay_id,
+            nonce,
+        };
+        if let Ok(bytes) = challenge.to_bytes() {
+            let _ = self.socket.send_to(&bytes, from).await;
+        }
+    }
+
+    /// Complete a registration once the client has signed the challenge
+    async fn handle_challenge_response(&self, signature: &[u8], from: SocketAddr) {
+        let Some(pending) = self.pending.write().await.remove(&from) else {
+            self.send_error(
+                from,
+                RelayErrorCode::NotRegistered,
+                "No registration in progress",
+            )
+            .await;
+            return;
+        };
+
+        let message = registration_message(&self.relay_id, &pending.nonce, &pending.node_id);
+        let verified = pending.issued.elapsed() < self.config.challenge_timeout
+            && VerifyingKey::from_bytes(&pending.public_key).is_ok_and(|key| {
+                Signature::from_slice(signature)
+                    .is_ok_and(|signature| key.verify(&message, &signature).is_ok())
+            });
+        if !verified {
+            RelayCounters::increment(&self.counters.auth_failures);
+            self.send_error(from, RelayErrorCode::AuthFailed, "Challenge failed")
+                .await;
+            return;
+        }
+
         let mut clients = self.clients.write().await;
 
         // Check if server is full
-        if clients.len() >= self.config.max_clients && !clients.contains_key(&node_id) {
+        if clients.len() >= self.config.max_clients && !clients.contains_key(&pending.node_id) {
             drop(clients);
             self.send_error(from, RelayErrorCode::ServerFull, "Server at capacity")
                 .await;
             return;
         }
 
-        // Register or update client
-        clients.insert(node_id, ClientConnection::new(from, public_key));
+        // One node per address; the proven key may move the node ID here
+        clients.retain(|id, client| client.addr != from || *id == pending.node_id);
+        match clients.get_mut(&pending.node_id) {
+            // Re-registering keeps the client's quotas and counters
+            Some(client) if client.public_key == pending.public_key => {
+                client.addr = from;
+                client.touch();
+            }
+            _ => {
+                clients.insert(
+                    pending.node_id,
+                    ClientConnection::new(from, pending.public_key, &self.config),
+                );
+            }
+        }
 
         drop(clients);
+        RelayCounters::increment(&self.counters.registrations);
 
         // Send acknowledgment
         let ack = RelayMessage::RegisterAck {
@@ -285,6 +636,9 @@ impl RelayServer {
     }
 
     /// Handle packet forwarding
+    ///
+    /// Checks the sender's quotas and queues the packet for the forwarding
+    /// task.
     async fn handle_send_packet(
         &self,
         src_id: NodeId,
@@ -292,34 +646,59 @@ impl RelayServer {
         payload: Vec<u8>,
         from: SocketAddr,
     ) {
-        // Check rate limit
-        {
-            let mut limiter = self.rate_limiter.write().await;
-            if !limiter.check(src_id) {
-                drop(limiter);
-                self.send_error(from, RelayErrorCode::RateLimited, "Rate limit exceeded")
-                    .await;
-                return;
-            }
-        }
+        let payload_len = payload.len();
+        let mut clients = self.clients.write().await;
 
-        // Find destination client
-        let clients = self.clients.read().await;
-        if let Some(dest_client) = clients.get(&dest_id) {
-            let dest_addr = dest_client.addr;
+        let Some(dest_addr) = clients.get(&dest_id).map(|dest| dest.addr) else {
             drop(clients);
+            self.send_error(from, RelayErrorCode::PeerNotFound, "Peer not found")
+                .await;
+            return;
+        };
+        let Some(src) = clients.get_mut(&src_id) else {
+            return;
+        };
+        src.touch();
 
-            // Forward packet
+        // Check quotas
+        let rejection = if self
+            .config
+            .byte_quota
+            .is_some_and(|quota| src.stats.bytes_sent + payload_len as u64 > quota)
+        {
+            RelayCounters::increment(&self.counters.quota_exceeded);
+            Some((RelayErrorCode::QuotaExceeded, "Byte quota exceeded"))
+        } else if !src.packet_rate.try_take(1.0) || !src.bandwidth.try_take(payload_len as f64) {
+            RelayCounters::increment(&self.counters.rate_limited);
+            Some((RelayErrorCode::RateLimited, "Rate limit exceeded"))
+        } else {
             let forward = RelayMessage::RecvPacket { src_id, payload };
+            let queued = forward.to_bytes().is_ok_and(|bytes| {
+                self.queue.lock().expect("relay queue poisoned").push(
+                    src_id,
+                    QueuedPacket {
+                        dest_id,
+                        dest_addr,
+                        payload_len,
+                        bytes,
+                    },
+                )
+            });
+            (!queued).then_some((RelayErrorCode::RateLimited, "Relay queue full"))
+        };
 
-            if let Ok(bytes) = forward.to_bytes() {
-                let _ = self.socket.send_to(&bytes, dest_addr).await;
-            }
-        } else {
+        if let Some((code, message)) = rejection {
+            src.stats.packets_dropped += 1;
             drop(clients);
-            self.send_error(from, RelayErrorCode::PeerNotFound, "Peer not found")
-                .await;
+            RelayCounters::increment(&self.counters.packets_dropped);
+            self.send_error(from, code, message).await;
+            return;
         }
+
+        src.stats.packets_sent += 1;
+        src.stats.bytes_sent += payload_len as u64;
+        drop(clients);
+        self.queue_ready.notify_one();
     }
 
     /// Send error message to client
@@ -345,11 +724,35 @@ impl RelayServer {
         None
     }
 
-    /// Spawn cleanup task to remove stale clients
-    fn spawn_cleanup_task(&self) {
+    /// Forwarding state shared with the forwarding task
+    fn forwarder(&self) -> Forwarder {
+        Forwarder {
+            socket: self.socket.clone(),
+            queue: self.queue.clone(),
+            clients: self.clients.clone(),
+            counters: self.counters.clone(),
+        }
+    }
+
+    /// Spawn the task that sends queued packets
+    fn spawn_forwarding_task(&self) -> JoinHandle<()> {
+        let forwarder = self.forwarder();
+        let ready = self.queue_ready.clone();
+
+        tokio::spawn(async move {
+            loop {
+                ready.notified().await;
+                forwarder.drain().await;
+            }
+        })
+    }
+
+    /// Spawn cleanup task to remove stale clients and expired challenges
+    fn spawn_cleanup_task(&self) -> JoinHandle<()> {
         let clients = self.clients.clone();
-        let rate_limiter = self.rate_limiter.clone();
+        let pending = self.pending.clone();
         let timeout = self.config.client_timeout;
+        let challenge_timeout = self.config.challenge_timeout;
         let interval = self.config.cleanup_interval;
 
         tokio::spawn(async move {
@@ -364,13 +767,13 @@ impl RelayServer {
                     clients_guard.retain(|_, client| client.is_alive(timeout));
                 }
 
-                // Clean up rate limiter
+                // Clean up unanswered challenges
                 {
-                    let mut limiter = rate_limiter.write().await;
-                    limiter.cleanup();
+                    let mut pending_guard = pending.write().await;
+                    pending_guard.retain(|_, p| p.issued.elapsed() < challenge_timeout);
                 }
             }
-        });
+        })
     }
 
     /// Get number of connected clients
@@ -378,16 +781,144 @@ impl RelayServer {
         self.clients.read().await.len()
     }
 
+    /// Get server accounting counters
+    pub async fn stats(&self) -> RelayStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let counters = &self.counters;
+        RelayStats {
+            clients: self.client_count().await,
+            pending_registrations: self.pending.read().await.len(),
+            queued_packets: self.queue.lock().expect("relay queue poisoned").len(),
+            registrations: load(&counters.registrations),
+            auth_failures: load(&counters.auth_failures),
+            packets_relayed: load(&counters.packets_relayed),
+            bytes_relayed: load(&counters.bytes_relayed),
+            packets_dropped: load(&counters.packets_dropped),
+            rate_limited: load(&counters.rate_limited),
+            quota_exceeded: load(&counters.quota_exceeded),
+        }
+    }
+
+    /// Get traffic counters for a registered client
+    pub async fn client_stats(&self, node_id: &NodeId) -> Option<ClientStats> {
+        self.clients
+            .read()
+            .await
+            .get(node_id)
+            .map(|client| client.stats)
+    }
+
+    /// Get the address the server is bound to
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the socket address cannot be read.
+    pub fn local_addr(&self) -> Result<SocketAddr, RelayError> {
+        Ok(self.socket.local_addr()?)
+    }
+
     /// Get server relay ID
     #[must_use]
     pub fn relay_id(&self) -> [u8; 32] {
         self.relay_id
     }
+
+    /// Announce this relay on the local network
+    ///
+    /// Publishes the relay under [`RELAY_SERVICE_TYPE`] with its relay ID as
+    /// the instance ID; `config.service_type` and `config.announce` are
+    /// overridden. Clients browsing for that service type can feed what they
+    /// find to [`RelaySelector::add_lan_relays`](super::RelaySelector::add_lan_relays).
+    /// Call [`MdnsService::shutdown`] to withdraw the announcement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the multicast socket cannot be set up.
+    pub async fn announce(&self, config: MdnsConfig) -> Result<MdnsService, MdnsError> {
+        let config = MdnsConfig {
+            service_type: RELAY_SERVICE_TYPE.to_string(),
+            announce: true,
+            ..config
+        };
+        MdnsService::start(
+            config,
+            DhtNodeId::from_bytes(self.relay_id),
+            self.socket.local_addr()?,
+        )
+        .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wraith_crypto::signatures::SigningKey;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    async fn test_server(config: RelayServerConfig) -> RelayServer {
+        RelayServer::bind_with_config("127.0.0.1:0".parse().unwrap(), config)
+            .await
+            .unwrap()
+    }
+
+    /// Send a Register message and return the challenge nonce, if one was issued
+    async fn start_registration(
+        server: &RelayServer,
+        key: &SigningKey,
+        from: SocketAddr,
+    ) -> Option<[u8; 32]> {
+        let public_key = key.verifying_key().to_bytes();
+        server
+            .handle_message(
+                RelayMessage::Register {
+                    node_id: public_key,
+                    public_key,
+                },
+                from,
+            )
+            .await;
+        server.pending.read().await.get(&from).map(|p| p.nonce)
+    }
+
+    /// Run the full challenge-response registration for `key`
+    async fn register(server: &RelayServer, key: &SigningKey, from: SocketAddr) -> NodeId {
+        let node_id = key.verifying_key().to_bytes();
+        let nonce = start_registration(server, key, from).await.unwrap();
+        let message = registration_message(&server.relay_id, &nonce, &node_id);
+        let signature = key.sign(&message).as_bytes().to_vec();
+        server
+            .handle_message(RelayMessage::ChallengeResponse { signature }, from)
+            .await;
+        node_id
+    }
+
+    async fn send(server: &RelayServer, dest_id: NodeId, len: usize, from: SocketAddr) {
+        server
+            .handle_message(
+                RelayMessage::SendPacket {
+                    dest_id,
+                    payload: vec![0u8; len],
+                },
+                from,
+            )
+            .await;
+    }
+
+    fn queued(len: usize) -> QueuedPacket {
+        QueuedPacket {
+            dest_id: [0u8; 32],
+            dest_addr: addr(9),
+            payload_len: len,
+            bytes: vec![0u8; len],
+        }
+    }
 
     #[tokio::test]
     async fn test_relay_server_creation() {
@@ -408,13 +939,15 @@ mod tests {
         let config = RelayServerConfig::default();
         assert_eq!(config.max_clients, 10_000);
         assert_eq!(config.rate_limit, 100);
+        assert_eq!(config.bandwidth_limit, 1024 * 1024);
+        assert_eq!(config.byte_quota, None);
     }
 
     #[test]
     fn test_client_connection() {
         let addr = "127.0.0.1:8000".parse().unwrap();
         let public_key = [1u8; 32];
-        let mut conn = ClientConnection::new(addr, public_key);
+        let mut conn = ClientConnection::new(addr, public_key, &RelayServerConfig::default());
 
         assert!(conn.is_alive(Duration::from_secs(60)));
 
@@ -423,35 +956,38 @@ mod tests {
     }
 
     #[test]
-    fn test_rate_limiter() {
-        let mut limiter = RateLimiter::new(3, Duration::from_secs(1));
-        let node_id = [1u8; 32];
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(3.0);
 
-        assert!(limiter.check(node_id));
-        assert!(limiter.check(node_id));
-        assert!(limiter.check(node_id));
-        assert!(!limiter.check(node_id)); // Should be rate limited
+        assert!(bucket.try_take(1.0));
+        assert!(bucket.try_take(1.0));
+        assert!(bucket.try_take(1.0));
+        assert!(!bucket.try_take(1.0)); // Should be rate limited
     }
 
     #[test]
-    fn test_rate_limiter_multiple_nodes() {
-        let mut limiter = RateLimiter::new(2, Duration::from_secs(1));
-        let node_a = [1u8; 32];
-        let node_b = [2u8; 32];
+    fn test_token_bucket_bytes() {
+        let mut bucket = TokenBucket::new(1000.0);
+
+        assert!(bucket.try_take(600.0));
+        assert!(!bucket.try_take(600.0)); // Not enough bytes left
+        assert!(bucket.try_take(400.0));
+    }
 
-        assert!(limiter.check(node_a));
-        assert!(limiter.check(node_a));
-        assert!(!limiter.check(node_a)); // a is limited
+    #[test]
+    fn test_token_bucket_refills() {
+        let mut bucket = TokenBucket::new(1000.0);
+        assert!(bucket.try_take(1000.0));
+        assert!(!bucket.try_take(100.0));
 
-        assert!(limiter.check(node_b)); // b is independent
-        assert!(limiter.check(node_b));
-        assert!(!limiter.check(node_b)); // b is limited
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(bucket.try_take(100.0));
     }
 
     #[test]
     fn test_client_connection_not_alive() {
         let addr = "127.0.0.1:8000".parse().unwrap();
-        let conn = ClientConnection::new(addr, [1u8; 32]);
+        let conn = ClientConnection::new(addr, [1u8; 32], &RelayServerConfig::default());
         // With a zero timeout, connection should not be alive
         assert!(!conn.is_alive(Duration::from_secs(0)));
     }
@@ -459,11 +995,45 @@ mod tests {
     #[test]
     fn test_client_connection_debug() {
         let addr = "127.0.0.1:8000".parse().unwrap();
-        let conn = ClientConnection::new(addr, [1u8; 32]);
+        let conn = ClientConnection::new(addr, [1u8; 32], &RelayServerConfig::default());
         let debug = format!("{:?}", conn);
         assert!(debug.contains("ClientConnection"));
     }
 
+    #[test]
+    fn test_fair_queue_round_robin() {
+        let alice = [1u8; 32];
+        let bob = [2u8; 32];
+        let mut queue = FairQueue::new(64);
+
+        for _ in 0..10 {
+            assert!(queue.push(alice, queued(1000)));
+        }
+        for _ in 0..2 {
+            assert!(queue.push(bob, queued(1000)));
+        }
+        assert_eq!(queue.len(), 12);
+
+        let order: Vec<NodeId> = std::iter::from_fn(|| queue.pop().map(|(src, _)| src)).collect();
+        assert_eq!(order.len(), 12);
+
+        // Bob's packets are interleaved with Alice's backlog, not stuck behind it
+        let last_bob = order.iter().rposition(|src| *src == bob).unwrap();
+        assert!(last_bob < 5, "bob served at {order:?}");
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_fair_queue_limit() {
+        let alice = [1u8; 32];
+        let mut queue = FairQueue::new(2);
+
+        assert!(queue.push(alice, queued(10)));
+        assert!(queue.push(alice, queued(10)));
+        assert!(!queue.push(alice, queued(10)));
+        assert!(queue.push([2u8; 32], queued(10)));
+    }
+
     #[tokio::test]
     async fn test_relay_server_with_config() {
         let config = RelayServerConfig {
@@ -471,6 +1041,7 @@ mod tests {
             rate_limit: 10,
             client_timeout: Duration::from_secs(30),
             cleanup_interval: Duration::from_secs(15),
+            ..Default::default()
         };
         let addr = "127.0.0.1:0".parse().unwrap();
         let server = RelayServer::bind_with_config(addr, config).await;
@@ -490,89 +1061,160 @@ mod tests {
 
     #[tokio::test]
     async fn test_relay_server_handle_register() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
-        let server_addr = server.socket.local_addr().unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
+        let server_addr = server.local_addr().unwrap();
 
-        // Send a register message from a client
+        // Register from a real socket so the challenge can be observed
         let client_socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
-        let register_msg = RelayMessage::Register {
-            node_id: [1u8; 32],
-            public_key: [2u8; 32],
-        };
-        let bytes = register_msg.to_bytes().unwrap();
-        client_socket.send_to(&bytes, server_addr).await.unwrap();
-
-        // Handle the message directly
         let from = client_socket.local_addr().unwrap();
+        assert_ne!(server_addr, from);
+
+        let nonce = start_registration(&server, &key(1), from).await.unwrap();
+        // Not registered until the challenge is answered
+        assert_eq!(server.client_count().await, 0);
+
+        let mut buf = [0u8; 256];
+        let (len, _) = client_socket.recv_from(&mut buf).await.unwrap();
+        match RelayMessage::from_bytes(&buf[..len]).unwrap() {
+            RelayMessage::Challenge { relay_id, nonce: n } => {
+                assert_eq!(relay_id, server.relay_id());
+                assert_eq!(n, nonce);
+            }
+            other => panic!("expected challenge, got {other:?}"),
+        }
+
+        let node_id = key(1).verifying_key().to_bytes();
+        let message = registration_message(&server.relay_id, &nonce, &node_id);
+        let signature = key(1).sign(&message).as_bytes().to_vec();
         server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from,
-            )
+            .handle_message(RelayMessage::ChallengeResponse { signature }, from)
             .await;
 
         assert_eq!(server.client_count().await, 1);
+        let (len, _) = client_socket.recv_from(&mut buf).await.unwrap();
+        assert!(matches!(
+            RelayMessage::from_bytes(&buf[..len]).unwrap(),
+            RelayMessage::RegisterAck { success: true, .. }
+        ));
     }
 
     #[tokio::test]
-    async fn test_relay_server_handle_register_full() {
-        let config = RelayServerConfig {
-            max_clients: 1,
-            rate_limit: 100,
-            client_timeout: Duration::from_secs(60),
-            cleanup_interval: Duration::from_secs(30),
-        };
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind_with_config(addr, config).await.unwrap();
+    async fn test_relay_server_rejects_mismatched_node_id() {
+        let server = test_server(RelayServerConfig::default()).await;
+        let from = addr(10001);
 
-        // Register first client
-        let from1: SocketAddr = "127.0.0.1:10001".parse().unwrap();
+        // Claim someone else's node ID
         server
             .handle_message(
                 RelayMessage::Register {
                     node_id: [1u8; 32],
-                    public_key: [2u8; 32],
+                    public_key: key(2).verifying_key().to_bytes(),
                 },
-                from1,
+                from,
             )
             .await;
-        assert_eq!(server.client_count().await, 1);
 
-        // Second client should be rejected (server full)
-        let from2: SocketAddr = "127.0.0.1:10002".parse().unwrap();
+        assert!(server.pending.read().await.is_empty());
+        assert_eq!(server.stats().await.auth_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_rejects_wrong_signature() {
+        let server = test_server(RelayServerConfig::default()).await;
+        let from = addr(10001);
+
+        // Register the victim's key but sign with another one
+        let victim = key(1);
+        let nonce = start_registration(&server, &victim, from).await.unwrap();
+        let node_id = victim.verifying_key().to_bytes();
+        let message = registration_message(&server.relay_id, &nonce, &node_id);
+        let signature = key(2).sign(&message).as_bytes().to_vec();
+        server
+            .handle_message(RelayMessage::ChallengeResponse { signature }, from)
+            .await;
+
+        assert_eq!(server.client_count().await, 0);
+        assert!(server.pending.read().await.is_empty());
+        assert_eq!(server.stats().await.auth_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_rejects_expired_challenge() {
+        let server = test_server(RelayServerConfig {
+            challenge_timeout: Duration::ZERO,
+            ..Default::default()
+        })
+        .await;
+
+        register(&server, &key(1), addr(10001)).await;
+        assert_eq!(server.client_count().await, 0);
+        assert_eq!(server.stats().await.auth_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_response_without_register() {
+        let server = test_server(RelayServerConfig::default()).await;
+
         server
             .handle_message(
-                RelayMessage::Register {
-                    node_id: [3u8; 32],
-                    public_key: [4u8; 32],
+                RelayMessage::ChallengeResponse {
+                    signature: vec![0u8; 64],
                 },
-                from2,
+                addr(10001),
             )
             .await;
+        assert_eq!(server.client_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_handle_register_full() {
+        let server = test_server(RelayServerConfig {
+            max_clients: 1,
+            ..Default::default()
+        })
+        .await;
+
+        // Register first client
+        register(&server, &key(1), addr(10001)).await;
+        assert_eq!(server.client_count().await, 1);
+
+        // Second client should be rejected (server full)
+        assert!(
+            start_registration(&server, &key(2), addr(10002))
+                .await
+                .is_none()
+        );
         // Still only 1 client
         assert_eq!(server.client_count().await, 1);
     }
 
+    #[tokio::test]
+    async fn test_relay_server_pending_limit() {
+        let server = test_server(RelayServerConfig {
+            max_pending: 1,
+            ..Default::default()
+        })
+        .await;
+
+        assert!(
+            start_registration(&server, &key(1), addr(10001))
+                .await
+                .is_some()
+        );
+        assert!(
+            start_registration(&server, &key(2), addr(10002))
+                .await
+                .is_none()
+        );
+    }
+
     #[tokio::test]
     async fn test_relay_server_handle_keepalive() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
 
         // Register a client first
-        let from: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from,
-            )
-            .await;
+        let from = addr(10001);
+        register(&server, &key(1), from).await;
 
         // Send keepalive
         server.handle_message(RelayMessage::Keepalive, from).await;
@@ -581,19 +1223,10 @@ mod tests {
 
     #[tokio::test]
     async fn test_relay_server_handle_disconnect() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
 
-        let from: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from,
-            )
-            .await;
+        let from = addr(10001);
+        register(&server, &key(1), from).await;
         assert_eq!(server.client_count().await, 1);
 
         server.handle_message(RelayMessage::Disconnect, from).await;
@@ -602,93 +1235,156 @@ mod tests {
 
     #[tokio::test]
     async fn test_relay_server_handle_send_not_registered() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
 
         // Try sending from unregistered address
-        let from: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::SendPacket {
-                    dest_id: [2u8; 32],
-                    payload: vec![1, 2, 3],
-                },
-                from,
-            )
-            .await;
-        // No crash, error is sent to client
+        send(&server, [2u8; 32], 3, addr(10001)).await;
+        assert_eq!(server.stats().await.queued_packets, 0);
     }
 
     #[tokio::test]
     async fn test_relay_server_handle_send_peer_not_found() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
 
         // Register sender
-        let from: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from,
-            )
-            .await;
+        let from = addr(10001);
+        let node_id = register(&server, &key(1), from).await;
 
         // Send to non-existent peer
-        server
-            .handle_message(
-                RelayMessage::SendPacket {
-                    dest_id: [99u8; 32],
-                    payload: vec![1, 2, 3],
-                },
-                from,
-            )
-            .await;
-        // No crash
+        send(&server, [99u8; 32], 3, from).await;
+        assert_eq!(server.stats().await.queued_packets, 0);
+        assert_eq!(server.client_stats(&node_id).await.unwrap().packets_sent, 0);
     }
 
     #[tokio::test]
     async fn test_relay_server_handle_send_forward() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
+
+        // Destination is a real socket so the forwarded packet can be read
+        let socket_b = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let from_a = addr(10001);
+        let id_a = register(&server, &key(1), from_a).await;
+        let id_b = register(&server, &key(2), socket_b.local_addr().unwrap()).await;
+
+        // Drain the challenge and ack sent to B
+        let mut buf = [0u8; 256];
+        for _ in 0..2 {
+            socket_b.recv_from(&mut buf).await.unwrap();
+        }
 
-        // Register sender
-        let from_a: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from_a,
-            )
-            .await;
+        // Send from A to B
+        send(&server, id_b, 3, from_a).await;
+        assert_eq!(server.stats().await.queued_packets, 1);
+        server.forwarder().drain().await;
+
+        let (len, _) = socket_b.recv_from(&mut buf).await.unwrap();
+        match RelayMessage::from_bytes(&buf[..len]).unwrap() {
+            RelayMessage::RecvPacket { src_id, payload } => {
+                assert_eq!(src_id, id_a);
+                assert_eq!(payload, vec![0u8; 3]);
+            }
+            other => panic!("expected packet, got {other:?}"),
+        }
 
-        // Register destination
-        let from_b: SocketAddr = "127.0.0.1:10002".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [3u8; 32],
-                    public_key: [4u8; 32],
-                },
-                from_b,
-            )
-            .await;
+        let stats = server.stats().await;
+        assert_eq!(stats.packets_relayed, 1);
+        assert_eq!(stats.bytes_relayed, 3);
+        assert_eq!(stats.queued_packets, 0);
+        assert_eq!(server.client_stats(&id_a).await.unwrap().bytes_sent, 3);
+        assert_eq!(server.client_stats(&id_b).await.unwrap().bytes_received, 3);
+    }
 
-        // Send from A to B
-        server
-            .handle_message(
-                RelayMessage::SendPacket {
-                    dest_id: [3u8; 32],
-                    payload: vec![1, 2, 3],
-                },
-                from_a,
-            )
-            .await;
-        // No crash, packet forwarded
+    #[tokio::test]
+    async fn test_relay_server_per_client_rate_limit() {
+        let server = test_server(RelayServerConfig {
+            rate_limit: 2,
+            ..Default::default()
+        })
+        .await;
+
+        let id_a = register(&server, &key(1), addr(10001)).await;
+        let id_b = register(&server, &key(2), addr(10002)).await;
+
+        for _ in 0..3 {
+            send(&server, id_b, 10, addr(10001)).await;
+        }
+        // A's limit does not affect B
+        for _ in 0..2 {
+            send(&server, id_a, 10, addr(10002)).await;
+        }
+
+        let stats_a = server.client_stats(&id_a).await.unwrap();
+        let stats_b = server.client_stats(&id_b).await.unwrap();
+        assert_eq!((stats_a.packets_sent, stats_a.packets_dropped), (2, 1));
+        assert_eq!((stats_b.packets_sent, stats_b.packets_dropped), (2, 0));
+        assert_eq!(server.stats().await.rate_limited, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_bandwidth_limit() {
+        let server = test_server(RelayServerConfig {
+            bandwidth_limit: 1000,
+            ..Default::default()
+        })
+        .await;
+
+        let id_a = register(&server, &key(1), addr(10001)).await;
+        let id_b = register(&server, &key(2), addr(10002)).await;
+
+        send(&server, id_b, 800, addr(10001)).await;
+        send(&server, id_b, 800, addr(10001)).await;
+
+        let stats = server.client_stats(&id_a).await.unwrap();
+        assert_eq!(stats.bytes_sent, 800);
+        assert_eq!(stats.packets_dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_byte_quota() {
+        let server = test_server(RelayServerConfig {
+            byte_quota: Some(250),
+            ..Default::default()
+        })
+        .await;
+
+        let id_a = register(&server, &key(1), addr(10001)).await;
+        let id_b = register(&server, &key(2), addr(10002)).await;
+
+        for _ in 0..3 {
+            send(&server, id_b, 100, addr(10001)).await;
+        }
+
+        let stats = server.client_stats(&id_a).await.unwrap();
+        assert_eq!(stats.bytes_sent, 200);
+        assert_eq!(stats.packets_dropped, 1);
+
+        let stats = server.stats().await;
+        assert_eq!(stats.quota_exceeded, 1);
+        assert_eq!(stats.packets_dropped, 1);
+
+        // Registering again does not reset the quota
+        register(&server, &key(1), addr(10001)).await;
+        send(&server, id_b, 100, addr(10001)).await;
+        assert_eq!(server.client_stats(&id_a).await.unwrap().bytes_sent, 200);
+        assert_eq!(server.stats().await.quota_exceeded, 2);
+    }
+
+    #[tokio::test]
+    async fn test_relay_server_queue_limit() {
+        let server = test_server(RelayServerConfig {
+            queue_limit: 1,
+            ..Default::default()
+        })
+        .await;
+
+        let id_a = register(&server, &key(1), addr(10001)).await;
+        let id_b = register(&server, &key(2), addr(10002)).await;
+
+        send(&server, id_b, 10, addr(10001)).await;
+        send(&server, id_b, 10, addr(10001)).await;
+
+        assert_eq!(server.stats().await.queued_packets, 1);
+        assert_eq!(server.client_stats(&id_a).await.unwrap().packets_dropped, 1);
     }
 
     #[tokio::test]
@@ -726,32 +1422,22 @@ mod tests {
 
     #[tokio::test]
     async fn test_relay_server_re_register() {
-        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
-        let server = RelayServer::bind(addr).await.unwrap();
+        let server = test_server(RelayServerConfig::default()).await;
 
-        let from: SocketAddr = "127.0.0.1:10001".parse().unwrap();
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [2u8; 32],
-                },
-                from,
-            )
-            .await;
+        let from = addr(10001);
+        register(&server, &key(1), from).await;
         assert_eq!(server.client_count().await, 1);
 
         // Re-register same node (should update, not add)
-        server
-            .handle_message(
-                RelayMessage::Register {
-                    node_id: [1u8; 32],
-                    public_key: [3u8; 32],
-                },
-                from,
-            )
-            .await;
+        let first = register(&server, &key(1), addr(10003)).await;
+        assert_eq!(server.client_count().await, 1);
+        assert_eq!(server.clients.read().await[&first].addr, addr(10003));
+
+        // A different key at the same address replaces the old registration
+        let node_id = register(&server, &key(2), addr(10003)).await;
         assert_eq!(server.client_count().await, 1);
+        assert!(server.client_stats(&node_id).await.is_some());
+        assert_eq!(server.stats().await.registrations, 3);
     }
 
     #[test]
@@ -767,16 +1453,4 @@ mod tests {
         let cloned = config.clone();
         assert_eq!(cloned.max_clients, config.max_clients);
     }
-
-    #[test]
-    fn test_rate_limiter_cleanup() {
-        let mut limiter = RateLimiter::new(10, Duration::from_millis(100));
-        let node_id = [1u8; 32];
-
-        limiter.check(node_id);
-        assert_eq!(limiter.counts.len(), 1);
-
-        limiter.cleanup();
-        assert_eq!(limiter.counts.len(), 1);
-    }
 }
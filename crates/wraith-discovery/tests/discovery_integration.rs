@@ -3,9 +3,16 @@
 //! These tests verify end-to-end functionality of the discovery system,
 //! including DHT lookup, NAT traversal, and relay fallback.
 
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::time::Duration;
-use wraith_discovery::dht::NodeId;
+use wraith_discovery::dht::{
+    CompactPeer, DhtError, DhtPeer, FindNodeRpc, K, LookupConfig, MAX_PEERS_PER_SUBNET, NodeId,
+    OperationError, RoutingTable, disjoint_lookup,
+};
 use wraith_discovery::{
     ConnectionType, DiscoveryConfig, DiscoveryError, DiscoveryManager, DiscoveryState, RelayInfo,
 };
@@ -256,3 +263,228 @@ async fn test_concurrent_peer_discovery() {
     assert!(results.1.is_err() || results.1.is_ok());
     assert!(results.2.is_err() || results.2.is_ok());
 }
+
+// ============================================================================
+// Sybil and eclipse simulations
+// ============================================================================
+
+/// How malicious nodes answer FIND_NODE
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Attack {
+    /// Return the colluding nodes closest to the target
+    Colluders,
+    /// Return invented node IDs right next to the target, all pointing at
+    /// attacker addresses
+    ForgedIds,
+}
+
+/// Simulated DHT where a fraction of nodes collude to eclipse lookups
+struct SimulatedDht {
+    tables: HashMap<NodeId, RoutingTable>,
+    peers: Vec<DhtPeer>,
+    malicious: HashSet<NodeId>,
+    attack: Attack,
+}
+
+impl SimulatedDht {
+    /// Build `size` nodes, the first `malicious` of which are attackers
+    ///
+    /// Every node has a key-bound ID and its own public /24, and its routing
+    /// table is filled from `known` random nodes, so lookups take several
+    /// hops.
+    fn new(size: usize, malicious: usize, known: usize, attack: Attack, rng: &mut StdRng) -> Self {
+        let peers: Vec<DhtPeer> = (0..size)
+            .map(|i| {
+                let mut key = [0xA5u8; 32];
+                key[..8].copy_from_slice(&(i as u64).to_le_bytes());
+                let addr = format!("11.{}.{}.1:8420", i / 256, i % 256);
+                DhtPeer::from_public_key(key, addr.parse().unwrap())
+            })
+            .collect();
+
+        let mut tables = HashMap::new();
+        for peer in &peers {
+            let mut table = RoutingTable::new(peer.id);
+            for other in peers.choose_multiple(rng, known).cloned() {
+                let _ = table.insert(other);
+            }
+            tables.insert(peer.id, table);
+        }
+
+        Self {
+            tables,
+            malicious: peers[..malicious].iter().map(|p| p.id).collect(),
+            peers,
+            attack,
+        }
+    }
+
+    fn honest(&self) -> impl Iterator<Item = &DhtPeer> {
+        self.peers
+            .iter()
+            .filter(|p| !self.malicious.contains(&p.id))
+    }
+
+    /// Look `target` up from `source`
+    ///
+    /// Succeeds if the target comes back as the closest node and honest
+    /// nodes make up most of the returned neighbourhood, which is where
+    /// records for the target's key would be stored.
+    async fn lookup(&self, source: &DhtPeer, target: &NodeId, config: &LookupConfig) -> bool {
+        let seeds = self.tables[&source.id].closest_peers(target, config.k);
+        let result = disjoint_lookup(&source.id, seeds, target, self, config).await;
+        let honest = result
+            .closest
+            .iter()
+            .filter(|p| self.tables.contains_key(&p.id) && !self.malicious.contains(&p.id))
+            .count();
+        result.closest.first().is_some_and(|p| p.id == *target) && 2 * honest > result.closest.len()
+    }
+
+    /// Fraction of lookups between random honest nodes that succeed
+    async fn success_rate(&self, lookups: usize, config: &LookupConfig, rng: &mut StdRng) -> f64 {
+        let honest: Vec<_> = self.honest().cloned().collect();
+        let mut found = 0;
+        for _ in 0..lookups {
+            let pair: Vec<_> = honest.choose_multiple(rng, 2).collect();
+            if self.lookup(pair[0], &pair[1].id, config).await {
+                found += 1;
+            }
+        }
+        f64::from(found) / lookups as f64
+    }
+}
+
+fn compact(peer: &DhtPeer) -> CompactPeer {
+    CompactPeer {
+        id: peer.id,
+        addr: peer.addr,
+        public_key: peer.public_key,
+    }
+}
+
+impl FindNodeRpc for SimulatedDht {
+    async fn find_node(
+        &self,
+        peer: &DhtPeer,
+        target: &NodeId,
+    ) -> Result<Vec<CompactPeer>, OperationError> {
+        // Invented IDs are served by the attackers too
+        let honest = self.tables.contains_key(&peer.id) && !self.malicious.contains(&peer.id);
+        if honest {
+            let closest = self.tables[&peer.id].closest_peers(target, K);
+            return Ok(closest.iter().map(compact).collect());
+        }
+
+        let mut colluders: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|p| self.malicious.contains(&p.id))
+            .collect();
+        colluders.sort_by_key(|p| p.id.distance(target));
+        colluders.truncate(K);
+
+        Ok(match self.attack {
+            Attack::Colluders => colluders.into_iter().map(compact).collect(),
+            Attack::ForgedIds => colluders
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let mut id = *target.as_bytes();
+                    id[31] ^= u8::try_from(i + 1).unwrap();
+                    CompactPeer {
+                        id: NodeId::from_bytes(id),
+                        addr: p.addr,
+                        public_key: p.public_key,
+                    }
+                })
+                .collect(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_disjoint_lookups_under_attack() {
+    let mut rng = StdRng::seed_from_u64(0x5EED_0019);
+    // 20% of nodes collude; each node knows 30 others
+    let dht = SimulatedDht::new(400, 80, 30, Attack::Colluders, &mut rng);
+
+    let single = LookupConfig {
+        paths: 1,
+        ..LookupConfig::default()
+    };
+    let disjoint = LookupConfig::default();
+
+    let single_rate = dht.success_rate(100, &single, &mut rng).await;
+    let disjoint_rate = dht.success_rate(100, &disjoint, &mut rng).await;
+
+    assert!(
+        disjoint_rate >= 0.95,
+        "disjoint lookups succeeded {disjoint_rate}"
+    );
+    assert!(
+        disjoint_rate > single_rate,
+        "disjoint {disjoint_rate} <= single {single_rate}"
+    );
+}
+
+#[tokio::test]
+async fn test_forged_ids_cannot_eclipse_bound_lookups() {
+    let mut rng = StdRng::seed_from_u64(0x5EED_1019);
+    let dht = SimulatedDht::new(400, 80, 30, Attack::ForgedIds, &mut rng);
+
+    let unbound = LookupConfig {
+        require_bound_ids: false,
+        ..LookupConfig::default()
+    };
+    let bound = LookupConfig::default();
+
+    let unbound_rate = dht.success_rate(100, &unbound, &mut rng).await;
+    let bound_rate = dht.success_rate(100, &bound, &mut rng).await;
+
+    assert!(bound_rate >= 0.95, "bound lookups succeeded {bound_rate}");
+    assert!(
+        bound_rate > unbound_rate,
+        "bound {bound_rate} <= unbound {unbound_rate}"
+    );
+}
+
+#[test]
+fn test_subnet_limit_bounds_attacker_share() {
+    let local_id = NodeId::random();
+    let mut table = RoutingTable::new(local_id);
+
+    // An attacker with one /24 generates many identities
+    let mut attackers = HashSet::new();
+    let mut limited = 0;
+    for i in 0..500u32 {
+        let mut key = [0x66u8; 32];
+        key[..4].copy_from_slice(&i.to_le_bytes());
+        let addr = format!("66.66.66.{}:{}", i % 256, 9000 + i);
+        let peer = DhtPeer::from_public_key(key, addr.parse().unwrap());
+        attackers.insert(peer.id);
+        if matches!(table.insert(peer), Err(DhtError::SubnetLimit)) {
+            limited += 1;
+        }
+    }
+
+    // Honest nodes spread over many networks
+    for i in 0..100u32 {
+        let mut key = [0x11u8; 32];
+        key[..4].copy_from_slice(&i.to_le_bytes());
+        let addr = format!("11.0.{i}.1:8420");
+        let _ = table.insert(DhtPeer::from_public_key(key, addr.parse().unwrap()));
+    }
+
+    let all = table.all_peers();
+    let in_table = all.iter().filter(|p| attackers.contains(&p.id)).count();
+    let occupied_buckets = all
+        .iter()
+        .filter_map(|p| p.id.bucket_index(&local_id))
+        .collect::<HashSet<_>>()
+        .len();
+
+    assert!(limited > 400, "only {limited} identities rejected");
+    assert!(in_table <= MAX_PEERS_PER_SUBNET * occupied_buckets);
+    assert!(in_table < all.len() - in_table);
+}
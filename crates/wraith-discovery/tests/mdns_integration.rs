@@ -0,0 +1,241 @@
+//! Integration tests for local network discovery over multicast DNS
+//!
+//! Each test uses the mDNS multicast group on its own port, so tests do not
+//! see each other or real responders on the host.
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use wraith_discovery::dht::NodeId;
+use wraith_discovery::mdns::MDNS_MULTICAST_ADDR;
+use wraith_discovery::relay::{LAN_REGION, RELAY_SERVICE_TYPE, RelaySelector, RelayServer};
+use wraith_discovery::{
+    ConnectionType, DiscoveryConfig, DiscoveryManager, LanPeer, MdnsConfig, MdnsService,
+};
+
+/// An mDNS configuration on an otherwise unused port
+fn test_config() -> MdnsConfig {
+    let port = std::net::UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    MdnsConfig {
+        multicast_addr: SocketAddrV4::new(*MDNS_MULTICAST_ADDR.ip(), port),
+        ..MdnsConfig::default()
+    }
+}
+
+/// Poll `service` until it has a peer matching `id`
+async fn wait_for_peer(service: &MdnsService, id: &[u8; 32]) -> Option<LanPeer> {
+    for _ in 0..50 {
+        if let Some(peer) = service.lookup(id).await {
+            return Some(peer);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    None
+}
+
+#[tokio::test]
+async fn test_services_discover_each_other() {
+    let config = test_config();
+    let id_a = NodeId::from_bytes([0xA1; 32]);
+    let id_b = NodeId::from_bytes([0xB2; 32]);
+    let addr_a: SocketAddr = "127.0.0.1:41001".parse().unwrap();
+    let addr_b: SocketAddr = "127.0.0.1:41002".parse().unwrap();
+
+    let a = MdnsService::start(
+        MdnsConfig {
+            peer_key: Some([0x11; 32]),
+            ..config.clone()
+        },
+        id_a,
+        addr_a,
+    )
+    .await
+    .unwrap();
+    let b = MdnsService::start(config, id_b, addr_b).await.unwrap();
+
+    let peer_b = wait_for_peer(&a, id_b.as_bytes()).await.expect("a finds b");
+    assert_eq!(peer_b.addrs, vec![addr_b]);
+    assert_eq!(peer_b.peer_key, None);
+
+    // Peers can be looked up by handshake key as well as node ID
+    let peer_a = wait_for_peer(&b, &[0x11; 32]).await.expect("b finds a");
+    assert_eq!(peer_a.node_id, id_a);
+    assert_eq!(peer_a.addrs, vec![addr_a]);
+
+    // A service never lists itself
+    assert!(a.lookup(id_a.as_bytes()).await.is_none());
+    assert_eq!(a.peers().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_browse_only_service_is_not_discovered() {
+    let config = test_config();
+    let id_public = NodeId::from_bytes([0x01; 32]);
+    let id_private = NodeId::from_bytes([0x02; 32]);
+
+    let public = MdnsService::start(
+        config.clone(),
+        id_public,
+        "127.0.0.1:41003".parse().unwrap(),
+    )
+    .await
+    .unwrap();
+    let private = MdnsService::start(
+        MdnsConfig {
+            announce: false,
+            ..config
+        },
+        id_private,
+        "127.0.0.1:41004".parse().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert!(
+        wait_for_peer(&private, id_public.as_bytes())
+            .await
+            .is_some()
+    );
+
+    // Queries from the browse-only node keep prompting answers, but nothing
+    // ever announces it
+    private.query().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(public.lookup(id_private.as_bytes()).await.is_none());
+}
+
+#[tokio::test]
+async fn test_goodbye_removes_peer() {
+    let config = test_config();
+    let id_a = NodeId::from_bytes([0x0A; 32]);
+    let id_b = NodeId::from_bytes([0x0B; 32]);
+
+    let a = MdnsService::start(config.clone(), id_a, "127.0.0.1:41005".parse().unwrap())
+        .await
+        .unwrap();
+    let b = MdnsService::start(config, id_b, "127.0.0.1:41006".parse().unwrap())
+        .await
+        .unwrap();
+    assert!(wait_for_peer(&a, id_b.as_bytes()).await.is_some());
+
+    b.shutdown().await;
+    for _ in 0..50 {
+        if a.lookup(id_b.as_bytes()).await.is_none() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("peer still listed after goodbye");
+}
+
+#[tokio::test]
+async fn test_relay_announcement_reaches_selector() {
+    let config = test_config();
+    let relay = RelayServer::bind("127.0.0.1:0".parse().unwrap())
+        .await
+        .unwrap();
+    let relay_addr = relay.local_addr().unwrap();
+    let announcement = relay.announce(config.clone()).await.unwrap();
+
+    // Plain node browsing does not see relays
+    let nodes = MdnsService::start(
+        MdnsConfig {
+            announce: false,
+            ..config.clone()
+        },
+        NodeId::from_bytes([0x31; 32]),
+        "127.0.0.1:41031".parse().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let relays = MdnsService::start(
+        MdnsConfig {
+            service_type: RELAY_SERVICE_TYPE.to_string(),
+            announce: false,
+            ..config
+        },
+        NodeId::from_bytes([0x32; 32]),
+        "127.0.0.1:41032".parse().unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let found = wait_for_peer(&relays, &relay.relay_id())
+        .await
+        .expect("relay is announced");
+    assert_eq!(found.addrs, vec![relay_addr]);
+    assert!(nodes.peers().await.is_empty());
+
+    let mut selector = RelaySelector::new();
+    assert_eq!(selector.add_lan_relays(&relays.peers().await), 1);
+    let best = selector.select_best().unwrap();
+    assert_eq!(best.addr, relay_addr);
+    assert_eq!(best.region, LAN_REGION);
+
+    announcement.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_manager_connects_to_lan_peer() {
+    let mdns = test_config();
+    let manager_config = |node_id, listen_addr| DiscoveryConfig {
+        nat_detection_enabled: false,
+        relay_enabled: false,
+        relay_identity: None,
+        mdns: Some(MdnsConfig {
+            peer_key: Some([0x77; 32]),
+            ..mdns.clone()
+        }),
+        ..DiscoveryConfig::new(node_id, listen_addr)
+    };
+
+    let id_a = NodeId::random();
+    let id_b = NodeId::random();
+    let addr_b: SocketAddr = "127.0.0.1:41008".parse().unwrap();
+    let a = DiscoveryManager::new(manager_config(id_a, "127.0.0.1:41007".parse().unwrap()))
+        .await
+        .unwrap();
+    let b = DiscoveryManager::new(manager_config(id_b, addr_b))
+        .await
+        .unwrap();
+    a.start().await.unwrap();
+    b.start().await.unwrap();
+
+    for _ in 0..50 {
+        if !a.lan_peers().await.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(a.lan_peers().await.len(), 1);
+
+    // The LAN address is a candidate, not a shortcut past the DHT
+    let candidates = a.peer_candidates(id_b).await.unwrap();
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].peer_id, id_b);
+    assert_eq!(candidates[0].addr, addr_b);
+    assert_eq!(candidates[0].connection_type, ConnectionType::Direct);
+    assert!(a.connect_to_peer(id_b).await.is_err());
+
+    b.shutdown().await.unwrap();
+    a.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_manager_without_mdns() {
+    let config = DiscoveryConfig {
+        nat_detection_enabled: false,
+        relay_enabled: false,
+        relay_identity: None,
+        mdns: None,
+        ..DiscoveryConfig::new(NodeId::random(), "127.0.0.1:41009".parse().unwrap())
+    };
+    let manager = DiscoveryManager::new(config).await.unwrap();
+    manager.start().await.unwrap();
+    assert!(manager.lan_peers().await.is_empty());
+    manager.shutdown().await.unwrap();
+}
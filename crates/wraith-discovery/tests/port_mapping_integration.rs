@@ -0,0 +1,521 @@
+//! Integration tests for gateway port mapping against mock gateways
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use wraith_discovery::dht::NodeId;
+use wraith_discovery::nat::IceGatherer;
+use wraith_discovery::{
+    CandidateType, DiscoveryConfig, DiscoveryManager, MappingProtocol, PortMapper,
+    PortMappingConfig, PortMappingError,
+};
+
+const EXTERNAL_IP: Ipv4Addr = Ipv4Addr::new(203, 0, 113, 50);
+
+/// Mappings held by a mock gateway: internal port -> (external port, lifetime)
+type Mappings = Arc<Mutex<HashMap<u16, (u16, u32)>>>;
+
+/// A mock gateway's address, its mappings and the number of map requests seen
+struct MockGateway {
+    addr: SocketAddr,
+    mappings: Mappings,
+    requests: Arc<Mutex<usize>>,
+}
+
+/// External port a mock gateway assigns to `internal`
+fn assigned_port(internal: u16) -> u16 {
+    internal.wrapping_add(10_000)
+}
+
+fn config_for(protocols: Vec<MappingProtocol>, gateway: SocketAddr) -> PortMappingConfig {
+    PortMappingConfig {
+        gateway: Some(gateway),
+        ssdp_addr: gateway,
+        protocols,
+        timeout: Duration::from_secs(2),
+        ..PortMappingConfig::default()
+    }
+}
+
+/// NAT-PMP gateway that answers PCP requests with UNSUPP_VERSION
+///
+/// Grants at most `max_lifetime` seconds.
+async fn spawn_natpmp_gateway(max_lifetime: u32) -> MockGateway {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mappings: Mappings = Arc::default();
+    let requests = Arc::new(Mutex::new(0));
+
+    let state = Arc::clone(&mappings);
+    let counter = Arc::clone(&requests);
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1100];
+        loop {
+            let Ok((len, from)) = socket.recv_from(&mut buf).await else {
+                return;
+            };
+            let request = &buf[..len];
+            let response = match (request[0], request[1]) {
+                // PCP request: NAT-PMP servers reply with version 0, UNSUPP_VERSION
+                (2, op) => vec![0, 128 + (op & 0x7f), 0, 1, 0, 0, 0, 0],
+                (0, 0) => {
+                    let mut response = vec![0, 128, 0, 0, 0, 0, 0, 1];
+                    response.extend_from_slice(&EXTERNAL_IP.octets());
+                    response
+                }
+                (0, 1) if len >= 12 => {
+                    let internal = u16::from_be_bytes([request[4], request[5]]);
+                    let requested =
+                        u32::from_be_bytes([request[8], request[9], request[10], request[11]]);
+                    let (external, lifetime) = if requested == 0 {
+                        state.lock().unwrap().remove(&internal);
+                        (0, 0)
+                    } else {
+                        *counter.lock().unwrap() += 1;
+                        let granted = (assigned_port(internal), requested.min(max_lifetime));
+                        state.lock().unwrap().insert(internal, granted);
+                        granted
+                    };
+                    let mut response = vec![0, 129, 0, 0, 0, 0, 0, 1];
+                    response.extend_from_slice(&internal.to_be_bytes());
+                    response.extend_from_slice(&external.to_be_bytes());
+                    response.extend_from_slice(&lifetime.to_be_bytes());
+                    response
+                }
+                _ => continue,
+            };
+            let _ = socket.send_to(&response, from).await;
+        }
+    });
+
+    MockGateway {
+        addr,
+        mappings,
+        requests,
+    }
+}
+
+/// PCP gateway that echoes the nonce and records which nonce owns each mapping
+async fn spawn_pcp_gateway() -> (MockGateway, Arc<Mutex<Vec<[u8; 12]>>>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mappings: Mappings = Arc::default();
+    let requests = Arc::new(Mutex::new(0));
+    let nonces = Arc::new(Mutex::new(Vec::new()));
+
+    let state = Arc::clone(&mappings);
+    let counter = Arc::clone(&requests);
+    let seen = Arc::clone(&nonces);
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1100];
+        loop {
+            let Ok((len, from)) = socket.recv_from(&mut buf).await else {
+                return;
+            };
+            if len != 60 || buf[0] != 2 || buf[1] != 1 {
+                continue;
+            }
+            let mut response = buf[..60].to_vec();
+            let requested = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+            let internal = u16::from_be_bytes([buf[40], buf[41]]);
+            let mut nonce = [0u8; 12];
+            nonce.copy_from_slice(&buf[24..36]);
+            seen.lock().unwrap().push(nonce);
+
+            let external = if requested == 0 {
+                state.lock().unwrap().remove(&internal);
+                0
+            } else {
+                *counter.lock().unwrap() += 1;
+                let external = assigned_port(internal);
+                state
+                    .lock()
+                    .unwrap()
+                    .insert(internal, (external, requested));
+                external
+            };
+
+            response[1] = 0x81;
+            response[3] = 0;
+            response[8..24].fill(0);
+            response[42..44].copy_from_slice(&external.to_be_bytes());
+            response[44..60].copy_from_slice(&EXTERNAL_IP.to_ipv6_mapped().octets());
+            let _ = socket.send_to(&response, from).await;
+        }
+    });
+
+    (
+        MockGateway {
+            addr,
+            mappings,
+            requests,
+        },
+        nonces,
+    )
+}
+
+/// UPnP IGD that only accepts permanent leases
+///
+/// Answers SSDP searches on a UDP socket and serves its description and
+/// control endpoint over HTTP on a TCP listener at the same port.
+async fn spawn_upnp_gateway() -> MockGateway {
+    let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let http_addr = http.local_addr().unwrap();
+    let ssdp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let ssdp_addr = ssdp.local_addr().unwrap();
+    let mappings: Mappings = Arc::default();
+    let requests = Arc::new(Mutex::new(0));
+
+    tokio::spawn(async move {
+        let mut buf = [0u8; 2048];
+        loop {
+            let Ok((len, from)) = ssdp.recv_from(&mut buf).await else {
+                return;
+            };
+            let search = String::from_utf8_lossy(&buf[..len]);
+            if !search.starts_with("M-SEARCH") || !search.contains("InternetGatewayDevice:1") {
+                continue;
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=120\r\n\
+                 ST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\
+                 LOCATION: http://{http_addr}/rootDesc.xml\r\n\r\n"
+            );
+            let _ = ssdp.send_to(response.as_bytes(), from).await;
+        }
+    });
+
+    let state = Arc::clone(&mappings);
+    let counter = Arc::clone(&requests);
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = http.accept().await else {
+                return;
+            };
+            let state = Arc::clone(&state);
+            let counter = Arc::clone(&counter);
+            tokio::spawn(async move {
+                let request = read_http_request(&mut stream).await;
+                let (status, body) = handle_upnp_request(&request, &state, &counter);
+                // Descriptions are sent chunked, SOAP responses with a length
+                let response = if request.starts_with("GET") {
+                    format!(
+                        "HTTP/1.1 {status}\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{body}\r\n0\r\n\r\n",
+                        body.len()
+                    )
+                } else {
+                    format!(
+                        "HTTP/1.1 {status}\r\nContent-Type: text/xml\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    MockGateway {
+        addr: ssdp_addr,
+        mappings,
+        requests,
+    }
+}
+
+/// Read one HTTP request (headers plus Content-Length body)
+async fn read_http_request(stream: &mut tokio::net::TcpStream) -> String {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&data);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if data.len() >= end + 4 + length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&data).into_owned()
+}
+
+/// Text between `<name>` and `</name>` in `xml`
+fn arg<'a>(xml: &'a str, name: &str) -> &'a str {
+    let start = xml.find(&format!("<{name}>")).unwrap() + name.len() + 2;
+    let end = xml.find(&format!("</{name}>")).unwrap();
+    &xml[start..end]
+}
+
+fn handle_upnp_request(
+    request: &str,
+    mappings: &Mappings,
+    requests: &Arc<Mutex<usize>>,
+) -> (&'static str, String) {
+    const SERVICE: &str = "urn:schemas-upnp-org:service:WANIPConnection:1";
+
+    if request.starts_with("GET /rootDesc.xml") {
+        let description = format!(
+            "<?xml version=\"1.0\"?><root xmlns=\"urn:schemas-upnp-org:device-1-0\"><device>\
+             <deviceList><device><serviceList><service><serviceType>{SERVICE}</serviceType>\
+             <controlURL>/ctl/IPConn</controlURL></service></serviceList></device></deviceList>\
+             </device></root>"
+        );
+        return ("200 OK", description);
+    }
+    if !request.starts_with("POST /ctl/IPConn") {
+        return ("404 Not Found", String::new());
+    }
+
+    let fault = |code: u16| {
+        (
+            "500 Internal Server Error",
+            format!(
+                "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body>\
+                 <s:Fault><faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring>\
+                 <detail><UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\">\
+                 <errorCode>{code}</errorCode></UPnPError></detail></s:Fault></s:Body></s:Envelope>"
+            ),
+        )
+    };
+    let ok = |action: &str, args: String| {
+        (
+            "200 OK",
+            format!(
+                "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body>\
+                 <u:{action}Response xmlns:u=\"{SERVICE}\">{args}</u:{action}Response>\
+                 </s:Body></s:Envelope>"
+            ),
+        )
+    };
+
+    if request.contains(&format!("SOAPAction: \"{SERVICE}#GetExternalIPAddress\"")) {
+        ok(
+            "GetExternalIPAddress",
+            format!("<NewExternalIPAddress>{EXTERNAL_IP}</NewExternalIPAddress>"),
+        )
+    } else if request.contains(&format!("SOAPAction: \"{SERVICE}#AddPortMapping\"")) {
+        if arg(request, "NewProtocol") != "UDP" || arg(request, "NewInternalClient") != "127.0.0.1"
+        {
+            return fault(402);
+        }
+        // OnlyPermanentLeasesSupported
+        if arg(request, "NewLeaseDuration") != "0" {
+            return fault(725);
+        }
+        *requests.lock().unwrap() += 1;
+        let internal: u16 = arg(request, "NewInternalPort").parse().unwrap();
+        let external: u16 = arg(request, "NewExternalPort").parse().unwrap();
+        mappings.lock().unwrap().insert(internal, (external, 0));
+        ok("AddPortMapping", String::new())
+    } else if request.contains(&format!("SOAPAction: \"{SERVICE}#DeletePortMapping\"")) {
+        let external: u16 = arg(request, "NewExternalPort").parse().unwrap();
+        let mut mappings = mappings.lock().unwrap();
+        let before = mappings.len();
+        mappings.retain(|_, (port, _)| *port != external);
+        if mappings.len() == before {
+            return fault(714);
+        }
+        ok("DeletePortMapping", String::new())
+    } else {
+        fault(401)
+    }
+}
+
+#[tokio::test]
+async fn test_natpmp_map_renew_unmap() {
+    let gateway = spawn_natpmp_gateway(3600).await;
+    let mapper = PortMapper::new(config_for(vec![MappingProtocol::NatPmp], gateway.addr));
+
+    let mapping = mapper.map(8420).await.unwrap();
+    assert_eq!(mapping.protocol, MappingProtocol::NatPmp);
+    assert_eq!(mapping.internal_port, 8420);
+    assert_eq!(
+        mapping.external_addr,
+        SocketAddr::new(EXTERNAL_IP.into(), assigned_port(8420))
+    );
+    assert_eq!(mapping.lifetime, Duration::from_secs(3600));
+    assert_eq!(mapping.renew_after(), Duration::from_secs(1800));
+
+    let renewed = mapper.renew(&mapping).await.unwrap();
+    assert_eq!(renewed.external_addr, mapping.external_addr);
+    assert_eq!(*gateway.requests.lock().unwrap(), 2);
+
+    mapper.unmap(&renewed).await.unwrap();
+    assert!(gateway.mappings.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_pcp_falls_back_to_natpmp() {
+    let gateway = spawn_natpmp_gateway(3600).await;
+    let mapper = PortMapper::new(config_for(
+        vec![MappingProtocol::Pcp, MappingProtocol::NatPmp],
+        gateway.addr,
+    ));
+
+    let mapping = mapper.map(9000).await.unwrap();
+    assert_eq!(mapping.protocol, MappingProtocol::NatPmp);
+    assert!(gateway.mappings.lock().unwrap().contains_key(&9000));
+
+    let pcp_only = PortMapper::new(config_for(vec![MappingProtocol::Pcp], gateway.addr));
+    assert!(matches!(
+        pcp_only.map(9000).await,
+        Err(PortMappingError::Rejected {
+            protocol: MappingProtocol::Pcp,
+            code: 1
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_pcp_renewal_reuses_nonce() {
+    let (gateway, nonces) = spawn_pcp_gateway().await;
+    let mapper = PortMapper::new(PortMappingConfig {
+        lifetime: Duration::from_secs(600),
+        ..config_for(vec![MappingProtocol::Pcp], gateway.addr)
+    });
+
+    let mapping = mapper.map(8420).await.unwrap();
+    assert_eq!(mapping.protocol, MappingProtocol::Pcp);
+    assert_eq!(
+        mapping.external_addr,
+        SocketAddr::new(EXTERNAL_IP.into(), assigned_port(8420))
+    );
+    assert_eq!(mapping.lifetime, Duration::from_secs(600));
+
+    mapper.renew(&mapping).await.unwrap();
+    mapper.unmap(&mapping).await.unwrap();
+    assert!(gateway.mappings.lock().unwrap().is_empty());
+
+    // Renewal and deletion must come from the nonce that created the mapping
+    let nonces = nonces.lock().unwrap();
+    assert_eq!(nonces.len(), 3);
+    assert!(nonces.iter().all(|nonce| *nonce == nonces[0]));
+}
+
+#[tokio::test]
+async fn test_upnp_permanent_lease_fallback() {
+    let gateway = spawn_upnp_gateway().await;
+    let mapper = PortMapper::new(config_for(vec![MappingProtocol::Upnp], gateway.addr));
+
+    let mapping = mapper.map(8420).await.unwrap();
+    assert_eq!(mapping.protocol, MappingProtocol::Upnp);
+    assert_eq!(
+        mapping.external_addr,
+        SocketAddr::new(EXTERNAL_IP.into(), 8420)
+    );
+    assert_eq!(mapping.lifetime, Duration::ZERO);
+    assert_eq!(
+        gateway.mappings.lock().unwrap().get(&8420),
+        Some(&(8420, 0))
+    );
+
+    mapper.renew(&mapping).await.unwrap();
+    assert_eq!(*gateway.requests.lock().unwrap(), 2);
+
+    mapper.unmap(&mapping).await.unwrap();
+    assert!(gateway.mappings.lock().unwrap().is_empty());
+    assert!(matches!(
+        mapper.unmap(&mapping).await,
+        Err(PortMappingError::Rejected {
+            protocol: MappingProtocol::Upnp,
+            code: 714
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_all_protocols_fail_without_gateway() {
+    // Nothing listens here; every protocol must give up within its timeout
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mapper = PortMapper::new(PortMappingConfig {
+        timeout: Duration::from_millis(300),
+        ..config_for(
+            vec![
+                MappingProtocol::Pcp,
+                MappingProtocol::NatPmp,
+                MappingProtocol::Upnp,
+            ],
+            silent.local_addr().unwrap(),
+        )
+    });
+    assert!(matches!(
+        mapper.map(8420).await,
+        Err(PortMappingError::Timeout)
+    ));
+}
+
+#[tokio::test]
+async fn test_spawned_mapping_renews_and_feeds_ice() {
+    // The gateway grants 2-second leases, so renewals happen every second
+    let gateway = spawn_natpmp_gateway(2).await;
+    let handle =
+        PortMapper::new(config_for(vec![MappingProtocol::NatPmp], gateway.addr)).spawn(8420);
+
+    let mut updates = handle.subscribe();
+    tokio::time::timeout(Duration::from_secs(5), updates.wait_for(Option::is_some))
+        .await
+        .unwrap()
+        .unwrap();
+    let external = handle.external_addr().unwrap();
+
+    let local: SocketAddr = "127.0.0.1:8420".parse().unwrap();
+    let candidates = IceGatherer::with_stun_servers(Vec::new())
+        .with_port_mapping(handle.subscribe())
+        .gather(local)
+        .await
+        .unwrap();
+    assert!(
+        candidates.iter().any(|c| {
+            c.candidate_type == CandidateType::ServerReflexive && c.address == external
+        })
+    );
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert!(*gateway.requests.lock().unwrap() >= 3);
+
+    handle.shutdown().await;
+    assert!(gateway.mappings.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_discovery_manager_port_mapping() {
+    let gateway = spawn_natpmp_gateway(3600).await;
+    let mut config = DiscoveryConfig::new(NodeId::random(), "127.0.0.1:0".parse().unwrap());
+    config.nat_detection_enabled = false;
+    config.relay_enabled = false;
+    config.port_mapping = Some(PortMappingConfig {
+        internal_port: Some(7777),
+        ..config_for(vec![MappingProtocol::NatPmp], gateway.addr)
+    });
+
+    let manager = DiscoveryManager::new(config).await.unwrap();
+    assert_eq!(manager.mapped_address(), None);
+    manager.start().await.unwrap();
+
+    let mut updates = manager.subscribe_port_mapping();
+    tokio::time::timeout(Duration::from_secs(5), updates.wait_for(Option::is_some))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        manager.mapped_address(),
+        Some(SocketAddr::new(EXTERNAL_IP.into(), assigned_port(7777)))
+    );
+
+    manager.shutdown().await.unwrap();
+    assert_eq!(manager.mapped_address(), None);
+    assert!(gateway.mappings.lock().unwrap().is_empty());
+}
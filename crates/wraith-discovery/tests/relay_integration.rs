@@ -1,9 +1,12 @@
 //! Integration tests for the relay infrastructure
 
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
+use wraith_crypto::signatures::SigningKey;
 use wraith_discovery::relay::{
-    RelayClient, RelayInfo, RelaySelector, RelayServer, SelectionStrategy,
+    RelayClient, RelayError, RelayIdentity, RelayInfo, RelaySelector, RelayServer,
+    SelectionStrategy,
 };
 
 #[tokio::test]
@@ -94,6 +97,72 @@ async fn test_relay_server_client_count() {
     assert_eq!(count, 0); // No clients connected yet
 }
 
+#[tokio::test]
+async fn test_relay_forwarding_end_to_end() {
+    let server = Arc::new(
+        RelayServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap(),
+    );
+    let relay_addr = server.local_addr().unwrap();
+    let running = server.clone();
+    tokio::spawn(async move { running.run().await });
+
+    let alice = RelayIdentity::new(SigningKey::from_bytes(&[1u8; 32]));
+    let bob = RelayIdentity::new(SigningKey::from_bytes(&[2u8; 32]));
+
+    let mut alice_client = RelayClient::connect(relay_addr, alice.public_key())
+        .await
+        .unwrap();
+    alice_client.register(&alice).await.unwrap();
+    let mut bob_client = RelayClient::connect(relay_addr, bob.public_key())
+        .await
+        .unwrap();
+    bob_client.register(&bob).await.unwrap();
+    bob_client.spawn_receiver();
+
+    alice_client
+        .send_to_peer(bob.public_key(), b"hello")
+        .await
+        .unwrap();
+    let (from, data) = tokio::time::timeout(Duration::from_secs(5), bob_client.recv_from_peer())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(from, alice.public_key());
+    assert_eq!(data, b"hello");
+
+    let stats = server.stats().await;
+    assert_eq!(stats.clients, 2);
+    assert_eq!(stats.registrations, 2);
+    assert_eq!(stats.packets_relayed, 1);
+    assert_eq!(stats.bytes_relayed, 5);
+}
+
+#[tokio::test]
+async fn test_relay_rejects_registration_for_foreign_node_id() {
+    let server = Arc::new(
+        RelayServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap(),
+    );
+    let relay_addr = server.local_addr().unwrap();
+    let running = server.clone();
+    tokio::spawn(async move { running.run().await });
+
+    // Claim the victim's node ID while holding a different key
+    let victim = RelayIdentity::new(SigningKey::from_bytes(&[1u8; 32]));
+    let attacker = RelayIdentity::new(SigningKey::from_bytes(&[2u8; 32]));
+
+    let mut client = RelayClient::connect(relay_addr, victim.public_key())
+        .await
+        .unwrap();
+    let result = client.register(&attacker).await;
+    assert!(matches!(result, Err(RelayError::AuthFailed)));
+    assert_eq!(server.client_count().await, 0);
+    assert_eq!(server.stats().await.auth_failures, 1);
+}
+
 #[tokio::test]
 async fn test_relay_info_load_bounds() {
     let addr = "127.0.0.1:8000".parse().unwrap();
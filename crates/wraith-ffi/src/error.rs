@@ -115,6 +115,9 @@ impl From<wraith_core::node::NodeError> for WraithError {
             NodeError::Transport(_) => Self::new(WraithErrorCode::TransportError, err.to_string()),
             NodeError::Crypto(_) => Self::new(WraithErrorCode::CryptoError, err.to_string()),
             NodeError::Handshake(_) => Self::new(WraithErrorCode::CryptoError, err.to_string()),
+            NodeError::PeerKeyMismatch { .. } => {
+                Self::new(WraithErrorCode::CryptoError, err.to_string())
+            }
             NodeError::SessionEstablishment(_) => {
                 Self::new(WraithErrorCode::InternalError, err.to_string())
             }
@@ -124,6 +127,7 @@ impl From<wraith_core::node::NodeError> for WraithError {
             }
             NodeError::Transfer(_) => Self::new(WraithErrorCode::InternalError, err.to_string()),
             NodeError::TransferNotFound(_) => Self::transfer_not_found(),
+            NodeError::Stream(_) => Self::new(WraithErrorCode::InternalError, err.to_string()),
             NodeError::HashMismatch => Self::new(WraithErrorCode::CryptoError, err.to_string()),
             NodeError::Io(_) => Self::new(WraithErrorCode::IoError, err.to_string()),
             NodeError::Discovery(_) => Self::new(WraithErrorCode::DiscoveryError, err.to_string()),
@@ -96,6 +96,16 @@ impl FileTreeHash {
     pub fn get_chunk_hash(&self, chunk_index: usize) -> Option<&[u8; 32]> {
         self.chunks.get(chunk_index)
     }
+
+    /// Check that the chunk hash layer is consistent with the root
+    ///
+    /// Recomputes the Merkle root from `chunks` and compares it against
+    /// `root`. A receiver must call this before trusting chunk hashes
+    /// supplied by a remote peer.
+    #[must_use]
+    pub fn verify_root(&self) -> bool {
+        !self.chunks.is_empty() && compute_merkle_root(&self.chunks) == self.root
+    }
 }
 
 /// Compute tree hash for a file
@@ -493,6 +503,22 @@ mod tests {
         assert!(tree_with_hash.verify_chunk(0, &chunk_data));
     }
 
+    #[test]
+    fn test_verify_root() {
+        let data = vec![0xAA; 4096];
+        let tree = compute_tree_hash_from_data(&data, 1024);
+        assert!(tree.verify_root());
+
+        // Tampered chunk hash no longer matches the root
+        let mut tampered = tree.clone();
+        tampered.chunks[2] = [0xFF; 32];
+        assert!(!tampered.verify_root());
+
+        // Missing chunk hashes cannot be verified
+        let root_only = FileTreeHash::new(tree.root, Vec::new());
+        assert!(!root_only.verify_root());
+    }
+
     #[test]
     fn test_incremental_hasher_buffering() {
         let mut hasher = IncrementalTreeHasher::new(1024);
@@ -0,0 +1,99 @@
+//! Batched datagram I/O benchmarks for wraith-transport.
+//!
+//! Run with: `cargo bench -p wraith-transport --bench batch_bench`
+//!
+//! Compares sending a burst of datagrams with one `send_to` call each
+//! against a single `send_batch` call, which uses sendmmsg and UDP GSO on
+//! Linux.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use wraith_transport::batch::{GRO_SLOT_SIZE, RecvBatch};
+use wraith_transport::transport::Transport;
+use wraith_transport::udp_async::AsyncUdpTransport;
+
+/// Datagram size used by the node for file chunks
+const DATAGRAM_SIZE: usize = 1200;
+
+/// Bind a sender and a receiver that drains everything sent to it
+fn setup(runtime: &Runtime) -> (AsyncUdpTransport, SocketAddr) {
+    runtime.block_on(async {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let receiver = Arc::new(AsyncUdpTransport::bind(addr).await.unwrap());
+        let _ = receiver.set_gro(true);
+        let receiver_addr = receiver.local_addr().unwrap();
+
+        let drain = Arc::clone(&receiver);
+        tokio::spawn(async move {
+            let mut batch = RecvBatch::new(8, GRO_SLOT_SIZE);
+            while drain.recv_batch(&mut batch).await.is_ok() {}
+        });
+
+        let sender = AsyncUdpTransport::bind(addr).await.unwrap();
+        (sender, receiver_addr)
+    })
+}
+
+/// Benchmark a burst of datagrams sent one call at a time
+fn bench_send_to_loop(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (sender, receiver_addr) = setup(&runtime);
+    let mut group = c.benchmark_group("send_to_loop");
+
+    for count in [8, 32, 64] {
+        let data = vec![0xAA; DATAGRAM_SIZE];
+        group.throughput(Throughput::Bytes((count * DATAGRAM_SIZE) as u64));
+
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter(|| {
+                runtime.block_on(async {
+                    for _ in 0..count {
+                        black_box(sender.send_to(&data, receiver_addr).await.unwrap());
+                    }
+                });
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Benchmark the same burst submitted with `send_batch`
+fn bench_send_batch(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (sender, receiver_addr) = setup(&runtime);
+    let mut group = c.benchmark_group("send_batch");
+
+    for count in [8, 32, 64] {
+        let data = vec![0xAA; DATAGRAM_SIZE];
+        let datagrams: Vec<(&[u8], SocketAddr)> = (0..count)
+            .map(|_| (data.as_slice(), receiver_addr))
+            .collect();
+        group.throughput(Throughput::Bytes((count * DATAGRAM_SIZE) as u64));
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &datagrams,
+            |b, datagrams| {
+                b.iter(|| {
+                    runtime.block_on(async {
+                        let mut rest = &datagrams[..];
+                        while !rest.is_empty() {
+                            let sent = sender.send_batch(rest).await.unwrap();
+                            rest = &rest[sent..];
+                        }
+                        black_box(rest);
+                    });
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_send_to_loop, bench_send_batch);
+criterion_main!(benches);
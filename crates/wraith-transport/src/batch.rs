@@ -0,0 +1,580 @@
+//! Batched datagram I/O.
+//!
+//! [`Transport::send_batch`](crate::transport::Transport::send_batch) and
+//! [`Transport::recv_batch`](crate::transport::Transport::recv_batch) move
+//! many datagrams per call. Their default implementations loop over
+//! `send_to`/`recv_from`; the async UDP transport overrides them on Linux to
+//! cut the per-packet syscall cost:
+//!
+//! - `sendmmsg(2)` submits a whole batch in one syscall
+//! - UDP generic segmentation offload (`UDP_SEGMENT`) sends a run of
+//!   equal-sized datagrams to the same peer as one large buffer, which the
+//!   kernel or NIC splits into datagrams
+//! - `recvmmsg(2)` drains every queued datagram in one syscall
+//! - UDP generic receive offload (`UDP_GRO`, opt-in) delivers consecutive
+//!   datagrams of a flow as one buffer, split again by [`RecvBatch`]
+//!
+//! Other platforms and transports keep the per-datagram path.
+
+use std::net::SocketAddr;
+
+/// Datagrams submitted to the kernel per `sendmmsg`/`recvmmsg` call
+pub const MAX_BATCH_SIZE: usize = 64;
+
+/// Receive slot size needed while UDP GRO is enabled
+///
+/// A coalesced GRO buffer can hold up to 64 KiB of datagrams.
+pub const GRO_SLOT_SIZE: usize = u16::MAX as usize;
+
+/// A received datagram's location in the batch buffer
+#[derive(Debug, Clone, Copy)]
+struct Received {
+    offset: usize,
+    len: usize,
+    addr: SocketAddr,
+}
+
+/// Reusable buffer for [`Transport::recv_batch`]
+///
+/// Holds a fixed number of equally sized receive slots. Each slot receives
+/// one datagram, or several when UDP GRO coalesced them.
+///
+/// [`Transport::recv_batch`]: crate::transport::Transport::recv_batch
+///
+/// # Examples
+///
+/// ```no_run
+/// use wraith_transport::batch::RecvBatch;
+/// use wraith_transport::transport::Transport;
+/// use wraith_transport::udp_async::AsyncUdpTransport;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let transport = AsyncUdpTransport::bind("127.0.0.1:40000".parse::<std::net::SocketAddr>()?).await?;
+/// let mut batch = RecvBatch::new(32, 1500);
+///
+/// transport.recv_batch(&mut batch).await?;
+/// for (data, from) in batch.iter() {
+///     println!("{} bytes from {}", data.len(), from);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecvBatch {
+    buf: Vec<u8>,
+    slot_size: usize,
+    received: Vec<Received>,
+}
+
+impl RecvBatch {
+    /// Create a batch of `slots` receive slots of `slot_size` bytes each
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slots` or `slot_size` is zero.
+    #[must_use]
+    pub fn new(slots: usize, slot_size: usize) -> Self {
+        assert!(slots > 0 && slot_size > 0, "empty receive batch");
+        Self {
+            buf: vec![0u8; slots * slot_size],
+            slot_size,
+            received: Vec::with_capacity(slots),
+        }
+    }
+
+    /// Number of receive slots
+    #[must_use]
+    pub fn slots(&self) -> usize {
+        self.buf.len() / self.slot_size
+    }
+
+    /// Size of each receive slot in bytes
+    #[must_use]
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    /// Number of datagrams received
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.received.len()
+    }
+
+    /// Whether no datagram was received
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.received.is_empty()
+    }
+
+    /// Forget the received datagrams
+    pub fn clear(&mut self) {
+        self.received.clear();
+    }
+
+    /// Datagram `index` and its sender
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<(&[u8], SocketAddr)> {
+        self.received
+            .get(index)
+            .map(|r| (&self.buf[r.offset..r.offset + r.len], r.addr))
+    }
+
+    /// Received datagrams and their senders, in arrival order
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], SocketAddr)> {
+        self.received
+            .iter()
+            .map(|r| (&self.buf[r.offset..r.offset + r.len], r.addr))
+    }
+
+    /// Receive slot `slot`, for transports filling the batch themselves
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is out of range.
+    pub fn slot_mut(&mut self, slot: usize) -> &mut [u8] {
+        let offset = slot * self.slot_size;
+        &mut self.buf[offset..offset + self.slot_size]
+    }
+
+    /// Record a datagram of `len` bytes received into `slot` from `addr`
+    pub fn push(&mut self, slot: usize, len: usize, addr: SocketAddr) {
+        self.push_segments(slot, len, len, addr);
+    }
+
+    /// Record `len` bytes of `segment`-sized datagrams coalesced into `slot`
+    ///
+    /// The last datagram may be shorter than `segment`. Lengths beyond the
+    /// slot are truncated, as `recv_from` truncates oversized datagrams.
+    pub fn push_segments(&mut self, slot: usize, len: usize, segment: usize, addr: SocketAddr) {
+        let start = slot * self.slot_size;
+        let len = len.min(self.slot_size);
+        if len == 0 || segment == 0 {
+            self.received.push(Received {
+                offset: start,
+                len,
+                addr,
+            });
+            return;
+        }
+        let mut offset = 0;
+        while offset < len {
+            let datagram = segment.min(len - offset);
+            self.received.push(Received {
+                offset: start + offset,
+                len: datagram,
+                addr,
+            });
+            offset += datagram;
+        }
+    }
+
+    /// Whole receive buffer, for vectored receives
+    #[cfg(target_os = "linux")]
+    pub(crate) fn buffer_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+/// `sendmmsg`/`recvmmsg` with UDP GSO and GRO
+#[cfg(target_os = "linux")]
+pub(crate) mod linux {
+    use super::{MAX_BATCH_SIZE, RecvBatch};
+    use socket2::{SockAddr, SockAddrStorage};
+    use std::io;
+    use std::net::SocketAddr;
+    use std::ops::Range;
+    use std::os::fd::RawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// `UDP_SEGMENT` socket option and control message (linux/udp.h)
+    const UDP_SEGMENT: libc::c_int = 103;
+
+    /// `UDP_GRO` socket option and control message (linux/udp.h)
+    const UDP_GRO: libc::c_int = 104;
+
+    /// Segments the kernel accepts in one GSO send (`UDP_MAX_SEGMENTS`)
+    const MAX_GSO_SEGMENTS: usize = 64;
+
+    /// Largest UDP payload over IPv4
+    const MAX_UDP_PAYLOAD: usize = 65_507;
+
+    /// Space for one control message carrying an `int`
+    // SAFETY: CMSG_SPACE only computes an aligned size
+    const CONTROL_LEN: usize =
+        unsafe { libc::CMSG_SPACE(size_of::<libc::c_int>() as u32) } as usize;
+
+    /// Control message buffer, aligned for `cmsghdr`
+    #[derive(Clone, Copy)]
+    #[repr(C, align(8))]
+    struct ControlBuf([u8; CONTROL_LEN]);
+
+    /// Whether the kernel supports UDP GSO on this socket
+    pub(crate) fn gso_supported(fd: RawFd) -> bool {
+        let mut value: libc::c_int = 0;
+        let mut len = size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: getsockopt writes at most `len` bytes to `value`, which
+        // outlives the call
+        let ret = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_UDP,
+                UDP_SEGMENT,
+                (&raw mut value).cast(),
+                &mut len,
+            )
+        };
+        ret == 0
+    }
+
+    /// Enable or disable UDP GRO on the socket
+    pub(crate) fn set_gro(fd: RawFd, enable: bool) -> io::Result<()> {
+        let value = libc::c_int::from(enable);
+        // SAFETY: setsockopt reads `size_of::<c_int>()` bytes from `value`,
+        // which outlives the call
+        let ret = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_UDP,
+                UDP_GRO,
+                (&raw const value).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// Split a batch into runs sent as one message each
+    ///
+    /// With GSO, a run is consecutive datagrams to the same address that
+    /// all have the size of the first, except a shorter last one. Without
+    /// GSO every datagram is its own run.
+    pub(crate) fn gso_runs(datagrams: &[(&[u8], SocketAddr)], gso: bool) -> Vec<Range<usize>> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        while start < datagrams.len() {
+            let (first, addr) = datagrams[start];
+            let segment = first.len();
+            let mut end = start + 1;
+            let mut total = segment;
+            if gso && segment > 0 {
+                while end < datagrams.len() && end - start < MAX_GSO_SEGMENTS {
+                    let (next, next_addr) = datagrams[end];
+                    if next_addr != addr
+                        || next.is_empty()
+                        || next.len() > segment
+                        || total + next.len() > MAX_UDP_PAYLOAD
+                    {
+                        break;
+                    }
+                    total += next.len();
+                    end += 1;
+                    if next.len() < segment {
+                        break;
+                    }
+                }
+            }
+            runs.push(start..end);
+            start = end;
+        }
+        runs
+    }
+
+    /// Send `runs` of `datagrams` with one `sendmmsg` call
+    ///
+    /// Returns the number of runs sent.
+    fn sendmmsg(
+        fd: RawFd,
+        datagrams: &[(&[u8], SocketAddr)],
+        runs: &[Range<usize>],
+        gso: bool,
+    ) -> io::Result<usize> {
+        let base = runs[0].start;
+        let end = runs[runs.len() - 1].end;
+
+        // Every vector is sized up front: the headers point into them
+        let mut iovecs: Vec<libc::iovec> = datagrams[base..end]
+            .iter()
+            .map(|(buf, _)| libc::iovec {
+                iov_base: buf.as_ptr().cast_mut().cast(),
+                iov_len: buf.len(),
+            })
+            .collect();
+        let addrs: Vec<SockAddr> = runs
+            .iter()
+            .map(|run| SockAddr::from(datagrams[run.start].1))
+            .collect();
+        let mut controls = vec![ControlBuf([0; CONTROL_LEN]); runs.len()];
+        let iov_base = iovecs.as_mut_ptr();
+        let control_base = controls.as_mut_ptr();
+
+        let mut msgs = Vec::with_capacity(runs.len());
+        for (i, run) in runs.iter().enumerate() {
+            // SAFETY: all-zero is a valid msghdr (null pointers, zero lengths)
+            let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+            hdr.msg_name = addrs[i].as_ptr().cast_mut().cast();
+            hdr.msg_namelen = addrs[i].len();
+            // SAFETY: runs are in bounds of `iovecs`, one iovec per datagram
+            hdr.msg_iov = unsafe { iov_base.add(run.start - base) };
+            hdr.msg_iovlen = run.len() as _;
+
+            if gso && run.len() > 1 {
+                let segment = datagrams[run.start].0.len();
+                // SAFETY: one control buffer per run
+                hdr.msg_control = unsafe { control_base.add(i) }.cast();
+                hdr.msg_controllen = CONTROL_LEN as _;
+                // SAFETY: msg_control points to CONTROL_LEN aligned bytes,
+                // room for one cmsghdr and an int, so CMSG_FIRSTHDR is non-null
+                // and CMSG_DATA has space for the segment size
+                unsafe {
+                    let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+                    (*cmsg).cmsg_level = libc::SOL_UDP;
+                    (*cmsg).cmsg_type = UDP_SEGMENT;
+                    (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<u16>() as u32) as _;
+                    std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<u16>(), segment as u16);
+                }
+            }
+            msgs.push(libc::mmsghdr {
+                msg_hdr: hdr,
+                msg_len: 0,
+            });
+        }
+
+        // SAFETY: every header points into `iovecs`, `addrs` and `controls`,
+        // which are not modified until the call returns, and the iovecs point
+        // into `datagrams`, which outlives the call
+        let ret = unsafe { libc::sendmmsg(fd, msgs.as_mut_ptr(), msgs.len() as _, 0) };
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret as usize)
+        }
+    }
+
+    /// Send datagrams in order with `sendmmsg`, coalescing runs with GSO
+    ///
+    /// Returns the number of datagrams sent, which is fewer than all of them
+    /// if the socket buffer filled up or an error stopped the batch part-way.
+    /// Errors are returned only if nothing was sent. GSO is switched off in
+    /// `gso` if the kernel or NIC rejects a segmented send.
+    pub(crate) fn send(
+        fd: RawFd,
+        datagrams: &[(&[u8], SocketAddr)],
+        gso: &AtomicBool,
+    ) -> io::Result<usize> {
+        let mut sent = 0;
+        while sent < datagrams.len() {
+            let use_gso = gso.load(Ordering::Relaxed);
+            let rest = &datagrams[sent..];
+            let runs = gso_runs(rest, use_gso);
+            let runs = &runs[..runs.len().min(MAX_BATCH_SIZE)];
+
+            match sendmmsg(fd, rest, runs, use_gso) {
+                Ok(count) => {
+                    let datagrams_sent = runs[..count]
+                        .iter()
+                        .map(ExactSizeIterator::len)
+                        .sum::<usize>();
+                    sent += datagrams_sent;
+                    if count < runs.len() {
+                        break;
+                    }
+                }
+                // EIO: no checksum offload on the device; EINVAL: no GSO
+                Err(e)
+                    if use_gso
+                        && runs[0].len() > 1
+                        && matches!(e.raw_os_error(), Some(libc::EIO | libc::EINVAL)) =>
+                {
+                    tracing::debug!("UDP GSO unavailable ({}), sending datagrams one by one", e);
+                    gso.store(false, Ordering::Relaxed);
+                }
+                Err(_) if sent > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Segment size of a GRO-coalesced message, if any
+    fn gro_segment(hdr: &libc::msghdr) -> Option<usize> {
+        // SAFETY: the kernel filled msg_control with msg_controllen bytes of
+        // well-formed control messages, which the CMSG macros walk
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(hdr);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_UDP && (*cmsg).cmsg_type == UDP_GRO {
+                    let segment =
+                        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::c_int>());
+                    return usize::try_from(segment).ok();
+                }
+                cmsg = libc::CMSG_NXTHDR(hdr, cmsg);
+            }
+        }
+        None
+    }
+
+    /// Receive every queued datagram, up to the batch size, with `recvmmsg`
+    ///
+    /// Returns `WouldBlock` if nothing is queued.
+    pub(crate) fn recv(fd: RawFd, batch: &mut RecvBatch) -> io::Result<usize> {
+        batch.clear();
+        let slots = batch.slots().min(MAX_BATCH_SIZE);
+        let slot_size = batch.slot_size();
+        let buf = batch.buffer_mut();
+
+        let mut iovecs: Vec<libc::iovec> = buf
+            .chunks_exact_mut(slot_size)
+            .take(slots)
+            .map(|slot| libc::iovec {
+                iov_base: slot.as_mut_ptr().cast(),
+                iov_len: slot.len(),
+            })
+            .collect();
+        let mut names: Vec<SockAddrStorage> =
+            (0..slots).map(|_| SockAddrStorage::zeroed()).collect();
+        let mut controls = vec![ControlBuf([0; CONTROL_LEN]); slots];
+        let name_len = SockAddrStorage::zeroed().size_of();
+        let iov_base = iovecs.as_mut_ptr();
+        let name_base = names.as_mut_ptr();
+        let control_base = controls.as_mut_ptr();
+
+        let mut msgs = Vec::with_capacity(slots);
+        for i in 0..slots {
+            // SAFETY: all-zero is a valid msghdr (null pointers, zero lengths)
+            let mut hdr: libc::msghdr = unsafe { std::mem::zeroed() };
+            // SAFETY: `i` is in bounds of every per-slot vector
+            unsafe {
+                hdr.msg_name = name_base.add(i).cast();
+                hdr.msg_iov = iov_base.add(i);
+                hdr.msg_control = control_base.add(i).cast();
+            }
+            hdr.msg_namelen = name_len;
+            hdr.msg_iovlen = 1;
+            hdr.msg_controllen = CONTROL_LEN as _;
+            msgs.push(libc::mmsghdr {
+                msg_hdr: hdr,
+                msg_len: 0,
+            });
+        }
+
+        // SAFETY: every header points into `iovecs`, `names` and `controls`,
+        // and the iovecs into the batch buffer; none of them is touched until
+        // the call returns
+        let ret = unsafe {
+            libc::recvmmsg(
+                fd,
+                msgs.as_mut_ptr(),
+                slots as _,
+                libc::MSG_DONTWAIT as _,
+                std::ptr::null_mut(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let count = ret as usize;
+        for (slot, (msg, name)) in msgs.iter().zip(names).take(count).enumerate() {
+            // SAFETY: the kernel wrote a socket address of msg_namelen bytes
+            let addr = unsafe { SockAddr::new(name, msg.msg_hdr.msg_namelen) };
+            let Some(addr) = addr.as_socket() else {
+                continue;
+            };
+            let len = msg.msg_len as usize;
+            let segment = gro_segment(&msg.msg_hdr).unwrap_or(len);
+            batch.push_segments(slot, len, segment, addr);
+        }
+        Ok(batch.len())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn addr(port: u16) -> SocketAddr {
+            SocketAddr::from(([127, 0, 0, 1], port))
+        }
+
+        #[test]
+        fn test_gso_runs_group_equal_sizes_per_peer() {
+            let full = [0u8; 1200];
+            let short = [0u8; 300];
+            let datagrams = [
+                (&full[..], addr(1)),
+                (&full[..], addr(1)),
+                (&short[..], addr(1)),
+                (&full[..], addr(1)),
+                (&full[..], addr(2)),
+                (&short[..], addr(2)),
+                (&full[..], addr(2)),
+            ];
+
+            assert_eq!(gso_runs(&datagrams, true), vec![0..3, 3..4, 4..6, 6..7]);
+            assert_eq!(gso_runs(&datagrams, false).len(), datagrams.len());
+        }
+
+        #[test]
+        fn test_gso_runs_respect_kernel_limits() {
+            let datagram = [0u8; 100];
+            let datagrams = vec![(&datagram[..], addr(1)); MAX_GSO_SEGMENTS + 1];
+            assert_eq!(
+                gso_runs(&datagrams, true),
+                vec![0..MAX_GSO_SEGMENTS, MAX_GSO_SEGMENTS..MAX_GSO_SEGMENTS + 1]
+            );
+
+            let large = vec![0u8; 30_000];
+            let datagrams = vec![(&large[..], addr(1)); 3];
+            assert_eq!(gso_runs(&datagrams, true), vec![0..2, 2..3]);
+
+            let empty: [u8; 0] = [];
+            let datagrams = [(&empty[..], addr(1)), (&empty[..], addr(1))];
+            assert_eq!(gso_runs(&datagrams, true), vec![0..1, 1..2]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recv_batch_slots() {
+        let mut batch = RecvBatch::new(4, 100);
+        assert_eq!(batch.slots(), 4);
+        assert_eq!(batch.slot_size(), 100);
+        assert!(batch.is_empty());
+
+        let from: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        batch.slot_mut(2)[..3].copy_from_slice(b"abc");
+        batch.push(2, 3, from);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.get(0), Some((&b"abc"[..], from)));
+        assert_eq!(batch.get(1), None);
+
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn test_recv_batch_splits_segments() {
+        let mut batch = RecvBatch::new(1, 64);
+        let from: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        for (i, byte) in batch.slot_mut(0).iter_mut().enumerate() {
+            *byte = (i / 10) as u8;
+        }
+
+        batch.push_segments(0, 25, 10, from);
+        let lens: Vec<usize> = batch.iter().map(|(data, _)| data.len()).collect();
+        assert_eq!(lens, vec![10, 10, 5]);
+        assert!(batch.get(1).unwrap().0.iter().all(|&b| b == 1));
+
+        // Oversized lengths are truncated to the slot
+        batch.clear();
+        batch.push(0, 1000, from);
+        assert_eq!(batch.get(0).unwrap().0.len(), 64);
+    }
+}
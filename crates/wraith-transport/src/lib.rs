@@ -4,12 +4,14 @@
 //!
 //! This crate provides:
 //! - Transport trait abstraction for multiple backends
+//! - Batched datagram I/O (sendmmsg/recvmmsg, UDP GSO/GRO on Linux)
 //! - Async UDP transport using Tokio
 //! - TCP transport with length-prefixed framing
 //! - WebSocket transport for HTTP proxy traversal
 //! - QUIC transport using quinn (TLS 1.3, 0-RTT, connection migration)
 //! - Transport manager with multi-transport orchestration and migration
 //! - Transport factory for configuration-based creation
+//! - Deterministic simulated network for reproducible tests
 //! - AF_XDP socket management for zero-copy packet I/O (Linux-only)
 //! - io_uring integration for async file and network operations (Linux-only)
 //! - UDP socket fallback for non-Linux systems
@@ -19,8 +21,10 @@
 #![warn(clippy::all)]
 
 // Transport trait and implementations
+pub mod batch;
 pub mod factory;
 pub mod quic;
+pub mod simulated;
 pub mod tcp;
 pub mod transport;
 pub mod udp_async;
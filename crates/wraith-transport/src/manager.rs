@@ -11,6 +11,7 @@
 //! UDP to QUIC) without dropping packets. The manager coordinates the migration
 //! by briefly buffering sends during the transition.
 
+use crate::batch::RecvBatch;
 use crate::factory::TransportType;
 use crate::transport::{Transport, TransportError, TransportResult, TransportStats};
 use async_trait::async_trait;
@@ -199,6 +200,56 @@ impl TransportManager {
         }
     }
 
+    /// Get the registered transport of the given type, if any.
+    pub async fn transport(&self, ty: TransportType) -> Option<Arc<dyn Transport>> {
+        self.transports
+            .read()
+            .await
+            .iter()
+            .find(|t| t.transport_type() == ty)
+            .cloned()
+    }
+
+    /// Get all registered transports, primary first by registration order.
+    pub async fn transports(&self) -> Vec<Arc<dyn Transport>> {
+        self.transports.read().await.clone()
+    }
+
+    /// Send through the transport of the given type, bypassing the selector.
+    ///
+    /// Used when a peer is reachable over one specific transport, e.g. a
+    /// session that has migrated from UDP to TCP.
+    ///
+    /// # Errors
+    /// Returns `TransportError::Closed` if the manager is closed and
+    /// `TransportError::Other` if no transport of the requested type is
+    /// registered.
+    pub async fn send_via(
+        &self,
+        ty: TransportType,
+        buf: &[u8],
+        addr: SocketAddr,
+    ) -> TransportResult<usize> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(TransportError::Closed);
+        }
+        let transport = self
+            .transport(ty)
+            .await
+            .ok_or_else(|| TransportError::Other(format!("No transport of type {ty} available")))?;
+        transport.send_to(buf, addr).await
+    }
+
+    /// Get the local address of every registered transport.
+    pub async fn local_addrs(&self) -> Vec<(TransportType, SocketAddr)> {
+        self.transports
+            .read()
+            .await
+            .iter()
+            .filter_map(|t| Some((t.transport_type(), t.local_addr().ok()?)))
+            .collect()
+    }
+
     /// Get the number of registered transports.
     pub async fn transport_count(&self) -> usize {
         self.transports.read().await.len()
@@ -296,13 +347,33 @@ impl Transport for TransportManager {
         transport.recv_from(buf).await
     }
 
+    async fn send_batch(&self, datagrams: &[(&[u8], SocketAddr)]) -> TransportResult<usize> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(TransportError::Closed);
+        }
+        let transport = self.select_transport().await?;
+        transport.send_batch(datagrams).await
+    }
+
+    async fn recv_batch(&self, batch: &mut RecvBatch) -> TransportResult<usize> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(TransportError::Closed);
+        }
+        let transport = self.select_transport().await?;
+        transport.recv_batch(batch).await
+    }
+
     fn local_addr(&self) -> TransportResult<SocketAddr> {
-        // Use primary transport's address
-        // We need to block on the async read - use try_read for sync context
-        // Since this is called in sync context, we check primary index directly
-        Err(TransportError::Other(
-            "Use active transport's local_addr directly".to_string(),
-        ))
+        // Sync context: only available while no transport is being added
+        let transports = self
+            .transports
+            .try_read()
+            .map_err(|_| TransportError::Other("Transport list is being modified".to_string()))?;
+        let idx = self.primary_index.load(Ordering::Relaxed);
+        transports
+            .get(idx)
+            .ok_or_else(|| TransportError::Other("No transports available".to_string()))?
+            .local_addr()
     }
 
     async fn close(&self) -> TransportResult<()> {
@@ -498,6 +569,66 @@ mod tests {
         assert_eq!(manager.active_transport_type().await, TransportType::Udp);
     }
 
+    #[tokio::test]
+    async fn test_manager_transport_lookup() {
+        let udp = AsyncUdpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let udp_addr = udp.local_addr().unwrap();
+        let tcp = crate::tcp::TcpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let tcp_addr = tcp.local_addr().unwrap();
+
+        let manager = TransportManager::new(Arc::new(udp));
+        manager.add_transport(Arc::new(tcp)).await;
+
+        assert_eq!(manager.local_addr().unwrap(), udp_addr);
+        assert!(manager.transport(TransportType::Tcp).await.is_some());
+        assert!(manager.transport(TransportType::Quic).await.is_none());
+        assert_eq!(manager.transports().await.len(), 2);
+        assert_eq!(
+            manager.local_addrs().await,
+            vec![
+                (TransportType::Udp, udp_addr),
+                (TransportType::Tcp, tcp_addr)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manager_send_via() {
+        let server = crate::tcp::TcpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let server_addr = server.local_addr().unwrap();
+
+        let udp = AsyncUdpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let tcp = crate::tcp::TcpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let manager = TransportManager::new(Arc::new(udp));
+        manager.add_transport(Arc::new(tcp)).await;
+
+        manager
+            .send_via(TransportType::Tcp, b"over tcp", server_addr)
+            .await
+            .unwrap();
+        let mut buf = vec![0u8; 64];
+        let (size, _) = timeout(Duration::from_secs(2), server.recv_from(&mut buf))
+            .await
+            .expect("Timeout")
+            .unwrap();
+        assert_eq!(&buf[..size], b"over tcp");
+
+        let result = manager
+            .send_via(TransportType::WebSocket, b"x", server_addr)
+            .await;
+        assert!(matches!(result, Err(TransportError::Other(_))));
+    }
+
     #[tokio::test]
     async fn test_selector_default() {
         assert_eq!(TransportSelector::default(), TransportSelector::Primary);
@@ -0,0 +1,1111 @@
+//! Deterministic simulated network for testing.
+//!
+//! [`SimulatedNetwork`] is an in-process virtual network, and
+//! [`SimulatedTransport`] is an endpoint on it implementing the `Transport`
+//! trait. Links between addresses can add latency, jitter, a bandwidth
+//! limit with a bounded queue, loss, duplication and reordering, and drop
+//! datagrams larger than their MTU. Hosts can sit behind NAT gateways of
+//! any of the classic cone/symmetric types.
+//!
+//! Every random decision is drawn from one RNG seeded at construction, and
+//! delivery times come from Tokio's clock. A test on a current-thread
+//! runtime with the clock paused (`#[tokio::test(start_paused = true)]`,
+//! which needs Tokio's `test-util` feature) therefore replays identically
+//! for the same seed, however slow the machine running it.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use wraith_transport::simulated::{LinkConfig, NatType, SimulatedNetwork};
+//! use wraith_transport::transport::Transport;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let network = SimulatedNetwork::new(42);
+//! network.set_default_link(LinkConfig {
+//!     latency: Duration::from_millis(20),
+//!     loss: 0.01,
+//!     ..LinkConfig::default()
+//! });
+//! network.add_nat("203.0.113.1".parse()?, NatType::PortRestrictedCone);
+//!
+//! let server = network.bind("198.51.100.1:9000".parse()?)?;
+//! let client = network.bind_behind_nat("192.168.1.2:0".parse()?, "203.0.113.1".parse()?)?;
+//!
+//! client.send_to(b"hello", server.local_addr()?).await?;
+//! let mut buf = [0u8; 1500];
+//! let (len, from) = server.recv_from(&mut buf).await?;
+//! assert_eq!(from.ip(), "203.0.113.1".parse::<std::net::IpAddr>()?);
+//! # let _ = len;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::batch::RecvBatch;
+use crate::factory::TransportType;
+use crate::transport::{Transport, TransportError, TransportResult, TransportStats};
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// First port handed out for binds to port 0 and for NAT mappings
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Characteristics of a one-way link between two IP addresses
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkConfig {
+    /// One-way propagation delay
+    pub latency: Duration,
+    /// Largest extra delay, drawn uniformly for each datagram
+    pub jitter: Duration,
+    /// Link rate in bytes per second (`None` = unlimited)
+    pub bandwidth: Option<u64>,
+    /// Bytes that may wait for a rate-limited link before further datagrams
+    /// are tail-dropped (`None` = unbounded)
+    pub queue_limit: Option<usize>,
+    /// Probability that a datagram is lost, from 0 to 1
+    pub loss: f64,
+    /// Probability that a datagram is delivered twice
+    pub duplicate: f64,
+    /// Probability that a datagram is held back by `reorder_delay`, letting
+    /// later ones overtake it
+    pub reorder: f64,
+    /// Extra delay for reordered datagrams
+    pub reorder_delay: Duration,
+    /// Largest UDP payload the link carries; larger datagrams are dropped
+    pub mtu: usize,
+}
+
+impl Default for LinkConfig {
+    /// A perfect link: no delay, no loss and an Ethernet-sized MTU
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            bandwidth: None,
+            queue_limit: None,
+            loss: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            reorder_delay: Duration::from_millis(10),
+            mtu: 1472,
+        }
+    }
+}
+
+/// Mapping and filtering behavior of a simulated NAT gateway
+///
+/// Mirrors the types reported by NAT detection in `wraith-discovery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NatType {
+    /// One public port per internal address; anyone may send to it
+    FullCone,
+    /// One public port per internal address; only IPs the host has sent
+    /// to may send to it
+    RestrictedCone,
+    /// One public port per internal address; only IP:port pairs the host
+    /// has sent to may send to it
+    PortRestrictedCone,
+    /// A new public port for every destination; only that destination may
+    /// answer on it
+    Symmetric,
+}
+
+/// Datagram counters for a whole simulated network
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    /// Datagrams handed to the network
+    pub sent: u64,
+    /// Datagrams queued for a receiving endpoint, duplicates included
+    pub delivered: u64,
+    /// Datagrams dropped by random loss
+    pub lost: u64,
+    /// Extra copies delivered by duplication
+    pub duplicated: u64,
+    /// Datagrams delayed to arrive out of order
+    pub reordered: u64,
+    /// Datagrams dropped for exceeding the link MTU
+    pub dropped_mtu: u64,
+    /// Datagrams tail-dropped by a full link queue
+    pub dropped_queue: u64,
+    /// Datagrams rejected by a NAT's inbound filter
+    pub dropped_nat: u64,
+    /// Datagrams addressed to no bound endpoint
+    pub unreachable: u64,
+}
+
+/// A datagram waiting for its delivery time
+struct Queued {
+    deliver_at: Instant,
+    /// Send order, which breaks ties between equal delivery times
+    seq: u64,
+    from: SocketAddr,
+    data: Vec<u8>,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (self.deliver_at, self.seq).cmp(&(other.deliver_at, other.seq))
+    }
+}
+
+/// Receive queue shared by an endpoint and the network
+#[derive(Default)]
+struct Inbox {
+    queue: Mutex<BinaryHeap<Reverse<Queued>>>,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl Inbox {
+    fn queue(&self) -> MutexGuard<'_, BinaryHeap<Reverse<Queued>>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Pop the next datagram if its delivery time has come
+    fn try_pop(&self) -> Option<Queued> {
+        let mut queue = self.queue();
+        let ready = queue
+            .peek()
+            .is_some_and(|Reverse(next)| next.deliver_at <= Instant::now());
+        ready
+            .then(|| queue.pop().map(|Reverse(next)| next))
+            .flatten()
+    }
+
+    /// Wait for the next datagram
+    async fn pop(&self) -> TransportResult<Queued> {
+        loop {
+            if self.closed.load(Ordering::Relaxed) {
+                return Err(TransportError::Closed);
+            }
+            if let Some(datagram) = self.try_pop() {
+                return Ok(datagram);
+            }
+
+            let next = self.queue().peek().map(|Reverse(next)| next.deliver_at);
+            match next {
+                Some(deliver_at) => {
+                    tokio::select! {
+                        () = tokio::time::sleep_until(deliver_at) => {}
+                        () = self.notify.notified() => {}
+                    }
+                }
+                None => self.notify.notified().await,
+            }
+        }
+    }
+}
+
+/// A bound endpoint
+struct Host {
+    inbox: Arc<Inbox>,
+    /// Public IP of the NAT the host sits behind
+    nat: Option<IpAddr>,
+}
+
+/// Public port state of a NAT gateway
+struct NatMapping {
+    internal: SocketAddr,
+    /// Remote addresses the internal host has sent to through this port
+    contacted: HashSet<SocketAddr>,
+}
+
+/// A NAT gateway
+struct Nat {
+    nat_type: NatType,
+    next_port: u16,
+    /// Public port for each (internal address, destination) pair; the
+    /// destination is only part of the key for symmetric NATs
+    outbound: HashMap<(SocketAddr, Option<SocketAddr>), u16>,
+    inbound: HashMap<u16, NatMapping>,
+}
+
+impl Nat {
+    fn new(nat_type: NatType) -> Self {
+        Self {
+            nat_type,
+            next_port: EPHEMERAL_PORT_START,
+            outbound: HashMap::new(),
+            inbound: HashMap::new(),
+        }
+    }
+
+    /// Public port for a datagram from `internal` to `remote`, creating
+    /// the mapping if needed
+    fn map_outbound(&mut self, internal: SocketAddr, remote: SocketAddr) -> u16 {
+        let key = (
+            internal,
+            (self.nat_type == NatType::Symmetric).then_some(remote),
+        );
+        let port = match self.outbound.get(&key) {
+            Some(port) => *port,
+            None => {
+                let port = self.next_port;
+                self.next_port = self
+                    .next_port
+                    .checked_add(1)
+                    .unwrap_or(EPHEMERAL_PORT_START);
+                self.outbound.insert(key, port);
+                self.inbound.insert(
+                    port,
+                    NatMapping {
+                        internal,
+                        contacted: HashSet::new(),
+                    },
+                );
+                port
+            }
+        };
+        if let Some(mapping) = self.inbound.get_mut(&port) {
+            mapping.contacted.insert(remote);
+        }
+        port
+    }
+
+    /// Internal address for a datagram from `remote` to public `port`, if
+    /// the NAT lets it in
+    fn map_inbound(&self, port: u16, remote: SocketAddr) -> Option<SocketAddr> {
+        let mapping = self.inbound.get(&port)?;
+        let allowed = match self.nat_type {
+            NatType::FullCone => true,
+            NatType::RestrictedCone => mapping
+                .contacted
+                .iter()
+                .any(|addr| addr.ip() == remote.ip()),
+            NatType::PortRestrictedCone | NatType::Symmetric => mapping.contacted.contains(&remote),
+        };
+        allowed.then_some(mapping.internal)
+    }
+}
+
+/// Network state, guarded by one lock
+struct NetworkState {
+    rng: StdRng,
+    next_seq: u64,
+    default_link: LinkConfig,
+    links: HashMap<(IpAddr, IpAddr), LinkConfig>,
+    /// When each rate-limited link finishes sending what it has queued
+    busy_until: HashMap<(IpAddr, IpAddr), Instant>,
+    hosts: HashMap<SocketAddr, Host>,
+    nats: HashMap<IpAddr, Nat>,
+    stats: NetworkStats,
+}
+
+impl NetworkState {
+    /// Route a datagram from the endpoint bound at `from` to `to`
+    fn route(&mut self, from: SocketAddr, to: SocketAddr, data: &[u8]) {
+        self.stats.sent += 1;
+        let Some(sender_nat) = self.hosts.get(&from).map(|host| host.nat) else {
+            return;
+        };
+
+        // Hosts behind the same NAT reach each other directly; anything
+        // else leaves through the sender's NAT
+        let same_lan = sender_nat.is_some()
+            && self
+                .hosts
+                .get(&to)
+                .is_some_and(|host| host.nat == sender_nat);
+        let source = match sender_nat {
+            Some(public_ip) if !same_lan => match self.nats.get_mut(&public_ip) {
+                Some(nat) => SocketAddr::new(public_ip, nat.map_outbound(from, to)),
+                None => from,
+            },
+            _ => from,
+        };
+
+        let key = (source.ip(), to.ip());
+        let link = self.links.get(&key).unwrap_or(&self.default_link).clone();
+        let Some(deliver_at) = self.transmit(key, &link, data.len()) else {
+            return;
+        };
+
+        // Find the receiving endpoint
+        let target = match self.nats.get(&to.ip()) {
+            Some(nat) => match nat.map_inbound(to.port(), source) {
+                Some(internal) => internal,
+                None => {
+                    self.stats.dropped_nat += 1;
+                    return;
+                }
+            },
+            None => to,
+        };
+        let inbox = match self.hosts.get(&target) {
+            Some(host) if host.nat.is_none() || same_lan || target != to => Arc::clone(&host.inbox),
+            _ => {
+                self.stats.unreachable += 1;
+                return;
+            }
+        };
+
+        let copies = if self.rng.gen_bool(link.duplicate.clamp(0.0, 1.0)) {
+            self.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        let mut queue = inbox.queue();
+        for _ in 0..copies {
+            queue.push(Reverse(Queued {
+                deliver_at,
+                seq: self.next_seq,
+                from: source,
+                data: data.to_vec(),
+            }));
+            self.next_seq += 1;
+            self.stats.delivered += 1;
+        }
+        drop(queue);
+        inbox.notify.notify_one();
+    }
+
+    /// Put `len` bytes on a link, returning when they arrive, or `None` if
+    /// the link drops them
+    fn transmit(
+        &mut self,
+        key: (IpAddr, IpAddr),
+        link: &LinkConfig,
+        len: usize,
+    ) -> Option<Instant> {
+        if len > link.mtu {
+            self.stats.dropped_mtu += 1;
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut departure = now;
+        if let Some(bandwidth) = link.bandwidth.filter(|&rate| rate > 0) {
+            let start = self
+                .busy_until
+                .get(&key)
+                .copied()
+                .map_or(now, |busy| busy.max(now));
+            let bandwidth = u128::from(bandwidth);
+            let queued = (start - now).as_nanos() * bandwidth / NANOS_PER_SEC;
+            if link
+                .queue_limit
+                .is_some_and(|limit| queued + len as u128 > limit as u128)
+            {
+                self.stats.dropped_queue += 1;
+                return None;
+            }
+            let send_time = (len as u128 * NANOS_PER_SEC).div_ceil(bandwidth);
+            departure = start + Duration::from_nanos(u64::try_from(send_time).unwrap_or(u64::MAX));
+            self.busy_until.insert(key, departure);
+        }
+
+        if self.rng.gen_bool(link.loss.clamp(0.0, 1.0)) {
+            self.stats.lost += 1;
+            return None;
+        }
+
+        let mut delay = link.latency;
+        if !link.jitter.is_zero() {
+            let jitter = u64::try_from(link.jitter.as_nanos()).unwrap_or(u64::MAX);
+            delay += Duration::from_nanos(self.rng.gen_range(0..=jitter));
+        }
+        if self.rng.gen_bool(link.reorder.clamp(0.0, 1.0)) {
+            self.stats.reordered += 1;
+            delay += link.reorder_delay;
+        }
+        Some(departure + delay)
+    }
+
+    /// Pick a free port on `ip`
+    fn ephemeral_port(&self, ip: IpAddr) -> Option<u16> {
+        (EPHEMERAL_PORT_START..=u16::MAX)
+            .find(|&port| !self.hosts.contains_key(&SocketAddr::new(ip, port)))
+    }
+}
+
+/// An in-process virtual network of [`SimulatedTransport`] endpoints
+///
+/// Cloning shares the same network. Links not configured with
+/// [`set_link`](Self::set_link) use the default link. Links are keyed by
+/// the IP addresses a datagram carries on the wire, so a host behind a NAT
+/// is reached over the NAT's public address.
+#[derive(Clone)]
+pub struct SimulatedNetwork {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl SimulatedNetwork {
+    /// Create an empty network whose random decisions derive from `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(NetworkState {
+                rng: StdRng::seed_from_u64(seed),
+                next_seq: 0,
+                default_link: LinkConfig::default(),
+                links: HashMap::new(),
+                busy_until: HashMap::new(),
+                hosts: HashMap::new(),
+                nats: HashMap::new(),
+                stats: NetworkStats::default(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, NetworkState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the link used between addresses without their own link
+    pub fn set_default_link(&self, config: LinkConfig) {
+        self.state().default_link = config;
+    }
+
+    /// Set the link between `a` and `b`, in both directions
+    pub fn set_link(&self, a: IpAddr, b: IpAddr, config: LinkConfig) {
+        let mut state = self.state();
+        state.links.insert((a, b), config.clone());
+        state.links.insert((b, a), config);
+    }
+
+    /// Set the link from `from` to `to` only
+    pub fn set_link_one_way(&self, from: IpAddr, to: IpAddr, config: LinkConfig) {
+        self.state().links.insert((from, to), config);
+    }
+
+    /// Add a NAT gateway with public address `public_ip`
+    ///
+    /// Replaces any gateway already at that address, dropping its mappings.
+    pub fn add_nat(&self, public_ip: IpAddr, nat_type: NatType) {
+        self.state().nats.insert(public_ip, Nat::new(nat_type));
+    }
+
+    /// Drop every mapping of the NAT at `public_ip`, as a gateway reboot
+    /// or mapping timeout would
+    ///
+    /// Hosts behind it get new public ports on their next send, which
+    /// looks like a NAT rebinding to their peers.
+    ///
+    /// # Errors
+    /// Returns `TransportError::InvalidConfig` if there is no NAT at
+    /// `public_ip`
+    pub fn reset_nat(&self, public_ip: IpAddr) -> TransportResult<()> {
+        let mut state = self.state();
+        let nat = state
+            .nats
+            .get_mut(&public_ip)
+            .ok_or_else(|| TransportError::InvalidConfig(format!("no NAT at {public_ip}")))?;
+        nat.outbound.clear();
+        nat.inbound.clear();
+        Ok(())
+    }
+
+    /// Bind an endpoint on a public address
+    ///
+    /// Port 0 picks a free port.
+    ///
+    /// # Errors
+    /// Returns `TransportError::BindFailed` if the address is unspecified,
+    /// in use, or belongs to a NAT
+    pub fn bind(&self, addr: SocketAddr) -> TransportResult<SimulatedTransport> {
+        self.bind_host(addr, None)
+    }
+
+    /// Bind an endpoint on a private address behind the NAT at `public_ip`
+    ///
+    /// Port 0 picks a free port.
+    ///
+    /// # Errors
+    /// Returns `TransportError::InvalidConfig` if there is no NAT at
+    /// `public_ip`, or `TransportError::BindFailed` as for [`bind`](Self::bind)
+    pub fn bind_behind_nat(
+        &self,
+        addr: SocketAddr,
+        public_ip: IpAddr,
+    ) -> TransportResult<SimulatedTransport> {
+        self.bind_host(addr, Some(public_ip))
+    }
+
+    fn bind_host(
+        &self,
+        addr: SocketAddr,
+        nat: Option<IpAddr>,
+    ) -> TransportResult<SimulatedTransport> {
+        let mut state = self.state();
+        if let Some(public_ip) = nat
+            && !state.nats.contains_key(&public_ip)
+        {
+            return Err(TransportError::InvalidConfig(format!(
+                "no NAT at {public_ip}"
+            )));
+        }
+        if addr.ip().is_unspecified() {
+            return Err(TransportError::BindFailed(format!(
+                "{addr}: simulated endpoints need a specific IP"
+            )));
+        }
+        if state.nats.contains_key(&addr.ip()) {
+            return Err(TransportError::BindFailed(format!(
+                "{addr}: address belongs to a NAT"
+            )));
+        }
+
+        let mut local_addr = addr;
+        if addr.port() == 0 {
+            let port = state
+                .ephemeral_port(addr.ip())
+                .ok_or_else(|| TransportError::BindFailed(format!("{addr}: no free ports")))?;
+            local_addr.set_port(port);
+        } else if state.hosts.contains_key(&addr) {
+            return Err(TransportError::BindFailed(format!(
+                "{addr}: address in use"
+            )));
+        }
+
+        let inbox = Arc::new(Inbox::default());
+        state.hosts.insert(
+            local_addr,
+            Host {
+                inbox: Arc::clone(&inbox),
+                nat,
+            },
+        );
+        let (mtu, latency) = (state.default_link.mtu, state.default_link.latency);
+        Ok(SimulatedTransport {
+            network: self.clone(),
+            local_addr,
+            inbox,
+            stats: Mutex::new(TransportStats::default()),
+            mtu,
+            latency,
+        })
+    }
+
+    /// Datagram counters for the whole network
+    #[must_use]
+    pub fn stats(&self) -> NetworkStats {
+        self.state().stats.clone()
+    }
+}
+
+/// An endpoint on a [`SimulatedNetwork`]
+///
+/// Behaves like an unconnected UDP socket: sends never block, datagrams
+/// to unbound addresses vanish, and oversized receives are truncated.
+/// Dropping the transport unbinds its address.
+pub struct SimulatedTransport {
+    network: SimulatedNetwork,
+    local_addr: SocketAddr,
+    inbox: Arc<Inbox>,
+    stats: Mutex<TransportStats>,
+    /// Default link MTU when bound
+    mtu: usize,
+    /// Default link latency when bound
+    latency: Duration,
+}
+
+impl SimulatedTransport {
+    /// The network this endpoint is bound on
+    #[must_use]
+    pub fn network(&self) -> &SimulatedNetwork {
+        &self.network
+    }
+
+    fn stats_mut(&self) -> MutexGuard<'_, TransportStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Copy a datagram into `buf`, truncating it to fit
+    fn deliver(&self, datagram: &Queued, buf: &mut [u8]) -> usize {
+        let len = datagram.data.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram.data[..len]);
+        self.stats_mut().record_recv(len);
+        len
+    }
+
+    fn unbind(&self) {
+        let mut state = self.network.state();
+        if state
+            .hosts
+            .get(&self.local_addr)
+            .is_some_and(|host| Arc::ptr_eq(&host.inbox, &self.inbox))
+        {
+            state.hosts.remove(&self.local_addr);
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for SimulatedTransport {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> TransportResult<usize> {
+        if self.is_closed() {
+            return Err(TransportError::Closed);
+        }
+        self.network.state().route(self.local_addr, addr, buf);
+        self.stats_mut().record_send(buf.len());
+        Ok(buf.len())
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> TransportResult<(usize, SocketAddr)> {
+        let datagram = self.inbox.pop().await?;
+        Ok((self.deliver(&datagram, buf), datagram.from))
+    }
+
+    async fn recv_batch(&self, batch: &mut RecvBatch) -> TransportResult<usize> {
+        batch.clear();
+        let mut next = Some(self.inbox.pop().await?);
+        let mut slot = 0;
+        while let Some(datagram) = next {
+            let len = self.deliver(&datagram, batch.slot_mut(slot));
+            batch.push(slot, len, datagram.from);
+            slot += 1;
+            next = if slot < batch.slots() {
+                self.inbox.try_pop()
+            } else {
+                None
+            };
+        }
+        Ok(slot)
+    }
+
+    fn local_addr(&self) -> TransportResult<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    async fn close(&self) -> TransportResult<()> {
+        self.inbox.closed.store(true, Ordering::Relaxed);
+        self.unbind();
+        self.inbox.notify.notify_waiters();
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inbox.closed.load(Ordering::Relaxed)
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.stats_mut().clone()
+    }
+
+    fn transport_type(&self) -> TransportType {
+        TransportType::Udp
+    }
+
+    fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    fn latency_estimate(&self) -> Duration {
+        self.latency
+    }
+}
+
+impl Drop for SimulatedTransport {
+    fn drop(&mut self) {
+        self.unbind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    /// Receive everything that arrives within `window`
+    async fn drain(transport: &SimulatedTransport, window: Duration) -> Vec<(Vec<u8>, SocketAddr)> {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 2048];
+        let deadline = Instant::now() + window;
+        while let Ok(Ok((len, from))) =
+            tokio::time::timeout_at(deadline, transport.recv_from(&mut buf)).await
+        {
+            received.push((buf[..len].to_vec(), from));
+        }
+        received
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_send_recv() {
+        let network = SimulatedNetwork::new(1);
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:5000")).unwrap();
+        assert_eq!(a.local_addr().unwrap(), addr("10.0.0.1:49152"));
+
+        a.send_to(b"hello", b.local_addr().unwrap()).await.unwrap();
+        let mut buf = [0u8; 64];
+        let (len, from) = b.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"hello");
+        assert_eq!(from, a.local_addr().unwrap());
+
+        assert_eq!(a.stats().packets_sent, 1);
+        assert_eq!(b.stats().bytes_received, 5);
+        assert!(a.transport_type() == TransportType::Udp);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_bind_errors() {
+        let network = SimulatedNetwork::new(1);
+        let _a = network.bind(addr("10.0.0.1:5000")).unwrap();
+        assert!(matches!(
+            network.bind(addr("10.0.0.1:5000")),
+            Err(TransportError::BindFailed(_))
+        ));
+        assert!(matches!(
+            network.bind(addr("0.0.0.0:0")),
+            Err(TransportError::BindFailed(_))
+        ));
+        assert!(matches!(
+            network.bind_behind_nat(addr("192.168.0.2:0"), ip("203.0.113.1")),
+            Err(TransportError::InvalidConfig(_))
+        ));
+
+        network.add_nat(ip("203.0.113.1"), NatType::FullCone);
+        assert!(matches!(
+            network.bind(addr("203.0.113.1:0")),
+            Err(TransportError::BindFailed(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_close_unbinds() {
+        let network = SimulatedNetwork::new(1);
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:5000")).unwrap();
+
+        b.close().await.unwrap();
+        assert!(b.is_closed());
+        let mut buf = [0u8; 64];
+        assert!(matches!(
+            b.recv_from(&mut buf).await,
+            Err(TransportError::Closed)
+        ));
+        assert!(matches!(
+            b.send_to(b"x", a.local_addr().unwrap()).await,
+            Err(TransportError::Closed)
+        ));
+
+        a.send_to(b"lost", addr("10.0.0.2:5000")).await.unwrap();
+        assert_eq!(network.stats().unreachable, 1);
+
+        // The address is free again, also after a drop
+        let c = network.bind(addr("10.0.0.2:5000")).unwrap();
+        drop(c);
+        network.bind(addr("10.0.0.2:5000")).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_close_wakes_receiver() {
+        let network = SimulatedNetwork::new(1);
+        let a = Arc::new(network.bind(addr("10.0.0.1:0")).unwrap());
+
+        let receiver = Arc::clone(&a);
+        let task = tokio::spawn(async move {
+            let mut buf = [0u8; 64];
+            receiver.recv_from(&mut buf).await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        a.close().await.unwrap();
+        assert!(matches!(task.await.unwrap(), Err(TransportError::Closed)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_latency_and_jitter() {
+        let network = SimulatedNetwork::new(7);
+        network.set_default_link(LinkConfig {
+            latency: Duration::from_millis(50),
+            jitter: Duration::from_millis(10),
+            ..LinkConfig::default()
+        });
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+
+        let start = Instant::now();
+        a.send_to(b"ping", b.local_addr().unwrap()).await.unwrap();
+        let mut buf = [0u8; 64];
+        b.recv_from(&mut buf).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+        assert!(elapsed <= Duration::from_millis(60), "{elapsed:?}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_bandwidth_and_queue() {
+        let network = SimulatedNetwork::new(1);
+        // 100 KB/s: a 1000-byte datagram takes 10 ms, and at most 4000
+        // bytes may be waiting or on the wire
+        network.set_default_link(LinkConfig {
+            bandwidth: Some(100_000),
+            queue_limit: Some(4000),
+            ..LinkConfig::default()
+        });
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..10 {
+            a.send_to(&[0u8; 1000], b.local_addr().unwrap())
+                .await
+                .unwrap();
+        }
+        let mut buf = [0u8; 2048];
+        for i in 1..=4u32 {
+            b.recv_from(&mut buf).await.unwrap();
+            assert_eq!(start.elapsed(), Duration::from_millis(10) * i);
+        }
+        assert!(drain(&b, Duration::from_secs(1)).await.is_empty());
+
+        let stats = network.stats();
+        assert_eq!(stats.delivered, 4);
+        assert_eq!(stats.dropped_queue, 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_mtu() {
+        let network = SimulatedNetwork::new(1);
+        network.set_link(
+            ip("10.0.0.1"),
+            ip("10.0.0.2"),
+            LinkConfig {
+                mtu: 1200,
+                ..LinkConfig::default()
+            },
+        );
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+        let c = network.bind(addr("10.0.0.3:0")).unwrap();
+
+        a.send_to(&[1u8; 1300], b.local_addr().unwrap())
+            .await
+            .unwrap();
+        a.send_to(&[2u8; 1200], b.local_addr().unwrap())
+            .await
+            .unwrap();
+        a.send_to(&[3u8; 1300], c.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        let received = drain(&b, Duration::from_millis(10)).await;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].0.len(), 1200);
+        assert_eq!(drain(&c, Duration::from_millis(10)).await.len(), 1);
+        assert_eq!(network.stats().dropped_mtu, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_truncates_to_buffer() {
+        let network = SimulatedNetwork::new(1);
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+
+        a.send_to(&[9u8; 100], b.local_addr().unwrap())
+            .await
+            .unwrap();
+        let mut buf = [0u8; 10];
+        let (len, _) = b.recv_from(&mut buf).await.unwrap();
+        assert_eq!(len, 10);
+    }
+
+    /// Send 1000 numbered datagrams over an impaired link and return the
+    /// sequence numbers received
+    async fn impaired_run(seed: u64) -> (Vec<u32>, NetworkStats) {
+        let network = SimulatedNetwork::new(seed);
+        network.set_default_link(LinkConfig {
+            latency: Duration::from_millis(20),
+            jitter: Duration::from_millis(5),
+            loss: 0.1,
+            duplicate: 0.05,
+            reorder: 0.1,
+            ..LinkConfig::default()
+        });
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+
+        for i in 0..1000u32 {
+            a.send_to(&i.to_be_bytes(), b.local_addr().unwrap())
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let received = drain(&b, Duration::from_secs(1))
+            .await
+            .into_iter()
+            .map(|(data, _)| u32::from_be_bytes(data.try_into().unwrap()))
+            .collect();
+        (received, network.stats())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_impairments() {
+        let (received, stats) = impaired_run(3).await;
+
+        assert_eq!(stats.sent, 1000);
+        assert!((50..150).contains(&stats.lost), "{stats:?}");
+        assert!(stats.duplicated > 0 && stats.reordered > 0, "{stats:?}");
+        assert_eq!(received.len() as u64, stats.delivered);
+        assert_eq!(stats.delivered, 1000 - stats.lost + stats.duplicated);
+        assert!(received.windows(2).any(|pair| pair[0] > pair[1]));
+        assert!(received.windows(2).any(|pair| pair[0] == pair[1]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_same_seed_replays() {
+        let first = impaired_run(11).await;
+        let second = impaired_run(11).await;
+        let other = impaired_run(12).await;
+
+        assert_eq!(first, second);
+        assert_ne!(first.0, other.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_recv_batch() {
+        let network = SimulatedNetwork::new(1);
+        let a = network.bind(addr("10.0.0.1:0")).unwrap();
+        let b = network.bind(addr("10.0.0.2:0")).unwrap();
+
+        let datagrams: Vec<Vec<u8>> = (0..10u8).map(|i| vec![i; 100]).collect();
+        let batch: Vec<(&[u8], SocketAddr)> = datagrams
+            .iter()
+            .map(|data| (data.as_slice(), b.local_addr().unwrap()))
+            .collect();
+        assert_eq!(a.send_batch(&batch).await.unwrap(), 10);
+
+        let mut recv = RecvBatch::new(8, 1500);
+        assert_eq!(b.recv_batch(&mut recv).await.unwrap(), 8);
+        assert_eq!(b.recv_batch(&mut recv).await.unwrap(), 2);
+        let (data, from) = recv.get(1).unwrap();
+        assert_eq!(data, &[9u8; 100][..]);
+        assert_eq!(from, a.local_addr().unwrap());
+    }
+
+    /// Whether `peer` can reach a host behind `nat_type` after the host
+    /// sent to 198.51.100.1:9000
+    async fn nat_lets_in(nat_type: NatType, peer: &str) -> bool {
+        let network = SimulatedNetwork::new(1);
+        network.add_nat(ip("203.0.113.1"), nat_type);
+        let host = network
+            .bind_behind_nat(addr("192.168.1.2:4000"), ip("203.0.113.1"))
+            .unwrap();
+        let contacted = network.bind(addr("198.51.100.1:9000")).unwrap();
+        let peer = network.bind(addr(peer)).unwrap();
+
+        host.send_to(b"out", contacted.local_addr().unwrap())
+            .await
+            .unwrap();
+        let mapped = drain(&contacted, Duration::from_millis(1)).await[0].1;
+        assert_eq!(mapped.ip(), ip("203.0.113.1"));
+
+        peer.send_to(b"in", mapped).await.unwrap();
+        !drain(&host, Duration::from_millis(1)).await.is_empty()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_nat_filtering() {
+        // Another port on the contacted host
+        let same_ip = "198.51.100.1:9001";
+        // A host never contacted
+        let other_ip = "198.51.100.2:9000";
+
+        assert!(nat_lets_in(NatType::FullCone, same_ip).await);
+        assert!(nat_lets_in(NatType::FullCone, other_ip).await);
+        assert!(nat_lets_in(NatType::RestrictedCone, same_ip).await);
+        assert!(!nat_lets_in(NatType::RestrictedCone, other_ip).await);
+        assert!(!nat_lets_in(NatType::PortRestrictedCone, same_ip).await);
+        assert!(!nat_lets_in(NatType::Symmetric, same_ip).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_nat_mapping() {
+        let network = SimulatedNetwork::new(1);
+        network.add_nat(ip("203.0.113.1"), NatType::PortRestrictedCone);
+        network.add_nat(ip("203.0.113.2"), NatType::Symmetric);
+        let cone = network
+            .bind_behind_nat(addr("192.168.1.2:4000"), ip("203.0.113.1"))
+            .unwrap();
+        let symmetric = network
+            .bind_behind_nat(addr("192.168.2.2:4000"), ip("203.0.113.2"))
+            .unwrap();
+        let s1 = network.bind(addr("198.51.100.1:9000")).unwrap();
+        let s2 = network.bind(addr("198.51.100.2:9000")).unwrap();
+
+        // A cone NAT keeps one public port for all destinations, a
+        // symmetric one allocates a port per destination
+        for host in [&cone, &symmetric] {
+            host.send_to(b"a", s1.local_addr().unwrap()).await.unwrap();
+            host.send_to(b"b", s2.local_addr().unwrap()).await.unwrap();
+        }
+        let seen_by_s1 = drain(&s1, Duration::from_millis(1)).await;
+        let seen_by_s2 = drain(&s2, Duration::from_millis(1)).await;
+        assert_eq!(seen_by_s1[0].1, seen_by_s2[0].1);
+        assert_ne!(seen_by_s1[1].1, seen_by_s2[1].1);
+
+        // Replies reach the host through the mapping
+        s1.send_to(b"reply", seen_by_s1[0].1).await.unwrap();
+        let reply = drain(&cone, Duration::from_millis(1)).await;
+        assert_eq!(reply[0].1, s1.local_addr().unwrap());
+
+        // After a reset the host comes out on a fresh port, and the old
+        // mapping no longer lets replies in
+        network.reset_nat(ip("203.0.113.1")).unwrap();
+        cone.send_to(b"c", s2.local_addr().unwrap()).await.unwrap();
+        let rebound = drain(&s2, Duration::from_millis(1)).await;
+        assert_ne!(rebound[0].1, seen_by_s2[0].1);
+        s1.send_to(b"stale", seen_by_s1[0].1).await.unwrap();
+        assert!(drain(&cone, Duration::from_millis(1)).await.is_empty());
+        assert_eq!(network.stats().dropped_nat, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_simulated_private_addresses() {
+        let network = SimulatedNetwork::new(1);
+        network.add_nat(ip("203.0.113.1"), NatType::FullCone);
+        let a = network
+            .bind_behind_nat(addr("192.168.1.2:4000"), ip("203.0.113.1"))
+            .unwrap();
+        let b = network
+            .bind_behind_nat(addr("192.168.1.3:4000"), ip("203.0.113.1"))
+            .unwrap();
+        let outside = network.bind(addr("198.51.100.1:9000")).unwrap();
+
+        // Hosts on the same LAN talk directly
+        a.send_to(b"lan", b.local_addr().unwrap()).await.unwrap();
+        let received = drain(&b, Duration::from_millis(1)).await;
+        assert_eq!(received[0].1, a.local_addr().unwrap());
+
+        // Private addresses are unreachable from outside
+        outside
+            .send_to(b"x", a.local_addr().unwrap())
+            .await
+            .unwrap();
+        assert!(drain(&a, Duration::from_millis(1)).await.is_empty());
+        assert_eq!(network.stats().unreachable, 1);
+    }
+}
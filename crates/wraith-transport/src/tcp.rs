@@ -23,18 +23,28 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinSet;
 
 /// Maximum message size for TCP framing (16 MiB).
 const MAX_MESSAGE_SIZE: u32 = 16 * 1024 * 1024;
 
+/// Received messages buffered before connection readers apply backpressure.
+const RECV_QUEUE_CAPACITY: usize = 1024;
+
+/// A received message and the peer it came from.
+type Incoming = (Vec<u8>, SocketAddr);
+
 /// TCP transport with length-prefixed framing.
 ///
 /// This transport provides reliable, ordered delivery over TCP connections.
-/// It maintains a map of peer connections and accepts incoming connections
-/// via a bound listener.
+/// Every connection, whether accepted by the bound listener or opened by
+/// `send_to`, is read continuously in the background, so messages flow in
+/// both directions over a single connection. Replies to an accepted peer go
+/// back over the connection it opened.
 ///
 /// # Examples
 ///
@@ -51,14 +61,16 @@ const MAX_MESSAGE_SIZE: u32 = 16 * 1024 * 1024;
 /// # }
 /// ```
 pub struct TcpTransport {
-    listener: Arc<TcpListener>,
     local_addr: SocketAddr,
     closed: Arc<AtomicBool>,
-    /// Outbound connections keyed by peer address.
-    connections: Arc<Mutex<HashMap<SocketAddr, TcpStream>>>,
-    /// Buffer of received messages from accepted connections: (data, peer_addr).
-    #[allow(clippy::type_complexity)]
-    recv_queue: Arc<Mutex<Vec<(Vec<u8>, SocketAddr)>>>,
+    /// Write halves of open connections keyed by peer address.
+    connections: Arc<Mutex<HashMap<SocketAddr, OwnedWriteHalf>>>,
+    /// Messages read from all connections.
+    incoming: Mutex<mpsc::Receiver<Incoming>>,
+    /// Sender handed to connection readers (taken on close).
+    incoming_tx: Arc<std::sync::Mutex<Option<mpsc::Sender<Incoming>>>>,
+    /// Accept loop and connection reader tasks.
+    tasks: Arc<std::sync::Mutex<JoinSet<()>>>,
     bytes_sent: Arc<AtomicU64>,
     bytes_received: Arc<AtomicU64>,
     packets_sent: Arc<AtomicU64>,
@@ -84,36 +96,76 @@ impl TcpTransport {
             .local_addr()
             .map_err(|e| TransportError::BindFailed(e.to_string()))?;
 
-        Ok(Self {
-            listener: Arc::new(listener),
+        let (tx, rx) = mpsc::channel(RECV_QUEUE_CAPACITY);
+        let transport = Self {
             local_addr,
             closed: Arc::new(AtomicBool::new(false)),
             connections: Arc::new(Mutex::new(HashMap::new())),
-            recv_queue: Arc::new(Mutex::new(Vec::new())),
+            incoming: Mutex::new(rx),
+            incoming_tx: Arc::new(std::sync::Mutex::new(Some(tx))),
+            tasks: Arc::new(std::sync::Mutex::new(JoinSet::new())),
             bytes_sent: Arc::new(AtomicU64::new(0)),
             bytes_received: Arc::new(AtomicU64::new(0)),
             packets_sent: Arc::new(AtomicU64::new(0)),
             packets_received: Arc::new(AtomicU64::new(0)),
             send_errors: Arc::new(AtomicU64::new(0)),
             recv_errors: Arc::new(AtomicU64::new(0)),
-        })
+        };
+        transport.spawn_accept_loop(listener);
+        Ok(transport)
     }
 
-    /// Get or create a connection to the given peer address.
-    async fn get_or_connect(&self, addr: SocketAddr) -> TransportResult<()> {
-        let mut conns = self.connections.lock().await;
+    /// Handle for starting background readers on new connections.
+    fn readers(&self) -> ConnectionReaders {
+        ConnectionReaders {
+            connections: Arc::clone(&self.connections),
+            incoming_tx: Arc::clone(&self.incoming_tx),
+            tasks: Arc::clone(&self.tasks),
+            recv_errors: Arc::clone(&self.recv_errors),
+        }
+    }
+
+    /// Accept connections in the background and start reading each one.
+    fn spawn_accept_loop(&self, listener: TcpListener) {
+        let readers = self.readers();
+        let mut tasks = self.tasks.lock().expect("task set lock poisoned");
+        tasks.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer_addr)) => {
+                        let (reader, writer) = stream.into_split();
+                        readers.connections.lock().await.insert(peer_addr, writer);
+                        readers.spawn(reader, peer_addr);
+                    }
+                    Err(e) => {
+                        tracing::debug!("TCP accept failed: {}", e);
+                        readers.recv_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Open a connection to the given peer address unless one is open.
+    async fn get_or_connect(
+        &self,
+        conns: &mut HashMap<SocketAddr, OwnedWriteHalf>,
+        addr: SocketAddr,
+    ) -> TransportResult<()> {
         if conns.contains_key(&addr) {
             return Ok(());
         }
         let stream = TcpStream::connect(addr)
             .await
             .map_err(|e| TransportError::ConnectionFailed(e.to_string()))?;
-        conns.insert(addr, stream);
+        let (reader, writer) = stream.into_split();
+        conns.insert(addr, writer);
+        self.readers().spawn(reader, addr);
         Ok(())
     }
 
     /// Write a length-prefixed message to a TCP stream.
-    async fn write_framed(stream: &mut TcpStream, data: &[u8]) -> TransportResult<usize> {
+    async fn write_framed(stream: &mut OwnedWriteHalf, data: &[u8]) -> TransportResult<usize> {
         let len = data.len() as u32;
         stream
             .write_all(&len.to_be_bytes())
@@ -125,7 +177,7 @@ impl TcpTransport {
     }
 
     /// Read a length-prefixed message from a TCP stream.
-    async fn read_framed(stream: &mut TcpStream) -> TransportResult<Vec<u8>> {
+    async fn read_framed<R: AsyncRead + Unpin>(stream: &mut R) -> TransportResult<Vec<u8>> {
         let mut len_buf = [0u8; 4];
         stream
             .read_exact(&mut len_buf)
@@ -147,6 +199,61 @@ impl TcpTransport {
     }
 }
 
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.abort_all();
+        }
+    }
+}
+
+/// Shared state needed to read a connection in the background.
+struct ConnectionReaders {
+    connections: Arc<Mutex<HashMap<SocketAddr, OwnedWriteHalf>>>,
+    incoming_tx: Arc<std::sync::Mutex<Option<mpsc::Sender<Incoming>>>>,
+    tasks: Arc<std::sync::Mutex<JoinSet<()>>>,
+    recv_errors: Arc<AtomicU64>,
+}
+
+impl ConnectionReaders {
+    /// Forward every message read from `reader` until the connection ends.
+    fn spawn(&self, mut reader: OwnedReadHalf, peer_addr: SocketAddr) {
+        let Some(tx) = self
+            .incoming_tx
+            .lock()
+            .expect("incoming sender lock poisoned")
+            .clone()
+        else {
+            return;
+        };
+        let connections = Arc::clone(&self.connections);
+        let recv_errors = Arc::clone(&self.recv_errors);
+
+        let mut tasks = self.tasks.lock().expect("task set lock poisoned");
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(async move {
+            loop {
+                match TcpTransport::read_framed(&mut reader).await {
+                    Ok(data) => {
+                        if tx.send((data, peer_addr)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        if !matches!(&e, TransportError::Io(io) if io.kind() == std::io::ErrorKind::UnexpectedEof)
+                        {
+                            tracing::debug!("TCP connection to {} failed: {}", peer_addr, e);
+                            recv_errors.fetch_add(1, Ordering::Relaxed);
+                        }
+                        connections.lock().await.remove(&peer_addr);
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
 #[async_trait]
 impl Transport for TcpTransport {
     async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> TransportResult<usize> {
@@ -154,12 +261,12 @@ impl Transport for TcpTransport {
             return Err(TransportError::Closed);
         }
 
-        if let Err(e) = self.get_or_connect(addr).await {
+        let mut conns = self.connections.lock().await;
+        if let Err(e) = self.get_or_connect(&mut conns, addr).await {
             self.send_errors.fetch_add(1, Ordering::Relaxed);
             return Err(e);
         }
 
-        let mut conns = self.connections.lock().await;
         let stream = conns.get_mut(&addr).ok_or_else(|| {
             TransportError::ConnectionFailed("Connection not found after connect".to_string())
         })?;
@@ -184,36 +291,14 @@ impl Transport for TcpTransport {
             return Err(TransportError::Closed);
         }
 
-        // Check recv queue first
-        {
-            let mut queue = self.recv_queue.lock().await;
-            if let Some((data, addr)) = queue.pop() {
-                let len = data.len().min(buf.len());
-                buf[..len].copy_from_slice(&data[..len]);
-                self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
-                self.packets_received.fetch_add(1, Ordering::Relaxed);
-                return Ok((len, addr));
-            }
-        }
-
-        // Accept a new connection and read from it
-        let (mut stream, peer_addr) = self.listener.accept().await.map_err(TransportError::Io)?;
-
-        match Self::read_framed(&mut stream).await {
-            Ok(data) => {
-                let len = data.len().min(buf.len());
-                buf[..len].copy_from_slice(&data[..len]);
-                self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
-                self.packets_received.fetch_add(1, Ordering::Relaxed);
-                // Store the accepted connection for future use
-                self.connections.lock().await.insert(peer_addr, stream);
-                Ok((len, peer_addr))
-            }
-            Err(e) => {
-                self.recv_errors.fetch_add(1, Ordering::Relaxed);
-                Err(e)
-            }
-        }
+        let Some((data, addr)) = self.incoming.lock().await.recv().await else {
+            return Err(TransportError::Closed);
+        };
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        Ok((len, addr))
     }
 
     fn local_addr(&self) -> TransportResult<SocketAddr> {
@@ -222,6 +307,15 @@ impl Transport for TcpTransport {
 
     async fn close(&self) -> TransportResult<()> {
         self.closed.store(true, Ordering::Relaxed);
+        // Stop accepting and reading; pending receivers see the queue close
+        self.incoming_tx
+            .lock()
+            .expect("incoming sender lock poisoned")
+            .take();
+        self.tasks
+            .lock()
+            .expect("task set lock poisoned")
+            .abort_all();
         // Shutdown all connections
         let mut conns = self.connections.lock().await;
         for (_, mut stream) in conns.drain() {
@@ -337,6 +431,43 @@ mod tests {
         assert_eq!(&received, b"Hello TCP!");
     }
 
+    #[tokio::test]
+    async fn test_tcp_reply_over_accepted_connection() {
+        let server = TcpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = TcpTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+
+        // Several messages share the client's connection
+        client.send_to(b"one", server_addr).await.unwrap();
+        client.send_to(b"two", server_addr).await.unwrap();
+
+        let mut buf = vec![0u8; 64];
+        let mut from = None;
+        for expected in [&b"one"[..], b"two"] {
+            let (size, addr) = timeout(Duration::from_secs(2), server.recv_from(&mut buf))
+                .await
+                .expect("Timeout")
+                .unwrap();
+            assert_eq!(&buf[..size], expected);
+            from = Some(addr);
+        }
+
+        // The server answers over the connection the client opened
+        let from = from.unwrap();
+        assert_ne!(from, client.local_addr().unwrap());
+        server.send_to(b"reply", from).await.unwrap();
+        let (size, addr) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+            .await
+            .expect("Timeout")
+            .unwrap();
+        assert_eq!(&buf[..size], b"reply");
+        assert_eq!(addr, server_addr);
+    }
+
     #[tokio::test]
     async fn test_tcp_large_message() {
         let server_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
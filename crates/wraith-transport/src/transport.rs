@@ -9,6 +9,7 @@ use std::io;
 use std::net::SocketAddr;
 use std::time::Duration;
 
+use crate::batch::RecvBatch;
 use crate::factory::TransportType;
 
 /// Transport layer errors
@@ -99,6 +100,48 @@ pub trait Transport: Send + Sync {
     /// Returns `TransportError` if the receive operation fails
     async fn recv_from(&self, buf: &mut [u8]) -> TransportResult<(usize, SocketAddr)>;
 
+    /// Send several datagrams, in order.
+    ///
+    /// Transports that can submit many datagrams per syscall override this;
+    /// the default calls `send_to` for each one.
+    ///
+    /// # Returns
+    /// The number of datagrams sent. Fewer than `datagrams.len()` are sent
+    /// only if an error stopped the batch part-way; the caller may retry the
+    /// rest.
+    ///
+    /// # Errors
+    /// Returns `TransportError` if not even the first datagram could be sent
+    async fn send_batch(&self, datagrams: &[(&[u8], SocketAddr)]) -> TransportResult<usize> {
+        for (sent, (buf, addr)) in datagrams.iter().enumerate() {
+            if let Err(e) = self.send_to(buf, *addr).await {
+                if sent == 0 {
+                    return Err(e);
+                }
+                return Ok(sent);
+            }
+        }
+        Ok(datagrams.len())
+    }
+
+    /// Receive one or more datagrams into `batch`.
+    ///
+    /// Waits for at least one datagram, then takes whatever else is already
+    /// queued, up to the batch's capacity. The default receives a single
+    /// datagram with `recv_from`.
+    ///
+    /// # Returns
+    /// The number of datagrams now in `batch`
+    ///
+    /// # Errors
+    /// Returns `TransportError` if the receive operation fails
+    async fn recv_batch(&self, batch: &mut RecvBatch) -> TransportResult<usize> {
+        batch.clear();
+        let (len, addr) = self.recv_from(batch.slot_mut(0)).await?;
+        batch.push(0, len, addr);
+        Ok(1)
+    }
+
     /// Get the local address this transport is bound to.
     ///
     /// # Errors
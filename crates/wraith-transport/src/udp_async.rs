@@ -1,14 +1,21 @@
 //! Async UDP transport implementation.
 //!
 //! This module provides an async UDP transport implementation using Tokio
-//! that implements the `Transport` trait.
+//! that implements the `Transport` trait. On Linux, batched sends and
+//! receives use `sendmmsg`/`recvmmsg` and UDP GSO (see [`crate::batch`]).
 
+#[cfg(target_os = "linux")]
+use crate::batch::{self, RecvBatch};
 use crate::factory::TransportType;
 use crate::transport::{Transport, TransportError, TransportResult, TransportStats};
 use async_trait::async_trait;
 use std::net::SocketAddr;
+#[cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(target_os = "linux")]
+use tokio::io::Interest;
 use tokio::net::UdpSocket;
 
 /// Async UDP transport using Tokio.
@@ -47,6 +54,8 @@ pub struct AsyncUdpTransport {
     packets_received: Arc<AtomicU64>,
     send_errors: Arc<AtomicU64>,
     recv_errors: Arc<AtomicU64>,
+    /// Whether batched sends use UDP GSO
+    gso: Arc<AtomicBool>,
 }
 
 impl AsyncUdpTransport {
@@ -106,16 +115,7 @@ impl AsyncUdpTransport {
         let socket = UdpSocket::from_std(std_socket)
             .map_err(|e| TransportError::BindFailed(e.to_string()))?;
 
-        Ok(Self {
-            socket: Arc::new(socket),
-            closed: Arc::new(AtomicBool::new(false)),
-            bytes_sent: Arc::new(AtomicU64::new(0)),
-            bytes_received: Arc::new(AtomicU64::new(0)),
-            packets_sent: Arc::new(AtomicU64::new(0)),
-            packets_received: Arc::new(AtomicU64::new(0)),
-            send_errors: Arc::new(AtomicU64::new(0)),
-            recv_errors: Arc::new(AtomicU64::new(0)),
-        })
+        Ok(Self::from_socket(socket))
     }
 
     /// Create from an existing Tokio UdpSocket.
@@ -124,6 +124,11 @@ impl AsyncUdpTransport {
     /// * `socket` - An already-bound Tokio UdpSocket
     #[must_use]
     pub fn from_socket(socket: UdpSocket) -> Self {
+        #[cfg(target_os = "linux")]
+        let gso = batch::linux::gso_supported(socket.as_raw_fd());
+        #[cfg(not(target_os = "linux"))]
+        let gso = false;
+
         Self {
             socket: Arc::new(socket),
             closed: Arc::new(AtomicBool::new(false)),
@@ -133,8 +138,50 @@ impl AsyncUdpTransport {
             packets_received: Arc::new(AtomicU64::new(0)),
             send_errors: Arc::new(AtomicU64::new(0)),
             recv_errors: Arc::new(AtomicU64::new(0)),
+            gso: Arc::new(AtomicBool::new(gso)),
         }
     }
+
+    /// Whether batched sends coalesce datagrams with UDP GSO
+    ///
+    /// Detected at bind time; switched off if the kernel or NIC later
+    /// rejects a segmented send.
+    #[must_use]
+    pub fn gso_enabled(&self) -> bool {
+        self.gso.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable UDP GRO (Linux only)
+    ///
+    /// With GRO the kernel hands over consecutive datagrams of a flow as one
+    /// buffer. Once enabled, receive only with `recv_batch` and slots of at
+    /// least [`GRO_SLOT_SIZE`](crate::batch::GRO_SLOT_SIZE) bytes: `recv_from` would return the coalesced
+    /// datagrams as one.
+    ///
+    /// # Errors
+    /// Returns `TransportError` if the kernel does not support GRO or the
+    /// platform is not Linux
+    pub fn set_gro(&self, enable: bool) -> TransportResult<()> {
+        #[cfg(target_os = "linux")]
+        {
+            batch::linux::set_gro(self.socket.as_raw_fd(), enable).map_err(TransportError::Io)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = enable;
+            Err(TransportError::InvalidConfig(
+                "UDP GRO is only available on Linux".to_string(),
+            ))
+        }
+    }
+
+    /// Count a send of `datagrams`
+    fn record_sent(&self, datagrams: &[(&[u8], SocketAddr)]) {
+        let bytes: usize = datagrams.iter().map(|(buf, _)| buf.len()).sum();
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.packets_sent
+            .fetch_add(datagrams.len() as u64, Ordering::Relaxed);
+    }
 }
 
 #[async_trait]
@@ -176,6 +223,69 @@ impl Transport for AsyncUdpTransport {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    async fn send_batch(&self, datagrams: &[(&[u8], SocketAddr)]) -> TransportResult<usize> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(TransportError::Closed);
+        }
+        if datagrams.is_empty() {
+            return Ok(0);
+        }
+
+        let fd = self.socket.as_raw_fd();
+        let mut sent = 0;
+        while sent < datagrams.len() {
+            // Waits for writability whenever the socket buffer is full
+            let result = self
+                .socket
+                .async_io(Interest::WRITABLE, || {
+                    batch::linux::send(fd, &datagrams[sent..], &self.gso)
+                })
+                .await;
+            match result {
+                Ok(count) => {
+                    self.record_sent(&datagrams[sent..sent + count]);
+                    sent += count;
+                }
+                Err(e) => {
+                    self.send_errors.fetch_add(1, Ordering::Relaxed);
+                    if sent == 0 {
+                        return Err(TransportError::Io(e));
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn recv_batch(&self, batch: &mut RecvBatch) -> TransportResult<usize> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(TransportError::Closed);
+        }
+
+        let fd = self.socket.as_raw_fd();
+        let result = self
+            .socket
+            .async_io(Interest::READABLE, || batch::linux::recv(fd, batch))
+            .await;
+        match result {
+            Ok(count) => {
+                let bytes: usize = batch.iter().map(|(data, _)| data.len()).sum();
+                self.bytes_received
+                    .fetch_add(bytes as u64, Ordering::Relaxed);
+                self.packets_received
+                    .fetch_add(count as u64, Ordering::Relaxed);
+                Ok(count)
+            }
+            Err(e) => {
+                self.recv_errors.fetch_add(1, Ordering::Relaxed);
+                Err(TransportError::Io(e))
+            }
+        }
+    }
+
     fn local_addr(&self) -> TransportResult<SocketAddr> {
         self.socket.local_addr().map_err(TransportError::Io)
     }
@@ -217,6 +327,7 @@ impl Transport for AsyncUdpTransport {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::batch::RecvBatch;
     use std::time::Duration;
     use tokio::time::timeout;
 
@@ -498,4 +609,117 @@ mod tests {
         let result = transport.recv_from(&mut buf).await;
         assert!(matches!(result, Err(TransportError::Closed)));
     }
+
+    /// Receive batches until `count` datagrams arrived
+    async fn recv_datagrams(
+        transport: &AsyncUdpTransport,
+        batch: &mut RecvBatch,
+        count: usize,
+    ) -> Vec<(Vec<u8>, SocketAddr)> {
+        let mut received = Vec::new();
+        while received.len() < count {
+            timeout(Duration::from_secs(1), transport.recv_batch(batch))
+                .await
+                .expect("Timeout")
+                .unwrap();
+            received.extend(batch.iter().map(|(data, from)| (data.to_vec(), from)));
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_udp_send_recv_batch() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let server = AsyncUdpTransport::bind(addr).await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = AsyncUdpTransport::bind(addr).await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        // Equal-sized runs with a short tail, as a chunk stream produces
+        let payloads: Vec<Vec<u8>> = (0..100u8)
+            .map(|i| vec![i; if i % 10 == 9 { 300 } else { 1200 }])
+            .collect();
+        let datagrams: Vec<(&[u8], SocketAddr)> = payloads
+            .iter()
+            .map(|payload| (payload.as_slice(), server_addr))
+            .collect();
+
+        let sent = client.send_batch(&datagrams).await.unwrap();
+        assert_eq!(sent, payloads.len());
+
+        let mut batch = RecvBatch::new(16, 1500);
+        let received = recv_datagrams(&server, &mut batch, payloads.len()).await;
+        assert_eq!(received.len(), payloads.len());
+        for ((data, from), payload) in received.iter().zip(&payloads) {
+            assert_eq!(data, payload);
+            assert_eq!(*from, client_addr);
+        }
+
+        let bytes: u64 = payloads.iter().map(|p| p.len() as u64).sum();
+        assert_eq!(client.stats().packets_sent, 100);
+        assert_eq!(client.stats().bytes_sent, bytes);
+        assert_eq!(server.stats().packets_received, 100);
+        assert_eq!(server.stats().bytes_received, bytes);
+    }
+
+    #[tokio::test]
+    async fn test_udp_send_batch_to_several_peers() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let first = AsyncUdpTransport::bind(addr).await.unwrap();
+        let second = AsyncUdpTransport::bind(addr).await.unwrap();
+        let client = AsyncUdpTransport::bind(addr).await.unwrap();
+
+        let datagrams = [
+            (&b"one"[..], first.local_addr().unwrap()),
+            (&b"two"[..], second.local_addr().unwrap()),
+            (&b"three"[..], first.local_addr().unwrap()),
+        ];
+        assert_eq!(client.send_batch(&datagrams).await.unwrap(), 3);
+        assert_eq!(client.send_batch(&[]).await.unwrap(), 0);
+
+        let mut batch = RecvBatch::new(4, 1500);
+        let received = recv_datagrams(&first, &mut batch, 2).await;
+        assert_eq!(received[0].0, b"one");
+        assert_eq!(received[1].0, b"three");
+        let received = recv_datagrams(&second, &mut batch, 1).await;
+        assert_eq!(received[0].0, b"two");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_udp_recv_batch_with_gro() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let server = AsyncUdpTransport::bind(addr).await.unwrap();
+        if server.set_gro(true).is_err() {
+            // Kernel without UDP GRO
+            return;
+        }
+        let server_addr = server.local_addr().unwrap();
+        let client = AsyncUdpTransport::bind(addr).await.unwrap();
+
+        let payloads: Vec<Vec<u8>> = (0..40u8).map(|i| vec![i; 1000]).collect();
+        let datagrams: Vec<(&[u8], SocketAddr)> = payloads
+            .iter()
+            .map(|payload| (payload.as_slice(), server_addr))
+            .collect();
+        assert_eq!(client.send_batch(&datagrams).await.unwrap(), 40);
+
+        // Coalesced or not, every datagram comes back separately and in order
+        let mut batch = RecvBatch::new(4, crate::batch::GRO_SLOT_SIZE);
+        let received = recv_datagrams(&server, &mut batch, payloads.len()).await;
+        let received: Vec<Vec<u8>> = received.into_iter().map(|(data, _)| data).collect();
+        assert_eq!(received, payloads);
+    }
+
+    #[tokio::test]
+    async fn test_udp_batch_after_close() {
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let transport = AsyncUdpTransport::bind(addr).await.unwrap();
+        transport.close().await.unwrap();
+
+        let result = transport.send_batch(&[(&b"x"[..], addr)]).await;
+        assert!(matches!(result, Err(TransportError::Closed)));
+        let result = transport.recv_batch(&mut RecvBatch::new(1, 1500)).await;
+        assert!(matches!(result, Err(TransportError::Closed)));
+    }
 }
@@ -8,11 +8,13 @@
 //!
 //! The transport operates in listener mode, accepting WebSocket upgrade requests
 //! from incoming TCP connections. For outbound communication, it establishes
-//! WebSocket connections to peer addresses on demand.
+//! WebSocket connections to peer addresses on demand. Both kinds of connection
+//! carry messages in either direction.
 
 use crate::factory::TransportType;
 use crate::transport::{Transport, TransportError, TransportResult, TransportStats};
 use async_trait::async_trait;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -20,69 +22,29 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinSet;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, accept_async, connect_async};
 
-/// Type alias for a server-side WebSocket stream.
-type ServerWsStream = WebSocketStream<tokio::net::TcpStream>;
+/// WebSocket stream type shared by accepted and outbound connections.
+type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
 
-/// Type alias for a client-side WebSocket stream.
-type ClientWsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+/// Sending half of a WebSocket connection.
+type WsSink = SplitSink<WsStream, Message>;
 
-/// Wrapper enum to unify server and client WebSocket streams.
-enum WsStream {
-    /// Server-side accepted stream.
-    Server(ServerWsStream),
-    /// Client-side connected stream.
-    Client(ClientWsStream),
-}
+/// Received messages buffered before connection readers apply backpressure.
+const RECV_QUEUE_CAPACITY: usize = 1024;
 
-impl WsStream {
-    /// Send a binary message.
-    async fn send_binary(&mut self, data: Vec<u8>) -> Result<(), TransportError> {
-        let msg = Message::Binary(data);
-        match self {
-            Self::Server(s) => s.send(msg).await,
-            Self::Client(s) => s.send(msg).await,
-        }
-        .map_err(|e| TransportError::Other(format!("WebSocket send error: {e}")))
-    }
-
-    /// Receive the next binary message.
-    async fn recv_binary(&mut self) -> Result<Vec<u8>, TransportError> {
-        loop {
-            let msg = match self {
-                Self::Server(s) => s.next().await,
-                Self::Client(s) => s.next().await,
-            };
-            match msg {
-                Some(Ok(Message::Binary(data))) => return Ok(data),
-                Some(Ok(Message::Close(_))) | None => {
-                    return Err(TransportError::Closed);
-                }
-                Some(Ok(_)) => continue, // Skip non-binary messages (ping/pong/text)
-                Some(Err(e)) => {
-                    return Err(TransportError::Other(format!("WebSocket recv error: {e}")));
-                }
-            }
-        }
-    }
-
-    /// Close the stream.
-    async fn close_stream(&mut self) -> Result<(), TransportError> {
-        match self {
-            Self::Server(s) => s.close(None).await,
-            Self::Client(s) => s.close(None).await,
-        }
-        .map_err(|e| TransportError::Other(format!("WebSocket close error: {e}")))
-    }
-}
+/// A received message and the peer it came from.
+type Incoming = (Vec<u8>, SocketAddr);
 
 /// WebSocket transport for HTTP proxy traversal.
 ///
 /// This transport sends and receives data as binary WebSocket frames,
 /// making it suitable for environments where only HTTP/HTTPS traffic is allowed.
+/// Every connection is read continuously in the background, so replies to an
+/// accepted peer travel back over the connection it opened.
 ///
 /// # Examples
 ///
@@ -99,11 +61,16 @@ impl WsStream {
 /// # }
 /// ```
 pub struct WebSocketTransport {
-    listener: Arc<TcpListener>,
     local_addr: SocketAddr,
     closed: Arc<AtomicBool>,
-    /// Outbound WebSocket connections keyed by peer address.
-    connections: Arc<Mutex<HashMap<SocketAddr, WsStream>>>,
+    /// Sending halves of open WebSocket connections keyed by peer address.
+    connections: Arc<Mutex<HashMap<SocketAddr, WsSink>>>,
+    /// Messages read from all connections.
+    incoming: Mutex<mpsc::Receiver<Incoming>>,
+    /// Sender handed to connection readers (taken on close).
+    incoming_tx: Arc<std::sync::Mutex<Option<mpsc::Sender<Incoming>>>>,
+    /// Accept loop and connection reader tasks.
+    tasks: Arc<std::sync::Mutex<JoinSet<()>>>,
     bytes_sent: Arc<AtomicU64>,
     bytes_received: Arc<AtomicU64>,
     packets_sent: Arc<AtomicU64>,
@@ -129,18 +96,132 @@ impl WebSocketTransport {
             .local_addr()
             .map_err(|e| TransportError::BindFailed(e.to_string()))?;
 
-        Ok(Self {
-            listener: Arc::new(listener),
+        let (tx, rx) = mpsc::channel(RECV_QUEUE_CAPACITY);
+        let transport = Self {
             local_addr,
             closed: Arc::new(AtomicBool::new(false)),
             connections: Arc::new(Mutex::new(HashMap::new())),
+            incoming: Mutex::new(rx),
+            incoming_tx: Arc::new(std::sync::Mutex::new(Some(tx))),
+            tasks: Arc::new(std::sync::Mutex::new(JoinSet::new())),
             bytes_sent: Arc::new(AtomicU64::new(0)),
             bytes_received: Arc::new(AtomicU64::new(0)),
             packets_sent: Arc::new(AtomicU64::new(0)),
             packets_received: Arc::new(AtomicU64::new(0)),
             send_errors: Arc::new(AtomicU64::new(0)),
             recv_errors: Arc::new(AtomicU64::new(0)),
-        })
+        };
+        transport.spawn_accept_loop(listener);
+        Ok(transport)
+    }
+
+    /// Handle for starting background readers on new connections.
+    fn readers(&self) -> ConnectionReaders {
+        ConnectionReaders {
+            connections: Arc::clone(&self.connections),
+            incoming_tx: Arc::clone(&self.incoming_tx),
+            tasks: Arc::clone(&self.tasks),
+            recv_errors: Arc::clone(&self.recv_errors),
+        }
+    }
+
+    /// Accept connections in the background and start reading each one.
+    ///
+    /// The WebSocket upgrade runs in the connection's own task so a slow
+    /// client cannot stall the accept loop.
+    fn spawn_accept_loop(&self, listener: TcpListener) {
+        let readers = self.readers();
+        let mut tasks = self.tasks.lock().expect("task set lock poisoned");
+        tasks.spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((tcp_stream, peer_addr)) => readers.accept(tcp_stream, peer_addr),
+                    Err(e) => {
+                        tracing::debug!("WebSocket accept failed: {}", e);
+                        readers.recv_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.abort_all();
+        }
+    }
+}
+
+/// Shared state needed to read a connection in the background.
+#[derive(Clone)]
+struct ConnectionReaders {
+    connections: Arc<Mutex<HashMap<SocketAddr, WsSink>>>,
+    incoming_tx: Arc<std::sync::Mutex<Option<mpsc::Sender<Incoming>>>>,
+    tasks: Arc<std::sync::Mutex<JoinSet<()>>>,
+    recv_errors: Arc<AtomicU64>,
+}
+
+impl ConnectionReaders {
+    /// Upgrade an accepted TCP connection and read it in the background.
+    fn accept(&self, tcp_stream: tokio::net::TcpStream, peer_addr: SocketAddr) {
+        let readers = self.clone();
+        self.spawn_task(async move {
+            match accept_async(MaybeTlsStream::Plain(tcp_stream)).await {
+                Ok(ws) => {
+                    let (sink, stream) = ws.split();
+                    readers.connections.lock().await.insert(peer_addr, sink);
+                    readers.read(stream, peer_addr).await;
+                }
+                Err(e) => {
+                    tracing::debug!("WebSocket upgrade from {} failed: {}", peer_addr, e);
+                    readers.recv_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    /// Read an outbound connection in the background.
+    fn spawn(&self, stream: SplitStream<WsStream>, peer_addr: SocketAddr) {
+        let readers = self.clone();
+        self.spawn_task(async move { readers.read(stream, peer_addr).await });
+    }
+
+    fn spawn_task(&self, task: impl std::future::Future<Output = ()> + Send + 'static) {
+        let mut tasks = self.tasks.lock().expect("task set lock poisoned");
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(task);
+    }
+
+    /// Forward every binary message from `stream` until the connection ends.
+    async fn read(&self, mut stream: SplitStream<WsStream>, peer_addr: SocketAddr) {
+        let Some(tx) = self
+            .incoming_tx
+            .lock()
+            .expect("incoming sender lock poisoned")
+            .clone()
+        else {
+            return;
+        };
+
+        loop {
+            match stream.next().await {
+                Some(Ok(Message::Binary(data))) => {
+                    if tx.send((data, peer_addr)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {} // Skip non-binary messages (ping/pong/text)
+                Some(Err(e)) => {
+                    tracing::debug!("WebSocket connection to {} failed: {}", peer_addr, e);
+                    self.recv_errors.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
+        self.connections.lock().await.remove(&peer_addr);
     }
 }
 
@@ -156,10 +237,13 @@ impl Transport for WebSocketTransport {
         // Connect if not already connected
         if let std::collections::hash_map::Entry::Vacant(e) = conns.entry(addr) {
             let url = format!("ws://{addr}");
-            let (ws_stream, _) = connect_async(&url)
-                .await
-                .map_err(|e| TransportError::ConnectionFailed(format!("WebSocket connect: {e}")))?;
-            e.insert(WsStream::Client(ws_stream));
+            let (ws_stream, _) = connect_async(&url).await.map_err(|e| {
+                self.send_errors.fetch_add(1, Ordering::Relaxed);
+                TransportError::ConnectionFailed(format!("WebSocket connect: {e}"))
+            })?;
+            let (sink, stream) = ws_stream.split();
+            e.insert(sink);
+            self.readers().spawn(stream, addr);
         }
 
         let ws = conns
@@ -167,7 +251,7 @@ impl Transport for WebSocketTransport {
             .ok_or_else(|| TransportError::ConnectionFailed("Connection not found".to_string()))?;
 
         let len = buf.len();
-        match ws.send_binary(buf.to_vec()).await {
+        match ws.send(Message::Binary(buf.to_vec())).await {
             Ok(()) => {
                 self.bytes_sent.fetch_add(len as u64, Ordering::Relaxed);
                 self.packets_sent.fetch_add(1, Ordering::Relaxed);
@@ -176,7 +260,7 @@ impl Transport for WebSocketTransport {
             Err(e) => {
                 self.send_errors.fetch_add(1, Ordering::Relaxed);
                 conns.remove(&addr);
-                Err(e)
+                Err(TransportError::Other(format!("WebSocket send error: {e}")))
             }
         }
     }
@@ -186,30 +270,14 @@ impl Transport for WebSocketTransport {
             return Err(TransportError::Closed);
         }
 
-        // Accept a new connection and perform WebSocket handshake
-        let (tcp_stream, peer_addr) = self.listener.accept().await.map_err(TransportError::Io)?;
-
-        let ws_stream = accept_async(tcp_stream)
-            .await
-            .map_err(|e| TransportError::Other(format!("WebSocket accept error: {e}")))?;
-
-        let mut ws = WsStream::Server(ws_stream);
-
-        match ws.recv_binary().await {
-            Ok(data) => {
-                let len = data.len().min(buf.len());
-                buf[..len].copy_from_slice(&data[..len]);
-                self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
-                self.packets_received.fetch_add(1, Ordering::Relaxed);
-                // Store connection
-                self.connections.lock().await.insert(peer_addr, ws);
-                Ok((len, peer_addr))
-            }
-            Err(e) => {
-                self.recv_errors.fetch_add(1, Ordering::Relaxed);
-                Err(e)
-            }
-        }
+        let Some((data, addr)) = self.incoming.lock().await.recv().await else {
+            return Err(TransportError::Closed);
+        };
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.bytes_received.fetch_add(len as u64, Ordering::Relaxed);
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        Ok((len, addr))
     }
 
     fn local_addr(&self) -> TransportResult<SocketAddr> {
@@ -218,9 +286,18 @@ impl Transport for WebSocketTransport {
 
     async fn close(&self) -> TransportResult<()> {
         self.closed.store(true, Ordering::Relaxed);
+        // Stop accepting and reading; pending receivers see the queue close
+        self.incoming_tx
+            .lock()
+            .expect("incoming sender lock poisoned")
+            .take();
+        self.tasks
+            .lock()
+            .expect("task set lock poisoned")
+            .abort_all();
         let mut conns = self.connections.lock().await;
         for (_, mut ws) in conns.drain() {
-            let _ = ws.close_stream().await;
+            let _ = ws.close().await;
         }
         Ok(())
     }
@@ -321,6 +398,39 @@ mod tests {
         assert_eq!(&received, b"Hello WS!");
     }
 
+    #[tokio::test]
+    async fn test_ws_reply_over_accepted_connection() {
+        let server = WebSocketTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+        let server_addr = server.local_addr().unwrap();
+        let client = WebSocketTransport::bind("127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .await
+            .unwrap();
+
+        client.send_to(b"one", server_addr).await.unwrap();
+        client.send_to(b"two", server_addr).await.unwrap();
+
+        let mut buf = vec![0u8; 64];
+        let mut from = None;
+        for expected in [&b"one"[..], b"two"] {
+            let (size, addr) = timeout(Duration::from_secs(2), server.recv_from(&mut buf))
+                .await
+                .expect("Timeout")
+                .unwrap();
+            assert_eq!(&buf[..size], expected);
+            from = Some(addr);
+        }
+
+        server.send_to(b"reply", from.unwrap()).await.unwrap();
+        let (size, addr) = timeout(Duration::from_secs(2), client.recv_from(&mut buf))
+            .await
+            .expect("Timeout")
+            .unwrap();
+        assert_eq!(&buf[..size], b"reply");
+        assert_eq!(addr, server_addr);
+    }
+
     #[tokio::test]
     async fn test_ws_stats() {
         let server_addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
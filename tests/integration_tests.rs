@@ -1848,7 +1848,7 @@ async fn test_noise_handshake_loopback() {
 
     // 4. Establish session from node1 to node2 using known address
     let session_id = node1
-        .establish_session_with_addr(node2.node_id(), node2_addr)
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
         .await
         .unwrap();
 
@@ -1874,6 +1874,1219 @@ async fn test_noise_handshake_loopback() {
     node2.stop().await.unwrap();
 }
 
+/// Test that dialing with the wrong expected peer ID fails the handshake
+///
+/// The initiator must abort before sending msg3 when the responder's static
+/// key differs from the expected peer ID, and must not record a session. An
+/// unverified dial to the same address reports the responder's real key.
+#[tokio::test]
+async fn test_handshake_rejects_unexpected_peer_key() {
+    use wraith_core::node::{Node, NodeError};
+
+    let node1 = Node::new_random_with_port(0).await.unwrap();
+    let node2 = Node::new_random_with_port(0).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+    let node2_addr = node2.listen_addr().await.unwrap();
+
+    let wrong_id = [0x5a; 32];
+    let err = node1
+        .establish_session_with_addr(&wrong_id, node2_addr)
+        .await
+        .unwrap_err();
+    match err {
+        NodeError::PeerKeyMismatch {
+            expected,
+            presented,
+        } => {
+            assert_eq!(expected, wrong_id);
+            assert_eq!(&presented, node2.x25519_public_key());
+        }
+        other => panic!("expected PeerKeyMismatch, got {other:?}"),
+    }
+    assert!(node1.active_sessions().await.is_empty());
+
+    // node2 keeps waiting for node1's msg3 until its handshake times out, so
+    // dial unverified from a fresh node
+    let node3 = Node::new_random_with_port(0).await.unwrap();
+    node3.start().await.unwrap();
+    let peer_id = node3
+        .establish_session_with_unverified_addr(node2_addr)
+        .await
+        .unwrap();
+    assert_eq!(&peer_id, node2.x25519_public_key());
+    assert_eq!(node3.active_sessions().await, vec![peer_id]);
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+    node3.stop().await.unwrap();
+}
+
+/// Test hybrid post-quantum suite negotiation between two nodes (loopback)
+///
+/// With default configuration both nodes allow Suites A and B (X25519 +
+/// ML-KEM-768) and must select one of them over the classical Suite D:
+/// Suite B (AES-256-GCM) on CPUs with AES hardware, Suite A otherwise.
+#[tokio::test]
+async fn test_hybrid_suite_negotiation_loopback() {
+    use wraith_core::node::{CryptoSuite, Node};
+
+    let expected = if wraith_crypto::aead::gcm::hardware_accelerated() {
+        CryptoSuite::SuiteB
+    } else {
+        CryptoSuite::SuiteA
+    };
+
+    let node1 = Node::new_random_with_port(0).await.unwrap();
+    let node2 = Node::new_random_with_port(0).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+
+    let node2_addr = node2.listen_addr().await.unwrap();
+    node1
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(
+        node1.get_session_crypto_suite(node2.x25519_public_key()),
+        Some(expected)
+    );
+    assert_eq!(
+        node2.get_session_crypto_suite(node1.x25519_public_key()),
+        Some(expected)
+    );
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+}
+
+/// Test an Elligator2-encoded handshake between two nodes (loopback)
+///
+/// Only the initiator enables the encoding; the responder detects it from
+/// msg1 and answers in kind.
+#[tokio::test]
+async fn test_elligator_handshake_loopback() {
+    use wraith_core::node::{Node, NodeConfig};
+
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    let responder_config = config.clone();
+    config.obfuscation.elligator_handshake = true;
+
+    let node1 = Node::new_with_config(config).await.unwrap();
+    let node2 = Node::new_with_config(responder_config).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+
+    let node2_addr = node2.listen_addr().await.unwrap();
+    node1
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let suite = node1.get_session_crypto_suite(node2.x25519_public_key());
+    assert!(suite.is_some());
+    assert_eq!(
+        node2.get_session_crypto_suite(node1.x25519_public_key()),
+        suite
+    );
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+}
+
+/// Test a file transfer over an AES-256-GCM (Suite B) session
+///
+/// Both nodes only allow Suite B, so this runs on the software AES fallback
+/// where the CPU has no AES hardware.
+#[tokio::test]
+async fn test_suite_b_file_transfer() {
+    use wraith_core::node::{CryptoSuite, Node, NodeConfig};
+
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    config.crypto.suites = vec![CryptoSuite::SuiteB];
+    // Chunks must fit a single v2 frame
+    config.transfer.chunk_size = 8 * 1024;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut receiver_config = config.clone();
+    receiver_config.transfer.download_dir = temp_dir.path().join("downloads");
+    std::fs::create_dir_all(&receiver_config.transfer.download_dir).unwrap();
+
+    let sender = Node::new_with_config(config).await.unwrap();
+    let receiver = Node::new_with_config(receiver_config).await.unwrap();
+    sender.start().await.unwrap();
+    receiver.start().await.unwrap();
+
+    let receiver_addr = receiver.listen_addr().await.unwrap();
+    sender
+        .establish_session_with_addr(receiver.x25519_public_key(), receiver_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(
+        sender.get_session_crypto_suite(receiver.x25519_public_key()),
+        Some(CryptoSuite::SuiteB)
+    );
+
+    let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let path = temp_dir.path().join("suite_b.bin");
+    std::fs::write(&path, &data).unwrap();
+    let transfer_id = sender
+        .send_file(&path, receiver.x25519_public_key())
+        .await
+        .unwrap();
+    tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("transfer timed out")
+    .unwrap();
+
+    let received_path = temp_dir.path().join("downloads").join("suite_b.bin");
+    let received = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            if let Ok(received) = std::fs::read(&received_path)
+                && received.len() == data.len()
+            {
+                return received;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("received file never completed");
+    assert!(received == data, "received file differs from the original");
+
+    sender.stop().await.unwrap();
+    receiver.stop().await.unwrap();
+}
+
+/// Test that a post-quantum-only node refuses a classical-only peer
+#[tokio::test]
+async fn test_pq_only_node_refuses_classical_peer() {
+    use wraith_core::node::{CryptoSuite, Node, NodeConfig};
+
+    let mut pq_only = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    pq_only.crypto.suites = vec![CryptoSuite::SuiteA];
+    let mut classical_only = pq_only.clone();
+    classical_only.crypto.suites = vec![CryptoSuite::SuiteD];
+
+    let node1 = Node::new_with_config(pq_only).await.unwrap();
+    let node2 = Node::new_with_config(classical_only).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+
+    let node2_addr = node2.listen_addr().await.unwrap();
+    let result = node1
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+        .await;
+
+    assert!(result.is_err());
+    assert!(node1.active_sessions().await.is_empty());
+    assert!(node2.active_sessions().await.is_empty());
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+}
+
+/// Send a file that `receiver` refuses and check the rejection reaches `sender`
+///
+/// The StreamOpen metadata frame and the StreamReset verdict travel over the
+/// live data path, so this proves frames flow in both directions.
+async fn assert_transfer_rejected(
+    sender: &wraith_core::node::Node,
+    receiver: &wraith_core::node::Node,
+    dir: &std::path::Path,
+) {
+    use std::sync::Arc;
+    use wraith_core::node::TrustedPeers;
+
+    receiver
+        .set_transfer_acceptor(Arc::new(TrustedPeers::new([])))
+        .await;
+    let path = dir.join(format!("{}.bin", hex::encode(&sender.node_id()[..4])));
+    std::fs::write(&path, vec![0x5A; 4096]).unwrap();
+
+    let transfer_id = sender
+        .send_file(&path, receiver.x25519_public_key())
+        .await
+        .unwrap();
+    let result = tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("rejection never reached the sender");
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("rejected"), "unexpected error: {err}");
+}
+
+/// A peer speaking the protocol as it was before suite negotiation
+///
+/// It handshakes with plain Noise_XX and empty payloads, derives keys from the
+/// handshake hash and exchanges v1 frames sealed with implicit packet counters,
+/// all over a raw UDP socket.
+struct BaselinePeer {
+    socket: tokio::net::UdpSocket,
+    node_addr: std::net::SocketAddr,
+    peer_id: [u8; 32],
+    cid: [u8; 8],
+    crypto: SessionCrypto,
+}
+
+impl BaselinePeer {
+    /// Complete a baseline handshake with `node`
+    async fn connect(node: &wraith_core::node::Node) -> Self {
+        use wraith_crypto::noise::{NoiseHandshake, NoiseKeypair};
+
+        let node_addr = node.listen_addr().await.unwrap();
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let keypair = NoiseKeypair::generate().unwrap();
+        let mut noise = NoiseHandshake::new_initiator(&keypair).unwrap();
+        let msg1 = noise.write_message(&[]).unwrap();
+        socket.send_to(&msg1, node_addr).await.unwrap();
+        let msg2 = Self::recv_packet(&socket).await;
+        let payload2 = noise.read_message(&msg2).unwrap();
+        assert!(
+            payload2.is_empty(),
+            "node answered a baseline peer with a suite selection"
+        );
+        assert_eq!(
+            noise.get_remote_static().unwrap(),
+            *node.x25519_public_key()
+        );
+        let msg3 = noise.write_message(&[]).unwrap();
+        socket.send_to(&msg3, node_addr).await.unwrap();
+
+        let keys = noise.into_session_keys().unwrap();
+        let peer = Self {
+            cid: keys.derive_connection_id(),
+            crypto: SessionCrypto::new(keys.send_key, keys.recv_key, &keys.chain_key),
+            peer_id: *keypair.public_key(),
+            socket,
+            node_addr,
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        peer
+    }
+
+    /// Receive one datagram, failing the test if the node stays silent
+    async fn recv_packet(socket: &tokio::net::UdpSocket) -> Vec<u8> {
+        let mut buf = vec![0u8; 65536];
+        let (len, _) = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            socket.recv_from(&mut buf),
+        )
+        .await
+        .expect("node never answered")
+        .unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    /// Seal a v1 frame and send it as connection ID || ciphertext
+    async fn send_frame(&mut self, frame: &[u8]) {
+        let mut packet = self.cid.to_vec();
+        packet.extend_from_slice(&self.crypto.encrypt(frame, &[]).unwrap());
+        self.socket.send_to(&packet, self.node_addr).await.unwrap();
+    }
+
+    /// Receive and open the next frame from the node
+    async fn recv_frame(&mut self) -> Vec<u8> {
+        let packet = Self::recv_packet(&self.socket).await;
+        assert_eq!(packet[..8], self.cid);
+        self.crypto.decrypt(&packet[8..], &[]).unwrap()
+    }
+
+    /// Send a file the way a baseline node does
+    ///
+    /// StreamOpen carries the metadata on the stream named by the first two
+    /// bytes of the transfer ID, then every chunk follows as a Data frame
+    /// without waiting for a verdict.
+    async fn send_file(
+        &mut self,
+        transfer_id: [u8; 32],
+        name: &str,
+        data: &[u8],
+        chunk_size: usize,
+    ) {
+        use wraith_core::node::FileMetadata;
+
+        let stream_id = u16::from_be_bytes([transfer_id[0], transfer_id[1]]);
+        let metadata = FileMetadata {
+            transfer_id,
+            file_name: name.to_string(),
+            file_size: data.len() as u64,
+            chunk_size: chunk_size as u32,
+            total_chunks: data.len().div_ceil(chunk_size) as u64,
+            root_hash: wraith_files::tree_hash::compute_tree_hash_from_data(data, chunk_size).root,
+        };
+        let payload = metadata.serialize();
+        let open = FrameBuilder::new()
+            .frame_type(FrameType::StreamOpen)
+            .stream_id(stream_id)
+            .payload(&payload)
+            .build(FRAME_HEADER_SIZE + payload.len())
+            .unwrap();
+        self.send_frame(&open).await;
+        // Packets are handled concurrently: let the node register the transfer
+        // first, as the gap while a baseline sender opens the file would
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        for (index, chunk) in data.chunks(chunk_size).enumerate() {
+            let frame = FrameBuilder::new()
+                .frame_type(FrameType::Data)
+                .stream_id(stream_id)
+                .sequence(index as u32)
+                .offset((index * chunk_size) as u64)
+                .payload(chunk)
+                .build(FRAME_HEADER_SIZE + chunk.len())
+                .unwrap();
+            self.send_frame(&frame).await;
+        }
+    }
+
+    /// Receive a file the way a baseline node does
+    ///
+    /// StreamOpen carries the metadata and every chunk follows as a Data frame
+    /// whose sequence number is its index. No verdict is sent back.
+    async fn receive_file(&mut self) -> (wraith_core::node::FileMetadata, Vec<u8>) {
+        use wraith_core::node::FileMetadata;
+
+        let mut open: Option<(u16, FileMetadata)> = None;
+        let mut chunks = std::collections::BTreeMap::new();
+        loop {
+            if let Some((_, metadata)) = &open
+                && chunks.len() as u64 == metadata.total_chunks
+            {
+                break;
+            }
+            let plaintext = self.recv_frame().await;
+            let frame = Frame::parse(&plaintext).expect("node sent a frame that is not v1");
+            match frame.frame_type() {
+                FrameType::StreamOpen => {
+                    let metadata = FileMetadata::deserialize(frame.payload()).unwrap();
+                    open = Some((frame.stream_id(), metadata));
+                }
+                FrameType::Data => {
+                    let (stream_id, _) = open.as_ref().expect("chunk before StreamOpen");
+                    assert_eq!(frame.stream_id(), *stream_id);
+                    chunks.insert(frame.sequence(), frame.payload().to_vec());
+                }
+                _ => {}
+            }
+        }
+
+        let (_, metadata) = open.unwrap();
+        (metadata, chunks.into_values().flatten().collect())
+    }
+}
+
+/// Test sending a file to a baseline peer
+///
+/// Baseline receivers never send TRANSFER_ACCEPT, so the node has to stream
+/// the file right after StreamOpen instead of waiting out the acceptance
+/// timeout.
+#[tokio::test]
+async fn test_send_file_to_baseline_peer() {
+    use std::time::Duration;
+    use wraith_core::node::{Node, NodeConfig};
+
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    // v1 Data frames are not split, so a chunk has to fit one datagram
+    config.transfer.chunk_size = 8 * 1024;
+    config.transfer.acceptance_timeout = Duration::from_secs(60);
+    let node = Node::new_with_config(config).await.unwrap();
+    node.start().await.unwrap();
+    let mut peer = BaselinePeer::connect(&node).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let data: Vec<u8> = (0..40 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let path = dir.path().join("to-baseline.bin");
+    std::fs::write(&path, &data).unwrap();
+
+    let transfer_id = node.send_file(&path, &peer.peer_id).await.unwrap();
+    let (metadata, received) = peer.receive_file().await;
+    assert_eq!(metadata.transfer_id, transfer_id);
+    assert_eq!(metadata.file_name, "to-baseline.bin");
+    assert_eq!(received, data);
+
+    tokio::time::timeout(Duration::from_secs(10), node.wait_for_transfer(transfer_id))
+        .await
+        .expect("transfer to a baseline peer waited for a verdict")
+        .unwrap();
+
+    node.stop().await.unwrap();
+}
+
+/// Test a baseline peer against a v2 node
+///
+/// The peer speaks the protocol as it was before suite negotiation: a plain
+/// Noise_XX exchange with empty payloads, keys from the handshake hash and
+/// v1 frames with implicit packet counters. The node settles on the
+/// classical suite and v1 wire format and answers in kind, and files move
+/// in both directions without chunk hash layers or TRANSFER_ACCEPT.
+#[tokio::test]
+async fn test_v1_node_interoperates_with_v2_node() {
+    use std::time::Duration;
+    use wraith_core::WireFormat;
+    use wraith_core::node::{Node, NodeConfig};
+    use wraith_crypto::suite::CryptoSuite;
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    config.transfer.download_dir = download_dir.path().to_path_buf();
+    // v1 Data frames are not split, so a chunk has to fit one datagram
+    config.transfer.chunk_size = 8 * 1024;
+    let node = Node::new_with_config(config).await.unwrap();
+    node.start().await.unwrap();
+    let mut peer = BaselinePeer::connect(&node).await;
+
+    assert_eq!(
+        node.get_session_wire_format(&peer.peer_id),
+        Some(WireFormat::V1)
+    );
+    assert_eq!(
+        node.get_session_crypto_suite(&peer.peer_id),
+        Some(CryptoSuite::SuiteD)
+    );
+
+    // A v1 PING goes out as connection ID || ciphertext and comes back as a PONG
+    let ping = FrameBuilder::new()
+        .frame_type(FrameType::Ping)
+        .stream_id(0)
+        .sequence(42)
+        .build(128)
+        .unwrap();
+    peer.send_frame(&ping).await;
+
+    let plaintext = peer.recv_frame().await;
+    let pong = Frame::parse(&plaintext).expect("node sent a frame that is not v1");
+    assert_eq!(pong.frame_type(), FrameType::Pong);
+    assert_eq!(pong.sequence(), 42);
+
+    // Baseline sender -> node: verified against the root alone once it lands
+    let data: Vec<u8> = (0..40 * 1024u32).map(|i| (i % 241) as u8).collect();
+    peer.send_file([0x5A; 32], "from-baseline.bin", &data, 8 * 1024)
+        .await;
+    wait_for_received_file(&download_dir.path().join("from-baseline.bin"), &data).await;
+
+    // Node -> baseline receiver: streamed without waiting for a verdict
+    let path = download_dir.path().join("to-baseline.bin");
+    let data: Vec<u8> = (0..24 * 1024u32).map(|i| (i % 239) as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+    let transfer_id = node.send_file(&path, &peer.peer_id).await.unwrap();
+    let (metadata, received) = peer.receive_file().await;
+    assert_eq!(metadata.transfer_id, transfer_id);
+    assert_eq!(received, data);
+    tokio::time::timeout(Duration::from_secs(10), node.wait_for_transfer(transfer_id))
+        .await
+        .expect("transfer to a baseline peer stalled")
+        .unwrap();
+
+    node.stop().await.unwrap();
+}
+
+/// Test two default nodes exchanging v2 frames end to end
+#[tokio::test]
+async fn test_v2_nodes_exchange_v2_frames() {
+    use wraith_core::WireFormat;
+    use wraith_core::node::Node;
+
+    let node1 = Node::new_random_with_port(0).await.unwrap();
+    let node2 = Node::new_random_with_port(0).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+
+    let node2_addr = node2.listen_addr().await.unwrap();
+    node1
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    assert_eq!(
+        node1.get_session_wire_format(node2.x25519_public_key()),
+        Some(WireFormat::V2)
+    );
+    assert_eq!(
+        node2.get_session_wire_format(node1.x25519_public_key()),
+        Some(WireFormat::V2)
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    assert_transfer_rejected(&node1, &node2, dir.path()).await;
+    assert_transfer_rejected(&node2, &node1, dir.path()).await;
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+}
+
+/// Test that a v2-only node refuses a v1-only peer
+#[tokio::test]
+async fn test_v2_only_node_refuses_v1_only_peer() {
+    use wraith_core::FormatNegotiation;
+    use wraith_core::node::{Node, NodeConfig};
+
+    let mut v2_only = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    v2_only.transport.wire_format = FormatNegotiation::v2_only();
+    let mut v1_only = v2_only.clone();
+    v1_only.transport.wire_format = FormatNegotiation::v1_only();
+
+    let node1 = Node::new_with_config(v2_only).await.unwrap();
+    let node2 = Node::new_with_config(v1_only).await.unwrap();
+    node1.start().await.unwrap();
+    node2.start().await.unwrap();
+
+    let node2_addr = node2.listen_addr().await.unwrap();
+    let result = node1
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
+        .await;
+
+    assert!(result.is_err());
+    assert!(node1.active_sessions().await.is_empty());
+    assert!(node2.active_sessions().await.is_empty());
+
+    node1.stop().await.unwrap();
+    node2.stop().await.unwrap();
+}
+
+/// Test migrating a live session from UDP to TCP in the middle of a transfer
+///
+/// Both nodes also listen on TCP and advertise it after the handshake. The
+/// sender moves the session to the receiver's TCP listener while chunks are
+/// in flight; the transfer completes over TCP with the same session keys.
+#[tokio::test]
+async fn test_session_migrates_to_tcp_mid_transfer() {
+    use wraith_core::node::{Node, NodeConfig};
+    use wraith_transport::factory::{TransportFactoryConfig, TransportType};
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    config.transport.additional_transports =
+        vec![TransportFactoryConfig::tcp("127.0.0.1:0".parse().unwrap())];
+    config.transfer.download_dir = download_dir.path().to_path_buf();
+    // Chunks must fit a single v2 frame
+    config.transfer.chunk_size = 8 * 1024;
+
+    let sender = Node::new_with_config(config.clone()).await.unwrap();
+    let receiver = Node::new_with_config(config).await.unwrap();
+    sender.start().await.unwrap();
+    receiver.start().await.unwrap();
+
+    let receiver_addr = receiver.listen_addr().await.unwrap();
+    sender
+        .establish_session_with_addr(receiver.x25519_public_key(), receiver_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let receiver_id = *receiver.x25519_public_key();
+    let established_at = sender.get_session_established_at(&receiver_id).unwrap();
+    let tcp_addr = receiver
+        .transport_addrs()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|(transport, _)| *transport == TransportType::Tcp)
+        .map(|(_, addr)| addr)
+        .unwrap();
+
+    let source_dir = tempfile::tempdir().unwrap();
+    let data: Vec<u8> = (0..2 * 1024 * 1024u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect();
+    let path = source_dir.path().join("migrating.bin");
+    std::fs::write(&path, &data).unwrap();
+
+    let transfer_id = sender.send_file(&path, &receiver_id).await.unwrap();
+    sender
+        .migrate_session_to_transport(&receiver_id, TransportType::Tcp, tcp_addr)
+        .await
+        .unwrap();
+    assert_eq!(
+        sender.get_session_transport(&receiver_id),
+        Some(TransportType::Tcp)
+    );
+    assert_eq!(
+        receiver.get_session_transport(sender.x25519_public_key()),
+        Some(TransportType::Tcp)
+    );
+
+    tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("transfer stalled after migration")
+    .unwrap();
+
+    // Still the session from the UDP handshake
+    assert_eq!(sender.active_sessions().await, vec![receiver_id]);
+    assert_eq!(
+        sender.get_session_established_at(&receiver_id),
+        Some(established_at)
+    );
+    let received = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            if let Ok(received) = std::fs::read(download_dir.path().join("migrating.bin"))
+                && received.len() == data.len()
+            {
+                return received;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("received file never completed");
+    assert!(received == data, "received file differs from the original");
+
+    sender.stop().await.unwrap();
+    receiver.stop().await.unwrap();
+}
+
+/// Test a file transfer across several in-band rekeys
+///
+/// A low packet limit forces the session through repeated key generations
+/// while chunks are in flight; the file must arrive intact over the same
+/// session.
+#[tokio::test]
+async fn test_transfer_survives_repeated_rekeys() {
+    use wraith_core::node::{Node, NodeConfig};
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let mut config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    config.transfer.download_dir = download_dir.path().to_path_buf();
+    // Chunks must fit a single v2 frame
+    config.transfer.chunk_size = 8 * 1024;
+    config.crypto.rekey.packet_limit = 16;
+
+    let sender = Node::new_with_config(config.clone()).await.unwrap();
+    let receiver = Node::new_with_config(config).await.unwrap();
+    sender.start().await.unwrap();
+    receiver.start().await.unwrap();
+
+    let receiver_addr = receiver.listen_addr().await.unwrap();
+    sender
+        .establish_session_with_addr(receiver.x25519_public_key(), receiver_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let receiver_id = *receiver.x25519_public_key();
+    let sender_id = *sender.x25519_public_key();
+    let established_at = sender.get_session_established_at(&receiver_id).unwrap();
+
+    let source_dir = tempfile::tempdir().unwrap();
+    let data: Vec<u8> = (0..2 * 1024 * 1024u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect();
+    let path = source_dir.path().join("rekeyed.bin");
+    std::fs::write(&path, &data).unwrap();
+
+    let transfer_id = sender.send_file(&path, &receiver_id).await.unwrap();
+    tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("transfer stalled across rekeys")
+    .unwrap();
+
+    let received = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+        loop {
+            if let Ok(received) = std::fs::read(download_dir.path().join("rekeyed.bin"))
+                && received.len() == data.len()
+            {
+                return received;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("received file never completed");
+    assert!(received == data, "received file differs from the original");
+
+    // Still the handshake's session, now several key generations on
+    assert_eq!(
+        sender.get_session_established_at(&receiver_id),
+        Some(established_at)
+    );
+    let sender_epoch = sender.get_session_key_epoch(&receiver_id).unwrap();
+    let receiver_epoch = receiver.get_session_key_epoch(&sender_id).unwrap();
+    assert!(sender_epoch >= 3, "only {sender_epoch} rekeys completed");
+    assert!(sender_epoch.abs_diff(receiver_epoch) <= 1);
+
+    // The session keeps working under the latest keys
+    let path = source_dir.path().join("after.bin");
+    std::fs::write(&path, &data[..64 * 1024]).unwrap();
+    let transfer_id = sender.send_file(&path, &receiver_id).await.unwrap();
+    tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("transfer after rekeys stalled")
+    .unwrap();
+
+    sender.stop().await.unwrap();
+    receiver.stop().await.unwrap();
+}
+
+// ============================================================================
+// Simulated Network Tests
+// ============================================================================
+//
+// These run on tokio's paused clock: timers fire as soon as every task is
+// idle, so link latency and timeouts cost no wall time, and the seeded
+// network makes every run deliver the same datagrams.
+
+/// Configuration for a node on a simulated network
+///
+/// Links keep their default 1472-byte MTU, so chunks are sized to fit one
+/// datagram and FEC is on to repair loss.
+fn simulated_node_config(download_dir: &std::path::Path) -> wraith_core::node::NodeConfig {
+    use wraith_core::node::{FecConfig, NodeConfig};
+
+    let mut config = NodeConfig::default();
+    config.transfer.download_dir = download_dir.to_path_buf();
+    config.transfer.chunk_size = 1024;
+    config.transfer.fec = FecConfig {
+        enabled: true,
+        ..FecConfig::default()
+    };
+    config
+}
+
+/// Start a node on a transport bound to a simulated network
+async fn start_simulated_node(
+    config: wraith_core::node::NodeConfig,
+    transport: wraith_transport::simulated::SimulatedTransport,
+) -> wraith_core::node::Node {
+    use std::sync::Arc;
+    use wraith_core::node::Node;
+
+    let node = Node::new_with_config(config).await.unwrap();
+    node.start_with_transport(Arc::new(transport))
+        .await
+        .unwrap();
+    node
+}
+
+/// Deterministic, non-repeating file contents
+fn simulated_file_data(len: u32) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+        .collect()
+}
+
+/// Send `data` as `name` from `sender` to `receiver` and check it arrives intact
+async fn send_over_simulated_network(
+    sender: &wraith_core::node::Node,
+    receiver: &wraith_core::node::Node,
+    download_dir: &std::path::Path,
+    name: &str,
+    data: &[u8],
+) {
+    use std::time::Duration;
+
+    let source_dir = tempfile::tempdir().unwrap();
+    let path = source_dir.path().join(name);
+    std::fs::write(&path, data).unwrap();
+
+    let transfer_id = sender
+        .send_file(&path, receiver.x25519_public_key())
+        .await
+        .unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(60),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .unwrap_or_else(|_| panic!("transfer of {name} stalled"))
+    .unwrap();
+
+    wait_for_received_file(&download_dir.join(name), data).await;
+}
+
+/// Wait until the file at `path` holds `data`
+async fn wait_for_received_file(path: &std::path::Path, data: &[u8]) {
+    use std::time::Duration;
+
+    let received = tokio::time::timeout(Duration::from_secs(60), async {
+        loop {
+            if let Ok(received) = std::fs::read(path)
+                && received.len() == data.len()
+            {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("{} never completed", path.display()));
+    assert!(received == data, "received file differs from the original");
+}
+
+/// Test a file transfer between nodes on an impaired simulated network
+///
+/// Once the session is up the link is rate-limited and loses, delays,
+/// duplicates and reorders datagrams; FEC has to repair the lost chunks
+/// and the file must still arrive intact.
+#[tokio::test(start_paused = true)]
+async fn test_transfer_over_simulated_impaired_link() {
+    use std::time::Duration;
+    use wraith_transport::simulated::{LinkConfig, SimulatedNetwork};
+
+    let network = SimulatedNetwork::new(0x5eed);
+    let download_dir = tempfile::tempdir().unwrap();
+    let sender = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network.bind("10.0.0.1:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+    let receiver = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network.bind("10.0.0.2:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+
+    let receiver_addr = receiver.listen_addr().await.unwrap();
+    assert_eq!(receiver_addr, "10.0.0.2:7000".parse().unwrap());
+    sender
+        .establish_session_with_addr(receiver.x25519_public_key(), receiver_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    network.set_default_link(LinkConfig {
+        latency: Duration::from_millis(5),
+        jitter: Duration::from_millis(2),
+        bandwidth: Some(4 * 1024 * 1024),
+        loss: 0.01,
+        duplicate: 0.05,
+        reorder: 0.1,
+        ..LinkConfig::default()
+    });
+
+    let data = simulated_file_data(256 * 1024);
+    send_over_simulated_network(
+        &sender,
+        &receiver,
+        download_dir.path(),
+        "impaired.bin",
+        &data,
+    )
+    .await;
+
+    let stats = network.stats();
+    assert!(
+        stats.lost > 0 && stats.duplicated > 0 && stats.reordered > 0,
+        "{stats:?}"
+    );
+    assert_eq!(stats.dropped_mtu, 0, "{stats:?}");
+
+    sender.stop().await.unwrap();
+    receiver.stop().await.unwrap();
+}
+
+/// Test NAT traversal from a node behind a port-restricted NAT
+///
+/// The NAT'd node reaches a public node through `traverse_nat`; the public
+/// node can then send a file back, which only gets through the mapping the
+/// NAT'd node's own traffic opened.
+#[tokio::test(start_paused = true)]
+async fn test_traverse_nat_over_simulated_network() {
+    use std::time::{Duration, SystemTime};
+    use wraith_core::node::{NatType, NodeCapabilities, PeerInfo};
+    use wraith_transport::simulated::{self, SimulatedNetwork};
+
+    let network = SimulatedNetwork::new(0x7a7);
+    let gateway = "203.0.113.1".parse().unwrap();
+    network.add_nat(gateway, simulated::NatType::PortRestrictedCone);
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let public = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network.bind("198.51.100.1:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+    let natted = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network
+            .bind_behind_nat("192.168.1.2:7000".parse().unwrap(), gateway)
+            .unwrap(),
+    )
+    .await;
+
+    let public_info = PeerInfo {
+        peer_id: *public.x25519_public_key(),
+        addresses: vec![public.listen_addr().await.unwrap()],
+        nat_type: NatType::None,
+        capabilities: NodeCapabilities::default(),
+        last_seen: SystemTime::now(),
+    };
+    let connection =
+        tokio::time::timeout(Duration::from_secs(30), natted.traverse_nat(&public_info))
+            .await
+            .expect("NAT traversal stalled")
+            .unwrap();
+    assert_eq!(connection.peer_id, *public.x25519_public_key());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(
+        public
+            .active_sessions()
+            .await
+            .contains(natted.x25519_public_key())
+    );
+
+    let data = simulated_file_data(64 * 1024);
+    send_over_simulated_network(
+        &public,
+        &natted,
+        download_dir.path(),
+        "through-nat.bin",
+        &data,
+    )
+    .await;
+
+    public.stop().await.unwrap();
+    natted.stop().await.unwrap();
+}
+
+/// Test migrating a session after the NAT in front of a node rebinds
+///
+/// Resetting the NAT drops the mapping the session uses, so the public
+/// node's traffic no longer gets through. Migrating the session from the
+/// NAT'd side validates the path from a new public port, and the public
+/// node switches to it.
+#[tokio::test(start_paused = true)]
+async fn test_migrate_session_after_nat_rebinding() {
+    use std::time::Duration;
+    use wraith_transport::simulated::{self, SimulatedNetwork};
+
+    let network = SimulatedNetwork::new(0x316);
+    let gateway = "203.0.113.1".parse().unwrap();
+    network.add_nat(gateway, simulated::NatType::PortRestrictedCone);
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let public = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network.bind("198.51.100.1:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+    let natted = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network
+            .bind_behind_nat("192.168.1.2:7000".parse().unwrap(), gateway)
+            .unwrap(),
+    )
+    .await;
+
+    let public_addr = public.listen_addr().await.unwrap();
+    natted
+        .establish_session_with_addr(public.x25519_public_key(), public_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let data = simulated_file_data(32 * 1024);
+    send_over_simulated_network(&public, &natted, download_dir.path(), "before.bin", &data).await;
+
+    network.reset_nat(gateway).unwrap();
+    natted
+        .migrate_session(public.x25519_public_key(), public_addr)
+        .await
+        .unwrap();
+
+    send_over_simulated_network(&public, &natted, download_dir.path(), "after.bin", &data).await;
+
+    public.stop().await.unwrap();
+    natted.stop().await.unwrap();
+}
+
+/// Test pausing a transfer, losing the link, and resuming it
+///
+/// The sender pauses partway through, the link then drops everything for
+/// a while, and once it is back the resumed transfer completes with the
+/// file intact. A fixed send delay paces the sender so the pause lands
+/// mid-transfer.
+#[tokio::test(start_paused = true)]
+async fn test_resume_transfer_after_simulated_outage() {
+    use std::time::Duration;
+    use wraith_core::node::TimingMode;
+    use wraith_transport::simulated::{LinkConfig, SimulatedNetwork};
+
+    let network = SimulatedNetwork::new(0x4e5);
+    let link = LinkConfig {
+        latency: Duration::from_millis(10),
+        ..LinkConfig::default()
+    };
+    network.set_default_link(link.clone());
+
+    let download_dir = tempfile::tempdir().unwrap();
+    let mut paced = simulated_node_config(download_dir.path());
+    paced.obfuscation.timing_mode = TimingMode::Fixed(Duration::from_millis(2));
+    let sender = start_simulated_node(
+        paced,
+        network.bind("10.0.0.1:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+    let receiver = start_simulated_node(
+        simulated_node_config(download_dir.path()),
+        network.bind("10.0.0.2:7000".parse().unwrap()).unwrap(),
+    )
+    .await;
+    sender
+        .establish_session_with_addr(
+            receiver.x25519_public_key(),
+            receiver.listen_addr().await.unwrap(),
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let source_dir = tempfile::tempdir().unwrap();
+    let data = simulated_file_data(512 * 1024);
+    let path = source_dir.path().join("resumed.bin");
+    std::fs::write(&path, &data).unwrap();
+    let transfer_id = sender
+        .send_file(&path, receiver.x25519_public_key())
+        .await
+        .unwrap();
+
+    // At one chunk per 2 ms the file takes about a second
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    sender.pause_transfer(&transfer_id).await.unwrap();
+    // Chunks already queued for the current batch still go out
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let paused_at = sender
+        .get_transfer_progress(&transfer_id)
+        .await
+        .unwrap()
+        .chunks_sent;
+    assert!(paused_at > 0 && paused_at < data.len() / 1024);
+
+    network.set_default_link(LinkConfig {
+        loss: 1.0,
+        ..link.clone()
+    });
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let progress = sender.get_transfer_progress(&transfer_id).await.unwrap();
+    assert_eq!(
+        progress.chunks_sent, paused_at,
+        "paused transfer kept sending"
+    );
+
+    network.set_default_link(link);
+    sender.resume_transfer(&transfer_id).await.unwrap();
+    tokio::time::timeout(
+        Duration::from_secs(60),
+        sender.wait_for_transfer(transfer_id),
+    )
+    .await
+    .expect("resumed transfer stalled")
+    .unwrap();
+    wait_for_received_file(&download_dir.path().join("resumed.bin"), &data).await;
+
+    sender.stop().await.unwrap();
+    receiver.stop().await.unwrap();
+}
+
+/// Test tunnelling bytes over a session with `open_stream`/`accept_stream`
+///
+/// The client writes a buffer larger than the flow-control window and shuts
+/// down its write side; the server echoes everything back through the same
+/// stream and closes it.
+#[tokio::test]
+async fn test_byte_stream_echo() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wraith_core::node::{Node, NodeConfig};
+
+    let config = NodeConfig {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        ..NodeConfig::default()
+    };
+    let client = Node::new_with_config(config.clone()).await.unwrap();
+    let server = Node::new_with_config(config).await.unwrap();
+    client.start().await.unwrap();
+    server.start().await.unwrap();
+
+    let server_addr = server.listen_addr().await.unwrap();
+    client
+        .establish_session_with_addr(server.x25519_public_key(), server_addr)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let echo = tokio::spawn({
+        let server = server.clone();
+        async move {
+            let mut stream = server.accept_stream().await.unwrap();
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).await.unwrap();
+            stream.write_all(&received).await.unwrap();
+            stream.shutdown().await.unwrap();
+            (*stream.peer_id(), received.len())
+        }
+    });
+
+    let data: Vec<u8> = (0..300 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let exchange = async {
+        let mut stream = client
+            .open_stream(server.x25519_public_key())
+            .await
+            .unwrap();
+        stream.write_all(&data).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut echoed = Vec::new();
+        stream.read_to_end(&mut echoed).await.unwrap();
+        echoed
+    };
+    let echoed = tokio::time::timeout(std::time::Duration::from_secs(30), exchange)
+        .await
+        .expect("stream exchange stalled");
+    assert!(echoed == data, "echoed bytes differ from the original");
+
+    let (peer, received_len) = echo.await.unwrap();
+    assert_eq!(&peer, client.x25519_public_key());
+    assert_eq!(received_len, data.len());
+
+    client.stop().await.unwrap();
+    server.stop().await.unwrap();
+}
+
 /// Test encrypted frame exchange after handshake
 ///
 /// Verifies that after Noise handshake:
@@ -2225,7 +3438,7 @@ async fn test_end_to_end_file_transfer() {
     // Establish session first (so receiver knows about sender)
     // Use Ed25519 node_id for addressing, session is stored by X25519 key
     let _session = sender
-        .establish_session_with_addr(receiver.node_id(), receiver_addr)
+        .establish_session_with_addr(receiver.x25519_public_key(), receiver_addr)
         .await
         .unwrap();
 
@@ -2294,7 +3507,7 @@ async fn test_connection_establishment() {
 
     // Establish session using known address
     let session_id = node1
-        .establish_session_with_addr(node2.node_id(), node2_addr)
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
         .await
         .unwrap();
 
@@ -2360,7 +3573,7 @@ async fn test_discovery_and_peer_finding() {
 
     // Establish session from node1 to node2
     let _session_id1 = node1
-        .establish_session_with_addr(node2.node_id(), node2_addr)
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
         .await
         .unwrap();
 
@@ -2369,7 +3582,7 @@ async fn test_discovery_and_peer_finding() {
 
     // Establish session from node2 to node1
     let _session_id2 = node2
-        .establish_session_with_addr(node1.node_id(), node1_addr)
+        .establish_session_with_addr(node1.x25519_public_key(), node1_addr)
         .await
         .unwrap();
 
@@ -2430,11 +3643,11 @@ async fn test_multi_path_transfer_node_api() {
     // Establish sessions with multiple peers
     // Use Ed25519 node_id for addressing, session is stored by X25519 key
     let _session1 = sender
-        .establish_session_with_addr(receiver1.node_id(), receiver1_addr)
+        .establish_session_with_addr(receiver1.x25519_public_key(), receiver1_addr)
         .await
         .unwrap();
     let _session2 = sender
-        .establish_session_with_addr(receiver2.node_id(), receiver2_addr)
+        .establish_session_with_addr(receiver2.x25519_public_key(), receiver2_addr)
         .await
         .unwrap();
 
@@ -2511,7 +3724,7 @@ async fn test_error_recovery_node_api() {
     // Now establish a valid session - node should work normally after error
     let node2_addr = node2.listen_addr().await.unwrap();
     let result = node1
-        .establish_session_with_addr(node2.node_id(), node2_addr)
+        .establish_session_with_addr(node2.x25519_public_key(), node2_addr)
         .await;
     assert!(
         result.is_ok(),
@@ -2575,15 +3788,15 @@ async fn test_concurrent_transfers_node_api() {
     let receiver3_addr = receiver3.listen_addr().await.unwrap();
 
     sender
-        .establish_session_with_addr(receiver1.node_id(), receiver1_addr)
+        .establish_session_with_addr(receiver1.x25519_public_key(), receiver1_addr)
         .await
         .unwrap();
     sender
-        .establish_session_with_addr(receiver2.node_id(), receiver2_addr)
+        .establish_session_with_addr(receiver2.x25519_public_key(), receiver2_addr)
         .await
         .unwrap();
     sender
-        .establish_session_with_addr(receiver3.node_id(), receiver3_addr)
+        .establish_session_with_addr(receiver3.x25519_public_key(), receiver3_addr)
         .await
         .unwrap();
 
@@ -15,6 +15,7 @@ const ALL_FRAME_TYPES: &[FrameTypeV2] = &[
     FrameTypeV2::GoAway,
     FrameTypeV2::QosUpdate,
     FrameTypeV2::Timestamp,
+    FrameTypeV2::Control,
     FrameTypeV2::Rekey,
     FrameTypeV2::RekeyAck,
     FrameTypeV2::FecRepair,